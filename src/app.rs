@@ -1,96 +1,588 @@
+use crate::midi::{gain_velocity, ClockSync, MidiClock, MidiClockIn, PadNote, DEFAULT_NOTE_GATE_MS};
 use crate::mixer::Mixer;
-use crate::sequencer::Sequencer;
-use crate::sample::SampleBank;
-use crate::state::{AppState, UIState};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::sequencer::{quantize_offset, Automation, ClockDivision, Pattern, RemapMode, Sequencer, Signature, MAX_BEATS_PER_BAR, STEPS_PER_PAGE, STEPS_PER_PATTERN};
+use crate::song::Song;
+use crate::sample::{self, Kit, SampleBank, SampleStorage, SAMPLE_CONFIG_PATH};
+use crate::sampling::{self, InputRecorder, RecordedAudio, RECORDED_SAMPLES_DIR};
+use crate::state::{transport_position, AppState, Panel, Prompt, PromptKind, Transport, UIState, ViewMode};
 use crate::audio_manager::AudioManager;
-use crate::error::Result;
+use crate::bus::{master_gain, master_gain_label, BUS_SAMPLE_RATE, MAX_CAPTURE_SECS, MAX_MASTER_GAIN_DB, MIN_MASTER_GAIN_DB};
+use crate::error::{AppError, Result};
+use crate::sequencer::{accented_gain, max_micro_offset_ms, velocity_gain, Humanize, DEFAULT_HUMANIZE, DEFAULT_MORPH_BARS, DEFAULT_VELOCITY, MAX_GROUPS, MAX_MORPH_BARS, MAX_MICRO_OFFSET_MS, MAX_PATTERNS, MAX_VELOCITY, PADS_PER_GROUP, PAD_COUNTS};
+use crate::render::{self, RENDER_CHANNELS, RENDER_SAMPLE_RATE};
+use crate::pad_settings::{load_pad_settings, save_pad_settings, PAD_SETTINGS_PATH};
+use crate::project::{save_in_background, Autosave, Project, ProjectPattern};
+use crate::config::Config;
+use crate::input::InputMapper;
+use crate::theme::Theme;
+
+pub const RESAMPLED_SAMPLES_DIR: &str = "samples/resampled";
+pub const OUTPUT_RECORDINGS_DIR: &str = "recordings";
+pub const PATTERN_EXPORT_PATH: &str = "pattern.json";
+pub const DEFAULT_QUANTIZE_STRENGTH: f32 = 1.0;
+pub const DEFAULT_ACCENT_BOOST: f32 = 0.3;
+
+// Previewed patterns play under the live mix so they don't clash with it
+const AUDITION_GAIN: f32 = 0.4;
+
+// One press moves the grid glyph by one digit
+const VELOCITY_STEP: i16 = 16;
+const PROBABILITY_STEP: i16 = 10;
+
+// Trim points move by 1% of the sample per press
+pub const TRIM_STEP: f32 = 0.01;
+
+// Nudges move a hit a couple of milliseconds per press
+pub const NUDGE_STEP_MS: i16 = 2;
+
+// The main loop wakes this often while something is moving on screen
+pub const DEFAULT_TICK_INTERVAL: Duration = Duration::from_millis(50);
+// and backs off to this when stopped with nothing to animate
+pub const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Terminals send a held key again about every 30ms after a delay of up to half a second,
+// so a pad pressed within this long counts as still held for note repeat
+pub const NOTE_REPEAT_HOLD: Duration = Duration::from_millis(600);
+
+// A measured tempo this far off the set one is shown beside it
+pub const TEMPO_DRIFT_BPM: f32 = 0.5;
+
+// Times the sequencer's wraps to step 0, to see how fast it really runs when a slow
+// terminal makes ticks late
+#[derive(Debug, Clone, Copy, Default)]
+struct TempoMeter {
+    last_wrap: Option<(Instant, usize, f32, usize)>, // When, at which tick count, and the tempo and steps per beat then
+    measured: Option<f32>,
+}
+
+impl TempoMeter {
+    fn record_wrap(&mut self, at: Instant, tick: usize, tempo: f32, steps_per_beat: usize) {
+        // A tempo or resolution change mid-cycle would read as drift, so that cycle isn't measured
+        self.measured = self.last_wrap.and_then(|(last, last_tick, last_tempo, last_steps_per_beat)| {
+            let secs = at.checked_duration_since(last)?.as_secs_f32();
+            let steps = tick.wrapping_sub(last_tick);
+            let steady = last_tempo == tempo && last_steps_per_beat == steps_per_beat;
+            (steady && steps > 0 && secs > 0.0).then(|| 60.0 * steps as f32 / (steps_per_beat as f32 * secs))
+        });
+        self.last_wrap = Some((at, tick, tempo, steps_per_beat));
+    }
+}
+
+// A nudged hit waiting for its moment
+#[derive(Debug, Clone, Copy)]
+struct PendingHit {
+    at: Instant,
+    group: usize,
+    pad: usize,
+    gain: f32,
+    velocity: u8, // Of the step, for velocity-switched pads
+}
 
+fn offset_instant(base: Instant, offset_ms: i16) -> Instant {
+    let offset = Duration::from_millis(offset_ms.unsigned_abs() as u64);
+    if offset_ms < 0 {
+        base.checked_sub(offset).unwrap_or(base)
+    } else {
+        base + offset
+    }
+}
+
+// What loading a kit into a group reports
+fn kit_message(group_name: &str, kit: &Kit, failed: usize, pads: usize) -> String {
+    let mut message = format!("{} kit: {}", group_name, kit.name);
+    if kit.skipped > 0 {
+        message += &format!(" ({} files past {} skipped)", kit.skipped, pads);
+    }
+    if failed > 0 {
+        message += &format!(" ({} failed to load)", failed);
+    }
+    message
+}
+
+// A pattern as it was before and right after an undoable edit. Undo only puts `before`
+// back while the pattern still matches `after`, so later hand edits aren't thrown away.
+#[derive(Debug)]
+struct PatternUndo {
+    group: usize,
+    pattern: usize,
+    before: Pattern,
+    after: Pattern,
+    edit: &'static str,
+}
+
+/// The whole instrument. Frontends drive it with [`Command`](crate::command::Command)s and
+/// call [`App::tick`] regularly so the sequencer keeps time.
 pub struct App {
     pub mixer: Mixer,
     pub sequencer: Sequencer,
     pub sample_bank: SampleBank,
     pub state: AppState,
     pub ui_state: UIState,
+    pub song: Song,
+    pub input_device: Option<String>, // None uses the system default input
+    pub quantize_strength: f32,       // How far recorded hits are pulled onto the nearest step
+    pub accent_boost: f32,            // Gain added to every hit on an accented step
+    pub tick_interval: Duration,      // Poll interval while playing or animating
+    pub humanize: Humanize,           // How far humanize moves each hit
+    pub morph_bars: usize,            // Bars a morph from the prompt takes
+    pub midi_clock: Option<MidiClock>, // Clock and transport sent to external gear
+    pub clock_in: Option<MidiClockIn>, // External clock the sequencer follows instead of its own
+    pub midi_notes: HashMap<(usize, usize), PadNote>, // (group, pad) hits also sent out as notes
+    pub midi_gate: Duration,                           // How long those notes are held
+    recorded_triggers: Vec<(usize, usize, Instant)>, // (group, pad, when) played this bar while recording
+    bar_start: Option<Instant>, // When step 0 of the playing bar ticked, None until the clock runs
+    bar_start_tick: usize, // Sequencer tick count at bar_start, for groups on another clock division
+    tempo_meter: TempoMeter,
+    pattern_tempo: Option<f32>, // Stored tempo of the playing patterns, as last adopted
+    sampling: Option<(InputRecorder, usize, usize)>, // Active recorder and its target (group, pad)
+    output_recording: Option<(String, Instant)>, // Where the master output is being streamed, and since when
+    pending_hits: Vec<PendingHit>, // Nudged hits ordered by when they play
+    pending_automation: Vec<(Instant, usize, Automation)>, // (when, group, move) of double-time moves due halfway through a step
+    rng: StdRng, // Humanize and random layers draw from it; tests swap in a seeded one
+    undo: Option<PatternUndo>, // The last humanize, fill or other whole-pattern edit
+    clipboard: Option<(usize, usize, Pattern)>, // (group, pattern, contents) of the last copy
+    pub autosave: Option<Autosave>,                // Crash recovery snapshots, off when None
+    pub last_error: Option<String>, // Why the last hit that failed to play didn't, also flashed in the footer
+    transpose_source: Option<(usize, usize)>, // (group, pad) the pad keys play pitched; None plays pads as usual
+    note_repeat: bool, // Held pad keys retrigger on every step while playing
+    held_pad: Option<(usize, usize, Instant)>, // (group, pad, last press) note repeat plays
+    pub pad_settings_path: Option<String>, // Read again whenever samples are swapped; None never reads it
 }
 
 impl App {
     pub fn new() -> Result<Self> {
-        let audio_manager = AudioManager::new()?;
-        
-        let mut sequencer = Sequencer::new();
-        
-        // Initialize all groups to use pattern 0
-        for group in 0..4 {
-            sequencer.set_active_pattern(group, 0);
-        }
-        
-        Ok(Self {
-            mixer: audio_manager.mixer,
-            sequencer,
-            sample_bank: audio_manager.sample_bank,
-            state: AppState::new(),
-            ui_state: UIState::new(),
-        })
+        let audio_manager = AudioManager::new(Vec::new(), PADS_PER_GROUP, sample::DEFAULT_STREAM_THRESHOLD_MB)?;
+        let mut app = Self::with_parts(audio_manager.mixer, audio_manager.sample_bank);
+        app.pad_settings_path = Some(PAD_SETTINGS_PATH.to_string());
+        Ok(app)
     }
     
-    pub fn with_audio_test() -> Result<Self> {
-        let mut audio_manager = AudioManager::new()?;
-        audio_manager.test_audio()?;
-        audio_manager.validate_audio_system()?;
+    // Neither the audio test nor an empty sample bank stops the app; both end up in the footer
+    pub fn with_config(config: &Config, audio_test: bool) -> Result<Self> {
+        let mut audio_manager = AudioManager::new(config.group_defs(), config.layout.pads_per_group, config.samples.stream_threshold_mb)?;
+        let tested = audio_test.then(|| audio_manager.test_audio().unwrap_or_else(|e| format!("Audio test failed: {}", e)));
+        let warning = audio_manager.validate_audio_system();
         
+        let silent = audio_manager.mixer.output_error().map(|e| format!("No audio device, running silent: {}", e));
+        let mut app = Self::with_parts(audio_manager.mixer, audio_manager.sample_bank);
+        app.pad_settings_path = Some(PAD_SETTINGS_PATH.to_string());
+        if let Some(message) = silent.or(warning).or(tested) {
+            app.ui_state.notify(message);
+        }
+        Ok(app)
+    }
+
+    /// An app with no audio device and no samples, for tests and embedding.
+    ///
+    /// Nothing is heard until [`Mixer::render_frames`] pulls audio from the bus, and no
+    /// config, sample or pad settings files are read.
+    pub fn detached() -> Self {
+        Self::with_parts(Mixer::detached(), SampleBank::new())
+    }
+
+    fn with_parts(mixer: Mixer, sample_bank: SampleBank) -> Self {
         let mut sequencer = Sequencer::new();
         
         // Initialize all groups to use pattern 0
-        for group in 0..4 {
+        for group in 0..MAX_GROUPS {
             sequencer.set_active_pattern(group, 0);
         }
         
-        Ok(Self {
-            mixer: audio_manager.mixer,
+        Self {
+            mixer,
             sequencer,
-            sample_bank: audio_manager.sample_bank,
+            sample_bank,
             state: AppState::new(),
             ui_state: UIState::new(),
-        })
+            song: Song::new(),
+            input_device: None,
+            quantize_strength: DEFAULT_QUANTIZE_STRENGTH,
+            accent_boost: DEFAULT_ACCENT_BOOST,
+            tick_interval: DEFAULT_TICK_INTERVAL,
+            humanize: DEFAULT_HUMANIZE,
+            morph_bars: DEFAULT_MORPH_BARS,
+            midi_clock: None,
+            clock_in: None,
+            midi_notes: HashMap::new(),
+            midi_gate: Duration::from_millis(DEFAULT_NOTE_GATE_MS),
+            recorded_triggers: Vec::new(),
+            bar_start: None,
+            bar_start_tick: 0,
+            tempo_meter: TempoMeter::default(),
+            pattern_tempo: None,
+            sampling: None,
+            output_recording: None,
+            pending_hits: Vec::new(),
+            pending_automation: Vec::new(),
+            rng: StdRng::from_entropy(),
+            undo: None,
+            clipboard: None,
+            autosave: None,
+            last_error: None,
+            transpose_source: None,
+            note_repeat: false,
+            held_pad: None,
+            pad_settings_path: None,
+        }
+    }
+
+    // Settings the config can change while running; patterns and samples are left alone
+    pub fn apply_config(&mut self, config: &Config) {
+        self.set_group_count(config.group_count());
+        self.set_pad_count(config.layout.pads_per_group);
+        self.sample_bank.set_groups(config.group_defs());
+        self.sample_bank.set_normalize(config.samples.normalize, config.samples.normalize_target_db);
+        self.sample_bank.set_stream_threshold_mb(config.samples.stream_threshold_mb);
+        self.input_device = config.audio.input_device.clone();
+        if let Some(result) = self.mixer.set_output_device(config.audio.output_device.clone()) {
+            self.ui_state.notify(result.unwrap_or_else(|e| e));
+        }
+        self.ui_state.follow_playhead = config.ui.follow_playhead;
+        let flash_duration = Duration::from_millis(config.ui.flash_duration_ms);
+        self.ui_state.set_flash_duration(config.ui.flash_pads.then_some(flash_duration));
+        self.quantize_strength = config.audio.quantize_strength.clamp(0.0, 1.0);
+        self.accent_boost = config.audio.accent_boost.clamp(0.0, 1.0);
+        self.humanize = Humanize {
+            velocity: config.audio.humanize_velocity.clamp(0.0, 1.0),
+            timing_ms: config.audio.humanize_timing_ms.clamp(0, MAX_MICRO_OFFSET_MS),
+        };
+        self.morph_bars = config.audio.morph_bars.clamp(1, MAX_MORPH_BARS);
+        self.tick_interval = Duration::from_millis(config.ui.tick_interval_ms);
+        self.mixer.configure_limiter(config.audio.limiter, config.audio.limiter_threshold_db);
+        self.mixer.set_volume_ramp_ms(config.audio.volume_ramp_ms);
+        self.midi_notes = config.midi.output.pad_notes();
+        self.midi_gate = Duration::from_millis(config.midi.output.gate_ms);
+    }
+
+    // Everything is parsed before anything is swapped, so a broken file keeps the running setup
+    pub fn reload_config(&mut self, path: &str, input_mapper: &mut InputMapper, theme: &mut Theme) {
+        let reloaded = Config::load_from(path).and_then(|config| {
+            let mapper = InputMapper::from_config(&config);
+            let new_theme = Theme::from_config(&config.theme, &config.groups)?;
+            Ok((config, mapper, new_theme))
+        });
+        match reloaded {
+            Ok((config, mapper, new_theme)) => {
+                self.apply_config(&config);
+                *input_mapper = mapper;
+                *theme = new_theme;
+                self.ui_state.notify(format!("Reloaded {}", path));
+                self.report_config_problems(path, &config.validate());
+            }
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
+    }
+
+    // The footer has room for one problem, so it names the first and counts the rest
+    pub fn report_config_problems(&mut self, path: &str, problems: &[String]) {
+        let Some(first) = problems.first() else {
+            return;
+        };
+        match problems.len() - 1 {
+            0 => self.ui_state.notify(format!("{}: {}", path, first)),
+            more => self.ui_state.notify(format!("{}: {} (+{} more)", path, first, more)),
+        }
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.state.group_count()
+    }
+
+    // Resizes every per-group table; a shrink keeps the hidden groups' patterns and samples
+    pub fn set_group_count(&mut self, groups: usize) {
+        self.state.set_group_count(groups);
+        let groups = self.state.group_count();
+        self.mixer.set_group_count(groups);
+        self.sequencer.set_group_count(groups);
+    }
+
+    pub fn pad_count(&self) -> usize {
+        self.sequencer.pad_count()
+    }
+
+    // Counts other than PAD_COUNTS are ignored; config validation reports them
+    pub fn set_pad_count(&mut self, pads: usize) {
+        if !PAD_COUNTS.contains(&pads) {
+            return;
+        }
+        self.sequencer.set_pad_count(pads);
+        self.sample_bank.set_pad_count(pads);
+        self.ui_state.set_pad_count(pads);
     }
 
     pub fn trigger_pad(&mut self, pad: usize) {
-        if pad < 16 {
-            // Play the sample only if not recording
-            if !self.state.is_recording {
-                if let Some(sample) = self.sample_bank.get_sample(self.state.current_group, pad) {
-                    self.mixer.play_sample(sample, self.state.current_group);
-                }
+        if pad >= self.pad_count() {
+            return;
+        }
+        
+        // The source pad stays selected, and these hits aren't recorded: a step can't hold a pitch
+        if let Some((group, source)) = self.transpose_source {
+            if !self.mixer.is_pad_muted(group, source) {
+                self.play_pad_pitched(group, source, 1.0, MAX_VELOCITY, pad as u8);
             }
-            
-            // Record if recording
-            if self.state.is_recording && self.state.is_playing {
-                self.sequencer.record_hit(
-                    self.state.current_group,
-                    self.state.group_patterns[self.state.current_group],
-                    pad,
-                );
+            return;
+        }
+        let record_into = self.record_groups();
+        self.hit_pad(self.state.current_group, pad, &record_into);
+        self.ui_state.select_pad(pad);
+        if self.note_repeat {
+            self.held_pad = Some((self.state.current_group, pad, self.state.now()));
+        }
+    }
+
+    // Like an MPC's note repeat: a held pad key plays its pad on every step
+    pub fn toggle_note_repeat(&mut self) {
+        self.note_repeat = !self.note_repeat;
+        self.held_pad = None;
+        self.ui_state.notify(if self.note_repeat { "Note repeat on: hold a pad to play it every step" } else { "Note repeat off" });
+    }
+
+    pub fn is_note_repeat(&self) -> bool {
+        self.note_repeat
+    }
+
+    // The held pad, until key repeat stops refreshing it; not muted or recorded, like a key press
+    fn repeated_pad(&mut self) -> Option<(usize, usize)> {
+        let (group, pad, pressed) = self.held_pad?;
+        if self.state.now().saturating_duration_since(pressed) >= NOTE_REPEAT_HOLD {
+            self.held_pad = None;
+            return None;
+        }
+        Some((group, pad))
+    }
+
+    // While it's on, every pad key plays the selected pad's sample a semitone higher per pad
+    pub fn toggle_transpose_mode(&mut self) {
+        if self.transpose_source.take().is_some() {
+            self.ui_state.notify("Transpose off");
+            return;
+        }
+        let group = self.state.current_group;
+        let Some(pad) = self.ui_state.selected_pad.filter(|&pad| self.sample_bank.has_sample(group, pad)) else {
+            self.ui_state.notify("Select a pad with a sample to transpose");
+            return;
+        };
+        self.transpose_source = Some((group, pad));
+        self.ui_state.notify(format!("Transpose pad {}: each pad plays it a semitone higher", pad + 1));
+    }
+
+    // The (group, pad) every pad key plays, pitched, while transpose mode is on
+    pub fn transpose_source(&self) -> Option<(usize, usize)> {
+        self.transpose_source
+    }
+
+    // Plays (or records) a pad in any group without changing the selection
+    pub fn trigger_group_pad(&mut self, group: usize, pad: usize) {
+        if group >= self.group_count() || pad >= self.pad_count() {
+            return;
+        }
+        self.hit_pad(group, pad, &[group]);
+    }
+
+    // Plays the group's pad, or records the same pad into each of record_into
+    fn hit_pad(&mut self, group: usize, pad: usize, record_into: &[usize]) {
+        // Play the sample only if not recording; muted pads are still recorded
+        if !self.state.is_recording && !self.mixer.is_pad_muted(group, pad) {
+            // Keys have no velocity of their own, so they hit as hard as they can
+            self.play_pad(group, pad, 1.0, MAX_VELOCITY);
+        }
+        
+        // Record if recording; hits are placed on steps at the end of the bar
+        if self.state.is_recording && self.state.is_playing() {
+            let now = self.state.now();
+            for &group in record_into {
+                if self.bar_start.is_some() {
+                    self.recorded_triggers.push((group, pad, now));
+                } else {
+                    self.sequencer.record_hit(group, self.state.group_patterns[group], pad);
+                }
             }
-            
-            self.ui_state.select_pad(pad);
+        }
+    }
+
+    // The armed groups, or the current one when none are armed
+    pub fn record_groups(&self) -> Vec<usize> {
+        let armed: Vec<usize> = (0..self.group_count()).filter(|&group| self.is_record_armed(group)).collect();
+        if armed.is_empty() {
+            vec![self.state.current_group]
+        } else {
+            armed
+        }
+    }
+
+    pub fn is_record_armed(&self, group: usize) -> bool {
+        self.state.record_armed.get(group).copied().unwrap_or(false)
+    }
+
+    pub fn toggle_record_arm(&mut self, group: usize) {
+        let Some(armed) = self.state.record_armed.get_mut(group) else {
+            return;
+        };
+        *armed = !*armed;
+        let name = self.sample_bank.get_group_name(group);
+        if self.is_record_armed(group) {
+            self.ui_state.notify(format!("{} armed: recording goes into it", name));
+        } else if self.state.record_armed.contains(&true) {
+            self.ui_state.notify(format!("{} disarmed", name));
+        } else {
+            self.ui_state.notify(format!("{} disarmed: recording goes into the current group", name));
         }
     }
 
     pub fn toggle_playback(&mut self) {
+        let from_stop = self.state.transport == Transport::Stopped;
         self.state.toggle_playback();
-        if self.state.is_playing {
-            self.sequencer.reset_position();
+        
+        // Resuming from a pause carries on mid-pattern
+        if from_stop {
+            self.rewind();
+        } else if !self.state.is_playing() {
+            self.flush_recorded_triggers();
+            self.bar_start = None;
+            self.tempo_meter = TempoMeter::default();
+            self.pending_hits.clear();
+            self.pending_automation.clear();
+            self.sequencer.clear_lookahead();
+        }
+        
+        if let Some(clock) = &mut self.midi_clock {
+            clock.set_tempo(self.state.tempo);
+            match (from_stop, self.state.is_playing()) {
+                (true, _) => clock.start(),
+                (false, true) => clock.resume(),
+                (false, false) => clock.stop(),
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.flush_recorded_triggers();
+        self.state.stop();
+        self.rewind();
+        self.sample_bank.reset_layer_positions();
+        
+        if let Some(clock) = &self.midi_clock {
+            clock.stop();
+            clock.rewind();
         }
+        self.autosave();
+    }
+
+    fn rewind(&mut self) {
+        self.bar_start = None;
+        self.tempo_meter = TempoMeter::default();
+        self.pending_hits.clear();
+        self.pending_automation.clear();
+        self.sequencer.reset_position();
+        self.follow_chains();
+        self.state.reset_position();
+        self.song.reset_position();
+        self.apply_tempo_automation();
     }
 
     pub fn toggle_recording(&mut self) {
+        self.flush_recorded_triggers();
         self.state.toggle_recording();
     }
 
+    // Places hits recorded since the bar started, timed against the tick clock
+    fn flush_recorded_triggers(&mut self) {
+        if self.bar_start.is_none() {
+            return;
+        }
+        for (group, pad, time) in std::mem::take(&mut self.recorded_triggers) {
+            if let Some((pattern, step)) = self.recorded_step(group, time) {
+                self.sequencer.record_hit_at(group, pattern, pad, step);
+            }
+        }
+    }
+
+    // The (pattern, step) something done at `time` is recorded on; None before the clock runs
+    fn recorded_step(&self, group: usize, time: Instant) -> Option<(usize, usize)> {
+        let bar_start = self.bar_start?;
+        let step_duration = self.state.step_duration().as_secs_f64();
+        let offset = match time.checked_duration_since(bar_start) {
+            Some(after) => after.as_secs_f64(),
+            None => -bar_start.duration_since(time).as_secs_f64(),
+        };
+        // The layout can shrink under a take that's still being recorded
+        let &pattern = self.state.group_patterns.get(group)?;
+        let length = self.sequencer.get_pattern(group, pattern).map_or(STEPS_PER_PATTERN, |p| p.length);
+        
+        // Divided groups span more or less than a bar, so they're placed against the running tick count
+        let position = match self.sequencer.get_division(group) {
+            ClockDivision::Normal => offset / step_duration,
+            division => (self.bar_start_tick as f64 + offset / step_duration) / division.step_scale() as f64,
+        };
+        Some((pattern, quantize_offset(position, self.quantize_strength, length)))
+    }
+
+    // Mixer moves made while recording go into the group's pattern, on the nearest step
+    fn record_automation(&mut self, group: usize, automation: Automation) {
+        if !self.state.is_recording || !self.state.is_playing() {
+            return;
+        }
+        if let Some((pattern, step)) = self.recorded_step(group, self.state.now()) {
+            self.sequencer.record_automation(group, pattern, step, automation);
+        }
+    }
+
+    // Replays recorded mixer moves; a move made by hand holds until the next one comes round
+    fn apply_automation(&mut self) {
+        let half_step = self.state.last_tick + self.state.step_duration() / 2;
+        for (group, automation, sub_step) in self.sequencer.automation_on_tick() {
+            if sub_step {
+                self.pending_automation.push((half_step, group, automation));
+            } else {
+                self.apply_move(group, automation);
+            }
+        }
+    }
+
+    fn apply_move(&mut self, group: usize, automation: Automation) {
+        match automation {
+            Automation::Mute(muted) => self.mixer.set_group_mute(group, muted),
+            Automation::Volume(volume) => self.mixer.set_group_volume(group, volume),
+        }
+    }
+
+    pub fn clear_automation(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        self.sequencer.clear_automation(group, pattern);
+        self.remember_undo(group, pattern, before, "automation");
+        let name = self.sample_bank.get_group_name(group);
+        self.ui_state.notify(format!("{} {:02} automation cleared", name, pattern + 1));
+    }
+
+    // Whether the group's playing pattern has recorded mixer moves
+    pub fn is_group_automated(&self, group: usize) -> bool {
+        self.sequencer.get_playing_pattern(group).is_some_and(Pattern::has_automation)
+    }
+
     pub fn clear_pattern(&mut self) {
         self.sequencer.clear_pattern(self.state.current_group, self.state.group_patterns[self.state.current_group]);
     }
 
+    // Unlike clearing, this also drops the pattern's length, signature and stored tempo
+    pub fn reset_pattern(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        self.sequencer.reset_pattern(group, pattern);
+        self.remember_undo(group, pattern, before, "reset");
+        self.ui_state.cursor_step = self.ui_state.cursor_step.min(STEPS_PER_PATTERN - 1);
+        self.ui_state.move_cursor_step(0, STEPS_PER_PATTERN);
+        self.ui_state.notify(format!("Pattern {:02} reset to {} steps", pattern + 1, STEPS_PER_PATTERN));
+    }
+
     pub fn next_group(&mut self) {
         self.state.next_group();
     }
@@ -109,223 +601,2844 @@ impl App {
         self.sequencer.set_active_pattern(self.state.current_group, self.state.group_patterns[self.state.current_group]);
     }
 
-    pub fn adjust_tempo(&mut self, delta: i32) {
+    pub fn adjust_tempo(&mut self, delta: f32) {
+        if self.refuse_tempo_change() {
+            return;
+        }
         self.state.adjust_tempo(delta);
+        self.mixer.set_tempo(self.state.tempo);
     }
 
-    pub fn tick(&mut self) {
-        // Update UI state
-        self.ui_state.update_flash();
-        
-        if self.state.should_tick() {
-            self.state.update_tick_time();
-            
-            // Get hits for current position
-            let hits = self.sequencer.tick(self.state.tempo);
-            
-            // Start flash for new hits
-            self.ui_state.start_flash(hits.clone());
-            
-            // Play all hits
-            for (group, pad) in hits {
-                if let Some(sample) = self.sample_bank.get_sample(group, pad) {
-                    self.mixer.play_sample(sample, group);
-                }
-            }
+    pub fn set_tempo(&mut self, bpm: f32) {
+        if self.refuse_tempo_change() {
+            return;
         }
+        self.state.set_tempo(bpm);
+        self.mixer.set_tempo(self.state.tempo);
     }
 
-    pub fn get_pattern_grid(&self) -> Vec<Vec<bool>> {
-        self.sequencer.get_pattern_grid(self.state.current_group, self.state.group_patterns[self.state.current_group])
+    // The tempo belongs to the external clock while it's driving the sequencer
+    fn refuse_tempo_change(&mut self) -> bool {
+        if self.is_clock_slaved() {
+            self.ui_state.notify("Tempo follows the MIDI clock");
+        }
+        self.is_clock_slaved()
     }
-    
-    pub fn get_current_pattern(&self) -> usize {
-        self.state.get_current_pattern()
+
+    pub fn adjust_delay_send(&mut self, delta: f32) {
+        self.mixer.adjust_delay_send(self.state.current_group, delta);
     }
 
-    pub fn get_current_step(&self) -> usize {
-        self.sequencer.get_current_step()
+    pub fn adjust_reverb_send(&mut self, delta: f32) {
+        self.mixer.adjust_reverb_send(self.state.current_group, delta);
     }
-    
-    // Getter methods for UI
-    pub fn get_current_group(&self) -> usize {
-        self.state.current_group
+
+    pub fn cycle_reverb_size(&mut self) {
+        let size = self.mixer.cycle_reverb_size();
+        self.ui_state.notify(format!("Reverb room: {}", size.name()));
     }
-    
-    pub fn get_selected_pad(&self) -> Option<usize> {
-        self.ui_state.selected_pad
+
+    pub fn get_delay_send(&self, group: usize) -> f32 {
+        self.mixer.get_delay_send(group)
     }
-    
-    pub fn is_pad_flashing(&self, group: usize, pad: usize) -> bool {
-        self.ui_state.is_pad_flashing(group, pad)
+
+    pub fn get_reverb_send(&self, group: usize) -> f32 {
+        self.mixer.get_reverb_send(group)
     }
-    
-    pub fn is_playing(&self) -> bool {
-        self.state.is_playing
+
+    pub fn toggle_sampling(&mut self) {
+        match self.sampling.take() {
+            Some((recorder, group, pad)) => self.finish_sampling(recorder, group, pad),
+            None => self.arm_sampling(),
+        }
     }
-    
-    pub fn is_recording(&self) -> bool {
-        self.state.is_recording
+
+    fn arm_sampling(&mut self) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to sample into first");
+            return;
+        };
+        
+        // A missing input device is common (e.g. desktops without a mic), so report it instead of failing
+        match InputRecorder::start(self.input_device.as_deref()) {
+            Ok(recorder) => {
+                self.ui_state.notify(format!("Sampling from {} into pad {}", recorder.device_name(), pad + 1));
+                self.sampling = Some((recorder, self.state.current_group, pad));
+            }
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
     }
-    
-    pub fn get_tempo(&self) -> u32 {
-        self.state.tempo
+
+    fn finish_sampling(&mut self, recorder: InputRecorder, group: usize, pad: usize) {
+        let mut audio = recorder.stop();
+        if audio.samples.is_empty() {
+            self.ui_state.notify("Nothing was recorded");
+            return;
+        }
+        
+        sampling::normalize(&mut audio.samples);
+        
+        match self.store_recording(group, pad, &audio) {
+            Ok(path) => self.ui_state.notify(format!("Recorded {} -> pad {}", path, pad + 1)),
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
     }
 
-    // Mixer control methods
-    pub fn adjust_master_volume(&mut self, delta: f32) {
-        self.mixer.adjust_master_volume(delta);
+    fn store_recording(&mut self, group: usize, pad: usize, audio: &RecordedAudio) -> Result<String> {
+        std::fs::create_dir_all(RECORDED_SAMPLES_DIR)
+            .map_err(|e| AppError::Sample(format!("Failed to create {}: {}", RECORDED_SAMPLES_DIR, e)))?;
+        
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = format!("{}/g{}p{:02}-{}.wav", RECORDED_SAMPLES_DIR, group + 1, pad + 1, timestamp);
+        
+        sampling::write_wav(&path, &audio.samples, 1, audio.sample_rate)?;
+        self.sample_bank.load_sample(group, pad, &path)?;
+        self.sample_bank.set_sample_name(group, pad, &format!("Rec{:02}", pad + 1));
+        
+        // Persist the mapping, otherwise the recording is lost on restart
+        self.sample_bank.save_sample_config(SAMPLE_CONFIG_PATH)?;
+        
+        Ok(path)
     }
 
-    pub fn toggle_master_mute(&mut self) {
-        self.mixer.toggle_master_mute();
+    pub fn export_pattern(&mut self, path: &str) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let json = self.sequencer.export_pattern_json(group, pattern);
+        
+        match std::fs::write(path, json) {
+            Ok(()) => self.ui_state.notify(format!("Exported pattern {:02} to {}", pattern + 1, path)),
+            Err(e) => self.ui_state.notify(format!("Failed to write {}: {}", path, e)),
+        }
     }
 
-    pub fn adjust_group_volume(&mut self, group: usize, delta: f32) {
-        self.mixer.adjust_group_volume(group, delta);
+    pub fn import_pattern(&mut self, path: &str) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        
+        let result = std::fs::read_to_string(path)
+            .map_err(AppError::from)
+            .and_then(|json| self.sequencer.import_pattern_json(group, pattern, &json));
+        
+        match result {
+            Ok(()) => self.ui_state.notify(format!("Imported {} into pattern {:02}", path, pattern + 1)),
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
     }
 
-    pub fn toggle_group_mute(&mut self, group: usize) {
-        self.mixer.toggle_group_mute(group);
+    pub fn project(&self) -> Project {
+        let mut patterns: Vec<ProjectPattern> = self.sequencer
+            .pattern_keys()
+            .filter_map(|&(group, index)| {
+                let pattern = self.sequencer.get_pattern(group, index)?;
+                // An empty pattern is still worth keeping for its tempo, signature or automation
+                let keep = !pattern.is_empty()
+                    || pattern.get_tempo().is_some()
+                    || pattern.get_signature() != Signature::default()
+                    || pattern.has_automation();
+                keep.then(|| ProjectPattern { group, index, pattern: pattern.clone() })
+            })
+            .collect();
+        patterns.sort_by_key(|entry| (entry.group, entry.index));
+        
+        Project {
+            tempo: self.state.tempo,
+            swing_preset: self.state.swing_preset,
+            group_patterns: self.state.group_patterns.clone(),
+            mixer: self.mixer.settings(),
+            patterns,
+            chains: (0..self.group_count()).map(|group| self.sequencer.get_chain(group).to_vec()).collect(),
+            tempo_automation: self.song.tempo_automation.clone(),
+            divisions: (0..self.group_count()).map(|group| self.sequencer.get_division(group)).collect(),
+        }
     }
 
-    pub fn get_master_volume(&self) -> f32 {
-        self.mixer.get_master_volume()
+    // Replaces the patterns, tempo and mix with the project's; playback stops first
+    pub fn apply_project(&mut self, project: Project) {
+        self.stop();
+        self.sequencer.clear_all_patterns();
+        for entry in project.patterns {
+            self.sequencer.set_pattern(entry.group, entry.index, entry.pattern);
+        }
+        // Projects from a layout with more groups keep the extra patterns but don't play them
+        let groups = self.group_count();
+        for (group, &pattern) in project.group_patterns.iter().enumerate().take(groups) {
+            self.state.group_patterns[group] = pattern;
+            self.sequencer.set_active_pattern(group, pattern);
+        }
+        for (group, chain) in project.chains.iter().enumerate().take(groups) {
+            self.sequencer.clear_chain(group);
+            for &pattern in chain {
+                self.sequencer.append_to_chain(group, pattern);
+            }
+        }
+        for group in 0..groups {
+            self.sequencer.set_division(group, project.divisions.get(group).copied().unwrap_or_default());
+        }
+        self.song.tempo_automation = project.tempo_automation;
+        self.state.swing_preset = project.swing_preset;
+        self.set_tempo(project.tempo);
+        self.mixer.apply_settings(&project.mixer);
+        self.undo = None;
+        self.reload_pad_settings();
     }
 
-    pub fn get_group_volume(&self, group: usize) -> f32 {
-        self.mixer.get_group_volume(group)
+    // The pad settings file sits with the samples, so whatever swaps samples reads it again
+    fn reload_pad_settings(&mut self) {
+        let Some(path) = self.pad_settings_path.clone() else {
+            return;
+        };
+        match load_pad_settings(&path) {
+            Ok(settings) => self.mixer.replace_pad_settings(settings),
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
     }
 
-    pub fn is_master_muted(&self) -> bool {
-        self.mixer.is_master_muted()
+    pub fn save_project(&mut self, path: &str) {
+        match self.project().save(path) {
+            Ok(()) => self.ui_state.notify(format!("Saved session to {}", path)),
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
     }
 
-    pub fn is_group_muted(&self, group: usize) -> bool {
-        self.mixer.is_group_muted(group)
+    pub fn load_project(&mut self, path: &str) {
+        match Project::load(path) {
+            Ok(project) => {
+                self.apply_project(project);
+                self.ui_state.notify(format!("Loaded session from {}", path));
+            }
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Startup load: a missing file is a fresh start, a broken one a warning, never an exit
+    pub fn autoload_project(&mut self, path: &str) {
+        if !std::path::Path::new(path).exists() {
+            return;
+        }
+        match Project::load(path) {
+            Ok(project) => {
+                self.apply_project(project);
+                self.ui_state.notify(format!("Loaded session from {}", path));
+            }
+            Err(e) => self.ui_state.notify(format!("Starting empty: {}", e)),
+        }
+    }
 
-    #[test]
-    fn test_app_creation() {
-        let app = App::new().unwrap();
-        assert_eq!(app.state.current_group, 0);
-        assert_eq!(app.state.group_patterns, [0; 4]);
-        assert!(!app.state.is_playing);
-        assert!(!app.state.is_recording);
-        assert_eq!(app.state.tempo, 120);
-        assert_eq!(app.ui_state.selected_pad, None);
-        assert!(app.ui_state.flashing_pads.is_empty());
+    // Snapshots the project and writes it on another thread so playback never waits on disk
+    fn autosave(&mut self) {
+        // Don't overwrite the file that's on offer for restoring
+        if self.ui_state.prompt.as_ref().is_some_and(|prompt| prompt.kind == PromptKind::RestoreAutosave) {
+            return;
+        }
+        let project = self.project();
+        let Some(autosave) = &mut self.autosave else {
+            return;
+        };
+        autosave.mark_saved(Instant::now());
+        save_in_background(project, autosave.path.clone());
     }
 
-    #[test]
-    fn test_group_navigation() {
-        let mut app = App::new().unwrap();
-        
-        // Test next group
-        app.next_group();
-        assert_eq!(app.state.current_group, 1);
+    pub fn offer_autosave_restore(&mut self) {
+        self.ui_state.view = ViewMode::Normal;
+        self.ui_state.panel = Panel::Sequencer;
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::RestoreAutosave));
+    }
+
+    fn submit_restore_autosave(&mut self, input: &str) -> std::result::Result<String, String> {
+        if !input.eq_ignore_ascii_case("y") {
+            return Ok("Autosave not restored".to_string());
+        }
+        let Some(path) = self.autosave.as_ref().map(|autosave| autosave.path.clone()) else {
+            return Ok("No autosave to restore".to_string());
+        };
+        // Close the prompt first so the restore can't be blocked by it
+        self.ui_state.prompt = None;
+        match Project::load(&path) {
+            Ok(project) => {
+                // Loading stops the transport, which would autosave over the file being restored
+                let autosave = self.autosave.take();
+                self.apply_project(project);
+                self.autosave = autosave;
+                Ok("Restored autosave".to_string())
+            }
+            Err(e) => Ok(format!("Couldn't restore autosave: {}", e)),
+        }
+    }
+
+    pub fn save_pad_settings(&mut self, path: &str) {
+        match save_pad_settings(path, self.mixer.pad_settings()) {
+            Ok(()) => self.ui_state.notify(format!("Saved pad settings to {}", path)),
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
+    }
+
+    pub fn toggle_output_recording(&mut self) {
+        if self.output_recording.is_none() {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            let path = format!("{}/jam-{}.wav", OUTPUT_RECORDINGS_DIR, timestamp);
+            let result = std::fs::create_dir_all(OUTPUT_RECORDINGS_DIR)
+                .map_err(|e| AppError::Sample(format!("Failed to create {}: {}", OUTPUT_RECORDINGS_DIR, e)))
+                .and_then(|_| self.start_output_recording(&path));
+            match result {
+                Ok(()) => self.ui_state.notify(format!("Recording output to {} (up to {} minutes)", path, MAX_CAPTURE_SECS / 60)),
+                Err(e) => self.ui_state.notify(e.to_string()),
+            }
+            return;
+        }
         
-        app.next_group();
-        assert_eq!(app.state.current_group, 2);
+        match self.stop_output_recording() {
+            Ok((path, length)) => {
+                let secs = length.as_secs();
+                let capped = if secs >= MAX_CAPTURE_SECS as u64 { ", stopped at the limit" } else { "" };
+                self.ui_state.notify(format!("Saved {} ({}:{:02}{})", path, secs / 60, secs % 60, capped));
+            }
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
+    }
+
+    // Streams what the output plays from now on to a WAV at path, as it plays
+    pub fn start_output_recording(&mut self, path: &str) -> Result<()> {
+        self.mixer.start_output_recording(path)?;
+        self.output_recording = Some((path.to_string(), Instant::now()));
+        Ok(())
+    }
+
+    // Finishes the file output recording was streaming to; returns its path and length
+    pub fn stop_output_recording(&mut self) -> Result<(String, Duration)> {
+        let Some((path, _)) = self.output_recording.take() else {
+            return Err(AppError::Audio("Output isn't being recorded".to_string()));
+        };
+        let frames = self.mixer.stop_output_recording().unwrap_or(Ok(0))?;
+        Ok((path, Duration::from_secs_f64(frames as f64 / BUS_SAMPLE_RATE as f64)))
+    }
+
+    // Wall-clock time since output recording started, None while it's off
+    pub fn get_output_recording_time(&self) -> Option<Duration> {
+        self.output_recording
+            .as_ref()
+            .map(|(_, started)| started.elapsed().min(Duration::from_secs(MAX_CAPTURE_SECS as u64)))
+    }
+
+    pub fn resample_pattern_to_pad(&mut self) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to resample into first");
+            return;
+        };
+        let group = self.state.current_group;
+        
+        let levels = std::array::from_fn(|group| self.mixer.bounce_level(group));
+        let audio = render::render_pattern_loop(
+            &self.sequencer,
+            &self.sample_bank,
+            self.mixer.pad_settings(),
+            self.mixer.muted_pads(),
+            &levels,
+            render::Feel {
+                tempo: self.state.tempo,
+                swing: self.state.swing_delay_fraction(),
+                accent_boost: self.accent_boost,
+                master_gain: master_gain(self.mixer.get_master_gain_db()),
+            },
+            &mut self.rng,
+        );
+        let result = render::encode_wav(&audio, RENDER_CHANNELS, RENDER_SAMPLE_RATE)
+            .and_then(|wav| self.store_resample(group, pad, wav));
+        
+        match result {
+            Ok(name) => self.ui_state.notify(format!("Resampled pattern -> pad {} ({})", pad + 1, name)),
+            Err(e) => self.ui_state.notify(e.to_string()),
+        }
+    }
+
+    fn store_resample(&mut self, group: usize, pad: usize, wav: Vec<u8>) -> Result<String> {
+        std::fs::create_dir_all(RESAMPLED_SAMPLES_DIR)
+            .map_err(|e| AppError::Sample(format!("Failed to create {}: {}", RESAMPLED_SAMPLES_DIR, e)))?;
+        
+        // Never overwrite an earlier bounce
+        let mut n = 1;
+        while std::path::Path::new(&format!("{}/resample-{}.wav", RESAMPLED_SAMPLES_DIR, n)).exists() {
+            n += 1;
+        }
+        let name = format!("resample-{}", n);
+        let path = format!("{}/{}.wav", RESAMPLED_SAMPLES_DIR, name);
+        
+        std::fs::write(&path, &wav)
+            .map_err(|e| AppError::Sample(format!("Failed to write {}: {}", path, e)))?;
+        self.sample_bank.load_sample_data(group, pad, &name, wav)?;
+        self.sample_bank.set_sample_path(group, pad, &path);
+        self.sample_bank.save_sample_config(SAMPLE_CONFIG_PATH)?;
+        
+        Ok(name)
+    }
+
+    // Kits come from subfolders of the group's sample folder, found at startup
+    pub fn switch_kit(&mut self, delta: i32) {
+        let group = self.state.current_group;
+        let name = self.sample_bank.get_group_name(group);
+        let pads = self.pad_count();
+        match self.sample_bank.switch_kit(group, delta) {
+            None => self.ui_state.notify(format!("{} has no other kits", name)),
+            Some((kit, failed)) => {
+                let message = kit_message(&name, kit, failed, pads);
+                self.ui_state.notify(message);
+                self.reload_pad_settings();
+            }
+        }
+    }
+
+    // The prompt takes a folder path, relative to where the app was started unless absolute
+    pub fn open_import_folder(&mut self) {
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::ImportFolder));
+    }
+
+    fn submit_import_folder(&mut self, input: &str) -> std::result::Result<String, String> {
+        let dir = input.trim();
+        if dir.is_empty() {
+            return Err("Type a folder of samples".to_string());
+        }
+        let group = self.state.current_group;
+        let name = self.sample_bank.get_group_name(group);
+        let pads = self.pad_count();
+        let message = match self.sample_bank.load_directory_into_group(group, dir) {
+            Ok((kit, failed)) => kit_message(&name, kit, failed, pads),
+            Err(e) => return Err(e.to_string()),
+        };
+        self.reload_pad_settings();
+        Ok(message)
+    }
+
+    // Gives a user without sample files something to play: empty DRUMS pads get a
+    // built-in sound, laid out like a kit across the pads
+    pub fn fill_empty_pads_with_synth(&mut self) {
+        const DRUMS: usize = 0;
+        let mut filled = 0;
+        for pad in 0..self.pad_count() {
+            if self.sample_bank.has_sample(DRUMS, pad) {
+                continue;
+            }
+            let (name, wav) = match pad % 8 {
+                0 => ("synth-kick", sample::generate_kick()),
+                1 => ("synth-snare", sample::generate_snare()),
+                2 => ("synth-hihat", sample::generate_hihat()),
+                3 => ("synth-clap", sample::generate_clap()),
+                4 => ("synth-tom-lo", sample::generate_tom(90.0)),
+                5 => ("synth-tom-mid", sample::generate_tom(130.0)),
+                6 => ("synth-tom-hi", sample::generate_tom(190.0)),
+                _ => ("synth-rim", sample::generate_tom(420.0)),
+            };
+            if self.sample_bank.load_sample_data(DRUMS, pad, name, wav).is_ok() {
+                filled += 1;
+            }
+        }
+        
+        match filled {
+            0 => self.ui_state.notify(format!("Every {} pad already has a sound", self.sample_bank.get_group_name(DRUMS))),
+            filled => self.ui_state.notify(format!("Loaded synth drums into {} empty pads", filled)),
+        }
+    }
+
+    pub fn is_sampling(&self) -> bool {
+        self.sampling.is_some()
+    }
+
+    pub fn get_sampling_level(&self) -> Option<f32> {
+        self.sampling.as_ref().map(|(recorder, _, _)| recorder.level())
+    }
+
+    pub fn audition_pattern(&mut self, group: usize, pattern: usize) {
+        self.sequencer.start_audition(group, pattern);
+    }
+
+    pub fn start_audition(&mut self) {
+        // Preview the slot the user would move to next, leaving the live pattern playing
+        let pattern = (self.state.get_current_pattern() + 1) % MAX_PATTERNS;
+        self.audition_pattern(self.state.current_group, pattern);
+    }
+
+    pub fn stop_audition(&mut self) {
+        self.sequencer.stop_audition();
+    }
+
+    pub fn panic(&mut self) {
+        // Cut every ringing voice and anything queued to play next; the transport keeps running
+        self.mixer.stop_all();
+        self.sequencer.stop_audition();
+        self.pending_hits.clear();
+        self.ui_state.notify("All sounds stopped");
+    }
+
+    pub fn reinit_audio(&mut self) {
+        let message = self.mixer.reinit_output().unwrap_or_else(|e| e);
+        self.ui_state.notify(message);
+    }
+
+    pub fn tick(&mut self) {
+        // Update UI state
+        self.ui_state.update_flash();
+        self.ui_state.update_notification();
+        
+        // Tempo can change from keys, remote control or automation
+        if let Some(clock) = &mut self.midi_clock {
+            clock.set_tempo(self.state.tempo);
+        }
+        
+        if self.autosave.as_ref().is_some_and(|autosave| autosave.is_due(Instant::now())) {
+            self.autosave();
+        }
+        
+        // The sequencer keeps running while the output is swapped or missing
+        if let Some(message) = self.mixer.check_output(Instant::now()) {
+            self.ui_state.notify(message);
+        }
+        
+        // A MIDI clock input takes over from the internal step timer
+        let slaved = self.clock_in.is_some();
+        if slaved {
+            self.follow_midi_clock();
+        }
+        
+        // Steps last as long as the playing signature's resolution says
+        self.state.steps_per_beat = self.sequencer.clock_signature().steps_per_beat;
+        self.mixer.set_steps_per_beat(self.state.steps_per_beat);
+        if !slaved && self.state.should_tick() {
+            self.state.update_tick_time();
+            self.play_step();
+        } else if self.sequencer.is_auditioning() && !self.state.is_playing() && self.state.is_tick_due() {
+            // Auditions keep running on the same clock while the transport is stopped
+            self.state.update_tick_time();
+            self.play_audition_step();
+        }
+        // Patterns switched at the bar line, by song mode or by hand bring their tempo
+        if !slaved {
+            self.follow_pattern_tempo();
+        }
+        
+        self.play_due_hits(self.state.now());
+    }
+
+    // Only a change in what the patterns ask for moves the clock, so the tempo can still be
+    // adjusted by hand while a pattern with a stored tempo plays
+    fn follow_pattern_tempo(&mut self) {
+        let tempo = self.sequencer.pattern_tempo();
+        if tempo == self.pattern_tempo {
+            return;
+        }
+        self.pattern_tempo = tempo;
+        if let Some(tempo) = tempo {
+            self.set_tempo(tempo);
+        }
+    }
+
+    // Stores the current tempo in the current group's pattern
+    pub fn capture_pattern_tempo(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        self.sequencer.set_pattern_tempo(group, pattern, Some(self.state.tempo));
+        let name = self.sample_bank.get_group_name(group);
+        self.ui_state.notify(format!("{} {:02} plays at {:.1} BPM", name, pattern + 1, self.state.tempo));
+    }
+
+    pub fn clear_pattern_tempo(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        self.sequencer.set_pattern_tempo(group, pattern, None);
+        let name = self.sample_bank.get_group_name(group);
+        self.ui_state.notify(format!("{} {:02} follows the global tempo", name, pattern + 1));
+    }
+
+    // Whether the clock is running at a playing pattern's stored tempo
+    pub fn is_tempo_from_pattern(&self) -> bool {
+        self.pattern_tempo == Some(self.state.tempo)
+    }
+
+    // Plays the step under the playhead and moves on; state.last_tick is when the step began
+    fn play_step(&mut self) {
+        self.ui_state.mark_dirty(); // The playhead moves
+        
+        // Get hits for current position
+        let step = self.sequencer.get_current_step();
+        self.mixer.sync_gates_to_step(step);
+        let tick = self.sequencer.get_tick_count();
+        let morphing: Vec<usize> = (0..self.group_count()).filter(|&group| self.sequencer.get_morph(group).is_some()).collect();
+        self.apply_automation();
+        let mut hits = self.sequencer.tick(self.state.tempo);
+        hits.retain(|hit| !self.mixer.is_pad_muted(hit.group, hit.pad));
+        let repeated = self.repeated_pad().filter(|&(group, pad)| !self.mixer.is_pad_muted(group, pad));
+        
+        // Start flash for new hits; early hits for the next step flash when it arrives
+        let mut flashing: Vec<(usize, usize)> = hits.iter().filter(|hit| !hit.ahead).map(|hit| (hit.group, hit.pad)).collect();
+        if let Some((group, pad)) = repeated {
+            self.play_pad(group, pad, 1.0, MAX_VELOCITY);
+            flashing.push((group, pad));
+        }
+        self.ui_state.start_flash(flashing);
+        
+        // Play hits on the grid now and queue nudged ones for their time
+        let tick_time = self.state.last_tick;
+        let step_duration = self.state.step_duration();
+        let swing_delay = self.state.swing_delay();
+        for hit in hits {
+            // A morphing group's hits come from the pattern it's playing this bar
+            let playing = self.sequencer.get_playing_pattern(hit.group);
+            let velocity = playing.map_or(DEFAULT_VELOCITY, |pattern| pattern.get_velocity(hit.pad, hit.step));
+            let accented = playing.is_some_and(|pattern| pattern.is_accented(hit.step));
+            let gain = accented_gain(velocity_gain(velocity), accented, self.accent_boost);
+            
+            // Swing holds back the group's off-beat steps, in proportion to their length
+            let scale = self.sequencer.get_division(hit.group).step_scale();
+            let swing = if hit.step % 2 == 1 { swing_delay.mul_f32(scale) } else { Duration::ZERO };
+            
+            let on_grid = hit.offset_ms == 0 && !hit.ahead && !hit.sub_step && swing.is_zero();
+            let step_start = if hit.ahead {
+                tick_time + step_duration
+            } else if hit.sub_step {
+                tick_time + step_duration / 2
+            } else {
+                tick_time
+            } + swing;
+            let at = offset_instant(step_start, hit.offset_ms);
+            
+            // Ratchets split the group's step evenly, starting where the hit itself lands
+            let ratchet = playing.map_or(1, |pattern| pattern.get_ratchet(hit.pad, hit.step)) as u32;
+            let spacing = step_duration.mul_f32(scale) / ratchet;
+            for repeat in 0..ratchet {
+                if repeat == 0 && on_grid {
+                    self.play_pad(hit.group, hit.pad, gain, velocity);
+                } else {
+                    self.schedule_hit(PendingHit { at: at + spacing * repeat, group: hit.group, pad: hit.pad, gain, velocity });
+                }
+            }
+        }
+        
+        self.play_audition_step();
+        
+        // The bar clock restarts on step 0, or mid-bar when resuming from a pause
+        if step == 0 {
+            self.flush_recorded_triggers();
+            self.tempo_meter.record_wrap(self.state.last_tick, tick, self.state.tempo, self.state.steps_per_beat);
+        }
+        if step == 0 || self.bar_start.is_none() {
+            self.bar_start = Some(self.state.last_tick - self.state.step_duration() * step as u32);
+            self.bar_start_tick = tick.wrapping_sub(step);
+        }
+        
+        if self.sequencer.get_current_step() == 0 {
+            self.state.bars_elapsed += 1;
+            self.follow_chains();
+            self.follow_morphs(&morphing);
+            self.advance_song_position();
+        }
+    }
+
+    fn follow_midi_clock(&mut self) {
+        let Some(clock_in) = &mut self.clock_in else {
+            return;
+        };
+        let events = clock_in.poll();
+        if let Some(bpm) = clock_in.bpm() {
+            if (bpm - self.state.tempo).abs() >= 0.05 {
+                self.state.set_tempo(bpm);
+                self.mixer.set_tempo(self.state.tempo);
+                self.ui_state.mark_dirty();
+            }
+        }
+        
+        for event in events {
+            match event {
+                ClockSync::Start => {
+                    if self.state.transport != Transport::Stopped {
+                        self.stop();
+                    }
+                    self.toggle_playback();
+                }
+                // Stop pauses, so a Continue picks up where the song was
+                ClockSync::Continue if !self.state.is_playing() => self.toggle_playback(),
+                ClockSync::Stop if self.state.is_playing() => self.toggle_playback(),
+                ClockSync::Step(at) if self.state.is_playing() => {
+                    self.state.last_tick = at;
+                    self.play_step();
+                }
+                ClockSync::Lost => {
+                    self.stop();
+                    self.ui_state.notify("MIDI clock lost, stopped");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_clock_slaved(&self) -> bool {
+        self.clock_in.is_some()
+    }
+
+    // Keeps the queue ordered by time so due hits are always at the front
+    fn schedule_hit(&mut self, hit: PendingHit) {
+        let index = self.pending_hits.partition_point(|pending| pending.at <= hit.at);
+        self.pending_hits.insert(index, hit);
+    }
+
+    fn play_due_hits(&mut self, now: Instant) {
+        // Moves are queued a tick at a time, so they're already in order
+        let due = self.pending_automation.partition_point(|&(at, _, _)| at <= now);
+        for (_, group, automation) in self.pending_automation.drain(..due).collect::<Vec<_>>() {
+            self.apply_move(group, automation);
+        }
+        
+        let due = self.pending_hits.partition_point(|pending| pending.at <= now);
+        for hit in self.pending_hits.drain(..due).collect::<Vec<_>>() {
+            // Ratchets and nudged hits queued before the pad was muted
+            if self.mixer.is_pad_muted(hit.group, hit.pad) {
+                continue;
+            }
+            self.play_pad(hit.group, hit.pad, hit.gain, hit.velocity);
+        }
+    }
+
+    // When the main loop should wake up next for a queued hit or mixer move
+    pub fn next_pending_hit(&self) -> Option<Instant> {
+        let hit = self.pending_hits.first().map(|hit| hit.at);
+        let automation = self.pending_automation.first().map(|&(at, _, _)| at);
+        hit.into_iter().chain(automation).min()
+    }
+
+    // One pass of the arrangement: the longest chain, or up to the last tempo breakpoint
+    pub fn song_length_bars(&self) -> usize {
+        let chains = (0..self.group_count()).map(|group| self.get_chain(group).len());
+        let automation = self.song.tempo_automation.last().map(|&(bar, _)| bar + 1);
+        chains.chain(automation).max().unwrap_or(0).max(1)
+    }
+
+    // Bars before the playing patterns come round again: chained groups go through their
+    // chain, and a half-time group takes two bars over each pattern
+    pub fn pattern_loop_bars(&self) -> usize {
+        (0..self.group_count())
+            .map(|group| self.get_chain(group).len().max(1) * self.sequencer.get_division(group).step_scale().ceil() as usize)
+            .max()
+            .unwrap_or(1)
+    }
+
+    pub fn append_to_chain(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let length = self.sequencer.append_to_chain(group, pattern);
+        self.ui_state.notify(format!("Chained pattern {:02} ({} in chain)", pattern + 1, length));
+    }
+
+    pub fn clear_chain(&mut self) {
+        self.sequencer.clear_chain(self.state.current_group);
+        self.ui_state.notify("Chain cleared");
+    }
+
+    pub fn get_chain(&self, group: usize) -> &[usize] {
+        self.sequencer.get_chain(group)
+    }
+
+    pub fn get_chain_position(&self, group: usize) -> Option<usize> {
+        self.sequencer.get_chain_position(group)
+    }
+
+    // Chained groups show the pattern the sequencer moved them to
+    fn follow_chains(&mut self) {
+        for group in 0..self.group_count() {
+            if self.sequencer.get_chain_position(group).is_some() {
+                self.state.group_patterns[group] = self.sequencer.get_active_pattern(group);
+            }
+        }
+    }
+
+    // Groups whose morph ended at the bar line are now on its target pattern
+    fn follow_morphs(&mut self, morphing: &[usize]) {
+        for &group in morphing {
+            if self.sequencer.get_morph(group).is_none() {
+                let pattern = self.sequencer.get_active_pattern(group);
+                self.state.group_patterns[group] = pattern;
+                if group == self.state.current_group {
+                    self.ui_state.notify(format!("Morphed into pattern {:02}", pattern + 1));
+                }
+            }
+        }
+    }
+
+    // Crossfades the current group into a 0-based pattern slot over the coming bars
+    pub fn start_morph(&mut self, to: usize, bars: usize) -> std::result::Result<String, String> {
+        let group = self.state.current_group;
+        if to >= MAX_PATTERNS {
+            return Err(format!("No pattern {:02}; choose 01–{}", to.wrapping_add(1), MAX_PATTERNS));
+        }
+        if to == self.state.group_patterns[group] {
+            return Err(format!("Already on pattern {:02}", to + 1));
+        }
+        let bars = bars.clamp(1, MAX_MORPH_BARS);
+        let seed = self.rng.gen();
+        self.sequencer.start_morph(group, to, bars, seed);
+        Ok(format!("Morphing into pattern {:02} over {} bars", to + 1, bars))
+    }
+
+    pub fn get_morph(&self, group: usize) -> Option<(usize, f32)> {
+        self.sequencer.get_morph(group)
+    }
+
+    pub fn cycle_clock_division(&mut self) {
+        let group = self.state.current_group;
+        let division = self.sequencer.cycle_division(group);
+        self.ui_state.notify(format!("Clock {}", division.label()));
+    }
+
+    pub fn get_clock_division(&self, group: usize) -> ClockDivision {
+        self.sequencer.get_division(group)
+    }
+
+    pub fn cycle_swing(&mut self) {
+        self.state.cycle_swing();
+        self.ui_state.notify(format!("Swing {}%", self.state.swing_percent()));
+    }
+
+    pub fn get_swing_percent(&self) -> u8 {
+        self.state.swing_percent()
+    }
+
+    pub fn nudge_step(&mut self, delta_ms: i16) {
+        self.edit_cursor_step("Nudge", |sequencer, group, pattern, pad, step| {
+            sequencer
+                .nudge_step(group, pattern, pad, step, delta_ms)
+                .map(|offset| format!("{:+} ms", offset))
+        });
+    }
+
+    // Called at each bar boundary while playing
+    pub fn advance_song_position(&mut self) {
+        self.song.bar += 1;
+        self.apply_tempo_automation();
+    }
+
+    fn apply_tempo_automation(&mut self) {
+        if self.is_clock_slaved() {
+            return;
+        }
+        if let Some(bpm) = self.song.tempo_at(self.song.bar) {
+            if bpm != self.state.tempo {
+                self.set_tempo(bpm);
+            }
+        }
+    }
+
+    pub fn add_tempo_breakpoint(&mut self) {
+        self.song.add_tempo_breakpoint(self.song.bar, self.state.tempo);
+        self.ui_state.notify(format!("Tempo {:.1} BPM at bar {}", self.state.tempo, self.song.bar + 1));
+    }
+
+    pub fn clear_tempo_automation(&mut self) {
+        self.song.clear_tempo_automation();
+        self.ui_state.notify("Tempo automation cleared");
+    }
+
+    // Next breakpoint's tempo while the automation is ramping towards it
+    pub fn get_tempo_target(&self) -> Option<f32> {
+        self.song.tempo_target(self.song.bar)
+    }
+
+    fn play_audition_step(&mut self) {
+        let Some((pattern, step)) = self.sequencer.get_audition().map(|a| (a.pattern_idx, a.step)) else {
+            return;
+        };
+        
+        for (group, pad) in self.sequencer.tick_audition() {
+            let velocity = self.sequencer.get_step_velocity(group, pattern, pad, step);
+            self.play_pad(group, pad, AUDITION_GAIN * velocity_gain(velocity), velocity);
+        }
+    }
+
+    // Every pad hit goes through here so it picks up the pad's trim points, normalization and MIDI note
+    fn play_pad(&mut self, group: usize, pad: usize, gain: f32, velocity: u8) {
+        self.play_pad_pitched(group, pad, gain, velocity, 0);
+    }
+
+    // A hit shifted by semitones on top of the pad's own pitch, MIDI note included
+    fn play_pad_pitched(&mut self, group: usize, pad: usize, gain: f32, velocity: u8, semitones: u8) {
+        let note = self.midi_notes.get(&(group, pad)).copied();
+        let note = note.map(|note| PadNote { note: note.note.saturating_add(semitones).min(127), ..note });
+        // External gear goes quiet along with the group, like the samples do
+        if let (Some(note), Some(clock)) = (note, &self.midi_clock) {
+            if self.mixer.is_group_heard(group) {
+                clock.send_note(note, gain_velocity(gain), self.midi_gate);
+            }
+        }
+        if note.is_some_and(|note| note.midi_only) {
+            return;
+        }
+        
+        // Stacked pads play every layer, round-robin and random ones pick one per hit, and
+        // velocity-switched ones play the layers the velocity falls in
+        let picked = self.sample_bank.next_layers(group, pad, velocity, &mut self.rng);
+        let layers: Vec<(&SampleStorage, f32)> = picked
+            .into_iter()
+            .filter_map(|(index, weight)| self.sample_bank.get_layer_sample(group, pad, index).map(|(data, level)| (data, level * weight)))
+            .collect();
+        if !layers.is_empty() {
+            let trim = self.sample_bank.get_trim(group, pad);
+            // stderr is hidden behind the TUI, so failures go to the footer instead
+            if let Err(e) = self.mixer.play_pad_layers(&layers, group, pad, gain, trim, semitones as f32) {
+                let message = format!("{} pad {}: {}", self.sample_bank.get_group_name(group), pad + 1, e);
+                self.ui_state.notify(message.clone());
+                self.last_error = Some(message);
+            }
+        }
+    }
+
+    pub fn adjust_trim_start(&mut self, delta: f32) {
+        self.edit_selected_trim(|bank, group, pad| {
+            bank.adjust_start_offset(group, pad, delta);
+        });
+    }
+
+    pub fn adjust_trim_end(&mut self, delta: f32) {
+        self.edit_selected_trim(|bank, group, pad| {
+            bank.adjust_end_offset(group, pad, delta);
+        });
+    }
+
+    fn edit_selected_trim(&mut self, edit: impl FnOnce(&mut SampleBank, usize, usize)) {
+        let group = self.state.current_group;
+        let Some(pad) = self.ui_state.selected_pad.filter(|&pad| self.sample_bank.has_sample(group, pad)) else {
+            self.ui_state.notify("Select a pad with a sample to trim it");
+            return;
+        };
+        
+        edit(&mut self.sample_bank, group, pad);
+        let (start, end) = self.sample_bank.get_trim(group, pad);
+        self.ui_state.notify(format!(
+            "Trim pad {}: {:.0}%–{:.0}%",
+            pad + 1,
+            start * 100.0,
+            end * 100.0
+        ));
+    }
+
+    pub fn toggle_panel(&mut self) {
+        self.ui_state.toggle_panel();
+    }
+
+    // The prompt lives in the pattern panel, so make sure it's on screen
+    pub fn open_goto_pattern(&mut self) {
+        self.ui_state.view = ViewMode::Normal;
+        self.ui_state.panel = Panel::Sequencer;
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::GotoPattern));
+    }
+
+    pub fn open_morph(&mut self) {
+        self.ui_state.view = ViewMode::Normal;
+        self.ui_state.panel = Panel::Sequencer;
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::Morph));
+    }
+
+    pub fn prompt_input(&mut self, c: char) {
+        let Some(prompt) = &mut self.ui_state.prompt else {
+            return;
+        };
+        prompt.push(c);
+        if !prompt.is_complete() {
+            return;
+        }
+        
+        // A rejected entry starts over so the next digits are a fresh try
+        self.submit_prompt();
+        if let Some(prompt) = &mut self.ui_state.prompt {
+            prompt.input.clear();
+        }
+    }
+
+    pub fn prompt_backspace(&mut self) {
+        if let Some(prompt) = &mut self.ui_state.prompt {
+            prompt.backspace();
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.ui_state.prompt = None;
+    }
+
+    // Invalid input leaves the prompt open so it can be corrected
+    pub fn submit_prompt(&mut self) {
+        let Some(prompt) = self.ui_state.prompt.clone() else {
+            return;
+        };
+        
+        let result = match prompt.kind {
+            PromptKind::GotoPattern => self.submit_goto_pattern(&prompt.input),
+            PromptKind::Morph => self.submit_morph(&prompt.input),
+            PromptKind::RestoreAutosave => self.submit_restore_autosave(&prompt.input),
+            PromptKind::Euclidean => self.submit_euclidean(&prompt.input),
+            PromptKind::ImportFolder => self.submit_import_folder(&prompt.input),
+            PromptKind::Signature => self.submit_signature(&prompt.input),
+            PromptKind::RescaleSignature(signature) => self.submit_rescale_signature(signature, &prompt.input),
+            PromptKind::MasterGain => self.submit_master_gain(&prompt.input),
+        };
+        match result {
+            Ok(message) => {
+                // A follow-up question replaces the prompt rather than closing it
+                if self.ui_state.prompt.as_ref() == Some(&prompt) {
+                    self.ui_state.prompt = None;
+                }
+                self.ui_state.notify(message);
+            }
+            Err(message) => self.ui_state.notify(message),
+        }
+    }
+
+    // Patterns are typed as numbered on screen, starting from 01
+    fn submit_goto_pattern(&mut self, input: &str) -> std::result::Result<String, String> {
+        let number: usize = input.parse().map_err(|_| "Type a pattern number".to_string())?;
+        self.go_to_pattern(number.wrapping_sub(1))
+    }
+
+    fn submit_morph(&mut self, input: &str) -> std::result::Result<String, String> {
+        let number: usize = input.parse().map_err(|_| "Type a pattern number".to_string())?;
+        self.start_morph(number.wrapping_sub(1), self.morph_bars)
+    }
+
+    // Switches the current group straight to a 0-based pattern slot
+    pub fn go_to_pattern(&mut self, pattern: usize) -> std::result::Result<String, String> {
+        if pattern >= MAX_PATTERNS {
+            return Err(format!("No pattern {:02}; choose 01–{}", pattern.wrapping_add(1), MAX_PATTERNS));
+        }
+        
+        self.state.set_current_pattern(pattern);
+        self.sequencer.set_active_pattern(self.state.current_group, pattern);
+        Ok(format!("Pattern {:02}", pattern + 1))
+    }
+
+    pub fn open_signature(&mut self) {
+        self.ui_state.view = ViewMode::Normal;
+        self.ui_state.panel = Panel::Sequencer;
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::Signature));
+    }
+
+    // Only a change of resolution on a pattern with hits needs asking about
+    fn submit_signature(&mut self, input: &str) -> std::result::Result<String, String> {
+        let (beats, steps) = input.split_once(',').ok_or_else(|| "Type beats, a comma, then steps per beat".to_string())?;
+        let signature = beats
+            .parse()
+            .ok()
+            .zip(steps.parse().ok())
+            .and_then(|(beats, steps)| Signature::new(beats, steps))
+            .ok_or_else(|| format!("Use 1–{} beats of 2, 3, 4, 6 or 8 steps", MAX_BEATS_PER_BAR))?;
+        
+        let pattern = self.get_current_pattern_data();
+        let regrid = pattern.is_some_and(|pattern| !pattern.is_empty() && pattern.get_signature().steps_per_beat != signature.steps_per_beat);
+        if regrid {
+            self.ui_state.prompt = Some(Prompt::new(PromptKind::RescaleSignature(signature)));
+            return Ok(signature.label());
+        }
+        Ok(self.set_signature(signature, false))
+    }
+
+    fn submit_rescale_signature(&mut self, signature: Signature, input: &str) -> std::result::Result<String, String> {
+        Ok(self.set_signature(signature, input.eq_ignore_ascii_case("y")))
+    }
+
+    // Turns the current pattern into one bar of the signature; see Pattern::set_signature
+    pub fn set_signature(&mut self, signature: Signature, rescale: bool) -> String {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        self.sequencer.set_pattern_signature(group, pattern, signature, rescale);
+        self.remember_undo(group, pattern, before, "signature");
+        let length = signature.bar_steps();
+        self.ui_state.cursor_step = self.ui_state.cursor_step.min(length - 1);
+        self.ui_state.move_cursor_step(0, length);
+        format!("Pattern {:02}: {}, {} steps", pattern + 1, signature.label(), signature.bar_steps())
+    }
+
+    pub fn toggle_pad_detail(&mut self) {
+        if !self.ui_state.pad_detail && self.ui_state.selected_pad.is_none() {
+            self.ui_state.notify("Select a pad to see its details");
+            return;
+        }
+        self.ui_state.pad_detail = !self.ui_state.pad_detail;
+    }
+
+    // The pad keys play the active bank, so a second bank is reached by switching
+    pub fn cycle_pad_bank(&mut self) {
+        let pads = self.pad_count();
+        if pads <= PADS_PER_GROUP {
+            self.ui_state.notify(format!("Only {} pads; set [layout] pads_per_group = 32 for a second bank", pads));
+            return;
+        }
+        self.ui_state.cycle_pad_bank(pads);
+        let bank = self.ui_state.bank_pads(pads);
+        self.ui_state.notify(format!("Pads {}–{}", bank.start + 1, bank.end));
+    }
+
+    pub fn cycle_layer_mode(&mut self) {
+        let group = self.state.current_group;
+        let Some(pad) = self.ui_state.selected_pad.filter(|&pad| self.sample_bank.has_sample(group, pad)) else {
+            self.ui_state.notify("Select a pad with a sample to change how its layers play");
+            return;
+        };
+        
+        let mode = self.sample_bank.get_layer_mode(group, pad).next();
+        self.sample_bank.set_layer_mode(group, pad, mode);
+        let samples = self.sample_bank.layer_count(group, pad);
+        self.ui_state.notify(format!(
+            "Pad {} layers: {}{}",
+            pad + 1,
+            mode.label(),
+            if samples < 2 { " (only one sample loaded)" } else { "" }
+        ));
+    }
+
+    // Pad keys are bound to the first bank; this moves them onto the active one
+    pub fn bank_pad(&self, pad: usize) -> usize {
+        if pad < PADS_PER_GROUP {
+            pad + self.ui_state.bank_pads(self.pad_count()).start
+        } else {
+            pad
+        }
+    }
+
+    pub fn get_visible_pads(&self) -> std::ops::Range<usize> {
+        self.ui_state.bank_pads(self.pad_count())
+    }
+
+    // Opens on the current pattern
+    pub fn toggle_pattern_map(&mut self) {
+        self.ui_state.pattern_map = match self.ui_state.pattern_map {
+            Some(_) => None,
+            None => Some(self.state.get_current_pattern()),
+        };
+    }
+
+    pub fn move_pattern_map_cursor(&mut self, delta: i32) {
+        if let Some(slot) = &mut self.ui_state.pattern_map {
+            *slot = (*slot as i32 + delta).clamp(0, MAX_PATTERNS as i32 - 1) as usize;
+        }
+    }
+
+    pub fn select_pattern_map_slot(&mut self) {
+        let Some(slot) = self.ui_state.pattern_map.take() else {
+            return;
+        };
+        if let Ok(message) = self.go_to_pattern(slot) {
+            self.ui_state.notify(message);
+        }
+    }
+
+    pub fn copy_pattern(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let contents = self.sequencer.get_pattern(group, pattern).cloned().unwrap_or_else(Pattern::new);
+        self.clipboard = Some((group, pattern, contents));
+        self.ui_state.notify(format!("Copied {} {:02}", self.sample_bank.get_group_name(group), pattern + 1));
+    }
+
+    // Pastes into the current slot of whichever group is selected now. Compacting moves the
+    // copied rows onto the pads that have samples here, for copies between differently loaded groups.
+    pub fn paste_pattern(&mut self, compact: bool) {
+        let Some((source_group, source_pattern, contents)) = &self.clipboard else {
+            self.ui_state.notify("Copy a pattern first");
+            return;
+        };
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let remap = if compact {
+            RemapMode::Compact((0..self.pad_count()).filter(|&pad| self.sample_bank.has_sample(group, pad)).collect())
+        } else {
+            RemapMode::Identity
+        };
+        
+        let from = format!("{} {:02}", self.sample_bank.get_group_name(*source_group), source_pattern + 1);
+        let dropped = self.sequencer.paste_pattern(group, pattern, contents, remap);
+        if dropped > 0 {
+            self.ui_state.notify(format!(
+                "Pasted {} into pattern {:02}; dropped {} rows with no loaded pad",
+                from,
+                pattern + 1,
+                dropped
+            ));
+        } else {
+            self.ui_state.notify(format!("Pasted {} into pattern {:02}", from, pattern + 1));
+        }
+    }
+
+    // Copies the current pattern into the group's first empty slot and switches to it
+    pub fn duplicate_to_new_slot(&mut self) {
+        let group = self.state.current_group;
+        let source = self.state.group_patterns[group];
+        let Some(pattern) = self.sequencer.get_pattern(group, source).filter(|pattern| !pattern.is_empty()).cloned() else {
+            self.ui_state.notify(format!("Pattern {:02} is empty", source + 1));
+            return;
+        };
+        let Some(target) = self.sequencer.first_empty_pattern(group) else {
+            self.ui_state.notify("No empty pattern slots left in this group");
+            return;
+        };
+        
+        self.sequencer.set_pattern(group, target, pattern);
+        if self.go_to_pattern(target).is_ok() {
+            self.ui_state.notify(format!("Duplicated pattern {:02} to {:02}", source + 1, target + 1));
+        }
+    }
+
+    // Patterns in the current group that have at least one hit
+    pub fn used_patterns(&self) -> Vec<usize> {
+        let group = self.state.current_group;
+        let mut used: Vec<usize> = self.sequencer
+            .pattern_keys()
+            .filter(|&&(g, pattern)| g == group && !self.sequencer.pattern_is_empty(g, pattern))
+            .map(|&(_, pattern)| pattern)
+            .collect();
+        used.sort_unstable();
+        used
+    }
+
+    pub fn toggle_overview(&mut self) {
+        self.ui_state.toggle_overview();
+    }
+
+    // Picking a lane in the overview goes straight back to editing that group
+    pub fn select_group(&mut self, group: usize) {
+        self.state.select_group(group);
+        self.ui_state.view = ViewMode::Normal;
+    }
+
+    pub fn cycle_grid_mode(&mut self) {
+        self.ui_state.cycle_grid_mode();
+    }
+
+    pub fn move_step_cursor(&mut self, delta: i32) {
+        let length = self.get_current_pattern_length();
+        self.ui_state.move_cursor_step(delta, length);
+    }
+
+    pub fn move_step_page(&mut self, delta: i32) {
+        let length = self.get_current_pattern_length();
+        self.ui_state.move_step_page(delta, length);
+        let steps = self.ui_state.visible_steps(0, false, length, STEPS_PER_PAGE);
+        self.ui_state.notify(format!("Steps {}–{}", steps.start + 1, steps.end));
+    }
+
+    // Borrowed so the pattern view doesn't copy the grid every frame
+    pub fn get_current_pattern_data(&self) -> Option<&Pattern> {
+        self.get_group_pattern_data(self.state.current_group)
+    }
+
+    pub fn get_group_pattern_data(&self, group: usize) -> Option<&Pattern> {
+        let pattern = *self.state.group_patterns.get(group)?;
+        self.sequencer.get_pattern(group, pattern)
+    }
+
+    pub fn get_group_pattern(&self, group: usize) -> usize {
+        self.state.group_patterns.get(group).copied().unwrap_or(0)
+    }
+
+    pub fn get_current_pattern_length(&self) -> usize {
+        self.get_current_pattern_data().map_or(STEPS_PER_PATTERN, |pattern| pattern.length)
+    }
+
+    // Steps that fit in a pattern view `window` columns wide
+    pub fn get_visible_steps(&self, window: usize) -> Range<usize> {
+        let length = self.get_current_pattern_length();
+        self.ui_state.visible_steps(self.get_current_pattern_step(), self.is_playing(), length, window)
+    }
+
+    pub fn adjust_step_velocity(&mut self, delta: i16) {
+        self.edit_cursor_step("Velocity", |sequencer, group, pattern, pad, step| {
+            sequencer.adjust_step_velocity(group, pattern, pad, step, delta * VELOCITY_STEP)
+        });
+    }
+
+    pub fn adjust_step_probability(&mut self, delta: i16) {
+        self.edit_cursor_step("Probability", |sequencer, group, pattern, pad, step| {
+            sequencer.adjust_step_probability(group, pattern, pad, step, delta * PROBABILITY_STEP)
+        });
+    }
+
+    // Clicked grid cells flip their hit and move the step cursor there
+    pub fn toggle_step(&mut self, pad: usize, step: usize) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        if pad >= self.pad_count() || step >= self.get_current_pattern_length() {
+            return;
+        }
+        
+        self.sequencer.toggle_step(group, pattern, pad, step);
+        self.ui_state.select_pad(pad);
+        self.ui_state.cursor_step = step;
+    }
+
+    // The accent row works on steps rather than pads, so it needs no selected pad
+    pub fn toggle_accent(&mut self, step: usize) {
+        let group = self.state.current_group;
+        if step >= self.get_current_pattern_length() {
+            return;
+        }
+        
+        let accented = self.sequencer.toggle_accent(group, self.state.group_patterns[group], step);
+        self.ui_state.cursor_step = step;
+        self.ui_state.notify(format!("Accent step {}: {}", step + 1, if accented { "on" } else { "off" }));
+    }
+
+    pub fn toggle_accent_at_cursor(&mut self) {
+        self.toggle_accent(self.ui_state.cursor_step);
+    }
+
+    pub fn is_step_accented(&self, step: usize) -> bool {
+        let group = self.state.current_group;
+        self.sequencer.is_step_accented(group, self.state.group_patterns[group], step)
+    }
+
+    pub fn cycle_ratchet(&mut self) {
+        self.edit_cursor_step("Ratchet", |sequencer, group, pattern, pad, step| {
+            sequencer.cycle_ratchet(group, pattern, pad, step).map(|ratchet| format!("×{}", ratchet))
+        });
+    }
+
+    pub fn get_step_ratchet(&self, pad: usize, step: usize) -> u8 {
+        let group = self.state.current_group;
+        self.sequencer.get_step_ratchet(group, self.state.group_patterns[group], pad, step)
+    }
+
+    pub fn toggle_step_mute(&mut self) {
+        self.edit_cursor_step("Mute", |sequencer, group, pattern, pad, step| {
+            sequencer
+                .toggle_step_mute(group, pattern, pad, step)
+                .map(|muted| if muted { "on" } else { "off" })
+        });
+    }
+
+    pub fn humanize_pad(&mut self) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to humanize its row");
+            return;
+        };
+        self.humanize_pads(pad..pad + 1);
+    }
+
+    pub fn humanize_pattern(&mut self) {
+        self.humanize_pads(0..self.pad_count());
+    }
+
+    fn humanize_pads(&mut self, pads: std::ops::Range<usize>) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.sequencer.get_pattern(group, pattern).cloned();
+        
+        // Timing never moves a hit past the point playback would clamp it to anyway
+        let max_offset = max_micro_offset_ms(self.state.tempo);
+        let touched = self.sequencer.humanize(group, pattern, pads, self.humanize, max_offset, &mut self.rng);
+        let Some(before) = before.filter(|_| touched > 0) else {
+            self.ui_state.notify("Nothing to humanize");
+            return;
+        };
+        
+        self.remember_undo(group, pattern, before, "humanize");
+        self.ui_state.notify(format!(
+            "Humanized {} steps, vel ±{:.0}%, ±{} ms",
+            touched,
+            self.humanize.velocity * 100.0,
+            self.humanize.timing_ms
+        ));
+    }
+
+    // Puts a hit on every step of the selected pad's row, within the pattern length
+    pub fn fill_selected_pad(&mut self) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to fill its row");
+            return;
+        };
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        
+        self.sequencer.fill_pad_row(group, pattern, pad, true);
+        self.remember_undo(group, pattern, before, "fill");
+        self.ui_state.notify(format!("Filled pad {} on every step", pad + 1));
+    }
+
+    // Strips the pattern down to a skeleton: hits on every 4th step first, then every 8th
+    // and 16th on repeated presses, each time keeping a hit on every row that had one
+    pub fn thin_pattern(&mut self) {
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        let thinned = [4, 8, 16].into_iter().find(|&keep_every| before.clone().thin(keep_every) > 0);
+        let Some(keep_every) = thinned else {
+            self.ui_state.notify("Nothing left to thin");
+            return;
+        };
+        
+        let removed = self.sequencer.thin_pattern(group, pattern, keep_every);
+        self.remember_undo(group, pattern, before, "thin");
+        self.ui_state.notify(format!("Thinned to every {} steps, {} hits removed", keep_every, removed));
+    }
+
+    pub fn clear_selected_pad(&mut self) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to clear its row");
+            return;
+        };
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        if !before.steps[pad].contains(&true) {
+            self.ui_state.notify(format!("Pad {} has no hits to clear", pad + 1));
+            return;
+        }
+        
+        self.sequencer.fill_pad_row(group, pattern, pad, false);
+        self.remember_undo(group, pattern, before, "clear");
+        self.ui_state.notify(format!("Cleared pad {}", pad + 1));
+    }
+
+    // The prompt lives in the pattern panel, like goto pattern's
+    pub fn open_euclidean(&mut self) {
+        if self.ui_state.selected_pad.is_none() {
+            self.ui_state.notify("Select a pad to generate a rhythm on");
+            return;
+        }
+        self.ui_state.view = ViewMode::Normal;
+        self.ui_state.panel = Panel::Sequencer;
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::Euclidean));
+    }
+
+    fn submit_euclidean(&mut self, input: &str) -> std::result::Result<String, String> {
+        let pad = self.ui_state.selected_pad.ok_or_else(|| "Select a pad first".to_string())?;
+        let (pulses, rotation) = input.split_once(',').unwrap_or((input, "0"));
+        let pulses: usize = pulses.parse().map_err(|_| "Type the number of hits".to_string())?;
+        let rotation: usize = rotation.parse().unwrap_or(0);
+        let length = self.get_current_pattern_length();
+        if pulses > length {
+            return Err(format!("At most {} hits fit in this pattern", length));
+        }
+        
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let before = self.current_pattern_or_empty();
+        self.sequencer.euclidean(group, pattern, pad, pulses, rotation);
+        self.remember_undo(group, pattern, before, "euclid");
+        Ok(match rotation % length.max(1) {
+            0 => format!("Pad {}: E({},{})", pad + 1, pulses, length),
+            rotation => format!("Pad {}: E({},{}) rotated {}", pad + 1, pulses, length, rotation),
+        })
+    }
+
+    fn pattern_or_empty(&self, group: usize, pattern: usize) -> Pattern {
+        self.sequencer
+            .get_pattern(group, pattern)
+            .cloned()
+            .unwrap_or_else(|| Pattern::with_size(self.pad_count(), STEPS_PER_PATTERN))
+    }
+
+    fn current_pattern_or_empty(&self) -> Pattern {
+        let group = self.state.current_group;
+        self.pattern_or_empty(group, self.state.group_patterns[group])
+    }
+
+    // Called once the edit is made, so anything that changes the pattern after it shows
+    fn remember_undo(&mut self, group: usize, pattern: usize, before: Pattern, edit: &'static str) {
+        let after = self.pattern_or_empty(group, pattern);
+        self.undo = Some(PatternUndo { group, pattern, before, after, edit });
+    }
+
+    pub fn undo(&mut self) {
+        match self.undo.take() {
+            Some(undo) if self.pattern_or_empty(undo.group, undo.pattern) == undo.after => {
+                self.sequencer.set_pattern(undo.group, undo.pattern, undo.before);
+                self.ui_state.notify(format!("Undid {} on pattern {:02}", undo.edit, undo.pattern + 1));
+            }
+            Some(undo) => self.ui_state.notify(format!(
+                "Pattern {:02} has changed since the {}, nothing to undo",
+                undo.pattern + 1,
+                undo.edit
+            )),
+            None => self.ui_state.notify("Nothing to undo"),
+        }
+    }
+
+    fn edit_cursor_step<T: std::fmt::Display>(
+        &mut self,
+        label: &str,
+        edit: impl FnOnce(&mut Sequencer, usize, usize, usize, usize) -> Option<T>,
+    ) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to edit its steps");
+            return;
+        };
+        let group = self.state.current_group;
+        let pattern = self.state.group_patterns[group];
+        let step = self.ui_state.cursor_step;
+        
+        match edit(&mut self.sequencer, group, pattern, pad, step) {
+            Some(value) => self.ui_state.notify(format!("{} pad {} step {}: {}", label, pad + 1, step + 1, value)),
+            None => self.ui_state.notify(format!("No hit on pad {} step {}", pad + 1, step + 1)),
+        }
+    }
+
+    pub fn get_step_velocity(&self, pad: usize, step: usize) -> u8 {
+        let group = self.state.current_group;
+        self.sequencer.get_step_velocity(group, self.state.group_patterns[group], pad, step)
+    }
+
+    pub fn get_step_probability(&self, pad: usize, step: usize) -> u8 {
+        let group = self.state.current_group;
+        self.sequencer.get_step_probability(group, self.state.group_patterns[group], pad, step)
+    }
+
+    pub fn get_step_micro_offset(&self, pad: usize, step: usize) -> i16 {
+        self.get_current_pattern_data().map_or(0, |pattern| pattern.get_micro_offset(pad, step))
+    }
+
+    pub fn is_step_muted(&self, pad: usize, step: usize) -> bool {
+        let group = self.state.current_group;
+        self.sequencer.is_step_muted(group, self.state.group_patterns[group], pad, step)
+    }
+
+    
+    pub fn get_current_pattern(&self) -> usize {
+        self.state.get_current_pattern()
+    }
+
+    #[cfg(test)]
+    pub fn get_current_step(&self) -> usize {
+        self.sequencer.get_current_step()
+    }
+
+    // (bar, beat, step) of the playhead counted from where playback started
+    pub fn get_transport_position(&self) -> (u32, u32, u32) {
+        transport_position(self.state.bars_elapsed, self.sequencer.get_current_step(), self.state.steps_per_beat)
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.state.elapsed()
+    }
+
+    // Playhead within the current pattern, wrapping at its own length
+    pub fn get_current_pattern_step(&self) -> usize {
+        self.get_group_pattern_step(self.state.current_group)
+    }
+
+    // Each group's playhead follows its own clock division and pattern length
+    pub fn get_group_pattern_step(&self, group: usize) -> usize {
+        let step = self.sequencer.get_group_step(group);
+        self.get_group_pattern_data(group).map_or(step, |pattern| pattern.wrap_step(step))
+    }
+    
+    // Getter methods for UI
+    pub fn get_current_group(&self) -> usize {
+        self.state.current_group
+    }
+    
+    pub fn get_selected_pad(&self) -> Option<usize> {
+        self.ui_state.selected_pad
+    }
+    
+    pub fn is_pad_flashing(&self, group: usize, pad: usize) -> bool {
+        self.ui_state.is_pad_flashing(group, pad)
+    }
+    
+    pub fn is_playing(&self) -> bool {
+        self.state.is_playing()
+    }
+
+    pub fn get_transport(&self) -> Transport {
+        self.state.transport
+    }
+    
+    pub fn is_recording(&self) -> bool {
+        self.state.is_recording
+    }
+    
+    pub fn get_tempo(&self) -> f32 {
+        self.state.tempo
+    }
+    
+    // The tempo the last full cycle actually played at, once one has
+    pub fn measured_bpm(&self) -> Option<f32> {
+        self.tempo_meter.measured
+    }
+    
+    pub fn get_audition(&self) -> Option<(usize, usize)> {
+        self.sequencer.get_audition().map(|a| (a.group, a.pattern_idx))
+    }
+
+    // Mixer control methods
+    pub fn adjust_master_volume(&mut self, delta: f32) {
+        self.mixer.adjust_master_volume(delta);
+    }
+
+    pub fn toggle_master_mute(&mut self) {
+        self.mixer.toggle_master_mute();
+    }
+
+    pub fn open_master_gain(&mut self) {
+        self.ui_state.view = ViewMode::Normal;
+        self.ui_state.panel = Panel::Sequencer;
+        self.ui_state.prompt = Some(Prompt::new(PromptKind::MasterGain));
+    }
+
+    fn submit_master_gain(&mut self, input: &str) -> std::result::Result<String, String> {
+        let db: f32 = input.parse().map_err(|_| format!("Type a gain from {} to +{} dB", MIN_MASTER_GAIN_DB, MAX_MASTER_GAIN_DB))?;
+        self.mixer.set_master_gain_db(db);
+        Ok(format!("Master gain {}", master_gain_label(self.mixer.get_master_gain_db())))
+    }
+
+    pub fn get_master_gain_db(&self) -> f32 {
+        self.mixer.get_master_gain_db()
+    }
+
+    // The master output is going over 0 dBFS, only possible with the limiter off
+    pub fn is_clipping(&self) -> bool {
+        self.mixer.is_clipping()
+    }
+
+    pub fn adjust_master_width(&mut self, delta: f32) {
+        self.mixer.adjust_master_width(delta);
+    }
+
+    pub fn adjust_group_volume(&mut self, group: usize, delta: f32) {
+        self.mixer.adjust_group_volume(group, delta);
+        self.record_automation(group, Automation::Volume(self.mixer.get_group_volume(group)));
+    }
+
+    pub fn toggle_group_mute(&mut self, group: usize) {
+        self.mixer.toggle_group_mute(group);
+        self.record_automation(group, Automation::Mute(self.mixer.is_group_muted(group)));
+    }
+
+    pub fn get_master_volume(&self) -> f32 {
+        self.mixer.get_master_volume()
+    }
+
+    pub fn get_group_volume(&self, group: usize) -> f32 {
+        self.mixer.get_group_volume(group)
+    }
+
+    pub fn is_master_muted(&self) -> bool {
+        self.mixer.is_master_muted()
+    }
+
+    // Stopped with nothing flashing or decaying, nothing needs a fast wakeup
+    pub fn desired_poll_interval(&self) -> Duration {
+        // A followed clock's Start can arrive at any moment
+        if crate::ui::is_animating(self) || self.is_clock_slaved() {
+            self.tick_interval
+        } else {
+            IDLE_POLL_INTERVAL.max(self.tick_interval)
+        }
+    }
+
+    pub fn is_limiting(&self) -> bool {
+        self.mixer.is_limiting()
+    }
+
+    pub fn get_group_level(&self, group: usize) -> f32 {
+        self.mixer.get_group_level(group)
+    }
+
+    pub fn get_master_level(&self) -> f32 {
+        self.mixer.get_master_level()
+    }
+
+    pub fn is_group_muted(&self, group: usize) -> bool {
+        self.mixer.is_group_muted(group)
+    }
+
+    pub fn toggle_selected_pad_mute(&mut self) {
+        let Some(pad) = self.ui_state.selected_pad else {
+            self.ui_state.notify("Select a pad to mute it");
+            return;
+        };
+        let group = self.state.current_group;
+        if self.mixer.toggle_pad_mute(group, pad) {
+            self.ui_state.notify(format!("Pad {} muted", pad + 1));
+        } else {
+            self.ui_state.notify(format!("Pad {} unmuted", pad + 1));
+        }
+    }
+
+    pub fn is_pad_muted(&self, group: usize, pad: usize) -> bool {
+        self.mixer.is_pad_muted(group, pad)
+    }
+
+    pub fn toggle_group_solo(&mut self, group: usize) {
+        self.mixer.toggle_group_solo(group);
+    }
+
+    pub fn is_group_soloed(&self, group: usize) -> bool {
+        self.mixer.is_group_soloed(group)
+    }
+
+    pub fn toggle_group_gate(&mut self, group: usize) {
+        let name = self.sample_bank.get_group_name(group);
+        if self.mixer.toggle_group_gate(group) {
+            self.ui_state.notify(format!("{} gate on: 8th notes", name));
+        } else {
+            self.ui_state.notify(format!("{} gate off", name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pad_settings::PadSettings;
+
+    #[test]
+    fn test_app_creation() {
+        let app = App::new().unwrap();
+        assert_eq!(app.state.current_group, 0);
+        assert_eq!(app.state.group_patterns, [0; 4]);
+        assert!(!app.state.is_playing());
+        assert!(!app.state.is_recording);
+        assert_eq!(app.state.tempo, 120.0);
+        assert_eq!(app.ui_state.selected_pad, None);
+        assert!(app.ui_state.flashing_pads.is_empty());
+    }
+
+    #[test]
+    fn test_group_navigation() {
+        let mut app = App::new().unwrap();
+        
+        // Test next group
+        app.next_group();
+        assert_eq!(app.state.current_group, 1);
+        
+        app.next_group();
+        assert_eq!(app.state.current_group, 2);
+        
+        app.next_group();
+        assert_eq!(app.state.current_group, 3);
+        
+        // Test wrap around
+        app.next_group();
+        assert_eq!(app.state.current_group, 0);
+        
+        // Test previous group
+        app.prev_group();
+        assert_eq!(app.state.current_group, 3);
+        
+        app.prev_group();
+        assert_eq!(app.state.current_group, 2);
+    }
+
+    #[test]
+    fn test_pattern_navigation() {
+        let mut app = App::new().unwrap();
+        
+        // Test next pattern
+        app.next_pattern();
+        assert_eq!(app.state.group_patterns[0], 1);
+        
+        // Test wrap around (max is 99)
+        app.state.group_patterns[0] = 98;
+        app.next_pattern();
+        assert_eq!(app.state.group_patterns[0], 0);
+        
+        // Test previous pattern
+        app.prev_pattern();
+        assert_eq!(app.state.group_patterns[0], 98);
+        
+        app.state.group_patterns[0] = 1;
+        app.prev_pattern();
+        assert_eq!(app.state.group_patterns[0], 0);
+    }
+
+    #[test]
+    fn test_tempo_adjustment() {
+        let mut app = App::new().unwrap();
+        
+        // Test increase tempo
+        app.adjust_tempo(10.0);
+        assert_eq!(app.state.tempo, 130.0);
+        
+        // Test decrease tempo
+        app.adjust_tempo(-20.0);
+        assert_eq!(app.state.tempo, 110.0);
+        
+        // Test tempo bounds
+        app.adjust_tempo(-100.0);
+        assert_eq!(app.state.tempo, 60.0); // Min tempo
+        
+        app.adjust_tempo(300.0);
+        assert_eq!(app.state.tempo, 300.0); // Max tempo
+        
+        app.adjust_tempo(10.0);
+        assert_eq!(app.state.tempo, 300.0); // Should not exceed max
+    }
+
+    #[test]
+    fn test_measured_bpm_from_wraps() {
+        let mut app = App::new().unwrap();
+        assert_eq!(app.measured_bpm(), None);
+        
+        // 16 steps in 2 s is 120 BPM; the first wrap only starts the clock
+        let start = Instant::now();
+        app.tempo_meter.record_wrap(start, 0, 120.0, 4);
+        assert_eq!(app.measured_bpm(), None);
+        app.tempo_meter.record_wrap(start + Duration::from_secs(2), 16, 120.0, 4);
+        assert!((app.measured_bpm().unwrap() - 120.0).abs() < 0.01);
+        
+        // Late ticks stretch the cycle: 2.5 s for 16 steps is 96 BPM
+        app.tempo_meter.record_wrap(start + Duration::from_millis(4500), 32, 120.0, 4);
+        assert!((app.measured_bpm().unwrap() - 96.0).abs() < 0.01);
+        
+        // A cycle with a tempo change in it isn't measured
+        app.tempo_meter.record_wrap(start + Duration::from_millis(6500), 48, 130.0, 4);
+        assert_eq!(app.measured_bpm(), None);
+        
+        // Stopping forgets the measurement
+        app.tempo_meter.record_wrap(start + Duration::from_millis(8500), 64, 130.0, 4);
+        assert!(app.measured_bpm().is_some());
+        app.stop();
+        assert_eq!(app.measured_bpm(), None);
+        
+        // Six 8th-note steps are three beats: 120 BPM in 1.5 s
+        app.tempo_meter.record_wrap(start, 0, 120.0, 2);
+        app.tempo_meter.record_wrap(start + Duration::from_millis(1500), 6, 120.0, 2);
+        assert!((app.measured_bpm().unwrap() - 120.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_playback_toggle() {
+        let mut app = App::new().unwrap();
+        
+        assert!(!app.state.is_playing());
+        app.toggle_playback();
+        assert!(app.state.is_playing());
+        app.toggle_playback();
+        assert!(!app.state.is_playing());
+    }
+
+    // Forces the next app tick to land on a step
+    fn tick_step(app: &mut App) {
+        app.state.last_tick = Instant::now() - app.state.step_duration();
+        app.tick();
+    }
+
+    #[test]
+    fn test_double_time_automation_waits_for_its_half_step() {
+        let mut app = App::new().unwrap();
+        app.sequencer.cycle_division(1);
+        app.sequencer.record_automation(1, 0, 1, Automation::Mute(true));
+        app.toggle_playback();
+        
+        // Step 1 of a double-time group starts halfway through the first tick
+        tick_step(&mut app);
+        assert!(!app.mixer.is_group_muted(1));
+        let half_step = app.next_pending_hit().unwrap();
+        assert_eq!(half_step, app.state.last_tick + app.state.step_duration() / 2);
+        app.play_due_hits(half_step);
+        assert!(app.mixer.is_group_muted(1));
+    }
+
+    #[test]
+    fn test_nudged_hits_are_scheduled_off_the_grid() {
+        let mut app = App::new().unwrap();
+        app.sequencer.record_hit_at(0, 0, 2, 0);
+        app.sequencer.record_hit_at(0, 0, 3, 1);
+        app.sequencer.nudge_step(0, 0, 2, 0, 20);
+        app.sequencer.nudge_step(0, 0, 3, 1, -20);
+        app.toggle_playback();
+        
+        tick_step(&mut app);
+        let tick_time = app.state.last_tick;
+        let queued: Vec<(usize, Instant)> = app.pending_hits.iter().map(|hit| (hit.pad, hit.at)).collect();
+        
+        // The late hit waits 20ms; the early one is queued 20ms before step 2 starts
+        assert_eq!(queued, vec![
+            (2, tick_time + Duration::from_millis(20)),
+            (3, tick_time + app.state.step_duration() - Duration::from_millis(20)),
+        ]);
+        assert_eq!(app.next_pending_hit(), Some(tick_time + Duration::from_millis(20)));
+        
+        // Once due they play and leave the queue
+        app.play_due_hits(tick_time + Duration::from_millis(25));
+        assert_eq!(app.pending_hits.len(), 1);
+        
+        app.stop();
+        assert!(app.pending_hits.is_empty());
+    }
+
+    #[test]
+    fn test_muted_pads_are_left_out_of_the_step() {
+        let mut app = App::new().unwrap();
+        app.sequencer.record_hit_at(0, 0, 2, 0);
+        app.sequencer.record_hit_at(0, 0, 3, 0);
+        app.sequencer.record_hit_at(0, 0, 2, 1);
+        app.sequencer.record_hit_at(0, 0, 2, 2);
+        app.sequencer.nudge_step(0, 0, 2, 2, 20);
+        
+        app.toggle_selected_pad_mute();
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to mute it"));
+        app.ui_state.select_pad(2);
+        app.toggle_selected_pad_mute();
+        assert_eq!(app.ui_state.active_notification(), Some("Pad 3 muted"));
+        assert!(app.is_pad_muted(0, 2));
+        assert!(!app.is_pad_muted(1, 2));
+        app.toggle_playback();
+        
+        tick_step(&mut app);
+        assert!(!app.is_pad_flashing(0, 2));
+        assert!(app.is_pad_flashing(0, 3));
+        
+        // Unmuting brings the pad straight back
+        app.toggle_selected_pad_mute();
+        assert_eq!(app.ui_state.active_notification(), Some("Pad 3 unmuted"));
+        tick_step(&mut app);
+        assert!(app.is_pad_flashing(0, 2));
+        
+        // A nudged hit queued before the mute is dropped when it comes due
+        tick_step(&mut app);
+        assert_eq!(app.pending_hits.len(), 1);
+        app.toggle_selected_pad_mute();
+        let later = app.state.last_tick + Duration::from_millis(25);
+        app.play_due_hits(later);
+        assert!(app.pending_hits.is_empty());
+    }
+
+    #[test]
+    fn test_ratchet_splits_the_step() {
+        let mut app = App::new().unwrap();
+        app.sequencer.record_hit_at(0, 0, 5, 0);
+        app.ui_state.select_pad(5);
+        app.cycle_ratchet();
+        app.cycle_ratchet();
+        assert_eq!(app.ui_state.active_notification(), Some("Ratchet pad 6 step 1: ×3"));
+        assert_eq!(app.get_step_ratchet(5, 0), 3);
+        app.toggle_playback();
+        
+        // The first of three plays is on the grid; the other two wait a third of a step each
+        tick_step(&mut app);
+        let tick_time = app.state.last_tick;
+        let third = app.state.step_duration() / 3;
+        let queued: Vec<(usize, Instant)> = app.pending_hits.iter().map(|hit| (hit.pad, hit.at)).collect();
+        assert_eq!(queued, vec![(5, tick_time + third), (5, tick_time + third * 2)]);
+    }
+
+    #[test]
+    fn test_swing_holds_back_odd_steps() {
+        let mut app = App::new().unwrap();
+        app.sequencer.record_hit_at(0, 0, 2, 1);
+        for _ in 0..2 {
+            app.cycle_swing();
+        }
+        assert_eq!(app.ui_state.active_notification(), Some("Swing 58%"));
+        app.toggle_playback();
+        tick_step(&mut app); // Step 1 is empty
         
+        tick_step(&mut app);
+        let expected = app.state.last_tick + app.state.swing_delay();
+        assert_eq!(app.next_pending_hit(), Some(expected));
+    }
+
+    #[test]
+    fn test_double_time_group_plays_between_ticks() {
+        let mut app = App::new().unwrap();
         app.next_group();
-        assert_eq!(app.state.current_group, 3);
+        app.sequencer.record_hit_at(1, 0, 4, 1);
+        app.cycle_clock_division();
+        assert_eq!(app.ui_state.active_notification(), Some("Clock ×2"));
+        assert_eq!(app.get_clock_division(1), ClockDivision::Double);
+        app.toggle_playback();
+        
+        // The group's step 1 is the second half of the first tick
+        tick_step(&mut app);
+        let expected = app.state.last_tick + app.state.step_duration() / 2;
+        assert_eq!(app.next_pending_hit(), Some(expected));
+        assert_eq!(app.get_current_pattern_step(), 2);
+    }
+
+    #[test]
+    fn test_nudges_never_reach_the_next_step() {
+        let mut app = App::new().unwrap();
+        app.sequencer.record_hit_at(0, 0, 2, 0);
+        for _ in 0..200 {
+            app.sequencer.nudge_step(0, 0, 2, 0, NUDGE_STEP_MS);
+        }
+        app.toggle_playback();
+        
+        tick_step(&mut app);
+        let step_end = app.state.last_tick + app.state.step_duration() / 2;
+        assert!(app.pending_hits[0].at < step_end);
+        
+        // Nudging needs a selected pad with a hit under the cursor
+        app.nudge_step(-NUDGE_STEP_MS);
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to edit its steps"));
+        app.ui_state.select_pad(2);
+        app.nudge_step(-NUDGE_STEP_MS);
+        assert_eq!(app.ui_state.active_notification(), Some("Nudge pad 3 step 1: +248 ms"));
+    }
+
+    #[test]
+    fn test_pause_keeps_position_and_stop_rewinds() {
+        let mut app = App::new().unwrap();
+        app.toggle_playback();
+        for _ in 0..5 {
+            app.sequencer.tick(120.0);
+        }
+        
+        app.toggle_playback();
+        assert_eq!(app.get_transport(), Transport::Paused);
+        app.toggle_playback();
+        assert_eq!(app.get_current_step(), 5);
+        
+        app.toggle_recording();
+        app.stop();
+        assert_eq!(app.get_transport(), Transport::Stopped);
+        assert_eq!(app.get_current_step(), 0);
+        assert!(!app.is_recording());
+    }
+
+    #[test]
+    fn test_transport_position_counts_bars() {
+        let mut app = App::new().unwrap();
+        app.toggle_playback();
+        assert_eq!(app.get_transport_position(), (1, 1, 1));
+        
+        for _ in 0..STEPS_PER_PATTERN + 5 {
+            tick_step(&mut app);
+        }
+        assert_eq!(app.get_transport_position(), (2, 2, 2));
+        
+        // A pause holds the position and a stop rewinds it
+        app.toggle_playback();
+        app.toggle_playback();
+        assert_eq!(app.get_transport_position(), (2, 2, 2));
+        app.stop();
+        assert_eq!(app.get_transport_position(), (1, 1, 1));
+        
+        // Bars of 3/4 in 8ths are six steps of two to a beat
+        let mut app = App::new().unwrap();
+        app.set_signature(Signature::new(3, 2).unwrap(), false);
+        app.tick(); // Picks up the longer steps before tick_step backdates by one
+        app.toggle_playback();
+        for _ in 0..8 {
+            tick_step(&mut app);
+        }
+        assert_eq!(app.get_transport_position(), (2, 2, 1));
+    }
+
+    #[test]
+    fn test_recording_toggle() {
+        let mut app = App::new().unwrap();
+        
+        assert!(!app.state.is_recording);
+        app.toggle_recording();
+        assert!(app.state.is_recording);
+        app.toggle_recording();
+        assert!(!app.state.is_recording);
+    }
+
+    #[test]
+    fn test_pad_trigger() {
+        let mut app = App::new().unwrap();
+        
+        // Test valid pad
+        app.trigger_pad(5);
+        assert_eq!(app.ui_state.selected_pad, Some(5));
+        
+        // Test invalid pad (should be ignored)
+        app.trigger_pad(20);
+        assert_eq!(app.ui_state.selected_pad, Some(5)); // Should remain unchanged
+    }
+
+    #[test]
+    fn test_audition_does_not_touch_live_playback() {
+        let mut app = App::new().unwrap();
+        
+        app.start_audition();
+        assert_eq!(app.get_audition(), Some((0, 1)));
+        
+        // Live pattern selection is unchanged while previewing
+        assert_eq!(app.get_current_pattern(), 0);
+        
+        app.stop_audition();
+        assert_eq!(app.get_audition(), None);
+        assert_eq!(app.get_current_step(), 0);
+    }
+
+    #[test]
+    fn test_sampling_requires_selected_pad() {
+        let mut app = App::new().unwrap();
+        
+        app.toggle_sampling();
+        assert!(!app.is_sampling());
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to sample into first"));
+    }
+
+    #[test]
+    fn test_sampling_with_missing_device_notifies() {
+        let mut app = App::new().unwrap();
+        app.input_device = Some("no-such-input-device".to_string());
+        app.trigger_pad(3);
+        
+        app.toggle_sampling();
+        assert!(!app.is_sampling());
+        assert!(app.ui_state.active_notification().unwrap().contains("no-such-input-device"));
+    }
+
+    #[test]
+    fn test_import_folder_prompt() {
+        let dir = std::env::temp_dir().join("ko2_import_prompt");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("p04_clap.wav"), sample::generate_snare()).unwrap();
+        
+        let mut app = App::new().unwrap();
+        app.next_group();
+        app.open_import_folder();
+        app.submit_prompt();
+        assert_eq!(app.ui_state.active_notification(), Some("Type a folder of samples"));
+        
+        // Paths take any printable character, and Enter loads the folder into the group
+        for c in dir.to_str().unwrap().chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert_eq!(app.ui_state.prompt, None);
+        assert_eq!(app.ui_state.active_notification(), Some("BASS kit: ko2_import_prompt"));
+        assert_eq!(app.sample_bank.get_sample_name(1, 3), Some("p04_clap"));
+        assert!((0..PADS_PER_GROUP).filter(|&pad| pad != 3).all(|pad| !app.sample_bank.has_sample(1, pad)));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_hit_sets_last_error() {
+        let mut app = App::new().unwrap();
+        app.trigger_group_pad(0, 0);
+        assert_eq!(app.last_error, None);
+        
+        // The start decodes, so the file loads; the rest of the data chunk is missing
+        let mut wav = sample::generate_kick();
+        wav.truncate(12_000);
+        app.sample_bank.load_sample_data(0, 3, "cut", wav).unwrap();
+        app.trigger_group_pad(0, 3);
+        let error = app.last_error.clone().unwrap();
+        assert!(error.starts_with("DRUMS pad 4: ") && error.contains("Failed to decode sample"), "{}", error);
+        assert_eq!(app.ui_state.active_notification(), Some(error.as_str()));
+    }
+
+    #[test]
+    fn test_note_repeat_plays_the_held_pad_every_step() {
+        let mut app = App::new().unwrap();
+        app.toggle_playback();
+        
+        // Off, a press plays once
+        app.trigger_pad(5);
+        tick_step(&mut app);
+        assert!(!app.is_pad_flashing(0, 5));
+        
+        app.toggle_note_repeat();
+        assert!(app.is_note_repeat());
+        assert_eq!(app.ui_state.active_notification(), Some("Note repeat on: hold a pad to play it every step"));
+        app.trigger_pad(5);
+        for _ in 0..4 {
+            tick_step(&mut app);
+            assert!(app.is_pad_flashing(0, 5));
+        }
+        
+        // Once key repeat stops refreshing the press, the pad is let go
+        let (group, pad, _) = app.held_pad.unwrap();
+        app.held_pad = Some((group, pad, app.state.now() - NOTE_REPEAT_HOLD));
+        tick_step(&mut app);
+        assert!(!app.is_pad_flashing(0, 5));
+        assert_eq!(app.held_pad, None);
+        
+        // Turning it off lets go straight away
+        app.trigger_pad(5);
+        app.toggle_note_repeat();
+        tick_step(&mut app);
+        assert!(!app.is_pad_flashing(0, 5));
+    }
+
+    #[test]
+    fn test_transpose_mode_pitches_the_source_pad() {
+        let mut app = App::detached();
+        let second = crate::render::encode_wav(&[0.5; 44100], 1, crate::bus::BUS_SAMPLE_RATE).unwrap();
+        app.sample_bank.load_sample_data(0, 0, "tone", second).unwrap();
+        app.toggle_transpose_mode();
+        assert_eq!(app.transpose_source(), None);
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad with a sample to transpose"));
+        
+        app.ui_state.select_pad(0);
+        app.toggle_transpose_mode();
+        assert_eq!(app.transpose_source(), Some((0, 0)));
+        
+        // Pad 12 plays the source an octave up, twice as fast, so it's over in half the time
+        app.trigger_pad(12);
+        assert_eq!(app.get_selected_pad(), Some(0));
+        app.mixer.render_frames(22_000);
+        assert_eq!(app.mixer.voice_count(), 1);
+        app.mixer.render_frames(100);
+        assert_eq!(app.mixer.voice_count(), 0);
+        
+        app.trigger_pad(0);
+        app.mixer.render_frames(22_100);
+        assert_eq!(app.mixer.voice_count(), 1);
+        app.mixer.stop_all();
+        
+        // Off again, pads play their own (here empty) samples
+        app.toggle_transpose_mode();
+        assert_eq!(app.transpose_source(), None);
+        app.trigger_pad(12);
+        assert_eq!(app.mixer.voice_count(), 0);
+        assert_eq!(app.get_selected_pad(), Some(12));
+    }
+
+    #[test]
+    fn test_morph_prompt_ends_on_the_target_pattern() {
+        let mut app = App::new().unwrap();
+        app.morph_bars = 2;
+        app.sequencer.record_hit_at(0, 1, 3, 0);
+        app.open_morph();
+        app.prompt_input('0');
+        app.prompt_input('1');
+        assert_eq!(app.ui_state.active_notification(), Some("Already on pattern 01"));
+        app.prompt_input('0');
+        app.prompt_input('2');
+        assert!(app.ui_state.prompt.is_none());
+        assert_eq!(app.ui_state.active_notification(), Some("Morphing into pattern 02 over 2 bars"));
+        assert_eq!(app.get_morph(0), Some((1, 0.0)));
+        
+        app.toggle_playback();
+        for _ in 0..STEPS_PER_PATTERN {
+            tick_step(&mut app);
+        }
+        assert_eq!(app.get_morph(0), Some((1, 0.5)));
+        assert_eq!(app.get_current_pattern(), 0);
+        for _ in 0..STEPS_PER_PATTERN {
+            tick_step(&mut app);
+        }
+        assert_eq!(app.get_morph(0), None);
+        assert_eq!(app.get_current_pattern(), 1);
+        assert_eq!(app.ui_state.active_notification(), Some("Morphed into pattern 02"));
+    }
+
+    #[test]
+    fn test_goto_pattern_prompt() {
+        let mut app = App::new().unwrap();
+        app.next_group();
+        app.toggle_panel();
+        app.open_goto_pattern();
+        assert_eq!(app.ui_state.panel, Panel::Sequencer);
+        
+        // Out-of-range numbers keep the prompt open with an error
+        app.prompt_input('0');
+        app.submit_prompt();
+        assert!(app.ui_state.prompt.is_some());
+        assert_eq!(app.ui_state.active_notification(), Some("No pattern 00; choose 01–99"));
+        
+        // One digit waits for another; the second jumps without Enter
+        app.prompt_backspace();
+        app.prompt_input('4');
+        assert_eq!(app.ui_state.prompt.as_ref().unwrap().input, "4");
+        app.prompt_input('8');
+        assert_eq!(app.ui_state.prompt, None);
+        assert_eq!(app.get_current_pattern(), 47);
+        assert_eq!(app.sequencer.get_active_pattern(1), 47);
+        assert_eq!(app.sequencer.get_active_pattern(0), 0);
+        
+        // A complete but invalid entry is cleared for another go
+        app.open_goto_pattern();
+        app.prompt_input('0');
+        app.prompt_input('0');
+        assert_eq!(app.ui_state.prompt.as_ref().unwrap().input, "");
+        assert_eq!(app.get_current_pattern(), 47);
+        
+        // Enter takes a single digit
+        app.prompt_input('7');
+        app.submit_prompt();
+        assert_eq!(app.get_current_pattern(), 6);
+        
+        assert!(app.go_to_pattern(98).is_ok());
+        assert_eq!(app.go_to_pattern(99), Err("No pattern 100; choose 01–99".to_string()));
+        assert_eq!(app.get_current_pattern(), 98);
+        
+        // Cancelling changes nothing
+        app.open_goto_pattern();
+        app.prompt_input('2');
+        app.cancel_prompt();
+        assert_eq!(app.ui_state.prompt, None);
+        assert_eq!(app.get_current_pattern(), 98);
+    }
+
+    #[test]
+    fn test_pattern_map() {
+        let mut app = App::new().unwrap();
+        app.sequencer.toggle_step(0, 12, 0, 0);
+        app.sequencer.toggle_step(1, 5, 0, 0);
+        app.sequencer.toggle_step(0, 20, 0, 0);
+        app.sequencer.toggle_step(0, 20, 0, 0);
+        assert_eq!(app.used_patterns(), vec![12]);
+
+        app.toggle_pattern_map();
+        assert_eq!(app.ui_state.pattern_map, Some(0));
+        app.move_pattern_map_cursor(-1);
+        assert_eq!(app.ui_state.pattern_map, Some(0));
+        app.move_pattern_map_cursor(10);
+        app.move_pattern_map_cursor(2);
+        app.select_pattern_map_slot();
+        assert_eq!(app.ui_state.pattern_map, None);
+        assert_eq!(app.get_current_pattern(), 12);
+        assert_eq!(app.sequencer.get_active_pattern(0), 12);
+
+        // The cursor stops at the last slot
+        app.toggle_pattern_map();
+        app.move_pattern_map_cursor(500);
+        assert_eq!(app.ui_state.pattern_map, Some(98));
+        app.toggle_pattern_map();
+        assert_eq!(app.ui_state.pattern_map, None);
+        assert_eq!(app.get_current_pattern(), 12);
+    }
+
+    #[test]
+    fn test_paste_between_groups() {
+        let mut app = App::new().unwrap();
+        app.paste_pattern(false);
+        assert_eq!(app.ui_state.active_notification(), Some("Copy a pattern first"));
+        
+        app.toggle_step(0, 0);
+        app.toggle_step(3, 4);
+        app.toggle_step(9, 8);
+        app.copy_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Copied DRUMS 01"));
+        
+        // Only two vocal pads have samples, so compacting can't fit the third row
+        app.select_group(3);
+        for pad in 0..PADS_PER_GROUP {
+            app.sample_bank.remove_sample(3, pad);
+        }
+        let kick = app.sample_bank.generate_simple_kick();
+        app.sample_bank.load_sample_data(3, 5, "kick", kick.clone()).unwrap();
+        app.sample_bank.load_sample_data(3, 11, "kick", kick).unwrap();
+        app.paste_pattern(true);
+        assert_eq!(
+            app.ui_state.active_notification(),
+            Some("Pasted DRUMS 01 into pattern 01; dropped 1 rows with no loaded pad")
+        );
+        let grid = app.sequencer.get_pattern_grid(3, 0);
+        assert!(grid[5][0] && grid[11][4]);
+        assert!(!grid[0][0] && !grid[3][4] && !grid[9][8]);
+        
+        app.paste_pattern(false);
+        assert_eq!(app.ui_state.active_notification(), Some("Pasted DRUMS 01 into pattern 01"));
+        assert_eq!(app.sequencer.get_pattern_grid(3, 0), app.sequencer.get_pattern_grid(0, 0));
+    }
+
+    #[test]
+    fn test_duplicate_to_new_slot() {
+        let mut app = App::new().unwrap();
+        app.duplicate_to_new_slot();
+        assert_eq!(app.ui_state.active_notification(), Some("Pattern 01 is empty"));
+        
+        app.sequencer.toggle_step(0, 1, 0, 0);
+        app.toggle_step(4, 6);
+        app.toggle_accent(6);
+        app.duplicate_to_new_slot();
+        assert_eq!(app.ui_state.active_notification(), Some("Duplicated pattern 01 to 03"));
+        assert_eq!(app.get_current_pattern(), 2);
+        assert_eq!(app.sequencer.get_active_pattern(0), 2);
+        assert!(app.sequencer.get_pattern_grid(0, 2)[4][6]);
+        assert!(app.is_step_accented(6));
+        
+        // The copy is independent of the original
+        app.toggle_step(4, 6);
+        assert!(app.sequencer.get_pattern_grid(0, 0)[4][6]);
+        
+        for pattern in 0..MAX_PATTERNS {
+            app.sequencer.record_hit_at(0, pattern, 0, 0);
+        }
+        app.duplicate_to_new_slot();
+        assert_eq!(app.ui_state.active_notification(), Some("No empty pattern slots left in this group"));
+        assert_eq!(app.get_current_pattern(), 2);
+    }
+
+    #[test]
+    fn test_resample_requires_selected_pad() {
+        let mut app = App::new().unwrap();
+        
+        app.resample_pattern_to_pad();
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to resample into first"));
+    }
+
+    #[test]
+    fn test_step_edits_need_a_pad_and_a_hit() {
+        let mut app = App::new().unwrap();
         
-        // Test wrap around
-        app.next_group();
-        assert_eq!(app.state.current_group, 0);
+        app.adjust_step_velocity(-1);
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to edit its steps"));
         
-        // Test previous group
-        app.prev_group();
-        assert_eq!(app.state.current_group, 3);
+        app.trigger_pad(2);
+        app.move_step_cursor(3);
+        app.adjust_step_velocity(-1);
+        assert_eq!(app.ui_state.active_notification(), Some("No hit on pad 3 step 4"));
         
-        app.prev_group();
-        assert_eq!(app.state.current_group, 2);
+        for _ in 0..3 {
+            app.sequencer.tick(120.0);
+        }
+        app.sequencer.record_hit(0, 0, 2);
+        app.adjust_step_velocity(-1);
+        app.adjust_step_probability(-2);
+        assert_eq!(app.get_step_velocity(2, 3), 111);
+        assert_eq!(app.get_step_probability(2, 3), 80);
+        assert_eq!(app.get_step_velocity(2, 2), 127);
     }
 
     #[test]
-    fn test_pattern_navigation() {
+    fn test_pattern_export_import_files() {
+        let path = std::env::temp_dir().join("ko2_pattern_export_test.json");
+        let path = path.to_str().unwrap();
         let mut app = App::new().unwrap();
+        app.sequencer.record_hit(0, 0, 4);
         
-        // Test next pattern
-        app.next_pattern();
-        assert_eq!(app.state.group_patterns[0], 1);
-        
-        // Test wrap around (max is 99)
-        app.state.group_patterns[0] = 98;
-        app.next_pattern();
-        assert_eq!(app.state.group_patterns[0], 0);
+        app.export_pattern(path);
+        app.clear_pattern();
+        app.import_pattern(path);
+        assert!(app.sequencer.get_pattern_grid(0, 0)[4][0]);
         
-        // Test previous pattern
-        app.prev_pattern();
-        assert_eq!(app.state.group_patterns[0], 98);
+        std::fs::write(path, "{ not json").unwrap();
+        app.import_pattern(path);
+        assert!(app.ui_state.active_notification().unwrap().starts_with("Pattern error"));
+        // A failed import leaves the pattern alone
+        assert!(app.sequencer.get_pattern_grid(0, 0)[4][0]);
         
-        app.state.group_patterns[0] = 1;
-        app.prev_pattern();
-        assert_eq!(app.state.group_patterns[0], 0);
+        let _ = std::fs::remove_file(path);
     }
 
     #[test]
-    fn test_tempo_adjustment() {
+    fn test_midi_clock_drives_the_sequencer() {
+        use crate::midi::{MIDI_CLOCK, MIDI_START};
+        
         let mut app = App::new().unwrap();
+        let start = Instant::now();
+        let interval = crate::midi::clock_interval(100.0);
+        let mut bytes = vec![(MIDI_START, start)];
+        bytes.extend((0..7).map(|pulse| (MIDI_CLOCK, start + interval * pulse)));
+        app.clock_in = Some(MidiClockIn::followed(&bytes));
         
-        // Test increase tempo
-        app.adjust_tempo(10);
-        assert_eq!(app.state.tempo, 130);
+        // Seven pulses after Start are two steps, however long the internal clock waits
+        app.tick();
+        assert!(app.is_playing());
+        assert_eq!(app.sequencer.get_current_step(), 2);
+        assert_eq!(app.state.last_tick, start + interval * 6);
+        app.state.last_tick = Instant::now() - Duration::from_secs(1);
+        app.tick();
+        assert_eq!(app.sequencer.get_current_step(), 2);
         
-        // Test decrease tempo
-        app.adjust_tempo(-20);
-        assert_eq!(app.state.tempo, 110);
+        // Tempo is the clock's to set
+        let tempo = app.get_tempo();
+        app.adjust_tempo(5.0);
+        assert_eq!(app.get_tempo(), tempo);
+        assert_eq!(app.ui_state.active_notification(), Some("Tempo follows the MIDI clock"));
+    }
+
+    #[test]
+    fn test_silenced_groups_send_no_midi_notes() {
+        struct ChannelOut(std::sync::mpsc::Sender<Vec<u8>>);
+        impl crate::midi::MidiOut for ChannelOut {
+            fn send(&mut self, message: &[u8]) -> std::io::Result<()> {
+                let _ = self.0.send(message.to_vec());
+                Ok(())
+            }
+        }
         
-        // Test tempo bounds
-        app.adjust_tempo(-100);
-        assert_eq!(app.state.tempo, 60); // Min tempo
+        let mut app = App::new().unwrap();
+        let (sender, sent) = std::sync::mpsc::channel();
+        app.midi_clock = Some(MidiClock::spawn(Box::new(ChannelOut(sender)), 120.0).notes_only());
+        app.midi_notes.insert((0, 0), PadNote { channel: 9, note: 36, midi_only: true });
+        app.midi_notes.insert((1, 0), PadNote { channel: 9, note: 38, midi_only: true });
+        // Notes go out in the order they're played, so the next Note On through is the next one sent
+        let first_note = || loop {
+            let message = sent.recv_timeout(Duration::from_secs(2)).unwrap();
+            if message[0] & 0xF0 == crate::midi::MIDI_NOTE_ON {
+                break message[1];
+            }
+        };
         
-        app.adjust_tempo(300);
-        assert_eq!(app.state.tempo, 300); // Max tempo
+        app.toggle_group_mute(0);
+        app.play_pad(0, 0, 1.0, MAX_VELOCITY);
+        app.play_pad(1, 0, 1.0, MAX_VELOCITY);
+        assert_eq!(first_note(), 38);
+        app.toggle_group_mute(0);
         
-        app.adjust_tempo(10);
-        assert_eq!(app.state.tempo, 300); // Should not exceed max
+        app.toggle_group_solo(1);
+        app.play_pad(0, 0, 1.0, MAX_VELOCITY);
+        app.toggle_group_solo(1);
+        app.toggle_master_mute();
+        app.play_pad(1, 0, 1.0, MAX_VELOCITY);
+        app.toggle_master_mute();
+        app.play_pad(0, 0, 1.0, MAX_VELOCITY);
+        assert_eq!(first_note(), 36);
     }
 
     #[test]
-    fn test_playback_toggle() {
+    fn test_mixer_moves_record_and_replay() {
         let mut app = App::new().unwrap();
         
-        assert!(!app.state.is_playing);
+        // Not recording, a mute is just a mute
+        app.toggle_playback();
+        tick_step(&mut app);
+        app.toggle_group_mute(1);
+        app.toggle_group_mute(1);
+        assert!(!app.is_group_automated(1));
+        app.stop();
+        
+        // Drop the bass on step 1 and bring it back on step 5, then pull it down
         app.toggle_playback();
-        assert!(app.state.is_playing);
+        app.toggle_recording();
+        tick_step(&mut app);
+        app.toggle_group_mute(1);
+        app.bar_start = app.bar_start.map(|start| start - app.state.step_duration() * 4);
+        app.toggle_group_mute(1);
+        app.adjust_group_volume(1, -0.3);
+        assert!(app.is_group_automated(1));
+        assert!(!app.is_group_automated(0));
+        app.stop();
+        app.adjust_group_volume(1, 0.3);
+        
         app.toggle_playback();
-        assert!(!app.state.is_playing);
+        tick_step(&mut app);
+        assert!(app.is_group_muted(1));
+        
+        // A mute lifted by hand holds until the next recorded move
+        app.toggle_group_mute(1);
+        for _ in 1..4 {
+            tick_step(&mut app);
+            assert!(!app.is_group_muted(1));
+        }
+        assert!((app.get_group_volume(1) - 0.8).abs() < 1e-6);
+        tick_step(&mut app);
+        assert!((app.get_group_volume(1) - 0.5).abs() < 1e-6);
+        for _ in 5..STEPS_PER_PATTERN {
+            tick_step(&mut app);
+        }
+        tick_step(&mut app);
+        assert!(app.is_group_muted(1));
+        
+        // Saved with the project, though the pattern has no hits
+        let project = Project::parse(&serde_json::to_string(&app.project()).unwrap()).unwrap();
+        let bass = project.patterns.iter().find(|entry| entry.group == 1).unwrap();
+        assert_eq!(bass.pattern.automated_levels(4), (Some(false), Some(0.5)));
+        
+        app.select_group(1);
+        app.clear_automation();
+        assert_eq!(app.ui_state.active_notification(), Some("BASS 01 automation cleared"));
+        assert!(!app.is_group_automated(1));
     }
 
     #[test]
-    fn test_recording_toggle() {
+    fn test_reset_pattern_restores_the_default_length() {
         let mut app = App::new().unwrap();
+        app.set_signature(Signature::new(3, 4).unwrap(), false);
+        app.toggle_step(0, 10);
+        app.clear_pattern();
+        assert_eq!(app.get_current_pattern_length(), 12);
         
-        assert!(!app.state.is_recording);
-        app.toggle_recording();
-        assert!(app.state.is_recording);
-        app.toggle_recording();
-        assert!(!app.state.is_recording);
+        app.toggle_step(0, 10);
+        app.reset_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Pattern 01 reset to 16 steps"));
+        assert_eq!(app.get_current_pattern_length(), 16);
+        assert!(app.get_current_pattern_data().unwrap().is_empty());
+        
+        app.undo();
+        assert_eq!(app.get_current_pattern_length(), 12);
+        assert!(app.get_current_pattern_data().unwrap().get_hit(0, 10));
     }
 
     #[test]
-    fn test_pad_trigger() {
+    fn test_master_gain_prompt() {
         let mut app = App::new().unwrap();
+        app.open_master_gain();
+        for c in "+6.5x-".chars() {
+            app.prompt_input(c);
+        }
+        assert_eq!(app.ui_state.prompt.as_ref().unwrap().input, "+6.5");
+        app.submit_prompt();
+        assert_eq!(app.ui_state.active_notification(), Some("Master gain +6.5 dB"));
+        assert_eq!(app.get_master_gain_db(), 6.5);
         
-        // Test valid pad
-        app.trigger_pad(5);
-        assert_eq!(app.ui_state.selected_pad, Some(5));
+        app.open_master_gain();
+        for c in "-90".chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert_eq!(app.ui_state.active_notification(), Some("Master gain -∞ dB"));
         
-        // Test invalid pad (should be ignored)
-        app.trigger_pad(20);
-        assert_eq!(app.ui_state.selected_pad, Some(5)); // Should remain unchanged
+        app.open_master_gain();
+        app.prompt_input('-');
+        app.submit_prompt();
+        assert_eq!(app.ui_state.active_notification(), Some("Type a gain from -60 to +12 dB"));
+    }
+
+    #[test]
+    fn test_signature_prompt() {
+        let mut app = App::new().unwrap();
+        app.open_signature();
+        for c in "3,5".chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert_eq!(app.ui_state.active_notification(), Some("Use 1–16 beats of 2, 3, 4, 6 or 8 steps"));
+        
+        // An empty pattern takes the signature straight away
+        app.open_signature();
+        for c in "3,4".chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert_eq!(app.ui_state.prompt, None);
+        assert_eq!(app.ui_state.active_notification(), Some("Pattern 01: 3/4 · 16ths, 12 steps"));
+        
+        // Changing the resolution under hits asks whether to move them
+        app.toggle_step(0, 4);
+        app.ui_state.cursor_step = 11;
+        app.open_signature();
+        for c in "3,2".chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert!(matches!(app.ui_state.prompt.as_ref().unwrap().kind, PromptKind::RescaleSignature(_)));
+        app.prompt_input('y');
+        assert_eq!(app.ui_state.prompt, None);
+        assert_eq!(app.ui_state.active_notification(), Some("Pattern 01: 3/4 · 8ths, 6 steps"));
+        let pattern = app.get_current_pattern_data().unwrap();
+        assert!(pattern.get_hit(0, 2));
+        assert!(!pattern.get_hit(0, 4));
+        assert_eq!(app.ui_state.cursor_step, 5);
+        
+        app.undo();
+        assert_eq!(app.get_current_pattern_data().unwrap().length(), 12);
+    }
+
+    #[test]
+    fn test_patterns_bring_their_tempo() {
+        let mut app = App::new().unwrap();
+        app.set_tempo(128.0);
+        app.capture_pattern_tempo();
+        assert_eq!(app.ui_state.active_notification(), Some("DRUMS 01 plays at 128.0 BPM"));
+        app.tick();
+        assert!(app.is_tempo_from_pattern());
+        
+        // A pattern without one leaves the tempo where it is
+        app.next_pattern();
+        app.set_tempo(100.0);
+        app.tick();
+        assert_eq!(app.get_tempo(), 100.0);
+        assert!(!app.is_tempo_from_pattern());
+        app.prev_pattern();
+        app.tick();
+        assert_eq!(app.get_tempo(), 128.0);
+        
+        // Adjusting by hand still works while the pattern plays
+        app.adjust_tempo(5.0);
+        app.tick();
+        assert_eq!(app.get_tempo(), 133.0);
+        assert!(!app.is_tempo_from_pattern());
+        
+        // BASS asking for another tempo doesn't override DRUMS, until DRUMS lets go
+        app.select_group(1);
+        app.set_tempo(90.0);
+        app.capture_pattern_tempo();
+        app.set_tempo(100.0);
+        app.tick();
+        assert_eq!(app.get_tempo(), 100.0);
+        app.select_group(0);
+        app.clear_pattern_tempo();
+        assert_eq!(app.ui_state.active_notification(), Some("DRUMS 01 follows the global tempo"));
+        app.tick();
+        assert_eq!(app.get_tempo(), 90.0);
+        
+        // Saved with the project even though the pattern has no hits
+        let project = app.project();
+        let bass = project.patterns.iter().find(|entry| entry.group == 1).unwrap();
+        assert_eq!(bass.pattern.get_tempo(), Some(90.0));
+    }
+
+    #[test]
+    fn test_autoload_applies_a_saved_project() {
+        let path = std::env::temp_dir().join("ko2_autoload_test.koii");
+        let path = path.to_str().unwrap();
+        let mut saved = App::new().unwrap();
+        saved.set_tempo(92.5);
+        saved.adjust_group_volume(2, -0.3);
+        saved.toggle_group_mute(1);
+        saved.select_group(2);
+        saved.go_to_pattern(6).unwrap();
+        saved.toggle_step(5, 3);
+        saved.save_project(path);
+        
+        let mut app = App::new().unwrap();
+        app.toggle_step(0, 0);
+        app.autoload_project(path);
+        assert_eq!(app.get_tempo(), 92.5);
+        assert!((app.get_group_volume(2) - 0.5).abs() < 1e-6);
+        assert!(app.is_group_muted(1));
+        assert_eq!(app.get_group_pattern(2), 6);
+        assert!(app.sequencer.get_pattern_grid(2, 6)[5][3]);
+        // Patterns that weren't in the project are gone
+        assert!(!app.sequencer.get_pattern_grid(0, 0)[0][0]);
+        
+        // A broken file leaves the session alone with a warning
+        std::fs::write(path, "{ not a project").unwrap();
+        app.autoload_project(path);
+        assert!(app.ui_state.active_notification().unwrap().starts_with("Starting empty: Project error"));
+        assert_eq!(app.get_tempo(), 92.5);
+        
+        let _ = std::fs::remove_file(path);
+        app.autoload_project(path);
+        assert_eq!(app.get_tempo(), 92.5);
+    }
+
+    #[test]
+    fn test_loading_a_project_reads_pad_settings_again() {
+        let path = std::env::temp_dir().join(format!("ko2_pad_settings_reload_{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let mut app = App::detached();
+        let panned = PadSettings { pan: -0.5, ..PadSettings::default() };
+        app.mixer.set_pad_settings(1, 2, panned);
+        app.save_pad_settings(&path);
+        
+        // A detached app never reads the file
+        app.mixer.set_pad_settings(1, 2, PadSettings::default());
+        app.mixer.set_pad_settings(0, 0, PadSettings { gain: 0.5, ..PadSettings::default() });
+        app.apply_project(app.project());
+        assert_eq!(app.mixer.get_pad_settings(1, 2), PadSettings::default());
+        
+        // Unsaved pad edits give way to the file, like the samples they go with
+        app.pad_settings_path = Some(path.clone());
+        app.apply_project(app.project());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(app.mixer.get_pad_settings(1, 2), panned);
+        assert_eq!(app.mixer.get_pad_settings(0, 0), PadSettings::default());
+    }
+
+    #[test]
+    fn test_song_length_follows_chains_and_automation() {
+        let mut app = App::new().unwrap();
+        assert_eq!(app.song_length_bars(), 1);
+        for _ in 0..3 {
+            app.append_to_chain();
+            app.next_pattern();
+        }
+        assert_eq!(app.song_length_bars(), 3);
+        app.song.bar = 5;
+        app.add_tempo_breakpoint();
+        assert_eq!(app.song_length_bars(), 6);
+        
+        // The arrangement travels with the project
+        let mut loaded = App::new().unwrap();
+        loaded.apply_project(app.project());
+        assert_eq!(loaded.get_chain(0), &[0, 1, 2]);
+        assert_eq!(loaded.song_length_bars(), 6);
+    }
+
+    #[test]
+    fn test_pattern_loop_bars() {
+        let mut app = App::new().unwrap();
+        assert_eq!(app.pattern_loop_bars(), 1);
+        app.sequencer.cycle_division(1);
+        app.sequencer.cycle_division(1);
+        assert_eq!(app.sequencer.get_division(1), ClockDivision::Half);
+        assert_eq!(app.pattern_loop_bars(), 2);
+        for _ in 0..3 {
+            app.append_to_chain();
+            app.next_pattern();
+        }
+        assert_eq!(app.pattern_loop_bars(), 3);
+    }
+
+    #[test]
+    fn test_reload_config_swaps_bindings() {
+        use crate::command::Command;
+        use crate::input::KeyBinding;
+        use crossterm::event::KeyCode;
+        
+        let path = std::env::temp_dir().join("ko2_reload_config_test.toml");
+        let path = path.to_str().unwrap();
+        let mut config = Config::default();
+        config.key_bindings.transport.play_stop = "F9".to_string();
+        config.ui.tick_interval_ms = 7;
+        std::fs::write(path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        
+        let mut app = App::new().unwrap();
+        app.toggle_step(0, 0);
+        let mut input_mapper = InputMapper::new();
+        let mut theme = Theme::default();
+        app.reload_config(path, &mut input_mapper, &mut theme);
+        assert_eq!(input_mapper.get_command(&KeyBinding::new(KeyCode::F(9))), Some(&Command::TogglePlayback));
+        assert_eq!(input_mapper.get_command(&KeyBinding::new(KeyCode::Char(' '))), None);
+        assert_eq!(app.tick_interval, Duration::from_millis(7));
+        assert!(app.sequencer.get_pattern_grid(0, 0)[0][0]);
+        
+        // Mistakes that still parse are applied and reported
+        config.key_bindings.transport.record = "Spce".to_string();
+        config.ui.tick_interval_ms = 0;
+        std::fs::write(path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        app.reload_config(path, &mut input_mapper, &mut theme);
+        let message = app.ui_state.active_notification().unwrap();
+        assert!(message.contains("key_bindings.transport.record has unknown key \"Spce\" (+1 more)"));
+        
+        // A broken file reports the error and keeps the bindings that work
+        std::fs::write(path, "[key_bindings").unwrap();
+        app.reload_config(path, &mut input_mapper, &mut theme);
+        assert!(app.ui_state.active_notification().unwrap().starts_with("Configuration error"));
+        assert_eq!(input_mapper.get_command(&KeyBinding::new(KeyCode::F(9))), Some(&Command::TogglePlayback));
+        
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_restore_autosave_prompt() {
+        let path = std::env::temp_dir().join("ko2_restore_autosave_test.koii");
+        let path = path.to_str().unwrap().to_string();
+        let mut crashed = App::new().unwrap();
+        crashed.set_tempo(133.0);
+        crashed.autosave = Autosave::new(path.clone(), 60);
+        crashed.stop();
+
+        // The stop autosave runs in the background; wait for it to land
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Project::load(&path).is_err() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut app = App::new().unwrap();
+        app.autosave = Autosave::new(path.clone(), 60);
+        app.offer_autosave_restore();
+        app.prompt_input('x');
+        assert_eq!(app.ui_state.prompt.as_ref().unwrap().input, "");
+        app.prompt_input('n');
+        assert_eq!(app.ui_state.prompt, None);
+        assert_ne!(app.get_tempo(), 133.0);
+
+        app.set_tempo(90.0);
+        app.offer_autosave_restore();
+        app.prompt_input('y');
+        assert_eq!(app.ui_state.prompt, None);
+        assert_eq!(app.get_tempo(), 133.0);
+        // Restoring doesn't autosave the session it replaced over the snapshot
+        assert!(app.autosave.is_some());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(Project::load(&path).unwrap().tempo, 133.0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_panic_stops_preview() {
+        let mut app = App::new().unwrap();
+        app.start_audition();
+        
+        app.panic();
+        assert!(app.get_audition().is_none());
+        assert_eq!(app.ui_state.active_notification(), Some("All sounds stopped"));
     }
 
     #[test]
@@ -363,4 +3476,393 @@ mod tests {
         app.toggle_group_mute(0);
         assert!(!app.is_group_muted(0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_level_getters_follow_audio() {
+        let mut app = App::new().unwrap();
+        app.mixer = Mixer::detached();
+        let constant = render::encode_wav(&[0.5; 2048], 1, RENDER_SAMPLE_RATE).unwrap();
+        app.sample_bank.load_sample_data(2, 0, "constant", constant).unwrap();
+        app.state.current_group = 2;
+        
+        app.toggle_group_mute(2);
+        app.mixer.render_frames(512); // Let the mute ramp settle
+        app.trigger_pad(0);
+        app.mixer.render_frames(256);
+        assert_eq!(app.get_group_level(2), 0.0);
+        
+        app.toggle_group_mute(2);
+        app.trigger_pad(0);
+        app.mixer.render_frames(256);
+        assert!(app.get_group_level(2) > 0.2);
+        assert!(app.get_master_level() > 0.2);
+    }
+
+
+    #[test]
+    fn test_tempo_automation_follows_bars() {
+        let mut app = App::new().unwrap();
+        app.add_tempo_breakpoint(); // Bar 1 at 120
+        app.song.bar = 4;
+        app.adjust_tempo(20.0);
+        app.add_tempo_breakpoint(); // Bar 5 at 140
+        
+        app.toggle_playback();
+        assert_eq!(app.get_tempo(), 120.0);
+        assert_eq!(app.get_tempo_target(), Some(140.0));
+        
+        app.advance_song_position();
+        assert_eq!(app.get_tempo(), 125.0);
+        for _ in 0..5 {
+            app.advance_song_position();
+        }
+        assert_eq!(app.get_tempo(), 140.0);
+        assert_eq!(app.get_tempo_target(), None);
+        
+        app.clear_tempo_automation();
+        assert!(app.song.tempo_automation.is_empty());
+    }
+
+
+    #[test]
+    fn test_triggers_between_ticks_land_on_nearest_steps() {
+        let mut app = App::new().unwrap();
+        app.toggle_recording();
+        app.toggle_playback();
+        
+        // Two hits inside the same tick interval, either side of its midpoint
+        let step = app.state.step_duration();
+        let start = Instant::now();
+        app.bar_start = Some(start);
+        app.recorded_triggers.push((0, 1, start + step.mul_f64(2.2)));
+        app.recorded_triggers.push((0, 2, start + step.mul_f64(2.8)));
+        app.flush_recorded_triggers();
+        
+        let grid = app.sequencer.get_pattern_grid(0, 0);
+        assert!(grid[1][2]);
+        assert!(grid[2][3]);
+        assert!(app.recorded_triggers.is_empty());
+    }
+
+
+    #[test]
+    fn test_hits_record_into_armed_groups() {
+        let mut app = App::new().unwrap();
+        app.state.group_patterns[2] = 1;
+        app.toggle_record_arm(0);
+        app.toggle_record_arm(2);
+        assert_eq!(app.record_groups(), [0, 2]);
+        assert_eq!(app.ui_state.active_notification(), Some("LEAD armed: recording goes into it"));
+        
+        // Viewing another group doesn't change where the hit lands
+        app.select_group(1);
+        app.toggle_recording();
+        app.toggle_playback();
+        app.trigger_pad(4);
+        app.flush_recorded_triggers();
+        assert!(app.sequencer.get_pattern_grid(0, 0)[4].contains(&true));
+        assert!(app.sequencer.get_pattern_grid(2, 1)[4].contains(&true));
+        assert!(!app.sequencer.get_pattern_grid(1, 0)[4].contains(&true));
+        
+        // With nothing armed, hits go back to the current group
+        app.toggle_record_arm(0);
+        app.toggle_record_arm(2);
+        assert_eq!(app.ui_state.active_notification(), Some("LEAD disarmed: recording goes into the current group"));
+        app.trigger_pad(6);
+        app.flush_recorded_triggers();
+        assert!(app.sequencer.get_pattern_grid(1, 0)[6].contains(&true));
+        assert!(!app.sequencer.get_pattern_grid(0, 0)[6].contains(&true));
+    }
+
+    #[test]
+    fn test_toggle_step_from_grid() {
+        let mut app = App::new().unwrap();
+        app.toggle_step(3, 9);
+        assert!(app.sequencer.get_pattern_grid(0, 0)[3][9]);
+        assert_eq!(app.ui_state.selected_pad, Some(3));
+        assert_eq!(app.ui_state.cursor_step, 9);
+
+        app.toggle_step(3, 9);
+        assert!(!app.sequencer.get_pattern_grid(0, 0)[3][9]);
+
+        // Out of range cells are ignored
+        app.toggle_step(3, 16);
+        assert_eq!(app.ui_state.cursor_step, 9);
+    }
+
+    #[test]
+    fn test_accent_toggles_at_the_cursor() {
+        let mut app = App::new().unwrap();
+        app.ui_state.cursor_step = 4;
+        app.toggle_accent_at_cursor();
+        assert!(app.is_step_accented(4));
+        assert_eq!(app.ui_state.active_notification(), Some("Accent step 5: on"));
+
+        // Accents belong to the pattern, not the group's other slots
+        app.next_pattern();
+        assert!(!app.is_step_accented(4));
+        app.prev_pattern();
+        app.toggle_accent(4);
+        assert!(!app.is_step_accented(4));
+
+        app.toggle_accent(16);
+        assert_eq!(app.ui_state.cursor_step, 4);
+    }
+
+    #[test]
+    fn test_chain_moves_the_shown_pattern() {
+        let mut app = App::new().unwrap();
+        app.append_to_chain();
+        app.next_pattern();
+        app.append_to_chain();
+        assert_eq!(app.ui_state.active_notification(), Some("Chained pattern 02 (2 in chain)"));
+        assert_eq!(app.get_chain(0), &[0, 1]);
+        
+        // Starting from the top plays the chain from its first entry
+        app.toggle_playback();
+        assert_eq!(app.get_current_pattern(), 0);
+        for _ in 0..STEPS_PER_PATTERN {
+            tick_step(&mut app);
+        }
+        assert_eq!(app.get_current_pattern(), 1);
+        for _ in 0..STEPS_PER_PATTERN {
+            tick_step(&mut app);
+        }
+        assert_eq!(app.get_current_pattern(), 0);
+        
+        app.clear_chain();
+        assert!(app.get_chain(0).is_empty());
+    }
+
+    #[test]
+    fn test_fill_and_clear_selected_pad() {
+        let mut app = App::new().unwrap();
+        app.fill_selected_pad();
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to fill its row"));
+        
+        app.ui_state.select_pad(0);
+        let hit_count = |app: &App| app.get_current_pattern_data().map_or(0, |p| p.steps[0].iter().filter(|&&hit| hit).count());
+        app.fill_selected_pad();
+        assert_eq!(app.ui_state.active_notification(), Some("Filled pad 1 on every step"));
+        assert_eq!(hit_count(&app), STEPS_PER_PATTERN);
+        
+        app.clear_selected_pad();
+        assert_eq!(hit_count(&app), 0);
+        app.undo();
+        assert_eq!(app.ui_state.active_notification(), Some("Undid clear on pattern 01"));
+        assert_eq!(hit_count(&app), STEPS_PER_PATTERN);
+    }
+
+    #[test]
+    fn test_undo_keeps_later_edits() {
+        let mut app = App::new().unwrap();
+        app.ui_state.select_pad(0);
+        app.fill_selected_pad();
+        app.toggle_step(1, 3);
+        
+        // Undoing the fill now would lose the hit on pad 2
+        app.undo();
+        assert_eq!(app.ui_state.active_notification(), Some("Pattern 01 has changed since the fill, nothing to undo"));
+        let pattern = app.get_current_pattern_data().unwrap();
+        assert!(pattern.get_hit(0, 5) && pattern.get_hit(1, 3));
+        
+        app.undo();
+        assert_eq!(app.ui_state.active_notification(), Some("Nothing to undo"));
+    }
+
+    #[test]
+    fn test_thin_pattern_gets_coarser_on_repeats() {
+        let mut app = App::detached();
+        app.thin_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Nothing left to thin"));
+        
+        app.ui_state.select_pad(0);
+        app.sequencer.fill_pad_row(0, 0, 0, true);
+        let hits = |app: &App| -> Vec<usize> {
+            let row = &app.get_current_pattern_data().unwrap().steps[0];
+            (0..STEPS_PER_PATTERN).filter(|&step| row[step]).collect()
+        };
+        app.thin_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Thinned to every 4 steps, 12 hits removed"));
+        assert_eq!(hits(&app), vec![0, 4, 8, 12]);
+        app.thin_pattern();
+        assert_eq!(hits(&app), vec![0, 8]);
+        app.thin_pattern();
+        assert_eq!(hits(&app), vec![0]);
+        app.thin_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Nothing left to thin"));
+        
+        app.undo();
+        assert_eq!(app.ui_state.active_notification(), Some("Undid thin on pattern 01"));
+        assert_eq!(hits(&app), vec![0, 8]);
+    }
+
+    #[test]
+    fn test_euclidean_prompt() {
+        let mut app = App::new().unwrap();
+        app.open_euclidean();
+        assert!(app.ui_state.prompt.is_none());
+        
+        app.ui_state.select_pad(1);
+        app.open_euclidean();
+        for c in "17".chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert_eq!(app.ui_state.active_notification(), Some("At most 16 hits fit in this pattern"));
+        
+        app.prompt_backspace();
+        app.prompt_backspace();
+        for c in "4,2".chars() {
+            app.prompt_input(c);
+        }
+        app.submit_prompt();
+        assert!(app.ui_state.prompt.is_none());
+        assert_eq!(app.ui_state.active_notification(), Some("Pad 2: E(4,16) rotated 2"));
+        let row = &app.get_current_pattern_data().unwrap().steps[1];
+        let hits: Vec<usize> = (0..STEPS_PER_PATTERN).filter(|&step| row[step]).collect();
+        assert_eq!(hits, vec![2, 6, 10, 14]);
+    }
+
+    #[test]
+    fn test_fill_empty_pads_with_synth() {
+        let mut app = App::new().unwrap();
+        for pad in 0..PADS_PER_GROUP {
+            app.sample_bank.remove_sample(0, pad);
+        }
+        app.sample_bank.load_sample_data(0, 3, "mine", app.sample_bank.generate_simple_kick()).unwrap();
+        
+        app.fill_empty_pads_with_synth();
+        assert_eq!(app.ui_state.active_notification(), Some("Loaded synth drums into 15 empty pads"));
+        assert_eq!(app.sample_bank.get_sample_name(0, 0), Some("synth-kick"));
+        assert_eq!(app.sample_bank.get_sample_name(0, 3), Some("mine"));
+        assert_eq!(app.sample_bank.get_sample_name(0, 9), Some("synth-snare"));
+        
+        app.fill_empty_pads_with_synth();
+        assert_eq!(app.ui_state.active_notification(), Some("Every DRUMS pad already has a sound"));
+    }
+
+    #[test]
+    fn test_pad_banks() {
+        let mut app = App::detached();
+        app.cycle_pad_bank();
+        assert_eq!(app.ui_state.active_notification(), Some("Only 16 pads; set [layout] pads_per_group = 32 for a second bank"));
+        assert_eq!(app.bank_pad(5), 5);
+        
+        app.set_pad_count(32);
+        assert_eq!(app.pad_count(), 32);
+        assert_eq!(app.get_visible_pads(), 0..16);
+        app.cycle_pad_bank();
+        assert_eq!(app.ui_state.active_notification(), Some("Pads 17–32"));
+        assert_eq!(app.get_visible_pads(), 16..32);
+        
+        // Pad keys land on the second bank; pads bound past 16 are left alone
+        assert_eq!(app.bank_pad(5), 21);
+        assert_eq!(app.bank_pad(20), 20);
+        app.trigger_pad(app.bank_pad(5));
+        assert_eq!(app.get_selected_pad(), Some(21));
+        app.toggle_step(21, 0);
+        assert!(app.get_current_pattern_data().unwrap().steps[21][0]);
+        
+        // Selecting a first-bank pad brings its rows back into view
+        app.trigger_pad(2);
+        assert_eq!(app.get_visible_pads(), 0..16);
+        app.trigger_pad(32);
+        assert_eq!(app.get_selected_pad(), Some(2));
+        
+        app.trigger_pad(30);
+        app.set_pad_count(16);
+        assert_eq!(app.get_selected_pad(), None);
+        assert_eq!(app.get_visible_pads(), 0..16);
+        assert_eq!(app.get_current_pattern_data().unwrap().steps.len(), 16);
+    }
+
+    #[test]
+    fn test_layer_mode_cycles_and_resets_on_stop() {
+        let mut app = App::detached();
+        app.cycle_layer_mode();
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad with a sample to change how its layers play"));
+        
+        let snare = app.sample_bank.generate_simple_kick();
+        app.sample_bank.load_sample_data(0, 1, "snare", snare.clone()).unwrap();
+        app.ui_state.select_pad(1);
+        app.cycle_layer_mode();
+        assert_eq!(app.ui_state.active_notification(), Some("Pad 2 layers: round-robin (only one sample loaded)"));
+        app.sample_bank.add_layer(0, 1, "snare-2", snare, 1.0).unwrap();
+        
+        // Each hit plays one layer, and stopping sends the next hit back to the first
+        app.trigger_pad(1);
+        assert_eq!(app.mixer.voice_count(), 1);
+        app.toggle_playback();
+        app.stop();
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(app.sample_bank.next_layers(0, 1, MAX_VELOCITY, &mut rng), [(0, 1.0)]);
+        
+        app.cycle_layer_mode();
+        app.cycle_layer_mode();
+        assert_eq!(app.ui_state.active_notification(), Some("Pad 2 layers: stack"));
+    }
+
+    #[test]
+    fn test_humanize_can_be_undone() {
+        let mut app = App::new().unwrap();
+        app.rng = StdRng::seed_from_u64(7);
+        app.humanize_pad();
+        assert_eq!(app.ui_state.active_notification(), Some("Select a pad to humanize its row"));
+        app.humanize_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Nothing to humanize"));
+        
+        for step in 0..STEPS_PER_PATTERN {
+            app.toggle_step(6, step);
+        }
+        app.toggle_step(2, 0);
+        let before = app.get_current_pattern_data().unwrap().clone();
+        app.humanize_pad();
+        assert_eq!(app.ui_state.active_notification(), Some("Humanized 1 steps, vel ±10%, ±3 ms"));
+        let after_pad = app.get_current_pattern_data().unwrap().clone();
+        app.humanize_pattern();
+        assert_eq!(app.ui_state.active_notification(), Some("Humanized 17 steps, vel ±10%, ±3 ms"));
+        assert_ne!(app.get_current_pattern_data().unwrap().velocities, before.velocities);
+        
+        // Undo steps back over the last pass only
+        app.undo();
+        app.undo();
+        assert_eq!(app.ui_state.active_notification(), Some("Nothing to undo"));
+        let pattern = app.get_current_pattern_data().unwrap();
+        assert_eq!(pattern.velocities, after_pad.velocities);
+        assert_eq!(pattern.micro_offsets, after_pad.micro_offsets);
+        assert_eq!(pattern.velocities[6], before.velocities[6]);
+    }
+
+    #[test]
+    fn test_poll_interval_backs_off_when_idle() {
+        let mut app = App::new().unwrap();
+        assert_eq!(app.desired_poll_interval(), IDLE_POLL_INTERVAL);
+
+        app.toggle_playback();
+        assert_eq!(app.desired_poll_interval(), DEFAULT_TICK_INTERVAL);
+        app.stop();
+        assert_eq!(app.desired_poll_interval(), IDLE_POLL_INTERVAL);
+
+        app.ui_state.start_flash(vec![(0, 3)]);
+        assert_eq!(app.desired_poll_interval(), DEFAULT_TICK_INTERVAL);
+    }
+
+    #[test]
+    fn test_trim_needs_a_loaded_pad() {
+        let mut app = App::new().unwrap();
+        app.sample_bank.remove_sample(0, 2);
+        app.ui_state.select_pad(2);
+        app.adjust_trim_start(TRIM_STEP);
+        assert_eq!(app.sample_bank.get_trim(0, 2), (0.0, 1.0));
+
+        let kick = app.sample_bank.generate_simple_kick();
+        app.sample_bank.load_sample_data(0, 2, "kick", kick).unwrap();
+        app.adjust_trim_start(TRIM_STEP * 10.0);
+        app.adjust_trim_end(-TRIM_STEP * 20.0);
+        let (start, end) = app.sample_bank.get_trim(0, 2);
+        assert!((start - 0.1).abs() < 1e-6);
+        assert!((end - 0.8).abs() < 1e-6);
+        assert_eq!(app.ui_state.active_notification(), Some("Trim pad 3: 10%–80%"));
+    }
+}
@@ -0,0 +1,789 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ringbuf::traits::Producer;
+use ringbuf::HeapProd;
+use rodio::Source;
+use crate::effects::{GateEffect, SendEffects};
+use crate::pad_settings::PadSettings;
+use crate::sample::{DecodedSample, SampleStream};
+use crate::sequencer::MAX_GROUPS;
+
+pub const BUS_SAMPLE_RATE: u32 = 44100;
+pub const BUS_CHANNELS: u16 = 2;
+pub const DEFAULT_LIMITER_THRESHOLD_DB: f32 = -1.0;
+pub const DEFAULT_VOLUME_RAMP_MS: f32 = 10.0;
+pub const MAX_MASTER_WIDTH: f32 = 2.0;
+// Master gain sits after the fader, so the limiter can be driven harder than 100% allows
+pub const MAX_MASTER_GAIN_DB: f32 = 12.0;
+pub const MIN_MASTER_GAIN_DB: f32 = -60.0; // Reads as -∞ and silences the master
+// Output recordings stop here, about 53 MB of 16-bit stereo WAV
+pub const MAX_CAPTURE_SECS: u32 = 300;
+
+// ~6ms at 44.1kHz: short enough that new hits land promptly, long enough to keep locking cheap
+const BLOCK_FRAMES: usize = 256;
+const LIMITER_RELEASE_MS: f32 = 80.0;
+const METER_DECAY_MS: f32 = 300.0;
+
+// Processes the summed output in place; blocks are interleaved stereo at BUS_SAMPLE_RATE
+pub trait MasterEffect: Send {
+    fn process(&mut self, block: &mut [f32]);
+}
+
+// Where a voice's frames come from
+enum VoiceSource {
+    Decoded { sample: Arc<DecodedSample>, position: f64 },
+    Streamed(SampleStream),
+}
+
+pub struct Voice {
+    source: VoiceSource,
+    rate: f64, // Source frames per bus frame
+    group: usize,
+    pad: Option<usize>, // None for voices that aren't pad hits, like test tones
+    gain: f32,
+    pan: (f32, f32), // Left and right gains
+}
+
+impl Voice {
+    pub fn new(sample: Arc<DecodedSample>, group: usize, gain: f32) -> Self {
+        let rate = sample.sample_rate as f64 / BUS_SAMPLE_RATE as f64;
+        Self {
+            source: VoiceSource::Decoded { sample, position: 0.0 },
+            rate,
+            group,
+            pad: None,
+            gain,
+            pan: (1.0, 1.0),
+        }
+    }
+
+    // A pad hit shaped by the pad's gain, pan and pitch
+    pub fn for_pad(sample: Arc<DecodedSample>, group: usize, pad: usize, gain: f32, settings: &PadSettings) -> Self {
+        let mut voice = Self::new(sample, group, gain * settings.gain);
+        voice.rate *= settings.pitch_ratio();
+        voice.pad = Some(pad);
+        voice.pan = settings.pan_gains();
+        voice
+    }
+
+    // Read from disk as it plays rather than decoded up front
+    pub fn from_stream(stream: SampleStream, group: usize, gain: f32) -> Self {
+        Self {
+            rate: stream.sample_rate as f64 / BUS_SAMPLE_RATE as f64,
+            source: VoiceSource::Streamed(stream),
+            group,
+            pad: None,
+            gain,
+            pan: (1.0, 1.0),
+        }
+    }
+
+    // A pad hit read from disk as it plays; gain, pan and pitch apply as for_pad's do
+    pub fn streamed(stream: SampleStream, group: usize, pad: usize, gain: f32, settings: &PadSettings) -> Self {
+        let mut voice = Self::from_stream(stream, group, gain * settings.gain);
+        voice.rate *= settings.pitch_ratio();
+        voice.pad = Some(pad);
+        voice.pan = settings.pan_gains();
+        voice
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match &self.source {
+            VoiceSource::Decoded { sample, position } => *position as usize >= sample.frames(),
+            VoiceSource::Streamed(stream) => stream.is_finished(),
+        }
+    }
+
+    fn mix_into(&mut self, block: &mut [f32]) {
+        for frame in block.chunks_exact_mut(2) {
+            let next = match &mut self.source {
+                VoiceSource::Decoded { sample, position } => {
+                    let index = *position as usize;
+                    if index >= sample.frames() {
+                        break;
+                    }
+                    let next = sample.interpolated_frame(index, (*position - index as f64) as f32);
+                    *position += self.rate;
+                    next
+                }
+                VoiceSource::Streamed(stream) => match stream.advance(self.rate) {
+                    Some(next) => next,
+                    None => break,
+                },
+            };
+            let (left, right) = next;
+            frame[0] += left * self.gain * self.pan.0;
+            frame[1] += right * self.gain * self.pan.1;
+        }
+    }
+}
+
+pub fn ms_to_frames(ms: f32) -> usize {
+    (ms.max(0.0) / 1000.0 * BUS_SAMPLE_RATE as f32) as usize
+}
+
+// Mid/side stereo width: 0.0 folds to mono, 1.0 leaves the mix alone, 2.0 doubles the side
+pub fn apply_width(block: &mut [f32], width: f32) {
+    for frame in block.chunks_exact_mut(2) {
+        let mid = (frame[0] + frame[1]) * 0.5;
+        let side = (frame[0] - frame[1]) * 0.5 * width;
+        frame[0] = mid + side;
+        frame[1] = mid - side;
+    }
+}
+
+// Gain that slides linearly to its target over a fixed number of frames,
+// so volume and mute changes don't click
+pub struct GainRamp {
+    current: f32,
+    target: f32,
+    step: f32,
+    ramp_frames: usize,
+}
+
+impl GainRamp {
+    pub fn new(gain: f32, ramp_frames: usize) -> Self {
+        Self {
+            current: gain,
+            target: gain,
+            step: 0.0,
+            ramp_frames,
+        }
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+        self.step = (target - self.current).abs() / self.ramp_frames.max(1) as f32;
+    }
+
+    // Skips the ramp, for setting levels before anything plays
+    pub fn jump_to(&mut self, gain: f32) {
+        self.current = gain;
+        self.target = gain;
+    }
+
+    pub fn set_ramp_frames(&mut self, ramp_frames: usize) {
+        self.ramp_frames = ramp_frames;
+        self.set_target(self.target);
+    }
+
+    // Gain for the next frame; never steps past the target
+    pub fn next_gain(&mut self) -> f32 {
+        if self.current < self.target {
+            self.current = (self.current + self.step).min(self.target);
+        } else if self.current > self.target {
+            self.current = (self.current - self.step).max(self.target);
+        }
+        self.current
+    }
+
+    fn apply(&mut self, block: &mut [f32]) {
+        for frame in block.chunks_exact_mut(2) {
+            let gain = self.next_gain();
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+}
+
+// Peak levels written by the audio thread and read by the UI, f32 bits
+pub struct Meters {
+    groups: [AtomicU32; MAX_GROUPS],
+    master: AtomicU32,
+    blocks: AtomicU64, // Blocks rendered, so an output device that stops pulling can be noticed
+}
+
+impl Meters {
+    pub fn new() -> Self {
+        Self {
+            groups: Default::default(),
+            master: AtomicU32::new(0),
+            blocks: AtomicU64::new(0),
+        }
+    }
+
+    pub fn blocks(&self) -> u64 {
+        self.blocks.load(Ordering::Relaxed)
+    }
+
+    pub fn group(&self, group: usize) -> f32 {
+        self.groups
+            .get(group)
+            .map(|level| f32::from_bits(level.load(Ordering::Relaxed)))
+            .unwrap_or(0.0)
+    }
+
+    pub fn master(&self) -> f32 {
+        f32::from_bits(self.master.load(Ordering::Relaxed))
+    }
+
+    // The held master peak is over 0 dBFS, which the limiter, when on, never lets happen
+    pub fn is_clipping(&self) -> bool {
+        self.master() > 1.0
+    }
+
+    // Peaks hold instantly and fall back over METER_DECAY_MS
+    fn update(level: &AtomicU32, block: &[f32]) {
+        let frames = block.len() / BUS_CHANNELS as usize;
+        let decay = (-(frames as f32) / (METER_DECAY_MS / 1000.0 * BUS_SAMPLE_RATE as f32)).exp();
+        let peak = block.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        let previous = f32::from_bits(level.load(Ordering::Relaxed));
+        level.store(peak.max(previous * decay).to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for Meters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct BusState {
+    voices: Vec<Voice>,
+    pub sends: SendEffects,
+    pub gates: [Option<GateEffect>; MAX_GROUPS], // Applied to each group after its fader
+    effects: Vec<Box<dyn MasterEffect>>,
+    meters: Arc<Meters>,
+    group_blocks: [Vec<f32>; MAX_GROUPS], // Dry sum of each group, for meters and sends
+    group_gains: [GainRamp; MAX_GROUPS],
+    master_gain: GainRamp,
+    width: f32,
+    capture: Option<HeapProd<f32>>, // Feeds the output recording's writer thread
+}
+
+impl BusState {
+    pub fn new(effects: Vec<Box<dyn MasterEffect>>, tempo: f32, meters: Arc<Meters>) -> Self {
+        Self {
+            voices: Vec::new(),
+            sends: SendEffects::new(tempo),
+            gates: Default::default(),
+            effects,
+            meters,
+            group_blocks: Default::default(),
+            group_gains: std::array::from_fn(|_| GainRamp::new(1.0, 0)),
+            master_gain: GainRamp::new(1.0, 0),
+            width: 1.0,
+            capture: None,
+        }
+    }
+
+    // Every block from here on goes into the ring as interleaved stereo at BUS_SAMPLE_RATE,
+    // exactly as it went to the output
+    pub fn start_capture(&mut self, ring: HeapProd<f32>) {
+        self.capture = Some(ring);
+    }
+
+    pub fn stop_capture(&mut self) {
+        self.capture = None;
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width;
+    }
+
+    // Fader levels, applied live to everything playing; ramped unless set before playback
+    pub fn set_gains(&mut self, master: f32, groups: &[f32; MAX_GROUPS], ramp: bool) {
+        let targets = std::iter::once((&mut self.master_gain, master))
+            .chain(self.group_gains.iter_mut().zip(groups.iter().copied()));
+        for (gain, target) in targets {
+            if ramp {
+                gain.set_target(target);
+            } else {
+                gain.jump_to(target);
+            }
+        }
+    }
+
+    pub fn set_ramp_frames(&mut self, ramp_frames: usize) {
+        self.master_gain.set_ramp_frames(ramp_frames);
+        for gain in &mut self.group_gains {
+            gain.set_ramp_frames(ramp_frames);
+        }
+    }
+
+    pub fn add_voice(&mut self, voice: Voice, max_voices: usize) {
+        self.voices.retain(|voice| !voice.is_finished());
+
+        // Steal the oldest voice rather than refusing new hits
+        if self.voices.len() >= max_voices {
+            self.voices.remove(0);
+        }
+        self.voices.push(voice);
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.iter().filter(|voice| !voice.is_finished()).count()
+    }
+
+    // Cuts whatever the pad is still playing
+    pub fn choke(&mut self, group: usize, pad: usize) {
+        self.voices.retain(|voice| voice.group != group || voice.pad != Some(pad));
+    }
+
+    pub fn clear_voices(&mut self) {
+        self.voices.clear();
+        self.sends.clear_tails();
+    }
+
+    pub fn render(&mut self, block: &mut [f32]) {
+        block.fill(0.0);
+        for group_block in &mut self.group_blocks {
+            group_block.clear();
+            group_block.resize(block.len(), 0.0);
+        }
+
+        for voice in &mut self.voices {
+            if let Some(group_block) = self.group_blocks.get_mut(voice.group) {
+                voice.mix_into(group_block);
+            }
+        }
+        self.voices.retain(|voice| !voice.is_finished());
+        for ((group_block, gain), gate) in self.group_blocks.iter_mut().zip(&mut self.group_gains).zip(&mut self.gates) {
+            gain.apply(group_block);
+            if let Some(gate) = gate {
+                gate.process(group_block);
+            }
+        }
+
+        let sends_idle = self.sends.is_idle();
+        if !sends_idle {
+            self.sends.begin_block(block.len());
+        }
+
+        for (group, group_block) in self.group_blocks.iter().enumerate() {
+            Meters::update(&self.meters.groups[group], group_block);
+            for (sample, &dry) in block.iter_mut().zip(group_block) {
+                *sample += dry;
+            }
+            if !sends_idle {
+                self.sends.accumulate(group, group_block);
+            }
+        }
+
+        if !sends_idle {
+            self.sends.process(block);
+        }
+        self.master_gain.apply(block);
+        // Before the limiter, so widening can't push the output past it
+        if self.width != 1.0 {
+            apply_width(block, self.width);
+        }
+
+        for effect in &mut self.effects {
+            effect.process(block);
+        }
+        Meters::update(&self.meters.master, block);
+        self.meters.blocks.fetch_add(1, Ordering::Relaxed);
+
+        // Never waits on the writer; if it has fallen seconds behind, the block is lost
+        if let Some(capture) = &mut self.capture {
+            capture.push_slice(block);
+        }
+    }
+}
+
+// The single source handed to rodio; everything the mixer plays is summed here
+pub struct BusSource {
+    state: Arc<Mutex<BusState>>,
+    block: Vec<f32>,
+    position: usize,
+}
+
+impl BusSource {
+    pub fn new(state: Arc<Mutex<BusState>>) -> Self {
+        let block = vec![0.0; BLOCK_FRAMES * BUS_CHANNELS as usize];
+        let position = block.len();
+        Self { state, block, position }
+    }
+}
+
+impl Iterator for BusSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.block.len() {
+            match self.state.lock() {
+                Ok(mut state) => state.render(&mut self.block),
+                Err(_) => self.block.fill(0.0),
+            }
+            self.position = 0;
+        }
+
+        let sample = self.block[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for BusSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        BUS_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        BUS_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+// The bottom of the master gain range stands for -∞
+pub fn master_gain(db: f32) -> f32 {
+    if db <= MIN_MASTER_GAIN_DB {
+        0.0
+    } else {
+        db_to_gain(db.min(MAX_MASTER_GAIN_DB))
+    }
+}
+
+pub fn master_gain_label(db: f32) -> String {
+    if db <= MIN_MASTER_GAIN_DB {
+        "-∞ dB".to_string()
+    } else {
+        format!("{:+.1} dB", db)
+    }
+}
+
+// Shared between the UI thread (settings, meter) and the audio thread (processing)
+pub struct LimiterControl {
+    enabled: AtomicBool,
+    threshold: AtomicU32, // Linear gain, f32 bits
+    gain: AtomicU32,      // Lowest gain applied in the last block, f32 bits
+}
+
+impl LimiterControl {
+    pub fn new(enabled: bool, threshold_db: f32) -> Self {
+        let control = Self {
+            enabled: AtomicBool::new(enabled),
+            threshold: AtomicU32::new(0),
+            gain: AtomicU32::new(1.0f32.to_bits()),
+        };
+        control.set_threshold_db(threshold_db);
+        control
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_threshold_db(&self, threshold_db: f32) {
+        let threshold = db_to_gain(threshold_db.clamp(-24.0, 0.0));
+        self.threshold.store(threshold.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn threshold(&self) -> f32 {
+        f32::from_bits(self.threshold.load(Ordering::Relaxed))
+    }
+
+    pub fn gain(&self) -> f32 {
+        f32::from_bits(self.gain.load(Ordering::Relaxed))
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        // Anything past ~0.1dB of reduction is audible enough to flag
+        self.is_enabled() && self.gain() < 0.99
+    }
+}
+
+// Peak limiter with instant attack and exponential release, so the output never
+// exceeds the threshold while quieter passages recover smoothly
+pub struct Limiter {
+    control: Arc<LimiterControl>,
+    gain: f32,
+    release: f32,
+}
+
+impl Limiter {
+    pub fn new(control: Arc<LimiterControl>) -> Self {
+        let release_frames = LIMITER_RELEASE_MS / 1000.0 * BUS_SAMPLE_RATE as f32;
+        Self {
+            control,
+            gain: 1.0,
+            release: 1.0 - (-1.0 / release_frames).exp(),
+        }
+    }
+}
+
+impl MasterEffect for Limiter {
+    fn process(&mut self, block: &mut [f32]) {
+        if !self.control.is_enabled() {
+            self.gain = 1.0;
+            self.control.gain.store(1.0f32.to_bits(), Ordering::Relaxed);
+            return;
+        }
+
+        let threshold = self.control.threshold();
+        let mut lowest_gain = 1.0f32;
+
+        for frame in block.chunks_exact_mut(2) {
+            let peak = frame[0].abs().max(frame[1].abs());
+            let target = if peak > threshold { threshold / peak } else { 1.0 };
+
+            if target < self.gain {
+                self.gain = target;
+            } else {
+                self.gain += (target - self.gain) * self.release;
+            }
+
+            frame[0] *= self.gain;
+            frame[1] *= self.gain;
+            lowest_gain = lowest_gain.min(self.gain);
+        }
+
+        self.control.gain.store(lowest_gain.to_bits(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_scale_sine(frames: usize) -> Arc<DecodedSample> {
+        let data = (0..frames)
+            .map(|i| (i as f32 / BUS_SAMPLE_RATE as f32 * 220.0 * 2.0 * std::f32::consts::PI).sin())
+            .collect();
+        Arc::new(DecodedSample {
+            channels: 1,
+            sample_rate: BUS_SAMPLE_RATE,
+            data,
+        })
+    }
+
+    fn limited_bus(control: &Arc<LimiterControl>) -> BusState {
+        BusState::new(vec![Box::new(Limiter::new(Arc::clone(control)))], 120.0, Arc::new(Meters::new()))
+    }
+
+    #[test]
+    fn test_width_math() {
+        let frame = [0.8, 0.2];
+        let widened = |width| {
+            let mut block = frame.to_vec();
+            apply_width(&mut block, width);
+            block
+        };
+
+        // Mono is the average on both sides
+        let mono = widened(0.0);
+        assert!((mono[0] - 0.5).abs() < 1e-6 && (mono[1] - 0.5).abs() < 1e-6);
+        let normal = widened(1.0);
+        assert!((normal[0] - 0.8).abs() < 1e-6 && (normal[1] - 0.2).abs() < 1e-6);
+        // Double width keeps the mid at 0.5 and doubles the side from 0.3 to 0.6
+        let wide = widened(2.0);
+        assert!((wide[0] - 1.1).abs() < 1e-6 && (wide[1] + 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_limiter_keeps_stacked_voices_in_range() {
+        let control = Arc::new(LimiterControl::new(true, 0.0));
+        let mut bus = limited_bus(&control);
+        let sine = full_scale_sine(BUS_SAMPLE_RATE as usize / 10);
+
+        // Eight full-scale voices on the same step sum to ±8.0 without the limiter
+        for _ in 0..8 {
+            bus.add_voice(Voice::new(Arc::clone(&sine), 0, 1.0), 32);
+        }
+
+        let mut block = vec![0.0; BLOCK_FRAMES * 2];
+        for _ in 0..8 {
+            bus.render(&mut block);
+            assert!(block.iter().all(|s| s.abs() <= 1.0));
+        }
+        assert!(control.is_engaged());
+    }
+
+    #[test]
+    fn test_master_gain_in_db() {
+        assert_eq!(master_gain(0.0), 1.0);
+        assert!((master_gain(6.0) - 1.995).abs() < 0.001);
+        assert!((master_gain(-6.0) - 0.501).abs() < 0.001);
+        assert!((master_gain(MAX_MASTER_GAIN_DB) - 3.981).abs() < 0.001);
+        assert!((master_gain(40.0) - 3.981).abs() < 0.001);
+        assert_eq!(master_gain(MIN_MASTER_GAIN_DB), 0.0);
+        assert_eq!(master_gain(f32::NEG_INFINITY), 0.0);
+        assert_eq!(master_gain_label(3.0), "+3.0 dB");
+        assert_eq!(master_gain_label(-60.0), "-∞ dB");
+    }
+
+    #[test]
+    fn test_clip_flag_follows_the_master_peak() {
+        let meters = Meters::new();
+        Meters::update(&meters.master, &[0.5, -1.0, 0.9, 0.2]);
+        assert!(!meters.is_clipping());
+        Meters::update(&meters.master, &[0.5, -1.2, 0.9, 0.2]);
+        assert!(meters.is_clipping());
+    }
+
+    #[test]
+    fn test_limiter_respects_threshold_and_bypass() {
+        let control = Arc::new(LimiterControl::new(true, -6.0));
+        let mut limiter = Limiter::new(Arc::clone(&control));
+
+        let mut block = vec![1.0; 64];
+        limiter.process(&mut block);
+        assert!(block.iter().all(|&s| s <= db_to_gain(-6.0) + 1e-6));
+
+        control.set_enabled(false);
+        let mut block = vec![1.5; 64];
+        limiter.process(&mut block);
+        assert!(block.iter().all(|&s| s == 1.5));
+        assert!(!control.is_engaged());
+    }
+
+    #[test]
+    fn test_quiet_signal_passes_untouched() {
+        let control = Arc::new(LimiterControl::new(true, DEFAULT_LIMITER_THRESHOLD_DB));
+        let mut limiter = Limiter::new(Arc::clone(&control));
+
+        let mut block = vec![0.25; 64];
+        limiter.process(&mut block);
+        assert!(block.iter().all(|&s| s == 0.25));
+        assert!(!control.is_engaged());
+    }
+
+    #[test]
+    fn test_oldest_voice_is_stolen_at_cap() {
+        let mut bus = BusState::new(Vec::new(), 120.0, Arc::new(Meters::new()));
+        let sine = full_scale_sine(1000);
+
+        bus.add_voice(Voice::new(Arc::clone(&sine), 0, 0.1), 2);
+        bus.add_voice(Voice::new(Arc::clone(&sine), 0, 0.2), 2);
+        bus.add_voice(Voice::new(Arc::clone(&sine), 0, 0.3), 2);
+
+        assert_eq!(bus.voices.len(), 2);
+        assert_eq!(bus.voices[0].gain, 0.2);
+    }
+
+    #[test]
+    fn test_choke_only_cuts_the_same_pad() {
+        let mut bus = BusState::new(Vec::new(), 120.0, Arc::new(Meters::new()));
+        let sine = full_scale_sine(1000);
+        let settings = PadSettings::default();
+
+        bus.add_voice(Voice::for_pad(Arc::clone(&sine), 0, 3, 1.0, &settings), 8);
+        bus.add_voice(Voice::for_pad(Arc::clone(&sine), 1, 3, 1.0, &settings), 8);
+        bus.add_voice(Voice::new(Arc::clone(&sine), 0, 1.0), 8);
+        bus.choke(0, 3);
+
+        assert_eq!(bus.voices.len(), 2);
+        assert!(bus.voices.iter().all(|voice| voice.group != 0 || voice.pad.is_none()));
+    }
+
+    #[test]
+    fn test_hard_left_pan_silences_right_channel() {
+        let control = Arc::new(LimiterControl::new(true, 0.0));
+        let mut bus = limited_bus(&control);
+        let settings = PadSettings { pan: -1.0, ..PadSettings::default() };
+        bus.add_voice(Voice::for_pad(full_scale_sine(100), 0, 0, 0.5, &settings), 32);
+
+        let mut block = vec![0.0; BLOCK_FRAMES * 2];
+        bus.render(&mut block);
+        assert!(block.iter().step_by(2).any(|&s| s != 0.0));
+        assert!(block.iter().skip(1).step_by(2).all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_voice_plays_at_the_samples_own_rate() {
+        // A 22050 Hz file decoded from disk keeps its rate, so it's read at half speed
+        let frames = vec![0.5; 100];
+        let wav = crate::render::encode_wav(&frames, 1, 22050).unwrap();
+        let sample = Arc::new(crate::sample::decode_sample(&wav).unwrap());
+        assert_eq!(sample.sample_rate, 22050);
+
+        let voice = Voice::new(sample, 0, 1.0);
+        assert_eq!(voice.rate, 0.5);
+
+        // ...and lasts twice as many bus frames as it has source frames
+        let mut bus = BusState::new(Vec::new(), 120.0, Arc::new(Meters::new()));
+        bus.add_voice(voice, 32);
+        let mut block = vec![0.0; BLOCK_FRAMES * 2];
+        bus.render(&mut block);
+        assert!(block[..199 * 2].iter().all(|&s| s != 0.0));
+        assert!(block[201 * 2..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_finished_voices_are_dropped() {
+        let control = Arc::new(LimiterControl::new(true, 0.0));
+        let mut bus = limited_bus(&control);
+        bus.add_voice(Voice::new(full_scale_sine(100), 0, 0.5), 32);
+        assert_eq!(bus.voices.len(), 1);
+
+        let mut block = vec![0.0; BLOCK_FRAMES * 2];
+        bus.render(&mut block);
+        assert_eq!(bus.voices.len(), 0);
+        // The tail of the block after the voice ended is silent
+        assert!(block[200..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_gain_ramp_reaches_target_without_overshoot() {
+        let mut ramp = GainRamp::new(0.0, 441);
+        ramp.set_target(0.8);
+
+        let mut previous = 0.0;
+        for _ in 0..441 {
+            let gain = ramp.next_gain();
+            assert!(gain >= previous && gain <= 0.8);
+            previous = gain;
+        }
+        assert!((ramp.next_gain() - 0.8).abs() < 1e-6);
+        assert_eq!(ramp.next_gain(), 0.8);
+
+        // Down to silence lands exactly on zero
+        ramp.set_target(0.0);
+        let gains: Vec<f32> = (0..442).map(|_| ramp.next_gain()).collect();
+        assert!(gains.iter().all(|&g| (0.0..=0.8).contains(&g)));
+        assert!(gains[0] > 0.7);
+        assert_eq!(gains[441], 0.0);
+    }
+
+    #[test]
+    fn test_gain_ramp_without_ramp_time_is_immediate() {
+        let mut ramp = GainRamp::new(1.0, 0);
+        ramp.set_target(0.25);
+        assert_eq!(ramp.next_gain(), 0.25);
+
+        ramp.set_ramp_frames(100);
+        ramp.jump_to(0.5);
+        assert_eq!(ramp.next_gain(), 0.5);
+    }
+
+    #[test]
+    fn test_group_gain_follows_playing_voice() {
+        let mut bus = BusState::new(Vec::new(), 120.0, Arc::new(Meters::new()));
+        bus.set_ramp_frames(ms_to_frames(DEFAULT_VOLUME_RAMP_MS));
+        bus.add_voice(Voice::new(full_scale_sine(BUS_SAMPLE_RATE as usize), 1, 1.0), 32);
+
+        let mut block = vec![0.0; BLOCK_FRAMES * 2];
+        bus.render(&mut block);
+        assert!(block.iter().any(|&s| s.abs() > 0.5));
+
+        // Muting the group fades the voice out instead of cutting it
+        let mut gains = [1.0; MAX_GROUPS];
+        gains[1] = 0.0;
+        bus.set_gains(1.0, &gains, true);
+        bus.render(&mut block);
+        assert!(block[2..].iter().any(|&s| s.abs() > 0.1));
+        for _ in 0..2 {
+            bus.render(&mut block);
+        }
+        assert!(block.iter().all(|&s| s == 0.0));
+    }
+
+}
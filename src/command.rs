@@ -1,42 +1,152 @@
-use crate::app::App;
+use crate::app::{App, PATTERN_EXPORT_PATH};
+use crate::pad_settings::PAD_SETTINGS_PATH;
+use crate::project::QUICK_SAVE_PATH;
+use crate::sequencer::Signature;
 
+/// Everything the app can be told to do, whether from a key, the mouse or OSC.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     // Transport
     TogglePlayback,
+    Stop,
     ToggleRecording,
     ClearPattern,
+    ResetPattern, // Clears and also restores the default length and signature
     
     // Navigation
     NextGroup,
     PrevGroup,
     NextPattern,
     PrevPattern,
+    TogglePanel,
+    ToggleOverview,
+    OpenGotoPattern,
+    GoToPattern(usize), // 0-based slot in the current group
+    OpenMorph,
+    StartMorph(usize, usize), // 0-based slot the current group morphs into, over this many bars
+    OpenSignature,
+    SetSignature(Signature, bool), // For the current pattern; true moves hits onto the new grid
+    TogglePatternMap,
+    TogglePadDetail, // Sample info and settings of the selected pad
+    CyclePadBank,    // Which 16 pads the pad keys play
+    CycleLayerMode,  // Stack, round-robin or random for the selected pad's layers
+    DuplicateToNewSlot,
+    CopyPattern,
+    PastePattern,        // Pads stay where they were
+    PastePatternCompact, // Rows move onto the pads that have samples
+    AppendToChain, // Adds the current pattern to the group's chain
+    ClearChain,
+    MovePatternMapCursor(i32),
+    SelectPatternMapSlot,
+    
+    // Text prompt
+    PromptInput(char),
+    PromptBackspace,
+    PromptSubmit,
+    PromptCancel,
+    SelectGroup(usize),
     
     // Tempo
     IncreaseTempo(i32),
     DecreaseTempo(i32),
+    AdjustTempoFine(f32),
+    SetTempo(f32),
+    AddTempoBreakpoint,
+    ClearTempoAutomation,
+    CapturePatternTempo, // The current pattern plays at the current tempo from now on
+    ClearPatternTempo,
     
     // Pad triggers
     TriggerPad(usize),
+    TriggerGroupPad(usize, usize), // From remote control; doesn't change the selection
+    
+    // Pattern sharing
+    ExportPattern,
+    ImportPattern,
+    SavePadSettings,
+    QuickSave,
+    QuickLoad,
+    
+    // Pattern preview
+    StartAudition,
+    StopAudition,
+    
+    // Step editing
+    CycleGridMode,
+    MoveStepCursor(i32),
+    MoveStepPage(i32),
+    IncreaseStepVelocity,
+    DecreaseStepVelocity,
+    IncreaseStepProbability,
+    DecreaseStepProbability,
+    ToggleStepMute,
+    CycleRatchet, // 1-4 plays of the hit under the cursor within its step
+    NudgeStep(i16), // Milliseconds later (+) or earlier (-)
+    CycleSwing,
+    CycleGroupClockDiv, // Half, normal or double time for the current group
+    ToggleStep(usize, usize), // (pad, step), from clicking the grid
+    ToggleAccent(usize),      // Step, from clicking the accent row
+    ToggleAccentAtCursor,
+    HumanizePad,     // The selected pad's row in the current pattern
+    HumanizePattern, // Every row in the current pattern
+    FillSelectedPad,  // Every step of the selected pad's row
+    ClearSelectedPad,
+    ThinPattern, // Hits off every 4th step go, then every 8th and 16th on repeats
+    OpenEuclidean, // Prompt for a Euclidean rhythm on the selected pad's row
+    Undo,
+    
+    // Sampling
+    ArmSampling,
+    ResamplePatternToPad,
+    ToggleOutputRecording, // Captures the live master output to a WAV under recordings/
+    FillEmptyPadsWithSynth, // Built-in drum sounds for DRUMS pads without a sample
+    NextKit, // Swaps the current group's pads for another kit folder's samples
+    PrevKit,
+    ImportFolderToGroup, // Prompt for a folder whose samples replace the current group's
+    ToggleTransposeMode, // Pad keys play the selected pad's sample up a semitone per pad
+    ToggleNoteRepeat, // A held pad key plays its pad on every step
+    AdjustTrimStart(f32), // For the selected pad, as a fraction of the sample
+    AdjustTrimEnd(f32),
     
     // Volume controls
     AdjustMasterVolume(f32),
     ToggleMasterMute,
+    OpenMasterGain, // Prompts for the master gain in dB
+    AdjustMasterWidth(f32),
     AdjustGroupVolume(usize, f32),
     ToggleGroupMute(usize),
+    ToggleGroupSolo(usize),
+    ToggleGroupGate(usize), // 8th-note trance gate on the group's output
+    ToggleRecordArm(usize), // Records pad hits into the group whichever group is shown
+    ToggleSelectedPadMute, // Only the selected pad in the current group
+    ClearAutomation, // Recorded mutes and volume moves in the current group's pattern
+    
+    // Send effects, for the current group
+    AdjustDelaySend(f32),
+    AdjustReverbSend(f32),
+    CycleReverbSize,
     
     // Application
+    Panic,
+    ReloadConfig,
+    ReinitAudio, // Reopens the audio output
     Quit,
 }
 
 impl Command {
     pub fn execute(&self, app: &mut App) -> Result<(), String> {
+        // Nearly every command changes something on screen, so don't bother telling them apart
+        app.ui_state.mark_dirty();
+        
         match self {
             Command::TogglePlayback => {
                 app.toggle_playback();
                 Ok(())
             }
+            Command::Stop => {
+                app.stop();
+                Ok(())
+            }
             Command::ToggleRecording => {
                 app.toggle_recording();
                 Ok(())
@@ -45,6 +155,10 @@ impl Command {
                 app.clear_pattern();
                 Ok(())
             }
+            Command::ResetPattern => {
+                app.reset_pattern();
+                Ok(())
+            }
             Command::NextGroup => {
                 app.next_group();
                 Ok(())
@@ -61,43 +175,403 @@ impl Command {
                 app.prev_pattern();
                 Ok(())
             }
+            Command::TogglePanel => {
+                app.toggle_panel();
+                Ok(())
+            }
+            Command::ToggleOverview => {
+                app.toggle_overview();
+                Ok(())
+            }
+            Command::OpenGotoPattern => {
+                app.open_goto_pattern();
+                Ok(())
+            }
+            Command::GoToPattern(pattern) => {
+                match app.go_to_pattern(*pattern) {
+                    Ok(message) | Err(message) => app.ui_state.notify(message),
+                }
+                Ok(())
+            }
+            Command::OpenMorph => {
+                app.open_morph();
+                Ok(())
+            }
+            Command::StartMorph(pattern, bars) => {
+                match app.start_morph(*pattern, *bars) {
+                    Ok(message) | Err(message) => app.ui_state.notify(message),
+                }
+                Ok(())
+            }
+            Command::OpenSignature => {
+                app.open_signature();
+                Ok(())
+            }
+            Command::SetSignature(signature, rescale) => {
+                let message = app.set_signature(*signature, *rescale);
+                app.ui_state.notify(message);
+                Ok(())
+            }
+            Command::TogglePatternMap => {
+                app.toggle_pattern_map();
+                Ok(())
+            }
+            Command::TogglePadDetail => {
+                app.toggle_pad_detail();
+                Ok(())
+            }
+            Command::CyclePadBank => {
+                app.cycle_pad_bank();
+                Ok(())
+            }
+            Command::CycleLayerMode => {
+                app.cycle_layer_mode();
+                Ok(())
+            }
+            Command::CopyPattern => {
+                app.copy_pattern();
+                Ok(())
+            }
+            Command::PastePattern => {
+                app.paste_pattern(false);
+                Ok(())
+            }
+            Command::PastePatternCompact => {
+                app.paste_pattern(true);
+                Ok(())
+            }
+            Command::DuplicateToNewSlot => {
+                app.duplicate_to_new_slot();
+                Ok(())
+            }
+            Command::AppendToChain => {
+                app.append_to_chain();
+                Ok(())
+            }
+            Command::ClearChain => {
+                app.clear_chain();
+                Ok(())
+            }
+            Command::MovePatternMapCursor(delta) => {
+                app.move_pattern_map_cursor(*delta);
+                Ok(())
+            }
+            Command::SelectPatternMapSlot => {
+                app.select_pattern_map_slot();
+                Ok(())
+            }
+            Command::PromptInput(c) => {
+                app.prompt_input(*c);
+                Ok(())
+            }
+            Command::PromptBackspace => {
+                app.prompt_backspace();
+                Ok(())
+            }
+            Command::PromptSubmit => {
+                app.submit_prompt();
+                Ok(())
+            }
+            Command::PromptCancel => {
+                app.cancel_prompt();
+                Ok(())
+            }
+            Command::SelectGroup(group) => {
+                if *group >= app.group_count() {
+                    return Err(format!("Invalid group {}", group));
+                }
+                app.select_group(*group);
+                Ok(())
+            }
             Command::IncreaseTempo(amount) => {
-                app.adjust_tempo(*amount);
+                app.adjust_tempo(*amount as f32);
                 Ok(())
             }
             Command::DecreaseTempo(amount) => {
-                app.adjust_tempo(-amount);
+                app.adjust_tempo(-*amount as f32);
+                Ok(())
+            }
+            Command::AdjustTempoFine(delta) => {
+                app.adjust_tempo(*delta);
+                Ok(())
+            }
+            Command::SetTempo(bpm) => {
+                app.set_tempo(*bpm);
+                Ok(())
+            }
+            Command::AddTempoBreakpoint => {
+                app.add_tempo_breakpoint();
+                Ok(())
+            }
+            Command::ClearTempoAutomation => {
+                app.clear_tempo_automation();
+                Ok(())
+            }
+            Command::CapturePatternTempo => {
+                app.capture_pattern_tempo();
+                Ok(())
+            }
+            Command::ClearPatternTempo => {
+                app.clear_pattern_tempo();
                 Ok(())
             }
             Command::TriggerPad(pad) => {
-                if *pad >= 16 {
+                if *pad >= app.pad_count() {
                     return Err(format!("Invalid pad index: {}", pad));
                 }
                 app.trigger_pad(*pad);
                 Ok(())
             }
+            Command::TriggerGroupPad(group, pad) => {
+                if *group >= app.group_count() || *pad >= app.pad_count() {
+                    return Err(format!("Invalid pad {}/{}", group, pad));
+                }
+                app.trigger_group_pad(*group, *pad);
+                Ok(())
+            }
+            Command::ExportPattern => {
+                app.export_pattern(PATTERN_EXPORT_PATH);
+                Ok(())
+            }
+            Command::ImportPattern => {
+                app.import_pattern(PATTERN_EXPORT_PATH);
+                Ok(())
+            }
+            Command::SavePadSettings => {
+                app.save_pad_settings(PAD_SETTINGS_PATH);
+                Ok(())
+            }
+            Command::QuickSave => {
+                app.save_project(QUICK_SAVE_PATH);
+                Ok(())
+            }
+            Command::QuickLoad => {
+                app.load_project(QUICK_SAVE_PATH);
+                Ok(())
+            }
+            Command::StartAudition => {
+                app.start_audition();
+                Ok(())
+            }
+            Command::StopAudition => {
+                app.stop_audition();
+                Ok(())
+            }
+            Command::CycleGridMode => {
+                app.cycle_grid_mode();
+                Ok(())
+            }
+            Command::MoveStepCursor(delta) => {
+                app.move_step_cursor(*delta);
+                Ok(())
+            }
+            Command::MoveStepPage(delta) => {
+                app.move_step_page(*delta);
+                Ok(())
+            }
+            Command::IncreaseStepVelocity => {
+                app.adjust_step_velocity(1);
+                Ok(())
+            }
+            Command::DecreaseStepVelocity => {
+                app.adjust_step_velocity(-1);
+                Ok(())
+            }
+            Command::IncreaseStepProbability => {
+                app.adjust_step_probability(1);
+                Ok(())
+            }
+            Command::DecreaseStepProbability => {
+                app.adjust_step_probability(-1);
+                Ok(())
+            }
+            Command::ToggleAccent(step) => {
+                app.toggle_accent(*step);
+                Ok(())
+            }
+            Command::ToggleAccentAtCursor => {
+                app.toggle_accent_at_cursor();
+                Ok(())
+            }
+            Command::HumanizePad => {
+                app.humanize_pad();
+                Ok(())
+            }
+            Command::HumanizePattern => {
+                app.humanize_pattern();
+                Ok(())
+            }
+            Command::FillSelectedPad => {
+                app.fill_selected_pad();
+                Ok(())
+            }
+            Command::ClearSelectedPad => {
+                app.clear_selected_pad();
+                Ok(())
+            }
+            Command::ThinPattern => {
+                app.thin_pattern();
+                Ok(())
+            }
+            Command::OpenEuclidean => {
+                app.open_euclidean();
+                Ok(())
+            }
+            Command::Undo => {
+                app.undo();
+                Ok(())
+            }
+            Command::CycleGroupClockDiv => {
+                app.cycle_clock_division();
+                Ok(())
+            }
+            Command::CycleSwing => {
+                app.cycle_swing();
+                Ok(())
+            }
+            Command::NudgeStep(delta_ms) => {
+                app.nudge_step(*delta_ms);
+                Ok(())
+            }
+            Command::CycleRatchet => {
+                app.cycle_ratchet();
+                Ok(())
+            }
+            Command::ToggleStepMute => {
+                app.toggle_step_mute();
+                Ok(())
+            }
+            Command::ToggleStep(pad, step) => {
+                if *pad >= app.pad_count() || *step >= app.get_current_pattern_length() {
+                    return Err(format!("Invalid step {}/{}", pad, step));
+                }
+                app.toggle_step(*pad, *step);
+                Ok(())
+            }
+            Command::ArmSampling => {
+                app.toggle_sampling();
+                Ok(())
+            }
+            Command::ToggleOutputRecording => {
+                app.toggle_output_recording();
+                Ok(())
+            }
+            Command::ResamplePatternToPad => {
+                app.resample_pattern_to_pad();
+                Ok(())
+            }
+            Command::FillEmptyPadsWithSynth => {
+                app.fill_empty_pads_with_synth();
+                Ok(())
+            }
+            Command::NextKit => {
+                app.switch_kit(1);
+                Ok(())
+            }
+            Command::PrevKit => {
+                app.switch_kit(-1);
+                Ok(())
+            }
+            Command::ImportFolderToGroup => {
+                app.open_import_folder();
+                Ok(())
+            }
+            Command::ToggleTransposeMode => {
+                app.toggle_transpose_mode();
+                Ok(())
+            }
+            Command::ToggleNoteRepeat => {
+                app.toggle_note_repeat();
+                Ok(())
+            }
+            Command::AdjustTrimStart(delta) => {
+                app.adjust_trim_start(*delta);
+                Ok(())
+            }
+            Command::AdjustTrimEnd(delta) => {
+                app.adjust_trim_end(*delta);
+                Ok(())
+            }
             Command::AdjustMasterVolume(delta) => {
                 app.adjust_master_volume(*delta);
                 Ok(())
             }
+            Command::OpenMasterGain => {
+                app.open_master_gain();
+                Ok(())
+            }
             Command::ToggleMasterMute => {
                 app.toggle_master_mute();
                 Ok(())
             }
+            Command::AdjustMasterWidth(delta) => {
+                app.adjust_master_width(*delta);
+                Ok(())
+            }
             Command::AdjustGroupVolume(group, delta) => {
-                if *group >= 4 {
+                if *group >= app.group_count() {
                     return Err(format!("Invalid group index: {}", group));
                 }
                 app.adjust_group_volume(*group, *delta);
                 Ok(())
             }
             Command::ToggleGroupMute(group) => {
-                if *group >= 4 {
+                if *group >= app.group_count() {
                     return Err(format!("Invalid group index: {}", group));
                 }
                 app.toggle_group_mute(*group);
                 Ok(())
             }
+            Command::ToggleGroupSolo(group) => {
+                if *group >= app.group_count() {
+                    return Err(format!("Invalid group index: {}", group));
+                }
+                app.toggle_group_solo(*group);
+                Ok(())
+            }
+            Command::ToggleGroupGate(group) => {
+                if *group >= app.group_count() {
+                    return Err(format!("Invalid group index: {}", group));
+                }
+                app.toggle_group_gate(*group);
+                Ok(())
+            }
+            Command::ToggleRecordArm(group) => {
+                if *group >= app.group_count() {
+                    return Err(format!("Invalid group index: {}", group));
+                }
+                app.toggle_record_arm(*group);
+                Ok(())
+            }
+            Command::ClearAutomation => {
+                app.clear_automation();
+                Ok(())
+            }
+            Command::ToggleSelectedPadMute => {
+                app.toggle_selected_pad_mute();
+                Ok(())
+            }
+            Command::AdjustDelaySend(delta) => {
+                app.adjust_delay_send(*delta);
+                Ok(())
+            }
+            Command::AdjustReverbSend(delta) => {
+                app.adjust_reverb_send(*delta);
+                Ok(())
+            }
+            Command::CycleReverbSize => {
+                app.cycle_reverb_size();
+                Ok(())
+            }
+            Command::Panic => {
+                app.panic();
+                Ok(())
+            }
+            Command::ReloadConfig => Ok(()), // Handled by the main loop, which owns the bindings
+            Command::ReinitAudio => {
+                app.reinit_audio();
+                Ok(())
+            }
             Command::Quit => Ok(()), // Handled by the main loop
         }
     }
@@ -176,10 +650,22 @@ mod tests {
         let initial_tempo = app.get_tempo();
         let cmd = Command::IncreaseTempo(10);
         assert!(cmd.execute(&mut app).is_ok());
-        assert_eq!(app.get_tempo(), initial_tempo + 10);
+        assert_eq!(app.get_tempo(), initial_tempo + 10.0);
         
         let cmd = Command::DecreaseTempo(5);
         assert!(cmd.execute(&mut app).is_ok());
-        assert_eq!(app.get_tempo(), initial_tempo + 5);
+        assert_eq!(app.get_tempo(), initial_tempo + 5.0);
+        
+        // Fine steps land on exact tenths however many are taken
+        for _ in 0..5 {
+            assert!(Command::AdjustTempoFine(-0.1).execute(&mut app).is_ok());
+        }
+        assert_eq!(app.get_tempo(), initial_tempo + 4.5);
+        
+        // Absolute tempo from remote control is kept in range
+        assert!(Command::SetTempo(500.0).execute(&mut app).is_ok());
+        assert_eq!(app.get_tempo(), 300.0);
+        assert!(Command::SetTempo(87.5).execute(&mut app).is_ok());
+        assert_eq!(app.get_tempo(), 87.5);
     }
 }
\ No newline at end of file
@@ -1,13 +1,224 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crossterm::event::KeyCode;
+use crate::app::{DEFAULT_ACCENT_BOOST, DEFAULT_QUANTIZE_STRENGTH};
+use crate::bus::{DEFAULT_LIMITER_THRESHOLD_DB, DEFAULT_VOLUME_RAMP_MS};
 use crate::error::{AppError, Result};
+use crate::project::DEFAULT_AUTOSAVE_SECS;
+use crate::sequencer::{DEFAULT_HUMANIZE, DEFAULT_MORPH_BARS};
+use crate::input::KeyBinding;
+use crate::midi::{PadNote, DEFAULT_NOTE_GATE_MS};
+use crate::sample::{GroupDef, Normalize, DEFAULT_NORMALIZE_TARGET_DB, DEFAULT_STREAM_THRESHOLD_MB};
+use crate::sequencer::{DEFAULT_GROUPS, MAX_GROUPS, MAX_PADS, PADS_PER_GROUP, PAD_COUNTS};
+use crate::state::{DEFAULT_FLASH_DURATION, MAX_TEMPO, MIN_TEMPO};
 
+/// Settings read from `config.toml`; every section falls back to its defaults.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub key_bindings: KeyBindingsConfig,
     pub audio: AudioConfig,
     pub ui: UIConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub midi: MidiConfig,
+    #[serde(default)]
+    pub startup: StartupConfig,
+    #[serde(default)]
+    pub project: ProjectConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub samples: SamplesConfig,
+    #[serde(default)]
+    pub groups: Vec<GroupConfig>, // [[groups]] entries, in group order
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>, // Paths of keys in the file that no setting reads, e.g. a typo
+}
+
+// Anything an entry leaves out keeps the built-in name, folder or color
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroupConfig {
+    pub name: Option<String>,
+    pub dir: Option<String>,   // Folder under samples/ scanned when there's no samples/config.json; defaults to the lowercased name
+    pub color: Option<String>, // Hex or ANSI name, like the theme colors
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayoutConfig {
+    #[serde(default = "default_groups")]
+    pub groups: usize, // Sample groups, each with its own patterns and mixer channel
+    #[serde(default = "default_pads_per_group")]
+    pub pads_per_group: usize, // 16 (a 4x4 grid) or 32 (8x4, played as two banks of keys)
+}
+
+fn default_groups() -> usize {
+    DEFAULT_GROUPS
+}
+
+fn default_pads_per_group() -> usize {
+    PADS_PER_GROUP
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self { groups: default_groups(), pads_per_group: default_pads_per_group() }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SamplesConfig {
+    #[serde(default)]
+    pub normalize: Normalize, // samples/config.json mappings can turn it on or off per file
+    #[serde(default = "default_normalize_target_db")]
+    pub normalize_target_db: f32, // Peak level normalized samples are brought to, -24 to 0
+    #[serde(default = "default_stream_threshold_mb")]
+    pub stream_threshold_mb: u32, // Bigger files play from disk instead of memory, 0 holds them all
+}
+
+fn default_normalize_target_db() -> f32 {
+    DEFAULT_NORMALIZE_TARGET_DB
+}
+
+fn default_stream_threshold_mb() -> u32 {
+    DEFAULT_STREAM_THRESHOLD_MB
+}
+
+impl Default for SamplesConfig {
+    fn default() -> Self {
+        Self {
+            normalize: Normalize::default(),
+            normalize_target_db: default_normalize_target_db(),
+            stream_threshold_mb: default_stream_threshold_mb(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StartupConfig {
+    pub autoload_project: Option<String>, // Project file loaded at startup when it exists
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(default = "default_autosave_secs")]
+    pub autosave_secs: u64, // Seconds between crash recovery snapshots, 0 turns them off
+}
+
+fn default_autosave_secs() -> u64 {
+    DEFAULT_AUTOSAVE_SECS
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self { autosave_secs: default_autosave_secs() }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MidiConfig {
+    #[serde(default)]
+    pub clock_out: bool,      // Send MIDI clock and Start/Stop while playing
+    #[serde(default)]
+    pub clock: ClockSource,
+    pub port: Option<String>, // All or part of the port name; None picks the first port
+    #[serde(default)]
+    pub output: MidiOutputConfig,
+}
+
+impl MidiConfig {
+    pub fn sends_clock(&self) -> bool {
+        self.clock_out || self.clock == ClockSource::Master
+    }
+}
+
+// Pads that play notes on external gear
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MidiOutputConfig {
+    #[serde(default = "default_gate_ms")]
+    pub gate_ms: u64, // How long each note is held before its Note Off
+    #[serde(default)]
+    pub pads: Vec<PadNoteConfig>,
+}
+
+impl Default for MidiOutputConfig {
+    fn default() -> Self {
+        Self { gate_ms: default_gate_ms(), pads: Vec::new() }
+    }
+}
+
+fn default_gate_ms() -> u64 {
+    DEFAULT_NOTE_GATE_MS
+}
+
+// Groups, pads and channels are numbered from 1, as on screen and on the gear
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PadNoteConfig {
+    pub group: usize,
+    pub pad: usize,
+    pub channel: u8,
+    pub note: u8,
+    #[serde(default)]
+    pub midi_only: bool,
+}
+
+impl PadNoteConfig {
+    fn problem(&self) -> Option<String> {
+        if !(1..=MAX_GROUPS).contains(&self.group) {
+            Some(format!("group must be from 1 to {}, found {}", MAX_GROUPS, self.group))
+        } else if !(1..=MAX_PADS).contains(&self.pad) {
+            Some(format!("pad must be from 1 to {}, found {}", MAX_PADS, self.pad))
+        } else if !(1..=16).contains(&self.channel) {
+            Some(format!("channel must be from 1 to 16, found {}", self.channel))
+        } else if self.note > 127 {
+            Some(format!("note must be from 0 to 127, found {}", self.note))
+        } else {
+            None
+        }
+    }
+}
+
+impl MidiOutputConfig {
+    // Keyed by 0-based (group, pad); entries validate() complains about are left out
+    pub fn pad_notes(&self) -> HashMap<(usize, usize), PadNote> {
+        self.pads
+            .iter()
+            .filter(|pad| pad.problem().is_none())
+            .map(|pad| {
+                let note = PadNote { channel: pad.channel - 1, note: pad.note, midi_only: pad.midi_only };
+                ((pad.group - 1, pad.pad - 1), note)
+            })
+            .collect()
+    }
+}
+
+// Where the sequencer's steps come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockSource {
+    #[default]
+    Internal,
+    Slave,  // Follow clock and Start/Stop/Continue from the MIDI input
+    Master, // Send clock and transport, the same as clock_out = true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    pub osc_port: Option<u16>, // UDP port for OSC remote control, off when unset
+    #[serde(default = "default_osc_bind")]
+    pub osc_bind: String, // Address the OSC listener binds; 0.0.0.0 accepts other machines
+}
+
+fn default_osc_bind() -> String {
+    "127.0.0.1".to_string()
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self { osc_port: None, osc_bind: default_osc_bind() }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,14 +226,116 @@ pub struct KeyBindingsConfig {
     pub transport: TransportKeys,
     pub navigation: NavigationKeys,
     pub volume: VolumeKeys,
+    #[serde(default)]
+    pub step_edit: StepEditKeys,
+    #[serde(default)]
+    pub sends: SendKeys,
+    #[serde(default)]
+    pub trim: TrimKeys,
     pub pads: HashMap<String, usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransportKeys {
-    pub play_stop: String,
+    pub play_stop: String, // Play/pause; Shift + this key stops
+    #[serde(default = "default_stop_key")]
+    pub stop: String,
     pub record: String,
     pub clear: String,
+    #[serde(default = "default_reset_pattern_key")]
+    pub reset_pattern: String,
+    #[serde(default = "default_audition_start_key")]
+    pub audition_start: String,
+    #[serde(default = "default_audition_stop_key")]
+    pub audition_stop: String,
+    #[serde(default = "default_sample_key")]
+    pub sample: String,
+    #[serde(default = "default_resample_key")]
+    pub resample: String,
+    #[serde(default = "default_synth_kit_key")]
+    pub synth_kit: String,
+    #[serde(default = "default_record_output_key")]
+    pub record_output: String,
+    #[serde(default = "default_panic_key")]
+    pub panic: String,
+    #[serde(default = "default_export_pattern_key")]
+    pub export_pattern: String,
+    #[serde(default = "default_import_pattern_key")]
+    pub import_pattern: String,
+    #[serde(default = "default_save_pad_settings_key")]
+    pub save_pad_settings: String,
+    #[serde(default = "default_quick_save_key")]
+    pub quick_save: String,
+    #[serde(default = "default_quick_load_key")]
+    pub quick_load: String,
+    #[serde(default = "default_reload_config_key")]
+    pub reload_config: String,
+    #[serde(default = "default_reinit_audio_key")]
+    pub reinit_audio: String, // Reopens the audio output, for a device plugged back in
+}
+
+fn default_stop_key() -> String {
+    "s".to_string()
+}
+
+fn default_reset_pattern_key() -> String {
+    "Ctrl+d".to_string()
+}
+
+fn default_audition_start_key() -> String {
+    "a".to_string()
+}
+
+fn default_audition_stop_key() -> String {
+    "A".to_string()
+}
+
+fn default_sample_key() -> String {
+    "S".to_string()
+}
+
+fn default_resample_key() -> String {
+    "B".to_string()
+}
+
+fn default_synth_kit_key() -> String {
+    "Ctrl+k".to_string()
+}
+
+fn default_record_output_key() -> String {
+    "W".to_string()
+}
+
+fn default_panic_key() -> String {
+    "Ctrl+.".to_string()
+}
+
+fn default_export_pattern_key() -> String {
+    "x".to_string()
+}
+
+fn default_import_pattern_key() -> String {
+    "X".to_string()
+}
+
+fn default_save_pad_settings_key() -> String {
+    "Ctrl+p".to_string()
+}
+
+fn default_quick_save_key() -> String {
+    "Ctrl+s".to_string()
+}
+
+fn default_quick_load_key() -> String {
+    "Ctrl+o".to_string()
+}
+
+fn default_reload_config_key() -> String {
+    "Ctrl+r".to_string()
+}
+
+fn default_reinit_audio_key() -> String {
+    "Ctrl+a".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +346,192 @@ pub struct NavigationKeys {
     pub prev_pattern: String,
     pub tempo_up: String,
     pub tempo_down: String,
+    #[serde(default = "default_tempo_step")]
+    pub tempo_step: i32, // BPM per press of tempo_up/tempo_down, 1 to 20
+    #[serde(default = "default_tempo_bpm_up_key")]
+    pub tempo_bpm_up: String, // One BPM at a time
+    #[serde(default = "default_tempo_bpm_down_key")]
+    pub tempo_bpm_down: String,
+    #[serde(default = "default_tempo_fine_up_key")]
+    pub tempo_fine_up: String,
+    #[serde(default = "default_tempo_fine_down_key")]
+    pub tempo_fine_down: String,
+    #[serde(default = "default_tempo_breakpoint_key")]
+    pub tempo_breakpoint: String,
+    #[serde(default = "default_clear_tempo_automation_key")]
+    pub clear_tempo_automation: String,
+    #[serde(default = "default_capture_pattern_tempo_key")]
+    pub capture_pattern_tempo: String, // Stores the tempo in the current pattern
+    #[serde(default = "default_clear_pattern_tempo_key")]
+    pub clear_pattern_tempo: String,
+    #[serde(default = "default_toggle_panel_key")]
+    pub toggle_panel: String,
+    #[serde(default = "default_toggle_overview_key")]
+    pub toggle_overview: String,
+    #[serde(default = "default_goto_pattern_key")]
+    pub goto_pattern: String,
+    #[serde(default = "default_morph_key")]
+    pub morph: String, // Asks for a pattern to morph the current group into
+    #[serde(default = "default_signature_key")]
+    pub signature: String, // Asks for the current pattern's beats per bar and steps per beat
+    #[serde(default = "default_pattern_map_key")]
+    pub pattern_map: String,
+    #[serde(default = "default_pad_detail_key")]
+    pub pad_detail: String,
+    #[serde(default = "default_pad_bank_key")]
+    pub pad_bank: String, // With 32 pads, moves the pad keys to the other 16
+    #[serde(default = "default_layer_mode_key")]
+    pub layer_mode: String,
+    #[serde(default = "default_copy_pattern_key")]
+    pub copy_pattern: String,
+    #[serde(default = "default_paste_pattern_key")]
+    pub paste_pattern: String,
+    #[serde(default = "default_paste_pattern_compact_key")]
+    pub paste_pattern_compact: String,
+    #[serde(default = "default_duplicate_pattern_key")]
+    pub duplicate_pattern: String,
+    #[serde(default = "default_chain_append_key")]
+    pub chain_append: String,
+    #[serde(default = "default_chain_clear_key")]
+    pub chain_clear: String,
+    #[serde(default = "default_next_kit_key")]
+    pub next_kit: String,
+    #[serde(default = "default_prev_kit_key")]
+    pub prev_kit: String,
+    #[serde(default = "default_import_folder_key")]
+    pub import_folder: String,
+    #[serde(default = "default_transpose_mode_key")]
+    pub transpose_mode: String,
+    #[serde(default = "default_note_repeat_key")]
+    pub note_repeat: String,
+}
+
+fn default_tempo_step() -> i32 {
+    DEFAULT_TEMPO_STEP
+}
+
+fn default_tempo_bpm_up_key() -> String {
+    "Shift+Up".to_string()
+}
+
+fn default_tempo_bpm_down_key() -> String {
+    "Shift+Down".to_string()
+}
+
+fn default_tempo_fine_up_key() -> String {
+    "Ctrl+Up".to_string()
+}
+
+fn default_tempo_fine_down_key() -> String {
+    "Ctrl+Down".to_string()
+}
+
+fn default_tempo_breakpoint_key() -> String {
+    "t".to_string()
+}
+
+fn default_clear_tempo_automation_key() -> String {
+    "T".to_string()
+}
+
+fn default_capture_pattern_tempo_key() -> String {
+    "U".to_string()
+}
+
+fn default_clear_pattern_tempo_key() -> String {
+    "Ctrl+u".to_string()
+}
+
+fn default_toggle_panel_key() -> String {
+    "w".to_string()
+}
+
+fn default_toggle_overview_key() -> String {
+    "v".to_string()
+}
+
+fn default_goto_pattern_key() -> String {
+    "g".to_string()
+}
+
+fn default_morph_key() -> String {
+    "G".to_string()
+}
+
+fn default_signature_key() -> String {
+    "E".to_string()
+}
+
+fn default_pattern_map_key() -> String {
+    "P".to_string()
+}
+
+fn default_pad_detail_key() -> String {
+    "I".to_string()
+}
+
+fn default_pad_bank_key() -> String {
+    "O".to_string()
+}
+
+fn default_layer_mode_key() -> String {
+    "L".to_string()
+}
+
+fn default_copy_pattern_key() -> String {
+    "C".to_string()
+}
+
+fn default_paste_pattern_key() -> String {
+    "V".to_string()
+}
+
+fn default_paste_pattern_compact_key() -> String {
+    "Ctrl+v".to_string()
+}
+
+fn default_duplicate_pattern_key() -> String {
+    "D".to_string()
+}
+
+fn default_chain_append_key() -> String {
+    "b".to_string()
+}
+
+fn default_chain_clear_key() -> String {
+    "Ctrl+b".to_string()
+}
+
+fn default_next_kit_key() -> String {
+    "K".to_string()
+}
+
+fn default_prev_kit_key() -> String {
+    "J".to_string()
+}
+
+fn default_import_folder_key() -> String {
+    "F".to_string()
+}
+
+fn default_transpose_mode_key() -> String {
+    "Ctrl+n".to_string()
+}
+
+fn default_note_repeat_key() -> String {
+    "Ctrl+l".to_string()
+}
+
+fn default_master_gain_key() -> String {
+    "Ctrl+g".to_string()
+}
+
+fn default_width_up_key() -> String {
+    "+".to_string()
+}
+
+fn default_width_down_key() -> String {
+    "_".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,22 +539,342 @@ pub struct VolumeKeys {
     pub master_up: String,
     pub master_down: String,
     pub master_mute: String,
+    #[serde(default = "default_master_gain_key")]
+    pub master_gain: String,
+    #[serde(default = "default_width_up_key")]
+    pub width_up: String,
+    #[serde(default = "default_width_down_key")]
+    pub width_down: String,
     pub group_up: Vec<String>,
     pub group_down: Vec<String>,
     pub group_mute: Vec<String>,
+    #[serde(default = "default_group_solo_keys")]
+    pub group_solo: Vec<String>,
+    #[serde(default = "default_group_gate_keys")]
+    pub group_gate: Vec<String>,
+    #[serde(default = "default_group_record_arm_keys")]
+    pub group_record_arm: Vec<String>,
+    #[serde(default = "default_pad_mute_key")]
+    pub pad_mute: String, // Mutes the selected pad
+    #[serde(default = "default_clear_automation_key")]
+    pub clear_automation: String,
+}
+
+fn default_pad_mute_key() -> String {
+    "N".to_string()
+}
+
+fn default_clear_automation_key() -> String {
+    "Ctrl+w".to_string()
+}
+
+fn default_group_solo_keys() -> Vec<String> {
+    ["F5", "F6", "F7", "F8"].map(String::from).to_vec()
+}
+
+fn default_group_gate_keys() -> Vec<String> {
+    ["Shift+F1", "Shift+F2", "Shift+F3", "Shift+F4"].map(String::from).to_vec()
+}
+
+fn default_group_record_arm_keys() -> Vec<String> {
+    ["Ctrl+F1", "Ctrl+F2", "Ctrl+F3", "Ctrl+F4"].map(String::from).to_vec()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepEditKeys {
+    pub grid_mode: String,
+    pub cursor_left: String,
+    pub cursor_right: String,
+    pub velocity_up: String,
+    pub velocity_down: String,
+    pub probability_up: String,
+    pub probability_down: String,
+    #[serde(default = "default_step_mute_key")]
+    pub step_mute: String,
+    #[serde(default = "default_page_prev_key")]
+    pub page_prev: String,
+    #[serde(default = "default_page_next_key")]
+    pub page_next: String,
+    #[serde(default = "default_nudge_earlier_key")]
+    pub nudge_earlier: String,
+    #[serde(default = "default_nudge_later_key")]
+    pub nudge_later: String,
+    #[serde(default = "default_swing_key")]
+    pub swing: String,
+    #[serde(default = "default_clock_division_key")]
+    pub clock_division: String,
+    #[serde(default = "default_accent_key")]
+    pub accent: String,
+    #[serde(default = "default_humanize_pad_key")]
+    pub humanize_pad: String,
+    #[serde(default = "default_humanize_pattern_key")]
+    pub humanize_pattern: String,
+    #[serde(default = "default_undo_key")]
+    pub undo: String,
+    #[serde(default = "default_fill_pad_key")]
+    pub fill_pad: String,
+    #[serde(default = "default_clear_pad_key")]
+    pub clear_pad: String,
+    #[serde(default = "default_thin_pattern_key")]
+    pub thin_pattern: String,
+    #[serde(default = "default_euclidean_key")]
+    pub euclidean: String,
+    #[serde(default = "default_ratchet_key")]
+    pub ratchet: String,
+}
+
+fn default_page_prev_key() -> String {
+    "PageUp".to_string()
+}
+
+fn default_page_next_key() -> String {
+    "PageDown".to_string()
+}
+
+fn default_step_mute_key() -> String {
+    "n".to_string()
+}
+
+fn default_nudge_earlier_key() -> String {
+    "h".to_string()
+}
+
+fn default_nudge_later_key() -> String {
+    "H".to_string()
+}
+
+fn default_swing_key() -> String {
+    "q".to_string()
+}
+
+fn default_clock_division_key() -> String {
+    "d".to_string()
+}
+
+fn default_accent_key() -> String {
+    "f".to_string()
+}
+
+fn default_humanize_pad_key() -> String {
+    "y".to_string()
+}
+
+fn default_humanize_pattern_key() -> String {
+    "Y".to_string()
+}
+
+fn default_undo_key() -> String {
+    "Ctrl+z".to_string()
+}
+
+fn default_fill_pad_key() -> String {
+    "Ctrl+f".to_string()
+}
+
+fn default_clear_pad_key() -> String {
+    "Ctrl+x".to_string()
+}
+
+fn default_thin_pattern_key() -> String {
+    "Ctrl+t".to_string()
+}
+
+fn default_euclidean_key() -> String {
+    "Ctrl+e".to_string()
+}
+
+fn default_ratchet_key() -> String {
+    "R".to_string()
+}
+
+impl Default for StepEditKeys {
+    fn default() -> Self {
+        Self {
+            grid_mode: "e".to_string(),
+            cursor_left: "[".to_string(),
+            cursor_right: "]".to_string(),
+            velocity_up: "}".to_string(),
+            velocity_down: "{".to_string(),
+            probability_up: ")".to_string(),
+            probability_down: "(".to_string(),
+            step_mute: default_step_mute_key(),
+            page_prev: default_page_prev_key(),
+            page_next: default_page_next_key(),
+            nudge_earlier: default_nudge_earlier_key(),
+            nudge_later: default_nudge_later_key(),
+            swing: default_swing_key(),
+            clock_division: default_clock_division_key(),
+            accent: default_accent_key(),
+            humanize_pad: default_humanize_pad_key(),
+            humanize_pattern: default_humanize_pattern_key(),
+            undo: default_undo_key(),
+            fill_pad: default_fill_pad_key(),
+            clear_pad: default_clear_pad_key(),
+            thin_pattern: default_thin_pattern_key(),
+            euclidean: default_euclidean_key(),
+            ratchet: default_ratchet_key(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendKeys {
+    pub delay_up: String,
+    pub delay_down: String,
+    pub reverb_up: String,
+    pub reverb_down: String,
+    #[serde(default = "default_reverb_size_key")]
+    pub reverb_size: String,
+}
+
+impl Default for SendKeys {
+    fn default() -> Self {
+        Self {
+            delay_up: "5".to_string(),
+            delay_down: "%".to_string(),
+            reverb_up: "6".to_string(),
+            reverb_down: "^".to_string(),
+            reverb_size: default_reverb_size_key(),
+        }
+    }
+}
+
+fn default_reverb_size_key() -> String {
+    "&".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrimKeys {
+    pub start_earlier: String,
+    pub start_later: String,
+    pub end_earlier: String,
+    pub end_later: String,
+}
+
+impl Default for TrimKeys {
+    fn default() -> Self {
+        Self {
+            start_earlier: "<".to_string(),
+            start_later: ">".to_string(),
+            end_earlier: "z".to_string(),
+            end_later: "Z".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AudioConfig {
-    pub default_tempo: u32,
+    pub default_tempo: f32, // Whole numbers from older configs still load
     pub sample_rate: u32,
     pub buffer_size: usize,
+    #[serde(default)]
+    pub input_device: Option<String>, // Device name for sampling, default input if unset
+    #[serde(default)]
+    pub output_device: Option<String>, // Device name to play on, default output if unset or missing
+    #[serde(default = "default_limiter")]
+    pub limiter: bool,
+    #[serde(default = "default_limiter_threshold_db")]
+    pub limiter_threshold_db: f32,
+    #[serde(default = "default_volume_ramp_ms")]
+    pub volume_ramp_ms: f32, // Fade time for volume and mute changes
+    #[serde(default = "default_quantize_strength")]
+    pub quantize_strength: f32, // 0.0 records hits where the playhead was, 1.0 snaps to the nearest step
+    #[serde(default = "default_accent_boost")]
+    pub accent_boost: f32, // Gain added to hits on accented steps, capped at full level
+    #[serde(default = "default_humanize_velocity")]
+    pub humanize_velocity: f32, // Largest humanize change to a hit's velocity, as a fraction of it
+    #[serde(default = "default_humanize_timing_ms")]
+    pub humanize_timing_ms: i16, // Largest humanize timing shift either way
+    #[serde(default = "default_morph_bars")]
+    pub morph_bars: usize, // Bars a morph into another pattern takes
+    #[serde(default)]
+    pub startup_test: bool, // Plays a loaded sample as the app starts, like --audio-test
+}
+
+fn default_limiter() -> bool {
+    true
+}
+
+fn default_limiter_threshold_db() -> f32 {
+    DEFAULT_LIMITER_THRESHOLD_DB
+}
+
+fn default_volume_ramp_ms() -> f32 {
+    DEFAULT_VOLUME_RAMP_MS
+}
+
+fn default_quantize_strength() -> f32 {
+    DEFAULT_QUANTIZE_STRENGTH
+}
+
+fn default_accent_boost() -> f32 {
+    DEFAULT_ACCENT_BOOST
+}
+
+fn default_humanize_velocity() -> f32 {
+    DEFAULT_HUMANIZE.velocity
+}
+
+fn default_humanize_timing_ms() -> i16 {
+    DEFAULT_HUMANIZE.timing_ms
+}
+
+fn default_morph_bars() -> usize {
+    DEFAULT_MORPH_BARS
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UIConfig {
     pub flash_duration_ms: u64,
+    #[serde(default = "default_flash_pads")]
+    pub flash_pads: bool, // Light pads as they play; off for flicker-sensitive users
     pub tick_interval_ms: u64,
+    #[serde(default = "default_follow_playhead")]
+    pub follow_playhead: bool, // Page the pattern view along with playback
+    #[serde(default)]
+    pub mouse: bool, // Click pads and steps, scroll over the tempo
+}
+
+fn default_follow_playhead() -> bool {
+    true
+}
+
+fn default_flash_pads() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: String, // "default", "high-contrast" or "mono"
+    // Optional overrides, hex ("#6496a0") or ANSI names ("cyan")
+    pub group_colors: Option<Vec<String>>,
+    pub selected_pad: Option<String>,
+    #[serde(alias = "flash")]
+    pub flashing_pad: Option<String>,
+    pub step_highlight: Option<String>,
+    pub accent: Option<String>,
+    pub muted: Option<String>,
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub truecolor: Option<bool>, // Unset detects it from COLORTERM
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            preset: "default".to_string(),
+            group_colors: None,
+            selected_pad: None,
+            flashing_pad: None,
+            step_highlight: None,
+            accent: None,
+            muted: None,
+            background: None,
+            text: None,
+            truecolor: None,
+        }
+    }
 }
 
 impl Default for Config {
@@ -78,8 +897,24 @@ impl Default for Config {
             key_bindings: KeyBindingsConfig {
                 transport: TransportKeys {
                     play_stop: " ".to_string(),
+                    stop: default_stop_key(),
                     record: "r".to_string(),
                     clear: "c".to_string(),
+                    reset_pattern: default_reset_pattern_key(),
+                    audition_start: default_audition_start_key(),
+                    audition_stop: default_audition_stop_key(),
+                    sample: default_sample_key(),
+                    resample: default_resample_key(),
+                    synth_kit: default_synth_kit_key(),
+                    record_output: default_record_output_key(),
+                    panic: default_panic_key(),
+                    export_pattern: default_export_pattern_key(),
+                    import_pattern: default_import_pattern_key(),
+                    save_pad_settings: default_save_pad_settings_key(),
+                    quick_save: default_quick_save_key(),
+                    quick_load: default_quick_load_key(),
+                    reload_config: default_reload_config_key(),
+                    reinit_audio: default_reinit_audio_key(),
                 },
                 navigation: NavigationKeys {
                     next_group: "Tab".to_string(),
@@ -88,34 +923,108 @@ impl Default for Config {
                     prev_pattern: "Left".to_string(),
                     tempo_up: "Up".to_string(),
                     tempo_down: "Down".to_string(),
+                    tempo_step: default_tempo_step(),
+                    tempo_bpm_up: default_tempo_bpm_up_key(),
+                    tempo_bpm_down: default_tempo_bpm_down_key(),
+                    tempo_fine_up: default_tempo_fine_up_key(),
+                    tempo_fine_down: default_tempo_fine_down_key(),
+                    tempo_breakpoint: default_tempo_breakpoint_key(),
+                    clear_tempo_automation: default_clear_tempo_automation_key(),
+                    capture_pattern_tempo: default_capture_pattern_tempo_key(),
+                    clear_pattern_tempo: default_clear_pattern_tempo_key(),
+                    toggle_panel: default_toggle_panel_key(),
+                    toggle_overview: default_toggle_overview_key(),
+                    goto_pattern: default_goto_pattern_key(),
+                    morph: default_morph_key(),
+                    signature: default_signature_key(),
+                    pattern_map: default_pattern_map_key(),
+                    pad_detail: default_pad_detail_key(),
+                    pad_bank: default_pad_bank_key(),
+                    layer_mode: default_layer_mode_key(),
+                    copy_pattern: default_copy_pattern_key(),
+                    paste_pattern: default_paste_pattern_key(),
+                    paste_pattern_compact: default_paste_pattern_compact_key(),
+                    duplicate_pattern: default_duplicate_pattern_key(),
+                    chain_append: default_chain_append_key(),
+                    chain_clear: default_chain_clear_key(),
+                    next_kit: default_next_kit_key(),
+                    prev_kit: default_prev_kit_key(),
+                    import_folder: default_import_folder_key(),
+                    transpose_mode: default_transpose_mode_key(),
+                    note_repeat: default_note_repeat_key(),
                 },
                 volume: VolumeKeys {
                     master_up: "=".to_string(),
                     master_down: "-".to_string(),
                     master_mute: "M".to_string(),
+                    master_gain: default_master_gain_key(),
+                    width_up: default_width_up_key(),
+                    width_down: default_width_down_key(),
                     group_up: vec!["1".to_string(), "2".to_string(), "3".to_string(), "4".to_string()],
                     group_down: vec!["!".to_string(), "@".to_string(), "#".to_string(), "$".to_string()],
                     group_mute: vec!["F1".to_string(), "F2".to_string(), "F3".to_string(), "F4".to_string()],
+                    group_solo: default_group_solo_keys(),
+                    group_gate: default_group_gate_keys(),
+                    group_record_arm: default_group_record_arm_keys(),
+                    pad_mute: default_pad_mute_key(),
+                    clear_automation: default_clear_automation_key(),
                 },
+                step_edit: StepEditKeys::default(),
+                sends: SendKeys::default(),
+                trim: TrimKeys::default(),
                 pads,
             },
             audio: AudioConfig {
-                default_tempo: 120,
+                default_tempo: 120.0,
                 sample_rate: 44100,
                 buffer_size: 1024,
+                input_device: None,
+                output_device: None,
+                limiter: default_limiter(),
+                limiter_threshold_db: default_limiter_threshold_db(),
+                volume_ramp_ms: default_volume_ramp_ms(),
+                quantize_strength: default_quantize_strength(),
+                accent_boost: default_accent_boost(),
+                humanize_velocity: default_humanize_velocity(),
+                humanize_timing_ms: default_humanize_timing_ms(),
+                morph_bars: default_morph_bars(),
+                startup_test: false,
             },
             ui: UIConfig {
-                flash_duration_ms: 150,
+                flash_duration_ms: DEFAULT_FLASH_DURATION.as_millis() as u64,
+                flash_pads: default_flash_pads(),
                 tick_interval_ms: 50,
+                follow_playhead: default_follow_playhead(),
+                mouse: false,
             },
+            theme: ThemeConfig::default(),
+            network: NetworkConfig::default(),
+            midi: MidiConfig::default(),
+            startup: StartupConfig::default(),
+            project: ProjectConfig::default(),
+            layout: LayoutConfig::default(),
+            samples: SamplesConfig::default(),
+            groups: Vec::new(),
+            unknown_keys: Vec::new(),
         }
     }
 }
 
+pub const CONFIG_PATH: &str = "config.toml";
+pub const EXAMPLE_CONFIG_PATH: &str = "config.example.toml";
+
+// Sane ranges for the UI timings; outside them the screen stalls or strobes
+const TICK_INTERVAL_RANGE_MS: std::ops::RangeInclusive<u64> = 1..=1000;
+pub const DEFAULT_TEMPO_STEP: i32 = 5;
+pub const TEMPO_STEP_RANGE: std::ops::RangeInclusive<i32> = 1..=20;
+const MAX_FLASH_DURATION_MS: u64 = 2000;
+
 impl Config {
     pub fn load() -> Result<Self> {
-        let config_path = "config.toml";
-        
+        Self::load_from(CONFIG_PATH)
+    }
+    
+    pub fn load_from(config_path: &str) -> Result<Self> {
         if !std::path::Path::new(config_path).exists() {
             // Use default config if file doesn't exist
             return Ok(Self::default());
@@ -123,33 +1032,190 @@ impl Config {
         
         let config_content = std::fs::read_to_string(config_path)
             .map_err(|e| AppError::Config(format!("Failed to read config file: {}", e)))?;
-        
-        toml::from_str(&config_content)
-            .map_err(|e| AppError::Config(format!("Failed to parse config file: {}", e)))
+        Self::parse(&config_content)
+    }
+    
+    // Keys serde would skip over are kept for validate to report
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut unknown_keys = Vec::new();
+        let mut config: Self = serde_ignored::deserialize(toml::Deserializer::new(content), |path| unknown_keys.push(path.to_string()))
+            .map_err(|e| AppError::Config(format!("Failed to parse config file: {}", e)))?;
+        config.unknown_keys = unknown_keys;
+        Ok(config)
     }
     
     pub fn save(&self) -> Result<()> {
         let config_content = toml::to_string_pretty(self)
             .map_err(|e| AppError::Config(format!("Failed to serialize config: {}", e)))?;
         
-        std::fs::write("config.toml", config_content)
+        std::fs::write(CONFIG_PATH, config_content)
             .map_err(|e| AppError::Config(format!("Failed to write config file: {}", e)))
     }
     
-    pub fn generate_example() -> Result<()> {
+    // [layout] groups, raised to cover every [[groups]] entry so naming six groups gives six
+    pub fn group_count(&self) -> usize {
+        self.layout.groups.max(self.groups.len()).clamp(1, MAX_GROUPS)
+    }
+
+    // Names and folders for the sample bank, with defaults filled in
+    pub fn group_defs(&self) -> Vec<GroupDef> {
+        self.groups
+            .iter()
+            .take(MAX_GROUPS)
+            .enumerate()
+            .map(|(group, entry)| {
+                // A renamed group is scanned from a folder named after it
+                let default = GroupDef::default_for(group);
+                let dir = entry.dir.clone().or_else(|| entry.name.as_ref().map(|name| name.to_lowercase()));
+                GroupDef {
+                    name: entry.name.clone().unwrap_or(default.name),
+                    dir: dir.unwrap_or(default.dir),
+                }
+            })
+            .collect()
+    }
+    
+    // With dry_run, only prints where the example would be written
+    pub fn generate_example(dry_run: bool) -> Result<()> {
         let config = Self::default();
         let config_content = toml::to_string_pretty(&config)
             .map_err(|e| AppError::Config(format!("Failed to serialize config: {}", e)))?;
+        if dry_run {
+            println!("Would write example config to {}", EXAMPLE_CONFIG_PATH);
+            return Ok(());
+        }
         
-        std::fs::write("config.example.toml", config_content)
+        std::fs::write(EXAMPLE_CONFIG_PATH, config_content)
             .map_err(|e| AppError::Config(format!("Failed to write example config: {}", e)))?;
         
-        println!("Generated example config at config.example.toml");
+        println!("Generated example config at {}", EXAMPLE_CONFIG_PATH);
         println!("Rename to config.toml and edit to customize");
         
         Ok(())
     }
     
+    // Mistakes that still deserialize, as messages naming the TOML path they're at
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        
+        if !(1..=MAX_GROUPS).contains(&self.layout.groups) {
+            problems.push(format!("layout.groups must be from 1 to {}, found {}", MAX_GROUPS, self.layout.groups));
+        }
+        if !PAD_COUNTS.contains(&self.layout.pads_per_group) {
+            problems.push(format!("layout.pads_per_group must be {} or {}, found {}", PADS_PER_GROUP, MAX_PADS, self.layout.pads_per_group));
+        }
+        if self.groups.len() > MAX_GROUPS {
+            problems.push(format!("groups has {} entries, at most {} are used", self.groups.len(), MAX_GROUPS));
+        }
+        if self.network.osc_bind.parse::<std::net::IpAddr>().is_err() {
+            problems.push(format!("network.osc_bind must be an IP address, found \"{}\"", self.network.osc_bind));
+        }
+        
+        // Fewer keys than groups is fine; the rest are reached by mouse or OSC
+        let groups = self.group_count();
+        let volume = &self.key_bindings.volume;
+        let group_keys = [
+            ("group_up", &volume.group_up),
+            ("group_down", &volume.group_down),
+            ("group_mute", &volume.group_mute),
+            ("group_solo", &volume.group_solo),
+            ("group_gate", &volume.group_gate),
+            ("group_record_arm", &volume.group_record_arm),
+        ];
+        for (name, keys) in group_keys {
+            if keys.len() > groups {
+                problems.push(format!("key_bindings.volume.{} has {} entries for {} groups", name, keys.len(), groups));
+            }
+        }
+        
+        // Keys can name a pad in either bank directly; the bank key only moves the first 16
+        let pad_count = Some(self.layout.pads_per_group).filter(|pads| PAD_COUNTS.contains(pads)).unwrap_or(PADS_PER_GROUP);
+        let mut pads: Vec<_> = self.key_bindings.pads.iter().collect();
+        pads.sort();
+        for (key, &pad) in pads {
+            if pad >= pad_count {
+                problems.push(format!("key_bindings.pads.\"{}\" must be a pad from 0 to {}, found {}", key, pad_count - 1, pad));
+            }
+        }
+        problems.extend(self.unknown_keys.iter().map(|path| format!("{} is not a setting", path)));
+        problems.extend(self.unparsed_keys());
+        for (index, pad) in self.midi.output.pads.iter().enumerate() {
+            if let Some(problem) = pad.problem() {
+                problems.push(format!("midi.output.pads[{}]: {}", index, problem));
+            }
+        }
+        
+        let tempo_step = self.key_bindings.navigation.tempo_step;
+        if !TEMPO_STEP_RANGE.contains(&tempo_step) {
+            problems.push(format!(
+                "key_bindings.navigation.tempo_step must be from {} to {}, found {}",
+                TEMPO_STEP_RANGE.start(),
+                TEMPO_STEP_RANGE.end(),
+                tempo_step
+            ));
+        }
+        
+        if !TICK_INTERVAL_RANGE_MS.contains(&self.ui.tick_interval_ms) {
+            problems.push(format!(
+                "ui.tick_interval_ms must be between {} and {}, found {}",
+                TICK_INTERVAL_RANGE_MS.start(),
+                TICK_INTERVAL_RANGE_MS.end(),
+                self.ui.tick_interval_ms
+            ));
+        }
+        if self.ui.flash_duration_ms > MAX_FLASH_DURATION_MS {
+            problems.push(format!(
+                "ui.flash_duration_ms must be at most {}, found {}",
+                MAX_FLASH_DURATION_MS, self.ui.flash_duration_ms
+            ));
+        }
+        if !(MIN_TEMPO..=MAX_TEMPO).contains(&self.audio.default_tempo) {
+            problems.push(format!(
+                "audio.default_tempo must be between {} and {}, found {}",
+                MIN_TEMPO, MAX_TEMPO, self.audio.default_tempo
+            ));
+        }
+        problems
+    }
+    
+    // Walks the bindings as TOML so every key, present or future, is checked under its path
+    fn unparsed_keys(&self) -> Vec<String> {
+        let Ok(toml::Value::Table(sections)) = toml::Value::try_from(&self.key_bindings) else {
+            return Vec::new();
+        };
+        let mut problems = Vec::new();
+        let mut check = |path: String, key: &str| {
+            if KeyBinding::parse(self, key).is_none() {
+                problems.push(format!("{} has unknown key \"{}\"", path, key));
+            }
+        };
+        
+        for (section, entries) in &sections {
+            let Some(entries) = entries.as_table() else {
+                continue;
+            };
+            for (name, value) in entries {
+                // Pad keys are the table's keys rather than its values
+                if section == "pads" {
+                    check(format!("key_bindings.pads.\"{}\"", name), name);
+                    continue;
+                }
+                match value {
+                    toml::Value::String(key) => check(format!("key_bindings.{}.{}", section, name), key),
+                    toml::Value::Array(keys) => {
+                        for (index, key) in keys.iter().enumerate() {
+                            if let Some(key) = key.as_str() {
+                                check(format!("key_bindings.{}.{}[{}]", section, name, index), key);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        problems
+    }
+    
     pub fn parse_key_code(&self, key_str: &str) -> Option<KeyCode> {
         match key_str {
             " " => Some(KeyCode::Char(' ')),
@@ -187,12 +1253,20 @@ mod tests {
     fn test_default_config() {
         let config = Config::default();
         
-        assert_eq!(config.audio.default_tempo, 120);
+        assert_eq!(config.audio.default_tempo, 120.0);
         assert_eq!(config.ui.flash_duration_ms, 150);
         assert_eq!(config.key_bindings.transport.play_stop, " ");
         assert_eq!(config.key_bindings.pads.get("7"), Some(&0));
     }
 
+    #[test]
+    fn test_default_tempo_accepts_integers() {
+        let audio: AudioConfig = toml::from_str("default_tempo = 90\nsample_rate = 44100\nbuffer_size = 1024").unwrap();
+        assert_eq!(audio.default_tempo, 90.0);
+        let audio: AudioConfig = toml::from_str("default_tempo = 87.5\nsample_rate = 44100\nbuffer_size = 1024").unwrap();
+        assert_eq!(audio.default_tempo, 87.5);
+    }
+
     #[test]
     fn test_parse_key_code() {
         let config = Config::default();
@@ -204,6 +1278,154 @@ mod tests {
         assert_eq!(config.parse_key_code("invalid"), None);
     }
 
+    #[test]
+    fn test_validate_reports_paths() {
+        assert!(Config::default().validate().is_empty());
+        
+        let mut config = Config::default();
+        config.key_bindings.volume.group_up.push("5".to_string());
+        config.key_bindings.pads.insert("q".to_string(), 16);
+        config.key_bindings.transport.record = "Spce".to_string();
+        config.key_bindings.volume.group_mute[2] = "F13x".to_string();
+        config.ui.tick_interval_ms = 0;
+        config.ui.flash_duration_ms = 10_000;
+        config.audio.default_tempo = 400.0;
+        config.key_bindings.navigation.tempo_step = 25;
+        config.network.osc_bind = "localhost:9000".to_string();
+        
+        let problems = config.validate();
+        for expected in [
+            "key_bindings.volume.group_up has 5 entries for 4 groups",
+            "key_bindings.pads.\"q\" must be a pad from 0 to 15, found 16",
+            "key_bindings.transport.record has unknown key \"Spce\"",
+            "key_bindings.volume.group_mute[2] has unknown key \"F13x\"",
+            "ui.tick_interval_ms must be between 1 and 1000, found 0",
+            "ui.flash_duration_ms must be at most 2000, found 10000",
+            "audio.default_tempo must be between 60 and 300, found 400",
+            "key_bindings.navigation.tempo_step must be from 1 to 20, found 25",
+            "network.osc_bind must be an IP address, found \"localhost:9000\"",
+        ] {
+            assert!(problems.iter().any(|problem| problem == expected), "missing: {}", expected);
+        }
+        assert_eq!(problems.len(), 9);
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout: LayoutConfig = toml::from_str("groups = 6").unwrap();
+        assert_eq!(layout.groups, 6);
+        assert_eq!(layout.pads_per_group, 16);
+        let mut config = Config { layout, ..Config::default() };
+        assert!(config.validate().is_empty());
+        
+        config.layout.pads_per_group = 32;
+        config.key_bindings.pads.insert("q".to_string(), 31);
+        assert!(config.validate().is_empty());
+        
+        config.layout.groups = 9;
+        config.layout.pads_per_group = 24;
+        let problems = config.validate();
+        assert!(problems.contains(&"layout.groups must be from 1 to 8, found 9".to_string()));
+        assert!(problems.contains(&"layout.pads_per_group must be 16 or 32, found 24".to_string()));
+        assert!(problems.contains(&"key_bindings.pads.\"q\" must be a pad from 0 to 15, found 31".to_string()));
+        
+        config.layout.groups = 2;
+        let problems = config.validate();
+        assert!(problems.contains(&"key_bindings.volume.group_mute has 4 entries for 2 groups".to_string()));
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_keys() {
+        let mut content = toml::to_string(&Config::default()).unwrap();
+        content = content.replace("follow_playhead = true", "follow_playhed = false");
+        content.push_str("\n[colour]\naccent = \"red\"\n");
+        
+        let config = Config::parse(&content).unwrap();
+        assert!(config.ui.follow_playhead); // The default, since the typo isn't read
+        assert_eq!(config.validate(), ["ui.follow_playhed is not a setting", "colour is not a setting"]);
+        
+        // A file written from the defaults has nothing stray
+        assert!(Config::parse(&toml::to_string(&Config::default()).unwrap()).unwrap().validate().is_empty());
+    }
+
+    #[test]
+    fn test_generate_example_dry_run() {
+        let _ = std::fs::remove_file(EXAMPLE_CONFIG_PATH);
+        
+        assert!(Config::generate_example(true).is_ok());
+        assert!(!std::path::Path::new(EXAMPLE_CONFIG_PATH).exists());
+        
+        Config::generate_example(false).unwrap();
+        let written: Config = toml::from_str(&std::fs::read_to_string(EXAMPLE_CONFIG_PATH).unwrap()).unwrap();
+        assert!(written.validate().is_empty());
+        let _ = std::fs::remove_file(EXAMPLE_CONFIG_PATH);
+    }
+
+    #[test]
+    fn test_samples_section() {
+        assert_eq!(Config::default().samples.normalize, Normalize::Off);
+        let samples: SamplesConfig = toml::from_str("normalize = \"peak\"").unwrap();
+        assert_eq!(samples.normalize, Normalize::Peak);
+        assert_eq!(samples.normalize_target_db, -1.0);
+        assert_eq!(samples.stream_threshold_mb, 20);
+        let samples: SamplesConfig = toml::from_str("stream_threshold_mb = 0").unwrap();
+        assert_eq!(samples.stream_threshold_mb, 0);
+        assert!(toml::from_str::<SamplesConfig>("normalize = \"rms\"").is_err());
+    }
+
+    #[test]
+    fn test_group_entries() {
+        let mut entries: HashMap<String, Vec<GroupConfig>> = toml::from_str(
+            r##"
+            [[groups]]
+            name = "PERC"
+            color = "#ff8800"
+
+            [[groups]]
+            dir = "basslines"
+            "##,
+        )
+        .unwrap();
+        let config = Config { groups: entries.remove("groups").unwrap(), ..Config::default() };
+        assert_eq!(config.group_defs(), [
+            GroupDef { name: "PERC".to_string(), dir: "perc".to_string() },
+            GroupDef { name: "BASS".to_string(), dir: "basslines".to_string() },
+        ]);
+        assert!(config.validate().is_empty());
+        
+        // Entries beyond the layout's count add groups
+        assert_eq!(config.group_count(), DEFAULT_GROUPS);
+        let six = Config { groups: vec![GroupConfig::default(); 6], ..Config::default() };
+        assert_eq!(six.group_count(), 6);
+        
+        let config = Config { groups: vec![GroupConfig::default(); MAX_GROUPS + 1], ..Config::default() };
+        assert_eq!(config.group_defs().len(), MAX_GROUPS);
+        assert_eq!(config.group_count(), MAX_GROUPS);
+        assert!(config.validate().contains(&"groups has 9 entries, at most 8 are used".to_string()));
+    }
+
+    #[test]
+    fn test_midi_output_pads() {
+        let midi: MidiConfig = toml::from_str(
+            r#"
+            [output]
+            gate_ms = 80
+            pads = [
+                { group = 1, pad = 1, channel = 10, note = 36, midi_only = true },
+                { group = 2, pad = 33, channel = 1, note = 60 },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(midi.output.gate_ms, 80);
+        let notes = midi.output.pad_notes();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[&(0, 0)], PadNote { channel: 9, note: 36, midi_only: true });
+        
+        let config = Config { midi, ..Config::default() };
+        assert_eq!(config.validate(), vec!["midi.output.pads[1]: pad must be from 1 to 32, found 33"]);
+    }
+
     #[test]
     fn test_save_and_load() {
         use std::fs;
@@ -221,6 +1443,7 @@ mod tests {
         
         let loaded = loaded.unwrap();
         assert_eq!(loaded.audio.default_tempo, config.audio.default_tempo);
+        assert_eq!(loaded.theme.preset, "default");
         assert_eq!(loaded.key_bindings.transport.play_stop, config.key_bindings.transport.play_stop);
         
         // Clean up
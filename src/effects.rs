@@ -0,0 +1,598 @@
+use serde::{Deserialize, Serialize};
+use crate::bus::{ms_to_frames, GainRamp, BUS_SAMPLE_RATE};
+use crate::render::resolution_step_frames;
+use crate::sequencer::MAX_GROUPS;
+
+// Longest delay we ever need: a dotted eighth at the slowest tempo
+const MAX_DELAY_SECONDS: f32 = 2.0;
+const DELAY_FEEDBACK: f32 = 0.4;
+const DELAY_BEATS: f32 = 0.75; // Dotted eighth
+
+// Freeverb tunings, trimmed to four combs per side to stay cheap
+const COMB_LENGTHS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASS_LENGTHS: [usize; 2] = [556, 441];
+const STEREO_SPREAD: usize = 23;
+const REVERB_INPUT_GAIN: f32 = 0.03;
+
+// Below this the tail is inaudible and processing can stop
+const SILENCE_THRESHOLD: f32 = 1e-5;
+
+// A gate pattern is one bar of 16th steps
+pub const GATE_STEPS: usize = 16;
+// 8th-note gate: open for one 8th, shut for the next
+pub const DEFAULT_GATE_PATTERN: [bool; GATE_STEPS] = [
+    true, true, false, false, true, true, false, false,
+    true, true, false, false, true, true, false, false,
+];
+// Each edge fades over this long so the gate doesn't click
+const GATE_RAMP_MS: f32 = 2.0;
+
+pub fn delay_frames_for_tempo(tempo: f32) -> usize {
+    let seconds = 60.0 / tempo.max(1.0) * DELAY_BEATS;
+    (seconds.min(MAX_DELAY_SECONDS) * BUS_SAMPLE_RATE as f32) as usize
+}
+
+// Room size presets; bigger rooms feed back more and darken less, so the tail lasts longer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReverbSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ReverbSize {
+    pub fn next(self) -> Self {
+        match self {
+            ReverbSize::Small => ReverbSize::Medium,
+            ReverbSize::Medium => ReverbSize::Large,
+            ReverbSize::Large => ReverbSize::Small,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReverbSize::Small => "Small",
+            ReverbSize::Medium => "Medium",
+            ReverbSize::Large => "Large",
+        }
+    }
+
+    // Comb feedback and damping
+    fn tuning(self) -> (f32, f32) {
+        match self {
+            ReverbSize::Small => (0.7, 0.4),
+            ReverbSize::Medium => (0.84, 0.2),
+            ReverbSize::Large => (0.92, 0.15),
+        }
+    }
+}
+
+pub struct Delay {
+    buffer: Vec<f32>, // Interleaved stereo ring buffer
+    write_frame: usize,
+    delay_frames: usize,
+}
+
+impl Delay {
+    pub fn new(delay_frames: usize) -> Self {
+        let capacity = (MAX_DELAY_SECONDS * BUS_SAMPLE_RATE as f32) as usize + 1;
+        Self {
+            buffer: vec![0.0; capacity * 2],
+            write_frame: 0,
+            delay_frames: delay_frames.clamp(1, capacity - 1),
+        }
+    }
+
+    pub fn set_delay_frames(&mut self, delay_frames: usize) {
+        self.delay_frames = delay_frames.clamp(1, self.buffer.len() / 2 - 1);
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.fill(0.0);
+    }
+
+    // Adds the wet signal for `input` to `output`
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        let frames = self.buffer.len() / 2;
+
+        for (frame_in, frame_out) in input.chunks_exact(2).zip(output.chunks_exact_mut(2)) {
+            let read_frame = (self.write_frame + frames - self.delay_frames) % frames;
+            let left = self.buffer[read_frame * 2];
+            let right = self.buffer[read_frame * 2 + 1];
+
+            frame_out[0] += left;
+            frame_out[1] += right;
+
+            self.buffer[self.write_frame * 2] = frame_in[0] + left * DELAY_FEEDBACK;
+            self.buffer[self.write_frame * 2 + 1] = frame_in[1] + right * DELAY_FEEDBACK;
+            self.write_frame = (self.write_frame + 1) % frames;
+        }
+    }
+}
+
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_store: f32,
+    feedback: f32,
+    damping: f32,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        let (feedback, damping) = ReverbSize::default().tuning();
+        Self {
+            buffer: vec![0.0; length],
+            index: 0,
+            filter_store: 0.0,
+            feedback,
+            damping,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_store = output * (1.0 - self.damping) + self.filter_store * self.damping;
+        self.buffer[self.index] = input + self.filter_store * self.feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.filter_store = 0.0;
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.index];
+        self.buffer[self.index] = input + buffered * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        buffered - input
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+struct ReverbChannel {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl ReverbChannel {
+    fn new(spread: usize) -> Self {
+        Self {
+            combs: COMB_LENGTHS.iter().map(|&length| Comb::new(length + spread)).collect(),
+            allpasses: ALLPASS_LENGTHS.iter().map(|&length| Allpass::new(length + spread)).collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let input = input * REVERB_INPUT_GAIN;
+        let mut output = self.combs.iter_mut().map(|comb| comb.process(input)).sum();
+        for allpass in &mut self.allpasses {
+            output = allpass.process(output);
+        }
+        output
+    }
+
+    fn clear(&mut self) {
+        self.combs.iter_mut().for_each(Comb::clear);
+        self.allpasses.iter_mut().for_each(Allpass::clear);
+    }
+
+    fn set_size(&mut self, size: ReverbSize) {
+        let (feedback, damping) = size.tuning();
+        for comb in &mut self.combs {
+            comb.feedback = feedback;
+            comb.damping = damping;
+        }
+    }
+}
+
+// Schroeder/Freeverb-style: parallel damped combs into series allpasses
+pub struct Reverb {
+    left: ReverbChannel,
+    right: ReverbChannel,
+}
+
+impl Reverb {
+    pub fn new() -> Self {
+        Self {
+            left: ReverbChannel::new(0),
+            right: ReverbChannel::new(STEREO_SPREAD),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.left.clear();
+        self.right.clear();
+    }
+
+    pub fn set_size(&mut self, size: ReverbSize) {
+        self.left.set_size(size);
+        self.right.set_size(size);
+    }
+
+    // Adds the wet signal for `input` to `output`
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        for (frame_in, frame_out) in input.chunks_exact(2).zip(output.chunks_exact_mut(2)) {
+            // Both sides get the mono sum; the stereo image comes from the spread
+            let mono = (frame_in[0] + frame_in[1]) * 0.5;
+            frame_out[0] += self.left.process(mono);
+            frame_out[1] += self.right.process(mono);
+        }
+    }
+}
+
+impl Default for Reverb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Trance gate on a group: its output is cut in and out on a repeating pattern of steps,
+// kept in line with the sequencer by sync_to_step
+pub struct GateEffect {
+    pattern: [bool; GATE_STEPS],
+    step_frames: usize,
+    position: usize, // Frames into the pattern, 0 being the start of step 0
+    gain: GainRamp,
+}
+
+impl GateEffect {
+    // Steps are as long as the playing signature's, `steps_per_beat` to a beat
+    pub fn new(pattern: [bool; GATE_STEPS], tempo: f32, steps_per_beat: usize) -> Self {
+        let open = if pattern[0] { 1.0 } else { 0.0 };
+        Self {
+            pattern,
+            step_frames: resolution_step_frames(tempo, steps_per_beat, BUS_SAMPLE_RATE).max(1),
+            position: 0,
+            gain: GainRamp::new(open, ms_to_frames(GATE_RAMP_MS)),
+        }
+    }
+
+    // Takes effect from the next step
+    pub fn set_pattern(&mut self, pattern: [bool; GATE_STEPS]) {
+        self.pattern = pattern;
+    }
+
+    // Keeps the step it's on and picks up from that step's start at the new length
+    pub fn set_tempo(&mut self, tempo: f32, steps_per_beat: usize) {
+        let step = self.position / self.step_frames;
+        self.step_frames = resolution_step_frames(tempo, steps_per_beat, BUS_SAMPLE_RATE).max(1);
+        self.position = step * self.step_frames;
+    }
+
+    // Called as the sequencer plays each step, so drift between the clocks never builds up
+    pub fn sync_to_step(&mut self, step: usize) {
+        self.position = (step % GATE_STEPS) * self.step_frames;
+    }
+
+    pub fn process(&mut self, block: &mut [f32]) {
+        let cycle = self.step_frames * GATE_STEPS;
+        for frame in block.chunks_exact_mut(2) {
+            if self.position.is_multiple_of(self.step_frames) {
+                let open = self.pattern[self.position / self.step_frames];
+                self.gain.set_target(if open { 1.0 } else { 0.0 });
+            }
+            let gain = self.gain.next_gain();
+            frame[0] *= gain;
+            frame[1] *= gain;
+            self.position = (self.position + 1) % cycle;
+        }
+    }
+}
+
+// Per-group send levels feeding one shared delay and reverb on the master bus
+pub struct SendEffects {
+    pub delay_sends: [f32; MAX_GROUPS],
+    pub reverb_sends: [f32; MAX_GROUPS],
+    delay: Delay,
+    reverb: Reverb,
+    delay_input: Vec<f32>,
+    reverb_input: Vec<f32>,
+    wet: Vec<f32>,
+    delay_active: bool, // Still ringing out, even if every send is now zero
+    reverb_active: bool,
+}
+
+impl SendEffects {
+    pub fn new(tempo: f32) -> Self {
+        Self {
+            delay_sends: [0.0; MAX_GROUPS],
+            reverb_sends: [0.0; MAX_GROUPS],
+            delay: Delay::new(delay_frames_for_tempo(tempo)),
+            reverb: Reverb::new(),
+            delay_input: Vec::new(),
+            reverb_input: Vec::new(),
+            wet: Vec::new(),
+            delay_active: false,
+            reverb_active: false,
+        }
+    }
+
+    pub fn set_tempo(&mut self, tempo: f32) {
+        self.delay.set_delay_frames(delay_frames_for_tempo(tempo));
+    }
+
+    pub fn set_reverb_size(&mut self, size: ReverbSize) {
+        self.reverb.set_size(size);
+    }
+
+    pub fn clear_tails(&mut self) {
+        self.delay.clear();
+        self.reverb.clear();
+        self.delay_active = false;
+        self.reverb_active = false;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        !self.delay_active
+            && !self.reverb_active
+            && self.delay_sends.iter().chain(&self.reverb_sends).all(|&send| send <= 0.0)
+    }
+
+    // Prepares the send inputs for a block of `len` samples
+    pub fn begin_block(&mut self, len: usize) {
+        for buffer in [&mut self.delay_input, &mut self.reverb_input, &mut self.wet] {
+            buffer.clear();
+            buffer.resize(len, 0.0);
+        }
+    }
+
+    // Feeds one voice's dry output into the sends of its group
+    pub fn accumulate(&mut self, group: usize, dry: &[f32]) {
+        let delay_send = self.delay_sends.get(group).copied().unwrap_or(0.0);
+        let reverb_send = self.reverb_sends.get(group).copied().unwrap_or(0.0);
+
+        if delay_send > 0.0 {
+            for (input, &sample) in self.delay_input.iter_mut().zip(dry) {
+                *input += sample * delay_send;
+            }
+        }
+        if reverb_send > 0.0 {
+            for (input, &sample) in self.reverb_input.iter_mut().zip(dry) {
+                *input += sample * reverb_send;
+            }
+        }
+    }
+
+    // Adds the wet returns to `block`; effects with no send and no tail are skipped
+    pub fn process(&mut self, block: &mut [f32]) {
+        if self.delay_sends.iter().any(|&send| send > 0.0) {
+            self.delay_active = true;
+        }
+        if self.reverb_sends.iter().any(|&send| send > 0.0) {
+            self.reverb_active = true;
+        }
+
+        if self.delay_active {
+            self.wet.fill(0.0);
+            self.delay.process(&self.delay_input, &mut self.wet);
+            self.delay_active = finish_or_keep(&self.delay_input, &self.wet);
+            if !self.delay_active {
+                self.delay.clear();
+            }
+            add_into(block, &self.wet);
+        }
+
+        if self.reverb_active {
+            self.wet.fill(0.0);
+            self.reverb.process(&self.reverb_input, &mut self.wet);
+            self.reverb_active = finish_or_keep(&self.reverb_input, &self.wet);
+            if !self.reverb_active {
+                self.reverb.clear();
+            }
+            add_into(block, &self.wet);
+        }
+    }
+}
+
+// An effect stays active while it is fed or its output is still audible
+fn finish_or_keep(input: &[f32], output: &[f32]) -> bool {
+    input.iter().chain(output).any(|sample| sample.abs() > SILENCE_THRESHOLD)
+}
+
+fn add_into(block: &mut [f32], wet: &[f32]) {
+    for (sample, &wet) in block.iter_mut().zip(wet) {
+        *sample += wet;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::step_frames;
+    use crate::sequencer::STEPS_PER_BEAT;
+
+    fn impulse(frames: usize) -> Vec<f32> {
+        let mut block = vec![0.0; frames * 2];
+        block[0] = 1.0;
+        block[1] = 1.0;
+        block
+    }
+
+    #[test]
+    fn test_gate_masks_off_steps() {
+        let step = step_frames(120.0, BUS_SAMPLE_RATE);
+        let mut gate = GateEffect::new(DEFAULT_GATE_PATTERN, 120.0, STEPS_PER_BEAT);
+        let mut block = vec![0.5; step * 4 * 2];
+        gate.process(&mut block);
+        
+        // Steps 0 and 1 pass, steps 2 and 3 are silent once the edge has faded
+        let frame = |step_index: usize, offset: usize| block[(step * step_index + offset) * 2];
+        assert_eq!(frame(0, step / 2), 0.5);
+        assert_eq!(frame(1, step - 1), 0.5);
+        assert_eq!(frame(2, step / 2), 0.0);
+        assert_eq!(frame(3, step - 1), 0.0);
+        assert!(frame(2, 10) > 0.0 && frame(2, 10) < 0.5);
+    }
+
+    #[test]
+    fn test_gate_follows_the_step_clock() {
+        let step = step_frames(120.0, BUS_SAMPLE_RATE);
+        let mut gate = GateEffect::new(DEFAULT_GATE_PATTERN, 120.0, STEPS_PER_BEAT);
+        gate.process(&mut vec![0.5; step * 2]);
+        
+        // Jumping to step 2 shuts it straight away rather than after step 1
+        gate.sync_to_step(2);
+        let mut block = vec![0.5; step * 2];
+        gate.process(&mut block);
+        assert_eq!(block[step], 0.0);
+        
+        // Step 16 wraps around to the open step 0
+        gate.sync_to_step(16);
+        let mut block = vec![0.5; step * 2];
+        gate.process(&mut block);
+        assert_eq!(block[step], 0.5);
+        
+        // A tempo change keeps the step and shortens it
+        gate.sync_to_step(1);
+        gate.set_tempo(240.0, STEPS_PER_BEAT);
+        let mut block = vec![0.5; step * 2];
+        gate.process(&mut block);
+        assert_eq!(block[step / 4], 0.5);
+        assert_eq!(block[step + step / 4], 0.0);
+    }
+
+    #[test]
+    fn test_gate_steps_follow_the_resolution() {
+        // An eighth at 120 BPM is a quarter of a second, twice a sixteenth
+        let step = resolution_step_frames(120.0, 2, BUS_SAMPLE_RATE);
+        assert_eq!(step, BUS_SAMPLE_RATE as usize / 4);
+        let mut gate = GateEffect::new(DEFAULT_GATE_PATTERN, 120.0, 2);
+        let mut block = vec![0.5; step * 3 * 2];
+        gate.process(&mut block);
+        assert_eq!(block[(step + step / 2) * 2], 0.5);
+        assert_eq!(block[(2 * step + step / 2) * 2], 0.0);
+    }
+
+    #[test]
+    fn test_delay_time_follows_tempo() {
+        // Dotted eighth at 120 BPM is 375ms
+        assert_eq!(delay_frames_for_tempo(120.0), (0.375 * BUS_SAMPLE_RATE as f32) as usize);
+        assert!(delay_frames_for_tempo(60.0) > delay_frames_for_tempo(120.0));
+        // Very slow tempos are capped to the buffer
+        assert!(delay_frames_for_tempo(1.0) <= (MAX_DELAY_SECONDS * BUS_SAMPLE_RATE as f32) as usize);
+    }
+
+    #[test]
+    fn test_delay_echoes_after_delay_time() {
+        let mut delay = Delay::new(100);
+        let input = impulse(300);
+        let mut output = vec![0.0; 600];
+
+        delay.process(&input, &mut output);
+        assert_eq!(output[0], 0.0);
+        assert_eq!(output[200], 1.0); // First echo at frame 100
+        assert!((output[400] - DELAY_FEEDBACK).abs() < 1e-6); // Second echo, with feedback
+    }
+
+    #[test]
+    fn test_comb_recirculates_through_its_buffer() {
+        let mut comb = Comb::new(10);
+        let (feedback, damping) = ReverbSize::default().tuning();
+        let output: Vec<f32> = (0..25).map(|i| comb.process(if i == 0 { 1.0 } else { 0.0 })).collect();
+
+        // The impulse comes back once per buffer length, scaled by the damped feedback
+        assert!(output[..10].iter().all(|&s| s == 0.0));
+        assert_eq!(output[10], 1.0);
+        assert!(output[11..20].iter().all(|&s| s == 0.0));
+        assert!((output[20] - (1.0 - damping) * feedback).abs() < 1e-6);
+
+        comb.clear();
+        assert!((0..25).all(|_| comb.process(0.0) == 0.0));
+    }
+
+    #[test]
+    fn test_larger_rooms_ring_longer() {
+        let tail_energy = |size: ReverbSize| {
+            let mut reverb = Reverb::new();
+            reverb.set_size(size);
+            let mut output = vec![0.0; 40000];
+            reverb.process(&impulse(20000), &mut output);
+            output[30000..].iter().map(|s| s * s).sum::<f32>()
+        };
+        assert!(tail_energy(ReverbSize::Large) > tail_energy(ReverbSize::Medium));
+        assert!(tail_energy(ReverbSize::Medium) > tail_energy(ReverbSize::Small));
+        assert_eq!(ReverbSize::Large.next(), ReverbSize::Small);
+    }
+
+    #[test]
+    fn test_reverb_produces_tail() {
+        let mut reverb = Reverb::new();
+        let input = impulse(4096);
+        let mut output = vec![0.0; 8192];
+
+        reverb.process(&input, &mut output);
+        assert!(output[4000..].iter().any(|s| s.abs() > 1e-4));
+        assert!(output.iter().all(|s| s.is_finite() && s.abs() < 1.0));
+    }
+
+    #[test]
+    fn test_sends_skip_processing_when_unused() {
+        let mut sends = SendEffects::new(120.0);
+        assert!(sends.is_idle());
+
+        sends.begin_block(512);
+        sends.accumulate(0, &impulse(256));
+        let mut block = vec![0.0; 512];
+        sends.process(&mut block);
+        assert!(block.iter().all(|&s| s == 0.0));
+        assert!(sends.is_idle());
+
+        sends.reverb_sends[0] = 0.5;
+        assert!(!sends.is_idle());
+    }
+
+    #[test]
+    fn test_zero_sends_leave_the_dry_mix_untouched() {
+        let mut sends = SendEffects::new(120.0);
+        let dry: Vec<f32> = (0..512).map(|i| (i as f32 * 0.1).sin() * 0.5).collect();
+
+        sends.begin_block(dry.len());
+        sends.accumulate(2, &dry);
+        let mut block = dry.clone();
+        sends.process(&mut block);
+        assert_eq!(block, dry);
+    }
+
+    #[test]
+    fn test_tail_rings_out_after_send_is_closed() {
+        let mut sends = SendEffects::new(120.0);
+        sends.reverb_sends[1] = 1.0;
+
+        sends.begin_block(512);
+        sends.accumulate(1, &impulse(256));
+        let mut block = vec![0.0; 512];
+        sends.process(&mut block);
+
+        // Closing the send keeps the effect running until its tail dies away
+        sends.reverb_sends[1] = 0.0;
+        assert!(!sends.is_idle());
+        for _ in 0..2000 {
+            sends.begin_block(512);
+            let mut block = vec![0.0; 512];
+            sends.process(&mut block);
+        }
+        assert!(sends.is_idle());
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use crate::app::App;
+use crate::command::Command;
+use crate::config::{ClockSource, Config};
+use crate::midi::{self, MidiClock};
+use crate::osc;
+
+/// What playback needs besides the app itself: OSC remote control and MIDI. The terminal
+/// UI and headless playback both drive it, a [`Engine::run`] pass at a time.
+///
+/// Nothing here prints; what the engine has to say is handed back for the caller to show,
+/// since there may be no terminal to print to.
+pub struct Engine {
+    remote_commands: Receiver<std::result::Result<Command, String>>, // Commands and dropped-message warnings from the OSC listener, drained every pass
+    listening: bool,                                                 // An OSC listener is open, so commands can arrive while idle
+}
+
+impl Engine {
+    /// Opens the OSC listener and MIDI ports the config asks for, and returns a line on
+    /// each for the caller to report.
+    pub fn start(app: &mut App, config: &Config, osc_port: Option<u16>) -> (Self, Vec<String>) {
+        let mut messages = Vec::new();
+        let (remote_sender, remote_commands) = mpsc::channel();
+        let mut listening = false;
+        if let Some(port) = osc_port.or(config.network.osc_port) {
+            let bind = &config.network.osc_bind;
+            match osc::spawn_listener(bind, port, remote_sender) {
+                Ok(()) => {
+                    listening = true;
+                    messages.push(format!("Listening for OSC on {} UDP port {}", bind, port));
+                }
+                Err(e) => messages.push(format!("Failed to start OSC listener on port {}: {}", port, e)),
+            }
+        }
+        // Clock and pad notes share one connection to the port
+        if config.midi.sends_clock() || !app.midi_notes.is_empty() {
+            match midi::open_output(config.midi.port.as_deref()) {
+                Ok(output) => {
+                    let clock = MidiClock::spawn(Box::new(output), app.get_tempo());
+                    app.midi_clock = Some(if config.midi.sends_clock() { clock } else { clock.notes_only() });
+                }
+                Err(e) => messages.push(format!("MIDI output disabled: {}", e)),
+            }
+        }
+        // Without an input to follow the internal clock carries on as usual
+        if config.midi.clock == ClockSource::Slave {
+            match midi::open_input(config.midi.port.as_deref()) {
+                Ok(clock_in) => app.clock_in = Some(clock_in),
+                Err(e) => messages.push(format!("Using the internal clock: {}", e)),
+            }
+        }
+        (Self { remote_commands, listening }, messages)
+    }
+
+    /// One pass: remote commands, then the sequencer clock. Returns why any remote
+    /// message was dropped or command failed.
+    pub fn run(&self, app: &mut App) -> Vec<String> {
+        let mut errors = Vec::new();
+        for received in self.remote_commands.try_iter() {
+            match received {
+                Ok(command) => {
+                    if let Err(e) = command.execute(app) {
+                        errors.push(format!("Remote command error: {}", e));
+                    }
+                }
+                Err(warning) => errors.push(warning),
+            }
+        }
+        // A failed port takes the clock thread with it, so the app stops sending to it
+        if let Some(failure) = app.midi_clock.as_ref().and_then(MidiClock::failure) {
+            app.midi_clock = None;
+            errors.push(failure);
+        }
+        app.tick();
+        errors
+    }
+
+    /// How long the caller can sleep before the next pass.
+    pub fn timeout(&self, app: &App) -> Duration {
+        // A remote Play shouldn't wait out the idle interval
+        let poll_interval = if self.listening { app.tick_interval } else { app.desired_poll_interval() };
+        // Wake early for a nudged hit rather than waiting out the whole tick
+        app.next_pending_hit()
+            .map_or(poll_interval, |at| at.saturating_duration_since(Instant::now()).min(poll_interval))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_commands_run_on_the_next_pass() {
+        let mut app = App::new().unwrap();
+        let (sender, remote_commands) = mpsc::channel();
+        let engine = Engine { remote_commands, listening: false };
+        
+        sender.send(Ok(Command::SetTempo(140.0))).unwrap();
+        sender.send(Ok(Command::SelectGroup(99))).unwrap();
+        sender.send(Err("Ignoring OSC message /koii/explode".to_string())).unwrap();
+        assert_eq!(app.get_tempo(), 120.0);
+        let errors = engine.run(&mut app);
+        assert_eq!(app.get_tempo(), 140.0);
+        assert_eq!(errors, ["Remote command error: Invalid group 99", "Ignoring OSC message /koii/explode"]);
+        
+        // Nothing is printed or kept from the start-up either
+        let (_, messages) = Engine::start(&mut app, &Config::default(), None);
+        assert!(messages.is_empty(), "{:?}", messages);
+    }
+
+    #[test]
+    fn test_listening_keeps_the_short_poll_interval() {
+        let app = App::new().unwrap();
+        let (_sender, remote_commands) = mpsc::channel();
+        let mut engine = Engine { remote_commands, listening: false };
+        assert!(engine.timeout(&app) > app.tick_interval);
+        
+        engine.listening = true;
+        assert_eq!(engine.timeout(&app), app.tick_interval);
+    }
+
+    #[test]
+    fn test_a_failed_midi_port_is_reported() {
+        struct FailingOut;
+        impl midi::MidiOut for FailingOut {
+            fn send(&mut self, _message: &[u8]) -> std::io::Result<()> {
+                Err(std::io::Error::other("device unplugged"))
+            }
+        }
+        
+        let mut app = App::new().unwrap();
+        let (_sender, remote_commands) = mpsc::channel();
+        let engine = Engine { remote_commands, listening: false };
+        let clock = MidiClock::spawn(Box::new(FailingOut), 120.0);
+        clock.start();
+        app.midi_clock = Some(clock);
+        
+        // The clock thread fails on its own time, so the engine gets a few passes to hear of it
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut errors = Vec::new();
+        while errors.is_empty() && Instant::now() < deadline {
+            errors = engine.run(&mut app);
+            std::thread::yield_now();
+        }
+        assert_eq!(errors, ["MIDI output failed, clock and notes stopped: device unplugged"]);
+        assert!(app.midi_clock.is_none());
+    }
+}
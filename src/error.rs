@@ -2,6 +2,7 @@ use std::fmt;
 use std::error::Error;
 use std::io;
 
+/// Errors from anywhere in the app, by the area they come from.
 #[derive(Debug)]
 pub enum AppError {
     Io(io::Error),
@@ -9,6 +10,9 @@ pub enum AppError {
     Config(String),
     Command(String),
     Sample(String),
+    Pattern(String),
+    Midi(String),
+    Project(String),
 }
 
 impl fmt::Display for AppError {
@@ -19,6 +23,9 @@ impl fmt::Display for AppError {
             AppError::Config(msg) => write!(f, "Configuration error: {}", msg),
             AppError::Command(msg) => write!(f, "Command error: {}", msg),
             AppError::Sample(msg) => write!(f, "Sample error: {}", msg),
+            AppError::Pattern(msg) => write!(f, "Pattern error: {}", msg),
+            AppError::Midi(msg) => write!(f, "MIDI error: {}", msg),
+            AppError::Project(msg) => write!(f, "Project error: {}", msg),
         }
     }
 }
@@ -50,6 +57,15 @@ mod tests {
         
         let err = AppError::Sample("Sample not found".to_string());
         assert_eq!(err.to_string(), "Sample error: Sample not found");
+        
+        let err = AppError::Pattern("Wrong size".to_string());
+        assert_eq!(err.to_string(), "Pattern error: Wrong size");
+        
+        let err = AppError::Midi("No output ports".to_string());
+        assert_eq!(err.to_string(), "MIDI error: No output ports");
+        
+        let err = AppError::Project("Invalid project file".to_string());
+        assert_eq!(err.to_string(), "Project error: Invalid project file");
     }
 
     #[test]
@@ -57,9 +73,6 @@ mod tests {
         let io_err = io::Error::new(io::ErrorKind::NotFound, "File not found");
         let app_err: AppError = io_err.into();
         
-        match app_err {
-            AppError::Io(_) => assert!(true),
-            _ => assert!(false, "Expected Io variant"),
-        }
+        assert!(matches!(app_err, AppError::Io(_)), "Expected Io variant");
     }
 }
\ No newline at end of file
@@ -1,8 +1,10 @@
-use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use std::collections::HashMap;
+use crate::app::{App, NUDGE_STEP_MS, TRIM_STEP};
 use crate::command::Command;
-use crate::config::Config;
-use crate::error::Result;
+use crate::config::{Config, DEFAULT_TEMPO_STEP, TEMPO_STEP_RANGE};
+use crate::state::ViewMode;
+use crate::ui::{HitAreas, HitTarget, PATTERN_MAP_COLUMNS};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct KeyBinding {
@@ -21,6 +23,38 @@ impl KeyBinding {
     pub fn with_modifiers(code: KeyCode, modifiers: KeyModifiers) -> Self {
         Self { code, modifiers }
     }
+
+    // Accepts anything parse_key_code does, optionally prefixed with "Ctrl+" or "Shift+".
+    // Shift+ on a letter means its capital, the way from_event reports it; other characters
+    // shift to whatever the keyboard layout says, so those don't parse
+    pub fn parse(config: &Config, key_str: &str) -> Option<Self> {
+        let (modifiers, rest) = if let Some(rest) = key_str.strip_prefix("Ctrl+") {
+            (KeyModifiers::CONTROL, rest)
+        } else if let Some(rest) = key_str.strip_prefix("Shift+") {
+            (KeyModifiers::SHIFT, rest)
+        } else {
+            (KeyModifiers::empty(), key_str)
+        };
+        let code = match config.parse_key_code(rest)? {
+            KeyCode::Char(c) if c != ' ' && modifiers == KeyModifiers::SHIFT => {
+                KeyCode::Char(c.is_ascii_alphabetic().then(|| c.to_ascii_uppercase())?)
+            }
+            code => code,
+        };
+        Some(Self::from_event(code, modifiers))
+    }
+
+    pub fn from_event(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Terminals report shifted characters (and BackTab) with SHIFT set even though
+        // the key code already encodes it, which would never match bindings like 'M'.
+        // Space has no shifted form, so Shift+Space stays distinct.
+        let modifiers = match code {
+            KeyCode::Char(c) if c != ' ' => modifiers - KeyModifiers::SHIFT,
+            KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self::with_modifiers(code, modifiers)
+    }
 }
 
 pub struct InputMapper {
@@ -38,79 +72,307 @@ impl InputMapper {
         Self::default()
     }
     
-    pub fn from_config(config: &Config) -> Result<Self> {
+    // Keys that don't parse are left unbound; Config::validate reports them by path
+    pub fn from_config(config: &Config) -> Self {
         let mut bindings = HashMap::new();
         
         // Transport controls
-        if let Some(key) = config.parse_key_code(&config.key_bindings.transport.play_stop) {
-            bindings.insert(KeyBinding::new(key), Command::TogglePlayback);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.play_stop) {
+            let shifted = KeyBinding::with_modifiers(binding.code, binding.modifiers | KeyModifiers::SHIFT);
+            bindings.insert(binding, Command::TogglePlayback);
+            bindings.insert(shifted, Command::Stop);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.stop) {
+            bindings.insert(binding, Command::Stop);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.record) {
+            bindings.insert(binding, Command::ToggleRecording);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.clear) {
+            bindings.insert(binding, Command::ClearPattern);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.reset_pattern) {
+            bindings.insert(binding, Command::ResetPattern);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.audition_start) {
+            bindings.insert(binding, Command::StartAudition);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.audition_stop) {
+            bindings.insert(binding, Command::StopAudition);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.sample) {
+            bindings.insert(binding, Command::ArmSampling);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.resample) {
+            bindings.insert(binding, Command::ResamplePatternToPad);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.synth_kit) {
+            bindings.insert(binding, Command::FillEmptyPadsWithSynth);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.record_output) {
+            bindings.insert(binding, Command::ToggleOutputRecording);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.panic) {
+            bindings.insert(binding, Command::Panic);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.export_pattern) {
+            bindings.insert(binding, Command::ExportPattern);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.import_pattern) {
+            bindings.insert(binding, Command::ImportPattern);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.transport.record) {
-            bindings.insert(KeyBinding::new(key), Command::ToggleRecording);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.save_pad_settings) {
+            bindings.insert(binding, Command::SavePadSettings);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.transport.clear) {
-            bindings.insert(KeyBinding::new(key), Command::ClearPattern);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.quick_save) {
+            bindings.insert(binding, Command::QuickSave);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.quick_load) {
+            bindings.insert(binding, Command::QuickLoad);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.reload_config) {
+            bindings.insert(binding, Command::ReloadConfig);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.transport.reinit_audio) {
+            bindings.insert(binding, Command::ReinitAudio);
         }
         
         // Navigation
-        if let Some(key) = config.parse_key_code(&config.key_bindings.navigation.next_group) {
-            bindings.insert(KeyBinding::new(key), Command::NextGroup);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.next_group) {
+            bindings.insert(binding, Command::NextGroup);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.prev_group) {
+            bindings.insert(binding, Command::PrevGroup);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.next_pattern) {
+            bindings.insert(binding, Command::NextPattern);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.prev_pattern) {
+            bindings.insert(binding, Command::PrevPattern);
+        }
+        let tempo_step = config.key_bindings.navigation.tempo_step.clamp(*TEMPO_STEP_RANGE.start(), *TEMPO_STEP_RANGE.end());
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_up) {
+            bindings.insert(binding, Command::IncreaseTempo(tempo_step));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_down) {
+            bindings.insert(binding, Command::DecreaseTempo(tempo_step));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_bpm_up) {
+            bindings.insert(binding, Command::IncreaseTempo(1));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_bpm_down) {
+            bindings.insert(binding, Command::DecreaseTempo(1));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_fine_up) {
+            bindings.insert(binding, Command::AdjustTempoFine(0.1));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_fine_down) {
+            bindings.insert(binding, Command::AdjustTempoFine(-0.1));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.tempo_breakpoint) {
+            bindings.insert(binding, Command::AddTempoBreakpoint);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.clear_tempo_automation) {
+            bindings.insert(binding, Command::ClearTempoAutomation);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.capture_pattern_tempo) {
+            bindings.insert(binding, Command::CapturePatternTempo);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.clear_pattern_tempo) {
+            bindings.insert(binding, Command::ClearPatternTempo);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.toggle_panel) {
+            bindings.insert(binding, Command::TogglePanel);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.navigation.prev_group) {
-            bindings.insert(KeyBinding::new(key), Command::PrevGroup);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.toggle_overview) {
+            bindings.insert(binding, Command::ToggleOverview);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.navigation.next_pattern) {
-            bindings.insert(KeyBinding::new(key), Command::NextPattern);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.goto_pattern) {
+            bindings.insert(binding, Command::OpenGotoPattern);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.navigation.prev_pattern) {
-            bindings.insert(KeyBinding::new(key), Command::PrevPattern);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.morph) {
+            bindings.insert(binding, Command::OpenMorph);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.navigation.tempo_up) {
-            bindings.insert(KeyBinding::new(key), Command::IncreaseTempo(5));
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.signature) {
+            bindings.insert(binding, Command::OpenSignature);
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.navigation.tempo_down) {
-            bindings.insert(KeyBinding::new(key), Command::DecreaseTempo(5));
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.pattern_map) {
+            bindings.insert(binding, Command::TogglePatternMap);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.pad_detail) {
+            bindings.insert(binding, Command::TogglePadDetail);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.pad_bank) {
+            bindings.insert(binding, Command::CyclePadBank);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.layer_mode) {
+            bindings.insert(binding, Command::CycleLayerMode);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.copy_pattern) {
+            bindings.insert(binding, Command::CopyPattern);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.paste_pattern) {
+            bindings.insert(binding, Command::PastePattern);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.paste_pattern_compact) {
+            bindings.insert(binding, Command::PastePatternCompact);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.duplicate_pattern) {
+            bindings.insert(binding, Command::DuplicateToNewSlot);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.chain_append) {
+            bindings.insert(binding, Command::AppendToChain);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.chain_clear) {
+            bindings.insert(binding, Command::ClearChain);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.next_kit) {
+            bindings.insert(binding, Command::NextKit);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.prev_kit) {
+            bindings.insert(binding, Command::PrevKit);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.import_folder) {
+            bindings.insert(binding, Command::ImportFolderToGroup);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.transpose_mode) {
+            bindings.insert(binding, Command::ToggleTransposeMode);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.navigation.note_repeat) {
+            bindings.insert(binding, Command::ToggleNoteRepeat);
+        }
+        
+        // Step editing
+        let step_edit = &config.key_bindings.step_edit;
+        let step_edit_bindings = [
+            (&step_edit.grid_mode, Command::CycleGridMode),
+            (&step_edit.cursor_left, Command::MoveStepCursor(-1)),
+            (&step_edit.cursor_right, Command::MoveStepCursor(1)),
+            (&step_edit.velocity_up, Command::IncreaseStepVelocity),
+            (&step_edit.velocity_down, Command::DecreaseStepVelocity),
+            (&step_edit.probability_up, Command::IncreaseStepProbability),
+            (&step_edit.probability_down, Command::DecreaseStepProbability),
+            (&step_edit.step_mute, Command::ToggleStepMute),
+            (&step_edit.page_prev, Command::MoveStepPage(-1)),
+            (&step_edit.page_next, Command::MoveStepPage(1)),
+            (&step_edit.nudge_earlier, Command::NudgeStep(-NUDGE_STEP_MS)),
+            (&step_edit.nudge_later, Command::NudgeStep(NUDGE_STEP_MS)),
+            (&step_edit.swing, Command::CycleSwing),
+            (&step_edit.clock_division, Command::CycleGroupClockDiv),
+            (&step_edit.accent, Command::ToggleAccentAtCursor),
+            (&step_edit.humanize_pad, Command::HumanizePad),
+            (&step_edit.humanize_pattern, Command::HumanizePattern),
+            (&step_edit.undo, Command::Undo),
+            (&step_edit.fill_pad, Command::FillSelectedPad),
+            (&step_edit.clear_pad, Command::ClearSelectedPad),
+            (&step_edit.thin_pattern, Command::ThinPattern),
+            (&step_edit.euclidean, Command::OpenEuclidean),
+            (&step_edit.ratchet, Command::CycleRatchet),
+        ];
+        for (key_str, command) in step_edit_bindings {
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, command);
+            }
+        }
+        
+        // Send effects
+        let sends = &config.key_bindings.sends;
+        let send_bindings = [
+            (&sends.delay_up, Command::AdjustDelaySend(0.05)),
+            (&sends.delay_down, Command::AdjustDelaySend(-0.05)),
+            (&sends.reverb_up, Command::AdjustReverbSend(0.05)),
+            (&sends.reverb_down, Command::AdjustReverbSend(-0.05)),
+            (&sends.reverb_size, Command::CycleReverbSize),
+        ];
+        for (key_str, command) in send_bindings {
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, command);
+            }
+        }
+        
+        // Sample trim for the selected pad
+        let trim = &config.key_bindings.trim;
+        let trim_bindings = [
+            (&trim.start_earlier, Command::AdjustTrimStart(-TRIM_STEP)),
+            (&trim.start_later, Command::AdjustTrimStart(TRIM_STEP)),
+            (&trim.end_earlier, Command::AdjustTrimEnd(-TRIM_STEP)),
+            (&trim.end_later, Command::AdjustTrimEnd(TRIM_STEP)),
+        ];
+        for (key_str, command) in trim_bindings {
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, command);
+            }
         }
         
         // Volume controls
-        if let Some(key) = config.parse_key_code(&config.key_bindings.volume.master_up) {
-            bindings.insert(KeyBinding::new(key), Command::AdjustMasterVolume(0.05));
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.master_up) {
+            bindings.insert(binding, Command::AdjustMasterVolume(0.05));
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.volume.master_down) {
-            bindings.insert(KeyBinding::new(key), Command::AdjustMasterVolume(-0.05));
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.master_down) {
+            bindings.insert(binding, Command::AdjustMasterVolume(-0.05));
         }
-        if let Some(key) = config.parse_key_code(&config.key_bindings.volume.master_mute) {
-            bindings.insert(KeyBinding::new(key), Command::ToggleMasterMute);
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.master_mute) {
+            bindings.insert(binding, Command::ToggleMasterMute);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.master_gain) {
+            bindings.insert(binding, Command::OpenMasterGain);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.width_up) {
+            bindings.insert(binding, Command::AdjustMasterWidth(0.1));
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.width_down) {
+            bindings.insert(binding, Command::AdjustMasterWidth(-0.1));
         }
         
         // Group volume and mute controls
         for (i, key_str) in config.key_bindings.volume.group_up.iter().enumerate() {
-            if let Some(key) = config.parse_key_code(key_str) {
-                bindings.insert(KeyBinding::new(key), Command::AdjustGroupVolume(i, 0.05));
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::AdjustGroupVolume(i, 0.05));
             }
         }
         for (i, key_str) in config.key_bindings.volume.group_down.iter().enumerate() {
-            if let Some(key) = config.parse_key_code(key_str) {
-                bindings.insert(KeyBinding::new(key), Command::AdjustGroupVolume(i, -0.05));
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::AdjustGroupVolume(i, -0.05));
             }
         }
         for (i, key_str) in config.key_bindings.volume.group_mute.iter().enumerate() {
-            if let Some(key) = config.parse_key_code(key_str) {
-                bindings.insert(KeyBinding::new(key), Command::ToggleGroupMute(i));
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::ToggleGroupMute(i));
+            }
+        }
+        for (i, key_str) in config.key_bindings.volume.group_solo.iter().enumerate() {
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::ToggleGroupSolo(i));
+            }
+        }
+        for (i, key_str) in config.key_bindings.volume.group_gate.iter().enumerate() {
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::ToggleGroupGate(i));
             }
         }
+        for (i, key_str) in config.key_bindings.volume.group_record_arm.iter().enumerate() {
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::ToggleRecordArm(i));
+            }
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.pad_mute) {
+            bindings.insert(binding, Command::ToggleSelectedPadMute);
+        }
+        if let Some(binding) = KeyBinding::parse(config, &config.key_bindings.volume.clear_automation) {
+            bindings.insert(binding, Command::ClearAutomation);
+        }
         
         // Pad triggers
         for (key_str, &pad) in &config.key_bindings.pads {
-            if let Some(key) = config.parse_key_code(key_str) {
-                bindings.insert(KeyBinding::new(key), Command::TriggerPad(pad));
+            if let Some(binding) = KeyBinding::parse(config, key_str) {
+                bindings.insert(binding, Command::TriggerPad(pad));
             }
         }
         
         // Application
         bindings.insert(KeyBinding::new(KeyCode::Esc), Command::Quit);
         
-        Ok(Self { bindings })
+        Self { bindings }
     }
     
     fn from_default_bindings() -> Self {
@@ -118,23 +380,96 @@ impl InputMapper {
         
         // Transport controls
         bindings.insert(KeyBinding::new(KeyCode::Char(' ')), Command::TogglePlayback);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char(' '), KeyModifiers::SHIFT), Command::Stop);
+        bindings.insert(KeyBinding::new(KeyCode::Char('s')), Command::Stop);
         bindings.insert(KeyBinding::new(KeyCode::Char('r')), Command::ToggleRecording);
         bindings.insert(KeyBinding::new(KeyCode::Char('c')), Command::ClearPattern);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('d'), KeyModifiers::CONTROL), Command::ResetPattern);
+        bindings.insert(KeyBinding::new(KeyCode::Char('a')), Command::StartAudition);
+        bindings.insert(KeyBinding::new(KeyCode::Char('A')), Command::StopAudition);
+        bindings.insert(KeyBinding::new(KeyCode::Char('S')), Command::ArmSampling);
+        bindings.insert(KeyBinding::new(KeyCode::Char('B')), Command::ResamplePatternToPad);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('k'), KeyModifiers::CONTROL), Command::FillEmptyPadsWithSynth);
+        bindings.insert(KeyBinding::new(KeyCode::Char('W')), Command::ToggleOutputRecording);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('.'), KeyModifiers::CONTROL), Command::Panic);
+        bindings.insert(KeyBinding::new(KeyCode::Char('x')), Command::ExportPattern);
+        bindings.insert(KeyBinding::new(KeyCode::Char('X')), Command::ImportPattern);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('p'), KeyModifiers::CONTROL), Command::SavePadSettings);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('s'), KeyModifiers::CONTROL), Command::QuickSave);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('o'), KeyModifiers::CONTROL), Command::QuickLoad);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('r'), KeyModifiers::CONTROL), Command::ReloadConfig);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('a'), KeyModifiers::CONTROL), Command::ReinitAudio);
         
         // Navigation
         bindings.insert(KeyBinding::new(KeyCode::Tab), Command::NextGroup);
         bindings.insert(KeyBinding::new(KeyCode::BackTab), Command::PrevGroup);
         bindings.insert(KeyBinding::new(KeyCode::Right), Command::NextPattern);
         bindings.insert(KeyBinding::new(KeyCode::Left), Command::PrevPattern);
+        bindings.insert(KeyBinding::new(KeyCode::Char('w')), Command::TogglePanel);
+        bindings.insert(KeyBinding::new(KeyCode::Char('v')), Command::ToggleOverview);
+        bindings.insert(KeyBinding::new(KeyCode::Char('g')), Command::OpenGotoPattern);
+        bindings.insert(KeyBinding::new(KeyCode::Char('G')), Command::OpenMorph);
+        bindings.insert(KeyBinding::new(KeyCode::Char('E')), Command::OpenSignature);
+        bindings.insert(KeyBinding::new(KeyCode::Char('P')), Command::TogglePatternMap);
+        bindings.insert(KeyBinding::new(KeyCode::Char('I')), Command::TogglePadDetail);
+        bindings.insert(KeyBinding::new(KeyCode::Char('O')), Command::CyclePadBank);
+        bindings.insert(KeyBinding::new(KeyCode::Char('L')), Command::CycleLayerMode);
+        bindings.insert(KeyBinding::new(KeyCode::Char('C')), Command::CopyPattern);
+        bindings.insert(KeyBinding::new(KeyCode::Char('V')), Command::PastePattern);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('v'), KeyModifiers::CONTROL), Command::PastePatternCompact);
+        bindings.insert(KeyBinding::new(KeyCode::Char('D')), Command::DuplicateToNewSlot);
+        bindings.insert(KeyBinding::new(KeyCode::Char('b')), Command::AppendToChain);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('b'), KeyModifiers::CONTROL), Command::ClearChain);
+        bindings.insert(KeyBinding::new(KeyCode::Char('K')), Command::NextKit);
+        bindings.insert(KeyBinding::new(KeyCode::Char('J')), Command::PrevKit);
+        bindings.insert(KeyBinding::new(KeyCode::Char('F')), Command::ImportFolderToGroup);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('n'), KeyModifiers::CONTROL), Command::ToggleTransposeMode);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('l'), KeyModifiers::CONTROL), Command::ToggleNoteRepeat);
         
         // Tempo
-        bindings.insert(KeyBinding::new(KeyCode::Up), Command::IncreaseTempo(5));
-        bindings.insert(KeyBinding::new(KeyCode::Down), Command::DecreaseTempo(5));
+        bindings.insert(KeyBinding::new(KeyCode::Up), Command::IncreaseTempo(DEFAULT_TEMPO_STEP));
+        bindings.insert(KeyBinding::new(KeyCode::Down), Command::DecreaseTempo(DEFAULT_TEMPO_STEP));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Up, KeyModifiers::SHIFT), Command::IncreaseTempo(1));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Down, KeyModifiers::SHIFT), Command::DecreaseTempo(1));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Up, KeyModifiers::CONTROL), Command::AdjustTempoFine(0.1));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Down, KeyModifiers::CONTROL), Command::AdjustTempoFine(-0.1));
+        bindings.insert(KeyBinding::new(KeyCode::Char('t')), Command::AddTempoBreakpoint);
+        bindings.insert(KeyBinding::new(KeyCode::Char('T')), Command::ClearTempoAutomation);
+        bindings.insert(KeyBinding::new(KeyCode::Char('U')), Command::CapturePatternTempo);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('u'), KeyModifiers::CONTROL), Command::ClearPatternTempo);
+        
+        // Step editing
+        bindings.insert(KeyBinding::new(KeyCode::Char('e')), Command::CycleGridMode);
+        bindings.insert(KeyBinding::new(KeyCode::Char('[')), Command::MoveStepCursor(-1));
+        bindings.insert(KeyBinding::new(KeyCode::Char(']')), Command::MoveStepCursor(1));
+        bindings.insert(KeyBinding::new(KeyCode::Char('}')), Command::IncreaseStepVelocity);
+        bindings.insert(KeyBinding::new(KeyCode::Char('{')), Command::DecreaseStepVelocity);
+        bindings.insert(KeyBinding::new(KeyCode::Char(')')), Command::IncreaseStepProbability);
+        bindings.insert(KeyBinding::new(KeyCode::Char('(')), Command::DecreaseStepProbability);
+        bindings.insert(KeyBinding::new(KeyCode::Char('n')), Command::ToggleStepMute);
+        bindings.insert(KeyBinding::new(KeyCode::Char('R')), Command::CycleRatchet);
+        bindings.insert(KeyBinding::new(KeyCode::Char('h')), Command::NudgeStep(-NUDGE_STEP_MS));
+        bindings.insert(KeyBinding::new(KeyCode::Char('H')), Command::NudgeStep(NUDGE_STEP_MS));
+        bindings.insert(KeyBinding::new(KeyCode::Char('q')), Command::CycleSwing);
+        bindings.insert(KeyBinding::new(KeyCode::Char('d')), Command::CycleGroupClockDiv);
+        bindings.insert(KeyBinding::new(KeyCode::Char('f')), Command::ToggleAccentAtCursor);
+        bindings.insert(KeyBinding::new(KeyCode::Char('y')), Command::HumanizePad);
+        bindings.insert(KeyBinding::new(KeyCode::Char('Y')), Command::HumanizePattern);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('z'), KeyModifiers::CONTROL), Command::Undo);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('f'), KeyModifiers::CONTROL), Command::FillSelectedPad);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('x'), KeyModifiers::CONTROL), Command::ClearSelectedPad);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('t'), KeyModifiers::CONTROL), Command::ThinPattern);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('e'), KeyModifiers::CONTROL), Command::OpenEuclidean);
+        bindings.insert(KeyBinding::new(KeyCode::PageUp), Command::MoveStepPage(-1));
+        bindings.insert(KeyBinding::new(KeyCode::PageDown), Command::MoveStepPage(1));
         
         // Volume controls
         bindings.insert(KeyBinding::new(KeyCode::Char('=')), Command::AdjustMasterVolume(0.05));
         bindings.insert(KeyBinding::new(KeyCode::Char('-')), Command::AdjustMasterVolume(-0.05));
         bindings.insert(KeyBinding::new(KeyCode::Char('M')), Command::ToggleMasterMute);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('g'), KeyModifiers::CONTROL), Command::OpenMasterGain);
+        bindings.insert(KeyBinding::new(KeyCode::Char('+')), Command::AdjustMasterWidth(0.1));
+        bindings.insert(KeyBinding::new(KeyCode::Char('_')), Command::AdjustMasterWidth(-0.1));
         
         // Group volume controls
         bindings.insert(KeyBinding::new(KeyCode::Char('1')), Command::AdjustGroupVolume(0, 0.05));
@@ -146,11 +481,38 @@ impl InputMapper {
         bindings.insert(KeyBinding::new(KeyCode::Char('4')), Command::AdjustGroupVolume(3, 0.05));
         bindings.insert(KeyBinding::new(KeyCode::Char('$')), Command::AdjustGroupVolume(3, -0.05));
         
+        // Send effects for the current group
+        bindings.insert(KeyBinding::new(KeyCode::Char('5')), Command::AdjustDelaySend(0.05));
+        bindings.insert(KeyBinding::new(KeyCode::Char('%')), Command::AdjustDelaySend(-0.05));
+        bindings.insert(KeyBinding::new(KeyCode::Char('6')), Command::AdjustReverbSend(0.05));
+        bindings.insert(KeyBinding::new(KeyCode::Char('^')), Command::AdjustReverbSend(-0.05));
+        bindings.insert(KeyBinding::new(KeyCode::Char('&')), Command::CycleReverbSize);
+        
+        // Sample trim for the selected pad
+        bindings.insert(KeyBinding::new(KeyCode::Char('<')), Command::AdjustTrimStart(-TRIM_STEP));
+        bindings.insert(KeyBinding::new(KeyCode::Char('>')), Command::AdjustTrimStart(TRIM_STEP));
+        bindings.insert(KeyBinding::new(KeyCode::Char('z')), Command::AdjustTrimEnd(-TRIM_STEP));
+        bindings.insert(KeyBinding::new(KeyCode::Char('Z')), Command::AdjustTrimEnd(TRIM_STEP));
+        
         // Group mute controls
         bindings.insert(KeyBinding::new(KeyCode::F(1)), Command::ToggleGroupMute(0));
         bindings.insert(KeyBinding::new(KeyCode::F(2)), Command::ToggleGroupMute(1));
         bindings.insert(KeyBinding::new(KeyCode::F(3)), Command::ToggleGroupMute(2));
         bindings.insert(KeyBinding::new(KeyCode::F(4)), Command::ToggleGroupMute(3));
+        bindings.insert(KeyBinding::new(KeyCode::F(5)), Command::ToggleGroupSolo(0));
+        bindings.insert(KeyBinding::new(KeyCode::F(6)), Command::ToggleGroupSolo(1));
+        bindings.insert(KeyBinding::new(KeyCode::F(7)), Command::ToggleGroupSolo(2));
+        bindings.insert(KeyBinding::new(KeyCode::F(8)), Command::ToggleGroupSolo(3));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(1), KeyModifiers::SHIFT), Command::ToggleGroupGate(0));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(2), KeyModifiers::SHIFT), Command::ToggleGroupGate(1));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(3), KeyModifiers::SHIFT), Command::ToggleGroupGate(2));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(4), KeyModifiers::SHIFT), Command::ToggleGroupGate(3));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(1), KeyModifiers::CONTROL), Command::ToggleRecordArm(0));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(2), KeyModifiers::CONTROL), Command::ToggleRecordArm(1));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(3), KeyModifiers::CONTROL), Command::ToggleRecordArm(2));
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::F(4), KeyModifiers::CONTROL), Command::ToggleRecordArm(3));
+        bindings.insert(KeyBinding::new(KeyCode::Char('N')), Command::ToggleSelectedPadMute);
+        bindings.insert(KeyBinding::with_modifiers(KeyCode::Char('w'), KeyModifiers::CONTROL), Command::ClearAutomation);
         
         // Pad triggers
         let pad_mappings = [
@@ -187,6 +549,76 @@ impl InputMapper {
     }
 }
 
+// While a prompt is open it gets every key; anything it doesn't use is swallowed
+pub fn prompt_command(key: &KeyBinding) -> Option<Command> {
+    match key.code {
+        KeyCode::Enter => Some(Command::PromptSubmit),
+        KeyCode::Esc => Some(Command::PromptCancel),
+        KeyCode::Backspace => Some(Command::PromptBackspace),
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => Some(Command::PromptInput(c)),
+        _ => None,
+    }
+}
+
+// The open pattern map is navigated with the arrows; Enter picks, Esc closes
+pub fn pattern_map_command(key: &KeyBinding) -> Option<Command> {
+    match key.code {
+        KeyCode::Left => Some(Command::MovePatternMapCursor(-1)),
+        KeyCode::Right => Some(Command::MovePatternMapCursor(1)),
+        KeyCode::Up => Some(Command::MovePatternMapCursor(-(PATTERN_MAP_COLUMNS as i32))),
+        KeyCode::Down => Some(Command::MovePatternMapCursor(PATTERN_MAP_COLUMNS as i32)),
+        KeyCode::Enter => Some(Command::SelectPatternMapSlot),
+        KeyCode::Esc => Some(Command::TogglePatternMap),
+        _ => None,
+    }
+}
+
+// In the overview the number keys pick a lane instead of changing group volume
+pub fn overview_command(key: &KeyBinding, groups: usize) -> Option<Command> {
+    match key.code {
+        KeyCode::Char(c @ '1'..='9') if key.modifiers.is_empty() => {
+            let group = c as usize - '1' as usize;
+            (group < groups).then_some(Command::SelectGroup(group))
+        }
+        _ => None,
+    }
+}
+
+// Open prompts and the overview see keys before the regular bindings do
+pub fn key_command(app: &App, input_mapper: &InputMapper, binding: &KeyBinding) -> Option<Command> {
+    if app.ui_state.prompt.is_some() {
+        return prompt_command(binding);
+    }
+    if app.ui_state.pattern_map.is_some() {
+        // Keys the map doesn't use still reach the regular bindings, so playing pads works
+        if let Some(command) = pattern_map_command(binding) {
+            return Some(command);
+        }
+    }
+    if app.ui_state.view == ViewMode::Overview {
+        if let Some(command) = overview_command(binding, app.group_count()) {
+            return Some(command);
+        }
+    }
+    match input_mapper.get_command(binding) {
+        Some(Command::TriggerPad(pad)) => Some(Command::TriggerPad(app.bank_pad(*pad))),
+        command => command.cloned(),
+    }
+}
+
+// Left clicks play pads and flip grid steps; the wheel over the tempo box nudges BPM
+pub fn mouse_command(event: &MouseEvent, areas: &HitAreas) -> Option<Command> {
+    let target = areas.target_at(event.column, event.row)?;
+    match (event.kind, target) {
+        (MouseEventKind::Down(MouseButton::Left), HitTarget::Pad(pad)) => Some(Command::TriggerPad(pad)),
+        (MouseEventKind::Down(MouseButton::Left), HitTarget::Step(pad, step)) => Some(Command::ToggleStep(pad, step)),
+        (MouseEventKind::Down(MouseButton::Left), HitTarget::Accent(step)) => Some(Command::ToggleAccent(step)),
+        (MouseEventKind::ScrollUp, HitTarget::Tempo) => Some(Command::IncreaseTempo(1)),
+        (MouseEventKind::ScrollDown, HitTarget::Tempo) => Some(Command::DecreaseTempo(1)),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,6 +634,64 @@ mod tests {
         assert_eq!(binding.modifiers, KeyModifiers::CONTROL);
     }
 
+    #[test]
+    fn test_key_binding_from_event_drops_shift_for_chars() {
+        let binding = KeyBinding::from_event(KeyCode::Char('M'), KeyModifiers::SHIFT);
+        assert_eq!(binding, KeyBinding::new(KeyCode::Char('M')));
+        
+        let binding = KeyBinding::from_event(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(binding, KeyBinding::new(KeyCode::BackTab));
+        
+        // Non-character keys keep their modifiers
+        let binding = KeyBinding::from_event(KeyCode::Up, KeyModifiers::SHIFT);
+        assert_eq!(binding.modifiers, KeyModifiers::SHIFT);
+        
+        // Shift+Space stops while Space plays/pauses
+        let mapper = InputMapper::from_default_bindings();
+        let binding = KeyBinding::from_event(KeyCode::Char(' '), KeyModifiers::SHIFT);
+        assert_eq!(mapper.get_command(&binding), Some(&Command::Stop));
+    }
+
+    #[test]
+    fn test_key_binding_parse_ctrl() {
+        let config = Config::default();
+        
+        let binding = KeyBinding::parse(&config, "Ctrl+.").unwrap();
+        assert_eq!(binding, KeyBinding::with_modifiers(KeyCode::Char('.'), KeyModifiers::CONTROL));
+        assert_eq!(KeyBinding::parse(&config, "Tab"), Some(KeyBinding::new(KeyCode::Tab)));
+        assert_eq!(KeyBinding::parse(&config, "Ctrl+Nope"), None);
+        
+        let mapper = InputMapper::from_config(&config);
+        assert_eq!(mapper.get_command(&binding), Some(&Command::Panic));
+    }
+
+    #[test]
+    fn test_tempo_step_from_config() {
+        let mut config = Config::default();
+        config.key_bindings.navigation.tempo_step = 8;
+        let mapper = InputMapper::from_config(&config);
+        assert_eq!(mapper.get_command(&KeyBinding::new(KeyCode::Up)), Some(&Command::IncreaseTempo(8)));
+        assert_eq!(mapper.get_command(&KeyBinding::new(KeyCode::Down)), Some(&Command::DecreaseTempo(8)));
+        
+        // Shift+arrows move a single BPM, whatever the step
+        let shift_up = KeyBinding::from_event(KeyCode::Up, KeyModifiers::SHIFT);
+        let shift_down = KeyBinding::from_event(KeyCode::Down, KeyModifiers::SHIFT);
+        assert_eq!(KeyBinding::parse(&config, "Shift+Up"), Some(shift_up.clone()));
+        
+        // A shifted letter is its capital, as the terminal reports it; shifted symbols depend on the layout
+        assert_eq!(KeyBinding::parse(&config, "Shift+m"), Some(KeyBinding::from_event(KeyCode::Char('M'), KeyModifiers::SHIFT)));
+        assert_eq!(KeyBinding::parse(&config, "Shift+1"), None);
+        assert_eq!(KeyBinding::parse(&config, "Shift+M"), KeyBinding::parse(&config, "M"));
+        assert_eq!(mapper.get_command(&shift_up), Some(&Command::IncreaseTempo(1)));
+        assert_eq!(mapper.get_command(&shift_down), Some(&Command::DecreaseTempo(1)));
+        
+        // Out-of-range steps are clamped here and reported by validate()
+        config.key_bindings.navigation.tempo_step = 50;
+        let mapper = InputMapper::from_config(&config);
+        assert_eq!(mapper.get_command(&KeyBinding::new(KeyCode::Up)), Some(&Command::IncreaseTempo(20)));
+        assert_eq!(InputMapper::default().get_command(&shift_up), Some(&Command::IncreaseTempo(1)));
+    }
+
     #[test]
     fn test_default_input_mapper() {
         let mapper = InputMapper::default();
@@ -248,4 +738,84 @@ mod tests {
             assert_eq!(mapper.get_command(&binding), Some(&Command::TriggerPad(expected_pad)));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_prompt_takes_every_key() {
+        assert_eq!(prompt_command(&KeyBinding::new(KeyCode::Char('7'))), Some(Command::PromptInput('7')));
+        assert_eq!(prompt_command(&KeyBinding::new(KeyCode::Enter)), Some(Command::PromptSubmit));
+        assert_eq!(prompt_command(&KeyBinding::new(KeyCode::Esc)), Some(Command::PromptCancel));
+        assert_eq!(prompt_command(&KeyBinding::new(KeyCode::Backspace)), Some(Command::PromptBackspace));
+        assert_eq!(prompt_command(&KeyBinding::new(KeyCode::Tab)), None);
+        
+        let mapper = InputMapper::default();
+        assert_eq!(mapper.get_command(&KeyBinding::new(KeyCode::Char('g'))), Some(&Command::OpenGotoPattern));
+    }
+
+    #[test]
+    fn test_pattern_map_keys() {
+        assert_eq!(pattern_map_command(&KeyBinding::new(KeyCode::Up)), Some(Command::MovePatternMapCursor(-10)));
+        assert_eq!(pattern_map_command(&KeyBinding::new(KeyCode::Right)), Some(Command::MovePatternMapCursor(1)));
+        assert_eq!(pattern_map_command(&KeyBinding::new(KeyCode::Enter)), Some(Command::SelectPatternMapSlot));
+        assert_eq!(pattern_map_command(&KeyBinding::new(KeyCode::Esc)), Some(Command::TogglePatternMap));
+        assert_eq!(pattern_map_command(&KeyBinding::new(KeyCode::Char('7'))), None);
+
+        let mapper = InputMapper::default();
+        assert_eq!(mapper.get_command(&KeyBinding::new(KeyCode::Char('P'))), Some(&Command::TogglePatternMap));
+    }
+
+    #[test]
+    fn test_overview_number_keys_select_lanes() {
+        assert_eq!(overview_command(&KeyBinding::new(KeyCode::Char('1')), 4), Some(Command::SelectGroup(0)));
+        assert_eq!(overview_command(&KeyBinding::new(KeyCode::Char('4')), 4), Some(Command::SelectGroup(3)));
+        assert_eq!(overview_command(&KeyBinding::new(KeyCode::Char('5')), 4), None);
+        assert_eq!(overview_command(&KeyBinding::new(KeyCode::Char('6')), 6), Some(Command::SelectGroup(5)));
+        assert_eq!(overview_command(&KeyBinding::with_modifiers(KeyCode::Char('1'), KeyModifiers::CONTROL), 4), None);
+        
+        let mapper = InputMapper::default();
+        assert_eq!(mapper.get_command(&KeyBinding::new(KeyCode::Char('v'))), Some(&Command::ToggleOverview));
+    }
+
+    #[test]
+    fn test_mouse_commands() {
+        use crate::app::App;
+        use crate::theme::Theme;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let app = App::new().unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(200, 50)).unwrap();
+        let mut areas = HitAreas::default();
+        terminal.draw(|f| areas = crate::ui::draw(f, &app, &Theme::default())).unwrap();
+
+        let find = |wanted: HitTarget| {
+            (0..200u16)
+                .flat_map(|column| (0..50u16).map(move |row| (column, row)))
+                .find(|&(column, row)| areas.target_at(column, row) == Some(wanted))
+                .unwrap()
+        };
+        let event = |kind, (column, row)| MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE };
+        let click = MouseEventKind::Down(MouseButton::Left);
+
+        assert_eq!(mouse_command(&event(click, find(HitTarget::Pad(3))), &areas), Some(Command::TriggerPad(3)));
+        assert_eq!(
+            mouse_command(&event(click, find(HitTarget::Step(2, 7))), &areas),
+            Some(Command::ToggleStep(2, 7))
+        );
+        assert_eq!(
+            mouse_command(&event(click, find(HitTarget::Accent(11))), &areas),
+            Some(Command::ToggleAccent(11))
+        );
+        assert_eq!(
+            mouse_command(&event(MouseEventKind::ScrollUp, find(HitTarget::Tempo)), &areas),
+            Some(Command::IncreaseTempo(1))
+        );
+        assert_eq!(
+            mouse_command(&event(MouseEventKind::ScrollDown, find(HitTarget::Tempo)), &areas),
+            Some(Command::DecreaseTempo(1))
+        );
+
+        // Scrolling over pads and right clicks do nothing
+        assert_eq!(mouse_command(&event(MouseEventKind::ScrollUp, find(HitTarget::Pad(0))), &areas), None);
+        let right = MouseEventKind::Down(MouseButton::Right);
+        assert_eq!(mouse_command(&event(right, find(HitTarget::Pad(0))), &areas), None);
+    }
+}
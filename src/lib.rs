@@ -0,0 +1,58 @@
+//! K.O.II Terminal as a library: the sampler/sequencer behind the terminal UI.
+//!
+//! [`app::App`] owns the whole instrument and is driven by [`command::Command`]s, the same
+//! way key presses, mouse clicks and OSC messages drive it in the binary. For tests or
+//! embedding, [`app::App::detached`] runs without an audio device:
+//!
+//! ```
+//! use ko2_terminal::app::App;
+//! use ko2_terminal::command::Command;
+//!
+//! let mut app = App::detached();
+//! Command::ToggleStep(0, 4).execute(&mut app).unwrap();
+//! assert!(app.get_current_pattern_data().unwrap().get_hit(0, 4));
+//! ```
+
+/// The application: sequencer, samples, mixer and UI state tied together.
+pub mod app;
+/// Everything the app can be told to do.
+pub mod command;
+/// `config.toml`: key bindings, theme and audio, MIDI and UI settings.
+pub mod config;
+/// The timing loop shared by the terminal UI and headless playback.
+pub mod engine;
+/// Error and result types used across the crate.
+pub mod error;
+/// Send effects on the master bus.
+pub mod effects;
+/// Key bindings from the config, and the keys prompts and views handle themselves.
+pub mod input;
+/// MIDI clock, transport and note output, and clock input.
+pub mod midi;
+/// The master bus: volumes, mutes, sends and per-pad playback settings.
+pub mod mixer;
+/// Per-pad pitch, playback mode and choke settings.
+pub mod pad_settings;
+/// Saving and loading whole projects, and crash recovery autosaves.
+pub mod project;
+/// Sample storage, decoding and the built-in synth drums.
+pub mod sample;
+/// Recording samples from an audio input.
+pub mod sampling;
+/// Patterns and the step sequencer that plays them.
+pub mod sequencer;
+/// Song arrangement.
+pub mod song;
+/// Transport and UI state.
+pub mod state;
+/// UI colors.
+pub mod theme;
+/// Entering the terminal UI and putting the terminal back on every way out.
+pub mod terminal;
+/// Drawing the terminal UI.
+pub mod ui;
+
+mod audio_manager;
+mod bus;
+mod osc;
+mod render;
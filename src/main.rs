@@ -1,48 +1,77 @@
-mod app;
-mod audio;
-mod mixer;
-mod ui;
-mod sequencer;
-mod sample;
-mod command;
-mod input;
-mod error;
-mod state;
-mod config;
-mod audio_manager;
-
 use std::io;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crossterm::{
     event::{self, Event, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
 
-use app::App;
-use sample::SampleBank;
-use command::Command;
-use input::{InputMapper, KeyBinding};
-use error::{AppError, Result};
-use config::Config;
+use ko2_terminal::app::App;
+use ko2_terminal::sample::{self, SampleBank, SAMPLE_CONFIG_PATH};
+use ko2_terminal::command::Command;
+use ko2_terminal::engine::Engine;
+use ko2_terminal::input::{key_command, mouse_command, InputMapper, KeyBinding};
+use ko2_terminal::error::{AppError, Result};
+use ko2_terminal::config::{Config, CONFIG_PATH};
+use ko2_terminal::project::{self, Autosave, Project};
+use ko2_terminal::terminal::{restore_on_panic, TerminalGuard};
+use ko2_terminal::theme::Theme;
+use ko2_terminal::{midi, sampling, ui};
 
 fn main() -> Result<()> {
     // Check for command line arguments
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    
+    // --osc <port> can go alongside any command
+    let mut osc_port = None;
+    if let Some(index) = args.iter().position(|arg| arg == "--osc") {
+        let port = args.get(index + 1).and_then(|port| port.parse::<u16>().ok());
+        if port.is_none() {
+            eprintln!("--osc needs a port number, e.g. --osc 9000");
+            std::process::exit(2);
+        }
+        osc_port = port;
+        args.drain(index..index + 2);
+    }
+    
+    // --dry-run makes commands that write files print what they'd write instead
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    args.retain(|arg| arg != "--dry-run");
+    
+    // --audio-test plays a loaded sample as the UI starts
+    let audio_test = args.iter().any(|arg| arg == "--audio-test");
+    args.retain(|arg| arg != "--audio-test");
     
     if args.len() > 1 {
         match args[1].as_str() {
             "generate-config" => {
-                SampleBank::generate_example_config()
+                // Group folders follow config.toml's [[groups]] when there is one
+                let mut bank = SampleBank::new();
+                bank.set_groups(Config::load().unwrap_or_default().group_defs());
+                bank.create_samples_directory(dry_run)?;
+                SampleBank::generate_example_config(dry_run)
                     .map_err(|e| AppError::Config(e.to_string()))?;
-                Config::generate_example()?;
+                Config::generate_example(dry_run)?;
                 return Ok(());
             }
+            "list-inputs" => {
+                for name in sampling::list_input_devices()? {
+                    println!("{}", name);
+                }
+                return Ok(());
+            }
+            "validate-samples" => return validate_samples(),
+            "list-midi" => {
+                for port in midi::list_output_ports() {
+                    println!("{}", port);
+                }
+                return Ok(());
+            }
+            "play" => return run_headless(&args[2..], osc_port),
             "help" | "--help" | "-h" => {
                 print_help();
                 return Ok(());
@@ -50,16 +79,17 @@ fn main() -> Result<()> {
             _ => {
                 eprintln!("Unknown command: {}", args[1]);
                 eprintln!("Run 'cargo run help' for usage information");
-                return Ok(());
+                std::process::exit(2);
             }
         }
     }
     
-    // Initialize the application
-    let app = initialize_app()?;
+    // The config picks the group folders the samples are scanned from
+    let config = Config::load()?;
+    let app = initialize_app(&config, audio_test || config.audio.startup_test)?;
     
     // Run the terminal UI
-    run_terminal(app)?;
+    run_terminal(app, config, osc_port)?;
     
     Ok(())
 }
@@ -70,69 +100,213 @@ fn print_help() {
     println!("Usage:");
     println!("  cargo run                  - Start the application");
     println!("  cargo run generate-config  - Generate example config file");
+    println!("  cargo run list-inputs      - List audio input devices for sampling");
+    println!("  cargo run list-midi        - List MIDI output ports for clock sync");
+    println!("  cargo run validate-samples - Check every file in samples/config.json");
+    println!("  cargo run play <project>   - Play a project without the UI; Ctrl+C stops");
+    println!("      --loops <n>            - Stop after the patterns have played round n times");
+    println!("      --song                 - Play the chains and tempo automation once, then stop");
     println!("  cargo run help             - Show this help");
+    println!();
+    println!("Options:");
+    println!("  --osc <port>               - Accept OSC remote control on a UDP port");
+    println!("  --dry-run                  - Print the files generate-config would write without writing them");
+    println!("  --audio-test               - Play a loaded sample on startup to check the output");
 }
 
-fn initialize_app() -> Result<App> {
+// A table of every mapping in the sample config; any problem makes the exit code non-zero
+fn validate_samples() -> Result<()> {
+    let checks = sample::validate_sample_config(SAMPLE_CONFIG_PATH)?;
+    println!("{:<6} {:<4} {:<10} FILE", "GROUP", "PAD", "STATUS");
+    for check in &checks {
+        println!("{:<6} {:<4} {:<10} {}", check.group, check.pad, check.status.label(), check.path);
+    }
+    
+    let problems = checks.iter().filter(|check| !check.status.is_ok()).count();
+    println!();
+    println!("{} mappings, {} problems", checks.len(), problems);
+    if problems > 0 {
+        return Err(AppError::Sample(format!("{} problems in {}", problems, SAMPLE_CONFIG_PATH)));
+    }
+    Ok(())
+}
+
+fn initialize_app(config: &Config, audio_test: bool) -> Result<App> {
     println!("Initializing application...");
-    let app = App::with_audio_test()?;
+    let app = App::with_config(config, audio_test)?;
     println!("Application initialized successfully!");
     Ok(app)
 }
 
-fn run_terminal(app: App) -> Result<()> {
-    // Load configuration
+// Plays a project with no terminal UI, printing a line per bar until it's told to stop.
+// Bad arguments exit with 2, like the other usage errors
+fn run_headless(args: &[String], osc_port: Option<u16>) -> Result<()> {
+    let usage = || -> ! {
+        eprintln!("Usage: cargo run play <project> [--loops <n>] [--song]");
+        std::process::exit(2);
+    };
+    let mut path = None;
+    let mut loops = None;
+    let mut song = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--loops" => match args.next().and_then(|n| n.parse::<u32>().ok()) {
+                Some(n) => loops = Some(n),
+                None => {
+                    eprintln!("--loops needs a number of loops, e.g. --loops 8");
+                    std::process::exit(2);
+                }
+            },
+            "--song" => song = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => usage(),
+        }
+    }
+    let Some(path) = path else {
+        usage();
+    };
+    
     let config = Config::load()?;
+    let project = Project::load(&path)?;
+    // The UI runs silent without a device, but a player with nothing to play through
+    // ends here with an error, and so a failing exit code. There's no footer to report
+    // an audio test in, so none is played
+    let mut app = initialize_app(&config, false)?;
+    if let Some(e) = app.mixer.output_error() {
+        return Err(AppError::Audio(format!("No audio device: {}", e)));
+    }
+    app.apply_config(&config);
+    app.apply_project(project);
+    let (engine, messages) = Engine::start(&mut app, &config, osc_port);
+    for message in messages {
+        println!("{}", message);
+    }
+    let max_bars = if song {
+        Some(app.song_length_bars() as u32)
+    } else {
+        loops.map(|loops| loops * app.pattern_loop_bars() as u32)
+    };
     
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, stop.clone())?;
+    }
     
-    // Create input mapper from config
-    let input_mapper = InputMapper::from_config(&config)
-        .unwrap_or_else(|_| InputMapper::new());
+    app.toggle_playback();
+    println!("Playing {} at {:.1} BPM", path, app.get_tempo());
+    let mut bars = 0;
+    while !stop.load(Ordering::Relaxed) {
+        for error in engine.run(&mut app) {
+            eprintln!("{}", error);
+        }
+        if app.state.bars_elapsed != bars {
+            bars = app.state.bars_elapsed;
+            if max_bars.is_some_and(|max| bars >= max) {
+                break;
+            }
+            println!("Bar {} · {:.1} BPM", bars + 1, app.get_tempo());
+        }
+        std::thread::sleep(engine.timeout(&app));
+    }
+    
+    app.stop();
+    println!("Stopped after {} bars", bars);
+    Ok(())
+}
+
+fn run_terminal(mut app: App, config: Config, osc_port: Option<u16>) -> Result<()> {
+    let theme = Theme::from_config(&config.theme, &config.groups)?;
     
-    // Run the app
-    let res = run_app(&mut terminal, app, input_mapper, config);
+    app.apply_config(&config);
+    // Listed here too so the full report is on the terminal after quitting
+    let problems = config.validate();
+    for problem in &problems {
+        eprintln!("{}: {}", CONFIG_PATH, problem);
+    }
+    app.report_config_problems(CONFIG_PATH, &problems);
+    // Decide on recovery before anything can write a fresh autosave
+    let autosave_path = project::autosave_path();
+    let last_save = [Some(project::QUICK_SAVE_PATH), config.startup.autoload_project.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter_map(project::modified_time)
+        .max();
+    let offer_restore = autosave_path
+        .as_deref()
+        .is_some_and(|path| project::autosave_is_newer(project::modified_time(path), last_save));
+    // Samples are already loaded, so a restored session plays straight away
+    if let Some(path) = &config.startup.autoload_project {
+        app.autoload_project(path);
+    }
+    app.autosave = autosave_path.and_then(|path| Autosave::new(path, config.project.autosave_secs));
+    if offer_restore && app.autosave.is_some() {
+        app.offer_autosave_restore();
+    }
+    let (engine, messages) = Engine::start(&mut app, &config, osc_port);
+    for message in messages {
+        app.ui_state.notify(message);
+    }
     
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    // Setup terminal; the guard restores it however run_app ends, and the hook does so
+    // before a panic message is printed
+    let _panic_hook = restore_on_panic(config.ui.mouse);
+    let _guard = TerminalGuard::enter(config.ui.mouse)?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    
+    // Create input mapper from config
+    let input_mapper = InputMapper::from_config(&config);
     
-    res
+    run_app(&mut terminal, app, input_mapper, theme, engine)
 }
 
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app: App,
-    input_mapper: InputMapper,
-    config: Config,
+    mut input_mapper: InputMapper,
+    mut theme: Theme,
+    engine: Engine,
 ) -> Result<()> {
+    let mut hit_areas = ui::HitAreas::default();
+    
     loop {
-        terminal.draw(|f| ui::draw(f, &app))?;
+        // Idle frames are skipped; only changes and running animations redraw
+        if app.ui_state.take_dirty() || ui::is_animating(&app) {
+            terminal.draw(|f| hit_areas = ui::draw(f, &app, &theme))?;
+        }
         
-        if crossterm::event::poll(Duration::from_millis(config.ui.tick_interval_ms))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    let binding = KeyBinding::with_modifiers(key.code, key.modifiers);
+        if crossterm::event::poll(engine.timeout(&app))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let binding = KeyBinding::from_event(key.code, key.modifiers);
                     
-                    if let Some(command) = input_mapper.get_command(&binding) {
+                    if let Some(command) = key_command(&app, &input_mapper, &binding) {
                         if matches!(command, Command::Quit) {
                             return Ok(());
                         }
                         
+                        if matches!(command, Command::ReloadConfig) {
+                            app.reload_config(CONFIG_PATH, &mut input_mapper, &mut theme);
+                        } else if let Err(e) = command.execute(&mut app) {
+                            app.ui_state.notify(e.to_string());
+                        }
+                    }
+                }
+                Event::Resize(_, _) => app.ui_state.mark_dirty(),
+                Event::Mouse(mouse) => {
+                    if let Some(command) = mouse_command(&mouse, &hit_areas) {
                         if let Err(e) = command.execute(&mut app) {
-                            eprintln!("Command error: {}", e);
+                            app.ui_state.notify(e.to_string());
                         }
                     }
                 }
+                _ => {}
             }
         }
         
-        app.tick();
+        for error in engine.run(&mut app) {
+            app.ui_state.notify(error);
+        }
     }
 }
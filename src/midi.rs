@@ -0,0 +1,817 @@
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use midir::{MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputConnection};
+use crate::error::{AppError, Result};
+
+// MIDI beat clock runs at 24 pulses per quarter note
+pub const CLOCK_PPQN: u32 = 24;
+
+pub const MIDI_CLOCK: u8 = 0xF8;
+pub const MIDI_START: u8 = 0xFA;
+pub const MIDI_CONTINUE: u8 = 0xFB;
+pub const MIDI_STOP: u8 = 0xFC;
+pub const MIDI_SONG_POSITION: u8 = 0xF2;
+pub const MIDI_NOTE_ON: u8 = 0x90;
+pub const MIDI_NOTE_OFF: u8 = 0x80;
+
+// The name K.O.II's connections show up under in other MIDI software
+const CLIENT_NAME: &str = "K.O.II";
+
+// How long the clock thread waits for events while stopped
+const IDLE_WAIT: Duration = Duration::from_millis(250);
+
+// Beyond this many missed pulses the clock restarts from now instead of bursting
+const MAX_CATCH_UP_PULSES: u32 = CLOCK_PPQN;
+
+// Six clock pulses make one 16th-note sequencer step
+pub const PULSES_PER_STEP: u32 = CLOCK_PPQN / 4;
+
+// How far each incoming pulse interval pulls the tempo estimate; small values ride out jitter
+const TEMPO_SMOOTHING: f64 = 0.1;
+
+// Gaps this many times the usual interval are dropouts, not tempo changes
+const DROPOUT_FACTOR: f64 = 4.0;
+
+// With no pulses arriving the follower keeps time for a beat, then gives up
+const COAST_PULSES: u32 = CLOCK_PPQN;
+
+pub trait MidiOut: Send {
+    fn send(&mut self, message: &[u8]) -> io::Result<()>;
+}
+
+impl MidiOut for MidiOutputConnection {
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        MidiOutputConnection::send(self, message).map_err(|e| io::Error::other(e.to_string()))
+    }
+}
+
+// Output port names as the system's MIDI layer lists them: ALSA, CoreMIDI or Windows MIDI
+pub fn list_output_ports() -> Vec<String> {
+    let Ok(output) = MidiOutput::new(CLIENT_NAME) else {
+        return Vec::new();
+    };
+    output.ports().iter().filter_map(|port| output.port_name(port).ok()).collect()
+}
+
+// Opens the port whose name contains the given one, or the first port if none is given
+pub fn open_output(port: Option<&str>) -> Result<MidiOutputConnection> {
+    let output = MidiOutput::new(CLIENT_NAME).map_err(|e| AppError::Midi(format!("Failed to start MIDI: {}", e)))?;
+    let (selected, name) = find_port(port, output.ports(), |candidate| output.port_name(candidate).ok())?;
+    output
+        .connect(&selected, "out")
+        .map_err(|e| AppError::Midi(format!("Failed to open {}: {}", name, e)))
+}
+
+// Clock input comes from the port matching the same name as the output
+pub fn open_input(port: Option<&str>) -> Result<MidiClockIn> {
+    let input = MidiInput::new(CLIENT_NAME).map_err(|e| AppError::Midi(format!("Failed to start MIDI: {}", e)))?;
+    let (selected, name) = find_port(port, input.ports(), |candidate| input.port_name(candidate).ok())?;
+    MidiClockIn::connect(input, &selected).map_err(|e| AppError::Midi(format!("Failed to open {}: {}", name, e)))
+}
+
+// The first port whose name contains wanted, ignoring case, with its name
+fn find_port<P>(wanted: Option<&str>, ports: Vec<P>, name: impl Fn(&P) -> Option<String>) -> Result<(P, String)> {
+    let mut named = ports.into_iter().filter_map(|port| name(&port).map(|name| (port, name)));
+    let selected = match wanted {
+        Some(wanted) => named.find(|(_, name)| name.to_lowercase().contains(&wanted.to_lowercase())),
+        None => named.next(),
+    };
+
+    selected.ok_or_else(|| {
+        AppError::Midi(match wanted {
+            Some(wanted) => format!("No MIDI port matching '{}'", wanted),
+            None => "No MIDI ports found".to_string(),
+        })
+    })
+}
+
+// Pad notes are short triggers unless the config holds them longer
+pub const DEFAULT_NOTE_GATE_MS: u64 = 50;
+
+// Where a pad's hits go on external gear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PadNote {
+    pub channel: u8, // 0-based, as sent on the wire
+    pub note: u8,
+    pub midi_only: bool, // Skip the pad's own sample
+}
+
+pub fn note_on(channel: u8, note: u8, velocity: u8) -> [u8; 3] {
+    [MIDI_NOTE_ON | (channel & 0x0F), note & 0x7F, velocity.clamp(1, 127)]
+}
+
+pub fn note_off(channel: u8, note: u8) -> [u8; 3] {
+    [MIDI_NOTE_OFF | (channel & 0x0F), note & 0x7F, 0]
+}
+
+// Hits are played as a 0.0–1.0 gain, which maps straight back onto MIDI velocity
+pub fn gain_velocity(gain: f32) -> u8 {
+    (gain.clamp(0.0, 1.0) * 127.0).round().max(1.0) as u8
+}
+
+// Song Position Pointer counts 16th notes since the start as a 14-bit value, low 7 bits first
+pub fn song_position(sixteenths: u16) -> [u8; 3] {
+    let sixteenths = sixteenths.min(0x3FFF);
+    [MIDI_SONG_POSITION, (sixteenths & 0x7F) as u8, (sixteenths >> 7) as u8]
+}
+
+pub fn clock_interval(bpm: f32) -> Duration {
+    Duration::from_secs_f64(60.0 / (bpm.max(1.0) as f64 * CLOCK_PPQN as f64))
+}
+
+// Works out how many clock pulses are due; pulses keep a fixed grid so they don't drift
+#[derive(Debug, Clone)]
+pub struct ClockScheduler {
+    interval: Duration,
+    next_pulse: Option<Instant>, // None while stopped
+}
+
+impl ClockScheduler {
+    pub fn new(bpm: f32) -> Self {
+        Self {
+            interval: clock_interval(bpm),
+            next_pulse: None,
+        }
+    }
+
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.interval = clock_interval(bpm);
+    }
+
+    // The first pulse goes out immediately, as the downbeat after Start
+    pub fn start(&mut self, now: Instant) {
+        self.next_pulse = Some(now);
+    }
+
+    pub fn stop(&mut self) {
+        self.next_pulse = None;
+    }
+
+    pub fn next_pulse(&self) -> Option<Instant> {
+        self.next_pulse
+    }
+
+    pub fn pulses_due(&mut self, now: Instant) -> u32 {
+        let Some(mut next) = self.next_pulse else {
+            return 0;
+        };
+
+        let mut pulses = 0;
+        while next <= now {
+            pulses += 1;
+            next += self.interval;
+            if pulses == MAX_CATCH_UP_PULSES {
+                next = now + self.interval;
+                break;
+            }
+        }
+        self.next_pulse = Some(next);
+        pulses
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClockEvent {
+    Start,
+    Continue,
+    Stop,
+    Rewind,
+    Tempo(f32),
+    Note { channel: u8, note: u8, velocity: u8, gate: Duration },
+}
+
+// Sends MIDI clock from its own thread so pulses aren't tied to the UI tick. Pad notes
+// share the thread and the port, and their note-offs are timed there too.
+pub struct MidiClock {
+    events: Sender<ClockEvent>,
+    tempo: f32,
+    sends_clock: bool, // Off when the port only carries pad notes
+    failure: Receiver<String>, // Why the clock thread stopped, if the port failed
+}
+
+impl MidiClock {
+    pub fn spawn(output: Box<dyn MidiOut>, bpm: f32) -> Self {
+        let (events, receiver) = mpsc::channel();
+        let (failed, failure) = mpsc::channel();
+        thread::spawn(move || run_clock(output, receiver, failed, bpm));
+        Self { events, tempo: bpm, sends_clock: true, failure }
+    }
+
+    // Why the port stopped working, once; the clock and its notes are gone from then on
+    pub fn failure(&self) -> Option<String> {
+        self.failure.try_recv().ok()
+    }
+
+    pub fn notes_only(mut self) -> Self {
+        self.sends_clock = false;
+        self
+    }
+
+    fn send_transport(&self, event: ClockEvent) {
+        if self.sends_clock {
+            let _ = self.events.send(event);
+        }
+    }
+
+    pub fn start(&self) {
+        self.send_transport(ClockEvent::Start);
+    }
+
+    pub fn resume(&self) {
+        self.send_transport(ClockEvent::Continue);
+    }
+
+    pub fn stop(&self) {
+        self.send_transport(ClockEvent::Stop);
+    }
+
+    // Tells followers the next Continue plays from the top
+    pub fn rewind(&self) {
+        self.send_transport(ClockEvent::Rewind);
+    }
+
+    // Note On now, Note Off once the gate has passed
+    pub fn send_note(&self, note: PadNote, velocity: u8, gate: Duration) {
+        let _ = self.events.send(ClockEvent::Note { channel: note.channel, note: note.note, velocity, gate });
+    }
+
+    // Cheap to call every tick; only changes are passed on
+    pub fn set_tempo(&mut self, bpm: f32) {
+        if bpm != self.tempo {
+            self.tempo = bpm;
+            let _ = self.events.send(ClockEvent::Tempo(bpm));
+        }
+    }
+}
+
+// What the clock thread keeps between wake-ups. Every call takes the time it runs at, so
+// tests can drive it without the thread.
+struct ClockOutput {
+    scheduler: ClockScheduler,
+    note_offs: Vec<(Instant, u8, u8)>, // (when, channel, note), soonest first
+}
+
+impl ClockOutput {
+    fn new(bpm: f32) -> Self {
+        Self { scheduler: ClockScheduler::new(bpm), note_offs: Vec::new() }
+    }
+
+    // When something is next due, if anything is
+    fn next_due(&self) -> Option<Instant> {
+        self.scheduler.next_pulse().into_iter().chain(self.note_offs.first().map(|&(at, _, _)| at)).min()
+    }
+
+    fn handle(&mut self, event: ClockEvent, now: Instant) -> Vec<u8> {
+        match event {
+            ClockEvent::Start => {
+                self.scheduler.start(now);
+                vec![MIDI_START]
+            }
+            ClockEvent::Continue => {
+                self.scheduler.start(now);
+                vec![MIDI_CONTINUE]
+            }
+            ClockEvent::Stop => {
+                self.scheduler.stop();
+                vec![MIDI_STOP]
+            }
+            ClockEvent::Rewind => song_position(0).to_vec(),
+            ClockEvent::Tempo(bpm) => {
+                self.scheduler.set_tempo(bpm);
+                Vec::new()
+            }
+            ClockEvent::Note { channel, note, velocity, gate } => {
+                let off_at = now + gate;
+                let index = self.note_offs.partition_point(|&(at, _, _)| at <= off_at);
+                self.note_offs.insert(index, (off_at, channel, note));
+                note_on(channel, note, velocity).to_vec()
+            }
+        }
+    }
+
+    // Note-offs and clock pulses that are due by now
+    fn due(&mut self, now: Instant) -> Vec<u8> {
+        let due = self.note_offs.partition_point(|&(at, _, _)| at <= now);
+        let mut bytes: Vec<u8> = self.note_offs.drain(..due).flat_map(|(_, channel, note)| note_off(channel, note)).collect();
+        let pulses = self.scheduler.pulses_due(now);
+        bytes.extend(std::iter::repeat_n(MIDI_CLOCK, pulses as usize));
+        bytes
+    }
+
+    // Everything still sounding, for when the app goes away
+    fn release_all(&mut self) -> Vec<u8> {
+        self.note_offs.drain(..).flat_map(|(_, channel, note)| note_off(channel, note)).collect()
+    }
+}
+
+fn run_clock(mut output: Box<dyn MidiOut>, events: Receiver<ClockEvent>, failed: Sender<String>, bpm: f32) {
+    let mut clock = ClockOutput::new(bpm);
+
+    loop {
+        let wait = clock.next_due().map_or(IDLE_WAIT, |next| next.saturating_duration_since(Instant::now()));
+        let mut bytes = match events.recv_timeout(wait) {
+            Ok(event) => clock.handle(event, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => Vec::new(),
+            // The app has shut down; don't leave notes hanging on the gear
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = output.send(&clock.release_all());
+                return;
+            }
+        };
+
+        bytes.extend(clock.due(Instant::now()));
+        if bytes.is_empty() {
+            continue;
+        }
+
+        // The TUI owns the terminal, so the app is told rather than stderr
+        if let Err(e) = output.send(&bytes) {
+            let _ = failed.send(format!("MIDI output failed, clock and notes stopped: {}", e));
+            return;
+        }
+    }
+}
+
+// What an external clock asks of the transport
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockSync {
+    Start,
+    Continue,
+    Stop,
+    Step(Instant), // When the pulse that starts the step arrived
+    Lost,          // Pulses stopped arriving mid-song
+}
+
+// Turns incoming clock pulses into sequencer steps and a smoothed tempo
+#[derive(Debug, Clone, Default)]
+pub struct ClockFollower {
+    running: bool,
+    pulses: u32,                // Pulses since Start, for finding step boundaries
+    last_pulse: Option<Instant>,
+    interval: Option<f64>,      // Smoothed seconds between pulses
+    coasted: u32,               // Pulses made up since the last real one
+}
+
+impl ClockFollower {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn receive(&mut self, byte: u8, at: Instant) -> Option<ClockSync> {
+        match byte {
+            MIDI_START => {
+                self.running = true;
+                self.pulses = 0;
+                self.coasted = 0;
+                Some(ClockSync::Start)
+            }
+            MIDI_CONTINUE => {
+                self.running = true;
+                self.coasted = 0;
+                Some(ClockSync::Continue)
+            }
+            MIDI_STOP => {
+                self.running = false;
+                Some(ClockSync::Stop)
+            }
+            MIDI_CLOCK => {
+                self.measure(at);
+                self.coasted = 0;
+                self.pulse(at)
+            }
+            _ => None,
+        }
+    }
+
+    fn measure(&mut self, at: Instant) {
+        // Pulses read in one go share a timestamp and say nothing about the tempo
+        if let Some(last) = self.last_pulse.filter(|&last| at > last) {
+            let interval = at.duration_since(last).as_secs_f64();
+            self.interval = Some(match self.interval {
+                Some(smoothed) if interval > smoothed * DROPOUT_FACTOR => smoothed,
+                Some(smoothed) => smoothed + (interval - smoothed) * TEMPO_SMOOTHING,
+                None => interval,
+            });
+        }
+        self.last_pulse = Some(at);
+    }
+
+    fn pulse(&mut self, at: Instant) -> Option<ClockSync> {
+        if !self.running {
+            return None;
+        }
+        let on_step = self.pulses.is_multiple_of(PULSES_PER_STEP);
+        self.pulses = self.pulses.wrapping_add(1);
+        on_step.then_some(ClockSync::Step(at))
+    }
+
+    pub fn bpm(&self) -> Option<f32> {
+        self.interval
+            .filter(|&interval| interval > 0.0)
+            .map(|interval| (60.0 / (interval * CLOCK_PPQN as f64)) as f32)
+    }
+
+    // When coast will next have a pulse to fill in, while a running clock's tempo is known
+    pub fn next_coast(&self) -> Option<Instant> {
+        let (Some(last), Some(interval)) = (self.last_pulse, self.bpm().and(self.interval)) else {
+            return None;
+        };
+        self.running.then(|| last + Duration::from_secs_f64(interval) * 2)
+    }
+
+    // Fills in late pulses at the last tempo so a hiccup doesn't stall the groove
+    pub fn coast(&mut self, now: Instant) -> Vec<ClockSync> {
+        let (Some(mut last), Some(interval)) = (self.last_pulse, self.bpm().and(self.interval)) else {
+            return Vec::new();
+        };
+        if !self.running {
+            return Vec::new();
+        }
+
+        // A pulse is only given up on once it's a whole interval late
+        let interval = Duration::from_secs_f64(interval);
+        let mut events = Vec::new();
+        while now >= last + interval * 2 {
+            if self.coasted == COAST_PULSES {
+                self.running = false;
+                self.last_pulse = None;
+                events.push(ClockSync::Lost);
+                return events;
+            }
+            last += interval;
+            self.coasted += 1;
+            events.extend(self.pulse(last));
+        }
+        self.last_pulse = Some(last);
+        events
+    }
+}
+
+// Reads clock from a MIDI input. Bytes are timestamped as they arrive and followed on a
+// thread of their own, which also fills in late pulses on time; the app collects the
+// resulting steps every tick
+pub struct MidiClockIn {
+    events: Receiver<ClockSync>,
+    bpm: Arc<AtomicU32>, // f32 bits of the followed tempo, 0 until it's known
+    _connection: Option<MidiInputConnection<()>>, // Open for as long as the clock is followed
+}
+
+impl MidiClockIn {
+    fn connect(input: MidiInput, port: &MidiInputPort) -> std::result::Result<Self, String> {
+        let (sender, bytes) = mpsc::channel();
+        let connection = input
+            .connect(
+                port,
+                "clock in",
+                move |_, message, _| {
+                    let at = Instant::now();
+                    for &byte in message {
+                        let _ = sender.send((byte, at));
+                    }
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        
+        let (sender, events) = mpsc::channel();
+        let bpm = Arc::new(AtomicU32::new(0));
+        let tempo = Arc::clone(&bpm);
+        thread::spawn(move || follow_clock(bytes, sender, &tempo));
+        Ok(Self { events, bpm, _connection: Some(connection) })
+    }
+
+    // A clock that has already followed these bytes, each given the time it arrived
+    #[cfg(test)]
+    pub fn followed(bytes: &[(u8, Instant)]) -> Self {
+        let mut follower = ClockFollower::new();
+        let (sender, events) = mpsc::channel();
+        for event in bytes.iter().filter_map(|&(byte, at)| follower.receive(byte, at)) {
+            let _ = sender.send(event);
+        }
+        Self { events, bpm: Arc::new(AtomicU32::new(follower.bpm().unwrap_or(0.0).to_bits())), _connection: None }
+    }
+
+    pub fn poll(&mut self) -> Vec<ClockSync> {
+        self.events.try_iter().collect()
+    }
+
+    pub fn bpm(&self) -> Option<f32> {
+        Some(f32::from_bits(self.bpm.load(Ordering::Relaxed))).filter(|&bpm| bpm > 0.0)
+    }
+}
+
+// Follows the bytes as they arrive, waking to fill in a pulse when one is late. Ends when the
+// app lets go of the clock, or when the input closes, which loses a running clock
+fn follow_clock(bytes: Receiver<(u8, Instant)>, events: Sender<ClockSync>, bpm: &AtomicU32) {
+    let mut follower = ClockFollower::new();
+    loop {
+        let wait = follower.next_coast().map_or(IDLE_WAIT, |at| at.saturating_duration_since(Instant::now()));
+        let followed: Vec<ClockSync> = match bytes.recv_timeout(wait) {
+            Ok((byte, at)) => follower.receive(byte, at).into_iter().collect(),
+            Err(RecvTimeoutError::Timeout) => follower.coast(Instant::now()),
+            Err(RecvTimeoutError::Disconnected) => {
+                if follower.running {
+                    let _ = events.send(ClockSync::Lost);
+                }
+                return;
+            }
+        };
+        bpm.store(follower.bpm().unwrap_or(0.0).to_bits(), Ordering::Relaxed);
+        if followed.into_iter().any(|event| events.send(event).is_err()) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Clock, ManualClock};
+
+    #[test]
+    fn test_clock_interval() {
+        // 120 BPM is two beats a second, so 48 pulses a second
+        assert_eq!(clock_interval(120.0), Duration::from_secs_f64(1.0 / 48.0));
+        assert!((clock_interval(60.0).as_secs_f64() * CLOCK_PPQN as f64 - 1.0).abs() < 1e-6);
+        assert!(clock_interval(150.0) < clock_interval(120.0));
+        // Fractional tempos aren't truncated to whole BPM
+        assert!(clock_interval(87.5) < clock_interval(87.0) && clock_interval(87.5) > clock_interval(88.0));
+    }
+
+    #[test]
+    fn test_scheduler_pulses_per_beat() {
+        let mut scheduler = ClockScheduler::new(120.0);
+        assert_eq!(scheduler.pulses_due(Instant::now()), 0); // Stopped
+
+        let start = Instant::now();
+        scheduler.start(start);
+        assert_eq!(scheduler.pulses_due(start), 1);
+
+        // Half a second is one beat at 120 BPM; the downbeat was already sent
+        let beat = start + Duration::from_millis(500);
+        assert_eq!(scheduler.pulses_due(beat), CLOCK_PPQN);
+
+        scheduler.set_tempo(60.0);
+        assert_eq!(scheduler.interval, clock_interval(60.0));
+
+        scheduler.stop();
+        assert_eq!(scheduler.pulses_due(beat + Duration::from_secs(1)), 0);
+    }
+
+    #[test]
+    fn test_scheduler_sends_96_pulses_per_bar() {
+        let mut scheduler = ClockScheduler::new(120.0);
+        let start = Instant::now();
+        scheduler.start(start);
+
+        // Uneven wake-ups, as a loaded machine would give, don't change the count
+        let bar = Duration::from_secs(2);
+        let mut pulses = 0;
+        let mut elapsed = Duration::ZERO;
+        while elapsed < bar {
+            pulses += scheduler.pulses_due(start + elapsed);
+            elapsed += Duration::from_micros(7_300);
+        }
+        pulses += scheduler.pulses_due(start + bar - Duration::from_micros(1));
+        assert_eq!(pulses, CLOCK_PPQN * 4);
+    }
+
+    #[test]
+    fn test_song_position() {
+        assert_eq!(song_position(0), [MIDI_SONG_POSITION, 0, 0]);
+        assert_eq!(song_position(200), [MIDI_SONG_POSITION, 0x48, 0x01]);
+        assert_eq!(song_position(u16::MAX), [MIDI_SONG_POSITION, 0x7F, 0x7F]);
+    }
+
+    #[test]
+    fn test_scheduler_skips_long_stalls() {
+        let mut scheduler = ClockScheduler::new(120.0);
+        let start = Instant::now();
+        scheduler.start(start);
+
+        let late = start + Duration::from_secs(10);
+        assert_eq!(scheduler.pulses_due(late), MAX_CATCH_UP_PULSES);
+        assert_eq!(scheduler.next_pulse(), Some(late + clock_interval(120.0)));
+    }
+
+    fn pulses(follower: &mut ClockFollower, start: Instant, count: u32, interval: Duration) -> Vec<ClockSync> {
+        (0..count).filter_map(|pulse| follower.receive(MIDI_CLOCK, start + interval * pulse)).collect()
+    }
+
+    #[test]
+    fn test_follower_steps_every_six_pulses() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        let interval = clock_interval(120.0);
+
+        // Pulses before Start only set the tempo
+        assert!(pulses(&mut follower, start, 12, interval).is_empty());
+        assert!((follower.bpm().unwrap() - 120.0).abs() < 0.01);
+
+        assert_eq!(follower.receive(MIDI_START, start), Some(ClockSync::Start));
+        let steps = pulses(&mut follower, start + interval * 12, 24, interval);
+        assert_eq!(steps.len(), 4);
+        assert_eq!(steps[0], ClockSync::Step(start + interval * 12));
+        assert_eq!(steps[1], ClockSync::Step(start + interval * 18));
+
+        assert_eq!(follower.receive(MIDI_STOP, start), Some(ClockSync::Stop));
+        assert!(pulses(&mut follower, start + interval * 36, 12, interval).is_empty());
+        assert_eq!(follower.receive(MIDI_CONTINUE, start), Some(ClockSync::Continue));
+    }
+
+    #[test]
+    fn test_follower_smooths_jitter() {
+        let mut follower = ClockFollower::new();
+        let mut at = Instant::now();
+        let interval = clock_interval(100.0);
+
+        // Pulses alternately 20% early and late still read as a steady 100 BPM
+        for pulse in 0..96 {
+            follower.receive(MIDI_CLOCK, at);
+            at += if pulse % 2 == 0 { interval.mul_f64(0.8) } else { interval.mul_f64(1.2) };
+        }
+        assert!((follower.bpm().unwrap() - 100.0).abs() < 2.5);
+
+        // A long gap isn't taken as a sudden tempo change
+        follower.receive(MIDI_CLOCK, at + Duration::from_secs(2));
+        assert!((follower.bpm().unwrap() - 100.0).abs() < 2.5);
+    }
+
+    #[test]
+    fn test_follower_coasts_then_gives_up() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        let interval = clock_interval(120.0);
+        follower.receive(MIDI_START, start);
+        pulses(&mut follower, start, 6, interval);
+        let last = start + interval * 5;
+
+        // Half a beat of silence is filled in at the last tempo
+        let coasted = follower.coast(last + interval * 13);
+        assert_eq!(coasted, vec![ClockSync::Step(last + interval), ClockSync::Step(last + interval * 7)]);
+
+        // After a beat without pulses the transport stops
+        let lost = follower.coast(last + interval * 40);
+        assert_eq!(lost.last(), Some(&ClockSync::Lost));
+        assert!(follower.coast(last + interval * 80).is_empty());
+    }
+
+    #[test]
+    fn test_clock_in_follows_pulses_by_their_arrival() {
+        let start = Instant::now();
+        let interval = clock_interval(120.0);
+        let (sender, bytes) = mpsc::channel();
+        sender.send((MIDI_START, start)).unwrap();
+        for pulse in 0..7 {
+            sender.send((MIDI_CLOCK, start + interval * pulse)).unwrap();
+        }
+        drop(sender);
+        
+        // Steps keep the time their pulse arrived, and an input that goes away loses the clock
+        let (events, followed) = mpsc::channel();
+        let bpm = AtomicU32::new(0);
+        follow_clock(bytes, events, &bpm);
+        let followed: Vec<ClockSync> = followed.try_iter().collect();
+        assert_eq!(followed, [ClockSync::Start, ClockSync::Step(start), ClockSync::Step(start + interval * 6), ClockSync::Lost]);
+        assert!((f32::from_bits(bpm.load(Ordering::Relaxed)) - 120.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_follower_knows_when_to_coast() {
+        let mut follower = ClockFollower::new();
+        let start = Instant::now();
+        let interval = Duration::from_millis(20);
+        assert_eq!(follower.next_coast(), None);
+        
+        follower.receive(MIDI_START, start);
+        pulses(&mut follower, start, 2, interval);
+        assert_eq!(follower.next_coast(), Some(start + interval * 3));
+        follower.receive(MIDI_STOP, start);
+        assert_eq!(follower.next_coast(), None);
+    }
+
+    // Hands every event to the output at the clock's time, then collects what's due
+    fn drive(output: &mut ClockOutput, clock: &ManualClock, events: &[ClockEvent]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &event in events {
+            bytes.extend(output.handle(event, clock.now()));
+        }
+        bytes.extend(output.due(clock.now()));
+        bytes
+    }
+
+    #[test]
+    fn test_clock_output_sends_transport_and_pulses() {
+        let clock = ManualClock::new();
+        let mut output = ClockOutput::new(300.0);
+        assert_eq!(output.next_due(), None);
+
+        // The downbeat pulse goes out with Start
+        assert_eq!(drive(&mut output, &clock, &[ClockEvent::Start]), [MIDI_START, MIDI_CLOCK]);
+        assert_eq!(output.next_due(), Some(clock.now() + clock_interval(300.0)));
+
+        clock.advance(clock_interval(300.0) * 3);
+        assert_eq!(drive(&mut output, &clock, &[]), [MIDI_CLOCK; 3]);
+
+        let stopped = drive(&mut output, &clock, &[ClockEvent::Stop, ClockEvent::Rewind]);
+        assert_eq!(stopped, [MIDI_STOP, MIDI_SONG_POSITION, 0, 0]);
+        clock.advance(Duration::from_secs(1));
+        assert!(drive(&mut output, &clock, &[]).is_empty());
+        assert_eq!(output.next_due(), None);
+    }
+
+    #[test]
+    fn test_pad_notes_get_their_note_off() {
+        let clock = ManualClock::new();
+        let mut output = ClockOutput::new(120.0);
+        let note = ClockEvent::Note { channel: 9, note: 36, velocity: gain_velocity(0.5), gate: Duration::from_millis(30) };
+
+        assert_eq!(drive(&mut output, &clock, &[note]), [0x99, 36, 64]);
+        assert_eq!(output.next_due(), Some(clock.now() + Duration::from_millis(30)));
+        clock.advance(Duration::from_millis(29));
+        assert!(drive(&mut output, &clock, &[]).is_empty());
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(drive(&mut output, &clock, &[]), [0x89, 36, 0]);
+
+        // Notes still held when the app goes away are let go
+        drive(&mut output, &clock, &[note]);
+        assert_eq!(output.release_all(), [0x89, 36, 0]);
+    }
+
+    #[test]
+    fn test_notes_only_port_skips_transport() {
+        let (events, receiver) = mpsc::channel();
+        let (_failed, failure) = mpsc::channel();
+        let clock = MidiClock { events, tempo: 120.0, sends_clock: true, failure }.notes_only();
+        let kick = PadNote { channel: 9, note: 36, midi_only: false };
+
+        clock.start();
+        clock.stop();
+        clock.send_note(kick, 100, Duration::from_millis(30));
+        let sent: Vec<ClockEvent> = receiver.try_iter().collect();
+        assert_eq!(sent, [ClockEvent::Note { channel: 9, note: 36, velocity: 100, gate: Duration::from_millis(30) }]);
+    }
+
+    #[test]
+    fn test_ports_are_found_by_part_of_their_name() {
+        let ports = vec!["Midi Through:Midi Through Port-0 14:0", "UM-ONE:UM-ONE MIDI 1 20:0"];
+        let find = |wanted| find_port(wanted, ports.clone(), |port| Some(port.to_string())).map(|(port, _)| port);
+        assert_eq!(find(Some("um-one")).unwrap(), ports[1]);
+        assert_eq!(find(None).unwrap(), ports[0]);
+        assert_eq!(find(Some("MPK")).unwrap_err().to_string(), "MIDI error: No MIDI port matching 'MPK'");
+        assert_eq!(find_port(None, Vec::<&str>::new(), |_| None).unwrap_err().to_string(), "MIDI error: No MIDI ports found");
+    }
+
+    struct FailingOut;
+    
+    impl MidiOut for FailingOut {
+        fn send(&mut self, _message: &[u8]) -> io::Result<()> {
+            Err(io::Error::other("device unplugged"))
+        }
+    }
+
+    #[test]
+    fn test_clock_reports_a_failed_port() {
+        let (events, receiver) = mpsc::channel();
+        let (failed, failure) = mpsc::channel();
+        events.send(ClockEvent::Start).unwrap();
+        run_clock(Box::new(FailingOut), receiver, failed, 120.0);
+        assert_eq!(failure.try_recv().unwrap(), "MIDI output failed, clock and notes stopped: device unplugged");
+        // The thread has stopped, so nothing more is said
+        assert!(failure.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_note_messages() {
+        assert_eq!(note_on(0, 60, 100), [0x90, 60, 100]);
+        // Velocity 0 would read as a note-off
+        assert_eq!(note_on(15, 60, 0), [0x9F, 60, 1]);
+        assert_eq!(note_off(2, 200), [0x82, 72, 0]);
+        assert_eq!(gain_velocity(1.0), 127);
+        assert_eq!(gain_velocity(0.0), 1);
+        assert_eq!(gain_velocity(100.0 / 127.0), 100);
+    }
+
+    #[test]
+    fn test_clock_output_sends_96_pulses_per_bar_at_120_bpm() {
+        let clock = ManualClock::new();
+        let mut output = ClockOutput::new(120.0);
+        let mut bytes = drive(&mut output, &clock, &[ClockEvent::Start]);
+
+        // Wake-ups land wherever the thread gets scheduled; the count stays on the grid
+        let bar = Duration::from_secs(2);
+        let started = clock.now();
+        while clock.now() + Duration::from_micros(3_100) < started + bar {
+            clock.advance(Duration::from_micros(3_100));
+            bytes.extend(drive(&mut output, &clock, &[]));
+        }
+        bytes.extend(drive(&mut output, &clock, &[ClockEvent::Stop]));
+        let pulses = bytes.iter().filter(|&&b| b == MIDI_CLOCK).count() as u32;
+        assert_eq!(pulses, CLOCK_PPQN * 4);
+    }
+}
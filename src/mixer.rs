@@ -1,96 +1,497 @@
-use rodio::{Decoder, OutputStream, Sink, Source};
-use std::io::Cursor;
+use cpal::traits::{DeviceTrait, HostTrait};
+use rodio::OutputStream;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::bus::{
+    master_gain, ms_to_frames, BusSource, BusState, Limiter, LimiterControl, Meters, Voice, BUS_CHANNELS, BUS_SAMPLE_RATE,
+    DEFAULT_LIMITER_THRESHOLD_DB, DEFAULT_VOLUME_RAMP_MS, MAX_CAPTURE_SECS, MAX_MASTER_GAIN_DB, MAX_MASTER_WIDTH,
+    MIN_MASTER_GAIN_DB,
+};
+use crate::effects::{GateEffect, ReverbSize, GATE_STEPS};
+use crate::error::Result;
+use crate::pad_settings::{PadSettings, PlaybackMode};
+use crate::render::GroupLevel;
+use crate::sample::{decode_sample, DecodedSample, SampleStorage, SampleStream};
+use crate::sampling::OutputRecorder;
+use crate::sequencer::{DEFAULT_GROUPS, MAX_GROUPS, MAX_PADS, STEPS_PER_BEAT};
+use crate::state::DEFAULT_TEMPO;
 
+// Oldest voices are cut once this many are ringing at the same time
+const MAX_VOICES: usize = 32;
+
+const DEFAULT_GROUP_VOLUME: f32 = 0.8;
+
+// A device that pulls no audio for this long is taken to be gone
+const OUTPUT_STALL: Duration = Duration::from_secs(2);
+
+// Opens an output device, by name or the default one for None, and starts it pulling from
+// the bus. Tests swap in their own so reconnecting can be tried without hardware
+pub(crate) type OutputFactory = Box<dyn FnMut(Option<&str>, BusSource) -> std::result::Result<OpenedOutput, String>>;
+
+pub(crate) struct OpenedOutput {
+    pub name: String,
+    pub _stream: Box<dyn Any>, // Dropping it closes the device
+}
+
+// The device the bus plays on and how to open another when it goes away
+struct Output {
+    factory: OutputFactory,
+    device: Option<String>, // Tried before the default device
+    opened: Option<OpenedOutput>, // None while running silent
+    failure: Option<String>, // Why the last attempt to open a device didn't
+    blocks: u64, // Bus blocks rendered when last checked
+    progress: Instant, // When the count last moved
+}
+
+fn open_output_device(name: Option<&str>, source: BusSource) -> std::result::Result<OpenedOutput, String> {
+    let host = cpal::default_host();
+    let device = match name {
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| format!("Failed to enumerate output devices: {}", e))?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("Output device not found: {}", name))?,
+        None => host.default_output_device().ok_or_else(|| "No audio output device available".to_string())?,
+    };
+    let name = device.name().unwrap_or_else(|_| "unknown".to_string());
+    let (stream, handle) = OutputStream::try_from_device(&device).map_err(|e| e.to_string())?;
+    handle.play_raw(source).map_err(|e| e.to_string())?;
+    Ok(OpenedOutput { name, _stream: Box::new(stream) })
+}
+
+// Levels and sends as saved with a project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixerSettings {
+    pub master_volume: f32,
+    pub master_gain_db: f32,
+    pub master_muted: bool,
+    pub master_width: f32,
+    pub group_volumes: Vec<f32>, // One entry per group
+    pub group_muted: Vec<bool>,
+    pub group_soloed: Vec<bool>,
+    pub delay_sends: Vec<f32>,
+    pub reverb_sends: Vec<f32>,
+    pub reverb_size: ReverbSize,
+    pub muted_pads: Vec<(usize, usize)>, // (group, pad), sorted
+    pub group_gates: Vec<Option<[bool; GATE_STEPS]>>, // None for groups without a gate
+}
+
+impl Default for MixerSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.7,
+            master_gain_db: 0.0,
+            master_muted: false,
+            master_width: 1.0,
+            group_volumes: vec![DEFAULT_GROUP_VOLUME; DEFAULT_GROUPS],
+            group_muted: vec![false; DEFAULT_GROUPS],
+            group_soloed: vec![false; DEFAULT_GROUPS],
+            delay_sends: vec![0.0; DEFAULT_GROUPS],
+            reverb_sends: vec![0.0; DEFAULT_GROUPS],
+            reverb_size: ReverbSize::default(),
+            muted_pads: Vec::new(),
+            group_gates: vec![None; DEFAULT_GROUPS],
+        }
+    }
+}
+
+/// The master bus: group volumes, mutes and solos, send effects and per-pad settings.
 pub struct Mixer {
-    _output_stream: OutputStream,
-    output_handle: rodio::OutputStreamHandle,
+    output: Option<Output>, // None for detached mixers, which never open a device
+    bus: Arc<Mutex<BusState>>, // Shared with the output source on the audio thread
+    limiter: Arc<LimiterControl>,
+    meters: Arc<Meters>, // Peak levels written by the bus on every block
     master_volume: f32,
-    group_volumes: [f32; 4], // Volume for each sample group
-    group_muted: [bool; 4],  // Mute state for each group
+    master_gain_db: f32, // After the fader and before the limiter, MIN_MASTER_GAIN_DB to MAX_MASTER_GAIN_DB
+    group_volumes: Vec<f32>, // Volume for each sample group; its length is the group count
+    group_muted: Vec<bool>,  // Mute state for each group
+    group_soloed: Vec<bool>, // While any group is soloed, only soloed groups are heard
     master_muted: bool,
+    master_width: f32, // Stereo width of the master, 0.0 (mono) to 2.0
+    delay_sends: Vec<f32>,  // Per-group send into the shared delay
+    reverb_sends: Vec<f32>, // Per-group send into the shared reverb
+    reverb_size: ReverbSize,
+    group_gates: Vec<Option<[bool; GATE_STEPS]>>, // Open steps of each group's trance gate
+    tempo: f32, // For the gates, which count steps at it
+    steps_per_beat: usize, // Resolution of the playing signature, which sets the gate step length
+    pad_settings: HashMap<(usize, usize), PadSettings>, // Pads missing here play with defaults
+    muted_pads: HashSet<(usize, usize)>, // (group, pad) pairs whose hits are skipped
+    decoded: HashMap<(usize, usize), Vec<DecodedLayer>>, // Each pad's layers as they were last played
+    output_recorder: Option<OutputRecorder>,
+}
+
+// A pad layer decoded, trimmed and reversed for its last hit, so the next one skips the decode
+struct DecodedLayer {
+    source: Arc<[u8]>, // Held so the address can't be reused by another sample while cached
+    trim: (f32, f32),
+    reverse: bool,
+    sample: Arc<DecodedSample>,
 }
 
 impl Mixer {
     pub fn new() -> Self {
-        let (output_stream, output_handle) = OutputStream::try_default()
-            .expect("Failed to create audio output stream");
-        
-        eprintln!("Mixer initialized successfully");
+        Self::with_output_factory(Box::new(open_output_device))
+    }
+
+    /// A mixer whose bus is only advanced by [`Mixer::render_frames`], so tests control
+    /// exactly what it hears.
+    pub fn detached() -> Self {
+        Self::with_output(None)
+    }
+
+    // Without a device the mixer runs silent, and the sequencer carries on regardless;
+    // output_error says why
+    pub(crate) fn with_output_factory(factory: OutputFactory) -> Self {
+        let mut mixer = Self::with_output(Some(factory));
+        let _ = mixer.open_output();
+        mixer
+    }
+
+    fn with_output(factory: Option<OutputFactory>) -> Self {
+        let limiter = Arc::new(LimiterControl::new(true, DEFAULT_LIMITER_THRESHOLD_DB));
+        let meters = Arc::new(Meters::new());
+        let mut bus = BusState::new(
+            vec![Box::new(Limiter::new(Arc::clone(&limiter)))],
+            DEFAULT_TEMPO,
+            Arc::clone(&meters),
+        );
+        bus.set_ramp_frames(ms_to_frames(DEFAULT_VOLUME_RAMP_MS));
         
-        Self {
-            _output_stream: output_stream,
-            output_handle,
-            master_volume: 0.7,
-            group_volumes: [0.8, 0.8, 0.8, 0.8], // Default volume for all groups
-            group_muted: [false; 4],
-            master_muted: false,
+        let defaults = MixerSettings::default();
+        let mut mixer = Self {
+            output: factory.map(|factory| Output { factory, device: None, opened: None, failure: None, blocks: 0, progress: Instant::now() }),
+            bus: Arc::new(Mutex::new(bus)),
+            limiter,
+            meters,
+            master_volume: defaults.master_volume,
+            master_gain_db: defaults.master_gain_db,
+            group_volumes: defaults.group_volumes,
+            group_muted: defaults.group_muted,
+            group_soloed: defaults.group_soloed,
+            master_muted: defaults.master_muted,
+            master_width: defaults.master_width,
+            delay_sends: defaults.delay_sends,
+            reverb_sends: defaults.reverb_sends,
+            reverb_size: defaults.reverb_size,
+            group_gates: defaults.group_gates,
+            tempo: DEFAULT_TEMPO,
+            steps_per_beat: STEPS_PER_BEAT,
+            pad_settings: HashMap::new(),
+            muted_pads: HashSet::new(),
+            decoded: HashMap::new(),
+            output_recorder: None,
+        };
+        // Start at the default levels rather than fading in from unity
+        mixer.sync_gains(false);
+        mixer
+    }
+
+    // Closes the device and opens the configured one, or the default if that fails.
+    // Returns the device's name, or why none could be opened
+    fn open_output(&mut self) -> std::result::Result<String, String> {
+        let Some(output) = &mut self.output else {
+            return Err("Audio isn't attached to a device".to_string());
+        };
+        // Only one device may pull from the bus, or voices would play at double speed
+        output.opened = None;
+        let mut failure = "No audio output device available".to_string();
+        let preferred = output.device.clone();
+        for candidate in [preferred.as_deref(), None].into_iter().skip(usize::from(preferred.is_none())) {
+            match (output.factory)(candidate, BusSource::new(Arc::clone(&self.bus))) {
+                Ok(opened) => {
+                    let name = opened.name.clone();
+                    output.opened = Some(opened);
+                    output.failure = None;
+                    output.blocks = self.meters.blocks();
+                    output.progress = Instant::now();
+                    return Ok(name);
+                }
+                Err(e) => failure = e,
+            }
         }
+        output.failure = Some(failure.clone());
+        Err(failure)
     }
 
-    pub fn play_sample(&mut self, sample_data: &[u8], group: usize) {
-        if sample_data.is_empty() || group >= 4 {
-            return;
+    // Manual reconnect, for a device plugged back in after it was lost
+    pub fn reinit_output(&mut self) -> std::result::Result<String, String> {
+        self.open_output()
+            .map(|name| format!("Audio on {}", name))
+            .map_err(|e| format!("No audio device, running silent: {}", e))
+    }
+
+    // Changing the device reopens the output; returns what happened, if anything did
+    pub fn set_output_device(&mut self, device: Option<String>) -> Option<std::result::Result<String, String>> {
+        let output = self.output.as_mut()?;
+        if output.device == device {
+            return None;
+        }
+        output.device = device;
+        Some(self.reinit_output())
+    }
+
+    pub fn output_device_name(&self) -> Option<&str> {
+        self.output.as_ref()?.opened.as_ref().map(|opened| opened.name.as_str())
+    }
+
+    // Why a mixer meant for a device is running silent; None while one plays, or when
+    // it was never attached to one
+    pub fn output_error(&self) -> Option<&str> {
+        let output = self.output.as_ref()?;
+        output.opened.is_none().then_some(output.failure.as_deref()).flatten()
+    }
+
+    // Called regularly; a device that has stopped pulling audio, like an unplugged USB
+    // interface, is swapped for another. Returns a message when that happens
+    pub fn check_output(&mut self, now: Instant) -> Option<String> {
+        let output = self.output.as_mut()?;
+        output.opened.as_ref()?;
+        let blocks = self.meters.blocks();
+        if blocks != output.blocks {
+            output.blocks = blocks;
+            output.progress = now;
+            return None;
         }
+        if now.saturating_duration_since(output.progress) < OUTPUT_STALL {
+            return None;
+        }
+        
+        Some(match self.open_output() {
+            Ok(name) => format!("Audio device lost, switched to {}", name),
+            Err(_) => "Audio device lost, running silent".to_string(),
+        })
+    }
 
-        // Calculate final volume
-        let final_volume = if self.master_muted || self.group_muted[group] {
-            0.0
-        } else {
-            self.master_volume * self.group_volumes[group]
-        };
+    pub fn play_sample(&mut self, sample_data: &[u8], group: usize) -> Result<()> {
+        self.play_sample_with_gain(sample_data, group, 1.0)
+    }
+
+    // A sample that won't decode is handed back as the error, for the caller to show
+    pub fn play_sample_with_gain(&mut self, sample_data: &[u8], group: usize, gain: f32) -> Result<()> {
+        if sample_data.is_empty() || group >= self.group_count() {
+            return Ok(());
+        }
+
+        // Volume and mute are applied live by the bus, so only the hit's own gain is fixed
+        let sample = decode_sample(sample_data)?;
+        self.add_voice(Voice::new(Arc::new(sample), group, gain));
+        Ok(())
+    }
+
+    // Plays a pad's sample between its (start, end) trim fractions, shaped by its pad settings
+    pub fn play_pad_sample(&mut self, sample_data: &[u8], group: usize, pad: usize, gain: f32, trim: (f32, f32)) -> Result<()> {
+        self.play_pad_layers(&[(&SampleStorage::InMemory(sample_data.into()), 1.0)], group, pad, gain, trim, 0.0)
+    }
 
-        let cursor = Cursor::new(sample_data.to_vec());
-        
-        match Decoder::new(cursor) {
-            Ok(source) => {
-                match Sink::try_new(&self.output_handle) {
-                    Ok(sink) => {
-                        // Apply volume to the source
-                        let amplified_source = source.amplify(final_volume);
-                        sink.append(amplified_source);
-                        sink.detach();
-                    }
-                    Err(e) => eprintln!("Failed to create audio sink: {}", e),
+    // Plays each (sample, level) as its own voice on the one hit; the pad's trim and
+    // settings apply to all of them, and a choke cuts them all at once. Semitones add to the
+    // pad's own pitch. A layer that won't decode is skipped so the rest still play, and the
+    // first failure is returned
+    pub fn play_pad_layers(&mut self, layers: &[(&SampleStorage, f32)], group: usize, pad: usize, gain: f32, trim: (f32, f32), semitones: f32) -> Result<()> {
+        if group >= self.group_count() {
+            return Ok(());
+        }
+        
+        let mut settings = self.get_pad_settings(group, pad);
+        settings.pitch += semitones;
+        if settings.mode == PlaybackMode::Choke {
+            if let Ok(mut bus) = self.bus.lock() {
+                bus.choke(group, pad);
+            }
+        }
+        let mut failure = None;
+        for &(storage, level) in layers {
+            let voice = match storage {
+                SampleStorage::InMemory(sample_data) if sample_data.is_empty() => continue,
+                SampleStorage::InMemory(sample_data) => self
+                    .decoded_layer(group, pad, sample_data, trim, settings.reverse)
+                    .map(|sample| Voice::for_pad(sample, group, pad, gain * level, &settings)),
+                // Trim and reverse need the whole sample, so streamed ones play straight through
+                SampleStorage::Streamed(path) => {
+                    SampleStream::open(path).map(|stream| Voice::streamed(stream, group, pad, gain * level, &settings))
+                }
+            };
+            match voice {
+                Ok(voice) => self.add_voice(voice),
+                Err(e) => {
+                    failure.get_or_insert(e);
                 }
             }
-            Err(e) => eprintln!("Failed to decode audio sample: {}", e),
         }
+        failure.map_or(Ok(()), Err)
     }
 
-    pub fn play_tone(&mut self, frequency: f32, duration: f32, group: usize) {
-        if group >= 4 {
-            return;
+    // The layer ready to play, decoded again only when the pad's sample, trim or reverse has
+    // changed since its last hit. Layers whose sample the bank has since dropped are let go
+    fn decoded_layer(&mut self, group: usize, pad: usize, source: &Arc<[u8]>, (start, end): (f32, f32), reverse: bool) -> Result<Arc<DecodedSample>> {
+        self.decoded.retain(|_, layers| {
+            layers.retain(|layer| Arc::strong_count(&layer.source) > 1);
+            !layers.is_empty()
+        });
+        let layers = self.decoded.entry((group, pad)).or_default();
+        if let Some(layer) = layers.iter().find(|layer| Arc::ptr_eq(&layer.source, source)) {
+            if layer.trim == (start, end) && layer.reverse == reverse {
+                return Ok(Arc::clone(&layer.sample));
+            }
+        }
+        
+        let mut sample = decode_sample(source)?;
+        if start > 0.0 || end < 1.0 {
+            sample = sample.trimmed(start, end);
         }
+        if reverse {
+            sample = sample.reversed();
+        }
+        let sample = Arc::new(sample);
+        layers.retain(|layer| !Arc::ptr_eq(&layer.source, source));
+        layers.push(DecodedLayer { source: Arc::clone(source), trim: (start, end), reverse, sample: Arc::clone(&sample) });
+        Ok(sample)
+    }
 
-        let final_volume = if self.master_muted || self.group_muted[group] {
-            0.0
+    // Voices still ringing, finished ones aside
+    pub fn voice_count(&self) -> usize {
+        self.bus.lock().map_or(0, |bus| bus.voice_count())
+    }
+
+    pub fn get_pad_settings(&self, group: usize, pad: usize) -> PadSettings {
+        self.pad_settings.get(&(group, pad)).copied().unwrap_or_default()
+    }
+
+    pub fn set_pad_settings(&mut self, group: usize, pad: usize, settings: PadSettings) {
+        if settings.is_default() {
+            self.pad_settings.remove(&(group, pad));
         } else {
-            self.master_volume * self.group_volumes[group] * 0.3
-        };
+            self.pad_settings.insert((group, pad), settings.clamped());
+        }
+    }
 
-        let sample_rate = 44100;
+    // Pads left out go back to the defaults
+    pub fn replace_pad_settings(&mut self, settings: HashMap<(usize, usize), PadSettings>) {
+        self.pad_settings.clear();
+        for ((group, pad), settings) in settings {
+            self.set_pad_settings(group, pad, settings);
+        }
+    }
+
+    pub fn pad_settings(&self) -> &HashMap<(usize, usize), PadSettings> {
+        &self.pad_settings
+    }
+
+    pub fn play_tone(&mut self, frequency: f32, duration: f32, group: usize) {
+        if group >= self.group_count() {
+            return;
+        }
+
+        let sample_rate = BUS_SAMPLE_RATE;
         let samples = (sample_rate as f32 * duration) as usize;
         
         let sine_wave = (0..samples)
             .map(|i| {
                 let t = i as f32 / sample_rate as f32;
-                (t * frequency * 2.0 * std::f32::consts::PI).sin() * final_volume
+                (t * frequency * 2.0 * std::f32::consts::PI).sin()
             })
             .collect::<Vec<f32>>();
 
-        match Sink::try_new(&self.output_handle) {
-            Ok(sink) => {
-                let source = rodio::buffer::SamplesBuffer::new(1, sample_rate, sine_wave);
-                sink.append(source);
-                sink.detach();
-            }
-            Err(e) => eprintln!("Failed to create audio sink for tone: {}", e),
+        let tone = DecodedSample {
+            channels: 1,
+            sample_rate,
+            data: sine_wave,
+        };
+        self.add_voice(Voice::new(Arc::new(tone), group, 0.3));
+    }
+
+    fn add_voice(&mut self, voice: Voice) {
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.add_voice(voice, MAX_VOICES);
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.clear_voices();
+        }
+    }
+
+    // Everything the bus plays from here on, live pads included, is streamed to a WAV at path
+    pub fn start_output_recording(&mut self, path: &str) -> Result<()> {
+        if let Some(Err(e)) = self.stop_output_recording() {
+            return Err(e);
+        }
+        let (recorder, ring) = OutputRecorder::start(path, BUS_CHANNELS, BUS_SAMPLE_RATE, MAX_CAPTURE_SECS)?;
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.start_capture(ring);
+        }
+        self.output_recorder = Some(recorder);
+        Ok(())
+    }
+
+    // Writes the rest and closes the file; returns the frames recorded, or None when the
+    // output wasn't being recorded
+    pub fn stop_output_recording(&mut self) -> Option<Result<usize>> {
+        let recorder = self.output_recorder.take()?;
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.stop_capture();
         }
+        Some(recorder.finish())
+    }
+
+    pub fn is_recording_output(&self) -> bool {
+        self.output_recorder.is_some()
+    }
+
+    pub fn set_volume_ramp_ms(&mut self, ramp_ms: f32) {
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.set_ramp_frames(ms_to_frames(ramp_ms.clamp(0.0, 500.0)));
+        }
+    }
+
+    // Pushes the fader targets to the bus, which ramps the playing audio to them
+    fn sync_gains(&mut self, ramp: bool) {
+        let master = if self.master_muted { 0.0 } else { self.master_volume * master_gain(self.master_gain_db) };
+        let groups = std::array::from_fn(|group| self.get_group_gain(group));
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.set_gains(master, &groups, ramp);
+        }
+    }
+
+    pub fn configure_limiter(&mut self, enabled: bool, threshold_db: f32) {
+        self.limiter.set_enabled(enabled);
+        self.limiter.set_threshold_db(threshold_db);
+    }
+
+    pub fn is_limiting(&self) -> bool {
+        self.limiter.is_engaged()
+    }
+
+    // Decaying peak levels, linear; the master reads after the limiter
+    pub fn get_group_level(&self, group: usize) -> f32 {
+        self.meters.group(group)
+    }
+
+    pub fn get_master_level(&self) -> f32 {
+        self.meters.master()
+    }
+
+    pub fn is_clipping(&self) -> bool {
+        self.meters.is_clipping()
+    }
+
+    /// Pulls interleaved stereo audio through the bus by hand, standing in for the output
+    /// device of a [`Mixer::detached`] mixer.
+    pub fn render_frames(&self, frames: usize) -> Vec<f32> {
+        let mut block = vec![0.0; frames * crate::bus::BUS_CHANNELS as usize];
+        self.bus.lock().unwrap().render(&mut block);
+        block
     }
 
     // Master volume controls
     pub fn set_master_volume(&mut self, volume: f32) {
         self.master_volume = volume.clamp(0.0, 1.0);
+        self.sync_gains(true);
     }
 
     pub fn get_master_volume(&self) -> f32 {
@@ -99,25 +500,70 @@ impl Mixer {
 
     pub fn adjust_master_volume(&mut self, delta: f32) {
         self.master_volume = (self.master_volume + delta).clamp(0.0, 1.0);
+        self.sync_gains(true);
+    }
+
+    pub fn set_master_gain_db(&mut self, db: f32) {
+        self.master_gain_db = db.clamp(MIN_MASTER_GAIN_DB, MAX_MASTER_GAIN_DB);
+        self.sync_gains(true);
+    }
+
+    pub fn get_master_gain_db(&self) -> f32 {
+        self.master_gain_db
     }
 
     pub fn toggle_master_mute(&mut self) {
         self.master_muted = !self.master_muted;
+        self.sync_gains(true);
     }
 
     pub fn is_master_muted(&self) -> bool {
         self.master_muted
     }
 
+    pub fn get_master_width(&self) -> f32 {
+        self.master_width
+    }
+
+    pub fn adjust_master_width(&mut self, delta: f32) {
+        self.set_master_width(self.master_width + delta);
+    }
+
+    pub fn set_master_width(&mut self, width: f32) {
+        self.master_width = width.clamp(0.0, MAX_MASTER_WIDTH);
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.set_width(self.master_width);
+        }
+    }
+
     // Group volume controls
+    pub fn group_count(&self) -> usize {
+        self.group_volumes.len()
+    }
+
+    // Added groups start at the default level with their sends closed
+    pub fn set_group_count(&mut self, groups: usize) {
+        let groups = groups.clamp(1, MAX_GROUPS);
+        self.group_volumes.resize(groups, DEFAULT_GROUP_VOLUME);
+        self.group_muted.resize(groups, false);
+        self.group_soloed.resize(groups, false);
+        self.delay_sends.resize(groups, 0.0);
+        self.reverb_sends.resize(groups, 0.0);
+        self.group_gates.resize(groups, None);
+        self.sync_gains(true);
+        self.sync_sends();
+        self.sync_gates();
+    }
+
     pub fn set_group_volume(&mut self, group: usize, volume: f32) {
-        if group < 4 {
+        if group < self.group_count() {
             self.group_volumes[group] = volume.clamp(0.0, 1.0);
+            self.sync_gains(true);
         }
     }
 
     pub fn get_group_volume(&self, group: usize) -> f32 {
-        if group < 4 {
+        if group < self.group_count() {
             self.group_volumes[group]
         } else {
             0.0
@@ -125,27 +571,254 @@ impl Mixer {
     }
 
     pub fn adjust_group_volume(&mut self, group: usize, delta: f32) {
-        if group < 4 {
+        if group < self.group_count() {
             self.group_volumes[group] = (self.group_volumes[group] + delta).clamp(0.0, 1.0);
+            self.sync_gains(true);
+        }
+    }
+
+    pub fn set_group_mute(&mut self, group: usize, muted: bool) {
+        if group < self.group_count() && self.group_muted[group] != muted {
+            self.group_muted[group] = muted;
+            self.sync_gains(true);
         }
     }
 
     pub fn toggle_group_mute(&mut self, group: usize) {
-        if group < 4 {
+        if group < self.group_count() {
             self.group_muted[group] = !self.group_muted[group];
+            self.sync_gains(true);
         }
     }
 
     pub fn is_group_muted(&self, group: usize) -> bool {
-        if group < 4 {
+        if group < self.group_count() {
             self.group_muted[group]
         } else {
             false
         }
     }
 
-    pub fn get_group_names() -> &'static [&'static str] {
-        &["DRUMS", "BASS", "LEAD", "VOCAL"]
+    pub fn toggle_group_solo(&mut self, group: usize) {
+        if group < self.group_count() {
+            self.group_soloed[group] = !self.group_soloed[group];
+            self.sync_gains(true);
+        }
+    }
+
+    pub fn is_group_soloed(&self, group: usize) -> bool {
+        self.group_soloed.get(group).copied().unwrap_or(false)
+    }
+
+    pub fn is_solo_active(&self) -> bool {
+        self.group_soloed.contains(&true)
+    }
+
+    // Returns whether the pad is now muted
+    pub fn toggle_pad_mute(&mut self, group: usize, pad: usize) -> bool {
+        if !self.muted_pads.remove(&(group, pad)) {
+            self.muted_pads.insert((group, pad));
+        }
+        self.is_pad_muted(group, pad)
+    }
+
+    pub fn is_pad_muted(&self, group: usize, pad: usize) -> bool {
+        self.muted_pads.contains(&(group, pad))
+    }
+
+    pub fn muted_pads(&self) -> &HashSet<(usize, usize)> {
+        &self.muted_pads
+    }
+
+    // What a bounce starts the group from: its fader, mute and solo. Of the master stage
+    // only the dB gain reaches a bounce, through render::Feel
+    pub fn bounce_level(&self, group: usize) -> GroupLevel {
+        GroupLevel {
+            volume: self.get_group_volume(group),
+            muted: self.is_group_muted(group),
+            soloed_out: group >= self.group_count() || (self.is_solo_active() && !self.is_group_soloed(group)),
+        }
+    }
+
+    // Whether the group reaches the output at all, whatever its volume: not muted, not left
+    // out by a solo, and the master isn't muted
+    pub fn is_group_heard(&self, group: usize) -> bool {
+        let level = self.bounce_level(group);
+        !self.master_muted && !level.muted && !level.soloed_out
+    }
+
+    // Group level as heard by a bounce: volume, mute and solo, but not the master stage
+    pub fn get_group_gain(&self, group: usize) -> f32 {
+        let soloed_out = self.is_solo_active() && !self.is_group_soloed(group);
+        if group >= self.group_count() || self.group_muted[group] || soloed_out {
+            0.0
+        } else {
+            self.group_volumes[group]
+        }
+    }
+
+    // Send effects
+    pub fn adjust_delay_send(&mut self, group: usize, delta: f32) {
+        if group < self.group_count() {
+            self.delay_sends[group] = (self.delay_sends[group] + delta).clamp(0.0, 1.0);
+            self.sync_sends();
+        }
+    }
+
+    pub fn get_delay_send(&self, group: usize) -> f32 {
+        self.delay_sends.get(group).copied().unwrap_or(0.0)
+    }
+
+    pub fn adjust_reverb_send(&mut self, group: usize, delta: f32) {
+        if group < self.group_count() {
+            self.reverb_sends[group] = (self.reverb_sends[group] + delta).clamp(0.0, 1.0);
+            self.sync_sends();
+        }
+    }
+
+    pub fn get_reverb_send(&self, group: usize) -> f32 {
+        self.reverb_sends.get(group).copied().unwrap_or(0.0)
+    }
+
+    pub fn cycle_reverb_size(&mut self) -> ReverbSize {
+        self.reverb_size = self.reverb_size.next();
+        self.sync_sends();
+        self.reverb_size
+    }
+
+    pub fn get_reverb_size(&self) -> ReverbSize {
+        self.reverb_size
+    }
+
+    fn sync_sends(&mut self) {
+        let delay_sends = std::array::from_fn(|group| self.get_delay_send(group));
+        let reverb_sends = std::array::from_fn(|group| self.get_reverb_send(group));
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.sends.delay_sends = delay_sends;
+            bus.sends.reverb_sends = reverb_sends;
+            bus.sends.set_reverb_size(self.reverb_size);
+        }
+    }
+
+    // Turns the group's gate on with the 8th-note pattern, or off; returns whether it's on
+    pub fn toggle_group_gate(&mut self, group: usize) -> bool {
+        let gate = match self.get_group_gate(group) {
+            Some(_) => None,
+            None => Some(crate::effects::DEFAULT_GATE_PATTERN),
+        };
+        self.set_group_gate(group, gate);
+        gate.is_some()
+    }
+
+    pub fn set_group_gate(&mut self, group: usize, gate: Option<[bool; GATE_STEPS]>) {
+        if group < self.group_count() {
+            self.group_gates[group] = gate;
+            self.sync_gates();
+        }
+    }
+
+    pub fn get_group_gate(&self, group: usize) -> Option<[bool; GATE_STEPS]> {
+        self.group_gates.get(group).copied().flatten()
+    }
+
+    // Lines every gate up with the step the sequencer is playing
+    pub fn sync_gates_to_step(&mut self, step: usize) {
+        if let Ok(mut bus) = self.bus.lock() {
+            for gate in bus.gates.iter_mut().flatten() {
+                gate.sync_to_step(step);
+            }
+        }
+    }
+
+    fn sync_gates(&mut self) {
+        let (tempo, steps_per_beat) = (self.tempo, self.steps_per_beat);
+        let patterns: [_; MAX_GROUPS] = std::array::from_fn(|group| self.get_group_gate(group));
+        if let Ok(mut bus) = self.bus.lock() {
+            for (gate, pattern) in bus.gates.iter_mut().zip(patterns) {
+                match (gate, pattern) {
+                    // A gate that stays on keeps its place in the bar
+                    (Some(gate), Some(pattern)) => gate.set_pattern(pattern),
+                    (gate, pattern) => *gate = pattern.map(|pattern| GateEffect::new(pattern, tempo, steps_per_beat)),
+                }
+            }
+        }
+    }
+
+    pub fn settings(&self) -> MixerSettings {
+        let mut muted_pads: Vec<_> = self.muted_pads.iter().copied().collect();
+        muted_pads.sort();
+        MixerSettings {
+            master_volume: self.master_volume,
+            master_gain_db: self.master_gain_db,
+            master_muted: self.master_muted,
+            master_width: self.master_width,
+            group_volumes: self.group_volumes.clone(),
+            group_muted: self.group_muted.clone(),
+            group_soloed: self.group_soloed.clone(),
+            delay_sends: self.delay_sends.clone(),
+            reverb_sends: self.reverb_sends.clone(),
+            reverb_size: self.reverb_size,
+            muted_pads,
+            group_gates: self.group_gates.clone(),
+        }
+    }
+
+    // Values from a file are clamped like the knobs would be, and fitted to the group count:
+    // groups the file doesn't have keep their defaults, extra ones are dropped
+    pub fn apply_settings(&mut self, settings: &MixerSettings) {
+        let groups = self.group_count();
+        let levels = |values: &[f32], default: f32| fit(values, groups, default).into_iter().map(|level| level.clamp(0.0, 1.0)).collect();
+        self.master_volume = settings.master_volume.clamp(0.0, 1.0);
+        self.master_gain_db = settings.master_gain_db.clamp(MIN_MASTER_GAIN_DB, MAX_MASTER_GAIN_DB);
+        self.master_muted = settings.master_muted;
+        self.set_master_width(settings.master_width);
+        self.group_volumes = levels(&settings.group_volumes, DEFAULT_GROUP_VOLUME);
+        self.group_muted = fit(&settings.group_muted, groups, false);
+        self.group_soloed = fit(&settings.group_soloed, groups, false);
+        self.delay_sends = levels(&settings.delay_sends, 0.0);
+        self.reverb_sends = levels(&settings.reverb_sends, 0.0);
+        self.reverb_size = settings.reverb_size;
+        self.muted_pads = settings.muted_pads.iter().copied().filter(|&(group, pad)| group < MAX_GROUPS && pad < MAX_PADS).collect();
+        self.group_gates = fit(&settings.group_gates, groups, None);
+        self.sync_gains(true);
+        self.sync_sends();
+        self.sync_gates();
+    }
+
+    // Keeps the tempo-synced delay and the gates in time
+    pub fn set_tempo(&mut self, tempo: f32) {
+        self.tempo = tempo;
+        if let Ok(mut bus) = self.bus.lock() {
+            bus.sends.set_tempo(tempo);
+            for gate in bus.gates.iter_mut().flatten() {
+                gate.set_tempo(tempo, self.steps_per_beat);
+            }
+        }
+    }
+
+    // Gate steps last as long as the sequencer's, so they follow the signature's resolution
+    pub fn set_steps_per_beat(&mut self, steps_per_beat: usize) {
+        if steps_per_beat == self.steps_per_beat {
+            return;
+        }
+        self.steps_per_beat = steps_per_beat;
+        if let Ok(mut bus) = self.bus.lock() {
+            for gate in bus.gates.iter_mut().flatten() {
+                gate.set_tempo(self.tempo, steps_per_beat);
+            }
+        }
+    }
+}
+
+fn fit<T: Copy>(values: &[T], len: usize, default: T) -> Vec<T> {
+    let mut values = values[..values.len().min(len)].to_vec();
+    values.resize(len, default);
+    values
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -211,6 +884,56 @@ mod tests {
         assert!(!mixer.is_master_muted());
     }
 
+    #[test]
+    fn test_solo_gain_decisions() {
+        let mut mixer = Mixer::detached();
+        let audible = |mixer: &Mixer| (0..4).map(|group| mixer.get_group_gain(group) > 0.0).collect::<Vec<_>>();
+        assert_eq!(audible(&mixer), [true; 4]);
+        
+        // Soloing one group silences the rest, muted or not
+        mixer.toggle_group_mute(3);
+        mixer.toggle_group_solo(1);
+        assert_eq!(audible(&mixer), [false, true, false, false]);
+        
+        // Several soloed groups all play
+        mixer.toggle_group_solo(2);
+        assert_eq!(audible(&mixer), [false, true, true, false]);
+        
+        // A soloed group that's also muted stays muted
+        mixer.toggle_group_solo(3);
+        assert_eq!(audible(&mixer), [false, true, true, false]);
+        mixer.toggle_group_mute(3);
+        assert_eq!(audible(&mixer), [false, true, true, true]);
+        
+        // Clearing every solo brings back the normal mix, mutes included
+        for group in 1..4 {
+            mixer.toggle_group_solo(group);
+        }
+        mixer.toggle_group_mute(0);
+        assert!(!mixer.is_solo_active());
+        assert_eq!(audible(&mixer), [false, true, true, true]);
+        assert!((mixer.get_group_gain(1) - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_master_width_folds_to_mono() {
+        let mut mixer = Mixer::detached();
+        assert_eq!(mixer.get_master_width(), 1.0);
+        mixer.adjust_master_width(5.0);
+        assert_eq!(mixer.get_master_width(), MAX_MASTER_WIDTH);
+        mixer.adjust_master_width(-5.0);
+        assert_eq!(mixer.get_master_width(), 0.0);
+        
+        // A hard-left pad comes out of both sides once the master is mono
+        let constant = crate::render::encode_wav(&[0.5; 2048], 1, BUS_SAMPLE_RATE).unwrap();
+        let settings = PadSettings { pan: -1.0, ..PadSettings::default() };
+        mixer.set_pad_settings(0, 0, settings);
+        mixer.play_pad_sample(&constant, 0, 0, 1.0, (0.0, 1.0)).unwrap();
+        let block = mixer.render_frames(256);
+        assert!(block[0] > 0.01);
+        assert!(block.chunks_exact(2).all(|frame| (frame[0] - frame[1]).abs() < 1e-6));
+    }
+
     #[test]
     fn test_group_volume_controls() {
         let mut mixer = Mixer::new();
@@ -292,12 +1015,33 @@ mod tests {
     fn test_play_sample_validation() {
         let mut mixer = Mixer::new();
         
-        // Test empty sample data (should not panic)
-        mixer.play_sample(&[], 0);
-        
-        // Test invalid group (should not panic)
+        // Empty sample data and groups that don't exist play nothing, without an error
+        assert!(mixer.play_sample(&[], 0).is_ok());
         let sample_data = vec![1, 2, 3, 4];
-        mixer.play_sample(&sample_data, 99);
+        assert!(mixer.play_sample(&sample_data, 99).is_ok());
+        
+        // A sample that won't decode comes back as the error instead of being dropped quietly
+        let err = mixer.play_sample_with_gain(b"RIFF\x24\x10\x00\x00WAVEnoise", 0, 1.0).unwrap_err();
+        assert!(err.to_string().contains("Failed to decode sample"), "{}", err);
+        assert_eq!(mixer.voice_count(), 0);
+    }
+
+    #[test]
+    fn test_pad_mutes_are_saved() {
+        let mut mixer = Mixer::detached();
+        assert!(mixer.toggle_pad_mute(1, 5));
+        assert!(mixer.toggle_pad_mute(0, 2));
+        assert!(mixer.is_pad_muted(1, 5));
+        assert!(!mixer.is_pad_muted(0, 5));
+        assert_eq!(mixer.settings().muted_pads, [(0, 2), (1, 5)]);
+        
+        assert!(!mixer.toggle_pad_mute(1, 5));
+        assert_eq!(mixer.settings().muted_pads, [(0, 2)]);
+        
+        // Pads that don't exist are dropped on load
+        let settings = MixerSettings { muted_pads: vec![(3, 31), (3, MAX_PADS), (MAX_GROUPS, 0)], ..MixerSettings::default() };
+        mixer.apply_settings(&settings);
+        assert_eq!(mixer.settings().muted_pads, [(3, 31)]);
     }
 
     #[test]
@@ -311,16 +1055,6 @@ mod tests {
         mixer.play_tone(440.0, 0.01, 0); // Very short duration to avoid blocking test
     }
 
-    #[test]
-    fn test_group_names() {
-        let names = Mixer::get_group_names();
-        assert_eq!(names.len(), 4);
-        assert_eq!(names[0], "DRUMS");
-        assert_eq!(names[1], "BASS");
-        assert_eq!(names[2], "LEAD");
-        assert_eq!(names[3], "VOCAL");
-    }
-
     #[test]
     fn test_volume_calculation_with_mute() {
         let mut mixer = Mixer::new();
@@ -344,4 +1078,341 @@ mod tests {
         // These tests verify the logic exists without testing audio output
         assert!(mixer.is_master_muted() || !mixer.is_master_muted()); // Basic state verification
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stop_all_clears_voices() {
+        let mut mixer = Mixer::new();
+        
+        mixer.play_tone(440.0, 2.0, 0);
+        mixer.play_tone(220.0, 2.0, 1);
+        
+        mixer.stop_all();
+        let mut block = vec![1.0; 512];
+        mixer.bus.lock().unwrap().render(&mut block);
+        assert!(block.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_output_recording_captures_what_plays() {
+        let mut mixer = Mixer::detached();
+        let constant = crate::render::encode_wav(&[0.5; 2048], 1, BUS_SAMPLE_RATE).unwrap();
+        let path = std::env::temp_dir().join("ko2_output_recording.wav");
+        let path = path.to_str().unwrap();
+        assert!(mixer.stop_output_recording().is_none());
+        
+        mixer.start_output_recording(path).unwrap();
+        assert!(mixer.is_recording_output());
+        mixer.render_frames(256);
+        mixer.play_sample(&constant, 1).unwrap();
+        let heard = mixer.render_frames(256);
+        
+        assert_eq!(mixer.stop_output_recording().unwrap().unwrap(), 512);
+        assert!(!mixer.is_recording_output());
+        // The silence before the hit is kept, then the hit as it went to the output
+        let capture: Vec<f32> = hound::WavReader::open(path)
+            .unwrap()
+            .into_samples::<i16>()
+            .map(|s| s.unwrap() as f32 / i16::MAX as f32)
+            .collect();
+        let _ = std::fs::remove_file(path);
+        assert_eq!(capture.len(), 512 * crate::bus::BUS_CHANNELS as usize);
+        assert!(capture[..512].iter().all(|&s| s == 0.0));
+        assert!(capture[512..].iter().zip(&heard).all(|(a, b)| (a - b).abs() < 1e-4));
+        assert!((capture[512] - 0.28).abs() < 0.01);
+        
+        // Nothing more is kept once it's stopped, and a path that can't be written fails up front
+        mixer.render_frames(256);
+        assert!(mixer.stop_output_recording().is_none());
+        assert!(mixer.start_output_recording("/no/such/dir/jam.wav").is_err());
+        assert!(!mixer.is_recording_output());
+    }
+
+    #[test]
+    fn test_level_meters() {
+        let mut mixer = Mixer::detached();
+        let constant = crate::render::encode_wav(&[0.5; 2048], 1, BUS_SAMPLE_RATE).unwrap();
+        
+        mixer.play_sample(&constant, 1).unwrap();
+        mixer.render_frames(256);
+        
+        // 0.5 through group 0.8, then master 0.7
+        assert!((mixer.get_group_level(1) - 0.4).abs() < 0.01);
+        assert!((mixer.get_master_level() - 0.28).abs() < 0.01);
+        assert_eq!(mixer.get_group_level(0), 0.0);
+        
+        // Once the sample ends the peak falls away rather than dropping at once
+        mixer.render_frames(4096);
+        let falling = mixer.get_group_level(1);
+        assert!(falling > 0.0 && falling < 0.4);
+        for _ in 0..300 {
+            mixer.render_frames(256);
+        }
+        assert!(mixer.get_group_level(1) < 0.01);
+    }
+
+    #[test]
+    fn test_master_gain_drives_the_limiter() {
+        let mut mixer = Mixer::detached();
+        mixer.set_volume_ramp_ms(0.0);
+        let constant = crate::render::encode_wav(&[0.5; 2048], 1, BUS_SAMPLE_RATE).unwrap();
+        mixer.set_master_volume(1.0);
+        mixer.set_master_gain_db(40.0);
+        assert_eq!(mixer.get_master_gain_db(), MAX_MASTER_GAIN_DB);
+        
+        // 0.4 from the group comes to about 1.6 at +12 dB, which the limiter holds down
+        mixer.play_sample(&constant, 1).unwrap();
+        let block = mixer.render_frames(1024);
+        assert!(block.iter().all(|s| s.abs() <= 1.0));
+        assert!(mixer.is_limiting());
+        assert!(!mixer.is_clipping());
+        
+        // Without it the master goes over and says so
+        mixer.configure_limiter(false, DEFAULT_LIMITER_THRESHOLD_DB);
+        mixer.stop_all();
+        mixer.play_sample(&constant, 1).unwrap();
+        mixer.render_frames(256);
+        assert!((mixer.get_master_level() - 1.59).abs() < 0.01);
+        assert!(mixer.is_clipping());
+        
+        // Saved with the mix, and the bottom of the range is silence
+        let mut restored = Mixer::detached();
+        restored.apply_settings(&mixer.settings());
+        assert_eq!(restored.get_master_gain_db(), MAX_MASTER_GAIN_DB);
+        mixer.set_master_gain_db(-100.0);
+        assert_eq!(mixer.get_master_gain_db(), MIN_MASTER_GAIN_DB);
+        mixer.stop_all();
+        mixer.play_sample(&constant, 1).unwrap();
+        assert!(mixer.render_frames(256).iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_streamed_pads_play_like_held_ones() {
+        // Short enough to be read whole before it's handed to the bus, so the reader can't lag
+        let samples: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let wav = crate::render::encode_wav(&samples, 1, 22050).unwrap();
+        let path = std::env::temp_dir().join("ko2_streamed_voice.wav");
+        std::fs::write(&path, &wav).unwrap();
+        
+        // Resampled, with the pad's gain and pan, and through the group fader
+        let settings = PadSettings { gain: 0.5, pan: -0.5, ..PadSettings::default() };
+        let render = |storage: SampleStorage| {
+            let mut mixer = Mixer::detached();
+            mixer.set_pad_settings(1, 2, settings);
+            mixer.play_pad_layers(&[(&storage, 0.8)], 1, 2, 1.0, (0.0, 1.0), 0.0).unwrap();
+            mixer.render_frames(20_000)
+        };
+        let held = render(SampleStorage::InMemory(wav.into()));
+        let streamed = render(SampleStorage::Streamed(path.clone()));
+        assert!(held.iter().any(|&sample| sample.abs() > 0.1));
+        assert!(held.iter().zip(&streamed).all(|(a, b)| (a - b).abs() < 1e-6));
+        
+        let mut mixer = Mixer::detached();
+        let missing = SampleStorage::Streamed(std::env::temp_dir().join("ko2_no_such_sample.wav"));
+        assert!(mixer.play_pad_layers(&[(&missing, 1.0)], 0, 0, 1.0, (0.0, 1.0), 0.0).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_streamed_pads_are_cleaned_up() {
+        // Within the stream's prefill, so playing out doesn't depend on the reader's timing
+        let wav = crate::render::encode_wav(&[0.5; 2000], 1, BUS_SAMPLE_RATE).unwrap();
+        let path = std::env::temp_dir().join("ko2_played_file.wav");
+        std::fs::write(&path, &wav).unwrap();
+        let storage = SampleStorage::Streamed(path.clone());
+        let play = |mixer: &mut Mixer, group| mixer.play_pad_layers(&[(&storage, 1.0)], group, 0, 1.0, (0.0, 1.0), 0.0);
+        
+        let mut mixer = Mixer::detached();
+        play(&mut mixer, 0).unwrap();
+        play(&mut mixer, 1).unwrap();
+        assert_eq!(mixer.voice_count(), 2);
+        assert!(mixer.render_frames(256).iter().any(|&sample| sample != 0.0));
+        
+        // Played out, the voices are gone from the bus rather than piling up
+        for _ in 0..20 {
+            mixer.render_frames(256);
+        }
+        assert_eq!(mixer.voice_count(), 0);
+        
+        // Stop-all cuts a file that's still playing
+        play(&mut mixer, 0).unwrap();
+        mixer.stop_all();
+        assert_eq!(mixer.voice_count(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_layers_play_and_choke_together() {
+        let mut mixer = Mixer::detached();
+        let long = SampleStorage::InMemory(crate::render::encode_wav(&[0.5; 44100], 1, BUS_SAMPLE_RATE).unwrap().into());
+        let layers: [(&SampleStorage, f32); 3] = [(&long, 1.0), (&long, 0.5), (&long, 0.5)];
+        
+        mixer.play_pad_layers(&layers, 0, 0, 1.0, (0.0, 1.0), 0.0).unwrap();
+        assert_eq!(mixer.voice_count(), 3);
+        
+        // A choke pad cuts all of its layers before they come back, rather than piling up
+        mixer.set_pad_settings(0, 0, PadSettings { mode: PlaybackMode::Choke, ..PadSettings::default() });
+        mixer.play_pad_layers(&layers, 0, 0, 1.0, (0.0, 1.0), 0.0).unwrap();
+        assert_eq!(mixer.voice_count(), 3);
+        mixer.play_pad_layers(&layers[..1], 0, 0, 1.0, (0.0, 1.0), 0.0).unwrap();
+        assert_eq!(mixer.voice_count(), 1);
+    }
+
+    #[test]
+    fn test_pad_hits_reuse_the_decoded_sample() {
+        let mut mixer = Mixer::detached();
+        let wav = crate::render::encode_wav(&[0.5; 4410], 1, BUS_SAMPLE_RATE).unwrap();
+        let storage = SampleStorage::InMemory(wav.clone().into());
+        let cached = |mixer: &Mixer| Arc::clone(&mixer.decoded[&(0, 0)][0].sample);
+        
+        mixer.play_pad_layers(&[(&storage, 1.0)], 0, 0, 1.0, (0.0, 1.0), 0.0).unwrap();
+        let first = cached(&mixer);
+        mixer.play_pad_layers(&[(&storage, 1.0)], 0, 0, 1.0, (0.0, 1.0), 0.0).unwrap();
+        assert!(Arc::ptr_eq(&first, &cached(&mixer)));
+        
+        // A new trim or reverse decodes again
+        mixer.play_pad_layers(&[(&storage, 1.0)], 0, 0, 1.0, (0.0, 0.5), 0.0).unwrap();
+        let trimmed = cached(&mixer);
+        assert!(!Arc::ptr_eq(&first, &trimmed));
+        assert_eq!(mixer.decoded[&(0, 0)].len(), 1);
+        mixer.set_pad_settings(0, 0, PadSettings { reverse: true, ..PadSettings::default() });
+        mixer.play_pad_layers(&[(&storage, 1.0)], 0, 0, 1.0, (0.0, 0.5), 0.0).unwrap();
+        assert!(!Arc::ptr_eq(&trimmed, &cached(&mixer)));
+        
+        // So does a new sample, even with the same bytes, and the old one is let go
+        drop(storage);
+        let replaced = SampleStorage::InMemory(wav.into());
+        mixer.play_pad_layers(&[(&replaced, 1.0)], 0, 0, 1.0, (0.0, 0.5), 0.0).unwrap();
+        assert_eq!(mixer.decoded[&(0, 0)].len(), 1);
+        assert!(matches!(&replaced, SampleStorage::InMemory(bytes) if Arc::ptr_eq(bytes, &mixer.decoded[&(0, 0)][0].source)));
+    }
+
+    #[test]
+    fn test_mute_fades_playing_voices() {
+        let mut mixer = Mixer::detached();
+        mixer.play_tone(440.0, 1.0, 0);
+        assert!(mixer.render_frames(256).iter().any(|&s| s.abs() > 0.1));
+        
+        // Already-playing audio follows the mute, over the ramp rather than at once
+        mixer.toggle_master_mute();
+        let fading = mixer.render_frames(256);
+        assert!(fading[..64].iter().any(|&s| s.abs() > 0.05));
+        mixer.render_frames(256);
+        assert!(mixer.render_frames(256).iter().all(|&s| s == 0.0));
+        
+        mixer.toggle_master_mute();
+        mixer.render_frames(512);
+        assert!(mixer.render_frames(256).iter().any(|&s| s.abs() > 0.1));
+    }
+
+    #[test]
+    fn test_group_gate() {
+        let mut mixer = Mixer::detached();
+        let step = crate::render::step_frames(DEFAULT_TEMPO, BUS_SAMPLE_RATE);
+        let constant = crate::render::encode_wav(&vec![0.5; step * 8], 1, BUS_SAMPLE_RATE).unwrap();
+        
+        assert!(mixer.toggle_group_gate(1));
+        assert_eq!(mixer.settings().group_gates[1], Some(crate::effects::DEFAULT_GATE_PATTERN));
+        assert_eq!(mixer.get_group_gate(0), None);
+        
+        // The gated group is heard on steps 0 and 1 and cut on 2 and 3
+        mixer.sync_gates_to_step(0);
+        mixer.play_sample(&constant, 1).unwrap();
+        let audio = mixer.render_frames(step * 4);
+        let level = |step_index: usize| audio[(step * step_index + step / 2) * 2].abs();
+        assert!(level(0) > 0.2 && level(1) > 0.2);
+        assert_eq!(level(2), 0.0);
+        assert_eq!(level(3), 0.0);
+        
+        // Saved with the mixer settings, and back off with another toggle
+        let mut loaded = Mixer::detached();
+        loaded.apply_settings(&mixer.settings());
+        assert!(loaded.get_group_gate(1).is_some());
+        assert!(!mixer.toggle_group_gate(1));
+        assert!(mixer.bus.lock().unwrap().gates.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_send_levels() {
+        let mut mixer = Mixer::new();
+        assert_eq!(mixer.get_delay_send(0), 0.0);
+        
+        mixer.adjust_delay_send(0, 0.3);
+        mixer.adjust_reverb_send(2, 1.5);
+        assert!((mixer.get_delay_send(0) - 0.3).abs() < 0.001);
+        assert_eq!(mixer.get_reverb_send(2), 1.0);
+        assert_eq!(mixer.get_reverb_send(0), 0.0);
+        
+        // Room size cycles and is saved with the other send settings
+        assert_eq!(mixer.cycle_reverb_size(), ReverbSize::Large);
+        assert_eq!(mixer.settings().reverb_size, ReverbSize::Large);
+        
+        // The bus sees the same levels
+        let bus = mixer.bus.lock().unwrap();
+        assert_eq!(bus.sends.delay_sends[..mixer.group_count()], mixer.delay_sends);
+        assert_eq!(bus.sends.reverb_sends[..mixer.group_count()], mixer.reverb_sends);
+    }
+
+    // Plays on whichever of the named devices are plugged in; None is the default device
+    fn fake_devices(plugged: &std::rc::Rc<std::cell::RefCell<Vec<&'static str>>>) -> OutputFactory {
+        let plugged = std::rc::Rc::clone(plugged);
+        Box::new(move |name: Option<&str>, _source: BusSource| {
+            let plugged = plugged.borrow();
+            let found = match name {
+                Some(name) => plugged.iter().find(|device| **device == name),
+                None => plugged.first(),
+            };
+            found
+                .map(|device| OpenedOutput { name: device.to_string(), _stream: Box::new(()) })
+                .ok_or_else(|| "No audio output device available".to_string())
+        })
+    }
+
+    #[test]
+    fn test_lost_device_switches_to_another() {
+        let plugged = std::rc::Rc::new(std::cell::RefCell::new(vec!["Speakers", "USB Interface"]));
+        let mut mixer = Mixer::with_output_factory(fake_devices(&plugged));
+        assert_eq!(mixer.set_output_device(Some("USB Interface".to_string())), Some(Ok("Audio on USB Interface".to_string())));
+        assert_eq!(mixer.output_device_name(), Some("USB Interface"));
+        
+        // A device that keeps pulling audio is left alone
+        let start = Instant::now();
+        mixer.render_frames(64);
+        assert_eq!(mixer.check_output(start + OUTPUT_STALL * 2), None);
+        
+        // Unplugged: nothing is rendered, and after the stall the default takes over
+        plugged.borrow_mut().retain(|device| *device != "USB Interface");
+        let later = start + OUTPUT_STALL * 2;
+        assert_eq!(mixer.check_output(later + OUTPUT_STALL / 2), None);
+        assert_eq!(mixer.check_output(later + OUTPUT_STALL), Some("Audio device lost, switched to Speakers".to_string()));
+        assert_eq!(mixer.output_device_name(), Some("Speakers"));
+        
+        // Plugged back in, a manual reinit goes back to the configured device
+        plugged.borrow_mut().push("USB Interface");
+        assert_eq!(mixer.reinit_output(), Ok("Audio on USB Interface".to_string()));
+    }
+
+    #[test]
+    fn test_no_device_runs_silent() {
+        let plugged = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut mixer = Mixer::with_output_factory(fake_devices(&plugged));
+        assert_eq!(mixer.output_device_name(), None);
+        assert_eq!(mixer.output_error(), Some("No audio output device available"));
+        assert!(mixer.reinit_output().unwrap_err().starts_with("No audio device, running silent"));
+        
+        // Nothing was opened, so there is nothing to lose
+        assert_eq!(mixer.check_output(Instant::now() + OUTPUT_STALL * 2), None);
+        
+        // A device that goes away with nothing to replace it leaves the mixer silent
+        plugged.borrow_mut().push("Speakers");
+        assert!(mixer.reinit_output().is_ok());
+        assert_eq!(mixer.output_error(), None);
+        plugged.borrow_mut().clear();
+        assert_eq!(mixer.check_output(Instant::now() + OUTPUT_STALL * 2), Some("Audio device lost, running silent".to_string()));
+        assert_eq!(mixer.output_device_name(), None);
+        mixer.render_frames(64);
+        
+        // Detached mixers never open anything, and aren't missing a device either
+        assert_eq!(Mixer::detached().output_error(), None);
+        assert!(Mixer::detached().reinit_output().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::net::UdpSocket;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use rosc::{OscMessage, OscPacket, OscType};
+use crate::command::Command;
+use crate::error::Result;
+use crate::sequencer::{MAX_GROUPS, MAX_MORPH_BARS, MAX_PADS, MAX_PATTERNS};
+
+// Largest datagram we accept; control messages are tiny
+const MAX_PACKET_SIZE: usize = 1536;
+
+// Listens on the given address and UDP port and forwards recognised messages to the main
+// loop, along with a line on each message it drops for the footer
+pub fn spawn_listener(bind: &str, port: u16, commands: Sender<std::result::Result<Command, String>>) -> Result<()> {
+    let socket = UdpSocket::bind((bind, port))?;
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        loop {
+            let Ok((len, from)) = socket.recv_from(&mut buffer) else {
+                continue;
+            };
+
+            let received = match rosc::decoder::decode_udp(&buffer[..len]) {
+                Ok((_, packet)) => packet_messages(packet)
+                    .iter()
+                    .map(|message| message_to_command(message).ok_or_else(|| format!("Ignoring OSC message {}", message.addr)))
+                    .collect(),
+                Err(_) => vec![Err(format!("Ignoring malformed OSC packet from {}", from))],
+            };
+
+            for command in received {
+                // The main loop is gone, so there's nobody left to listen for
+                if commands.send(command).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+// Maps /koii/... addresses onto commands; groups and pads are numbered from 1
+pub fn message_to_command(message: &OscMessage) -> Option<Command> {
+    let parts: Vec<&str> = message.addr.strip_prefix("/koii/")?.split('/').collect();
+    let value = message.args.first().and_then(arg_value);
+
+    // Controller buttons send 1 on press and 0 on release; only the press acts
+    let pressed = value.is_none_or(|value| value != 0.0);
+
+    match parts.as_slice() {
+        ["play"] if pressed => Some(Command::TogglePlayback),
+        ["stop"] if pressed => Some(Command::Stop),
+        ["record"] if pressed => Some(Command::ToggleRecording),
+        ["panic"] if pressed => Some(Command::Panic),
+        ["tempo"] => value.map(Command::SetTempo),
+        ["pattern", pattern] if pressed => Some(Command::GoToPattern(parse_index(pattern, MAX_PATTERNS)?)),
+        ["morph", pattern, bars] if pressed => {
+            let bars = bars.parse().ok().filter(|bars| (1..=MAX_MORPH_BARS).contains(bars))?;
+            Some(Command::StartMorph(parse_index(pattern, MAX_PATTERNS)?, bars))
+        }
+        ["trigger", group, pad] if pressed => {
+            let group = parse_index(group, MAX_GROUPS)?;
+            let pad = parse_index(pad, MAX_PADS)?;
+            Some(Command::TriggerGroupPad(group, pad))
+        }
+        _ => None,
+    }
+}
+
+fn parse_index(part: &str, count: usize) -> Option<usize> {
+    let number: usize = part.parse().ok()?;
+    (1..=count).contains(&number).then(|| number - 1)
+}
+
+// Controllers send numbers in whichever width they like
+fn arg_value(arg: &OscType) -> Option<f32> {
+    match *arg {
+        OscType::Int(value) => Some(value as f32),
+        OscType::Long(value) => Some(value as f32),
+        OscType::Float(value) => Some(value),
+        OscType::Double(value) => Some(value as f32),
+        _ => None,
+    }
+}
+
+// A packet is one message or a bundle of them, bundles nesting; messages come out in order
+fn packet_messages(packet: OscPacket) -> Vec<OscMessage> {
+    match packet {
+        OscPacket::Message(message) => vec![message],
+        OscPacket::Bundle(bundle) => bundle.content.into_iter().flat_map(packet_messages).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(address: &str, args: Vec<OscType>) -> OscMessage {
+        OscMessage { addr: address.to_string(), args }
+    }
+
+    #[test]
+    fn test_bundles_are_flattened() {
+        let play = OscPacket::Message(message("/koii/play", Vec::new()));
+        let tempo = OscPacket::Message(message("/koii/tempo", vec![OscType::Int(128)]));
+        let nested = OscPacket::Bundle(rosc::OscBundle { timetag: (0, 1).into(), content: vec![tempo] });
+        let bundle = OscPacket::Bundle(rosc::OscBundle { timetag: (0, 1).into(), content: vec![play, nested] });
+        
+        let bytes = rosc::encoder::encode(&bundle).unwrap();
+        let (_, decoded) = rosc::decoder::decode_udp(&bytes).unwrap();
+        let commands: Vec<Option<Command>> = packet_messages(decoded).iter().map(message_to_command).collect();
+        assert_eq!(commands, [Some(Command::TogglePlayback), Some(Command::SetTempo(128.0))]);
+        assert!(rosc::decoder::decode_udp(b"/koii/play").is_err());
+    }
+
+    #[test]
+    fn test_trigger_address() {
+        let command = message_to_command(&message("/koii/trigger/2/16", vec![OscType::Float(1.0)]));
+        assert_eq!(command, Some(Command::TriggerGroupPad(1, 15)));
+
+        // Releases and out-of-range pads do nothing
+        assert_eq!(message_to_command(&message("/koii/trigger/2/16", vec![OscType::Float(0.0)])), None);
+        assert_eq!(message_to_command(&message("/koii/trigger/9/1", Vec::new())), None);
+        assert_eq!(message_to_command(&message("/koii/trigger/1/0", Vec::new())), None);
+    }
+
+    #[test]
+    fn test_pattern_address() {
+        assert_eq!(message_to_command(&message("/koii/pattern/12", Vec::new())), Some(Command::GoToPattern(11)));
+        assert_eq!(message_to_command(&message("/koii/pattern/99", vec![OscType::Int(1)])), Some(Command::GoToPattern(98)));
+        assert_eq!(message_to_command(&message("/koii/morph/3/8", Vec::new())), Some(Command::StartMorph(2, 8)));
+        assert_eq!(message_to_command(&message("/koii/morph/3/0", Vec::new())), None);
+        assert_eq!(message_to_command(&message("/koii/pattern/100", Vec::new())), None);
+        assert_eq!(message_to_command(&message("/koii/pattern/0", Vec::new())), None);
+    }
+
+    #[test]
+    fn test_tempo_address() {
+        let command = message_to_command(&message("/koii/tempo", vec![OscType::Int(140)]));
+        assert_eq!(command, Some(Command::SetTempo(140.0)));
+        assert_eq!(
+            message_to_command(&message("/koii/tempo", vec![OscType::Float(97.5)])),
+            Some(Command::SetTempo(97.5))
+        );
+        assert_eq!(message_to_command(&message("/koii/tempo", Vec::new())), None);
+    }
+
+    #[test]
+    fn test_unknown_addresses() {
+        assert_eq!(message_to_command(&message("/koii/explode", Vec::new())), None);
+        assert_eq!(message_to_command(&message("/other/play", Vec::new())), None);
+        assert_eq!(message_to_command(&message("/koii/play", Vec::new())), Some(Command::TogglePlayback));
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use crate::error::{AppError, Result};
+use crate::sequencer::{MAX_GROUPS, MAX_PADS};
+
+// Sits next to the sample mappings so a sample folder carries its own pad setup
+pub const PAD_SETTINGS_PATH: &str = "samples/pad_settings.json";
+
+pub const MAX_PITCH_SEMITONES: f32 = 24.0;
+pub const MAX_PAD_GAIN: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackMode {
+    #[default]
+    OneShot, // Every hit rings out to the end
+    Choke,   // A new hit cuts the pad's previous one
+}
+
+impl PlaybackMode {
+    fn is_one_shot(&self) -> bool {
+        *self == PlaybackMode::OneShot
+    }
+}
+
+// Per-pad playback shaping; only fields that differ from the defaults are written out
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PadSettings {
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pan: f32, // -1.0 hard left .. 1.0 hard right
+    #[serde(skip_serializing_if = "is_zero")]
+    pub pitch: f32, // Semitones
+    #[serde(skip_serializing_if = "is_unity")]
+    pub gain: f32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub reverse: bool,
+    #[serde(skip_serializing_if = "PlaybackMode::is_one_shot")]
+    pub mode: PlaybackMode,
+}
+
+impl Default for PadSettings {
+    fn default() -> Self {
+        Self {
+            pan: 0.0,
+            pitch: 0.0,
+            gain: 1.0,
+            reverse: false,
+            mode: PlaybackMode::OneShot,
+        }
+    }
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+fn is_unity(value: &f32) -> bool {
+    *value == 1.0
+}
+
+impl PadSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Keeps hand-edited files within what playback can handle
+    pub fn clamped(self) -> Self {
+        Self {
+            pan: self.pan.clamp(-1.0, 1.0),
+            pitch: self.pitch.clamp(-MAX_PITCH_SEMITONES, MAX_PITCH_SEMITONES),
+            gain: self.gain.clamp(0.0, MAX_PAD_GAIN),
+            ..self
+        }
+    }
+
+    // Playback speed for the pitch shift; pitching up also shortens the sample
+    pub fn pitch_ratio(&self) -> f64 {
+        2f64.powf(self.pitch as f64 / 12.0)
+    }
+
+    // Balance law: the centre is untouched and panning only turns the far side down
+    pub fn pan_gains(&self) -> (f32, f32) {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PadSettingsEntry {
+    group: usize,
+    pad: usize,
+    #[serde(flatten)]
+    settings: PadSettings,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PadSettingsFile {
+    pads: Vec<PadSettingsEntry>,
+}
+
+// A missing file just means every pad is at its defaults
+pub fn load_pad_settings(path: &str) -> Result<HashMap<(usize, usize), PadSettings>> {
+    if !Path::new(path).exists() {
+        return Ok(HashMap::new());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::Config(format!("Failed to read pad settings {}: {}", path, e)))?;
+    parse_pad_settings(&content)
+}
+
+pub fn parse_pad_settings(content: &str) -> Result<HashMap<(usize, usize), PadSettings>> {
+    let file: PadSettingsFile = serde_json::from_str(content)
+        .map_err(|e| AppError::Config(format!("Failed to parse pad settings: {}", e)))?;
+
+    let mut settings = HashMap::new();
+    for entry in file.pads {
+        // Read again while the UI is up, so slots that don't exist are skipped quietly
+        if entry.group >= MAX_GROUPS || entry.pad >= MAX_PADS {
+            continue;
+        }
+        settings.insert((entry.group, entry.pad), entry.settings.clamped());
+    }
+    Ok(settings)
+}
+
+pub fn save_pad_settings(path: &str, settings: &HashMap<(usize, usize), PadSettings>) -> Result<()> {
+    let json = pad_settings_json(settings)?;
+    std::fs::write(path, json)
+        .map_err(|e| AppError::Config(format!("Failed to write pad settings {}: {}", path, e)))
+}
+
+// Pads at their defaults are left out, and entries are sorted so saves diff cleanly
+pub fn pad_settings_json(settings: &HashMap<(usize, usize), PadSettings>) -> Result<String> {
+    let mut pads: Vec<PadSettingsEntry> = settings
+        .iter()
+        .filter(|(_, settings)| !settings.is_default())
+        .map(|(&(group, pad), &settings)| PadSettingsEntry { group, pad, settings })
+        .collect();
+    pads.sort_by_key(|entry| (entry.group, entry.pad));
+
+    serde_json::to_string_pretty(&PadSettingsFile { pads })
+        .map_err(|e| AppError::Config(format!("Failed to serialize pad settings: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_skips_defaults() {
+        let mut settings = HashMap::new();
+        settings.insert((0, 0), PadSettings::default());
+        settings.insert((0, 3), PadSettings { pan: -0.5, ..PadSettings::default() });
+        settings.insert(
+            (2, 15),
+            PadSettings { pitch: 7.0, gain: 0.8, reverse: true, mode: PlaybackMode::Choke, ..PadSettings::default() },
+        );
+
+        let json = pad_settings_json(&settings).unwrap();
+        // Only customised fields are written
+        assert!(!json.contains("\"pitch\": 0"));
+        assert!(json.contains("\"pan\": -0.5"));
+        assert!(json.contains("\"mode\": \"choke\""));
+
+        let loaded = parse_pad_settings(&json).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[&(0, 3)], settings[&(0, 3)]);
+        assert_eq!(loaded[&(2, 15)], settings[&(2, 15)]);
+        assert!(!loaded.contains_key(&(0, 0)));
+    }
+
+    #[test]
+    fn test_parse_clamps_and_skips_bad_pads() {
+        let json = r#"{"pads": [
+            {"group": 1, "pad": 2, "pan": 4.0, "pitch": -60},
+            {"group": 9, "pad": 0, "gain": 0.5}
+        ]}"#;
+        let loaded = parse_pad_settings(json).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[&(1, 2)].pan, 1.0);
+        assert_eq!(loaded[&(1, 2)].pitch, -MAX_PITCH_SEMITONES);
+        assert_eq!(loaded[&(1, 2)].gain, 1.0);
+
+        assert!(parse_pad_settings("not json").is_err());
+        assert!(load_pad_settings("no/such/pad_settings.json").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pan_and_pitch() {
+        assert_eq!(PadSettings::default().pan_gains(), (1.0, 1.0));
+        assert_eq!(PadSettings { pan: -1.0, ..PadSettings::default() }.pan_gains(), (1.0, 0.0));
+        assert_eq!(PadSettings { pan: 0.5, ..PadSettings::default() }.pan_gains(), (0.5, 1.0));
+
+        assert_eq!(PadSettings::default().pitch_ratio(), 1.0);
+        assert!((PadSettings { pitch: 12.0, ..PadSettings::default() }.pitch_ratio() - 2.0).abs() < 1e-9);
+        assert!((PadSettings { pitch: -12.0, ..PadSettings::default() }.pitch_ratio() - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,254 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use crate::error::{AppError, Result};
+use crate::mixer::MixerSettings;
+use crate::sequencer::{ClockDivision, Pattern, MAX_GROUPS, MAX_PATTERNS};
+
+// Quick save and quick load always use this file in the working directory
+pub const QUICK_SAVE_PATH: &str = "session.koii";
+
+pub const DEFAULT_AUTOSAVE_SECS: u64 = 60;
+
+/// Everything needed to pick a session back up; samples stay in `samples.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub tempo: f32,
+    #[serde(default)]
+    pub swing_preset: u8,
+    #[serde(default)]
+    pub group_patterns: Vec<usize>, // One entry per group
+    pub mixer: MixerSettings,
+    #[serde(default)]
+    pub patterns: Vec<ProjectPattern>,
+    #[serde(default)]
+    pub chains: Vec<Vec<usize>>, // Pattern chain per group, empty when not chained
+    #[serde(default)]
+    pub tempo_automation: Vec<(usize, f32)>, // (bar, bpm) breakpoints, sorted by bar
+    #[serde(default)]
+    pub divisions: Vec<ClockDivision>, // One per group; older files leave every group at Normal
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectPattern {
+    pub group: usize,
+    pub index: usize,
+    pub pattern: Pattern,
+}
+
+impl Project {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Project(format!("Failed to read {}: {}", path, e)))?;
+        Self::parse(&content)
+    }
+
+    // Rejects slots and grids playback couldn't handle rather than loading half a project
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut project: Self = serde_json::from_str(content)
+            .map_err(|e| AppError::Project(format!("Invalid project file: {}", e)))?;
+
+        if project.group_patterns.len() > MAX_GROUPS || project.chains.len() > MAX_GROUPS || project.divisions.len() > MAX_GROUPS {
+            return Err(AppError::Project(format!("More than {} groups", MAX_GROUPS)));
+        }
+        if project.group_patterns.iter().chain(project.chains.iter().flatten()).any(|&pattern| pattern >= MAX_PATTERNS) {
+            return Err(AppError::Project("Selected pattern out of range".to_string()));
+        }
+        for entry in &mut project.patterns {
+            entry.pattern.fill_missing_ratchets();
+            if entry.group >= MAX_GROUPS || entry.index >= MAX_PATTERNS {
+                return Err(AppError::Project(format!("No pattern slot {}/{}", entry.group + 1, entry.index + 1)));
+            }
+            if !entry.pattern.is_well_formed() {
+                return Err(AppError::Project(format!(
+                    "Pattern {}/{} has rows of the wrong size",
+                    entry.group + 1,
+                    entry.index + 1
+                )));
+            }
+        }
+        Ok(project)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Project(format!("Failed to serialize project: {}", e)))?;
+
+        if let Some(parent) = Path::new(path).parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::Project(format!("Failed to create {}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(path, json).map_err(|e| AppError::Project(format!("Failed to write {}: {}", path, e)))
+    }
+}
+
+// Crash recovery copy under the user's data dir, so it never overwrites an explicit save
+pub fn autosave_path() -> Option<String> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("koii").join("autosave.koii").to_string_lossy().into_owned())
+}
+
+pub fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// Restoring is only worth offering when the autosave holds work no explicit save has
+pub fn autosave_is_newer(autosave: Option<SystemTime>, last_save: Option<SystemTime>) -> bool {
+    match (autosave, last_save) {
+        (Some(autosave), Some(last_save)) => autosave > last_save,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+// Writes a snapshot off the UI thread; the old file is only replaced once the new one is complete.
+// Each save gets its own partial file, so two running at once never write into the same one
+pub fn save_in_background(project: Project, path: String) -> JoinHandle<Result<()>> {
+    static SAVES: AtomicUsize = AtomicUsize::new(0);
+    let save = SAVES.fetch_add(1, Ordering::Relaxed);
+    std::thread::spawn(move || {
+        let partial = format!("{}.{}-{}.tmp", path, std::process::id(), save);
+        project.save(&partial)?;
+        std::fs::rename(&partial, &path)
+            .map_err(|e| AppError::Project(format!("Failed to write {}: {}", path, e)))
+    })
+}
+
+// When the next autosave is due; a zero interval turns autosave off
+#[derive(Debug, Clone)]
+pub struct Autosave {
+    pub path: String,
+    interval: Duration,
+    last_save: Instant,
+}
+
+impl Autosave {
+    pub fn new(path: String, interval_secs: u64) -> Option<Self> {
+        if interval_secs == 0 {
+            return None;
+        }
+        Some(Self { path, interval: Duration::from_secs(interval_secs), last_save: Instant::now() })
+    }
+
+    pub fn is_due(&self, now: Instant) -> bool {
+        now.duration_since(self.last_save) >= self.interval
+    }
+
+    pub fn mark_saved(&mut self, now: Instant) {
+        self.last_save = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> Project {
+        let mut pattern = Pattern::new();
+        pattern.set_hit(3, 5, true);
+        pattern.set_velocity(3, 5, 64);
+        Project {
+            tempo: 97.5,
+            swing_preset: 2,
+            group_patterns: vec![0, 4, 0, 0],
+            mixer: MixerSettings::default(),
+            patterns: vec![ProjectPattern { group: 1, index: 4, pattern }],
+            chains: vec![vec![], vec![4, 0], vec![], vec![]],
+            tempo_automation: vec![(0, 97.5), (8, 120.0)],
+            divisions: vec![ClockDivision::Normal, ClockDivision::Half, ClockDivision::Normal, ClockDivision::Double],
+        }
+    }
+
+    #[test]
+    fn test_project_round_trip() {
+        let path = std::env::temp_dir().join("ko2_project_round_trip.koii");
+        let path = path.to_str().unwrap();
+        project().save(path).unwrap();
+
+        let loaded = Project::load(path).unwrap();
+        assert_eq!(loaded.tempo, 97.5);
+        assert_eq!(loaded.group_patterns, [0, 4, 0, 0]);
+        assert_eq!(loaded.patterns[0].pattern.get_velocity(3, 5), 64);
+        assert!(loaded.patterns[0].pattern.steps[3][5]);
+        assert_eq!(loaded.chains[1], vec![4, 0]);
+        assert_eq!(loaded.tempo_automation, vec![(0, 97.5), (8, 120.0)]);
+        assert_eq!(loaded.divisions[1], ClockDivision::Half);
+        assert_eq!(loaded.divisions[3], ClockDivision::Double);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_project_rejects_bad_files() {
+        assert!(matches!(Project::parse("{"), Err(AppError::Project(_))));
+        assert!(Project::load("/no/such/dir/session.koii").is_err());
+
+        let mut bad = project();
+        bad.patterns[0].index = MAX_PATTERNS;
+        assert!(Project::parse(&serde_json::to_string(&bad).unwrap()).is_err());
+
+        let mut bad = project();
+        bad.chains[3].push(MAX_PATTERNS);
+        assert!(Project::parse(&serde_json::to_string(&bad).unwrap()).is_err());
+
+        let mut bad = project();
+        bad.patterns[0].pattern.velocities[2].pop();
+        assert!(Project::parse(&serde_json::to_string(&bad).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_background_save_round_trip() {
+        let path = std::env::temp_dir().join("ko2_autosave_round_trip.koii");
+        let path = path.to_str().unwrap().to_string();
+        // Two saves at once, e.g. an autosave during a manual save, don't share a partial file
+        let saves = [save_in_background(project(), path.clone()), save_in_background(project(), path.clone())];
+        for save in saves {
+            save.join().unwrap().unwrap();
+        }
+
+        let loaded = Project::load(&path).unwrap();
+        assert_eq!(loaded.tempo, 97.5);
+        assert_eq!(loaded.patterns[0].pattern.get_velocity(3, 5), 64);
+        let partial_prefix = format!("{}.", Path::new(&path).file_name().unwrap().to_str().unwrap());
+        let partials = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.starts_with(&partial_prefix) && name.ends_with(".tmp")))
+            .count();
+        assert_eq!(partials, 0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_autosave_offered_only_when_newer() {
+        let earlier = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let later = earlier + Duration::from_secs(60);
+
+        assert!(autosave_is_newer(Some(later), Some(earlier)));
+        assert!(autosave_is_newer(Some(later), None));
+        assert!(!autosave_is_newer(Some(earlier), Some(later)));
+        assert!(!autosave_is_newer(Some(earlier), Some(earlier)));
+        assert!(!autosave_is_newer(None, Some(earlier)));
+        assert!(!autosave_is_newer(None, None));
+    }
+
+    #[test]
+    fn test_autosave_timer() {
+        assert!(Autosave::new("autosave.koii".to_string(), 0).is_none());
+
+        let mut autosave = Autosave::new("autosave.koii".to_string(), 60).unwrap();
+        let start = autosave.last_save;
+        assert!(!autosave.is_due(start + Duration::from_secs(59)));
+        assert!(autosave.is_due(start + Duration::from_secs(60)));
+
+        autosave.mark_saved(start + Duration::from_secs(60));
+        assert!(!autosave.is_due(start + Duration::from_secs(90)));
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::ops::Range;
+
+use rand::Rng;
+
+use crate::error::{AppError, Result};
+use crate::pad_settings::{PadSettings, PlaybackMode};
+use crate::sample::{DecodedSample, SampleBank};
+use crate::sequencer::{accented_gain, max_micro_offset_ms, velocity_gain, Sequencer, MAX_GROUPS};
+
+pub const RENDER_SAMPLE_RATE: u32 = 44100;
+pub const RENDER_CHANNELS: u16 = 2;
+
+// Same step length the live clock uses: 16th notes at the given tempo
+#[cfg(test)]
+pub fn step_frames(tempo: f32, sample_rate: u32) -> usize {
+    resolution_step_frames(tempo, crate::sequencer::STEPS_PER_BEAT, sample_rate)
+}
+
+// Step length for a signature that splits each beat into `steps_per_beat`
+pub fn resolution_step_frames(tempo: f32, steps_per_beat: usize, sample_rate: u32) -> usize {
+    (sample_rate as f64 * 60.0 / (tempo.max(1.0) as f64 * steps_per_beat.max(1) as f64)).round() as usize
+}
+
+// How the loop is played, as set in the transport
+#[derive(Debug, Clone, Copy)]
+pub struct Feel {
+    pub tempo: f32,
+    pub swing: f32, // Fraction of a step that odd steps are held back
+    pub accent_boost: f32,
+    pub master_gain: f32, // The master's dB gain as a multiplier on the summed groups; the fader is left out
+}
+
+// A group's fader and mute going into a bounce. Automation in the group's pattern moves
+// them as it does live, but can't bring back a group left out by a solo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupLevel {
+    pub volume: f32,
+    pub muted: bool,
+    pub soloed_out: bool,
+}
+
+impl GroupLevel {
+    pub fn new(volume: f32) -> Self {
+        Self { volume, muted: false, soloed_out: false }
+    }
+
+    // Gain on a step, given the (mute, volume) automation has set by then
+    pub fn gain(self, (muted, volume): (Option<bool>, Option<f32>)) -> f32 {
+        if self.soloed_out || muted.unwrap_or(self.muted) {
+            0.0
+        } else {
+            volume.unwrap_or(self.volume).clamp(0.0, 1.0)
+        }
+    }
+}
+
+// Renders one loop of the active patterns as interleaved stereo. Tails that ring
+// past the end of the loop are cut so the result lines up with the tempo. A half-time
+// group stretches the loop to two bars so its pattern plays through once. Probability
+// and random layers draw from `rng`, so a seeded one gives the same bounce every time.
+// Pads in `muted_pads` are left out, as they are live.
+pub fn render_pattern_loop(
+    sequencer: &Sequencer,
+    sample_bank: &SampleBank,
+    pad_settings: &HashMap<(usize, usize), PadSettings>,
+    muted_pads: &HashSet<(usize, usize)>,
+    group_levels: &[GroupLevel; MAX_GROUPS],
+    Feel { tempo, swing, accent_boost, master_gain }: Feel,
+    rng: &mut impl Rng,
+) -> Vec<f32> {
+    // Patterns that store a tempo play at it, as they do live
+    let tempo = sequencer.pattern_tempo().unwrap_or(tempo);
+    // One bar of the signature the transport would count in
+    let signature = sequencer.clock_signature();
+    let step_frames = resolution_step_frames(tempo, signature.steps_per_beat, RENDER_SAMPLE_RATE);
+    let bar_steps = signature.bar_steps();
+    // A group is left out only if it's silent all the way through
+    let audible = |group: usize| {
+        let pattern = sequencer.get_pattern(group, sequencer.get_active_pattern(group));
+        (0..bar_steps).any(|step| group_levels[group].gain(pattern.map_or((None, None), |pattern| pattern.automated_levels(pattern.wrap_step(step)))) > 0.0)
+    };
+    let loop_steps = (0..MAX_GROUPS)
+        .filter(|&group| audible(group))
+        .map(|group| (bar_steps as f32 * sequencer.get_division(group).step_scale()) as usize)
+        .fold(bar_steps, usize::max);
+    let total_frames = step_frames * loop_steps;
+    let mut output = vec![0.0; total_frames * RENDER_CHANNELS as usize];
+    let mut decoded: HashMap<(usize, usize, usize), Option<DecodedSample>> = HashMap::new();
+    // Round-robin pads start from their own sample, as they do when the transport starts
+    let mut last_layers: HashMap<(usize, usize), usize> = HashMap::new();
+
+    for (group, &level) in group_levels.iter().enumerate() {
+        if !audible(group) {
+            continue;
+        }
+
+        let pattern_idx = sequencer.get_active_pattern(group);
+        let Some(pattern) = sequencer.get_pattern(group, pattern_idx) else {
+            continue;
+        };
+
+        // Steps here are the group's own, which may be longer or shorter than a global step
+        let scale = sequencer.get_division(group).step_scale();
+        let group_step_frames = step_frames as f32 * scale;
+        let max_offset = max_micro_offset_ms(tempo / scale);
+
+        // Probability is rolled just like live playback, so a bounce is one possible pass
+        let hits: Vec<(usize, usize)> = (0..(loop_steps as f32 / scale) as usize)
+            .flat_map(|step| pattern.roll_hits_at_step(pattern.wrap_step(step), rng).into_iter().map(move |pad| (step, pad)))
+            .filter(|&(_, pad)| !muted_pads.contains(&(group, pad)))
+            .collect();
+
+        // Swung and nudged hits start a little off the grid, the same way live playback schedules them
+        let start_frame = |step: usize, pad: usize| {
+            let pattern_step = pattern.wrap_step(step);
+            let offset_ms = pattern.get_micro_offset(pad, pattern_step).clamp(-max_offset, max_offset);
+            let mut offset_frames = offset_ms as i64 * RENDER_SAMPLE_RATE as i64 / 1000;
+            if pattern_step % 2 == 1 {
+                offset_frames += (group_step_frames * swing) as i64;
+            }
+            ((step as f32 * group_step_frames) as i64 + offset_frames).max(0) as usize
+        };
+
+        for (index, &(step, pad)) in hits.iter().enumerate() {
+            let settings = pad_settings.get(&(group, pad)).copied().unwrap_or_default();
+
+            // Choked pads stop where the same pad hits next
+            let end_frame = match settings.mode {
+                PlaybackMode::Choke => hits[index + 1..]
+                    .iter()
+                    .find(|&&(_, next_pad)| next_pad == pad)
+                    .map_or(total_frames, |&(next_step, _)| start_frame(next_step, pad)),
+                PlaybackMode::OneShot => total_frames,
+            };
+
+            let pattern_step = pattern.wrap_step(step);
+            let gain = level.gain(pattern.automated_levels(pattern_step));
+            let velocity_level = velocity_gain(pattern.get_velocity(pad, pattern_step));
+            let velocity_level = accented_gain(velocity_level, pattern.is_accented(pattern_step), accent_boost);
+            
+            // Ratchet repeats are evenly spaced; a choked pad cuts each at the next
+            let ratchet = pattern.get_ratchet(pad, pattern_step) as usize;
+            let spacing = group_step_frames as usize / ratchet;
+            let first = start_frame(step, pad);
+            for repeat in 0..ratchet {
+                let start = first + spacing * repeat;
+                let end = match settings.mode {
+                    PlaybackMode::Choke if repeat + 1 < ratchet => start + spacing,
+                    _ => end_frame,
+                };
+                
+                // Each repeat picks its layers the way a live hit would
+                let velocity = pattern.get_velocity(pad, pattern_step);
+                let picked = sample_bank.pick_layers(group, pad, velocity, last_layers.get(&(group, pad)).copied(), rng);
+                if let [(only, _)] = picked[..] {
+                    last_layers.insert((group, pad), only);
+                }
+                for (index, weight) in picked {
+                    let Some((data, level)) = sample_bank.get_layer_sample(group, pad, index) else {
+                        continue;
+                    };
+                    // Decode each sample once per render; undecodable ones render as silence
+                    let sample = decoded.entry((group, pad, index)).or_insert_with(|| {
+                        let (trim_start, trim_end) = sample_bank.get_trim(group, pad);
+                        data.decode()
+                            .ok()
+                            .map(|sample| sample.trimmed(trim_start, trim_end))
+                            .map(|sample| if settings.reverse { sample.reversed() } else { sample })
+                    });
+                    if let Some(sample) = sample {
+                        mix_into(&mut output, sample, start..end, master_gain * gain * velocity_level * level * weight, &settings);
+                    }
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn mix_into(output: &mut [f32], sample: &DecodedSample, frames: Range<usize>, gain: f32, settings: &PadSettings) {
+    let out_frames = output.len() / RENDER_CHANNELS as usize;
+    let source_frames = sample.frames();
+    let ratio = sample.sample_rate as f64 / RENDER_SAMPLE_RATE as f64 * settings.pitch_ratio();
+    let (left_gain, right_gain) = settings.pan_gains();
+    let gain = gain * settings.gain;
+    let start_frame = frames.start;
+
+    for frame in start_frame..frames.end.min(out_frames) {
+        // Linear interpolation handles samples recorded at other rates
+        let position = (frame - start_frame) as f64 * ratio;
+        let index = position as usize;
+        if index >= source_frames {
+            break;
+        }
+
+        let (left, right) = sample.interpolated_frame(index, (position - index as f64) as f32);
+        output[frame * 2] += left * gain * left_gain;
+        output[frame * 2 + 1] += right * gain * right_gain;
+    }
+}
+
+pub fn encode_wav(samples: &[f32], channels: u16, sample_rate: u32) -> Result<Vec<u8>> {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut buffer = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut buffer, spec)
+        .map_err(|e| AppError::Sample(format!("Failed to encode WAV: {}", e)))?;
+
+    for &sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer
+            .write_sample(value)
+            .map_err(|e| AppError::Sample(format!("Failed to encode WAV: {}", e)))?;
+    }
+
+    writer
+        .finalize()
+        .map_err(|e| AppError::Sample(format!("Failed to encode WAV: {}", e)))?;
+
+    Ok(buffer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{master_gain, MIN_MASTER_GAIN_DB};
+    use crate::sample::LayerMode;
+    use crate::sequencer::{Automation, Signature, STEPS_PER_PATTERN};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn bank_with_kick() -> SampleBank {
+        let mut bank = SampleBank::new();
+        let kick = bank.generate_simple_kick();
+        bank.load_sample_data(0, 0, "kick", kick).unwrap();
+        bank
+    }
+
+    // A straight loop at 120 BPM, with probability rolled from a fixed seed
+    fn render(sequencer: &Sequencer, bank: &SampleBank, gains: &[GroupLevel; MAX_GROUPS], accent_boost: f32) -> Vec<f32> {
+        let feel = Feel { tempo: 120.0, swing: 0.0, accent_boost, master_gain: 1.0 };
+        render_pattern_loop(sequencer, bank, &HashMap::new(), &HashSet::new(), gains, feel, &mut StdRng::seed_from_u64(1))
+    }
+
+    #[test]
+    fn test_step_frames() {
+        assert_eq!(step_frames(120.0, 44100), 5513);
+        assert_eq!(step_frames(60.0, 48000), 12000);
+        // Fractional tempos round to the nearest frame rather than truncating
+        assert_eq!(step_frames(87.5, 48000), 8229);
+    }
+
+    #[test]
+    fn test_render_empty_patterns_is_silent_loop() {
+        let sequencer = Sequencer::new();
+        let bank = bank_with_kick();
+
+        let audio = render(&sequencer, &bank, &[GroupLevel::new(0.8); MAX_GROUPS], 0.0);
+        assert_eq!(audio.len(), step_frames(120.0, RENDER_SAMPLE_RATE) * STEPS_PER_PATTERN * 2);
+        assert!(audio.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_render_plays_at_the_pattern_tempo() {
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern_tempo(0, 0, Some(150.0));
+        let bank = bank_with_kick();
+
+        let audio = render(&sequencer, &bank, &[GroupLevel::new(0.8); MAX_GROUPS], 0.0);
+        assert_eq!(audio.len(), step_frames(150.0, RENDER_SAMPLE_RATE) * STEPS_PER_PATTERN * 2);
+    }
+
+    #[test]
+    fn test_render_follows_the_signature() {
+        // A bar of 3/4 in 8ths is six steps of a quarter second at 120 BPM
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern_signature(0, 0, Signature::new(3, 2).unwrap(), false);
+        sequencer.record_hit(0, 0, 0);
+        let bank = bank_with_kick();
+        
+        let step = resolution_step_frames(120.0, 2, RENDER_SAMPLE_RATE);
+        assert_eq!(step, RENDER_SAMPLE_RATE as usize / 4);
+        let audio = render(&sequencer, &bank, &[GroupLevel::new(0.8); MAX_GROUPS], 0.0);
+        assert_eq!(audio.len(), step * 6 * 2);
+    }
+
+    #[test]
+    fn test_render_places_hits_on_steps() {
+        let mut sequencer = Sequencer::new();
+        for _ in 0..4 {
+            sequencer.tick(120.0);
+        }
+        sequencer.record_hit(0, 0, 0); // Step 4
+        let bank = bank_with_kick();
+
+        let audio = render(&sequencer, &bank, &[GroupLevel::new(0.8); MAX_GROUPS], 0.0);
+        let hit_start = step_frames(120.0, RENDER_SAMPLE_RATE) * 4 * 2;
+
+        assert!(audio[..hit_start].iter().all(|&s| s == 0.0));
+        assert!(audio[hit_start..].iter().any(|&s| s.abs() > 0.1));
+    }
+
+    #[test]
+    fn test_render_half_time_group_spans_two_bars() {
+        let mut sequencer = Sequencer::new();
+        sequencer.toggle_step(1, 0, 0, 8);
+        sequencer.cycle_division(1); // Double
+        sequencer.cycle_division(1); // Half
+        let mut bank = bank_with_kick();
+        bank.load_sample_data(1, 0, "kick", bank.generate_simple_kick()).unwrap();
+
+        let audio = render(&sequencer, &bank, &[GroupLevel::new(0.8); MAX_GROUPS], 0.0);
+        let step_frames = step_frames(120.0, RENDER_SAMPLE_RATE);
+        assert_eq!(audio.len(), step_frames * STEPS_PER_PATTERN * 2 * 2);
+
+        // The group's step 8 lands at the start of the second bar
+        let hit_start = step_frames * 16 * 2;
+        assert!(audio[..hit_start].iter().all(|&s| s == 0.0));
+        assert!(audio[hit_start..].iter().any(|&s| s.abs() > 0.1));
+    }
+
+    #[test]
+    fn test_render_boosts_accented_steps() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 0);
+        sequencer.adjust_step_velocity(0, 0, 0, 0, -64);
+        sequencer.toggle_accent(0, 0, 0);
+        let bank = bank_with_kick();
+        let gains = [GroupLevel::new(1.0); MAX_GROUPS];
+
+        let peak = |audio: Vec<f32>| audio.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let plain = peak(render(&sequencer, &bank, &gains, 0.0));
+        let accented = peak(render(&sequencer, &bank, &gains, 0.3));
+        let velocity = velocity_gain(crate::sequencer::MAX_VELOCITY - 64);
+        assert!((accented / plain - (velocity + 0.3) / velocity).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_render_respects_group_gain() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 0);
+        let bank = bank_with_kick();
+
+        let mut gains = [GroupLevel::new(0.8); MAX_GROUPS];
+        let loud = render(&sequencer, &bank, &gains, 0.0);
+
+        gains[0].volume = 0.4;
+        let quiet = render(&sequencer, &bank, &gains, 0.0);
+        let loud_peak = loud.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let quiet_peak = quiet.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((quiet_peak * 2.0 - loud_peak).abs() < 0.001);
+
+        // A muted group contributes nothing
+        gains[0].muted = true;
+        let muted = render(&sequencer, &bank, &gains, 0.0);
+        assert!(muted.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_render_skips_muted_pads() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit_at(0, 0, 0, 0);
+        let mut bank = bank_with_kick();
+        let kick = bank.generate_simple_kick();
+        bank.load_sample_data(0, 1, "kick", kick).unwrap();
+        let levels = [GroupLevel::new(0.8); MAX_GROUPS];
+        let feel = Feel { tempo: 120.0, swing: 0.0, accent_boost: 0.0, master_gain: 1.0 };
+        let render = |sequencer: &Sequencer, muted: &HashSet<(usize, usize)>| {
+            render_pattern_loop(sequencer, &bank, &HashMap::new(), muted, &levels, feel, &mut StdRng::seed_from_u64(1))
+        };
+        let alone = render(&sequencer, &HashSet::new());
+        
+        // Pad 1 is in the pattern but muted, so the bounce is pad 0 on its own
+        sequencer.record_hit_at(0, 0, 1, 8);
+        assert_ne!(render(&sequencer, &HashSet::new()), alone);
+        assert_eq!(render(&sequencer, &HashSet::from([(0, 1)])), alone);
+    }
+
+    #[test]
+    fn test_render_applies_the_master_gain() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 0);
+        let bank = bank_with_kick();
+        let levels = [GroupLevel::new(0.8); MAX_GROUPS];
+        let render = |master_gain_db: f32| {
+            let feel = Feel { tempo: 120.0, swing: 0.0, accent_boost: 0.0, master_gain: master_gain(master_gain_db) };
+            render_pattern_loop(&sequencer, &bank, &HashMap::new(), &HashSet::new(), &levels, feel, &mut StdRng::seed_from_u64(1))
+        };
+        let peak = |audio: Vec<f32>| audio.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        
+        // +6dB about doubles the bounce, and the bottom of the range silences it
+        let unity = peak(render(0.0));
+        assert!((peak(render(6.0)) / unity - 1.995).abs() < 0.01);
+        assert_eq!(peak(render(MIN_MASTER_GAIN_DB)), 0.0);
+    }
+
+    #[test]
+    fn test_render_plays_the_automation() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 0);
+        sequencer.toggle_step(0, 0, 0, 8);
+        let bank = bank_with_kick();
+        let step = step_frames(120.0, RENDER_SAMPLE_RATE) * RENDER_CHANNELS as usize;
+        let peak = |audio: &[f32]| audio.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let mut levels = [GroupLevel::new(0.8); MAX_GROUPS];
+        let plain = peak(&render(&sequencer, &bank, &levels, 0.0)[..step]);
+        
+        // Step 1 plays at the volume step 9 left behind on the pass before
+        sequencer.record_automation(0, 0, 8, Automation::Volume(0.4));
+        let audio = render(&sequencer, &bank, &levels, 0.0);
+        let (first, second) = (peak(&audio[..step]), peak(&audio[8 * step..9 * step]));
+        assert!((first * 2.0 - plain).abs() < 0.001);
+        assert!((first - second).abs() < 0.001);
+        
+        // A muted group comes back where the automation unmutes it
+        sequencer.record_automation(0, 0, 0, Automation::Mute(true));
+        sequencer.record_automation(0, 0, 8, Automation::Mute(false));
+        levels[0].muted = true;
+        let audio = render(&sequencer, &bank, &levels, 0.0);
+        assert_eq!(peak(&audio[..8 * step]), 0.0);
+        assert!((peak(&audio[8 * step..9 * step]) - second).abs() < 0.001);
+        
+        // but not one a solo leaves out
+        levels[0].soloed_out = true;
+        assert!(render(&sequencer, &bank, &levels, 0.0).iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_render_alternates_layers() {
+        let mut sequencer = Sequencer::new();
+        sequencer.toggle_step(0, 0, 0, 0);
+        sequencer.toggle_step(0, 0, 0, 8);
+        let mut bank = SampleBank::new();
+        let level = |level: f32| encode_wav(&[level; 1000], 1, RENDER_SAMPLE_RATE).unwrap();
+        bank.add_layer(0, 0, "loud", level(0.5), 1.0).unwrap();
+        bank.add_layer(0, 0, "quiet", level(0.25), 1.0).unwrap();
+        let eighth = step_frames(120.0, RENDER_SAMPLE_RATE) * 8 * 2;
+        let peaks = |audio: Vec<f32>| {
+            let peak = |audio: &[f32]| audio.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            (peak(&audio[..eighth]), peak(&audio[eighth..]))
+        };
+        
+        // Stacked, both hits play both layers
+        let (first, second) = peaks(render(&sequencer, &bank, &[GroupLevel::new(1.0); MAX_GROUPS], 0.0));
+        assert!((first - second).abs() < 0.001);
+        
+        // Round-robin, the second hit moves on to the quieter layer
+        bank.set_layer_mode(0, 0, LayerMode::RoundRobin);
+        let (loud, quiet) = peaks(render(&sequencer, &bank, &[GroupLevel::new(1.0); MAX_GROUPS], 0.0));
+        assert!((loud / quiet - 2.0).abs() < 0.01);
+        assert!((first / loud - 1.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_encode_wav_round_trip() {
+        let samples = vec![0.0, 0.5, -0.5, 1.0];
+        let wav = encode_wav(&samples, 2, 44100).unwrap();
+
+        let reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 44100);
+        assert_eq!(reader.len(), 4);
+    }
+}
@@ -1,7 +1,28 @@
 use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use regex::Regex;
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapRb};
+use rodio::{Decoder, Source};
 use serde::{Deserialize, Serialize};
+use crate::bus::{db_to_gain, BUS_SAMPLE_RATE};
 use crate::error::{AppError, Result};
+use crate::sequencer::{DEFAULT_GROUPS, MAX_GROUPS, MAX_PADS, MAX_VELOCITY, PADS_PER_GROUP};
+
+pub const SAMPLES_DIR: &str = "samples";
+pub const SAMPLE_CONFIG_PATH: &str = "samples/config.json";
+pub const EXAMPLE_SAMPLE_CONFIG_PATH: &str = "samples/config.example.json";
+
+// Trim points are fractions of the sample; this much always stays between them
+pub const MIN_TRIM_LENGTH: f32 = 0.01;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SampleMapping {
@@ -9,6 +30,209 @@ pub struct SampleMapping {
     pub pad: usize,
     pub file: String,
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize: Option<bool>, // Overrides [samples] normalize for this file
+    #[serde(default, skip_serializing_if = "is_main_layer")]
+    pub layer: usize, // 0 is the pad's own sample; 1 to 3 play on top of it, in order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f32>, // A layer's level against the pad's own sample
+    #[serde(default, skip_serializing_if = "LayerMode::is_stack")]
+    pub layer_mode: LayerMode, // Read from the pad's layer 0 mapping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vel_min: Option<u8>, // With vel_max, the hit velocities this layer plays for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vel_max: Option<u8>,
+}
+
+impl SampleMapping {
+    // (lowest, highest) velocity, when the mapping sets either end
+    fn velocity_range(&self) -> Option<(u8, u8)> {
+        if self.vel_min.is_none() && self.vel_max.is_none() {
+            return None;
+        }
+        let min = self.vel_min.unwrap_or(0).min(MAX_VELOCITY);
+        let max = self.vel_max.unwrap_or(MAX_VELOCITY).min(MAX_VELOCITY);
+        Some((min.min(max), min.max(max)))
+    }
+}
+
+fn is_main_layer(layer: &usize) -> bool {
+    *layer == 0
+}
+
+// Samples a pad can play at once, counting its own
+pub const MAX_LAYERS: usize = 4;
+
+// How a layered pad uses its samples on each hit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayerMode {
+    #[default]
+    Stack, // All of them at once
+    RoundRobin, // The next one in order
+    Random, // Any but the one played last
+}
+
+impl LayerMode {
+    pub fn next(self) -> Self {
+        match self {
+            LayerMode::Stack => LayerMode::RoundRobin,
+            LayerMode::RoundRobin => LayerMode::Random,
+            LayerMode::Random => LayerMode::Stack,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LayerMode::Stack => "stack",
+            LayerMode::RoundRobin => "round-robin",
+            LayerMode::Random => "random",
+        }
+    }
+
+    fn is_stack(&self) -> bool {
+        *self == LayerMode::Stack
+    }
+
+    // Which of `count` samples a hit plays, 0 being the pad's own, given the one played last
+    pub fn pick(self, count: usize, last: Option<usize>, rng: &mut impl Rng) -> Vec<usize> {
+        match self {
+            _ if count <= 1 => (0..count).collect(),
+            LayerMode::Stack => (0..count).collect(),
+            LayerMode::RoundRobin => vec![last.map_or(0, |last| (last + 1) % count)],
+            LayerMode::Random => {
+                // Drawing from one fewer and stepping over the last keeps repeats out
+                let pick = match last.filter(|&last| last < count) {
+                    Some(last) => {
+                        let pick = rng.gen_range(0..count - 1);
+                        if pick >= last { pick + 1 } else { pick }
+                    }
+                    None => rng.gen_range(0..count),
+                };
+                vec![pick]
+            }
+        }
+    }
+}
+
+// For velocity-switched pads: each sample's share of a hit, as (index, weight). Samples
+// whose ranges overlap at the velocity are blended by how far it is inside each range,
+// and a velocity in a gap plays the nearest sample alone
+pub fn pick_by_velocity(ranges: &[(u8, u8)], velocity: u8) -> Vec<(usize, f32)> {
+    let depths: Vec<(usize, f32)> = ranges
+        .iter()
+        .enumerate()
+        .filter(|(_, &(min, max))| (min..=max).contains(&velocity))
+        .map(|(index, &(min, max))| (index, ((velocity - min).min(max - velocity) + 1) as f32))
+        .collect();
+    if depths.is_empty() {
+        let distance = |&(min, max): &(u8, u8)| if velocity < min { min - velocity } else { velocity - max };
+        return ranges
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, range)| distance(range))
+            .map(|(index, _)| vec![(index, 1.0)])
+            .unwrap_or_default();
+    }
+    let total: f32 = depths.iter().map(|&(_, depth)| depth).sum();
+    depths.into_iter().map(|(index, depth)| (index, depth / total)).collect()
+}
+
+/// A sample played on top of a pad's own, e.g. a clap over a snare.
+#[derive(Debug, Clone)]
+pub struct SampleLayer {
+    pub name: String,
+    pub path: Option<String>, // None for samples made in memory
+    pub gain: f32,
+    pub velocity: Option<(u8, u8)>, // None plays at any velocity
+    pub data: SampleStorage,
+}
+
+/// How a loaded sample is kept: its encoded file in memory, or only its path for files
+/// over the stream threshold, which are decoded from disk on every hit. In-memory bytes are
+/// shared, so the mixer can tell a pad still holds the sample it decoded last time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SampleStorage {
+    InMemory(Arc<[u8]>),
+    Streamed(PathBuf),
+}
+
+impl SampleStorage {
+    // The encoded file; None when it's streamed
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            SampleStorage::InMemory(bytes) => Some(&bytes[..]),
+            SampleStorage::Streamed(_) => None,
+        }
+    }
+
+    pub fn is_streamed(&self) -> bool {
+        matches!(self, SampleStorage::Streamed(_))
+    }
+
+    // What keeping the sample costs in RAM
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes().map_or(0, <[u8]>::len)
+    }
+
+    // The whole sample, read from disk first when it's streamed
+    pub fn decode(&self) -> Result<DecodedSample> {
+        match self {
+            SampleStorage::InMemory(bytes) => decode_sample(bytes),
+            SampleStorage::Streamed(path) => {
+                let bytes = std::fs::read(path)
+                    .map_err(|e| AppError::Sample(format!("Failed to read sample file {}: {}", path.display(), e)))?;
+                decode_sample(&bytes)
+            }
+        }
+    }
+}
+
+pub const DEFAULT_STREAM_THRESHOLD_MB: u32 = 20;
+
+// Files over the threshold are played from disk instead of held in memory; 0 never streams.
+// AIFF is always held, since only the in-memory decoder reads it
+pub fn should_stream(file_bytes: u64, format: AudioFormat, threshold_mb: u32) -> bool {
+    threshold_mb > 0 && format != AudioFormat::Aiff && file_bytes > threshold_mb as u64 * 1024 * 1024
+}
+
+// How loaded samples are levelled before the pad's own gain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalize {
+    #[default]
+    Off,
+    Peak, // Scaled so the loudest sample lands on the target
+}
+
+pub const DEFAULT_NORMALIZE_TARGET_DB: f32 = -1.0;
+// Near-silent files would otherwise be blown up into noise
+const MAX_NORMALIZE_GAIN: f32 = 16.0;
+
+/// Levels measured across a whole decoded sample, and the gain normalizing it takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    pub peak: f32, // Linear, 0.0..=1.0 for anything that decoded in range
+    pub rms: f32,
+    pub gain: f32, // Applied to every hit of the pad
+}
+
+impl Loudness {
+    pub fn measure(sample: &DecodedSample, target_db: f32) -> Self {
+        let peak = sample.data.iter().fold(0.0f32, |peak, &s| peak.max(s.abs()));
+        let rms = if sample.data.is_empty() {
+            0.0
+        } else {
+            (sample.data.iter().map(|&s| s * s).sum::<f32>() / sample.data.len() as f32).sqrt()
+        };
+        Self { peak, rms, gain: 1.0 }.retarget(target_db)
+    }
+
+    // The gain for another target; the levels don't change
+    pub fn retarget(self, target_db: f32) -> Self {
+        let gain = if self.peak > 0.0 { (db_to_gain(target_db) / self.peak).min(MAX_NORMALIZE_GAIN) } else { 1.0 };
+        Self { gain, ..self }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,22 +240,861 @@ pub struct SampleConfig {
     pub mappings: Vec<SampleMapping>,
 }
 
+#[derive(Debug, Clone)]
+pub struct DecodedSample {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub data: Vec<f32>, // Interleaved frames
+}
+
+impl DecodedSample {
+    pub fn frames(&self) -> usize {
+        if self.channels == 0 {
+            0
+        } else {
+            self.data.len() / self.channels as usize
+        }
+    }
+
+    pub fn stereo_frame(&self, frame: usize) -> (f32, f32) {
+        let channels = self.channels as usize;
+        let start = frame * channels;
+        match channels {
+            0 => (0.0, 0.0),
+            1 => (self.data[start], self.data[start]),
+            // Anything beyond stereo keeps just the front pair
+            _ => (self.data[start], self.data[start + 1]),
+        }
+    }
+
+    // Frames between the start and end fractions; rounded outwards so short samples keep a frame
+    pub fn trimmed(&self, start: f32, end: f32) -> DecodedSample {
+        let frames = self.frames();
+        let start_frame = ((start.clamp(0.0, 1.0) * frames as f32).floor() as usize).min(frames);
+        let end_frame = ((end.clamp(0.0, 1.0) * frames as f32).ceil() as usize).clamp(start_frame, frames);
+        let channels = self.channels as usize;
+        
+        DecodedSample {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            data: self.data[start_frame * channels..end_frame * channels].to_vec(),
+        }
+    }
+
+    pub fn reversed(&self) -> DecodedSample {
+        let channels = (self.channels as usize).max(1);
+        DecodedSample {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            data: self.data.chunks_exact(channels).rev().flatten().copied().collect(),
+        }
+    }
+
+    // Linear interpolation between `frame` and the next one, for playback at other rates
+    pub fn interpolated_frame(&self, frame: usize, frac: f32) -> (f32, f32) {
+        let (left, right) = self.stereo_frame(frame);
+        if frame + 1 >= self.frames() {
+            return (left, right);
+        }
+        
+        let (next_left, next_right) = self.stereo_frame(frame + 1);
+        (left + (next_left - left) * frac, right + (next_right - right) * frac)
+    }
+}
+
+// Built-in drums, rendered at the rate the bus mixes at so they play without resampling
+pub fn generate_kick() -> Vec<u8> {
+    synth_wav(0.5, |t, _| {
+        let envelope = (-t * 8.0).exp(); // Exponential decay
+        
+        // Low frequency sweep plus a short click on the attack
+        let kick_freq = 60.0 * (1.0 - t * 0.8);
+        let kick = (t * kick_freq * TAU).sin();
+        let click = if t < 0.01 { (t * 5000.0 * TAU).sin() * 0.3 } else { 0.0 };
+        (kick + click) * envelope * 0.7
+    })
+}
+
+pub fn generate_snare() -> Vec<u8> {
+    synth_wav(0.3, |t, noise| {
+        let body = (t * 180.0 * TAU).sin() * (-t * 20.0).exp();
+        let rattle = noise * (-t * 15.0).exp();
+        (body * 0.5 + rattle * 0.5) * 0.8
+    })
+}
+
+pub fn generate_hihat() -> Vec<u8> {
+    // Differencing the noise takes out the low end, leaving the sizzle
+    let mut previous = 0.0;
+    synth_wav(0.15, move |t, noise| {
+        let bright = noise - previous;
+        previous = noise;
+        bright * (-t * 40.0).exp() * 0.4
+    })
+}
+
+pub fn generate_tom(freq: f32) -> Vec<u8> {
+    synth_wav(0.4, move |t, _| {
+        let pitch = freq * (1.0 - t * 0.3);
+        (t * pitch * TAU).sin() * (-t * 7.0).exp() * 0.7
+    })
+}
+
+pub fn generate_clap() -> Vec<u8> {
+    synth_wav(0.3, |t, noise| {
+        // Three quick slaps 10ms apart, then the room
+        let slaps: f32 = [0.0, 0.01, 0.02]
+            .iter()
+            .filter(|&&at| t >= at)
+            .map(|&at| (-(t - at) * 300.0).exp())
+            .sum();
+        let tail = if t >= 0.02 { (-(t - 0.02) * 18.0).exp() * 0.6 } else { 0.0 };
+        noise * (slaps + tail).min(1.0) * 0.6
+    })
+}
+
+// Renders `seconds` of mono audio from a voice given the time and a white noise sample
+fn synth_wav(seconds: f32, mut voice: impl FnMut(f32, f32) -> f32) -> Vec<u8> {
+    let sample_rate = BUS_SAMPLE_RATE;
+    let mut rng = StdRng::seed_from_u64(0x6b6f); // Same sound on every run
+    let samples: Vec<f32> = (0..(sample_rate as f32 * seconds) as usize)
+        .map(|i| voice(i as f32 / sample_rate as f32, rng.gen_range(-1.0..=1.0)))
+        .collect();
+    pcm16_wav(&samples, sample_rate)
+}
+
+// 16-bit mono PCM with the plain 44-byte header
+fn pcm16_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let audio_data: Vec<u8> = samples
+        .iter()
+        .flat_map(|&sample| ((sample.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes())
+        .collect();
+    let mut wav_data = Vec::with_capacity(audio_data.len() + 44);
+    
+    // RIFF header
+    wav_data.extend_from_slice(b"RIFF");
+    wav_data.extend_from_slice(&((audio_data.len() + 36) as u32).to_le_bytes());
+    wav_data.extend_from_slice(b"WAVE");
+    
+    // fmt chunk
+    wav_data.extend_from_slice(b"fmt ");
+    wav_data.extend_from_slice(&16u32.to_le_bytes()); // chunk size
+    wav_data.extend_from_slice(&1u16.to_le_bytes()); // audio format (PCM)
+    wav_data.extend_from_slice(&1u16.to_le_bytes()); // num channels
+    wav_data.extend_from_slice(&sample_rate.to_le_bytes()); // sample rate
+    wav_data.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    wav_data.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav_data.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    
+    // data chunk
+    wav_data.extend_from_slice(b"data");
+    wav_data.extend_from_slice(&(audio_data.len() as u32).to_le_bytes());
+    wav_data.extend_from_slice(&audio_data);
+    
+    wav_data
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Mp3,
+    Flac,
+    Ogg,
+    Aiff,
+}
+
+impl AudioFormat {
+    // Recognizes the formats the decoder plays from their header; too short to tell is None
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 12 {
+            return None;
+        }
+        if bytes.starts_with(b"RIFF") && bytes[8..12] == *b"WAVE" {
+            Some(AudioFormat::Wav)
+        } else if bytes.starts_with(b"ID3") || (bytes[0] == 0xFF && (bytes[1] & 0xE0) == 0xE0) {
+            // A tagless MP3 starts straight on a frame: 11 sync bits set
+            Some(AudioFormat::Mp3)
+        } else if bytes.starts_with(b"fLaC") {
+            Some(AudioFormat::Flac)
+        } else if bytes.starts_with(b"OggS") {
+            Some(AudioFormat::Ogg)
+        } else if bytes.starts_with(b"FORM") && matches!(&bytes[8..12], b"AIFF" | b"AIFC") {
+            Some(AudioFormat::Aiff)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Mp3 => "MP3",
+            AudioFormat::Flac => "FLAC",
+            AudioFormat::Ogg => "OGG",
+            AudioFormat::Aiff => "AIFF",
+        }
+    }
+}
+
+pub fn detect_format(bytes: &[u8]) -> Option<AudioFormat> {
+    AudioFormat::from_bytes(bytes)
+}
+
+// The first bytes as hex, so an unsupported file says what it actually starts with
+fn header_bytes(bytes: &[u8]) -> String {
+    bytes.iter().take(4).map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleStatus {
+    Ok(AudioFormat),
+    Missing,
+    BadFormat,
+    BadPad, // Group, pad or layer outside the grid
+}
+
+impl SampleStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SampleStatus::Ok(_) => "OK",
+            SampleStatus::Missing => "MISSING",
+            SampleStatus::BadFormat => "BADFORMAT",
+            SampleStatus::BadPad => "BADPAD",
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, SampleStatus::Ok(_))
+    }
+}
+
+// One mapping from the sample config and what was found at its file
+#[derive(Debug, Clone)]
+pub struct SampleCheck {
+    pub group: usize,
+    pub pad: usize,
+    pub path: String,
+    pub status: SampleStatus,
+}
+
+// Checks every mapping in a sample config the way loading would, without loading anything
+pub fn validate_sample_config(config_path: &str) -> Result<Vec<SampleCheck>> {
+    let config_content = std::fs::read_to_string(config_path)
+        .map_err(|e| AppError::Config(format!("Failed to read {}: {}", config_path, e)))?;
+    let config: SampleConfig = serde_json::from_str(&config_content)
+        .map_err(|e| AppError::Config(format!("Failed to parse {}: {}", config_path, e)))?;
+    
+    Ok(config.mappings.into_iter().map(|mapping| {
+        let path = mapping_path(&mapping.file);
+        let status = if mapping.group >= MAX_GROUPS || mapping.pad >= MAX_PADS || mapping.layer >= MAX_LAYERS {
+            SampleStatus::BadPad
+        } else {
+            match std::fs::read(&path) {
+                Ok(bytes) => match detect_format(&bytes) {
+                    // The header alone doesn't prove the decoder can play the rest
+                    Some(format) if probe_sample(&bytes).is_ok() => SampleStatus::Ok(format),
+                    _ => SampleStatus::BadFormat,
+                },
+                Err(_) => SampleStatus::Missing,
+            }
+        };
+        SampleCheck { group: mapping.group, pad: mapping.pad, path, status }
+    }).collect())
+}
+
+// Config files name samples relative to the samples directory unless the path is absolute
+fn mapping_path(file: &str) -> String {
+    if file.starts_with('/') {
+        file.to_string()
+    } else {
+        format!("{}/{}", SAMPLES_DIR, file)
+    }
+}
+
+// The checks every sample passes before it's kept, so a pad never holds one that won't play
+fn check_sample_data(sample_data: &[u8]) -> Result<(AudioFormat, SampleInfo)> {
+    // Verify it's a valid audio file by checking the header
+    if sample_data.len() < 12 {
+        return Err(AppError::Sample("Invalid audio file: too small".to_string()));
+    }
+    
+    // Basic format validation (check for common audio file headers)
+    let Some(format) = AudioFormat::from_bytes(sample_data) else {
+        return Err(AppError::Sample(format!(
+            "Unsupported audio format (starts with {}). Please use WAV, AIFF, MP3, FLAC, or OGG files.",
+            header_bytes(sample_data)
+        )));
+    };
+    
+    // A good header can still hide a truncated file or a codec the decoder can't play,
+    // which would otherwise only show up the first time the pad is hit
+    let info = probe_sample(sample_data)?;
+    Ok((format, info))
+}
+
+pub fn decode_sample(sample_data: &[u8]) -> Result<DecodedSample> {
+    if let Some(decoded) = decode_pcm(sample_data, usize::MAX) {
+        return decoded.map(|(_, sample)| sample);
+    }
+    
+    let decoder = Decoder::new(Cursor::new(sample_data.to_vec()))
+        .map_err(|e| AppError::Sample(format!("Failed to decode sample: {}", e)))?;
+    
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    let data = decoder.convert_samples::<f32>().collect();
+    
+    Ok(DecodedSample {
+        channels,
+        sample_rate,
+        data,
+    })
+}
+
+/// What decoding a sample when it was loaded found out about it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleInfo {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub duration: Option<Duration>, // None when the format doesn't say without decoding it all
+    pub bytes: usize, // The file's size, held in memory unless the pad streams it
+}
+
+// Samples decoded at load to prove a file plays, without paying for the whole file
+const PROBE_SAMPLES: usize = 4096;
+
+// Decodes the start of a sample so files that won't play are turned away at load
+pub fn probe_sample(sample_data: &[u8]) -> Result<SampleInfo> {
+    let (info, decoded) = match decode_pcm(sample_data, PROBE_SAMPLES) {
+        Some(decoded) => {
+            let (info, sample) = decoded?;
+            (info, sample.data.len())
+        }
+        None => {
+            let decoder = Decoder::new(Cursor::new(sample_data.to_vec()))
+                .map_err(|e| AppError::Sample(format!("Failed to decode sample: {}", e)))?;
+            let info = SampleInfo {
+                channels: decoder.channels(),
+                sample_rate: decoder.sample_rate(),
+                duration: decoder.total_duration(),
+                bytes: sample_data.len(),
+            };
+            (info, decoder.convert_samples::<f32>().take(PROBE_SAMPLES).count())
+        }
+    };
+    if decoded == 0 {
+        return Err(AppError::Sample("Failed to decode sample: no audio after the header".to_string()));
+    }
+    Ok(info)
+}
+
+// WAV and AIFF are read here at their full depth, where the generic decoder would squeeze
+// 24-bit and float audio through i16; None leaves the format to it
+fn decode_pcm(sample_data: &[u8], limit: usize) -> Option<Result<(SampleInfo, DecodedSample)>> {
+    match AudioFormat::from_bytes(sample_data)? {
+        AudioFormat::Wav => Some(decode_wav(sample_data, limit)),
+        AudioFormat::Aiff => Some(decode_aiff(sample_data, limit)),
+        _ => None,
+    }
+}
+
+fn pcm_info(sample_data: &[u8], channels: u16, sample_rate: u32, frames: u32) -> Result<SampleInfo> {
+    if channels == 0 || sample_rate == 0 {
+        return Err(AppError::Sample("Failed to decode sample: no channels or sample rate in the header".to_string()));
+    }
+    Ok(SampleInfo {
+        channels,
+        sample_rate,
+        duration: Some(Duration::from_secs_f64(frames as f64 / sample_rate as f64)),
+        bytes: sample_data.len(),
+    })
+}
+
+// Decodes up to limit samples of a WAV to f32
+fn decode_wav(sample_data: &[u8], limit: usize) -> Result<(SampleInfo, DecodedSample)> {
+    // A codec we don't play explains the failure better than whatever the reader tripped on
+    let reader = hound::WavReader::new(Cursor::new(sample_data)).map_err(|e| match unsupported_wav_codec(sample_data) {
+        Some(codec) => AppError::Sample(format!("Unsupported WAV codec: {}", codec)),
+        None => AppError::Sample(format!("Failed to decode sample: {}", e)),
+    })?;
+    let spec = reader.spec();
+    let info = pcm_info(sample_data, spec.channels, spec.sample_rate, reader.duration())?;
+    
+    let Some(samples) = wav_samples(reader) else {
+        let codec = unsupported_wav_codec(sample_data).unwrap_or_else(|| format!("{}-bit {:?}", spec.bits_per_sample, spec.sample_format));
+        return Err(AppError::Sample(format!("Unsupported WAV codec: {}", codec)));
+    };
+    let data = samples
+        .take(limit)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Sample(format!("Failed to decode sample: {}", e)))?;
+    
+    Ok((info, DecodedSample { channels: spec.channels, sample_rate: spec.sample_rate, data }))
+}
+
+// A WAV's samples as f32 at their full depth; None for a depth the decoder doesn't play
+fn wav_samples<'a, R: Read + Send + 'a>(reader: hound::WavReader<R>) -> Option<Box<dyn Iterator<Item = hound::Result<f32>> + Send + 'a>> {
+    let spec = reader.spec();
+    match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Float, 32) => Some(Box::new(reader.into_samples::<f32>())),
+        (hound::SampleFormat::Int, bits @ 1..=32) => {
+            let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+            Some(Box::new(reader.into_samples::<i32>().map(move |sample| sample.map(|value| value as f32 * scale))))
+        }
+        _ => None,
+    }
+}
+
+// Names the codec in a WAV's fmt chunk when it isn't PCM or float the decoder plays
+fn unsupported_wav_codec(bytes: &[u8]) -> Option<String> {
+    let (_, fmt) = riff_chunks(bytes, 12, u32::from_le_bytes).find(|(id, _)| id == b"fmt ")?;
+    if fmt.len() < 16 {
+        return None;
+    }
+    let mut tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+    if tag == 0xFFFE && fmt.len() >= 26 {
+        // WAVE_FORMAT_EXTENSIBLE keeps the real tag at the start of its sub-format GUID
+        tag = u16::from_le_bytes([fmt[24], fmt[25]]);
+    }
+    let codec = match tag {
+        0x0001 if (1..=32).contains(&bits) => return None,
+        0x0003 if bits == 32 => return None,
+        0x0001 => format!("{}-bit PCM", bits),
+        0x0002 => "MS ADPCM".to_string(),
+        0x0003 => format!("{}-bit float", bits),
+        0x0006 => "A-law".to_string(),
+        0x0007 => "µ-law".to_string(),
+        0x0011 => "IMA ADPCM".to_string(),
+        0x0055 => "MP3".to_string(),
+        tag => format!("format tag 0x{:04X}", tag),
+    };
+    Some(codec)
+}
+
+// The (id, body) chunks of a RIFF or IFF file from offset, stopping at the first one that runs off the end
+fn riff_chunks(bytes: &[u8], mut offset: usize, read_size: fn([u8; 4]) -> u32) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        let header = bytes.get(offset..offset + 8)?;
+        let id = [header[0], header[1], header[2], header[3]];
+        let size = read_size([header[4], header[5], header[6], header[7]]) as usize;
+        let body = bytes.get(offset + 8..(offset + 8).checked_add(size)?)?;
+        // Chunks are padded to an even length
+        offset += 8 + size + size % 2;
+        Some((id, body))
+    })
+}
+
+// How an AIFF's sound data is laid out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AiffEncoding {
+    BigEndian,
+    LittleEndian, // AIFF-C 'sowt'
+    Float, // AIFF-C 'fl32'
+}
+
+// Decodes up to limit samples of an AIFF or uncompressed AIFF-C to f32
+fn decode_aiff(sample_data: &[u8], limit: usize) -> Result<(SampleInfo, DecodedSample)> {
+    let is_aifc = sample_data[8..12] == *b"AIFC";
+    let mut comm = None;
+    let mut sound = None;
+    for (id, body) in riff_chunks(sample_data, 12, u32::from_be_bytes) {
+        match &id {
+            b"COMM" => comm = Some(body),
+            b"SSND" => sound = Some(body),
+            _ => {}
+        }
+    }
+    let Some(comm) = comm.filter(|comm| comm.len() >= 18) else {
+        return Err(AppError::Sample("Failed to decode sample: AIFF has no COMM chunk".to_string()));
+    };
+    let channels = u16::from_be_bytes([comm[0], comm[1]]);
+    let frames = u32::from_be_bytes([comm[2], comm[3], comm[4], comm[5]]);
+    let bits = u16::from_be_bytes([comm[6], comm[7]]);
+    let sample_rate = extended_to_f64(&comm[8..18]).round() as u32;
+    
+    let encoding = if is_aifc {
+        let codec = comm.get(18..22).unwrap_or(b"NONE");
+        match codec {
+            b"NONE" | b"twos" => AiffEncoding::BigEndian,
+            b"sowt" => AiffEncoding::LittleEndian,
+            b"fl32" | b"FL32" => AiffEncoding::Float,
+            _ => {
+                // The chunk carries a readable name after the four-letter code
+                let name = comm.get(22).and_then(|&len| comm.get(23..23 + len as usize)).map(String::from_utf8_lossy).unwrap_or_default();
+                let codec = String::from_utf8_lossy(codec);
+                return Err(AppError::Sample(if name.is_empty() {
+                    format!("Unsupported AIFF-C codec: {}", codec)
+                } else {
+                    format!("Unsupported AIFF-C codec: {} ({})", codec, name)
+                }));
+            }
+        }
+    } else {
+        AiffEncoding::BigEndian
+    };
+    if !(1..=32).contains(&bits) || (encoding == AiffEncoding::Float && bits != 32) {
+        return Err(AppError::Sample(format!("Unsupported AIFF codec: {}-bit {}", bits, if encoding == AiffEncoding::Float { "float" } else { "PCM" })));
+    }
+    let info = pcm_info(sample_data, channels, sample_rate, frames)?;
+    
+    // SSND starts with the offset to the first sample and a block size
+    let Some(sound) = sound.filter(|sound| sound.len() >= 8) else {
+        return Err(AppError::Sample("Failed to decode sample: AIFF has no SSND chunk".to_string()));
+    };
+    let offset = u32::from_be_bytes([sound[0], sound[1], sound[2], sound[3]]) as usize;
+    let audio = sound.get(8 + offset..).unwrap_or_default();
+    
+    // Samples are left-justified in whole bytes, so scaling by the container width is exact
+    let width = bits.div_ceil(8) as usize;
+    let count = (frames as usize * channels as usize).min(audio.len() / width).min(limit);
+    let data = audio.chunks_exact(width).take(count).map(|bytes| match encoding {
+        AiffEncoding::Float => f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        _ => {
+            let mut value: i32 = 0;
+            for i in 0..width {
+                let byte = if encoding == AiffEncoding::LittleEndian { bytes[width - 1 - i] } else { bytes[i] };
+                value = (value << 8) | byte as i32;
+            }
+            // Shift the top byte's sign bit into place before scaling
+            let shift = 32 - 8 * width as u32;
+            ((value << shift) >> shift) as f32 / (1u64 << (8 * width - 1)) as f32
+        }
+    }).collect();
+    
+    Ok((info, DecodedSample { channels, sample_rate, data }))
+}
+
+// AIFF stores its sample rate as an 80-bit extended float
+fn extended_to_f64(bytes: &[u8]) -> f64 {
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7FFF) as i32;
+    let mantissa = u64::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8], bytes[9]]);
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+// Frames a stream's reader keeps decoded ahead of the playhead, about 0.75s at 44.1kHz
+const STREAM_AHEAD_FRAMES: usize = 32_768;
+// Decoded before the stream is handed to the bus, so its first blocks don't wait on the reader
+const STREAM_PREFILL_FRAMES: usize = 2048;
+const STREAM_READER_POLL: Duration = Duration::from_millis(5);
+
+/// A sample decoded from its file while it plays, so a long one never sits in memory whole.
+/// A reader thread does the decoding, keeping a ring ahead of the playhead so the audio
+/// thread never touches the disk.
+pub struct SampleStream {
+    frames: HeapCons<(f32, f32)>, // Decoded by the reader, ahead of the playhead
+    ended: Arc<AtomicBool>, // Set once the reader has put the last frame in the ring
+    dropped: Arc<AtomicBool>, // Tells the reader to stop when the stream goes away
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub duration: Option<Duration>, // None when the format doesn't say without decoding it all
+    current: Option<(f32, f32)>, // None once the stream has run out
+    next: Option<(f32, f32)>,
+    position: f64, // How far between current and next the playhead is
+}
+
+impl SampleStream {
+    pub fn open(path: &Path) -> Result<Self> {
+        let stream_error = |e: &dyn std::fmt::Display| AppError::Sample(format!("Failed to stream {}: {}", path.display(), e));
+        // Enough of the file to tell the format and name a WAV's codec
+        let mut header = Vec::new();
+        File::open(path).and_then(|file| file.take(4096).read_to_end(&mut header)).map_err(|e| stream_error(&e))?;
+        
+        match AudioFormat::from_bytes(&header) {
+            Some(AudioFormat::Wav) => {
+                let reader = hound::WavReader::open(path).map_err(|e| stream_error(&e))?;
+                let spec = reader.spec();
+                let duration = Duration::from_secs_f64(reader.duration() as f64 / spec.sample_rate.max(1) as f64);
+                let Some(samples) = wav_samples(reader) else {
+                    let codec = unsupported_wav_codec(&header).unwrap_or_else(|| format!("{}-bit {:?}", spec.bits_per_sample, spec.sample_format));
+                    return Err(AppError::Sample(format!("Unsupported WAV codec: {}", codec)));
+                };
+                // A read error part way through ends the stream like the end of the file would
+                Ok(Self::from_samples(Box::new(samples.map_while(|sample| sample.ok())), spec.channels, spec.sample_rate, Some(duration)))
+            }
+            Some(AudioFormat::Aiff) | None => Err(stream_error(&"only WAV, MP3, FLAC and OGG can be streamed")),
+            Some(_) => {
+                let file = File::open(path).map_err(|e| stream_error(&e))?;
+                let decoder = Decoder::new(BufReader::new(file)).map_err(|e| stream_error(&e))?;
+                let (channels, sample_rate, duration) = (decoder.channels(), decoder.sample_rate(), decoder.total_duration());
+                Ok(Self::from_samples(Box::new(decoder.convert_samples::<f32>()), channels, sample_rate, duration))
+            }
+        }
+    }
+
+    // The first frames are decoded here and the rest on a reader thread
+    pub fn from_samples(mut samples: Box<dyn Iterator<Item = f32> + Send>, channels: u16, sample_rate: u32, duration: Option<Duration>) -> Self {
+        let (mut producer, frames) = HeapRb::new(STREAM_AHEAD_FRAMES).split();
+        let mut ended = false;
+        for _ in 0..STREAM_PREFILL_FRAMES {
+            match stream_frame(&mut samples, channels) {
+                Some(frame) => {
+                    let _ = producer.try_push(frame);
+                }
+                None => {
+                    ended = true;
+                    break;
+                }
+            }
+        }
+        let ended = Arc::new(AtomicBool::new(ended));
+        let dropped = Arc::new(AtomicBool::new(false));
+        if !ended.load(Ordering::Relaxed) {
+            let (ended, dropped) = (Arc::clone(&ended), Arc::clone(&dropped));
+            std::thread::spawn(move || {
+                while !dropped.load(Ordering::Relaxed) {
+                    if producer.is_full() {
+                        std::thread::sleep(STREAM_READER_POLL);
+                        continue;
+                    }
+                    match stream_frame(&mut samples, channels) {
+                        Some(frame) => {
+                            let _ = producer.try_push(frame);
+                        }
+                        None => break,
+                    }
+                }
+                ended.store(true, Ordering::Release);
+            });
+        }
+        
+        let mut stream = Self { frames, ended, dropped, channels, sample_rate, duration, current: None, next: None, position: 0.0 };
+        stream.current = stream.frames.try_pop();
+        stream.next = stream.frames.try_pop();
+        stream
+    }
+
+    // Every frame has been played out of the ring and the reader has no more
+    fn is_drained(&self) -> bool {
+        self.ended.load(Ordering::Acquire) && self.frames.is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current.is_none()
+    }
+
+    // The reader hasn't got the next frame ready, so the stream is waiting on it in silence
+    pub fn is_buffering(&self) -> bool {
+        self.current.is_some() && self.next.is_none() && !self.ended.load(Ordering::Acquire) && self.frames.is_empty()
+    }
+
+    // The frame at the playhead, interpolated towards the next, then moves on step source frames.
+    // If the reader has fallen behind, the stream stays put and plays silence until it catches up
+    pub fn advance(&mut self, step: f64) -> Option<(f32, f32)> {
+        let current = self.current?;
+        if self.next.is_none() && !self.is_drained() {
+            self.next = self.frames.try_pop();
+            if self.next.is_none() {
+                return Some((0.0, 0.0));
+            }
+        }
+        // The last frame holds, as DecodedSample::interpolated_frame's does
+        let next = self.next.unwrap_or(current);
+        let frac = self.position.min(1.0) as f32;
+        let frame = (current.0 + (next.0 - current.0) * frac, current.1 + (next.1 - current.1) * frac);
+        
+        self.position += step;
+        while self.position >= 1.0 && self.current.is_some() {
+            if self.next.is_none() && !self.is_drained() {
+                break;
+            }
+            self.position -= 1.0;
+            self.current = self.next.take();
+            self.next = self.frames.try_pop();
+        }
+        Some(frame)
+    }
+}
+
+impl Drop for SampleStream {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::Relaxed);
+    }
+}
+
+// Mono plays on both sides and anything beyond stereo keeps just the front pair,
+// as DecodedSample::stereo_frame does
+fn stream_frame(samples: &mut dyn Iterator<Item = f32>, channels: u16) -> Option<(f32, f32)> {
+    let left = samples.next()?;
+    let right = if channels >= 2 { samples.next()? } else { left };
+    for _ in 2..channels {
+        samples.next();
+    }
+    Some((left, right))
+}
+
+// Proves a streamed file plays and reads what it says about itself, without decoding it all
+fn probe_stream(path: &Path) -> Result<SampleInfo> {
+    let stream = SampleStream::open(path)?;
+    if stream.is_finished() {
+        return Err(AppError::Sample("Failed to decode sample: no audio after the header".to_string()));
+    }
+    let bytes = std::fs::metadata(path).map_or(0, |metadata| metadata.len() as usize);
+    Ok(SampleInfo { channels: stream.channels, sample_rate: stream.sample_rate, duration: stream.duration, bytes })
+}
+
+// Built-in names and sample folders of the first groups
+const DEFAULT_GROUP_DEFS: [(&str, &str); 4] = [("DRUMS", "drums"), ("BASS", "bass"), ("LEAD", "lead"), ("VOCAL", "vocal")];
+
+/// A group's display name and the folder under `samples/` it's scanned from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupDef {
+    pub name: String,
+    pub dir: String,
+}
+
+impl GroupDef {
+    // Groups past the four named ones come from a wider [layout]
+    pub fn default_for(group: usize) -> Self {
+        match DEFAULT_GROUP_DEFS.get(group) {
+            Some((name, dir)) => Self { name: name.to_string(), dir: dir.to_string() },
+            None => Self { name: format!("GROUP {}", group + 1), dir: format!("group{}", group + 1) },
+        }
+    }
+}
+
+// Kit folders are searched this many levels deep, counting the kit folder itself
+const MAX_KIT_DEPTH: usize = 3;
+
+// Sample files are read and decoded on up to this many threads at once
+const MAX_LOAD_THREADS: usize = 8;
+
+// Runs f over the items on a few threads at once; results come back in the order given
+fn map_on_threads<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(MAX_LOAD_THREADS)
+        .min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+    
+    // Each thread takes the next item, so one big file doesn't hold up a whole share
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<_> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| {
+                let mut done = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break done;
+                    };
+                    done.push((index, f(item)));
+                }
+            }))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// What reading a sample file finds out, on whichever thread read it
+struct ReadSample {
+    storage: SampleStorage,
+    format: AudioFormat,
+    info: SampleInfo,
+    loudness: Option<Loudness>, // Measured while the file was at hand, when every pad normalizes
+}
+
+/// Sample files that are swapped into a group together, from one folder of the library.
+#[derive(Debug, Clone)]
+pub struct Kit {
+    pub name: String,
+    files: Vec<PathBuf>,
+    pub skipped: usize, // Files past one per pad, which aren't loaded
+}
+
+impl Kit {
+    fn new(name: String, mut files: Vec<PathBuf>, pads: usize) -> Self {
+        files.sort();
+        let skipped = files.len().saturating_sub(pads);
+        files.truncate(pads);
+        Self { name, files, skipped }
+    }
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| matches!(ext.to_string_lossy().to_lowercase().as_str(), "wav" | "aif" | "aiff" | "mp3" | "flac" | "ogg"))
+}
+
+// Audio files under dir, looking at most depth folders down
+fn collect_audio_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 1 {
+                collect_audio_files(&path, depth - 1, files);
+            }
+        } else if is_audio_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
 pub struct SampleBank {
-    samples: HashMap<(usize, usize), Vec<u8>>, // (group, pad) -> sample data
+    groups: Vec<GroupDef>, // Groups past the end use GroupDef::default_for
+    pads: usize, // Pads per group that samples load into
+    kits: HashMap<usize, Vec<Kit>>, // Found by the directory scan, per group
+    current_kit: HashMap<usize, usize>, // group -> index into kits
+    samples: HashMap<(usize, usize), SampleStorage>, // (group, pad) -> sample data or the file it streams from
+    stream_threshold_mb: u32, // Files bigger than this are streamed; 0 holds everything
     sample_names: HashMap<(usize, usize), String>,
+    sample_paths: HashMap<(usize, usize), String>, // (group, pad) -> file the sample was loaded from
+    sample_formats: HashMap<(usize, usize), AudioFormat>, // (group, pad) -> format found in the header
+    sample_info: HashMap<(usize, usize), SampleInfo>, // (group, pad) -> found by decoding the sample
+    pad_start_offset: HashMap<(usize, usize), f32>, // (group, pad) -> fraction skipped at the start
+    pad_end_offset: HashMap<(usize, usize), f32>,   // (group, pad) -> fraction where playback stops
+    normalize: Normalize,
+    normalize_target_db: f32,
+    normalize_overrides: HashMap<(usize, usize), bool>, // From samples/config.json mappings
+    loudness: HashMap<(usize, usize), Loudness>, // Only for pads being normalized
+    layers: HashMap<(usize, usize), Vec<SampleLayer>>, // Played with the pad's sample
+    layer_modes: HashMap<(usize, usize), LayerMode>, // Only pads that don't stack
+    last_layers: HashMap<(usize, usize), usize>, // Where each alternating pad is up to
+    velocity_ranges: HashMap<(usize, usize), (u8, u8)>, // Of pads' own samples; layers keep theirs
 }
 
 impl SampleBank {
     pub fn new() -> Self {
         Self {
+            groups: Vec::new(),
+            pads: PADS_PER_GROUP,
+            kits: HashMap::new(),
+            current_kit: HashMap::new(),
             samples: HashMap::new(),
+            stream_threshold_mb: DEFAULT_STREAM_THRESHOLD_MB,
             sample_names: HashMap::new(),
+            sample_paths: HashMap::new(),
+            sample_formats: HashMap::new(),
+            sample_info: HashMap::new(),
+            pad_start_offset: HashMap::new(),
+            pad_end_offset: HashMap::new(),
+            normalize: Normalize::Off,
+            normalize_target_db: DEFAULT_NORMALIZE_TARGET_DB,
+            normalize_overrides: HashMap::new(),
+            loudness: HashMap::new(),
+            layers: HashMap::new(),
+            layer_modes: HashMap::new(),
+            last_layers: HashMap::new(),
+            velocity_ranges: HashMap::new(),
         }
     }
 
     pub fn load_defaults(&mut self) -> Result<()> {
         // First try to load from JSON config
-        if self.load_from_config().is_ok() {
+        if self.load_from_config(SAMPLE_CONFIG_PATH).is_ok() {
             eprintln!("Loaded samples from config file");
             return Ok(());
         }
@@ -39,258 +1102,601 @@ impl SampleBank {
         // Fallback to directory scanning
         eprintln!("No config file found, scanning directories...");
         
-        // Try to load samples from the samples directory
-        let samples_dir = "samples";
-        let group_dirs = ["drums", "bass", "lead", "vocal"];
+        let started = Instant::now();
+        let (loaded, failed) = self.scan_group_dirs(SAMPLES_DIR);
+        eprintln!("Loaded {} samples in {} ms ({} failed)", loaded, started.elapsed().as_millis(), failed);
         
-        for (group_idx, group_dir) in group_dirs.iter().enumerate() {
-            let group_path = format!("{}/{}", samples_dir, group_dir);
+        // Create placeholder names for empty pads
+        self.create_placeholder_names();
+        
+        Ok(())
+    }
+    
+    // Each group's folder is a kit of its own files, plus one kit per subfolder.
+    // The first kit is loaded; the rest are only listed until they're switched to.
+    // Every group's files are read together; returns how many loaded and how many failed
+    fn scan_group_dirs(&mut self, samples_dir: &str) -> (usize, usize) {
+        let mut files = Vec::new();
+        for group_idx in 0..MAX_GROUPS {
+            let group_dir = self.group_def(group_idx).dir;
+            let group_path = Path::new(samples_dir).join(&group_dir);
+            let Ok(entries) = std::fs::read_dir(&group_path) else {
+                continue;
+            };
             
-            // Try to load up to 16 samples from each group directory
-            if let Ok(entries) = std::fs::read_dir(&group_path) {
+            let mut root_files = Vec::new();
+            let mut subdirs = Vec::new();
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    subdirs.push(path);
+                } else if is_audio_file(&path) {
+                    root_files.push(path);
+                }
+            }
+            subdirs.sort();
+            
+            // Files at the group root load as before, ahead of any subfolder kits
+            let mut kits = Vec::new();
+            if !root_files.is_empty() {
+                kits.push(Kit::new(group_dir, root_files, self.pads));
+            }
+            for dir in subdirs {
                 let mut files = Vec::new();
-                
-                // Collect all audio files
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if let Some(extension) = path.extension() {
-                        let ext = extension.to_string_lossy().to_lowercase();
-                        if matches!(ext.as_str(), "wav" | "mp3" | "flac" | "ogg") {
-                            files.push(path);
-                        }
-                    }
+                collect_audio_files(&dir, MAX_KIT_DEPTH, &mut files);
+                if !files.is_empty() {
+                    let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    kits.push(Kit::new(name, files, self.pads));
                 }
-                
-                // Sort files to ensure consistent loading order
-                files.sort();
-                
-                // Load samples with intelligent pad assignment
-                for path in files {
-                    if let Some(path_str) = path.to_str() {
-                        let file_stem = path.file_stem()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_lowercase();
-                        
-                        // Try to extract pad number from filename
-                        let target_pad = self.extract_pad_from_filename(&file_stem);
-                        
-                        if let Some(pad_idx) = target_pad {
-                            // Load to specific pad if specified in filename
-                            if pad_idx < 16 && !self.samples.contains_key(&(group_idx, pad_idx)) {
-                                if let Err(e) = self.load_sample(group_idx, pad_idx, path_str) {
-                                    eprintln!("Failed to load sample {}: {}", path_str, e);
-                                } else {
-                                    eprintln!("Loaded sample: {} -> Group {} Pad {} (from filename)", path_str, group_idx, pad_idx);
-                                }
-                            }
-                        } else {
-                            // Load to next available pad
-                            for pad_idx in 0..16 {
-                                if !self.samples.contains_key(&(group_idx, pad_idx)) {
-                                    if let Err(e) = self.load_sample(group_idx, pad_idx, path_str) {
-                                        eprintln!("Failed to load sample {}: {}", path_str, e);
-                                    } else {
-                                        eprintln!("Loaded sample: {} -> Group {} Pad {} (auto-assigned)", path_str, group_idx, pad_idx);
-                                    }
-                                    break;
-                                }
-                            }
-                        }
+            }
+            
+            for kit in &kits {
+                if kit.skipped > 0 {
+                    eprintln!("Kit {}: {} files past the first {} skipped", kit.name, kit.skipped, self.pads);
+                }
+            }
+            if let Some(kit) = kits.first() {
+                files.extend(kit.files.iter().filter_map(|path| Some((group_idx, path.to_str()?.to_string()))));
+                eprintln!("Loading kit {} into group {}", kit.name, group_idx);
+                self.current_kit.insert(group_idx, 0);
+            }
+            self.kits.insert(group_idx, kits);
+        }
+        
+        let (loaded, failures) = self.load_into_groups(&files);
+        for failure in &failures {
+            eprintln!("{}", failure);
+        }
+        (loaded, failures.len())
+    }
+    
+    // Files go to the pad named in the file, or the next free one; returns the failures
+    fn load_files_into_group(&mut self, group_idx: usize, files: &[PathBuf]) -> Vec<String> {
+        let files: Vec<_> = files.iter().filter_map(|path| Some((group_idx, path.to_str()?.to_string()))).collect();
+        self.load_into_groups(&files).1
+    }
+    
+    // Reads every (group, file) on a few threads at once, then hands out pads in file order
+    // so they're the same however the reads finish. A file that fails takes no pad, leaving
+    // it to the next file. Returns how many loaded and the failures
+    fn load_into_groups(&mut self, files: &[(usize, String)]) -> (usize, Vec<String>) {
+        let reads = map_on_threads(files, |(_, path)| self.read_sample_file(path));
+        let mut loaded = 0;
+        let mut failures = Vec::new();
+        for ((group, path), read) in files.iter().zip(reads) {
+            match read {
+                Ok(read) => {
+                    if let Some(pad) = self.free_pad_for(*group, path) {
+                        self.place_sample(*group, pad, path, read);
+                        loaded += 1;
                     }
                 }
+                Err(e) => failures.push(format!("Failed to load sample {}: {}", path, e)),
             }
         }
+        (loaded, failures)
+    }
+    
+    // A pad number in the file name wins if that pad is free; otherwise the next free pad
+    fn free_pad_for(&self, group: usize, path: &str) -> Option<usize> {
+        let file_stem = Path::new(path).file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+        let is_free = |pad: usize| pad < self.pads && !self.samples.contains_key(&(group, pad));
+        match self.extract_pad_from_filename(&file_stem) {
+            Some(pad) => Some(pad).filter(|&pad| is_free(pad)),
+            None => (0..self.pads).find(|&pad| is_free(pad)),
+        }
+    }
+    
+    // Only named when the group has other kits to switch to
+    pub fn get_kit_name(&self, group: usize) -> Option<&str> {
+        let kits = self.kits.get(&group).filter(|kits| kits.len() > 1)?;
+        let kit = self.current_kit.get(&group)?;
+        kits.get(*kit).map(|kit| kit.name.as_str())
+    }
+    
+    // Swaps every pad in the group for the next (or previous) kit's samples. Returns the
+    // kit now loaded and how many of its files failed, or None with fewer than two kits
+    pub fn switch_kit(&mut self, group: usize, delta: i32) -> Option<(&Kit, usize)> {
+        let count = self.kits.get(&group).map_or(0, Vec::len);
+        if count < 2 {
+            return None;
+        }
+        let current = self.current_kit.get(&group).copied().unwrap_or(0);
+        let next = (current as i32 + delta).rem_euclid(count as i32) as usize;
         
-        // Create placeholder names for empty pads
+        for pad in 0..self.pads {
+            self.remove_sample(group, pad);
+        }
+        let files = self.kits[&group][next].files.clone();
+        let failed = self.load_files_into_group(group, &files).len();
+        self.create_placeholder_names();
+        self.current_kit.insert(group, next);
+        Some((&self.kits[&group][next], failed))
+    }
+    
+    // Replaces the group's samples with the audio files under dir, placed the way a kit
+    // folder's are. Removing each old sample drops its loudness and layer positions too.
+    // The folder joins the group's kits as the loaded one; returns it and how many files failed
+    pub fn load_directory_into_group(&mut self, group: usize, dir: &str) -> Result<(&Kit, usize)> {
+        if group >= MAX_GROUPS {
+            return Err(AppError::Sample(format!("No group {}", group + 1)));
+        }
+        let path = Path::new(dir);
+        if !path.is_dir() {
+            return Err(AppError::Sample(format!("Not a folder: {}", dir)));
+        }
+        let mut files = Vec::new();
+        collect_audio_files(path, MAX_KIT_DEPTH, &mut files);
+        if files.is_empty() {
+            return Err(AppError::Sample(format!("No audio files in {}", dir)));
+        }
+        let name = path.file_name().map_or_else(|| dir.to_string(), |name| name.to_string_lossy().to_string());
+        let kit = Kit::new(name, files, self.pads);
+        
+        for pad in 0..self.pads {
+            self.remove_sample(group, pad);
+        }
+        let failed = self.load_files_into_group(group, &kit.files).len();
         self.create_placeholder_names();
         
-        Ok(())
+        // Importing the same folder again reloads its kit instead of listing it twice
+        let kits = self.kits.entry(group).or_default();
+        let index = match kits.iter().position(|existing| existing.files == kit.files) {
+            Some(index) => index,
+            None => {
+                kits.push(kit);
+                kits.len() - 1
+            }
+        };
+        self.current_kit.insert(group, index);
+        Ok((&self.kits[&group][index], failed))
     }
     
     fn create_placeholder_names(&mut self) {
-        let drum_names = [
-            "Kick", "Snare", "HiHat", "OpenHat",
-            "Crash", "Ride", "Tom1", "Tom2",
-            "Perc1", "Perc2", "Perc3", "Perc4",
-            "FX1", "FX2", "FX3", "FX4"
-        ];
-
-        let bass_names = [
-            "Bass1", "Bass2", "Sub1", "Sub2",
-            "Pluck1", "Pluck2", "Saw1", "Saw2",
-            "Sine1", "Sine2", "FM1", "FM2",
-            "Noise1", "Noise2", "Sweep1", "Sweep2"
-        ];
-
-        let lead_names = [
-            "Lead1", "Lead2", "Arp1", "Arp2",
-            "Pad1", "Pad2", "Strings1", "Strings2",
-            "Brass1", "Brass2", "Choir1", "Choir2",
-            "Organ1", "Organ2", "Piano1", "Piano2"
-        ];
-
-        let vocal_names = [
-            "Vocal1", "Vocal2", "Chop1", "Chop2",
-            "Voice1", "Voice2", "Speak1", "Speak2",
-            "Breath1", "Breath2", "Scratch1", "Scratch2",
-            "Reverse1", "Reverse2", "Echo1", "Echo2"
-        ];
-
-        let group_names = [&drum_names, &bass_names, &lead_names, &vocal_names];
-
-        for (group, names) in group_names.iter().enumerate() {
-            for (pad, name) in names.iter().enumerate() {
+        for group in 0..MAX_GROUPS {
+            for pad in 0..self.pads {
                 // Only add names if we don't already have a sample loaded
                 if !self.samples.contains_key(&(group, pad)) {
-                    self.sample_names.insert((group, pad), name.to_string());
+                    self.sample_names.insert((group, pad), format!("Pad {:02}", pad + 1));
                 }
             }
         }
     }
 
     pub fn load_sample(&mut self, group: usize, pad: usize, path: &str) -> Result<()> {
+        let read = self.read_sample_file(path)?;
+        self.place_sample(group, pad, path, read);
+        Ok(())
+    }
+
+    fn place_sample(&mut self, group: usize, pad: usize, path: &str, read: ReadSample) {
+        let name = std::path::Path::new(path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        
+        self.insert_sample(group, pad, &name, read);
+        self.sample_paths.insert((group, pad), path.to_string());
+    }
+
+    // Reads the whole file into memory, or only checks it plays when it's big enough to stream.
+    // With peak normalization on, an in-memory file is measured here too, so a folder of
+    // them is decoded on the loading threads rather than one by one afterwards
+    fn read_sample_file(&self, path: &str) -> Result<ReadSample> {
         // Check if file exists
         if !std::path::Path::new(path).exists() {
             return Err(AppError::Sample(format!("Sample file not found: {}", path)));
         }
-
-        // Read the entire file into memory
-        let sample_data = std::fs::read(path)
+        
+        let mut header = Vec::new();
+        let file_bytes = File::open(path)
+            .and_then(|file| {
+                let file_bytes = file.metadata()?.len();
+                file.take(12).read_to_end(&mut header)?;
+                Ok(file_bytes)
+            })
             .map_err(|e| AppError::Sample(format!("Failed to read sample file {}: {}", path, e)))?;
         
-        // Verify it's a valid audio file by checking the header
-        if sample_data.len() < 12 {
-            return Err(AppError::Sample("Invalid audio file: too small".to_string()));
+        if let Some(format) = AudioFormat::from_bytes(&header).filter(|&format| should_stream(file_bytes, format, self.stream_threshold_mb)) {
+            let info = probe_stream(Path::new(path))?;
+            return Ok(ReadSample { storage: SampleStorage::Streamed(PathBuf::from(path)), format, info, loudness: None });
         }
         
-        // Basic format validation (check for common audio file headers)
-        let is_wav = sample_data.starts_with(b"RIFF") && sample_data[8..12] == *b"WAVE";
-        let is_mp3 = sample_data.starts_with(b"ID3") || 
-                    (sample_data.len() > 2 && sample_data[0] == 0xFF && (sample_data[1] & 0xE0) == 0xE0);
-        let is_flac = sample_data.starts_with(b"fLaC");
-        let is_ogg = sample_data.starts_with(b"OggS");
-        
-        if !is_wav && !is_mp3 && !is_flac && !is_ogg {
-            return Err(AppError::Sample("Unsupported audio format. Please use WAV, MP3, FLAC, or OGG files.".to_string()));
+        let sample_data = std::fs::read(path)
+            .map_err(|e| AppError::Sample(format!("Failed to read sample file {}: {}", path, e)))?;
+        let (format, info) = check_sample_data(&sample_data)?;
+        let loudness = (self.normalize == Normalize::Peak)
+            .then(|| decode_sample(&sample_data).ok())
+            .flatten()
+            .map(|sample| Loudness::measure(&sample, self.normalize_target_db));
+        Ok(ReadSample { storage: SampleStorage::InMemory(sample_data.into()), format, info, loudness })
+    }
+
+    pub fn load_sample_data(&mut self, group: usize, pad: usize, name: &str, sample_data: Vec<u8>) -> Result<()> {
+        let (format, info) = check_sample_data(&sample_data)?;
+        let read = ReadSample { storage: SampleStorage::InMemory(sample_data.into()), format, info, loudness: None };
+        self.insert_sample(group, pad, name, read);
+        Ok(())
+    }
+
+    fn insert_sample(&mut self, group: usize, pad: usize, name: &str, ReadSample { storage, format, info, loudness }: ReadSample) {
+        self.samples.insert((group, pad), storage);
+        self.sample_formats.insert((group, pad), format);
+        self.sample_info.insert((group, pad), info);
+        self.sample_names.insert((group, pad), name.to_string());
+        // In-memory samples have no backing file until one is recorded with set_sample_path
+        self.sample_paths.remove(&(group, pad));
+        // Trim points belong to the old sample
+        self.pad_start_offset.remove(&(group, pad));
+        self.pad_end_offset.remove(&(group, pad));
+        // A mapping's override was for the file it named, and layers went with the old sample
+        self.normalize_overrides.remove(&(group, pad));
+        self.layers.remove(&(group, pad));
+        self.velocity_ranges.remove(&(group, pad));
+        match loudness {
+            Some(loudness) => {
+                self.loudness.insert((group, pad), loudness);
+            }
+            None => self.refresh_loudness(group, pad),
+        }
+    }
+
+    // Adds a sample on top of the pad's own, or loads it as the pad's own on an empty pad.
+    // Returns the layer it went to, 0 being the pad's own sample
+    pub fn add_layer(&mut self, group: usize, pad: usize, name: &str, sample_data: Vec<u8>, gain: f32) -> Result<usize> {
+        if !self.has_sample(group, pad) {
+            self.load_sample_data(group, pad, name, sample_data)?;
+            return Ok(0);
+        }
+        self.check_layer_room(group, pad)?;
+        check_sample_data(&sample_data)?;
+        Ok(self.push_layer(group, pad, name, None, SampleStorage::InMemory(sample_data.into()), gain))
+    }
+
+    // Layer files big enough to stream are streamed, like a pad's own sample
+    pub fn add_layer_file(&mut self, group: usize, pad: usize, path: &str, gain: f32) -> Result<usize> {
+        if !self.has_sample(group, pad) {
+            self.load_sample(group, pad, path)?;
+            return Ok(0);
+        }
+        self.check_layer_room(group, pad)?;
+        let storage = self.read_sample_file(path)?.storage;
+        let name = Path::new(path).file_stem().unwrap_or_default().to_string_lossy().to_string();
+        Ok(self.push_layer(group, pad, &name, Some(path), storage, gain))
+    }
+
+    fn check_layer_room(&self, group: usize, pad: usize) -> Result<()> {
+        if self.layer_count(group, pad) >= MAX_LAYERS {
+            return Err(AppError::Sample(format!("Pad {} already has {} layers", pad + 1, MAX_LAYERS)));
         }
-        
-        self.samples.insert((group, pad), sample_data);
-        self.sample_names.insert((group, pad), 
-            std::path::Path::new(path)
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string()
-        );
-        
         Ok(())
     }
 
+    // Returns the new layer's index, counting the pad's own sample as 0
+    fn push_layer(&mut self, group: usize, pad: usize, name: &str, path: Option<&str>, data: SampleStorage, gain: f32) -> usize {
+        let layers = self.layers.entry((group, pad)).or_default();
+        layers.push(SampleLayer { name: name.to_string(), path: path.map(str::to_string), gain: gain.clamp(0.0, 2.0), velocity: None, data });
+        layers.len()
+    }
+
+    pub fn get_layer_mode(&self, group: usize, pad: usize) -> LayerMode {
+        self.layer_modes.get(&(group, pad)).copied().unwrap_or_default()
+    }
+
+    pub fn set_layer_mode(&mut self, group: usize, pad: usize, mode: LayerMode) {
+        if mode.is_stack() {
+            self.layer_modes.remove(&(group, pad));
+        } else {
+            self.layer_modes.insert((group, pad), mode);
+        }
+        self.last_layers.remove(&(group, pad));
+    }
+
+    // Index as in get_layer_sample; None plays at any velocity
+    pub fn get_velocity_range(&self, group: usize, pad: usize, index: usize) -> Option<(u8, u8)> {
+        match index {
+            0 => self.velocity_ranges.get(&(group, pad)).copied(),
+            index => self.get_layers(group, pad).get(index - 1).and_then(|layer| layer.velocity),
+        }
+    }
+
+    pub fn set_velocity_range(&mut self, group: usize, pad: usize, index: usize, range: Option<(u8, u8)>) {
+        match (index, range) {
+            (0, Some(range)) => {
+                self.velocity_ranges.insert((group, pad), range);
+            }
+            (0, None) => {
+                self.velocity_ranges.remove(&(group, pad));
+            }
+            (index, range) => {
+                if let Some(layer) = self.layers.get_mut(&(group, pad)).and_then(|layers| layers.get_mut(index - 1)) {
+                    layer.velocity = range;
+                }
+            }
+        }
+    }
+
+    // The samples a hit at `velocity` plays, as (index, weight). Once any of a pad's samples
+    // has a velocity range the pad switches on velocity, samples without one covering every
+    // velocity; otherwise its layer mode picks, from the sample played last
+    pub fn pick_layers(&self, group: usize, pad: usize, velocity: u8, last: Option<usize>, rng: &mut impl Rng) -> Vec<(usize, f32)> {
+        let count = self.layer_count(group, pad);
+        let ranges: Vec<Option<(u8, u8)>> = (0..count).map(|index| self.get_velocity_range(group, pad, index)).collect();
+        if ranges.iter().any(Option::is_some) {
+            let ranges: Vec<(u8, u8)> = ranges.into_iter().map(|range| range.unwrap_or((0, MAX_VELOCITY))).collect();
+            return pick_by_velocity(&ranges, velocity);
+        }
+        self.get_layer_mode(group, pad).pick(count, last, rng).into_iter().map(|index| (index, 1.0)).collect()
+    }
+
+    // pick_layers for a live hit; round-robin and random pads move on each call
+    pub fn next_layers(&mut self, group: usize, pad: usize, velocity: u8, rng: &mut impl Rng) -> Vec<(usize, f32)> {
+        let picked = self.pick_layers(group, pad, velocity, self.last_layers.get(&(group, pad)).copied(), rng);
+        if let [(only, _)] = picked[..] {
+            self.last_layers.insert((group, pad), only);
+        }
+        picked
+    }
+
+    // Round-robin pads start again from their own sample
+    pub fn reset_layer_positions(&mut self) {
+        self.last_layers.clear();
+    }
+
+    // One of the pad's samples and the level it plays at: 0 is its own at the normalize gain,
+    // then each layer at its gain
+    pub fn get_layer_sample(&self, group: usize, pad: usize, index: usize) -> Option<(&SampleStorage, f32)> {
+        match index {
+            0 => self.samples.get(&(group, pad)).map(|data| (data, self.get_normalize_gain(group, pad))),
+            index => self.get_layers(group, pad).get(index - 1).map(|layer| (&layer.data, layer.gain)),
+        }
+    }
+
+    // Layers on top of the pad's own sample, which isn't included
+    pub fn get_layers(&self, group: usize, pad: usize) -> &[SampleLayer] {
+        self.layers.get(&(group, pad)).map_or(&[], Vec::as_slice)
+    }
+
+    // Samples the pad plays per hit, its own included
+    pub fn layer_count(&self, group: usize, pad: usize) -> usize {
+        if self.has_sample(group, pad) {
+            1 + self.get_layers(group, pad).len()
+        } else {
+            0
+        }
+    }
+
+    // Measured pads only need the new target; pads that start normalizing are decoded on a
+    // few threads at once, like a folder being loaded
+    pub fn set_normalize(&mut self, normalize: Normalize, target_db: f32) {
+        let target_db = target_db.clamp(-24.0, 0.0);
+        if normalize == self.normalize && target_db == self.normalize_target_db {
+            return;
+        }
+        self.normalize = normalize;
+        self.normalize_target_db = target_db;
+        
+        let pads: Vec<_> = self.samples.keys().copied().filter(|&(group, pad)| self.normalizes(group, pad)).collect();
+        self.loudness.retain(|key, _| pads.contains(key));
+        for loudness in self.loudness.values_mut() {
+            *loudness = loudness.retarget(target_db);
+        }
+        let unmeasured: Vec<_> = pads.into_iter().filter(|key| !self.loudness.contains_key(key)).collect();
+        let measured = map_on_threads(&unmeasured, |&(group, pad)| {
+            let sample = decode_sample(self.get_sample(group, pad)?).ok()?;
+            Some(Loudness::measure(&sample, target_db))
+        });
+        for (key, loudness) in unmeasured.into_iter().zip(measured) {
+            if let Some(loudness) = loudness {
+                self.loudness.insert(key, loudness);
+            }
+        }
+    }
+
+    fn normalizes(&self, group: usize, pad: usize) -> bool {
+        self.normalize_overrides.get(&(group, pad)).copied().unwrap_or(self.normalize == Normalize::Peak)
+    }
+
+    fn set_normalize_override(&mut self, group: usize, pad: usize, normalize: bool) {
+        self.normalize_overrides.insert((group, pad), normalize);
+        // Measured on load already when every pad normalizes
+        if !(normalize && self.loudness.contains_key(&(group, pad))) {
+            self.refresh_loudness(group, pad);
+        }
+    }
+
+    // Normalizing means decoding the whole file, so it's only done for pads that use it,
+    // and never for streamed ones
+    fn refresh_loudness(&mut self, group: usize, pad: usize) {
+        let enabled = self.normalizes(group, pad);
+        let decoded = self.get_sample(group, pad).filter(|_| enabled).and_then(|data| decode_sample(data).ok());
+        match decoded {
+            Some(sample) => {
+                self.loudness.insert((group, pad), Loudness::measure(&sample, self.normalize_target_db));
+            }
+            None => {
+                self.loudness.remove(&(group, pad));
+            }
+        }
+    }
+
+    pub fn get_loudness(&self, group: usize, pad: usize) -> Option<Loudness> {
+        self.loudness.get(&(group, pad)).copied()
+    }
+
+    // 1.0 for pads that aren't normalized
+    pub fn get_normalize_gain(&self, group: usize, pad: usize) -> f32 {
+        self.loudness.get(&(group, pad)).map_or(1.0, |loudness| loudness.gain)
+    }
+
+    // The encoded file, for samples held in memory
     pub fn get_sample(&self, group: usize, pad: usize) -> Option<&[u8]> {
-        self.samples.get(&(group, pad)).map(|v| v.as_slice())
+        self.samples.get(&(group, pad)).and_then(SampleStorage::bytes)
+    }
+
+    pub fn is_streamed(&self, group: usize, pad: usize) -> bool {
+        self.samples.get(&(group, pad)).is_some_and(SampleStorage::is_streamed)
+    }
+
+    // Applies to files loaded from here on
+    pub fn set_stream_threshold_mb(&mut self, threshold_mb: u32) {
+        self.stream_threshold_mb = threshold_mb;
     }
 
     pub fn get_sample_name(&self, group: usize, pad: usize) -> Option<&str> {
         self.sample_names.get(&(group, pad)).map(|s| s.as_str())
     }
 
+    pub fn get_sample_format(&self, group: usize, pad: usize) -> Option<AudioFormat> {
+        self.sample_formats.get(&(group, pad)).copied()
+    }
+
+    pub fn get_sample_info(&self, group: usize, pad: usize) -> Option<SampleInfo> {
+        self.sample_info.get(&(group, pad)).copied()
+    }
+
+    pub fn get_sample_path(&self, group: usize, pad: usize) -> Option<&str> {
+        self.sample_paths.get(&(group, pad)).map(|s| s.as_str())
+    }
+
+    // (samples loaded, bytes they hold) across every group, layers included; streamed
+    // samples count but hold nothing
+    pub fn memory_usage(&self) -> (usize, usize) {
+        let layers = self.layers.values().flatten();
+        let count = self.samples.len() + layers.clone().count();
+        let bytes = self.samples.values().map(SampleStorage::memory_bytes).sum::<usize>() + layers.map(|layer| layer.data.memory_bytes()).sum::<usize>();
+        (count, bytes)
+    }
+
     pub fn has_sample(&self, group: usize, pad: usize) -> bool {
         self.samples.contains_key(&(group, pad))
     }
 
+    pub fn set_sample_path(&mut self, group: usize, pad: usize, path: &str) {
+        if self.samples.contains_key(&(group, pad)) {
+            self.sample_paths.insert((group, pad), path.to_string());
+        }
+    }
+
+    pub fn set_sample_name(&mut self, group: usize, pad: usize, name: &str) {
+        self.sample_names.insert((group, pad), name.to_string());
+    }
+
     pub fn remove_sample(&mut self, group: usize, pad: usize) {
         self.samples.remove(&(group, pad));
+        self.sample_formats.remove(&(group, pad));
+        self.sample_info.remove(&(group, pad));
         self.sample_names.remove(&(group, pad));
+        self.sample_paths.remove(&(group, pad));
+        self.pad_start_offset.remove(&(group, pad));
+        self.pad_end_offset.remove(&(group, pad));
+        self.normalize_overrides.remove(&(group, pad));
+        self.loudness.remove(&(group, pad));
+        self.layers.remove(&(group, pad));
+        self.layer_modes.remove(&(group, pad));
+        self.last_layers.remove(&(group, pad));
+        self.velocity_ranges.remove(&(group, pad));
+    }
+
+    // (start, end) as fractions of the sample; untrimmed pads play 0..1
+    pub fn get_trim(&self, group: usize, pad: usize) -> (f32, f32) {
+        (
+            self.pad_start_offset.get(&(group, pad)).copied().unwrap_or(0.0),
+            self.pad_end_offset.get(&(group, pad)).copied().unwrap_or(1.0),
+        )
+    }
+
+    // Moves the start point, stopping short of the end point; returns the new start
+    pub fn adjust_start_offset(&mut self, group: usize, pad: usize, delta: f32) -> f32 {
+        let (start, end) = self.get_trim(group, pad);
+        let start = (start + delta).clamp(0.0, end - MIN_TRIM_LENGTH);
+        self.pad_start_offset.insert((group, pad), start);
+        start
+    }
+
+    // Moves the end point, staying after the start point; returns the new end
+    pub fn adjust_end_offset(&mut self, group: usize, pad: usize, delta: f32) -> f32 {
+        let (start, end) = self.get_trim(group, pad);
+        let end = (end + delta).clamp(start + MIN_TRIM_LENGTH, 1.0);
+        self.pad_end_offset.insert((group, pad), end);
+        end
+    }
+
+    // Config can rename groups while running; folders only matter for the startup scan
+    pub fn set_groups(&mut self, groups: Vec<GroupDef>) {
+        self.groups = groups;
+    }
+
+    pub fn pad_count(&self) -> usize {
+        self.pads
+    }
+
+    // Set before the scan so kits fill every pad; samples already loaded stay put
+    pub fn set_pad_count(&mut self, pads: usize) {
+        self.pads = pads;
+    }
+
+    fn group_def(&self, group: usize) -> GroupDef {
+        self.groups.get(group).cloned().unwrap_or_else(|| GroupDef::default_for(group))
     }
 
     pub fn get_group_name(&self, group: usize) -> String {
-        match group {
-            0 => "DRUMS".to_string(),
-            1 => "BASS".to_string(),
-            2 => "LEAD".to_string(),
-            3 => "VOCAL".to_string(),
-            _ => format!("GROUP{}", group),
-        }
+        self.group_def(group).name
     }
     
-    pub fn create_samples_directory() -> Result<()> {
-        let samples_dir = "samples";
-        let group_dirs = ["drums", "bass", "lead", "vocal"];
-        
-        std::fs::create_dir_all(samples_dir)
-            .map_err(|e| AppError::Config(format!("Failed to create samples directory: {}", e)))?;
+    // With dry_run, only prints the directories it would create
+    pub fn create_samples_directory(&self, dry_run: bool) -> Result<()> {
+        let samples_dir = SAMPLES_DIR;
+        let group_dirs: Vec<String> = (0..self.groups.len().max(DEFAULT_GROUPS)).map(|group| self.group_def(group).dir).collect();
         
-        for group_dir in &group_dirs {
-            let group_path = format!("{}/{}", samples_dir, group_dir);
-            std::fs::create_dir_all(&group_path)
-                .map_err(|e| AppError::Config(format!("Failed to create group directory {}: {}", group_path, e)))?;
+        if dry_run {
+            println!("Would create samples directory structure:");
+        } else {
+            std::fs::create_dir_all(samples_dir)
+                .map_err(|e| AppError::Config(format!("Failed to create samples directory: {}", e)))?;
+            
+            for group_dir in &group_dirs {
+                let group_path = format!("{}/{}", samples_dir, group_dir);
+                std::fs::create_dir_all(&group_path)
+                    .map_err(|e| AppError::Config(format!("Failed to create group directory {}: {}", group_path, e)))?;
+            }
+            println!("Created samples directory structure:");
         }
         
-        println!("Created samples directory structure:");
         println!("samples/");
         for group_dir in &group_dirs {
             println!("  {}/", group_dir);
         }
-        println!("\nPlace your audio files in the appropriate directories and restart the app.");
-        
-        Ok(())
-    }
-    
-    pub fn generate_simple_kick(&self) -> Vec<u8> {
-        // Generate a simple kick drum sound as WAV data
-        let sample_rate = 44100;
-        let duration = 0.5; // 500ms
-        let samples = (sample_rate as f32 * duration) as usize;
-        
-        let mut audio_data = Vec::new();
-        
-        // Generate kick drum sound (low frequency sweep + click)
-        for i in 0..samples {
-            let t = i as f32 / sample_rate as f32;
-            let envelope = (-t * 8.0).exp(); // Exponential decay
-            
-            // Low frequency component (kick)
-            let kick_freq = 60.0 * (1.0 - t * 0.8); // Frequency sweep down
-            let kick = (t * kick_freq * 2.0 * std::f32::consts::PI).sin();
-            
-            // Click component
-            let click = if t < 0.01 { 
-                (t * 5000.0 * 2.0 * std::f32::consts::PI).sin() * 0.3 
-            } else { 
-                0.0 
-            };
-            
-            let sample = (kick + click) * envelope * 0.7;
-            let sample_i16 = (sample * 32767.0) as i16;
-            
-            audio_data.extend_from_slice(&sample_i16.to_le_bytes());
+        if !dry_run {
+            println!("\nPlace your audio files in the appropriate directories and restart the app.");
         }
         
-        // Create WAV header
-        let mut wav_data = Vec::new();
-        
-        // RIFF header
-        wav_data.extend_from_slice(b"RIFF");
-        wav_data.extend_from_slice(&((audio_data.len() + 36) as u32).to_le_bytes());
-        wav_data.extend_from_slice(b"WAVE");
-        
-        // fmt chunk
-        wav_data.extend_from_slice(b"fmt ");
-        wav_data.extend_from_slice(&16u32.to_le_bytes()); // chunk size
-        wav_data.extend_from_slice(&1u16.to_le_bytes()); // audio format (PCM)
-        wav_data.extend_from_slice(&1u16.to_le_bytes()); // num channels
-        wav_data.extend_from_slice(&(sample_rate as u32).to_le_bytes()); // sample rate
-        wav_data.extend_from_slice(&(sample_rate as u32 * 2).to_le_bytes()); // byte rate
-        wav_data.extend_from_slice(&2u16.to_le_bytes()); // block align
-        wav_data.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
-        
-        // data chunk
-        wav_data.extend_from_slice(b"data");
-        wav_data.extend_from_slice(&(audio_data.len() as u32).to_le_bytes());
-        wav_data.extend_from_slice(&audio_data);
-        
-        wav_data
+        Ok(())
+    }
+    
+    pub fn generate_simple_kick(&self) -> Vec<u8> {
+        generate_kick()
     }
     
     fn extract_pad_from_filename(&self, filename: &str) -> Option<usize> {
@@ -318,13 +1724,12 @@ impl SampleBank {
                 if let Some(captures) = regex.captures(filename) {
                     if let Some(num_str) = captures.get(1) {
                         if let Ok(pad_num) = num_str.as_str().parse::<usize>() {
-                            // Handle both 0-based (0-15) and 1-based (1-16) numbering
-                            if pad_num >= 1 && pad_num <= 16 {
+                            // Handle both 0-based (0 to pads - 1) and 1-based (1 to pads) numbering
+                            if (1..=self.pads).contains(&pad_num) {
                                 // 1-based: convert to 0-based
-                                let target_pad = pad_num - 1;
-                                return Some(target_pad);
-                            } else if pad_num <= 15 {
-                                // 0-based: use as-is  
+                                return Some(pad_num - 1);
+                            } else if pad_num < self.pads {
+                                // 0-based: use as-is
                                 return Some(pad_num);
                             }
                         }
@@ -336,48 +1741,106 @@ impl SampleBank {
         None
     }
     
-    fn load_from_config(&mut self) -> Result<()> {
-        let config_path = "samples/config.json";
-        
+    fn load_from_config(&mut self, config_path: &str) -> Result<()> {
         if !std::path::Path::new(config_path).exists() {
             return Err(AppError::Config("Config file not found".to_string()));
         }
         
         let config_content = std::fs::read_to_string(config_path)
             .map_err(|e| AppError::Config(format!("Failed to read config file: {}", e)))?;
-        let config: SampleConfig = serde_json::from_str(&config_content)
+        let mut config: SampleConfig = serde_json::from_str(&config_content)
             .map_err(|e| AppError::Config(format!("Failed to parse config file: {}", e)))?;
         
+        // Loading a pad's own sample drops its layers, so those go first
+        config.mappings.sort_by_key(|mapping| mapping.layer);
         for mapping in config.mappings {
-            if mapping.group < 4 && mapping.pad < 16 {
-                let full_path = if mapping.file.starts_with('/') {
-                    mapping.file.clone()
-                } else {
-                    format!("samples/{}", mapping.file)
-                };
+            if mapping.group < MAX_GROUPS && mapping.pad < self.pads && mapping.layer > 0 && mapping.layer < MAX_LAYERS {
+                let full_path = mapping_path(&mapping.file);
+                match self.add_layer_file(mapping.group, mapping.pad, &full_path, mapping.gain.unwrap_or(1.0)) {
+                    Ok(layer) => {
+                        self.set_velocity_range(mapping.group, mapping.pad, layer, mapping.velocity_range());
+                        eprintln!("Loaded: {} -> Group {} Pad {} layer {} (from config)", full_path, mapping.group, mapping.pad, layer);
+                    }
+                    Err(e) => eprintln!("Failed to load {}: {}", full_path, e),
+                }
+            } else if mapping.group < MAX_GROUPS && mapping.pad < self.pads && mapping.layer == 0 {
+                let full_path = mapping_path(&mapping.file);
                 
                 match self.load_sample(mapping.group, mapping.pad, &full_path) {
                     Ok(_) => {
                         eprintln!("Loaded: {} -> Group {} Pad {} (from config)", 
                                  full_path, mapping.group, mapping.pad);
                         
+                        self.set_velocity_range(mapping.group, mapping.pad, 0, mapping.velocity_range());
+                        
                         // Override name if specified in config
                         if let Some(name) = mapping.name {
                             self.sample_names.insert((mapping.group, mapping.pad), name);
                         }
+                        if let Some(normalize) = mapping.normalize {
+                            self.set_normalize_override(mapping.group, mapping.pad, normalize);
+                        }
+                        self.set_layer_mode(mapping.group, mapping.pad, mapping.layer_mode);
                     }
                     Err(e) => eprintln!("Failed to load {}: {}", full_path, e),
                 }
             } else {
-                eprintln!("Invalid mapping: group {} pad {} (must be group 0-3, pad 0-15)", 
-                         mapping.group, mapping.pad);
+                eprintln!("Invalid mapping: group {} pad {} layer {} (must be group 0-{}, pad 0-{}, layer 0-{})", 
+                         mapping.group, mapping.pad, mapping.layer, MAX_GROUPS - 1, self.pads - 1, MAX_LAYERS - 1);
             }
         }
         
         Ok(())
     }
     
-    pub fn generate_example_config() -> Result<()> {
+    pub fn save_sample_config(&self, config_path: &str) -> Result<()> {
+        let prefix = format!("{}/", SAMPLES_DIR);
+        
+        let mut mappings: Vec<SampleMapping> = self.sample_paths
+            .iter()
+            .map(|(&(group, pad), path)| SampleMapping {
+                group,
+                pad,
+                // load_from_config resolves relative paths against the samples directory
+                file: path.strip_prefix(&prefix).unwrap_or(path).to_string(),
+                name: self.sample_names.get(&(group, pad)).cloned(),
+                normalize: self.normalize_overrides.get(&(group, pad)).copied(),
+                layer: 0,
+                gain: None,
+                layer_mode: self.get_layer_mode(group, pad),
+                vel_min: self.get_velocity_range(group, pad, 0).map(|(min, _)| min),
+                vel_max: self.get_velocity_range(group, pad, 0).map(|(_, max)| max),
+            })
+            .collect();
+        for (&(group, pad), layers) in &self.layers {
+            for (index, layer) in layers.iter().enumerate() {
+                let Some(path) = &layer.path else {
+                    continue;
+                };
+                mappings.push(SampleMapping {
+                    group,
+                    pad,
+                    file: path.strip_prefix(&prefix).unwrap_or(path).to_string(),
+                    name: Some(layer.name.clone()),
+                    normalize: None,
+                    layer: index + 1,
+                    gain: Some(layer.gain).filter(|&gain| gain != 1.0),
+                    layer_mode: LayerMode::Stack,
+                    vel_min: layer.velocity.map(|(min, _)| min),
+                    vel_max: layer.velocity.map(|(_, max)| max),
+                });
+            }
+        }
+        mappings.sort_by_key(|m| (m.group, m.pad, m.layer));
+        
+        let config_json = serde_json::to_string_pretty(&SampleConfig { mappings })
+            .map_err(|e| AppError::Config(format!("Failed to serialize sample config: {}", e)))?;
+        std::fs::write(config_path, config_json)
+            .map_err(|e| AppError::Config(format!("Failed to write sample config: {}", e)))
+    }
+    
+    // With dry_run, the example is still built but only the path it would go to is printed
+    pub fn generate_example_config(dry_run: bool) -> Result<()> {
         let example_config = SampleConfig {
             mappings: vec![
                 SampleMapping {
@@ -385,45 +1848,78 @@ impl SampleBank {
                     pad: 0,
                     file: "drums/kick.wav".to_string(),
                     name: Some("Kick".to_string()),
+                    normalize: None,
+                    layer: 0,
+                    gain: None,
+                    layer_mode: LayerMode::Stack,
+                    vel_min: None,
+                    vel_max: None,
                 },
                 SampleMapping {
                     group: 0,
                     pad: 1,
                     file: "drums/snare.wav".to_string(),
                     name: Some("Snare".to_string()),
+                    normalize: None,
+                    layer: 0,
+                    gain: None,
+                    layer_mode: LayerMode::Stack,
+                    vel_min: None,
+                    vel_max: None,
                 },
                 SampleMapping {
                     group: 0,
                     pad: 2,
                     file: "drums/hihat.wav".to_string(),
                     name: Some("Hi-Hat".to_string()),
+                    normalize: None,
+                    layer: 0,
+                    gain: None,
+                    layer_mode: LayerMode::Stack,
+                    vel_min: None,
+                    vel_max: None,
                 },
                 SampleMapping {
                     group: 1,
                     pad: 0,
                     file: "bass/bass01.wav".to_string(),
                     name: Some("Bass 1".to_string()),
+                    normalize: None,
+                    layer: 0,
+                    gain: None,
+                    layer_mode: LayerMode::Stack,
+                    vel_min: None,
+                    vel_max: None,
                 },
             ],
         };
         
         let config_json = serde_json::to_string_pretty(&example_config)
             .map_err(|e| AppError::Config(format!("Failed to serialize config: {}", e)))?;
-        std::fs::write("samples/config.example.json", config_json)
+        if dry_run {
+            println!("Would write example config to {}", EXAMPLE_SAMPLE_CONFIG_PATH);
+            return Ok(());
+        }
+        std::fs::write(EXAMPLE_SAMPLE_CONFIG_PATH, config_json)
             .map_err(|e| AppError::Config(format!("Failed to write config file: {}", e)))?;
         
-        println!("Generated example config at samples/config.example.json");
+        println!("Generated example config at {}", EXAMPLE_SAMPLE_CONFIG_PATH);
         println!("Rename to config.json and edit to use custom mappings");
         
         Ok(())
     }
 }
 
+impl Default for SampleBank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
-    use std::path::Path;
 
     #[test]
     fn test_sample_bank_creation() {
@@ -439,7 +1935,319 @@ mod tests {
         assert_eq!(bank.get_group_name(1), "BASS");
         assert_eq!(bank.get_group_name(2), "LEAD");
         assert_eq!(bank.get_group_name(3), "VOCAL");
-        assert_eq!(bank.get_group_name(99), "GROUP99");
+        assert_eq!(bank.get_group_name(4), "GROUP 5");
+    }
+
+    #[test]
+    fn test_renamed_group_is_scanned_from_its_own_folder() {
+        let dir = std::env::temp_dir().join("ko2_group_dirs");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("perc")).unwrap();
+        fs::create_dir_all(dir.join("bass")).unwrap();
+        fs::write(dir.join("perc/shaker_03.wav"), generate_hihat()).unwrap();
+        fs::write(dir.join("bass/sub.wav"), generate_kick()).unwrap();
+        
+        let mut bank = SampleBank::new();
+        bank.set_groups(vec![GroupDef { name: "PERC".to_string(), dir: "perc".to_string() }]);
+        bank.scan_group_dirs(dir.to_str().unwrap());
+        let _ = fs::remove_dir_all(&dir);
+        
+        assert_eq!(bank.get_group_name(0), "PERC");
+        assert_eq!(bank.get_sample_name(0, 2), Some("shaker_03")); // Numbers from 1 in file names
+        // Groups the config leaves out keep their built-in folders
+        assert_eq!(bank.get_group_name(1), "BASS");
+        assert!(bank.has_sample(1, 0));
+    }
+
+    #[test]
+    fn test_subfolders_are_kits() {
+        let dir = std::env::temp_dir().join("ko2_kit_dirs");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("drums/909")).unwrap();
+        fs::create_dir_all(dir.join("drums/acoustic/brushes/soft/old")).unwrap();
+        fs::write(dir.join("drums/909/kick.wav"), generate_kick()).unwrap();
+        fs::write(dir.join("drums/acoustic/brushes/snare.wav"), generate_snare()).unwrap();
+        fs::write(dir.join("drums/acoustic/brushes/soft/old/too_deep.wav"), generate_snare()).unwrap();
+        fs::create_dir_all(dir.join("bass/big")).unwrap();
+        for i in 0..PADS_PER_GROUP + 2 {
+            fs::write(dir.join(format!("bass/big/note{}.wav", i)), generate_kick()).unwrap();
+        }
+        
+        let mut bank = SampleBank::new();
+        bank.scan_group_dirs(dir.to_str().unwrap());
+        bank.create_placeholder_names();
+        
+        // Kits are sorted by folder name and the first one is loaded
+        assert_eq!(bank.get_kit_name(0), Some("909"));
+        assert_eq!(bank.get_sample_name(0, 0), Some("kick"));
+        assert_eq!(bank.kits[&1][0].skipped, 2);
+        assert!((0..PADS_PER_GROUP).all(|pad| bank.has_sample(1, pad)));
+        
+        // Files in nested folders come along, down to the depth limit
+        let (kit, failed) = bank.switch_kit(0, 1).unwrap();
+        assert_eq!((kit.name.as_str(), failed), ("acoustic", 0));
+        assert_eq!(bank.get_sample_name(0, 0), Some("snare"));
+        assert!(!bank.has_sample(0, 1));
+        assert_eq!(bank.get_sample_name(0, 1), Some("Pad 02"));
+        
+        // Switching wraps around; a group with one kit has nothing to switch to
+        assert_eq!(bank.switch_kit(0, 1).unwrap().0.name, "909");
+        assert_eq!(bank.switch_kit(0, -1).unwrap().0.name, "acoustic");
+        assert!(bank.switch_kit(1, 1).is_none());
+        assert_eq!(bank.get_kit_name(1), None);
+        assert!(bank.switch_kit(2, 1).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_sequential_loading() {
+        let dir = std::env::temp_dir().join("ko2_parallel_scan");
+        let _ = fs::remove_dir_all(&dir);
+        // 32 files of different lengths, a few naming their pad and the rest taking free ones
+        for (group, group_dir) in ["drums", "bass"].iter().enumerate() {
+            fs::create_dir_all(dir.join(group_dir)).unwrap();
+            for i in 0..PADS_PER_GROUP {
+                let name = match i % 5 {
+                    0 => format!("hit_{:02}", PADS_PER_GROUP - i),
+                    _ => format!("take{:02}", i),
+                };
+                let wav = crate::render::encode_wav(&vec![0.25; 1000 + group * 100 + i], 1, 44100).unwrap();
+                fs::write(dir.join(group_dir).join(format!("{}.wav", name)), wav).unwrap();
+            }
+        }
+        fs::create_dir_all(dir.join("lead")).unwrap();
+        fs::write(dir.join("lead/broken.wav"), b"RIFF broken").unwrap();
+        
+        let mut bank = SampleBank::new();
+        let (loaded, failed) = bank.scan_group_dirs(dir.to_str().unwrap());
+        assert_eq!((loaded, failed), (32, 1));
+        
+        // Loading the same files one at a time, in order, ends up with the same pads
+        let mut sequential = SampleBank::new();
+        for (group, group_dir) in ["drums", "bass"].iter().enumerate() {
+            let mut files = Vec::new();
+            collect_audio_files(&dir.join(group_dir), 1, &mut files);
+            files.sort();
+            for path in files {
+                let stem = path.file_stem().unwrap().to_string_lossy().to_lowercase();
+                let pad = match sequential.extract_pad_from_filename(&stem) {
+                    Some(pad) => Some(pad).filter(|&pad| pad < PADS_PER_GROUP && !sequential.has_sample(group, pad)),
+                    None => (0..PADS_PER_GROUP).find(|&pad| !sequential.has_sample(group, pad)),
+                };
+                if let Some(pad) = pad {
+                    let _ = sequential.load_sample(group, pad, path.to_str().unwrap());
+                }
+            }
+        }
+        for group in 0..2 {
+            for pad in 0..PADS_PER_GROUP {
+                assert_eq!(bank.get_sample_name(group, pad), sequential.get_sample_name(group, pad), "group {} pad {}", group, pad);
+                assert_eq!(bank.get_sample(group, pad), sequential.get_sample(group, pad), "group {} pad {}", group, pad);
+            }
+        }
+        assert_eq!(bank.get_sample_name(0, 15), Some("hit_16"));
+        assert!(!bank.has_sample(2, 0));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_directory_into_group() {
+        let dir = std::env::temp_dir().join("ko2_import_folder");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("kick_03.wav"), generate_kick()).unwrap();
+        fs::write(dir.join("snare.wav"), generate_snare()).unwrap();
+        fs::write(dir.join("notes.txt"), b"not audio").unwrap();
+        
+        let mut bank = SampleBank::new();
+        bank.load_sample_data(2, 5, "old", generate_kick()).unwrap();
+        bank.set_layer_mode(2, 5, LayerMode::RoundRobin);
+        let (kit, failed) = bank.load_directory_into_group(2, dir.to_str().unwrap()).unwrap();
+        assert_eq!((kit.name.as_str(), failed), ("ko2_import_folder", 0));
+        
+        // The number in the name picks the pad; the other file takes the first free one
+        assert_eq!(bank.get_sample_name(2, 2), Some("kick_03"));
+        assert_eq!(bank.get_sample_name(2, 0), Some("snare"));
+        assert_eq!(bank.get_sample_path(2, 0), dir.join("snare.wav").to_str());
+        assert!(!bank.has_sample(2, 5));
+        assert_eq!(bank.get_sample_name(2, 5), Some("Pad 06"));
+        assert_eq!(bank.get_layer_mode(2, 5), LayerMode::Stack);
+        assert!((0..PADS_PER_GROUP).all(|pad| !bank.has_sample(1, pad)));
+        
+        // Importing it again doesn't list the kit twice
+        bank.load_directory_into_group(2, dir.to_str().unwrap()).unwrap();
+        assert_eq!(bank.kits[&2].len(), 1);
+        
+        // A folder with nothing to load leaves the group as it was
+        let empty = dir.join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        let err = bank.load_directory_into_group(2, empty.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("No audio files"), "{}", err);
+        assert!(bank.load_directory_into_group(2, dir.join("missing").to_str().unwrap()).is_err());
+        assert!(bank.has_sample(2, 2));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_folder_loads_measure_on_the_workers_and_skip_failures() {
+        let dir = std::env::temp_dir().join("ko2_import_measured");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let half_sine = synth_wav(0.1, |t, _| 0.5 * (t * 441.0 * TAU).sin());
+        fs::write(dir.join("a.wav"), &half_sine).unwrap();
+        fs::write(dir.join("b.wav"), b"RIFF broken").unwrap();
+        fs::write(dir.join("c.wav"), &half_sine).unwrap();
+        
+        let mut bank = SampleBank::new();
+        bank.set_normalize(Normalize::Peak, 0.0);
+        let (_, failed) = bank.load_directory_into_group(1, dir.to_str().unwrap()).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        
+        // The broken file takes no pad, so the next file moves up into it
+        assert_eq!(failed, 1);
+        assert_eq!(bank.get_sample_name(1, 0), Some("a"));
+        assert_eq!(bank.get_sample_name(1, 1), Some("c"));
+        assert!(!bank.has_sample(1, 2));
+        assert!((bank.get_normalize_gain(1, 1) - 2.0).abs() < 0.01);
+        
+        // A new target reuses the measured peak
+        bank.set_normalize(Normalize::Peak, -6.0);
+        assert!((bank.get_normalize_gain(1, 1) - 2.0 * db_to_gain(-6.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_streams_wait_for_their_reader() {
+        let (sender, samples) = std::sync::mpsc::channel();
+        for i in 0..STREAM_PREFILL_FRAMES {
+            sender.send(i as f32).unwrap();
+        }
+        let mut stream = SampleStream::from_samples(Box::new(samples.into_iter()), 1, BUS_SAMPLE_RATE, None);
+        for i in 0..STREAM_PREFILL_FRAMES - 1 {
+            assert_eq!(stream.advance(1.0), Some((i as f32, i as f32)));
+        }
+        
+        // Out of decoded frames, it plays silence in place instead of running past the gap
+        assert_eq!(stream.advance(1.0), Some((0.0, 0.0)));
+        assert!(stream.is_buffering() && !stream.is_finished());
+        
+        sender.send(STREAM_PREFILL_FRAMES as f32).unwrap();
+        drop(sender);
+        while stream.is_buffering() {
+            std::thread::yield_now();
+        }
+        let last = (STREAM_PREFILL_FRAMES - 1) as f32;
+        assert_eq!(stream.advance(1.0), Some((last, last)));
+        assert_eq!(stream.advance(1.0), Some((last + 1.0, last + 1.0)));
+        assert_eq!(stream.advance(1.0), None);
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn test_big_files_are_streamed() {
+        const MB: u64 = 1024 * 1024;
+        assert!(should_stream(21 * MB, AudioFormat::Wav, 20));
+        assert!(should_stream(21 * MB, AudioFormat::Flac, 20));
+        assert!(!should_stream(20 * MB, AudioFormat::Wav, 20));
+        assert!(!should_stream(500 * MB, AudioFormat::Wav, 0));
+        assert!(!should_stream(500 * MB, AudioFormat::Aiff, 20));
+        
+        let dir = std::env::temp_dir().join("ko2_streamed_samples");
+        fs::create_dir_all(&dir).unwrap();
+        let long = dir.join("ambient.wav");
+        let short = dir.join("kick.wav");
+        // 16-bit mono, so a little over a megabyte
+        let samples: Vec<f32> = (0..600_000).map(|i| (i as f32 * 0.01).sin() * 0.5).collect();
+        fs::write(&long, crate::render::encode_wav(&samples, 1, BUS_SAMPLE_RATE).unwrap()).unwrap();
+        fs::write(&short, generate_kick()).unwrap();
+        
+        let mut bank = SampleBank::new();
+        bank.set_stream_threshold_mb(1);
+        bank.load_sample(0, 0, long.to_str().unwrap()).unwrap();
+        bank.load_sample(0, 1, short.to_str().unwrap()).unwrap();
+        assert!(bank.is_streamed(0, 0) && !bank.is_streamed(0, 1));
+        assert_eq!(bank.get_sample(0, 0), None);
+        assert_eq!(bank.get_layer_sample(0, 0, 0).map(|(data, _)| data), Some(&SampleStorage::Streamed(long.clone())));
+        
+        // Streamed pads still know what they hold, but don't hold it
+        let info = bank.get_sample_info(0, 0).unwrap();
+        assert_eq!((info.channels, info.sample_rate), (1, BUS_SAMPLE_RATE));
+        assert_eq!(info.duration.map(|duration| duration.as_millis()), Some(13_605));
+        assert_eq!(info.bytes as u64, fs::metadata(&long).unwrap().len());
+        assert_eq!(bank.get_sample_format(0, 0), Some(AudioFormat::Wav));
+        assert_eq!(bank.memory_usage(), (2, generate_kick().len()));
+        
+        // The stream decodes what the whole file would, given time to read ahead
+        let mut stream = SampleStream::open(&long).unwrap();
+        let decoded = bank.get_layer_sample(0, 0, 0).unwrap().0.decode().unwrap();
+        assert_eq!(decoded.frames(), 600_000);
+        let mut next_frame = || {
+            while stream.is_buffering() {
+                std::thread::yield_now();
+            }
+            stream.advance(1.0)
+        };
+        assert!((0..decoded.frames()).all(|frame| next_frame() == Some(decoded.stereo_frame(frame))));
+        assert_eq!(next_frame(), None);
+        
+        // Off, everything is held in memory again
+        bank.set_stream_threshold_mb(0);
+        bank.load_sample(0, 0, long.to_str().unwrap()).unwrap();
+        assert!(!bank.is_streamed(0, 0));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut bank = SampleBank::new();
+        assert_eq!(bank.memory_usage(), (0, 0));
+        
+        bank.load_sample_data(0, 0, "kick", generate_kick()).unwrap();
+        bank.load_sample_data(3, 7, "snare", generate_snare()).unwrap();
+        let bytes = generate_kick().len() + generate_snare().len();
+        assert_eq!(bank.memory_usage(), (2, bytes));
+        // Samples made in memory have no file behind them
+        assert_eq!(bank.get_sample_path(3, 7), None);
+        
+        // Replacing or removing a sample doesn't leave its bytes counted
+        bank.load_sample_data(3, 7, "kick", generate_kick()).unwrap();
+        bank.remove_sample(0, 0);
+        assert_eq!(bank.memory_usage(), (1, generate_kick().len()));
+        assert_eq!(bank.get_sample_info(3, 7).map(|info| info.bytes), Some(generate_kick().len()));
+    }
+
+    #[test]
+    fn test_peak_normalization() {
+        // 441 Hz puts a sample right on each crest, so the peak is the half-scale amplitude
+        let half_sine = || synth_wav(0.1, |t, _| 0.5 * (t * 441.0 * TAU).sin());
+        let mut bank = SampleBank::new();
+        bank.load_sample_data(0, 0, "sine", half_sine()).unwrap();
+        assert_eq!(bank.get_loudness(0, 0), None);
+        assert_eq!(bank.get_normalize_gain(0, 0), 1.0);
+        
+        bank.set_normalize(Normalize::Peak, 0.0);
+        let loudness = bank.get_loudness(0, 0).unwrap();
+        assert!((loudness.peak - 0.5).abs() < 0.001);
+        assert!((loudness.rms - 0.5 / 2f32.sqrt()).abs() < 0.01);
+        assert!((bank.get_normalize_gain(0, 0) - 2.0).abs() < 0.01);
+        
+        // The default target leaves a decibel of headroom
+        bank.set_normalize(Normalize::Peak, DEFAULT_NORMALIZE_TARGET_DB);
+        assert!((bank.get_normalize_gain(0, 0) - 2.0 * db_to_gain(-1.0)).abs() < 0.01);
+        
+        // Samples loaded while it's on are measured straight away; a mapping can opt out
+        bank.load_sample_data(0, 1, "sine", half_sine()).unwrap();
+        assert!(bank.get_loudness(0, 1).is_some());
+        bank.set_normalize_override(0, 1, false);
+        assert_eq!(bank.get_normalize_gain(0, 1), 1.0);
+        bank.set_normalize(Normalize::Off, 0.0);
+        bank.set_normalize_override(0, 1, true);
+        assert!((bank.get_normalize_gain(0, 1) - 2.0).abs() < 0.01);
+        assert_eq!(bank.get_normalize_gain(0, 0), 1.0);
+        
+        // Silence isn't blown up
+        bank.load_sample_data(1, 0, "silence", synth_wav(0.1, |_, _| 0.0)).unwrap();
+        bank.set_normalize_override(1, 0, true);
+        assert_eq!(bank.get_normalize_gain(1, 0), 1.0);
     }
 
     #[test]
@@ -482,6 +2290,14 @@ mod tests {
         assert_eq!(bank.extract_pad_from_filename("kick"), None);
         assert_eq!(bank.extract_pad_from_filename("17"), None); // Out of range
         assert_eq!(bank.extract_pad_from_filename("pad99"), None); // Out of range
+        
+        // Wider groups take numbers up to their pad count
+        let mut bank = SampleBank::new();
+        bank.set_pad_count(32);
+        assert_eq!(bank.extract_pad_from_filename("kick_20"), Some(19));
+        assert_eq!(bank.extract_pad_from_filename("p17_crash"), Some(16));
+        assert_eq!(bank.extract_pad_from_filename("pad32"), Some(31));
+        assert_eq!(bank.extract_pad_from_filename("pad33"), None);
     }
 
     #[test]
@@ -500,6 +2316,20 @@ mod tests {
         assert!(kick_data.len() > 44); // WAV header is 44 bytes minimum
     }
 
+    #[test]
+    fn test_synth_drums_are_wav() {
+        let drums = [generate_kick(), generate_snare(), generate_hihat(), generate_tom(120.0), generate_clap()];
+        for wav in drums {
+            assert!(wav.starts_with(b"RIFF"));
+            assert_eq!(&wav[8..12], b"WAVE");
+            assert!(wav.len() > 44);
+            // And something that actually makes a sound once decoded
+            let decoded = decode_sample(&wav).unwrap();
+            assert!(decoded.data.iter().any(|sample| sample.abs() > 0.1));
+        }
+        assert_ne!(generate_tom(100.0), generate_tom(200.0));
+    }
+
     #[test] 
     fn test_sample_config_serialization() {
         let config = SampleConfig {
@@ -509,12 +2339,24 @@ mod tests {
                     pad: 0,
                     file: "kick.wav".to_string(),
                     name: Some("Kick".to_string()),
+                    normalize: None,
+                    layer: 0,
+                    gain: None,
+                    layer_mode: LayerMode::Stack,
+                    vel_min: None,
+                    vel_max: None,
                 },
                 SampleMapping {
                     group: 1,
                     pad: 5,
                     file: "bass01.wav".to_string(),
                     name: None,
+                    normalize: None,
+                    layer: 0,
+                    gain: None,
+                    layer_mode: LayerMode::Stack,
+                    vel_min: None,
+                    vel_max: None,
                 },
             ],
         };
@@ -548,8 +2390,12 @@ mod tests {
         // Clean up any existing file first
         let _ = fs::remove_file("samples/config.example.json");
         
+        // A dry run succeeds without writing anything
+        assert!(SampleBank::generate_example_config(true).is_ok());
+        assert!(!Path::new("samples/config.example.json").exists());
+        
         // Generate example config
-        let result = SampleBank::generate_example_config();
+        let result = SampleBank::generate_example_config(false);
         assert!(result.is_ok());
         
         // Check that file was created
@@ -564,16 +2410,530 @@ mod tests {
         let _ = fs::remove_file("samples/config.example.json");
     }
 
+    #[test]
+    fn test_create_samples_directory() {
+        let mut bank = SampleBank::new();
+        let group = |dir: &str| GroupDef { name: dir.to_uppercase(), dir: dir.to_string() };
+        bank.set_groups(vec![group("ko2-dry-run-a"), group("ko2-dry-run-b")]);
+        let dir = Path::new(SAMPLES_DIR).join("ko2-dry-run-a");
+        
+        assert!(bank.create_samples_directory(true).is_ok());
+        assert!(!dir.exists());
+        
+        bank.create_samples_directory(false).unwrap();
+        assert!(dir.is_dir() && Path::new(SAMPLES_DIR).join("ko2-dry-run-b").is_dir());
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(Path::new(SAMPLES_DIR).join("ko2-dry-run-b"));
+    }
+
+    #[test]
+    fn test_save_sample_config() {
+        let mut bank = SampleBank::new();
+        bank.load_sample(0, 0, "samples/drums/1.wav").unwrap();
+        bank.load_sample(1, 3, "samples/bass/2.wav").unwrap();
+        
+        let path = std::env::temp_dir().join("ko2_sample_config_test.json");
+        let path = path.to_str().unwrap();
+        bank.save_sample_config(path).unwrap();
+        
+        let content = fs::read_to_string(path).unwrap();
+        let parsed: SampleConfig = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed.mappings.len(), 2);
+        assert_eq!(parsed.mappings[0].file, "drums/1.wav");
+        assert_eq!(parsed.mappings[1].group, 1);
+        assert_eq!(parsed.mappings[1].pad, 3);
+        assert_eq!(parsed.mappings[1].file, "bass/2.wav");
+        
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_layers_round_trip_through_config() {
+        let dir = std::env::temp_dir().join("ko2_sample_layers");
+        fs::create_dir_all(&dir).unwrap();
+        let snare = dir.join("snare.wav");
+        let clap = dir.join("clap.wav");
+        fs::write(&snare, generate_kick()).unwrap();
+        fs::write(&clap, generate_kick()).unwrap();
+        let file = |path: &Path| path.to_str().unwrap().to_string();
+        
+        // Layers listed before the pad's own sample still land on top of it
+        let config = SampleConfig {
+            mappings: vec![
+                SampleMapping { group: 0, pad: 4, file: file(&clap), name: None, normalize: None, layer: 2, gain: Some(0.5), layer_mode: LayerMode::Stack, vel_min: Some(100), vel_max: None },
+                SampleMapping { group: 0, pad: 4, file: file(&clap), name: None, normalize: None, layer: 1, gain: None, layer_mode: LayerMode::Stack, vel_min: None, vel_max: None },
+                SampleMapping { group: 0, pad: 4, file: file(&snare), name: None, normalize: None, layer: 0, gain: None, layer_mode: LayerMode::RoundRobin, vel_min: None, vel_max: None },
+            ],
+        };
+        let config_path = dir.join("config.json");
+        fs::write(&config_path, serde_json::to_string(&config).unwrap()).unwrap();
+        
+        let mut bank = SampleBank::new();
+        bank.load_from_config(config_path.to_str().unwrap()).unwrap();
+        assert_eq!(bank.layer_count(0, 4), 3);
+        assert_eq!(bank.get_sample_path(0, 4), snare.to_str());
+        let gains: Vec<f32> = bank.get_layers(0, 4).iter().map(|layer| layer.gain).collect();
+        assert_eq!(gains, [1.0, 0.5]);
+        assert_eq!(bank.memory_usage().0, 3);
+        
+        bank.save_sample_config(config_path.to_str().unwrap()).unwrap();
+        let saved: SampleConfig = serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+        let layers: Vec<(usize, Option<f32>, LayerMode)> = saved.mappings.iter().map(|m| (m.layer, m.gain, m.layer_mode)).collect();
+        assert_eq!(layers, [(0, None, LayerMode::RoundRobin), (1, None, LayerMode::Stack), (2, Some(0.5), LayerMode::Stack)]);
+        assert!(!fs::read_to_string(&config_path).unwrap().contains("\"stack\""));
+        assert_eq!((saved.mappings[2].vel_min, saved.mappings[2].vel_max), (Some(100), Some(MAX_VELOCITY)));
+        assert_eq!(saved.mappings[1].vel_min, None);
+        
+        // The pad is full at four, and a new sample for the pad drops the old layers
+        bank.add_layer(0, 4, "extra", generate_kick(), 1.0).unwrap();
+        assert!(bank.add_layer(0, 4, "too many", generate_kick(), 1.0).is_err());
+        bank.load_sample(0, 4, snare.to_str().unwrap()).unwrap();
+        assert_eq!(bank.layer_count(0, 4), 1);
+        
+        // An empty pad takes its first layer as its own sample
+        assert_eq!(bank.add_layer(0, 5, "first", generate_kick(), 0.5).unwrap(), 0);
+        assert!(bank.add_layer(0, 5, "junk", vec![0; 64], 1.0).is_err());
+        assert_eq!(bank.layer_count(0, 5), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_layer_modes() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut bank = SampleBank::new();
+        let indices = |picked: Vec<(usize, f32)>| picked.into_iter().map(|(index, _)| index).collect::<Vec<_>>();
+        for name in ["snare", "snare-2", "snare-3"] {
+            bank.add_layer(0, 0, name, generate_kick(), 1.0).unwrap();
+        }
+        assert_eq!(indices(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng)), [0, 1, 2]);
+        
+        bank.set_layer_mode(0, 0, LayerMode::RoundRobin);
+        let hits: Vec<Vec<usize>> = (0..4).map(|_| indices(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng))).collect();
+        assert_eq!(hits, [[0], [1], [2], [0]]);
+        // Stopping the transport starts the cycle over
+        bank.reset_layer_positions();
+        assert_eq!(indices(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng)), [0]);
+        
+        // Random never plays the same layer twice running, and a seed repeats its choices
+        bank.set_layer_mode(0, 0, LayerMode::Random);
+        let random: Vec<usize> = (0..50).flat_map(|_| indices(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng))).collect();
+        assert!(random.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!((0..3).all(|layer| random.contains(&layer)));
+        let seeded = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10).map(|_| LayerMode::Random.pick(3, Some(0), &mut rng)[0]).collect::<Vec<_>>()
+        };
+        assert_eq!(seeded(9), seeded(9));
+        
+        // A pad with one sample plays it whatever the mode
+        bank.load_sample_data(0, 0, "single", generate_kick()).unwrap();
+        assert_eq!(indices(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng)), [0]);
+        assert_eq!(indices(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng)), [0]);
+        bank.remove_sample(0, 0);
+        assert_eq!(bank.get_layer_mode(0, 0), LayerMode::Stack);
+    }
+
+    #[test]
+    fn test_pick_by_velocity() {
+        let soft_hard = [(0, 63), (64, 127)];
+        assert_eq!(pick_by_velocity(&soft_hard, 0), [(0, 1.0)]);
+        assert_eq!(pick_by_velocity(&soft_hard, 63), [(0, 1.0)]);
+        assert_eq!(pick_by_velocity(&soft_hard, 64), [(1, 1.0)]);
+        assert_eq!(pick_by_velocity(&soft_hard, 127), [(1, 1.0)]);
+        
+        // Overlaps blend, evenly in the middle and mostly the deeper range near the edges
+        let overlapping = [(0, 80), (60, 127)];
+        assert_eq!(pick_by_velocity(&overlapping, 70), [(0, 0.5), (1, 0.5)]);
+        let edge = pick_by_velocity(&overlapping, 61);
+        assert!(edge[0].1 > 0.9 && (edge[0].1 + edge[1].1 - 1.0).abs() < 0.001);
+        
+        // Gaps fall back to the nearest range, the lower one on a tie
+        let gapped = [(0, 30), (90, 127)];
+        assert_eq!(pick_by_velocity(&gapped, 50), [(0, 1.0)]);
+        assert_eq!(pick_by_velocity(&gapped, 80), [(1, 1.0)]);
+        assert_eq!(pick_by_velocity(&gapped, 60), [(0, 1.0)]);
+        assert_eq!(pick_by_velocity(&[], 60), []);
+    }
+
+    #[test]
+    fn test_velocity_switched_layers() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut bank = SampleBank::new();
+        bank.add_layer(0, 0, "soft", generate_kick(), 1.0).unwrap();
+        bank.add_layer(0, 0, "hard", generate_kick(), 1.0).unwrap();
+        bank.set_layer_mode(0, 0, LayerMode::RoundRobin);
+        assert_eq!(bank.next_layers(0, 0, 10, &mut rng), [(0, 1.0)]);
+        assert_eq!(bank.next_layers(0, 0, 10, &mut rng), [(1, 1.0)]);
+        
+        // One range switches the pad over from its layer mode; the other sample plays anywhere
+        bank.set_velocity_range(0, 0, 1, Some((100, MAX_VELOCITY)));
+        assert_eq!(bank.next_layers(0, 0, 10, &mut rng), [(0, 1.0)]);
+        assert_eq!(bank.next_layers(0, 0, 10, &mut rng), [(0, 1.0)]);
+        assert_eq!(bank.next_layers(0, 0, MAX_VELOCITY, &mut rng).len(), 2);
+        
+        bank.set_velocity_range(0, 0, 0, Some((0, 99)));
+        assert_eq!(bank.next_layers(0, 0, 99, &mut rng), [(0, 1.0)]);
+        assert_eq!(bank.next_layers(0, 0, 100, &mut rng), [(1, 1.0)]);
+        
+        // Mappings give the ranges, filling in whichever end is left out
+        let mapping = |vel_min, vel_max| SampleMapping {
+            group: 0, pad: 0, file: String::new(), name: None, normalize: None,
+            layer: 0, gain: None, layer_mode: LayerMode::Stack, vel_min, vel_max,
+        };
+        assert_eq!(mapping(None, None).velocity_range(), None);
+        assert_eq!(mapping(Some(64), None).velocity_range(), Some((64, MAX_VELOCITY)));
+        assert_eq!(mapping(None, Some(200)).velocity_range(), Some((0, MAX_VELOCITY)));
+        assert_eq!(mapping(Some(90), Some(20)).velocity_range(), Some((20, 90)));
+        
+        // A new sample for the pad starts without ranges
+        bank.load_sample_data(0, 0, "kick", generate_kick()).unwrap();
+        assert_eq!(bank.get_velocity_range(0, 0, 0), None);
+    }
+
+    #[test]
+    fn test_load_sample_data_in_memory() {
+        let mut bank = SampleBank::new();
+        let kick = bank.generate_simple_kick();
+        
+        bank.load_sample_data(2, 7, "resample-1", kick).unwrap();
+        assert!(bank.has_sample(2, 7));
+        assert_eq!(bank.get_sample_name(2, 7), Some("resample-1"));
+        
+        // Anything that isn't audio is still rejected
+        assert!(bank.load_sample_data(2, 8, "junk", vec![0; 64]).is_err());
+        assert!(!bank.has_sample(2, 8));
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let padded = |header: &[u8]| {
+            let mut bytes = header.to_vec();
+            bytes.resize(16, 0);
+            bytes
+        };
+        assert_eq!(detect_format(&generate_kick()), Some(AudioFormat::Wav));
+        assert_eq!(detect_format(&padded(b"ID3\x04")), Some(AudioFormat::Mp3));
+        assert_eq!(detect_format(&padded(&[0xFF, 0xFB, 0x90])), Some(AudioFormat::Mp3)); // Bare MPEG frame
+        assert_eq!(detect_format(&padded(b"fLaC")), Some(AudioFormat::Flac));
+        assert_eq!(detect_format(&padded(b"OggS")), Some(AudioFormat::Ogg));
+        
+        assert_eq!(detect_format(&padded(b"RIFF\0\0\0\0AVI ")), None);
+        assert_eq!(detect_format(b"not an audio file at all"), None);
+        assert_eq!(detect_format(b"OggS"), None); // Too short to trust
+    }
+
+    #[test]
+    fn test_mp3_frame_sync() {
+        let frame = |first: u8, second: u8| {
+            let mut bytes = vec![0; 16];
+            bytes[0] = first;
+            bytes[1] = second;
+            bytes
+        };
+        // MPEG-1 Layer III, the usual first frame of a tagless MP3
+        assert_eq!(AudioFormat::from_bytes(&frame(0xFF, 0xFB)), Some(AudioFormat::Mp3));
+        assert_eq!(AudioFormat::from_bytes(&frame(0xFF, 0xE0)), Some(AudioFormat::Mp3));
+        // One sync bit short, or no leading 0xFF, is not a frame
+        assert_eq!(AudioFormat::from_bytes(&frame(0xFF, 0xDF)), None);
+        assert_eq!(AudioFormat::from_bytes(&frame(0xFE, 0xFB)), None);
+        assert_eq!(AudioFormat::Mp3.label(), "MP3");
+    }
+
+    #[test]
+    fn test_sample_format_is_stored() {
+        let mut bank = SampleBank::new();
+        bank.load_sample_data(0, 3, "kick", generate_kick()).unwrap();
+        assert_eq!(bank.get_sample_format(0, 3), Some(AudioFormat::Wav));
+        assert_eq!(bank.get_sample_format(0, 4), None);
+        
+        let err = bank.load_sample_data(0, 4, "exe", b"MZ\x90\x00 not audio".to_vec()).unwrap_err();
+        assert!(err.to_string().contains("starts with 4D 5A 90 00"), "{}", err);
+        
+        bank.remove_sample(0, 3);
+        assert_eq!(bank.get_sample_format(0, 3), None);
+    }
+
+    // A WAV header with noise where the fmt and data chunks should be
+    fn garbled_wav() -> Vec<u8> {
+        let mut bytes = b"RIFF\x24\x10\x00\x00WAVE".to_vec();
+        bytes.extend((0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8));
+        bytes
+    }
+
+    #[test]
+    fn test_load_decodes_samples() {
+        let dir = std::env::temp_dir().join("ko2_decode_on_load");
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("kick.wav");
+        let garbled = dir.join("garbled.wav");
+        let empty = dir.join("empty.wav");
+        fs::write(&good, generate_kick()).unwrap();
+        fs::write(&garbled, garbled_wav()).unwrap();
+        fs::write(&empty, b"").unwrap();
+        
+        let mut bank = SampleBank::new();
+        bank.load_sample(0, 0, good.to_str().unwrap()).unwrap();
+        let info = bank.get_sample_info(0, 0).unwrap();
+        assert_eq!((info.channels, info.sample_rate), (1, BUS_SAMPLE_RATE));
+        assert_eq!(info.bytes, generate_kick().len());
+        assert_eq!(bank.get_sample_path(0, 0), good.to_str());
+        assert_eq!(info.duration.map(|duration| duration.as_millis()), Some(500));
+        
+        // The header passes the format check, so only decoding catches it
+        assert_eq!(detect_format(&garbled_wav()), Some(AudioFormat::Wav));
+        let err = bank.load_sample(0, 1, garbled.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, AppError::Sample(_)));
+        assert!(err.to_string().contains("Failed to decode sample"), "{}", err);
+        assert!(bank.load_sample(0, 2, empty.to_str().unwrap()).is_err());
+        assert!(!bank.has_sample(0, 1) && !bank.has_sample(0, 2));
+        assert_eq!(bank.get_sample_info(0, 1), None);
+        
+        bank.remove_sample(0, 0);
+        assert_eq!(bank.get_sample_info(0, 0), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // A 24-bit mono WAV holding values, which the i16 path would round to zero at the bottom
+    fn wav_24bit(values: &[i32]) -> Vec<u8> {
+        let spec = hound::WavSpec { channels: 1, sample_rate: 48000, bits_per_sample: 24, sample_format: hound::SampleFormat::Int };
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+        for &value in values {
+            writer.write_sample(value).unwrap();
+        }
+        writer.finalize().unwrap();
+        buffer.into_inner()
+    }
+
+    // A WAV whose fmt chunk names codec with no data after it
+    fn wav_with_codec(tag: u16, bits: u16) -> Vec<u8> {
+        let mut bytes = b"RIFF\x24\x00\x00\x00WAVEfmt \x10\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&48000u32.to_le_bytes());
+        bytes.extend_from_slice(&(48000 * bits as u32 / 8).to_le_bytes());
+        bytes.extend_from_slice(&(bits / 8).to_le_bytes());
+        bytes.extend_from_slice(&bits.to_le_bytes());
+        bytes.extend_from_slice(b"data\x00\x00\x00\x00");
+        bytes
+    }
+
+    // A big-endian AIFF, or an AIFF-C naming codec, of interleaved 16- or 24-bit values
+    fn aiff(channels: u16, bits: u16, sample_rate: u32, values: &[i32], codec: Option<(&[u8; 4], &str)>) -> Vec<u8> {
+        let mut comm = Vec::new();
+        comm.extend_from_slice(&channels.to_be_bytes());
+        comm.extend_from_slice(&(values.len() as u32 / channels as u32).to_be_bytes());
+        comm.extend_from_slice(&bits.to_be_bytes());
+        let shift = (sample_rate as u64).leading_zeros();
+        comm.extend_from_slice(&((16383 + 63 - shift) as u16).to_be_bytes());
+        comm.extend_from_slice(&((sample_rate as u64) << shift).to_be_bytes());
+        if let Some((code, name)) = codec {
+            comm.extend_from_slice(code);
+            comm.push(name.len() as u8);
+            comm.extend_from_slice(name.as_bytes());
+            if comm.len() % 2 == 1 {
+                comm.push(0);
+            }
+        }
+        
+        let width = bits as usize / 8;
+        let mut sound = vec![0u8; 8];
+        for &value in values {
+            sound.extend_from_slice(&value.to_be_bytes()[4 - width..]);
+        }
+        
+        let mut bytes = b"FORM\x00\x00\x00\x00".to_vec();
+        bytes.extend_from_slice(if codec.is_some() { b"AIFC" } else { b"AIFF" });
+        for (id, body) in [(b"COMM", comm), (b"SSND", sound)] {
+            bytes.extend_from_slice(id);
+            bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&body);
+        }
+        let size = (bytes.len() - 8) as u32;
+        bytes[4..8].copy_from_slice(&size.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_loads_24bit_and_float_wav() {
+        let values: Vec<i32> = (0..4800).map(|i| (i - 2400) * 3491).chain([1, -1, 8_388_607, -8_388_608]).collect();
+        let mut bank = SampleBank::new();
+        bank.load_sample_data(0, 0, "deep", wav_24bit(&values)).unwrap();
+        let info = bank.get_sample_info(0, 0).unwrap();
+        assert_eq!((info.channels, info.sample_rate), (1, 48000));
+        assert_eq!(info.duration.map(|duration| duration.as_micros()), Some(100_083));
+        
+        // Every bit survives, down to the smallest step
+        let decoded = decode_sample(bank.get_sample(0, 0).unwrap()).unwrap();
+        assert_eq!(decoded.data.len(), values.len());
+        for (&sample, &value) in decoded.data.iter().zip(&values) {
+            assert_eq!(sample, value as f32 / 8_388_608.0);
+        }
+        assert_eq!(decoded.data[4800], 1.0 / 8_388_608.0);
+        assert_eq!(decoded.data[4803], -1.0);
+        
+        let spec = hound::WavSpec { channels: 2, sample_rate: 44100, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+        for sample in [0.25f32, -0.5, 1.5, 1e-6] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        let decoded = decode_sample(buffer.get_ref()).unwrap();
+        assert_eq!((decoded.channels, decoded.sample_rate), (2, 44100));
+        assert_eq!(decoded.data, vec![0.25, -0.5, 1.5, 1e-6]);
+        
+        // Recognized as WAV, but not a codec the decoder plays
+        let err = probe_sample(&wav_with_codec(0x0002, 4)).unwrap_err();
+        assert_eq!(err.to_string(), "Sample error: Unsupported WAV codec: MS ADPCM");
+        let err = probe_sample(&wav_with_codec(0x0003, 64)).unwrap_err();
+        assert!(err.to_string().contains("Unsupported WAV codec: 64-bit float"), "{}", err);
+    }
+
+    #[test]
+    fn test_loads_aiff() {
+        let stereo: Vec<i32> = (0..882).map(|i| (i * 37 % 65536) - 32768).collect();
+        let bytes = aiff(2, 16, 44100, &stereo, None);
+        assert_eq!(detect_format(&bytes), Some(AudioFormat::Aiff));
+        assert_eq!(AudioFormat::Aiff.label(), "AIFF");
+        
+        let dir = std::env::temp_dir().join("ko2_aiff_samples");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snare.aif");
+        fs::write(&path, &bytes).unwrap();
+        assert!(is_audio_file(&path) && is_audio_file(Path::new("pad.AIFF")));
+        
+        let mut bank = SampleBank::new();
+        bank.load_sample(0, 0, path.to_str().unwrap()).unwrap();
+        assert_eq!(bank.get_sample_format(0, 0), Some(AudioFormat::Aiff));
+        let info = bank.get_sample_info(0, 0).unwrap();
+        assert_eq!((info.channels, info.sample_rate), (2, 44100));
+        assert_eq!(info.duration.map(|duration| duration.as_millis()), Some(10));
+        let decoded = decode_sample(bank.get_sample(0, 0).unwrap()).unwrap();
+        let expected: Vec<f32> = stereo.iter().map(|&value| value as f32 / 32768.0).collect();
+        assert_eq!(decoded.data, expected);
+        let _ = fs::remove_dir_all(&dir);
+        
+        // 24-bit, and the byte-swapped AIFF-C flavour
+        let deep = [1, -1, 8_388_607, -8_388_608];
+        let decoded = decode_sample(&aiff(1, 24, 96000, &deep, None)).unwrap();
+        assert_eq!(decoded.sample_rate, 96000);
+        assert_eq!(decoded.data, vec![1.0 / 8_388_608.0, -1.0 / 8_388_608.0, 8_388_607.0 / 8_388_608.0, -1.0]);
+        let mut swapped = aiff(1, 16, 48000, &[0x0102, -2], Some((b"sowt", "")));
+        let sound = swapped.len() - 4;
+        swapped[sound..].copy_from_slice(&[0x02, 0x01, 0xFE, 0xFF]);
+        assert_eq!(decode_sample(&swapped).unwrap().data, vec![258.0 / 32768.0, -2.0 / 32768.0]);
+        
+        let err = probe_sample(&aiff(1, 16, 48000, &[0; 64], Some((b"ima4", "IMA 4:1")))).unwrap_err();
+        assert_eq!(err.to_string(), "Sample error: Unsupported AIFF-C codec: ima4 (IMA 4:1)");
+    }
+
+    #[test]
+    fn test_validate_sample_config() {
+        let dir = std::env::temp_dir().join("ko2_validate_samples");
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("kick.wav");
+        let bad = dir.join("notes.txt");
+        let garbled = dir.join("garbled.wav");
+        fs::write(&good, generate_kick()).unwrap();
+        fs::write(&bad, "definitely not audio").unwrap();
+        fs::write(&garbled, garbled_wav()).unwrap();
+        
+        let file = |path: &Path| path.to_str().unwrap().to_string();
+        let config = SampleConfig {
+            mappings: vec![
+                SampleMapping { group: 0, pad: 0, file: file(&good), name: None, normalize: None, layer: 0, gain: None, layer_mode: LayerMode::Stack, vel_min: None, vel_max: None },
+                SampleMapping { group: 0, pad: 1, file: file(&bad), name: None, normalize: None, layer: 0, gain: None, layer_mode: LayerMode::Stack, vel_min: None, vel_max: None },
+                SampleMapping { group: 1, pad: 2, file: file(&dir.join("gone.wav")), name: None, normalize: None, layer: 0, gain: None, layer_mode: LayerMode::Stack, vel_min: None, vel_max: None },
+                SampleMapping { group: MAX_GROUPS, pad: 0, file: file(&good), name: None, normalize: None, layer: 0, gain: None, layer_mode: LayerMode::Stack, vel_min: None, vel_max: None },
+                SampleMapping { group: 0, pad: 3, file: file(&garbled), name: None, normalize: None, layer: 0, gain: None, layer_mode: LayerMode::Stack, vel_min: None, vel_max: None },
+            ],
+        };
+        let config_path = dir.join("config.json");
+        fs::write(&config_path, serde_json::to_string(&config).unwrap()).unwrap();
+        
+        let checks = validate_sample_config(config_path.to_str().unwrap()).unwrap();
+        let statuses: Vec<_> = checks.iter().map(|check| check.status).collect();
+        assert_eq!(statuses, [
+            SampleStatus::Ok(AudioFormat::Wav),
+            SampleStatus::BadFormat,
+            SampleStatus::Missing,
+            SampleStatus::BadPad,
+            SampleStatus::BadFormat,
+        ]);
+        assert_eq!(checks[1].path, file(&bad));
+        
+        // Relative files live under the samples directory
+        assert_eq!(mapping_path("drums/kick.wav"), "samples/drums/kick.wav");
+        assert!(validate_sample_config(file(&dir.join("missing.json")).as_str()).is_err());
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_decode_sample() {
+        let bank = SampleBank::new();
+        let decoded = decode_sample(&bank.generate_simple_kick()).unwrap();
+        
+        assert_eq!(decoded.channels, 1);
+        assert_eq!(decoded.sample_rate, BUS_SAMPLE_RATE);
+        assert_eq!(decoded.frames(), BUS_SAMPLE_RATE as usize / 2); // 500ms
+        
+        let (left, right) = decoded.stereo_frame(100);
+        assert_eq!(left, right);
+    }
+
     #[test]
     fn test_create_placeholder_names() {
         let mut bank = SampleBank::new();
         bank.create_placeholder_names();
         
         // Check that placeholder names were created
-        assert_eq!(bank.get_sample_name(0, 0), Some("Kick"));
-        assert_eq!(bank.get_sample_name(0, 1), Some("Snare"));
-        assert_eq!(bank.get_sample_name(1, 0), Some("Bass1"));
-        assert_eq!(bank.get_sample_name(2, 0), Some("Lead1"));
-        assert_eq!(bank.get_sample_name(3, 0), Some("Vocal1"));
+        assert_eq!(bank.get_sample_name(0, 0), Some("Pad 01"));
+        assert_eq!(bank.get_sample_name(1, 15), Some("Pad 16"));
+        assert_eq!(bank.get_sample_name(MAX_GROUPS - 1, 0), Some("Pad 01"));
+    }
+
+    #[test]
+    fn test_trimmed_sub_slice() {
+        // Ten stereo frames whose left channel holds the frame index
+        let sample = DecodedSample {
+            channels: 2,
+            sample_rate: 44100,
+            data: (0..10).flat_map(|frame| [frame as f32, -(frame as f32)]).collect(),
+        };
+
+        let trimmed = sample.trimmed(0.2, 0.5);
+        assert_eq!(trimmed.frames(), 3);
+        assert_eq!(trimmed.data, vec![2.0, -2.0, 3.0, -3.0, 4.0, -4.0]);
+        assert_eq!(trimmed.sample_rate, 44100);
+
+        // Fractions between frames round outwards
+        assert_eq!(sample.trimmed(0.25, 0.51).data[0], 2.0);
+        assert_eq!(sample.trimmed(0.25, 0.51).frames(), 4);
+
+        assert_eq!(sample.trimmed(0.0, 1.0).data, sample.data);
+        assert_eq!(sample.trimmed(0.9, 2.0).data, vec![9.0, -9.0]);
+        assert_eq!(sample.trimmed(0.7, 0.3).frames(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_trim_offsets() {
+        let mut bank = SampleBank::new();
+        assert_eq!(bank.get_trim(0, 0), (0.0, 1.0));
+
+        assert_eq!(bank.adjust_start_offset(0, 0, 0.25), 0.25);
+        assert_eq!(bank.adjust_end_offset(0, 0, -0.25), 0.75);
+
+        // The points can't cross or leave 0..1
+        assert_eq!(bank.adjust_start_offset(0, 0, 1.0), 0.75 - MIN_TRIM_LENGTH);
+        assert_eq!(bank.adjust_end_offset(0, 0, -1.0), 0.75);
+        assert_eq!(bank.adjust_start_offset(0, 0, -5.0), 0.0);
+        assert_eq!(bank.adjust_end_offset(0, 0, 5.0), 1.0);
+
+        // A new sample on the pad starts untrimmed
+        bank.adjust_start_offset(0, 0, 0.5);
+        bank.load_sample_data(0, 0, "kick", bank.generate_simple_kick()).unwrap();
+        assert_eq!(bank.get_trim(0, 0), (0.0, 1.0));
+    }
+}
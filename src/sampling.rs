@@ -0,0 +1,296 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SizedSample};
+use ringbuf::traits::{Consumer, Split};
+use ringbuf::{HeapProd, HeapRb};
+use crate::error::{AppError, Result};
+
+pub const RECORDED_SAMPLES_DIR: &str = "samples/recorded";
+
+// Leave a little headroom so normalized recordings don't sit right at full scale
+const NORMALIZE_PEAK: f32 = 0.95;
+
+// Output the ring holds between the audio thread and the writer, which drains it every poll
+const CAPTURE_RING_SECS: usize = 4;
+const WRITER_POLL: Duration = Duration::from_millis(10);
+
+pub struct RecordedAudio {
+    pub samples: Vec<f32>, // Mono, -1.0..=1.0
+    pub sample_rate: u32,
+}
+
+pub struct InputRecorder {
+    stream: cpal::Stream,
+    buffer: Arc<Mutex<Vec<f32>>>,
+    level: Arc<AtomicU32>, // f32 bits of the most recent block peak
+    sample_rate: u32,
+    device_name: String,
+}
+
+impl InputRecorder {
+    pub fn start(device_name: Option<&str>) -> Result<Self> {
+        let host = cpal::default_host();
+
+        let device = match device_name {
+            Some(name) => host
+                .input_devices()
+                .map_err(|e| AppError::Audio(format!("Failed to enumerate input devices: {}", e)))?
+                .find(|device| device.name().map(|n| n == name).unwrap_or(false))
+                .ok_or_else(|| AppError::Audio(format!("Input device not found: {}", name)))?,
+            None => host
+                .default_input_device()
+                .ok_or_else(|| AppError::Audio("No audio input device available".to_string()))?,
+        };
+
+        let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
+        let supported = device
+            .default_input_config()
+            .map_err(|e| AppError::Audio(format!("Input device {} has no usable config: {}", device_name, e)))?;
+
+        let config = supported.config();
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let level = Arc::new(AtomicU32::new(0));
+
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => build_input_stream::<f32>(&device, &config, &buffer, &level),
+            cpal::SampleFormat::I16 => build_input_stream::<i16>(&device, &config, &buffer, &level),
+            cpal::SampleFormat::U16 => build_input_stream::<u16>(&device, &config, &buffer, &level),
+            cpal::SampleFormat::I32 => build_input_stream::<i32>(&device, &config, &buffer, &level),
+            format => {
+                return Err(AppError::Audio(format!("Unsupported input sample format: {:?}", format)));
+            }
+        }
+        .map_err(|e| AppError::Audio(format!("Failed to open input stream: {}", e)))?;
+
+        stream
+            .play()
+            .map_err(|e| AppError::Audio(format!("Failed to start input stream: {}", e)))?;
+
+        Ok(Self {
+            stream,
+            buffer,
+            level,
+            sample_rate: config.sample_rate.0,
+            device_name,
+        })
+    }
+
+    pub fn level(&self) -> f32 {
+        f32::from_bits(self.level.load(Ordering::Relaxed))
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn stop(self) -> RecordedAudio {
+        // Dropping the stream ends the callback before we take the buffer
+        drop(self.stream);
+
+        let samples = std::mem::take(&mut *self.buffer.lock().unwrap());
+        RecordedAudio {
+            samples,
+            sample_rate: self.sample_rate,
+        }
+    }
+}
+
+fn build_input_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    buffer: &Arc<Mutex<Vec<f32>>>,
+    level: &Arc<AtomicU32>,
+) -> std::result::Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let buffer = Arc::clone(buffer);
+    let level = Arc::clone(level);
+
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let block: Vec<f32> = data.iter().map(|&s| s.to_sample::<f32>()).collect();
+            let mono = downmix_to_mono(&block, channels);
+
+            level.store(peak(&mono).to_bits(), Ordering::Relaxed);
+            if let Ok(mut buffer) = buffer.lock() {
+                buffer.extend_from_slice(&mono);
+            }
+        },
+        |e| eprintln!("Input stream error: {}", e),
+        None,
+    )
+}
+
+pub fn list_input_devices() -> Result<Vec<String>> {
+    let host = cpal::default_host();
+    let devices = host
+        .input_devices()
+        .map_err(|e| AppError::Audio(format!("Failed to enumerate input devices: {}", e)))?;
+
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+pub fn downmix_to_mono(interleaved: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return interleaved.to_vec();
+    }
+
+    interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |max, s| max.max(s.abs()))
+}
+
+pub fn normalize(samples: &mut [f32]) {
+    let peak = peak(samples);
+
+    // Leave silence alone rather than amplifying the noise floor to full scale
+    if peak < 1e-4 {
+        return;
+    }
+
+    let gain = NORMALIZE_PEAK / peak;
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+pub fn write_wav(path: &str, samples: &[f32], channels: u16, sample_rate: u32) -> Result<()> {
+    let mut writer = create_wav(path, channels, sample_rate)?;
+    for &sample in samples {
+        write_wav_sample(&mut writer, path, sample)?;
+    }
+
+    writer
+        .finalize()
+        .map_err(|e| AppError::Sample(format!("Failed to finalize {}: {}", path, e)))
+}
+
+// 16-bit, which is what every write here produces
+fn create_wav(path: &str, channels: u16, sample_rate: u32) -> Result<hound::WavWriter<std::io::BufWriter<std::fs::File>>> {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    hound::WavWriter::create(path, spec).map_err(|e| AppError::Sample(format!("Failed to create {}: {}", path, e)))
+}
+
+fn write_wav_sample<W: std::io::Write + std::io::Seek>(writer: &mut hound::WavWriter<W>, path: &str, sample: f32) -> Result<()> {
+    let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+    writer
+        .write_sample(value)
+        .map_err(|e| AppError::Sample(format!("Failed to write {}: {}", path, e)))
+}
+
+/// Streams the live output to a WAV on its own thread. The audio thread only copies each
+/// block into a ring, and stopping only waits for what is still in it.
+pub struct OutputRecorder {
+    finished: Arc<AtomicBool>,
+    writer: JoinHandle<Result<usize>>, // Frames written
+}
+
+impl OutputRecorder {
+    // The file is created here, so a bad path fails at the start rather than at the end.
+    // The producer is for the bus; after max_secs the rest of the output is left out
+    pub fn start(path: &str, channels: u16, sample_rate: u32, max_secs: u32) -> Result<(Self, HeapProd<f32>)> {
+        let mut writer = create_wav(path, channels, sample_rate)?;
+        let samples_per_sec = sample_rate as usize * channels as usize;
+        let (producer, mut consumer) = HeapRb::<f32>::new(CAPTURE_RING_SECS * samples_per_sec).split();
+        let max_samples = max_secs as usize * samples_per_sec;
+        let finished = Arc::new(AtomicBool::new(false));
+        let done = Arc::clone(&finished);
+        let path = path.to_string();
+        
+        let writer = thread::spawn(move || {
+            let mut chunk = vec![0.0; 4096];
+            let mut written = 0;
+            loop {
+                // Read before draining, so everything pushed before the finish gets written
+                let finishing = done.load(Ordering::Acquire);
+                let count = consumer.pop_slice(&mut chunk);
+                for &sample in &chunk[..count.min(max_samples - written)] {
+                    write_wav_sample(&mut writer, &path, sample)?;
+                }
+                written = (written + count).min(max_samples);
+                if count == 0 {
+                    if finishing {
+                        break;
+                    }
+                    thread::sleep(WRITER_POLL);
+                }
+            }
+            writer
+                .finalize()
+                .map_err(|e| AppError::Sample(format!("Failed to finalize {}: {}", path, e)))?;
+            Ok(written / channels as usize)
+        });
+        Ok((Self { finished, writer }, producer))
+    }
+
+    // Call once the bus has dropped the producer. Writes what's left and closes the file;
+    // returns the frames recorded
+    pub fn finish(self) -> Result<usize> {
+        self.finished.store(true, Ordering::Release);
+        self.writer
+            .join()
+            .unwrap_or_else(|_| Err(AppError::Sample("Output recording stopped unexpectedly".to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downmix_to_mono() {
+        let stereo = [1.0, 0.0, 0.5, 0.5, -1.0, 1.0];
+        assert_eq!(downmix_to_mono(&stereo, 2), vec![0.5, 0.5, 0.0]);
+
+        let mono = [0.1, 0.2];
+        assert_eq!(downmix_to_mono(&mono, 1), vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut samples = vec![0.1, -0.25, 0.2];
+        normalize(&mut samples);
+        assert!((peak(&samples) - NORMALIZE_PEAK).abs() < 0.0001);
+        // Relative levels are preserved
+        assert!((samples[0] / samples[2] - 0.5).abs() < 0.0001);
+
+        // Silence is left untouched
+        let mut silence = vec![0.0; 8];
+        normalize(&mut silence);
+        assert!(silence.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_write_wav() {
+        let path = std::env::temp_dir().join("ko2_sampling_test.wav");
+        let path = path.to_str().unwrap();
+        let samples: Vec<f32> = (0..441).map(|i| (i as f32 / 441.0) - 0.5).collect();
+
+        write_wav(path, &samples, 1, 44100).unwrap();
+
+        let reader = hound::WavReader::open(path).unwrap();
+        assert_eq!(reader.spec().sample_rate, 44100);
+        assert_eq!(reader.spec().channels, 1);
+        assert_eq!(reader.len(), 441);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
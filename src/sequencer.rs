@@ -1,36 +1,475 @@
 use std::collections::HashMap;
+use std::ops::Range;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::error::{AppError, Result};
+use crate::state::{MAX_TEMPO, MIN_TEMPO};
 
 pub const STEPS_PER_PATTERN: usize = 16;
+pub const STEPS_PER_BEAT: usize = 4; // Sixteenth-note steps, so a pattern is one bar of 4/4
+pub const BEATS_PER_BAR: usize = 4;
+pub const STEP_RESOLUTIONS: [usize; 5] = [2, 3, 4, 6, 8]; // Steps per beat a pattern can use; 3 and 6 are triplets
+pub const MAX_BEATS_PER_BAR: usize = 16;
 pub const MAX_PATTERNS: usize = 99;
-pub const MAX_GROUPS: usize = 4;
-pub const PADS_PER_GROUP: usize = 16;
+pub const DEFAULT_GROUPS: usize = 4; // DRUMS, BASS, LEAD and VOCAL
+pub const MAX_GROUPS: usize = 8; // Most groups [layout] can ask for; per-group tables are this big
+const _: () = assert!(MAX_GROUPS >= DEFAULT_GROUPS);
+pub const PADS_PER_GROUP: usize = 16; // Pads in a group unless [layout] asks for more; one bank of keys
+pub const MAX_PADS: usize = 32;
+pub const PAD_COUNTS: [usize; 2] = [PADS_PER_GROUP, MAX_PADS]; // What [layout] pads_per_group accepts
+pub const STEPS_PER_PAGE: usize = 16; // Columns the pattern view shows at once
 
-#[derive(Debug, Clone)]
+pub const MAX_VELOCITY: u8 = 127;
+pub const DEFAULT_VELOCITY: u8 = MAX_VELOCITY; // Unedited steps play at the old fixed level
+pub const DEFAULT_PROBABILITY: u8 = 100;
+pub const MAX_MICRO_OFFSET_MS: i16 = 250; // Stored limit; playback narrows it to half a step
+pub const MAX_RATCHET: u8 = 4; // Plays squeezed into one step
+pub const DEFAULT_MORPH_BARS: usize = 4;
+pub const MAX_MORPH_BARS: usize = 64;
+
+/// A pattern's time signature: beats in a bar, each split into steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub beats_per_bar: usize,
+    pub steps_per_beat: usize,
+}
+
+impl Default for Signature {
+    fn default() -> Self {
+        Self { beats_per_bar: BEATS_PER_BAR, steps_per_beat: STEPS_PER_BEAT }
+    }
+}
+
+impl Signature {
+    // None unless the resolution is one of STEP_RESOLUTIONS and the bar has 1 to MAX_BEATS_PER_BAR beats
+    pub fn new(beats_per_bar: usize, steps_per_beat: usize) -> Option<Self> {
+        let valid = (1..=MAX_BEATS_PER_BAR).contains(&beats_per_bar) && STEP_RESOLUTIONS.contains(&steps_per_beat);
+        valid.then_some(Self { beats_per_bar, steps_per_beat })
+    }
+
+    pub fn bar_steps(&self) -> usize {
+        self.beats_per_bar * self.steps_per_beat
+    }
+
+    // Beats are quarter notes, so "3/4 · 8ths" has six steps
+    pub fn label(&self) -> String {
+        let resolution = match self.steps_per_beat {
+            2 => "8ths",
+            3 => "8th triplets",
+            4 => "16ths",
+            6 => "16th triplets",
+            _ => "32nds",
+        };
+        format!("{}/4 · {}", self.beats_per_bar, resolution)
+    }
+}
+
+// A mixer move recorded into a group's pattern, made again each time its step plays
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Automation {
+    Mute(bool),
+    Volume(f32),
+}
+
+/// One group's pattern: a hit grid with a row per pad plus per-step velocity, probability,
+/// mute, micro-timing, ratchet and accent.
+///
+/// Every row always spans [`Pattern::length`] steps; the setters keep it that way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Pattern {
-    pub steps: Vec<Vec<bool>>, // steps[pad][step]
-    pub length: usize,
+    pub(crate) steps: Vec<Vec<bool>>, // steps[pad][step]
+    pub(crate) velocities: Vec<Vec<u8>>,    // velocities[pad][step], 1..=MAX_VELOCITY
+    pub(crate) probabilities: Vec<Vec<u8>>, // probabilities[pad][step], percent chance to fire
+    pub(crate) muted: Vec<Vec<bool>>,       // muted[pad][step], kept but skipped on playback
+    pub(crate) micro_offsets: Vec<Vec<i16>>, // micro_offsets[pad][step], ms early (-) or late (+)
+    #[serde(default)]
+    pub(crate) ratchets: Vec<Vec<u8>>,       // ratchets[pad][step], plays per step from 1 to MAX_RATCHET
+    pub(crate) accent: Vec<bool>,            // accent[step], boosts every pad that plays on the step
+    pub(crate) length: usize,
+    #[serde(default)]
+    pub(crate) tempo: Option<f32>, // Adopted when the pattern starts playing; None follows the global tempo
+    #[serde(default)]
+    pub(crate) signature: Signature,
+    #[serde(default)]
+    pub(crate) automation: Vec<(usize, Automation)>, // (step, move) sorted by step, one of each kind per step
 }
 
 impl Pattern {
     pub fn new() -> Self {
+        Self::with_length(STEPS_PER_PATTERN)
+    }
+
+    pub fn with_length(length: usize) -> Self {
+        Self::with_size(PADS_PER_GROUP, length)
+    }
+
+    pub fn with_size(pads: usize, length: usize) -> Self {
         Self {
-            steps: vec![vec![false; STEPS_PER_PATTERN]; PADS_PER_GROUP],
-            length: STEPS_PER_PATTERN,
+            steps: vec![vec![false; length]; pads],
+            velocities: vec![vec![DEFAULT_VELOCITY; length]; pads],
+            probabilities: vec![vec![DEFAULT_PROBABILITY; length]; pads],
+            muted: vec![vec![false; length]; pads],
+            micro_offsets: vec![vec![0; length]; pads],
+            ratchets: vec![vec![1; length]; pads],
+            accent: vec![false; length],
+            length,
+            tempo: None,
+            signature: Signature::default(),
+            automation: Vec::new(),
         }
     }
 
+    // Rows in the grid, one per pad
+    pub fn pads(&self) -> usize {
+        self.steps.len()
+    }
+
+    // Rows for new pads start empty; dropped pads lose their hits
+    pub fn set_pad_count(&mut self, pads: usize) {
+        let length = self.length;
+        self.steps.resize(pads, vec![false; length]);
+        self.velocities.resize(pads, vec![DEFAULT_VELOCITY; length]);
+        self.probabilities.resize(pads, vec![DEFAULT_PROBABILITY; length]);
+        self.muted.resize(pads, vec![false; length]);
+        self.micro_offsets.resize(pads, vec![0; length]);
+        self.ratchets.resize(pads, vec![1; length]);
+    }
+
     pub fn clear(&mut self) {
         for pad in &mut self.steps {
             pad.fill(false);
         }
+        for pad in &mut self.velocities {
+            pad.fill(DEFAULT_VELOCITY);
+        }
+        for pad in &mut self.probabilities {
+            pad.fill(DEFAULT_PROBABILITY);
+        }
+        for pad in &mut self.muted {
+            pad.fill(false);
+        }
+        for pad in &mut self.micro_offsets {
+            pad.fill(0);
+        }
+        for pad in &mut self.ratchets {
+            pad.fill(1);
+        }
+        self.accent.fill(false);
     }
 
     pub fn set_hit(&mut self, pad: usize, step: usize, value: bool) {
-        if pad < PADS_PER_GROUP && step < self.length {
+        if pad < self.pads() && step < self.length {
+            // A fresh hit starts from defaults rather than whatever an old one left behind
+            if value && !self.steps[pad][step] {
+                self.velocities[pad][step] = DEFAULT_VELOCITY;
+                self.probabilities[pad][step] = DEFAULT_PROBABILITY;
+                self.muted[pad][step] = false;
+                self.micro_offsets[pad][step] = 0;
+                self.ratchets[pad][step] = 1;
+            }
             self.steps[pad][step] = value;
         }
     }
 
+    pub fn get_hit(&self, pad: usize, step: usize) -> bool {
+        self.steps.get(pad).and_then(|row| row.get(step)).copied().unwrap_or(false)
+    }
+
+    // Steps in the pattern, one bar of its signature unless made otherwise
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn get_velocity(&self, pad: usize, step: usize) -> u8 {
+        if pad < self.pads() && step < self.length {
+            self.velocities[pad][step]
+        } else {
+            DEFAULT_VELOCITY
+        }
+    }
+
+    pub fn set_velocity(&mut self, pad: usize, step: usize, velocity: u8) {
+        if pad < self.pads() && step < self.length {
+            self.velocities[pad][step] = velocity.clamp(1, MAX_VELOCITY);
+        }
+    }
+
+    pub fn get_probability(&self, pad: usize, step: usize) -> u8 {
+        if pad < self.pads() && step < self.length {
+            self.probabilities[pad][step]
+        } else {
+            DEFAULT_PROBABILITY
+        }
+    }
+
+    pub fn set_probability(&mut self, pad: usize, step: usize, probability: u8) {
+        if pad < self.pads() && step < self.length {
+            self.probabilities[pad][step] = probability.min(100);
+        }
+    }
+
+    pub fn get_micro_offset(&self, pad: usize, step: usize) -> i16 {
+        if pad < self.pads() && step < self.length {
+            self.micro_offsets[pad][step]
+        } else {
+            0
+        }
+    }
+
+    pub fn set_micro_offset(&mut self, pad: usize, step: usize, offset_ms: i16) {
+        if pad < self.pads() && step < self.length {
+            self.micro_offsets[pad][step] = offset_ms.clamp(-MAX_MICRO_OFFSET_MS, MAX_MICRO_OFFSET_MS);
+        }
+    }
+
+    pub fn get_ratchet(&self, pad: usize, step: usize) -> u8 {
+        if pad < self.pads() && step < self.length {
+            self.ratchets[pad][step]
+        } else {
+            1
+        }
+    }
+
+    pub fn set_ratchet(&mut self, pad: usize, step: usize, ratchet: u8) {
+        if pad < self.pads() && step < self.length {
+            self.ratchets[pad][step] = ratchet.clamp(1, MAX_RATCHET);
+        }
+    }
+
+    // Files saved before ratchets existed load without them, which means a single play everywhere
+    pub fn fill_missing_ratchets(&mut self) {
+        if self.ratchets.is_empty() {
+            self.ratchets = vec![vec![1; self.length]; self.pads()];
+        }
+    }
+
+    pub fn is_accented(&self, step: usize) -> bool {
+        self.accent.get(step).copied().unwrap_or(false)
+    }
+
+    pub fn set_accent(&mut self, step: usize, accented: bool) {
+        if let Some(accent) = self.accent.get_mut(step) {
+            *accent = accented;
+        }
+    }
+
+    pub fn get_signature(&self) -> Signature {
+        self.signature
+    }
+
+    // The pattern becomes one bar of the new signature. Rescaling moves each step to the
+    // nearest one at the same point in the bar; otherwise steps keep their numbers. Steps
+    // that end up past the new length are dropped
+    pub fn set_signature(&mut self, signature: Signature, rescale: bool) {
+        let length = signature.bar_steps();
+        let mut resized = Pattern::with_size(self.pads(), length);
+        resized.tempo = self.tempo;
+        resized.signature = signature;
+        let remap = |step: usize| {
+            if rescale {
+                (step as f32 * signature.steps_per_beat as f32 / self.signature.steps_per_beat as f32).round() as usize
+            } else {
+                step
+            }
+        };
+        for &(step, automation) in &self.automation {
+            resized.record_automation(remap(step), automation);
+        }
+        for step in 0..self.length {
+            let to = remap(step);
+            if to >= length {
+                continue;
+            }
+            for pad in 0..self.pads() {
+                if self.steps[pad][step] {
+                    resized.copy_step_from(self, pad, step, to);
+                }
+            }
+            if self.accent[step] {
+                resized.accent[to] = true;
+            }
+        }
+        *self = resized;
+    }
+
+    pub fn get_tempo(&self) -> Option<f32> {
+        self.tempo
+    }
+
+    // Replaces a move of the same kind already on the step
+    pub fn record_automation(&mut self, step: usize, automation: Automation) {
+        if step >= self.length {
+            return;
+        }
+        let kind = std::mem::discriminant(&automation);
+        self.automation.retain(|&(at, existing)| at != step || std::mem::discriminant(&existing) != kind);
+        let index = self.automation.partition_point(|&(at, _)| at <= step);
+        self.automation.insert(index, (step, automation));
+    }
+
+    pub fn automation_at_step(&self, step: usize) -> impl Iterator<Item = Automation> + '_ {
+        self.automation.iter().filter(move |&&(at, _)| at == step).map(|&(_, automation)| automation)
+    }
+
+    // The (mute, volume) the lane has left in place by `step`, carried round from the end of
+    // the previous pass; None for a kind it never sets
+    pub fn automated_levels(&self, step: usize) -> (Option<bool>, Option<f32>) {
+        let (before, after) = self.automation.split_at(self.automation.partition_point(|&(at, _)| at <= step));
+        let (mut muted, mut volume) = (None, None);
+        for &(_, automation) in after.iter().chain(before) {
+            match automation {
+                Automation::Mute(value) => muted = Some(value),
+                Automation::Volume(value) => volume = Some(value),
+            }
+        }
+        (muted, volume)
+    }
+
+    pub fn has_automation(&self) -> bool {
+        !self.automation.is_empty()
+    }
+
+    pub fn clear_automation(&mut self) {
+        self.automation.clear();
+    }
+
+    pub fn set_tempo(&mut self, tempo: Option<f32>) {
+        self.tempo = tempo;
+    }
+
+    pub fn is_muted(&self, pad: usize, step: usize) -> bool {
+        pad < self.pads() && step < self.length && self.muted[pad][step]
+    }
+
+    pub fn set_muted(&mut self, pad: usize, step: usize, muted: bool) {
+        if pad < self.pads() && step < self.length {
+            self.muted[pad][step] = muted;
+        }
+    }
+
+    // Jitters every hit on the given pads around its current level and timing, staying
+    // within the valid velocity range and the given offset so repeats can't run away.
+    // Returns how many hits were touched.
+    pub fn humanize(&mut self, pads: Range<usize>, amount: Humanize, max_offset_ms: i16, rng: &mut impl Rng) -> usize {
+        let velocity_range = amount.velocity.clamp(0.0, 1.0);
+        let timing_range = amount.timing_ms.clamp(0, max_offset_ms.max(0));
+        let limit = max_offset_ms.clamp(0, MAX_MICRO_OFFSET_MS);
+        let mut touched = 0;
+        
+        for pad in pads.start.min(self.pads())..pads.end.min(self.pads()) {
+            for step in 0..self.length {
+                if !self.steps[pad][step] {
+                    continue;
+                }
+                
+                let velocity = self.velocities[pad][step] as f32;
+                let jitter = rng.gen_range(-velocity_range..=velocity_range);
+                self.set_velocity(pad, step, (velocity * (1.0 + jitter)).round().clamp(1.0, MAX_VELOCITY as f32) as u8);
+                
+                let offset = self.micro_offsets[pad][step] + rng.gen_range(-timing_range..=timing_range);
+                self.set_micro_offset(pad, step, offset.clamp(-limit, limit));
+                touched += 1;
+            }
+        }
+        touched
+    }
+
+    // Drops every hit off the every-`keep_every`-steps grid, except that a row with none
+    // on it keeps its first hit so no sound vanishes. Surviving hits keep their velocity,
+    // probability and timing; accents go from steps left without any hit. Returns the
+    // number of hits removed.
+    pub fn thin(&mut self, keep_every: usize) -> usize {
+        let keep_every = keep_every.max(1);
+        let mut removed = 0;
+        for pad in 0..self.pads() {
+            let first = self.steps[pad][..self.length].iter().position(|&hit| hit);
+            let on_grid = (0..self.length).any(|step| self.steps[pad][step] && step.is_multiple_of(keep_every));
+            for step in 0..self.length {
+                let keep = step.is_multiple_of(keep_every) || (!on_grid && first == Some(step));
+                if self.steps[pad][step] && !keep {
+                    self.set_hit(pad, step, false);
+                    removed += 1;
+                }
+            }
+        }
+        for step in 0..self.length {
+            if self.steps.iter().all(|row| !row[step]) {
+                self.set_accent(step, false);
+            }
+        }
+        removed
+    }
+
+    // Moves everything stored for one pad's row, so a remapped row keeps its per-step edits
+    fn copy_row_from(&mut self, source: &Pattern, from: usize, to: usize) {
+        self.steps[to] = source.steps[from].clone();
+        self.velocities[to] = source.velocities[from].clone();
+        self.probabilities[to] = source.probabilities[from].clone();
+        self.muted[to] = source.muted[from].clone();
+        self.micro_offsets[to] = source.micro_offsets[from].clone();
+        self.ratchets[to] = source.ratchets[from].clone();
+    }
+
+    // Whether a pad's step holds the same hit, with the same settings, as one in another pattern
+    fn step_matches(&self, other: &Pattern, pad: usize, step: usize, other_step: usize) -> bool {
+        self.steps[pad][step] == other.steps[pad][other_step]
+            && (!self.steps[pad][step]
+                || (self.velocities[pad][step] == other.velocities[pad][other_step]
+                    && self.probabilities[pad][step] == other.probabilities[pad][other_step]
+                    && self.muted[pad][step] == other.muted[pad][other_step]
+                    && self.micro_offsets[pad][step] == other.micro_offsets[pad][other_step]
+                    && self.ratchets[pad][step] == other.ratchets[pad][other_step]))
+    }
+
+    fn copy_step_from(&mut self, source: &Pattern, pad: usize, from: usize, to: usize) {
+        self.steps[pad][to] = source.steps[pad][from];
+        self.velocities[pad][to] = source.velocities[pad][from];
+        self.probabilities[pad][to] = source.probabilities[pad][from];
+        self.muted[pad][to] = source.muted[pad][from];
+        self.micro_offsets[pad][to] = source.micro_offsets[pad][from];
+        self.ratchets[pad][to] = source.ratchets[pad][from];
+    }
+
+    // Hits that actually sound: muted steps are skipped and steps below 100% only fire on some passes
+    pub fn roll_hits_at_step(&self, step: usize, rng: &mut impl Rng) -> Vec<usize> {
+        self.get_hits_at_step(step)
+            .into_iter()
+            .filter(|&pad| !self.muted[pad][step])
+            .filter(|&pad| {
+                let probability = self.probabilities[pad][step];
+                probability >= 100 || rng.gen_range(0..100) < probability
+            })
+            .collect()
+    }
+
+    // Shorter patterns loop within the 16-step clock
+    pub fn wrap_step(&self, step: usize) -> usize {
+        step % self.length.max(1)
+    }
+
+    // Every per-step grid has a row per pad and a column per step, as loaded files must
+    pub fn is_well_formed(&self) -> bool {
+        fn fits<T>(grid: &[Vec<T>], pads: usize, length: usize) -> bool {
+            grid.len() == pads && grid.iter().all(|row| row.len() == length)
+        }
+        let pads = self.pads();
+        PAD_COUNTS.contains(&pads)
+            && self.length > 0
+            && fits(&self.steps, pads, self.length)
+            && fits(&self.velocities, pads, self.length)
+            && fits(&self.probabilities, pads, self.length)
+            && fits(&self.muted, pads, self.length)
+            && fits(&self.micro_offsets, pads, self.length)
+            && fits(&self.ratchets, pads, self.length)
+            && self.accent.len() == self.length
+            && self.automation.iter().all(|&(step, _)| step < self.length)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.iter().all(|pad| !pad.contains(&true))
+    }
+
     pub fn get_hits_at_step(&self, step: usize) -> Vec<usize> {
         let mut hits = Vec::new();
         if step < self.length {
@@ -44,10 +483,225 @@ impl Pattern {
     }
 }
 
+impl Default for Pattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// On-disk form of a single pattern; per-step values are optional so plain grids import too
+#[derive(Debug, Serialize, Deserialize)]
+struct PatternExport {
+    steps: Vec<Vec<bool>>,
+    #[serde(default)]
+    velocities: Option<Vec<Vec<u8>>>,
+    #[serde(default)]
+    probabilities: Option<Vec<Vec<u8>>>,
+    #[serde(default)]
+    muted: Option<Vec<Vec<bool>>>,
+    #[serde(default)]
+    micro_offsets: Option<Vec<Vec<i16>>>,
+    #[serde(default)]
+    ratchets: Option<Vec<Vec<u8>>>,
+    #[serde(default)]
+    accent: Option<Vec<bool>>,
+    #[serde(default)]
+    length: Option<usize>, // Files from before signatures are one 16-step bar
+    #[serde(default)]
+    signature: Signature,
+    #[serde(default)]
+    tempo: Option<f32>,
+}
+
+fn check_dimensions<T>(name: &str, grid: &[Vec<T>], pads: usize, length: usize) -> Result<()> {
+    if grid.len() != pads || grid.iter().any(|row| row.len() != length) {
+        return Err(AppError::Pattern(format!(
+            "{} must be {} pads x {} steps",
+            name, pads, length
+        )));
+    }
+    Ok(())
+}
+
+// The pattern view pages through long patterns STEPS_PER_PAGE steps at a time
+pub fn page_count(length: usize) -> usize {
+    length.div_ceil(STEPS_PER_PAGE).max(1)
+}
+
+pub fn page_of_step(step: usize) -> usize {
+    step / STEPS_PER_PAGE
+}
+
+pub fn page_range(page: usize, length: usize) -> Range<usize> {
+    let start = (page * STEPS_PER_PAGE).min(length);
+    start..(start + STEPS_PER_PAGE).min(length)
+}
+
+// First step of a `window`-wide view that keeps `step` in sight, moving `scroll` as
+// little as possible. The window stays full when the pattern is long enough
+pub fn scroll_to_show(scroll: usize, step: usize, window: usize, length: usize) -> usize {
+    let window = window.max(1);
+    let scroll = if step < scroll {
+        step
+    } else if step >= scroll + window {
+        step + 1 - window
+    } else {
+        scroll
+    };
+    scroll.min(length.saturating_sub(window))
+}
+
+// Step for a hit `offset` steps into the bar. Strength 1.0 snaps to the nearest step,
+// 0.0 keeps it on the step that was playing; hits just before the bar wrap to its end.
+pub fn quantize_offset(offset: f64, strength: f32, length: usize) -> usize {
+    let threshold = 1.0 - 0.5 * strength.clamp(0.0, 1.0) as f64;
+    let base = offset.floor();
+    let step = if offset - base >= threshold { base + 1.0 } else { base };
+    (step as i64).rem_euclid(length.max(1) as i64) as usize
+}
+
+pub fn velocity_gain(velocity: u8) -> f32 {
+    velocity.min(MAX_VELOCITY) as f32 / MAX_VELOCITY as f32
+}
+
+// Accents add a fixed boost on top of the velocity, but never past full level
+pub fn accented_gain(gain: f32, accented: bool, boost: f32) -> f32 {
+    if accented {
+        (gain + boost.max(0.0)).min(1.0)
+    } else {
+        gain
+    }
+}
+
+// Nudges stay under half a step so a hit can never reach its neighbour's slot
+pub fn max_micro_offset_ms(tempo: f32) -> i16 {
+    let step_ms = 15_000.0 / tempo.max(1.0);
+    (step_ms / 2.0 - 1.0).clamp(0.0, MAX_MICRO_OFFSET_MS as f32) as i16
+}
+
+// How far humanize may move each hit: velocity as a fraction of its level, timing in ms either way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Humanize {
+    pub velocity: f32,
+    pub timing_ms: i16,
+}
+
+pub const DEFAULT_HUMANIZE: Humanize = Humanize { velocity: 0.1, timing_ms: 3 };
+
+// How pasted rows land on the destination's pads
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemapMode {
+    Identity,           // Pad N stays pad N
+    Compact(Vec<usize>), // Rows with hits move, in order, onto these pads (the ones with samples)
+}
+
+// How fast a group's steps run against the global 16th-note clock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClockDivision {
+    Half,
+    #[default]
+    Normal,
+    Double,
+}
+
+impl ClockDivision {
+    pub fn next(self) -> Self {
+        match self {
+            ClockDivision::Normal => ClockDivision::Double,
+            ClockDivision::Double => ClockDivision::Half,
+            ClockDivision::Half => ClockDivision::Normal,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ClockDivision::Half => "×½",
+            ClockDivision::Normal => "×1",
+            ClockDivision::Double => "×2",
+        }
+    }
+
+    // Length of one of the group's steps, in global steps
+    pub fn step_scale(self) -> f32 {
+        match self {
+            ClockDivision::Half => 2.0,
+            ClockDivision::Normal => 1.0,
+            ClockDivision::Double => 0.5,
+        }
+    }
+}
+
+// A group's steps that start on a tick, halfway through it, and on the next one
+fn group_steps(division: ClockDivision, tick: usize, current_step: usize, next_step: usize) -> (Option<usize>, Option<usize>, Option<usize>) {
+    match division {
+        ClockDivision::Normal => (Some(current_step), None, Some(next_step)),
+        ClockDivision::Half if tick.is_multiple_of(2) => (Some(tick / 2), None, None),
+        ClockDivision::Half => (None, None, Some(tick / 2 + 1)),
+        ClockDivision::Double => (Some(tick * 2), Some(tick * 2 + 1), Some(tick * 2 + 2)),
+    }
+}
+
+// A hit returned by a tick. Early hits (negative offsets) come one tick ahead with `ahead`
+// set, so they can be queued before their step actually arrives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepHit {
+    pub group: usize,
+    pub pad: usize,
+    pub step: usize, // Step within the hit's pattern
+    pub offset_ms: i16,
+    pub ahead: bool, // Belongs to the step after this tick
+    pub sub_step: bool, // Starts half a step after this tick, from a double-time group
+}
+
+// A group crossfading into another pattern a bar at a time; see Sequencer::start_morph
+struct Morph {
+    from: usize,
+    to: usize,
+    bars: usize,
+    bar: usize, // Bar lines passed since it started
+    seed: u64, // Every bar rolls the same numbers, so steps taken from `to` stay taken
+    pattern: Pattern, // What the group plays this bar
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audition {
+    pub group: usize,
+    pub pattern_idx: usize,
+    pub step: usize,
+}
+
+/// Plays the patterns of every group the layout has, each with its own active pattern, clock
+/// division and chain.
 pub struct Sequencer {
     patterns: HashMap<(usize, usize), Pattern>, // (group, pattern_idx) -> Pattern
     current_step: usize,
     active_patterns: [usize; MAX_GROUPS], // Pattern index for each group
+    audition: Option<Audition>, // Preview position, independent of current_step
+    lookahead: bool, // The previous tick already returned this step's early hits
+    divisions: [ClockDivision; MAX_GROUPS],
+    tick_count: usize, // Ticks since the position was reset; divided groups count from it
+    group_chain: [Vec<usize>; MAX_GROUPS], // Patterns each group steps through, one per bar
+    chain_positions: [usize; MAX_GROUPS],  // Index into group_chain of the pattern playing now
+    morphs: [Option<Morph>; MAX_GROUPS], // Played in place of the active pattern while set
+    groups: usize, // Groups that play; tables above are sized for MAX_GROUPS
+    pads: usize, // Rows in every pattern, one of PAD_COUNTS
+}
+
+// Bjorklund's algorithm: pair the remainders onto the hits until at most one is left,
+// so E(3, 8) comes out as x..x..x.
+pub fn euclidean_rhythm(pulses: usize, steps: usize) -> Vec<bool> {
+    let pulses = pulses.min(steps);
+    let mut heads: Vec<Vec<bool>> = vec![vec![true]; pulses];
+    let mut tails: Vec<Vec<bool>> = vec![vec![false]; steps - pulses];
+    while tails.len() > 1 && !heads.is_empty() {
+        let pairs = heads.len().min(tails.len());
+        let remainder = if heads.len() > pairs { heads.split_off(pairs) } else { tails.split_off(pairs) };
+        for (head, tail) in heads.iter_mut().zip(tails) {
+            head.extend(tail);
+        }
+        tails = remainder;
+    }
+    heads.into_iter().chain(tails).flatten().collect()
 }
 
 impl Sequencer {
@@ -56,72 +710,737 @@ impl Sequencer {
             patterns: HashMap::new(),
             current_step: 0,
             active_patterns: [0; MAX_GROUPS],
+            audition: None,
+            lookahead: false,
+            divisions: [ClockDivision::Normal; MAX_GROUPS],
+            tick_count: 0,
+            group_chain: Default::default(),
+            chain_positions: [0; MAX_GROUPS],
+            morphs: Default::default(),
+            groups: DEFAULT_GROUPS,
+            pads: PADS_PER_GROUP,
+        }
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.groups
+    }
+
+    // Patterns of groups beyond the count are kept, just not played
+    pub fn set_group_count(&mut self, groups: usize) {
+        self.groups = groups.clamp(1, MAX_GROUPS);
+    }
+
+    pub fn pad_count(&self) -> usize {
+        self.pads
+    }
+
+    // Stored patterns grow or shrink with it; rows past a smaller count are dropped
+    pub fn set_pad_count(&mut self, pads: usize) {
+        if !PAD_COUNTS.contains(&pads) {
+            return;
+        }
+        self.pads = pads;
+        for pattern in self.patterns.values_mut() {
+            pattern.set_pad_count(pads);
         }
     }
 
-    pub fn tick(&mut self, _tempo: u32) -> Vec<(usize, usize)> {
+    pub fn tick(&mut self, tempo: f32) -> Vec<StepHit> {
         let mut all_hits = Vec::new();
         let current_step = self.current_step;
+        let next_step = (current_step + 1) % self.clock_signature().bar_steps();
+        let lookahead = self.lookahead;
+        let tick = self.tick_count;
+        let mut rng = rand::thread_rng();
         
         // Collect hits from all active patterns
-        for group in 0..MAX_GROUPS {
+        for group in 0..self.groups {
+            let division = self.divisions[group];
+            let max_offset = max_micro_offset_ms(tempo / division.step_scale());
             let pattern_idx = self.active_patterns[group];
-            let pattern = self.get_or_create_pattern(group, pattern_idx);
+            let pattern = match &self.morphs[group] {
+                Some(morph) => &morph.pattern,
+                None => self.get_or_create_pattern(group, pattern_idx),
+            };
+            
+            let (on_tick, sub_step, on_next_tick) = group_steps(division, tick, current_step, next_step);
             
-            let hits = pattern.get_hits_at_step(current_step);
-            for pad in hits {
-                all_hits.push((group, pad));
+            if let Some(step) = on_tick.map(|step| pattern.wrap_step(step)) {
+                for pad in pattern.roll_hits_at_step(step, &mut rng) {
+                    let offset_ms = pattern.get_micro_offset(pad, step).clamp(-max_offset, max_offset);
+                    // Early hits were already returned by the previous tick, unless there wasn't one
+                    if offset_ms < 0 && lookahead {
+                        continue;
+                    }
+                    all_hits.push(StepHit { group, pad, step, offset_ms: offset_ms.max(0), ahead: false, sub_step: false });
+                }
+            }
+            
+            // Nudges are under a quarter step here, so even early ones stay inside this tick
+            if let Some(step) = sub_step.map(|step| pattern.wrap_step(step)) {
+                for pad in pattern.roll_hits_at_step(step, &mut rng) {
+                    let offset_ms = pattern.get_micro_offset(pad, step).clamp(-max_offset, max_offset);
+                    all_hits.push(StepHit { group, pad, step, offset_ms, ahead: false, sub_step: true });
+                }
+            }
+            
+            if let Some(step) = on_next_tick.map(|step| pattern.wrap_step(step)) {
+                for pad in pattern.roll_hits_at_step(step, &mut rng) {
+                    let offset_ms = pattern.get_micro_offset(pad, step).clamp(-max_offset, max_offset);
+                    if offset_ms < 0 {
+                        all_hits.push(StepHit { group, pad, step, offset_ms, ahead: true, sub_step: false });
+                    }
+                }
             }
         }
         
         // Advance step
-        self.current_step = (self.current_step + 1) % STEPS_PER_PATTERN;
+        self.current_step = next_step;
+        self.tick_count = tick.wrapping_add(1);
+        self.lookahead = true;
+        if next_step == 0 {
+            self.advance_chains();
+            self.advance_morphs();
+        }
         
         all_hits
     }
 
+    // Recorded mixer moves on the steps each group starts this tick; read before tick() moves on
+    // (group, move, sub_step): sub_step moves belong halfway through the tick, from a
+    // double-time group, and are for the caller to hold back like sub-step hits
+    pub fn automation_on_tick(&self) -> Vec<(usize, Automation, bool)> {
+        let next_step = (self.current_step + 1) % self.clock_signature().bar_steps();
+        let mut moves = Vec::new();
+        for group in 0..self.groups {
+            let Some(pattern) = self.get_playing_pattern(group).filter(|pattern| pattern.has_automation()) else {
+                continue;
+            };
+            let (on_tick, sub_step, _) = group_steps(self.divisions[group], self.tick_count, self.current_step, next_step);
+            for (step, sub_step) in on_tick.map(|step| (step, false)).into_iter().chain(sub_step.map(|step| (step, true))) {
+                moves.extend(pattern.automation_at_step(pattern.wrap_step(step)).map(|automation| (group, automation, sub_step)));
+            }
+        }
+        moves
+    }
+
+    pub fn record_automation(&mut self, group: usize, pattern_idx: usize, step: usize, automation: Automation) {
+        if group < MAX_GROUPS && pattern_idx < MAX_PATTERNS {
+            self.get_or_create_pattern_mut(group, pattern_idx).record_automation(step, automation);
+        }
+    }
+
+    pub fn clear_automation(&mut self, group: usize, pattern_idx: usize) {
+        if let Some(pattern) = self.patterns.get_mut(&(group, pattern_idx)) {
+            pattern.clear_automation();
+        }
+    }
+
+    // Chained groups move on to their next pattern at the bar line
+    fn advance_chains(&mut self) {
+        for group in 0..MAX_GROUPS {
+            let chain = &self.group_chain[group];
+            if chain.is_empty() {
+                continue;
+            }
+            let position = (self.chain_positions[group] + 1) % chain.len();
+            self.chain_positions[group] = position;
+            self.active_patterns[group] = chain[position];
+        }
+    }
+
+    // Each bar line takes more of the target's steps; after the last bar it's the active pattern
+    fn advance_morphs(&mut self) {
+        for group in 0..MAX_GROUPS {
+            let Some(mut morph) = self.morphs[group].take() else {
+                continue;
+            };
+            morph.bar += 1;
+            if morph.bar >= morph.bars {
+                self.active_patterns[group] = morph.to;
+                continue;
+            }
+            let progress = morph.bar as f32 / morph.bars as f32;
+            morph.pattern = self.morph(group, morph.from, morph.to, progress, &mut StdRng::seed_from_u64(morph.seed));
+            self.morphs[group] = Some(morph);
+        }
+    }
+
+    // Pattern `from` with each step that differs in `to` taken from `to` with chance `progress`,
+    // so 0.0 gives `from` and 1.0 gives `to`. Accents move over the same way. The result is
+    // `from`'s length, with a `to` of another length wrapped to fit
+    pub fn morph(&self, group: usize, from: usize, to: usize, progress: f32, rng: &mut impl Rng) -> Pattern {
+        let empty = Pattern::with_size(self.pads, STEPS_PER_PATTERN);
+        let source = self.patterns.get(&(group, to)).unwrap_or(&empty);
+        let mut morphed = self.patterns.get(&(group, from)).unwrap_or(&empty).clone();
+        for step in 0..morphed.length {
+            let source_step = source.wrap_step(step);
+            for pad in 0..morphed.pads().min(source.pads()) {
+                // Rolled whether or not the step differs, so a step's roll doesn't depend on the others
+                let roll: f32 = rng.gen();
+                if roll < progress && !morphed.step_matches(source, pad, step, source_step) {
+                    morphed.copy_step_from(source, pad, source_step, step);
+                }
+            }
+            let roll: f32 = rng.gen();
+            if roll < progress {
+                morphed.set_accent(step, source.is_accented(source_step));
+            }
+        }
+        morphed
+    }
+
+    // Moves the group from its active pattern to `to` over the next `bars` bar lines. The
+    // seed picks which steps go first
+    pub fn start_morph(&mut self, group: usize, to: usize, bars: usize, seed: u64) {
+        if group >= MAX_GROUPS || to >= MAX_PATTERNS {
+            return;
+        }
+        let from = self.active_patterns[group];
+        let pattern = self.morph(group, from, to, 0.0, &mut StdRng::seed_from_u64(seed));
+        self.morphs[group] = Some(Morph { from, to, bars: bars.clamp(1, MAX_MORPH_BARS), bar: 0, seed, pattern });
+    }
+
+    // The pattern being morphed into and how far along it is, from 0.0
+    pub fn get_morph(&self, group: usize) -> Option<(usize, f32)> {
+        let morph = self.morphs.get(group)?.as_ref()?;
+        Some((morph.to, morph.bar as f32 / morph.bars as f32))
+    }
+
+    // What the group plays: its morph this bar, or else its active pattern
+    pub fn get_playing_pattern(&self, group: usize) -> Option<&Pattern> {
+        match self.morphs.get(group)? {
+            Some(morph) => Some(&morph.pattern),
+            None => self.patterns.get(&(group, self.get_active_pattern(group))),
+        }
+    }
+
+    // Returns the chain's new length
+    pub fn append_to_chain(&mut self, group: usize, pattern_idx: usize) -> usize {
+        if group >= MAX_GROUPS || pattern_idx >= MAX_PATTERNS {
+            return 0;
+        }
+        self.group_chain[group].push(pattern_idx);
+        self.group_chain[group].len()
+    }
+
+    pub fn clear_chain(&mut self, group: usize) {
+        if let Some(chain) = self.group_chain.get_mut(group) {
+            chain.clear();
+            self.chain_positions[group] = 0;
+        }
+    }
+
+    pub fn get_chain(&self, group: usize) -> &[usize] {
+        self.group_chain.get(group).map_or(&[], Vec::as_slice)
+    }
+
+    // Index into the group's chain of the pattern playing now, None when it isn't chained
+    pub fn get_chain_position(&self, group: usize) -> Option<usize> {
+        (!self.get_chain(group).is_empty()).then(|| self.chain_positions[group])
+    }
+
+    // After a pause nothing was queued ahead, so the next tick plays its own early hits
+    pub fn clear_lookahead(&mut self) {
+        self.lookahead = false;
+    }
+
     pub fn record_hit(&mut self, group: usize, pattern_idx: usize, pad: usize) {
-        let current_step = self.current_step;
+        self.record_hit_at(group, pattern_idx, pad, self.current_step);
+    }
+
+    pub fn record_hit_at(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize) {
+        let pattern = self.get_or_create_pattern_mut(group, pattern_idx);
+        pattern.set_hit(pad, step, true);
+    }
+
+    // Returns whether the step now has a hit
+    pub fn toggle_step(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> bool {
+        let pattern = self.get_or_create_pattern_mut(group, pattern_idx);
+        let active = pattern.steps.get(pad).and_then(|steps| steps.get(step)).is_some_and(|&hit| !hit);
+        pattern.set_hit(pad, step, active);
+        active
+    }
+
+    // Sets or clears every step of one pad's row, within the pattern length
+    pub fn fill_pad_row(&mut self, group: usize, pattern_idx: usize, pad: usize, value: bool) {
+        if value {
+            self.set_pad_every_n(group, pattern_idx, pad, 1);
+        } else if let Some(pattern) = self.patterns.get_mut(&(group, pattern_idx)) {
+            for step in 0..pattern.length {
+                pattern.set_hit(pad, step, false);
+            }
+        }
+    }
+
+    // Puts a hit on every nth step of the row starting from step 1 and clears the rest,
+    // e.g. n = 4 for four-on-the-floor
+    pub fn set_pad_every_n(&mut self, group: usize, pattern_idx: usize, pad: usize, n: usize) {
+        if pad >= self.pads || n == 0 {
+            return;
+        }
+        
+        let pattern = self.get_or_create_pattern_mut(group, pattern_idx);
+        for step in 0..pattern.length {
+            pattern.set_hit(pad, step, step.is_multiple_of(n));
+        }
+    }
+
+    // Spreads `pulses` hits evenly over the row and turns it `rotation` steps later,
+    // replacing whatever the row held
+    pub fn euclidean(&mut self, group: usize, pattern_idx: usize, pad: usize, pulses: usize, rotation: usize) {
+        if pad >= self.pads {
+            return;
+        }
+        
         let pattern = self.get_or_create_pattern_mut(group, pattern_idx);
-        pattern.set_hit(pad, current_step, true);
+        let mut row = euclidean_rhythm(pulses, pattern.length);
+        if !row.is_empty() {
+            let shift = rotation % row.len();
+            row.rotate_right(shift);
+        }
+        for (step, hit) in row.into_iter().enumerate() {
+            pattern.set_hit(pad, step, hit);
+        }
     }
 
+    pub fn humanize(
+        &mut self,
+        group: usize,
+        pattern_idx: usize,
+        pads: Range<usize>,
+        amount: Humanize,
+        max_offset_ms: i16,
+        rng: &mut impl Rng,
+    ) -> usize {
+        self.patterns
+            .get_mut(&(group, pattern_idx))
+            .map_or(0, |pattern| pattern.humanize(pads, amount, max_offset_ms, rng))
+    }
+
+    pub fn thin_pattern(&mut self, group: usize, pattern_idx: usize, keep_every: usize) -> usize {
+        self.patterns.get_mut(&(group, pattern_idx)).map_or(0, |pattern| pattern.thin(keep_every))
+    }
+
+    // Pastes a copied pattern into any group's slot. Returns how many rows with hits
+    // were dropped because compacting ran out of destination pads.
+    pub fn paste_pattern(&mut self, group: usize, pattern_idx: usize, source: &Pattern, remap: RemapMode) -> usize {
+        if group >= MAX_GROUPS || pattern_idx >= MAX_PATTERNS {
+            return 0;
+        }
+        
+        let targets = match remap {
+            RemapMode::Identity => {
+                let mut pattern = source.clone();
+                pattern.set_pad_count(self.pads);
+                self.patterns.insert((group, pattern_idx), pattern);
+                return 0;
+            }
+            RemapMode::Compact(targets) => targets,
+        };
+        
+        // Only the rows move; everything that belongs to the whole pattern comes across as is
+        let mut pattern = Pattern::with_size(self.pads, source.length);
+        pattern.accent = source.accent.clone();
+        pattern.signature = source.signature;
+        pattern.tempo = source.tempo;
+        pattern.automation = source.automation.clone();
+        let used_rows: Vec<usize> = (0..source.pads()).filter(|&pad| source.steps[pad].contains(&true)).collect();
+        let targets: Vec<usize> = targets.into_iter().filter(|&pad| pad < self.pads).collect();
+        for (&from, &to) in used_rows.iter().zip(&targets) {
+            pattern.copy_row_from(source, from, to);
+        }
+        
+        self.patterns.insert((group, pattern_idx), pattern);
+        used_rows.len().saturating_sub(targets.len())
+    }
+
+    // Drops every pattern, e.g. before loading a project
+    pub fn clear_all_patterns(&mut self) {
+        self.patterns.clear();
+    }
+
+    // Puts back a copy taken earlier, e.g. to undo an edit
+    pub fn set_pattern(&mut self, group: usize, pattern_idx: usize, mut pattern: Pattern) {
+        if group < MAX_GROUPS && pattern_idx < MAX_PATTERNS {
+            pattern.set_pad_count(self.pads);
+            self.patterns.insert((group, pattern_idx), pattern);
+        }
+    }
+
+    // Empties the steps but keeps the length, signature and stored tempo
     pub fn clear_pattern(&mut self, group: usize, pattern_idx: usize) {
         if let Some(pattern) = self.patterns.get_mut(&(group, pattern_idx)) {
             pattern.clear();
         }
     }
 
+    // Back to a fresh pattern: one empty bar of 4/4 in 16ths with no stored tempo
+    pub fn reset_pattern(&mut self, group: usize, pattern_idx: usize) {
+        if let Some(pattern) = self.patterns.get_mut(&(group, pattern_idx)) {
+            *pattern = Pattern::with_size(self.pads, STEPS_PER_PATTERN);
+        }
+    }
+
+    #[cfg(test)]
     pub fn get_pattern_grid(&self, group: usize, pattern_idx: usize) -> Vec<Vec<bool>> {
         if let Some(pattern) = self.patterns.get(&(group, pattern_idx)) {
             pattern.steps.clone()
         } else {
-            vec![vec![false; STEPS_PER_PATTERN]; PADS_PER_GROUP]
+            vec![vec![false; STEPS_PER_PATTERN]; self.pads]
+        }
+    }
+
+    pub fn get_step_velocity(&self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> u8 {
+        self.patterns
+            .get(&(group, pattern_idx))
+            .map(|pattern| pattern.get_velocity(pad, step))
+            .unwrap_or(DEFAULT_VELOCITY)
+    }
+
+    pub fn get_step_probability(&self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> u8 {
+        self.patterns
+            .get(&(group, pattern_idx))
+            .map(|pattern| pattern.get_probability(pad, step))
+            .unwrap_or(DEFAULT_PROBABILITY)
+    }
+
+    // Step edits only apply to steps that have a hit; returns the new value if one was changed
+    pub fn adjust_step_velocity(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize, delta: i16) -> Option<u8> {
+        let pattern = self.patterns.get_mut(&(group, pattern_idx))?;
+        if !*pattern.steps.get(pad)?.get(step)? {
+            return None;
         }
+        
+        let velocity = (pattern.get_velocity(pad, step) as i16 + delta).clamp(1, MAX_VELOCITY as i16) as u8;
+        pattern.set_velocity(pad, step, velocity);
+        Some(velocity)
+    }
+
+    pub fn adjust_step_probability(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize, delta: i16) -> Option<u8> {
+        let pattern = self.patterns.get_mut(&(group, pattern_idx))?;
+        if !*pattern.steps.get(pad)?.get(step)? {
+            return None;
+        }
+        
+        let probability = (pattern.get_probability(pad, step) as i16 + delta).clamp(0, 100) as u8;
+        pattern.set_probability(pad, step, probability);
+        Some(probability)
+    }
+
+    pub fn nudge_step(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize, delta_ms: i16) -> Option<i16> {
+        let pattern = self.patterns.get_mut(&(group, pattern_idx))?;
+        if !*pattern.steps.get(pad)?.get(step)? {
+            return None;
+        }
+        
+        pattern.set_micro_offset(pad, step, pattern.get_micro_offset(pad, step).saturating_add(delta_ms));
+        Some(pattern.get_micro_offset(pad, step))
+    }
+
+    pub fn is_step_muted(&self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> bool {
+        self.patterns
+            .get(&(group, pattern_idx))
+            .is_some_and(|pattern| pattern.is_muted(pad, step))
+    }
+
+    pub fn get_step_ratchet(&self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> u8 {
+        self.patterns
+            .get(&(group, pattern_idx))
+            .map_or(1, |pattern| pattern.get_ratchet(pad, step))
+    }
+
+    // Steps through 1, 2, 3 and 4 plays per step; None when there's no hit to ratchet
+    pub fn cycle_ratchet(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> Option<u8> {
+        let pattern = self.patterns.get_mut(&(group, pattern_idx))?;
+        if !*pattern.steps.get(pad)?.get(step)? {
+            return None;
+        }
+        
+        let ratchet = pattern.get_ratchet(pad, step) % MAX_RATCHET + 1;
+        pattern.set_ratchet(pad, step, ratchet);
+        Some(ratchet)
+    }
+
+    // Returns the new mute state, or None if the step has no hit
+    pub fn toggle_step_mute(&mut self, group: usize, pattern_idx: usize, pad: usize, step: usize) -> Option<bool> {
+        let pattern = self.patterns.get_mut(&(group, pattern_idx))?;
+        if !*pattern.steps.get(pad)?.get(step)? {
+            return None;
+        }
+        
+        let muted = !pattern.is_muted(pad, step);
+        pattern.set_muted(pad, step, muted);
+        Some(muted)
     }
 
     pub fn get_current_step(&self) -> usize {
         self.current_step
     }
 
+    // Chained groups go back to the start of their chain too
     pub fn reset_position(&mut self) {
         self.current_step = 0;
+        self.tick_count = 0;
+        self.lookahead = false;
+        for group in 0..MAX_GROUPS {
+            self.chain_positions[group] = 0;
+            if let Some(&first) = self.group_chain[group].first() {
+                self.active_patterns[group] = first;
+            }
+        }
+    }
+
+    // The group's own step counter; divided groups run off the tick count instead of the bar
+    pub fn get_group_step(&self, group: usize) -> usize {
+        match self.get_division(group) {
+            ClockDivision::Normal => self.current_step,
+            division => (self.tick_count as f32 / division.step_scale()) as usize,
+        }
+    }
+
+    pub fn get_tick_count(&self) -> usize {
+        self.tick_count
     }
 
+    pub fn get_division(&self, group: usize) -> ClockDivision {
+        self.divisions.get(group).copied().unwrap_or_default()
+    }
+
+    pub fn set_division(&mut self, group: usize, division: ClockDivision) {
+        if let Some(slot) = self.divisions.get_mut(group) {
+            *slot = division;
+        }
+    }
+
+    pub fn cycle_division(&mut self, group: usize) -> ClockDivision {
+        let Some(division) = self.divisions.get_mut(group) else {
+            return ClockDivision::Normal;
+        };
+        *division = division.next();
+        *division
+    }
+
+    pub fn get_active_pattern(&self, group: usize) -> usize {
+        self.active_patterns.get(group).copied().unwrap_or(0)
+    }
+
+    pub fn get_pattern(&self, group: usize, pattern_idx: usize) -> Option<&Pattern> {
+        self.patterns.get(&(group, pattern_idx))
+    }
+
+    // Picking a pattern ends any morph the group was in
     pub fn set_active_pattern(&mut self, group: usize, pattern_idx: usize) {
         if group < MAX_GROUPS && pattern_idx < MAX_PATTERNS {
             self.active_patterns[group] = pattern_idx;
+            self.morphs[group] = None;
+        }
+    }
+
+    pub fn start_audition(&mut self, group: usize, pattern_idx: usize) {
+        if group < MAX_GROUPS && pattern_idx < MAX_PATTERNS {
+            self.audition = Some(Audition { group, pattern_idx, step: 0 });
         }
     }
 
+    pub fn stop_audition(&mut self) {
+        self.audition = None;
+    }
+
+    pub fn is_auditioning(&self) -> bool {
+        self.audition.is_some()
+    }
+
+    pub fn get_audition(&self) -> Option<&Audition> {
+        self.audition.as_ref()
+    }
+
+    pub fn tick_audition(&mut self) -> Vec<(usize, usize)> {
+        let Some(audition) = self.audition.as_mut() else {
+            return Vec::new();
+        };
+        
+        // Read-only lookup so previewing never creates or records into a pattern
+        let hits = self.patterns
+            .get(&(audition.group, audition.pattern_idx))
+            .map(|pattern| pattern.roll_hits_at_step(audition.step, &mut rand::thread_rng()))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pad| (audition.group, pad))
+            .collect();
+        
+        let length = self.patterns
+            .get(&(audition.group, audition.pattern_idx))
+            .map_or(STEPS_PER_PATTERN, |pattern| pattern.length);
+        audition.step = (audition.step + 1) % length.max(1);
+        
+        hits
+    }
+
+    pub fn export_pattern_json(&self, group: usize, pattern_idx: usize) -> String {
+        let pattern = self.patterns.get(&(group, pattern_idx)).cloned().unwrap_or_else(|| Pattern::with_size(self.pads, STEPS_PER_PATTERN));
+        let export = PatternExport {
+            steps: pattern.steps,
+            velocities: Some(pattern.velocities),
+            probabilities: Some(pattern.probabilities),
+            muted: Some(pattern.muted),
+            micro_offsets: Some(pattern.micro_offsets),
+            ratchets: Some(pattern.ratchets),
+            accent: Some(pattern.accent),
+            length: Some(pattern.length),
+            signature: pattern.signature,
+            tempo: pattern.tempo,
+        };
+        
+        serde_json::to_string_pretty(&export).unwrap_or_default()
+    }
+
+    pub fn import_pattern_json(&mut self, group: usize, pattern_idx: usize, json: &str) -> Result<()> {
+        if group >= MAX_GROUPS || pattern_idx >= MAX_PATTERNS {
+            return Err(AppError::Pattern(format!("No pattern slot {}/{}", group + 1, pattern_idx + 1)));
+        }
+        
+        let export: PatternExport = serde_json::from_str(json)
+            .map_err(|e| AppError::Pattern(format!("Invalid pattern JSON: {}", e)))?;
+        
+        // Validate everything before touching the existing pattern. A file from a setup
+        // with the other pad count still imports; it's resized below.
+        let pads = export.steps.len();
+        if !PAD_COUNTS.contains(&pads) {
+            return Err(AppError::Pattern(format!("steps must have {} or {} pads, found {}", PADS_PER_GROUP, MAX_PADS, pads)));
+        }
+        let signature = Signature::new(export.signature.beats_per_bar, export.signature.steps_per_beat)
+            .ok_or_else(|| AppError::Pattern(format!("Unsupported signature {}", export.signature.label())))?;
+        let length = export.length.unwrap_or(signature.bar_steps());
+        if length != signature.bar_steps() {
+            return Err(AppError::Pattern(format!("length must be {} steps for {}", signature.bar_steps(), signature.label())));
+        }
+        if export.tempo.is_some_and(|tempo| !(MIN_TEMPO..=MAX_TEMPO).contains(&tempo)) {
+            return Err(AppError::Pattern(format!("tempo must be between {} and {} BPM", MIN_TEMPO, MAX_TEMPO)));
+        }
+        check_dimensions("steps", &export.steps, pads, length)?;
+        if let Some(velocities) = &export.velocities {
+            check_dimensions("velocities", velocities, pads, length)?;
+        }
+        if let Some(probabilities) = &export.probabilities {
+            check_dimensions("probabilities", probabilities, pads, length)?;
+        }
+        if let Some(muted) = &export.muted {
+            check_dimensions("muted", muted, pads, length)?;
+        }
+        if let Some(micro_offsets) = &export.micro_offsets {
+            check_dimensions("micro_offsets", micro_offsets, pads, length)?;
+        }
+        if let Some(ratchets) = &export.ratchets {
+            check_dimensions("ratchets", ratchets, pads, length)?;
+        }
+        if export.accent.as_ref().is_some_and(|accent| accent.len() != length) {
+            return Err(AppError::Pattern(format!("accent must be {} steps", length)));
+        }
+        
+        let mut pattern = Pattern::with_size(pads, length);
+        pattern.signature = signature;
+        pattern.tempo = export.tempo;
+        pattern.steps = export.steps;
+        if let Some(muted) = export.muted {
+            pattern.muted = muted;
+        }
+        if let Some(accent) = export.accent {
+            pattern.accent = accent;
+        }
+        for pad in 0..pads {
+            for step in 0..length {
+                if let Some(velocities) = &export.velocities {
+                    pattern.set_velocity(pad, step, velocities[pad][step]);
+                }
+                if let Some(probabilities) = &export.probabilities {
+                    pattern.set_probability(pad, step, probabilities[pad][step]);
+                }
+                if let Some(micro_offsets) = &export.micro_offsets {
+                    pattern.set_micro_offset(pad, step, micro_offsets[pad][step]);
+                }
+                if let Some(ratchets) = &export.ratchets {
+                    pattern.set_ratchet(pad, step, ratchets[pad][step]);
+                }
+            }
+        }
+        pattern.set_pad_count(self.pads);
+        
+        self.patterns.insert((group, pattern_idx), pattern);
+        Ok(())
+    }
+
+    // Returns whether the step is now accented
+    pub fn toggle_accent(&mut self, group: usize, pattern_idx: usize, step: usize) -> bool {
+        let pattern = self.get_or_create_pattern_mut(group, pattern_idx);
+        let accented = !pattern.is_accented(step);
+        pattern.set_accent(step, accented);
+        pattern.is_accented(step)
+    }
+
+    pub fn set_pattern_signature(&mut self, group: usize, pattern_idx: usize, signature: Signature, rescale: bool) {
+        self.get_or_create_pattern_mut(group, pattern_idx).set_signature(signature, rescale);
+    }
+
+    // The signature the transport counts bars and steps in: the lowest group whose active
+    // pattern isn't in plain 4/4 sixteenths sets it
+    pub fn clock_signature(&self) -> Signature {
+        (0..self.groups)
+            .filter_map(|group| self.get_pattern(group, self.get_active_pattern(group)))
+            .map(Pattern::get_signature)
+            .find(|signature| *signature != Signature::default())
+            .unwrap_or_default()
+    }
+
+    pub fn set_pattern_tempo(&mut self, group: usize, pattern_idx: usize, tempo: Option<f32>) {
+        self.get_or_create_pattern_mut(group, pattern_idx).set_tempo(tempo);
+    }
+
+    // The tempo the active patterns ask for. When several groups' patterns store one, the
+    // lowest-numbered group wins, so the drums (group 1 by default) set the pace
+    pub fn pattern_tempo(&self) -> Option<f32> {
+        (0..self.groups).find_map(|group| self.get_pattern(group, self.get_active_pattern(group))?.get_tempo())
+    }
+
+    pub fn is_step_accented(&self, group: usize, pattern_idx: usize, step: usize) -> bool {
+        self.patterns.get(&(group, pattern_idx)).is_some_and(|pattern| pattern.is_accented(step))
+    }
+
+    // Slots that were never created count as empty, as do created ones without hits
+    pub fn pattern_is_empty(&self, group: usize, pattern_idx: usize) -> bool {
+        self.patterns.get(&(group, pattern_idx)).is_none_or(Pattern::is_empty)
+    }
+
+    // Lowest slot in the group with no hits, for branching off a copy
+    pub fn first_empty_pattern(&self, group: usize) -> Option<usize> {
+        if group >= MAX_GROUPS {
+            return None;
+        }
+        (0..MAX_PATTERNS).find(|&pattern_idx| self.pattern_is_empty(group, pattern_idx))
+    }
+
+    // (group, pattern_idx) of every pattern that has been created, in no particular order
+    pub fn pattern_keys(&self) -> impl Iterator<Item = &(usize, usize)> {
+        self.patterns.keys()
+    }
+
     fn get_or_create_pattern(&mut self, group: usize, pattern_idx: usize) -> &Pattern {
+        let pads = self.pads;
         self.patterns.entry((group, pattern_idx))
-            .or_insert_with(Pattern::new)
+            .or_insert_with(|| Pattern::with_size(pads, STEPS_PER_PATTERN))
     }
 
     fn get_or_create_pattern_mut(&mut self, group: usize, pattern_idx: usize) -> &mut Pattern {
+        let pads = self.pads;
         self.patterns.entry((group, pattern_idx))
-            .or_insert_with(Pattern::new)
+            .or_insert_with(|| Pattern::with_size(pads, STEPS_PER_PATTERN))
+    }
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -129,6 +1448,10 @@ impl Sequencer {
 mod tests {
     use super::*;
 
+    fn hit_pads(hits: Vec<StepHit>) -> Vec<(usize, usize)> {
+        hits.into_iter().map(|hit| (hit.group, hit.pad)).collect()
+    }
+
     #[test]
     fn test_pattern_creation() {
         let pattern = Pattern::new();
@@ -231,13 +1554,13 @@ mod tests {
         let mut sequencer = Sequencer::new();
         
         // Test empty sequencer
-        let hits = sequencer.tick(120);
+        let hits = sequencer.tick(120.0);
         assert!(hits.is_empty());
         assert_eq!(sequencer.current_step, 1);
         
         // Test wrap around
         sequencer.current_step = STEPS_PER_PATTERN - 1;
-        sequencer.tick(120);
+        sequencer.tick(120.0);
         assert_eq!(sequencer.current_step, 0);
     }
 
@@ -256,8 +1579,71 @@ mod tests {
         sequencer.current_step = 5;
         sequencer.record_hit(0, 0, 10);
         
-        let pattern_grid = sequencer.get_pattern_grid(0, 0);
-        assert!(pattern_grid[10][5]); // pad 10, step 5
+        let pattern_grid = sequencer.get_pattern_grid(0, 0);
+        assert!(pattern_grid[10][5]); // pad 10, step 5
+    }
+
+    #[test]
+    fn test_automation_lane() {
+        let mut pattern = Pattern::new();
+        assert_eq!(pattern.automated_levels(0), (None, None));
+        pattern.record_automation(8, Automation::Mute(false));
+        pattern.record_automation(4, Automation::Mute(true));
+        pattern.record_automation(4, Automation::Volume(0.5));
+        pattern.record_automation(4, Automation::Volume(0.6));
+        pattern.record_automation(16, Automation::Mute(true));
+        assert_eq!(pattern.automation, [(4, Automation::Mute(true)), (4, Automation::Volume(0.6)), (8, Automation::Mute(false))]);
+        
+        // Before the first move, the lane carries over from the end of the last pass
+        assert_eq!(pattern.automated_levels(0), (Some(false), Some(0.6)));
+        assert_eq!(pattern.automated_levels(5), (Some(true), Some(0.6)));
+        assert_eq!(pattern.automated_levels(15), (Some(false), Some(0.6)));
+        
+        // Moves follow their steps onto a new grid
+        pattern.set_signature(Signature::new(4, 2).unwrap(), true);
+        assert_eq!(pattern.automation, [(2, Automation::Mute(true)), (2, Automation::Volume(0.6)), (4, Automation::Mute(false))]);
+        assert!(pattern.is_well_formed());
+        
+        // The sequencer hands them out as their steps start
+        let mut sequencer = Sequencer::new();
+        sequencer.record_automation(1, 0, 2, Automation::Mute(true));
+        sequencer.record_automation(1, 0, 2, Automation::Volume(0.6));
+        sequencer.tick(120.0);
+        assert!(sequencer.automation_on_tick().is_empty());
+        sequencer.tick(120.0);
+        assert_eq!(sequencer.automation_on_tick(), [(1, Automation::Mute(true), false), (1, Automation::Volume(0.6), false)]);
+        sequencer.clear_automation(1, 0);
+        assert!(sequencer.automation_on_tick().is_empty());
+        
+        // A double-time group's odd steps fall halfway through a tick
+        sequencer.reset_position();
+        sequencer.cycle_division(2);
+        assert_eq!(sequencer.get_division(2), ClockDivision::Double);
+        sequencer.record_automation(2, 0, 1, Automation::Mute(true));
+        assert_eq!(sequencer.automation_on_tick(), [(2, Automation::Mute(true), true)]);
+    }
+
+    #[test]
+    fn test_clear_keeps_the_length_and_reset_restores_it() {
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern_signature(0, 0, Signature::new(3, 4).unwrap(), false);
+        sequencer.set_pattern_tempo(0, 0, Some(90.0));
+        sequencer.record_hit(0, 0, 5);
+        
+        sequencer.clear_pattern(0, 0);
+        let pattern = sequencer.get_pattern(0, 0).unwrap();
+        assert!(pattern.is_empty());
+        assert_eq!(pattern.length(), 12);
+        assert_eq!(pattern.get_tempo(), Some(90.0));
+        
+        sequencer.record_hit(0, 0, 5);
+        sequencer.reset_pattern(0, 0);
+        let pattern = sequencer.get_pattern(0, 0).unwrap();
+        assert!(pattern.is_empty());
+        assert_eq!(pattern.length(), STEPS_PER_PATTERN);
+        assert_eq!(pattern.get_signature(), Signature::default());
+        assert_eq!(pattern.get_tempo(), None);
+        assert!(pattern.is_well_formed());
     }
 
     #[test]
@@ -326,6 +1712,253 @@ mod tests {
         assert_eq!(sequencer.active_patterns[0], 5);
     }
 
+    // Pattern 0 is four on the floor on pad 0; pattern 1 is offbeats on pad 1 with an accent
+    fn morph_patterns() -> Sequencer {
+        let mut sequencer = Sequencer::new();
+        for step in [0, 4, 8, 12] {
+            sequencer.record_hit_at(0, 0, 0, step);
+        }
+        for step in [2, 6, 10, 14] {
+            sequencer.record_hit_at(0, 1, 1, step);
+        }
+        sequencer.record_hit_at(0, 1, 0, 0);
+        sequencer.adjust_step_velocity(0, 1, 0, 0, -60);
+        sequencer.toggle_accent(0, 1, 2);
+        sequencer
+    }
+
+    #[test]
+    fn test_three_four_in_eighths_has_six_step_bars() {
+        let signature = Signature::new(3, 2).unwrap();
+        assert_eq!(signature.bar_steps(), 6);
+        assert_eq!(signature.label(), "3/4 · 8ths");
+        assert_eq!(Signature::new(3, 5), None);
+        assert_eq!(Signature::new(0, 4), None);
+        
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern_signature(1, 0, signature, false);
+        assert_eq!(sequencer.get_pattern(1, 0).unwrap().length(), 6);
+        assert_eq!(sequencer.clock_signature(), signature);
+        
+        // The bar line comes every six ticks, so a chain moves on after six
+        sequencer.append_to_chain(0, 0);
+        sequencer.append_to_chain(0, 1);
+        for _ in 0..6 {
+            sequencer.tick(120.0);
+        }
+        assert_eq!(sequencer.get_current_step(), 0);
+        assert_eq!(sequencer.get_active_pattern(0), 1);
+    }
+
+    #[test]
+    fn test_changing_resolution_rescales_or_keeps_steps() {
+        let mut pattern = Pattern::new();
+        for step in [0, 4, 6, 12] {
+            pattern.set_hit(0, step, true);
+        }
+        pattern.set_velocity(0, 4, 60);
+        pattern.set_accent(12, true);
+        
+        // Sixteenths to 8ths: each step lands at the same point in the bar
+        let mut rescaled = pattern.clone();
+        rescaled.set_signature(Signature::new(4, 2).unwrap(), true);
+        assert_eq!(rescaled.length(), 8);
+        let hits: Vec<usize> = (0..8).filter(|&step| rescaled.get_hit(0, step)).collect();
+        assert_eq!(hits, [0, 2, 3, 6]);
+        assert_eq!(rescaled.get_velocity(0, 2), 60);
+        assert!(rescaled.is_accented(6));
+        
+        // Kept indices lose whatever no longer fits
+        let mut kept = pattern.clone();
+        kept.set_signature(Signature::new(4, 2).unwrap(), false);
+        let hits: Vec<usize> = (0..8).filter(|&step| kept.get_hit(0, step)).collect();
+        assert_eq!(hits, [0, 4, 6]);
+        assert!(kept.is_well_formed());
+    }
+
+    #[test]
+    fn test_lowest_group_sets_the_pattern_tempo() {
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.pattern_tempo(), None);
+        
+        sequencer.set_pattern_tempo(2, 0, Some(90.0));
+        assert_eq!(sequencer.pattern_tempo(), Some(90.0));
+        sequencer.set_pattern_tempo(0, 0, Some(128.0));
+        assert_eq!(sequencer.pattern_tempo(), Some(128.0));
+        
+        // Only active patterns count
+        sequencer.set_active_pattern(0, 1);
+        assert_eq!(sequencer.pattern_tempo(), Some(90.0));
+        sequencer.set_pattern_tempo(2, 0, None);
+        assert_eq!(sequencer.pattern_tempo(), None);
+    }
+
+    #[test]
+    fn test_morph_goes_from_one_pattern_to_the_other() {
+        let sequencer = morph_patterns();
+        let mut rng = StdRng::seed_from_u64(11);
+        
+        let start = sequencer.morph(0, 0, 1, 0.0, &mut rng);
+        assert_eq!(start.steps, sequencer.get_pattern_grid(0, 0));
+        assert_eq!(start.get_velocity(0, 0), DEFAULT_VELOCITY);
+        
+        // Velocity and accents come across too, not just the hits
+        let end = sequencer.morph(0, 0, 1, 1.0, &mut rng);
+        assert_eq!(end.steps, sequencer.get_pattern_grid(0, 1));
+        assert_eq!(end.get_velocity(0, 0), DEFAULT_VELOCITY - 60);
+        assert!(end.is_accented(2));
+        
+        // Halfway is a mix of both, the same one every time for a seed
+        let half = sequencer.morph(0, 0, 1, 0.5, &mut StdRng::seed_from_u64(11));
+        assert_eq!(half.steps, sequencer.morph(0, 0, 1, 0.5, &mut StdRng::seed_from_u64(11)).steps);
+        let from_start = [4, 8, 12].iter().filter(|&&step| half.get_hit(0, step)).count();
+        let from_end = [2, 6, 10, 14].iter().filter(|&&step| half.get_hit(1, step)).count();
+        assert_eq!((from_start, from_end), (1, 3));
+    }
+
+    #[test]
+    fn test_morph_plays_out_over_bars() {
+        let mut sequencer = morph_patterns();
+        sequencer.start_morph(0, 1, 4, 5);
+        assert_eq!(sequencer.get_morph(0), Some((1, 0.0)));
+        
+        // Steps taken from the target stay taken as more come over
+        let mut taken = 0;
+        for bar in 1..4 {
+            for _ in 0..STEPS_PER_PATTERN {
+                sequencer.tick(120.0);
+            }
+            assert_eq!(sequencer.get_morph(0), Some((1, bar as f32 / 4.0)));
+            let playing = sequencer.get_playing_pattern(0).unwrap();
+            let now = (0..STEPS_PER_PATTERN).filter(|&step| playing.get_hit(1, step)).count();
+            assert!(now >= taken);
+            taken = now;
+            assert_eq!(sequencer.get_active_pattern(0), 0);
+        }
+        for _ in 0..STEPS_PER_PATTERN {
+            sequencer.tick(120.0);
+        }
+        assert_eq!(sequencer.get_morph(0), None);
+        assert_eq!(sequencer.get_active_pattern(0), 1);
+        
+        // Picking a pattern calls a morph off
+        sequencer.start_morph(0, 0, 4, 5);
+        sequencer.set_active_pattern(0, 2);
+        assert_eq!(sequencer.get_morph(0), None);
+    }
+
+    #[test]
+    fn test_chain_advances_each_bar_and_wraps() {
+        let mut sequencer = Sequencer::new();
+        for pattern in 0..3 {
+            sequencer.append_to_chain(1, pattern);
+        }
+        assert_eq!(sequencer.get_chain(1), &[0, 1, 2]);
+        
+        let mut played = Vec::new();
+        for _ in 0..4 {
+            played.push(sequencer.get_active_pattern(1));
+            for _ in 0..STEPS_PER_PATTERN {
+                sequencer.tick(120.0);
+            }
+        }
+        assert_eq!(played, vec![0, 1, 2, 0]);
+        assert_eq!(sequencer.get_chain_position(1), Some(1));
+        // Unchained groups stay put
+        assert_eq!(sequencer.get_active_pattern(0), 0);
+        assert_eq!(sequencer.get_chain_position(0), None);
+        
+        // Rewinding starts the chain over; clearing leaves the last pattern playing
+        sequencer.reset_position();
+        assert_eq!(sequencer.get_active_pattern(1), 0);
+        sequencer.set_active_pattern(1, 2);
+        sequencer.clear_chain(1);
+        for _ in 0..STEPS_PER_PATTERN {
+            sequencer.tick(120.0);
+        }
+        assert_eq!(sequencer.get_active_pattern(1), 2);
+        assert_eq!(sequencer.append_to_chain(1, MAX_PATTERNS), 0);
+    }
+
+    #[test]
+    fn test_clock_divisions() {
+        let mut sequencer = Sequencer::new();
+        sequencer.toggle_step(1, 0, 0, 0);
+        sequencer.toggle_step(1, 0, 0, 1);
+        sequencer.toggle_step(1, 0, 0, 3);
+        assert_eq!(sequencer.cycle_division(1), ClockDivision::Double);
+        assert_eq!(sequencer.cycle_division(1), ClockDivision::Half);
+        
+        // Half time: the group's step k lands on global tick 2k
+        let ticks: Vec<Vec<usize>> = (0..8)
+            .map(|_| sequencer.tick(120.0).iter().map(|hit| hit.step).collect())
+            .collect();
+        assert_eq!(ticks, vec![vec![0], vec![], vec![1], vec![], vec![], vec![], vec![3], vec![]]);
+        
+        // Double time: two of the group's steps per tick, the second half a step later
+        assert_eq!(sequencer.cycle_division(1), ClockDivision::Normal);
+        assert_eq!(sequencer.cycle_division(1), ClockDivision::Double);
+        sequencer.reset_position();
+        let hits = sequencer.tick(120.0);
+        assert_eq!(hits.iter().map(|hit| (hit.step, hit.sub_step)).collect::<Vec<_>>(), vec![(0, false), (1, true)]);
+        let hits = sequencer.tick(120.0);
+        assert_eq!(hits.iter().map(|hit| (hit.step, hit.sub_step)).collect::<Vec<_>>(), vec![(3, true)]);
+        
+        assert_eq!(sequencer.get_division(0), ClockDivision::Normal);
+    }
+
+    #[test]
+    fn test_short_pattern_loops_within_bar() {
+        let mut sequencer = Sequencer::new();
+        let mut pattern = Pattern::with_length(8);
+        pattern.set_hit(0, 0, true);
+        sequencer.patterns.insert((0, 0), pattern);
+        
+        let hits: Vec<bool> = (0..STEPS_PER_PATTERN).map(|_| hit_pads(sequencer.tick(120.0)).contains(&(0, 0))).collect();
+        assert!(hits[0] && hits[8]);
+        assert_eq!(hits.iter().filter(|&&hit| hit).count(), 2);
+    }
+
+    #[test]
+    fn test_micro_offsets_in_tick() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit_at(0, 0, 1, 0);
+        sequencer.record_hit_at(0, 0, 2, 1);
+        assert_eq!(sequencer.nudge_step(0, 0, 1, 0, 10), Some(10));
+        assert_eq!(sequencer.nudge_step(0, 0, 2, 1, -10), Some(-10));
+        assert_eq!(sequencer.nudge_step(0, 0, 3, 1, 10), None);
+        
+        // Late hits come with their step; early ones a tick ahead of it
+        let hits = sequencer.tick(120.0);
+        assert_eq!(hits, vec![
+            StepHit { group: 0, pad: 1, step: 0, offset_ms: 10, ahead: false, sub_step: false },
+            StepHit { group: 0, pad: 2, step: 1, offset_ms: -10, ahead: true, sub_step: false },
+        ]);
+        // ...and aren't returned again when their step arrives
+        assert!(sequencer.tick(120.0).is_empty());
+        
+        // Without a previous tick the early hit just plays on its step
+        sequencer.reset_position();
+        sequencer.current_step = 1;
+        assert_eq!(sequencer.tick(120.0), vec![StepHit { group: 0, pad: 2, step: 1, offset_ms: 0, ahead: false, sub_step: false }]);
+    }
+
+    #[test]
+    fn test_micro_offsets_clamp_to_half_a_step() {
+        assert_eq!(max_micro_offset_ms(120.0), 61);
+        assert_eq!(max_micro_offset_ms(240.0), 30);
+        
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit_at(0, 0, 1, 0);
+        assert_eq!(sequencer.nudge_step(0, 0, 1, 0, 1000), Some(MAX_MICRO_OFFSET_MS));
+        assert_eq!(sequencer.tick(240.0)[0].offset_ms, 30);
+        
+        // Re-entering the hit resets the nudge
+        sequencer.toggle_step(0, 0, 1, 0);
+        sequencer.toggle_step(0, 0, 1, 0);
+        assert_eq!(sequencer.get_pattern(0, 0).unwrap().get_micro_offset(1, 0), 0);
+    }
+
     #[test]
     fn test_sequencer_reset_position() {
         let mut sequencer = Sequencer::new();
@@ -361,7 +1994,7 @@ mod tests {
         
         // Reset to step 0 and tick
         sequencer.reset_position();
-        let hits = sequencer.tick(120);
+        let hits = hit_pads(sequencer.tick(120.0));
         
         // Should get hits from groups 0, 1, and 2
         assert_eq!(hits.len(), 3);
@@ -370,11 +2003,595 @@ mod tests {
         assert!(hits.contains(&(2, 10)));
     }
 
+    #[test]
+    fn test_audition_advances_independently() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(1, 4, 3); // Group 1, pattern 4, pad 3 at step 0
+        
+        // Move the main playhead somewhere else before auditioning
+        sequencer.current_step = 7;
+        sequencer.start_audition(1, 4);
+        
+        let hits = sequencer.tick_audition();
+        assert_eq!(hits, vec![(1, 3)]);
+        assert_eq!(sequencer.get_audition().unwrap().step, 1);
+        assert_eq!(sequencer.current_step, 7);
+        
+        // Main ticks don't move the audition position
+        sequencer.tick(120.0);
+        assert_eq!(sequencer.current_step, 8);
+        assert_eq!(sequencer.get_audition().unwrap().step, 1);
+        
+        // Audition wraps on its own
+        sequencer.audition.as_mut().unwrap().step = STEPS_PER_PATTERN - 1;
+        sequencer.tick_audition();
+        assert_eq!(sequencer.get_audition().unwrap().step, 0);
+    }
+
+    #[test]
+    fn test_stop_audition_leaves_main_sequencer_untouched() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 1);
+        sequencer.set_active_pattern(0, 0);
+        sequencer.current_step = 5;
+        
+        sequencer.start_audition(2, 10);
+        sequencer.tick_audition();
+        sequencer.tick_audition();
+        sequencer.stop_audition();
+        
+        assert!(!sequencer.is_auditioning());
+        assert!(sequencer.tick_audition().is_empty());
+        assert_eq!(sequencer.current_step, 5);
+        assert_eq!(sequencer.active_patterns, [0; MAX_GROUPS]);
+        // Auditioning an empty slot must not create it
+        assert!(!sequencer.patterns.contains_key(&(2, 10)));
+        assert!(sequencer.get_pattern_grid(0, 0)[1][0]);
+    }
+
     #[test]
     fn test_constants() {
         assert_eq!(STEPS_PER_PATTERN, 16);
         assert_eq!(MAX_PATTERNS, 99);
-        assert_eq!(MAX_GROUPS, 4);
+        assert_eq!(DEFAULT_GROUPS, 4);
         assert_eq!(PADS_PER_GROUP, 16);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_step_velocity_edits_are_independent() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 2); // Step 0
+        sequencer.current_step = 4;
+        sequencer.record_hit(0, 0, 2); // Step 4
+        sequencer.record_hit(0, 0, 3); // Step 4, another pad
+        
+        assert_eq!(sequencer.adjust_step_velocity(0, 0, 2, 4, -40), Some(DEFAULT_VELOCITY - 40));
+        assert_eq!(sequencer.get_step_velocity(0, 0, 2, 4), DEFAULT_VELOCITY - 40);
+        assert_eq!(sequencer.get_step_velocity(0, 0, 2, 0), DEFAULT_VELOCITY);
+        assert_eq!(sequencer.get_step_velocity(0, 0, 3, 4), DEFAULT_VELOCITY);
+        
+        // Clamped to the valid range
+        assert_eq!(sequencer.adjust_step_velocity(0, 0, 2, 4, -500), Some(1));
+        assert_eq!(sequencer.adjust_step_velocity(0, 0, 2, 4, 500), Some(MAX_VELOCITY));
+        
+        // Empty steps and missing patterns are left alone
+        assert_eq!(sequencer.adjust_step_velocity(0, 0, 2, 5, 10), None);
+        assert_eq!(sequencer.adjust_step_velocity(1, 7, 2, 4, 10), None);
+    }
+
+    #[test]
+    fn test_new_hit_resets_step_values() {
+        let mut pattern = Pattern::new();
+        pattern.set_hit(1, 1, true);
+        pattern.set_velocity(1, 1, 20);
+        pattern.set_probability(1, 1, 50);
+        
+        pattern.set_hit(1, 1, false);
+        pattern.set_hit(1, 1, true);
+        assert_eq!(pattern.get_velocity(1, 1), DEFAULT_VELOCITY);
+        assert_eq!(pattern.get_probability(1, 1), DEFAULT_PROBABILITY);
+    }
+
+    #[test]
+    fn test_humanize_stays_in_bounds() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let amount = Humanize { velocity: 0.1, timing_ms: 3 };
+        
+        let mut pattern = Pattern::new();
+        for step in (0..STEPS_PER_PATTERN).step_by(2) {
+            pattern.set_hit(4, step, true);
+        }
+        pattern.set_hit(5, 0, true);
+        
+        // Only hits on the chosen pads move, and one pass stays within the amount
+        assert_eq!(pattern.humanize(4..5, amount, 20, &mut rng), 8);
+        assert_eq!((pattern.get_velocity(5, 0), pattern.get_micro_offset(5, 0)), (DEFAULT_VELOCITY, 0));
+        assert!((0..STEPS_PER_PATTERN).step_by(2).all(|step| {
+            pattern.get_velocity(4, step) >= 114 && pattern.get_micro_offset(4, step).abs() <= 3
+        }));
+        assert!((0..STEPS_PER_PATTERN).any(|step| pattern.get_velocity(4, step) != DEFAULT_VELOCITY));
+        
+        // Repeated passes stay clamped instead of drifting without limit
+        for _ in 0..500 {
+            pattern.humanize(0..PADS_PER_GROUP, amount, 20, &mut rng);
+        }
+        for step in 0..STEPS_PER_PATTERN {
+            assert!((1..=MAX_VELOCITY).contains(&pattern.get_velocity(4, step)));
+            assert!(pattern.get_micro_offset(4, step).abs() <= 20);
+        }
+        
+        // The same seed gives the same result
+        let humanized = |seed| {
+            let mut pattern = Pattern::new();
+            pattern.set_hit(0, 3, true);
+            pattern.humanize(0..1, amount, 20, &mut rand::rngs::StdRng::seed_from_u64(seed));
+            (pattern.get_velocity(0, 3), pattern.get_micro_offset(0, 3))
+        };
+        assert_eq!(humanized(11), humanized(11));
+    }
+
+    #[test]
+    fn test_roll_hits_respects_probability() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        
+        let mut pattern = Pattern::new();
+        pattern.set_hit(0, 0, true);
+        pattern.set_hit(1, 0, true);
+        pattern.set_probability(1, 0, 0);
+        
+        for _ in 0..50 {
+            assert_eq!(pattern.roll_hits_at_step(0, &mut rng), vec![0]);
+        }
+        
+        pattern.set_probability(1, 0, 50);
+        let fired = (0..200)
+            .filter(|_| pattern.roll_hits_at_step(0, &mut rng).contains(&1))
+            .count();
+        assert!(fired > 50 && fired < 150);
+    }
+
+    #[test]
+    fn test_muted_step_keeps_its_values() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 2);
+        sequencer.adjust_step_velocity(0, 0, 2, 0, -60);
+        sequencer.adjust_step_probability(0, 0, 2, 0, -25);
+        
+        // Only steps with a hit can be muted
+        assert_eq!(sequencer.toggle_step_mute(0, 0, 3, 0), None);
+        assert_eq!(sequencer.toggle_step_mute(0, 0, 2, 0), Some(true));
+        assert!(sequencer.is_step_muted(0, 0, 2, 0));
+        
+        assert!(sequencer.tick(120.0).is_empty());
+        assert!(sequencer.get_pattern_grid(0, 0)[2][0]);
+        assert_eq!(sequencer.get_step_velocity(0, 0, 2, 0), 67);
+        assert_eq!(sequencer.get_step_probability(0, 0, 2, 0), 75);
+        
+        // Unmuting brings the hit back with its values intact
+        sequencer.adjust_step_probability(0, 0, 2, 0, 25);
+        assert_eq!(sequencer.toggle_step_mute(0, 0, 2, 0), Some(false));
+        sequencer.reset_position();
+        assert_eq!(hit_pads(sequencer.tick(120.0)), vec![(0, 2)]);
+        assert_eq!(sequencer.get_step_velocity(0, 0, 2, 0), 67);
+    }
+
+    #[test]
+    fn test_pattern_is_empty() {
+        let mut sequencer = Sequencer::new();
+        assert!(sequencer.pattern_is_empty(0, 3));
+
+        sequencer.toggle_step(0, 3, 1, 4);
+        assert!(!sequencer.pattern_is_empty(0, 3));
+
+        // Created but cleared again still reads as empty
+        sequencer.toggle_step(0, 3, 1, 4);
+        assert!(sequencer.pattern_is_empty(0, 3));
+        assert!(sequencer.pattern_keys().any(|&key| key == (0, 3)));
+    }
+
+    #[test]
+    fn test_paste_pattern_remaps_rows() {
+        let mut source = Pattern::new();
+        source.set_hit(0, 0, true);
+        source.set_hit(5, 4, true);
+        source.set_velocity(5, 4, 40);
+        source.set_hit(9, 8, true);
+        source.set_accent(4, true);
+        
+        // Identity keeps every row where it was
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.paste_pattern(3, 1, &source, RemapMode::Identity), 0);
+        let pasted = sequencer.get_pattern(3, 1).unwrap();
+        assert_eq!(pasted.steps, source.steps);
+        assert!(pasted.is_accented(4));
+        
+        // Compact packs used rows onto the given pads in order, with their step values
+        assert_eq!(sequencer.paste_pattern(2, 0, &source, RemapMode::Compact(vec![2, 3, 7, 12])), 0);
+        let pasted = sequencer.get_pattern(2, 0).unwrap();
+        assert!(pasted.steps[2][0] && pasted.steps[3][4] && pasted.steps[7][8]);
+        assert_eq!(pasted.get_velocity(3, 4), 40);
+        assert!(!pasted.steps[0][0] && !pasted.steps[5][4] && !pasted.steps[12].contains(&true));
+        assert!(pasted.is_accented(4));
+        
+        // Signature, tempo and automation aren't per row, so compacting keeps them
+        let mut source = Pattern::new();
+        source.set_signature(Signature::new(3, 4).unwrap(), false);
+        source.set_tempo(Some(90.0));
+        source.set_hit(8, 3, true);
+        source.record_automation(6, Automation::Mute(true));
+        sequencer.paste_pattern(2, 1, &source, RemapMode::Compact(vec![1]));
+        let pasted = sequencer.get_pattern(2, 1).unwrap();
+        assert_eq!(pasted.length(), 12);
+        assert_eq!(pasted.get_signature(), source.get_signature());
+        assert_eq!(pasted.get_tempo(), Some(90.0));
+        assert_eq!(pasted.automated_levels(6), (Some(true), None));
+        assert!(pasted.steps[1][3]);
+        let source = sequencer.get_pattern(3, 1).unwrap().clone();
+        
+        // Too few pads drops the rows that don't fit
+        assert_eq!(sequencer.paste_pattern(2, 0, &source, RemapMode::Compact(vec![6])), 2);
+        let pasted = sequencer.get_pattern(2, 0).unwrap();
+        assert!(pasted.steps[6][0]);
+        assert_eq!(pasted.steps.iter().filter(|row| row.contains(&true)).count(), 1);
+        assert_eq!(sequencer.paste_pattern(2, 0, &source, RemapMode::Compact(Vec::new())), 3);
+    }
+
+    #[test]
+    fn test_first_empty_pattern() {
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.first_empty_pattern(0), Some(0));
+        
+        sequencer.record_hit_at(0, 0, 1, 0);
+        sequencer.record_hit_at(0, 2, 1, 0);
+        // A created but cleared slot counts as empty again
+        sequencer.record_hit_at(0, 1, 1, 0);
+        sequencer.clear_pattern(0, 1);
+        assert_eq!(sequencer.first_empty_pattern(0), Some(1));
+        assert_eq!(sequencer.first_empty_pattern(1), Some(0));
+        assert_eq!(sequencer.first_empty_pattern(MAX_GROUPS), None);
+        
+        for pattern in 0..MAX_PATTERNS {
+            sequencer.record_hit_at(3, pattern, 0, 0);
+        }
+        assert_eq!(sequencer.first_empty_pattern(3), None);
+    }
+
+    #[test]
+    fn test_page_ranges() {
+        assert_eq!(page_count(16), 1);
+        assert_eq!(page_count(24), 2);
+        assert_eq!(page_count(64), 4);
+        
+        assert_eq!(page_range(0, 16), 0..16);
+        assert_eq!(page_range(1, 32), 16..32);
+        // The last page of an uneven length is short
+        assert_eq!(page_range(1, 24), 16..24);
+        assert_eq!(page_of_step(17), 1);
+        
+        let pattern = Pattern::with_length(32);
+        assert_eq!(pattern.steps[0].len(), 32);
+        assert_eq!(pattern.length, 32);
+    }
+
+    #[test]
+    fn test_scroll_to_show() {
+        // Steps already in the window don't move it
+        assert_eq!(scroll_to_show(0, 15, 16, 32), 0);
+        assert_eq!(scroll_to_show(8, 8, 16, 32), 8);
+        // Past the right edge the step lands on the last column, before the left on the first
+        assert_eq!(scroll_to_show(0, 16, 16, 32), 1);
+        assert_eq!(scroll_to_show(0, 20, 8, 32), 13);
+        assert_eq!(scroll_to_show(13, 2, 8, 32), 2);
+        // Wrapping back to step 1 scrolls all the way home
+        assert_eq!(scroll_to_show(16, 0, 16, 32), 0);
+        
+        for window in 1..=20 {
+            for step in 0..32 {
+                let scroll = scroll_to_show(7, step, window, 32);
+                assert!((scroll..scroll + window).contains(&step), "step {} window {}", step, window);
+            }
+        }
+        
+        // A window wider than the pattern, or a stale scroll from a longer one, starts at 1
+        assert_eq!(scroll_to_show(0, 10, 20, 16), 0);
+        assert_eq!(scroll_to_show(24, 15, 16, 16), 0);
+        // A page flip near the end still shows a full window
+        assert_eq!(scroll_to_show(16, 16, 16, 24), 8);
+        assert_eq!(scroll_to_show(0, 0, 0, 16), 0);
+    }
+
+    #[test]
+    fn test_quantize_offset() {
+        assert_eq!(quantize_offset(2.2, 1.0, 16), 2);
+        assert_eq!(quantize_offset(2.8, 1.0, 16), 3);
+        assert_eq!(quantize_offset(15.6, 1.0, 16), 0);
+        assert_eq!(quantize_offset(-0.3, 1.0, 16), 0);
+        assert_eq!(quantize_offset(-0.7, 1.0, 16), 15);
+        
+        // No strength leaves hits where the playhead was
+        assert_eq!(quantize_offset(2.8, 0.0, 16), 2);
+        // Half strength only pulls in hits in the last quarter of a step
+        assert_eq!(quantize_offset(2.7, 0.5, 16), 2);
+        assert_eq!(quantize_offset(2.8, 0.5, 16), 3);
+    }
+
+    #[test]
+    fn test_velocity_gain() {
+        assert_eq!(velocity_gain(MAX_VELOCITY), 1.0);
+        assert!(velocity_gain(1) > 0.0);
+        assert!((velocity_gain(64) - 0.504).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_accented_gain_clamps_at_full_level() {
+        assert_eq!(accented_gain(0.5, false, 0.3), 0.5);
+        assert!((accented_gain(0.5, true, 0.3) - 0.8).abs() < 1e-6);
+        assert_eq!(accented_gain(0.9, true, 0.3), 1.0);
+        assert_eq!(accented_gain(1.0, true, 0.3), 1.0);
+        // A negative boost from a bad config doesn't turn accents into cuts
+        assert_eq!(accented_gain(0.5, true, -0.3), 0.5);
+    }
+
+    #[test]
+    fn test_accent_row() {
+        let mut sequencer = Sequencer::new();
+        assert!(!sequencer.is_step_accented(0, 0, 4));
+        assert!(sequencer.toggle_accent(0, 0, 4));
+        assert!(sequencer.is_step_accented(0, 0, 4));
+        // Steps past the pattern's end can't be accented
+        assert!(!sequencer.toggle_accent(0, 0, STEPS_PER_PATTERN));
+        
+        sequencer.clear_pattern(0, 0);
+        assert!(!sequencer.is_step_accented(0, 0, 4));
+    }
+
+    fn rhythm(pulses: usize, steps: usize) -> String {
+        euclidean_rhythm(pulses, steps).into_iter().map(|hit| if hit { 'x' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn test_euclidean_rhythms() {
+        assert_eq!(rhythm(3, 8), "x..x..x.");
+        assert_eq!(rhythm(5, 8), "x.xx.xx.");
+        assert_eq!(rhythm(4, 16), "x...x...x...x...");
+        assert_eq!(rhythm(2, 5), "x.x..");
+        assert_eq!(rhythm(0, 4), "....");
+        // More pulses than steps just fills the row
+        assert_eq!(rhythm(9, 4), "xxxx");
+        assert_eq!(rhythm(3, 0), "");
+        
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern(0, 0, Pattern::with_length(8));
+        sequencer.record_hit(0, 0, 2);
+        sequencer.euclidean(0, 0, 2, 3, 0);
+        let row = |sequencer: &Sequencer| -> String {
+            sequencer.get_pattern_grid(0, 0)[2].iter().map(|&hit| if hit { 'x' } else { '.' }).collect()
+        };
+        assert_eq!(row(&sequencer), "x..x..x.");
+        // Rotation moves hits later and wraps around the pattern
+        sequencer.euclidean(0, 0, 2, 3, 1);
+        assert_eq!(row(&sequencer), ".x..x..x");
+        sequencer.euclidean(0, 0, 2, 3, 10);
+        assert_eq!(row(&sequencer), "x.x..x..");
+    }
+
+    #[test]
+    fn test_thin_pattern() {
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.thin_pattern(0, 0, 4), 0);
+        for pad in [0, 5] {
+            sequencer.fill_pad_row(0, 0, pad, true);
+        }
+        sequencer.adjust_step_velocity(0, 0, 5, 8, -40);
+        sequencer.toggle_accent(0, 0, 8);
+        sequencer.toggle_accent(0, 0, 9);
+        
+        assert_eq!(sequencer.thin_pattern(0, 0, 4), 24);
+        let pattern = sequencer.get_pattern(0, 0).unwrap();
+        for pad in [0, 5] {
+            let hits: Vec<usize> = (0..STEPS_PER_PATTERN).filter(|&step| pattern.steps[pad][step]).collect();
+            assert_eq!(hits, vec![0, 4, 8, 12]);
+        }
+        // Surviving steps keep their data; accents on emptied steps go
+        assert_eq!(pattern.get_velocity(5, 8), DEFAULT_VELOCITY - 40);
+        assert!(pattern.is_accented(8));
+        assert!(!pattern.is_accented(9));
+        
+        // A row with nothing on the grid keeps its first hit
+        sequencer.record_hit_at(0, 0, 2, 6);
+        sequencer.record_hit_at(0, 0, 2, 11);
+        assert_eq!(sequencer.thin_pattern(0, 0, 4), 1);
+        assert!(sequencer.get_pattern_grid(0, 0)[2][6]);
+        assert_eq!(sequencer.thin_pattern(0, 0, 4), 0);
+    }
+
+    #[test]
+    fn test_fill_and_every_n_pad_rows() {
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern(0, 0, Pattern::with_length(24));
+        sequencer.fill_pad_row(0, 0, 3, true);
+        let grid = sequencer.get_pattern_grid(0, 0);
+        assert_eq!(grid[3].len(), 24);
+        assert!(grid[3].iter().all(|&hit| hit));
+        assert!(!grid[2].contains(&true));
+        
+        sequencer.fill_pad_row(0, 0, 3, false);
+        assert!(sequencer.pattern_is_empty(0, 0));
+        
+        sequencer.set_pad_every_n(1, 0, 0, 4);
+        let hits: Vec<usize> = (0..STEPS_PER_PATTERN).filter(|&step| sequencer.get_pattern_grid(1, 0)[0][step]).collect();
+        assert_eq!(hits, vec![0, 4, 8, 12]);
+        // Zero spacing and pads outside the group leave the row alone
+        sequencer.set_pad_every_n(1, 0, 0, 0);
+        sequencer.set_pad_every_n(1, 0, PADS_PER_GROUP, 2);
+        assert_eq!(sequencer.get_pattern_grid(1, 0)[0].iter().filter(|&&hit| hit).count(), 4);
+    }
+
+    #[test]
+    fn test_cycle_ratchet() {
+        let mut sequencer = Sequencer::new();
+        assert_eq!(sequencer.cycle_ratchet(0, 0, 1, 2), None);
+        sequencer.record_hit_at(0, 0, 1, 2);
+        let ratchets: Vec<Option<u8>> = (0..4).map(|_| sequencer.cycle_ratchet(0, 0, 1, 2)).collect();
+        assert_eq!(ratchets, vec![Some(2), Some(3), Some(4), Some(1)]);
+        
+        sequencer.cycle_ratchet(0, 0, 1, 2);
+        assert_eq!(sequencer.get_step_ratchet(0, 0, 1, 2), 2);
+        // Removing and re-adding the hit starts it over at one play
+        sequencer.toggle_step(0, 0, 1, 2);
+        sequencer.toggle_step(0, 0, 1, 2);
+        assert_eq!(sequencer.get_step_ratchet(0, 0, 1, 2), 1);
+        
+        let mut old = Pattern::new();
+        old.ratchets.clear();
+        assert!(!old.is_well_formed());
+        old.fill_missing_ratchets();
+        assert!(old.is_well_formed());
+    }
+
+    #[test]
+    fn test_32_pad_pattern() {
+        let mut pattern = Pattern::with_size(MAX_PADS, STEPS_PER_PATTERN);
+        assert_eq!(pattern.pads(), 32);
+        assert!(pattern.is_well_formed());
+        
+        pattern.set_hit(31, 4, true);
+        pattern.set_velocity(31, 4, 90);
+        pattern.set_ratchet(31, 4, 3);
+        assert_eq!(pattern.get_hits_at_step(4), vec![31]);
+        assert_eq!(pattern.get_velocity(31, 4), 90);
+        pattern.set_hit(32, 4, true); // Past the last pad
+        assert_eq!(pattern.get_hits_at_step(4), vec![31]);
+        
+        // Shrinking drops the second bank; growing again brings back empty rows
+        pattern.set_pad_count(PADS_PER_GROUP);
+        assert!(pattern.is_well_formed());
+        assert!(pattern.is_empty());
+        pattern.set_pad_count(MAX_PADS);
+        assert!(pattern.is_well_formed());
+        assert_eq!(pattern.get_ratchet(31, 4), 1);
+        
+        // Row counts between the two aren't a valid pattern
+        pattern.set_pad_count(20);
+        assert!(!pattern.is_well_formed());
+    }
+
+    #[test]
+    fn test_sequencer_with_32_pads() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 3);
+        sequencer.set_pad_count(MAX_PADS);
+        assert_eq!(sequencer.pad_count(), 32);
+        
+        // Patterns made before the switch grow, new ones start at the full size
+        assert_eq!(sequencer.get_pattern_grid(0, 0).len(), 32);
+        assert!(sequencer.get_pattern_grid(0, 0)[3][0]);
+        sequencer.euclidean(1, 0, 31, 4, 0);
+        sequencer.set_pad_every_n(1, 0, 20, 8);
+        sequencer.set_pad_every_n(1, 0, 32, 8); // Past the last pad
+        assert_eq!(sequencer.get_pattern(1, 0).unwrap().pads(), 32);
+        
+        let hits = hit_pads(sequencer.tick(120.0));
+        assert!(hits.contains(&(0, 3)));
+        assert!(hits.contains(&(1, 20)));
+        assert!(hits.contains(&(1, 31)));
+        
+        // A 32-row export comes back whole, and a 16-row one is padded out
+        let json = sequencer.export_pattern_json(1, 0);
+        sequencer.clear_pattern(1, 0);
+        sequencer.import_pattern_json(1, 0, &json).unwrap();
+        assert!(sequencer.get_pattern_grid(1, 0)[31][0]);
+        let steps = vec![vec![true; STEPS_PER_PATTERN]; PADS_PER_GROUP];
+        sequencer.import_pattern_json(2, 0, &serde_json::json!({ "steps": steps }).to_string()).unwrap();
+        assert_eq!(sequencer.get_pattern(2, 0).unwrap().pads(), 32);
+        assert!(sequencer.get_pattern(2, 0).unwrap().is_well_formed());
+        let steps = vec![vec![true; STEPS_PER_PATTERN]; 24];
+        assert!(sequencer.import_pattern_json(2, 0, &serde_json::json!({ "steps": steps }).to_string()).is_err());
+        
+        // Going back to 16 pads drops the rows past 16 from every pattern
+        sequencer.set_pad_count(PADS_PER_GROUP);
+        assert_eq!(sequencer.get_pattern_grid(1, 0).len(), 16);
+        assert!(sequencer.get_pattern_grid(1, 0).iter().all(|row| !row.contains(&true)));
+        sequencer.set_pad_count(24); // Not a supported count
+        assert_eq!(sequencer.pad_count(), 16);
+    }
+
+    #[test]
+    fn test_pattern_json_round_trip() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(1, 3, 0);
+        sequencer.current_step = 9;
+        sequencer.record_hit(1, 3, 15);
+        sequencer.adjust_step_velocity(1, 3, 15, 9, -30);
+        sequencer.adjust_step_probability(1, 3, 0, 0, -50);
+        sequencer.toggle_accent(1, 3, 12);
+        sequencer.cycle_ratchet(1, 3, 15, 9);
+        
+        let original = sequencer.get_pattern(1, 3).unwrap().clone();
+        let json = sequencer.export_pattern_json(1, 3);
+        
+        sequencer.clear_pattern(1, 3);
+        assert!(sequencer.get_pattern(1, 3).unwrap().get_hits_at_step(0).is_empty());
+        
+        sequencer.import_pattern_json(1, 3, &json).unwrap();
+        let imported = sequencer.get_pattern(1, 3).unwrap();
+        assert_eq!(imported.steps, original.steps);
+        assert_eq!(imported.velocities, original.velocities);
+        assert_eq!(imported.probabilities, original.probabilities);
+        assert_eq!(imported.accent, original.accent);
+        assert_eq!(imported.ratchets, original.ratchets);
+    }
+
+    #[test]
+    fn test_pattern_json_round_trip_keeps_signature_and_tempo() {
+        let mut sequencer = Sequencer::new();
+        sequencer.set_pattern_signature(0, 2, Signature::new(3, 4).unwrap(), false);
+        sequencer.set_pattern_tempo(0, 2, Some(96.0));
+        sequencer.record_hit_at(0, 2, 5, 0);
+        sequencer.record_hit_at(0, 2, 7, 11);
+        
+        let json = sequencer.export_pattern_json(0, 2);
+        sequencer.import_pattern_json(1, 0, &json).unwrap();
+        let imported = sequencer.get_pattern(1, 0).unwrap();
+        assert_eq!(imported.length(), 12);
+        assert_eq!(imported.get_signature(), Signature::new(3, 4).unwrap());
+        assert_eq!(imported.get_tempo(), Some(96.0));
+        assert_eq!(imported.steps, sequencer.get_pattern(0, 2).unwrap().steps);
+        assert!(imported.is_well_formed());
+        
+        // A length that doesn't fit the signature is refused
+        let steps = vec![vec![false; 12]; PADS_PER_GROUP];
+        let json = serde_json::json!({ "steps": steps, "length": 12 }).to_string();
+        assert!(sequencer.import_pattern_json(1, 0, &json).is_err());
+    }
+
+    #[test]
+    fn test_pattern_json_rejects_bad_input() {
+        let mut sequencer = Sequencer::new();
+        sequencer.record_hit(0, 0, 2);
+        
+        assert!(matches!(sequencer.import_pattern_json(0, 0, "not json"), Err(AppError::Pattern(_))));
+        
+        // Wrong number of steps on one pad
+        let mut steps = vec![vec![false; STEPS_PER_PATTERN]; PADS_PER_GROUP];
+        steps[3].push(true);
+        let json = serde_json::json!({ "steps": steps }).to_string();
+        assert!(sequencer.import_pattern_json(0, 0, &json).is_err());
+        
+        // An accent row that doesn't match the steps
+        let steps = vec![vec![false; STEPS_PER_PATTERN]; PADS_PER_GROUP];
+        let json = serde_json::json!({ "steps": steps, "accent": [true, false] }).to_string();
+        assert!(sequencer.import_pattern_json(0, 0, &json).is_err());
+        
+        // Failed imports leave the slot untouched
+        assert!(sequencer.get_pattern_grid(0, 0)[2][0]);
+        
+        // Velocities are optional
+        let steps = vec![vec![true; STEPS_PER_PATTERN]; PADS_PER_GROUP];
+        let json = serde_json::json!({ "steps": steps }).to_string();
+        sequencer.import_pattern_json(0, 0, &json).unwrap();
+        assert_eq!(sequencer.get_step_velocity(0, 0, 5, 5), DEFAULT_VELOCITY);
+    }
+}
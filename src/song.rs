@@ -0,0 +1,115 @@
+// Song-level state that spans bars: where playback is and how the tempo moves
+#[derive(Debug, Clone, Default)]
+pub struct Song {
+    pub bar: usize,                         // Bars completed since playback started
+    pub tempo_automation: Vec<(usize, f32)>, // (bar, bpm) breakpoints, sorted by bar
+}
+
+impl Song {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset_position(&mut self) {
+        self.bar = 0;
+    }
+
+    // Replaces any breakpoint already on that bar
+    pub fn add_tempo_breakpoint(&mut self, bar: usize, bpm: f32) {
+        match self.tempo_automation.binary_search_by_key(&bar, |&(b, _)| b) {
+            Ok(index) => self.tempo_automation[index].1 = bpm,
+            Err(index) => self.tempo_automation.insert(index, (bar, bpm)),
+        }
+    }
+
+    pub fn clear_tempo_automation(&mut self) {
+        self.tempo_automation.clear();
+    }
+
+    // Linear between breakpoints, holding the first and last values outside them
+    pub fn tempo_at(&self, bar: usize) -> Option<f32> {
+        let &(first_bar, first_bpm) = self.tempo_automation.first()?;
+        if bar <= first_bar {
+            return Some(first_bpm);
+        }
+
+        for pair in self.tempo_automation.windows(2) {
+            let ((start_bar, start_bpm), (end_bar, end_bpm)) = (pair[0], pair[1]);
+            if bar < end_bar {
+                let t = (bar - start_bar) as f32 / (end_bar - start_bar) as f32;
+                return Some(start_bpm + (end_bpm - start_bpm) * t);
+            }
+        }
+
+        self.tempo_automation.last().map(|&(_, bpm)| bpm)
+    }
+
+    // Tempo of the next breakpoint while the automation is still moving towards it
+    pub fn tempo_target(&self, bar: usize) -> Option<f32> {
+        let current = self.tempo_at(bar)?;
+        self.tempo_automation
+            .iter()
+            .find(|&&(b, _)| b > bar)
+            .map(|&(_, bpm)| bpm)
+            .filter(|&bpm| bpm != current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp_song() -> Song {
+        let mut song = Song::new();
+        song.add_tempo_breakpoint(8, 140.0);
+        song.add_tempo_breakpoint(4, 120.0);
+        song
+    }
+
+    #[test]
+    fn test_no_automation() {
+        let song = Song::new();
+        assert_eq!(song.tempo_at(3), None);
+        assert_eq!(song.tempo_target(3), None);
+    }
+
+    #[test]
+    fn test_tempo_at_breakpoint() {
+        let song = ramp_song();
+        assert_eq!(song.tempo_automation, vec![(4, 120.0), (8, 140.0)]);
+        assert_eq!(song.tempo_at(4), Some(120.0));
+        assert_eq!(song.tempo_at(8), Some(140.0));
+        // Before the first breakpoint its value holds
+        assert_eq!(song.tempo_at(0), Some(120.0));
+    }
+
+    #[test]
+    fn test_tempo_between_breakpoints() {
+        let song = ramp_song();
+        assert_eq!(song.tempo_at(5), Some(125.0));
+        assert_eq!(song.tempo_at(6), Some(130.0));
+        assert_eq!(song.tempo_target(6), Some(140.0));
+
+        // Ramps pass through fractional tempos
+        let mut song = Song::new();
+        song.add_tempo_breakpoint(0, 87.0);
+        song.add_tempo_breakpoint(2, 88.0);
+        assert_eq!(song.tempo_at(1), Some(87.5));
+    }
+
+    #[test]
+    fn test_tempo_past_last_breakpoint_holds() {
+        let mut song = ramp_song();
+        assert_eq!(song.tempo_at(9), Some(140.0));
+        assert_eq!(song.tempo_at(100), Some(140.0));
+        assert_eq!(song.tempo_target(9), None);
+
+        // Re-adding a bar replaces its tempo
+        song.add_tempo_breakpoint(8, 150.0);
+        assert_eq!(song.tempo_automation.len(), 2);
+        assert_eq!(song.tempo_at(100), Some(150.0));
+
+        song.clear_tempo_automation();
+        assert_eq!(song.tempo_at(100), None);
+    }
+}
@@ -1,37 +1,376 @@
-use std::time::Instant;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::sequencer::{page_count, page_of_step, scroll_to_show, Signature, DEFAULT_GROUPS, MAX_GROUPS, PADS_PER_GROUP, STEPS_PER_BEAT, STEPS_PER_PAGE};
 
+const NOTIFICATION_DURATION: Duration = Duration::from_secs(3);
+const MAX_PATH_INPUT: usize = 255; // Characters a path prompt takes
+pub const DEFAULT_FLASH_DURATION: Duration = Duration::from_millis(150);
+
+pub const DEFAULT_TEMPO: f32 = 120.0;
+pub const MIN_TEMPO: f32 = 60.0;
+pub const MAX_TEMPO: f32 = 300.0;
+
+/// Where the transport reads the time. Tests swap in a [`ManualClock`] so they can step
+/// time by hand instead of sleeping.
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that stands still until advanced; clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self { now: Arc::new(Mutex::new(Instant::now())) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+// Swing amounts offered by the swing preset selector, as on MPCs
+pub const SWING_PRESETS: [u8; 5] = [50, 54, 58, 62, 66];
+
+// What the sequencer grid shows for each active step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridMode {
+    Steps,
+    Velocity,
+    Probability,
+}
+
+impl GridMode {
+    pub fn next(self) -> Self {
+        match self {
+            GridMode::Steps => GridMode::Velocity,
+            GridMode::Velocity => GridMode::Probability,
+            GridMode::Probability => GridMode::Steps,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GridMode::Steps => "STEPS",
+            GridMode::Velocity => "VEL",
+            GridMode::Probability => "PROB",
+        }
+    }
+}
+
+// Pane shown beside the pads when there isn't room for both the sequencer and the mixer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Sequencer,
+    Mixer,
+}
+
+impl Panel {
+    pub fn next(self) -> Self {
+        match self {
+            Panel::Sequencer => Panel::Mixer,
+            Panel::Mixer => Panel::Sequencer,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Panel::Sequencer => "Sequencer",
+            Panel::Mixer => "Mixer",
+        }
+    }
+}
+
+// What fills the main content area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Normal,   // Pads, sequencer and mixer for the current group
+    Overview, // One sequencer lane per group
+}
+
+// What a text prompt's input is for once it's confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    GotoPattern,
+    Morph, // Pattern the current group morphs into
+    RestoreAutosave,
+    Euclidean, // Pulses, optionally followed by a comma and a rotation
+    ImportFolder, // A folder of samples for the current group
+    Signature, // Beats per bar, a comma, then steps per beat
+    RescaleSignature(Signature), // Whether hits move with the grid when switching to this signature
+    MasterGain, // dB, signed and with an optional decimal
+}
+
+impl PromptKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptKind::GotoPattern => "Go to pattern",
+            PromptKind::Morph => "Morph to pattern",
+            PromptKind::RestoreAutosave => "Restore autosave? (y/n)",
+            PromptKind::Euclidean => "Euclid pulses[,rotation]",
+            PromptKind::ImportFolder => "Import folder",
+            PromptKind::Signature => "Beats per bar,steps per beat",
+            PromptKind::RescaleSignature(_) => "Move hits onto the new grid? (y/n)",
+            PromptKind::MasterGain => "Master gain dB (-60 is off, up to +12)",
+        }
+    }
+
+    fn accepts(self, c: char, input: &str) -> bool {
+        match self {
+            PromptKind::GotoPattern | PromptKind::Morph => c.is_ascii_digit() && input.len() < 2,
+            PromptKind::RestoreAutosave | PromptKind::RescaleSignature(_) => matches!(c, 'y' | 'Y' | 'n' | 'N') && input.is_empty(),
+            PromptKind::Euclidean | PromptKind::Signature => {
+                input.len() < 5 && (c.is_ascii_digit() || (c == ',' && !input.is_empty() && !input.contains(',')))
+            }
+            PromptKind::ImportFolder => !c.is_control() && input.len() < MAX_PATH_INPUT,
+            PromptKind::MasterGain => {
+                input.len() < 5 && (c.is_ascii_digit() || (matches!(c, '-' | '+') && input.is_empty()) || (c == '.' && !input.contains('.')))
+            }
+        }
+    }
+
+    // Input that can't take any more characters submits itself
+    fn is_complete(self, input: &str) -> bool {
+        match self {
+            PromptKind::GotoPattern | PromptKind::Morph => input.len() == 2,
+            PromptKind::RestoreAutosave | PromptKind::RescaleSignature(_) => input.len() == 1,
+            PromptKind::Euclidean | PromptKind::ImportFolder | PromptKind::Signature | PromptKind::MasterGain => false,
+        }
+    }
+}
+
+// A one-line text input that takes over the keyboard while it's open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self { kind, input: String::new() }
+    }
+
+    // Characters the prompt doesn't accept are ignored
+    pub fn push(&mut self, c: char) {
+        if self.kind.accepts(c, &self.input) {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.kind.is_complete(&self.input)
+    }
+}
+
+// Paused keeps the playhead where it is; Stopped rewinds it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Stopped,
+    Playing,
+    Paused,
+}
+
+/// What the terminal UI shows: selection, grid mode, prompts and notifications.
 #[derive(Debug, Clone)]
 pub struct UIState {
     pub selected_pad: Option<usize>,
     pub flashing_pads: Vec<(usize, usize)>, // (group, pad) pairs that are currently flashing
     pub flash_timer: Instant,
+    flash_duration: Option<Duration>, // How long pads stay lit; None turns flashing off
+    pub notification: Option<(String, Instant)>, // Message shown in the footer and when it was posted
+    pub grid_mode: GridMode,
+    pub cursor_step: usize, // Step edited by step commands, on the selected pad's row
+    pub step_page: usize,    // Page of the pattern view when it isn't following the playhead
+    pub grid_scroll: usize,  // First step the pattern view shows when it isn't following the playhead
+    pub follow_playhead: bool,
+    pub panel: Panel, // Secondary pane on compact and minimal layouts
+    pub view: ViewMode,
+    pub prompt: Option<Prompt>, // Open text input, which receives every key
+    pub pattern_map: Option<usize>, // Slot under the cursor while the pattern map is open
+    pub pad_detail: bool, // Selected pad's sample and settings shown over the main view
+    pub pad_bank: usize, // Which 16 pads the pad keys play and the pattern view shows
+    dirty: bool,      // Something visible changed since the last draw
 }
 
 impl UIState {
     pub fn new() -> Self {
+        Self::with_flash_duration(Some(DEFAULT_FLASH_DURATION))
+    }
+
+    pub fn with_flash_duration(flash_duration: Option<Duration>) -> Self {
         Self {
             selected_pad: None,
             flashing_pads: Vec::new(),
             flash_timer: Instant::now(),
+            flash_duration,
+            notification: None,
+            grid_mode: GridMode::Steps,
+            cursor_step: 0,
+            step_page: 0,
+            grid_scroll: 0,
+            follow_playhead: true,
+            panel: Panel::Sequencer,
+            view: ViewMode::Normal,
+            prompt: None,
+            pattern_map: None,
+            pad_detail: false,
+            pad_bank: 0,
+            dirty: true,
         }
     }
 
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    // Reports whether a redraw is due and clears the flag
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    pub fn notify(&mut self, message: impl Into<String>) {
+        self.notification = Some((message.into(), Instant::now()));
+        self.dirty = true;
+    }
+
+    // Drops an expired notification so the footer redraws without it
+    pub fn update_notification(&mut self) {
+        if self.notification.is_some() && self.active_notification().is_none() {
+            self.notification = None;
+            self.dirty = true;
+        }
+    }
+
+    pub fn active_notification(&self) -> Option<&str> {
+        self.notification
+            .as_ref()
+            .filter(|(_, posted)| posted.elapsed() < NOTIFICATION_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    pub fn cycle_grid_mode(&mut self) {
+        self.grid_mode = self.grid_mode.next();
+    }
+
+    pub fn toggle_panel(&mut self) {
+        self.panel = self.panel.next();
+    }
+
+    pub fn toggle_overview(&mut self) {
+        self.view = match self.view {
+            ViewMode::Normal => ViewMode::Overview,
+            ViewMode::Overview => ViewMode::Normal,
+        };
+    }
+
+    // The view pages along with the cursor so the edited step stays visible
+    pub fn move_cursor_step(&mut self, delta: i32, length: usize) {
+        self.cursor_step = (self.cursor_step as i32 + delta).rem_euclid(length.max(1) as i32) as usize;
+        self.step_page = page_of_step(self.cursor_step);
+        self.grid_scroll = scroll_to_show(self.grid_scroll, self.cursor_step, STEPS_PER_PAGE, length);
+    }
+
+    // Flips pages, keeping the cursor in the same column
+    pub fn move_step_page(&mut self, delta: i32, length: usize) {
+        let pages = page_count(length) as i32;
+        self.step_page = (self.step_page as i32 + delta).rem_euclid(pages) as usize;
+        let column = self.cursor_step % STEPS_PER_PAGE;
+        self.cursor_step = (self.step_page * STEPS_PER_PAGE + column).min(length.saturating_sub(1));
+        self.grid_scroll = scroll_to_show(self.step_page * STEPS_PER_PAGE, self.cursor_step, STEPS_PER_PAGE, length);
+    }
+
+    // Steps the pattern view shows in a `window` columns wide. While playing, following
+    // the playhead wins over the manual scroll; otherwise the cursor is kept in sight
+    pub fn visible_steps(&self, current_step: usize, playing: bool, length: usize, window: usize) -> Range<usize> {
+        let (scroll, focus) = if self.follow_playhead && playing {
+            (0, current_step)
+        } else {
+            (self.grid_scroll, self.cursor_step)
+        };
+        let start = scroll_to_show(scroll, focus.min(length.saturating_sub(1)), window, length);
+        start..(start + window.max(1)).min(length)
+    }
+
+    // The pattern view follows the selection into the other bank
     pub fn select_pad(&mut self, pad: usize) {
         self.selected_pad = Some(pad);
+        self.pad_bank = pad / PADS_PER_GROUP;
+    }
+
+    pub fn cycle_pad_bank(&mut self, pads: usize) {
+        self.pad_bank = (self.pad_bank + 1) % pads.div_ceil(PADS_PER_GROUP).max(1);
+    }
+
+    // Pads the active bank holds, out of `pads` in the group
+    pub fn bank_pads(&self, pads: usize) -> Range<usize> {
+        let start = (self.pad_bank * PADS_PER_GROUP).min(pads);
+        start..(start + PADS_PER_GROUP).min(pads)
+    }
+
+    // After the pad count shrinks nothing may point past the last pad
+    pub fn set_pad_count(&mut self, pads: usize) {
+        if self.selected_pad.is_some_and(|pad| pad >= pads) {
+            self.selected_pad = None;
+        }
+        if self.pad_bank * PADS_PER_GROUP >= pads {
+            self.pad_bank = 0;
+        }
     }
 
     pub fn clear_selection(&mut self) {
         self.selected_pad = None;
     }
 
+    pub fn set_flash_duration(&mut self, flash_duration: Option<Duration>) {
+        self.flash_duration = flash_duration;
+        if flash_duration.is_none() {
+            self.start_flash(Vec::new());
+        }
+    }
+
     pub fn start_flash(&mut self, pads: Vec<(usize, usize)>) {
+        let pads = if self.flash_duration.is_some() { pads } else { Vec::new() };
+        self.dirty |= !pads.is_empty() || !self.flashing_pads.is_empty();
         self.flashing_pads = pads;
         self.flash_timer = Instant::now();
     }
 
     pub fn update_flash(&mut self) {
-        if self.flash_timer.elapsed() >= std::time::Duration::from_millis(150) {
+        let expired = self.flash_duration.is_none_or(|duration| self.flash_timer.elapsed() >= duration);
+        if !self.flashing_pads.is_empty() && expired {
             self.flashing_pads.clear();
+            self.dirty = true;
         }
     }
 
@@ -46,34 +385,83 @@ impl Default for UIState {
     }
 }
 
+/// Transport, tempo and the current group and patterns.
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub current_group: usize,
-    pub group_patterns: [usize; 4], // Each group has its own current pattern
-    pub is_playing: bool,
+    pub group_patterns: Vec<usize>, // Each group has its own current pattern; one entry per group
+    pub transport: Transport,
     pub is_recording: bool,
-    pub tempo: u32,
+    pub record_armed: Vec<bool>, // Groups pad hits record into; none armed means the current group
+    pub tempo: f32, // BPM, kept to tenths
+    pub steps_per_beat: usize, // Of the signature the transport counts in
     pub last_tick: Instant,
+    pub swing_preset: u8, // Index into SWING_PRESETS
+    pub bars_elapsed: u32, // Bars completed since playback started from the top
+    play_started: Option<Instant>, // When the transport last started running
+    elapsed_before: Duration, // Play time banked before the latest pause
+    clock: Arc<dyn Clock>,
+}
+
+// 1-based (bar, beat, step within the beat) for a step in the bar, as DAWs count
+pub fn transport_position(bars_elapsed: u32, step: usize, steps_per_beat: usize) -> (u32, u32, u32) {
+    let steps_per_beat = steps_per_beat.max(1);
+    (bars_elapsed + 1, (step / steps_per_beat) as u32 + 1, (step % steps_per_beat) as u32 + 1)
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             current_group: 0,
-            group_patterns: [0; 4],
-            is_playing: false,
+            group_patterns: vec![0; DEFAULT_GROUPS],
+            transport: Transport::Stopped,
             is_recording: false,
-            tempo: 120,
+            record_armed: vec![false; DEFAULT_GROUPS],
+            tempo: DEFAULT_TEMPO,
+            steps_per_beat: STEPS_PER_BEAT,
             last_tick: Instant::now(),
+            swing_preset: 0,
+            bars_elapsed: 0,
+            play_started: None,
+            elapsed_before: Duration::ZERO,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    // The step timer restarts on the new clock, which may be far from the old one
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+        self.last_tick = self.now();
+        self.play_started = self.is_playing().then(|| self.now());
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.group_patterns.len()
+    }
+
+    // New groups start on pattern 1; the selection moves off groups that are gone
+    pub fn set_group_count(&mut self, groups: usize) {
+        self.group_patterns.resize(groups.clamp(1, MAX_GROUPS), 0);
+        self.record_armed.resize(self.group_patterns.len(), false);
+        self.current_group = self.current_group.min(self.group_count() - 1);
+    }
+
     pub fn next_group(&mut self) {
-        self.current_group = (self.current_group + 1) % 4;
+        self.current_group = (self.current_group + 1) % self.group_count();
     }
 
     pub fn prev_group(&mut self) {
-        self.current_group = if self.current_group == 0 { 3 } else { self.current_group - 1 };
+        self.current_group = if self.current_group == 0 { self.group_count() - 1 } else { self.current_group - 1 };
+    }
+
+    pub fn select_group(&mut self, group: usize) {
+        if group < self.group_count() {
+            self.current_group = group;
+        }
     }
 
     pub fn get_current_pattern(&self) -> usize {
@@ -93,12 +481,69 @@ impl AppState {
         self.group_patterns[self.current_group] = if current_pattern == 0 { 98 } else { current_pattern - 1 };
     }
 
-    pub fn adjust_tempo(&mut self, delta: i32) {
-        self.tempo = (self.tempo as i32 + delta).clamp(60, 300) as u32;
+    pub fn adjust_tempo(&mut self, delta: f32) {
+        self.set_tempo(self.tempo + delta);
+    }
+
+    // Rounded to tenths so repeated fine steps don't drift off 0.1 increments
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.tempo = ((bpm * 10.0).round() / 10.0).clamp(MIN_TEMPO, MAX_TEMPO);
+    }
+
+    pub fn cycle_swing(&mut self) {
+        self.swing_preset = (self.swing_preset + 1) % SWING_PRESETS.len() as u8;
+    }
+
+    pub fn swing_percent(&self) -> u8 {
+        SWING_PRESETS[self.swing_preset as usize % SWING_PRESETS.len()]
+    }
+
+    // MPC-style swing: at S% the off-beat 16th lands S% of the way through its 8th note,
+    // so odd steps slide back by (2 * S% - 1) of a step. 50% is straight.
+    pub fn swing_delay_fraction(&self) -> f32 {
+        2.0 * self.swing_percent() as f32 / 100.0 - 1.0
     }
 
+    // How long odd steps wait at the current tempo
+    pub fn swing_delay(&self) -> Duration {
+        self.step_duration().mul_f32(self.swing_delay_fraction())
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.transport == Transport::Playing
+    }
+
+    // Play/pause; starting from Stopped is left to the caller to rewind
     pub fn toggle_playback(&mut self) {
-        self.is_playing = !self.is_playing;
+        self.transport = match self.transport {
+            Transport::Playing => Transport::Paused,
+            Transport::Paused | Transport::Stopped => Transport::Playing,
+        };
+        
+        // The elapsed clock holds still through a pause
+        if self.is_playing() {
+            self.play_started = Some(self.now());
+        } else if let Some(started) = self.play_started.take() {
+            self.elapsed_before += self.now().duration_since(started);
+        }
+    }
+
+    // Recording stays armed through a pause but not through a stop
+    pub fn stop(&mut self) {
+        self.transport = Transport::Stopped;
+        self.is_recording = false;
+        self.reset_position();
+    }
+
+    // Back to bar 1 with the elapsed clock at zero, still running if playing
+    pub fn reset_position(&mut self) {
+        self.bars_elapsed = 0;
+        self.elapsed_before = Duration::ZERO;
+        self.play_started = self.is_playing().then(|| self.now());
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_before + self.play_started.map_or(Duration::ZERO, |started| self.now().duration_since(started))
     }
 
     pub fn toggle_recording(&mut self) {
@@ -106,17 +551,20 @@ impl AppState {
     }
 
     pub fn update_tick_time(&mut self) {
-        self.last_tick = Instant::now();
+        self.last_tick = self.now();
     }
 
     pub fn should_tick(&self) -> bool {
-        if !self.is_playing {
-            return false;
-        }
-        
-        let elapsed = Instant::now().duration_since(self.last_tick);
-        let tick_duration = std::time::Duration::from_millis(60000 / (self.tempo * 4) as u64);
-        elapsed >= tick_duration
+        self.is_playing() && self.is_tick_due()
+    }
+
+    pub fn is_tick_due(&self) -> bool {
+        let elapsed = self.now().duration_since(self.last_tick);
+        elapsed >= self.step_duration()
+    }
+
+    pub fn step_duration(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / (self.tempo as f64 * self.steps_per_beat.max(1) as f64))
     }
 }
 
@@ -129,6 +577,7 @@ impl Default for AppState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sequencer::STEPS_PER_PATTERN;
 
     #[test]
     fn test_ui_state() {
@@ -155,6 +604,184 @@ mod tests {
         assert!(state.flashing_pads.is_empty());
     }
 
+    #[test]
+    fn test_flash_duration_is_configurable() {
+        let mut state = UIState::with_flash_duration(Some(Duration::from_millis(400)));
+        state.start_flash(vec![(0, 1)]);
+        
+        // Still lit well past the old fixed 150ms
+        state.flash_timer = Instant::now() - Duration::from_millis(200);
+        state.update_flash();
+        assert!(state.is_pad_flashing(0, 1));
+        
+        state.flash_timer = Instant::now() - Duration::from_millis(400);
+        state.update_flash();
+        assert!(state.flashing_pads.is_empty());
+        
+        // A shorter flash clears sooner than the default would
+        let mut state = UIState::with_flash_duration(Some(Duration::from_millis(40)));
+        state.start_flash(vec![(0, 1)]);
+        state.flash_timer = Instant::now() - Duration::from_millis(50);
+        state.update_flash();
+        assert!(state.flashing_pads.is_empty());
+    }
+
+    #[test]
+    fn test_flashing_can_be_turned_off() {
+        let mut state = UIState::with_flash_duration(None);
+        state.start_flash(vec![(0, 1)]);
+        assert!(!state.is_pad_flashing(0, 1));
+        
+        // Turning it off mid-flash drops what's lit
+        let mut state = UIState::new();
+        state.start_flash(vec![(0, 1)]);
+        state.set_flash_duration(None);
+        assert!(state.flashing_pads.is_empty());
+    }
+
+    #[test]
+    fn test_grid_mode_and_cursor() {
+        let mut state = UIState::new();
+        assert_eq!(state.grid_mode, GridMode::Steps);
+        
+        state.cycle_grid_mode();
+        assert_eq!(state.grid_mode, GridMode::Velocity);
+        state.cycle_grid_mode();
+        assert_eq!(state.grid_mode, GridMode::Probability);
+        state.cycle_grid_mode();
+        assert_eq!(state.grid_mode, GridMode::Steps);
+        
+        // The cursor wraps around the pattern
+        state.move_cursor_step(-1, STEPS_PER_PATTERN);
+        assert_eq!(state.cursor_step, STEPS_PER_PATTERN - 1);
+        state.move_cursor_step(2, STEPS_PER_PATTERN);
+        assert_eq!(state.cursor_step, 1);
+    }
+
+    #[test]
+    fn test_panel_toggle() {
+        let mut state = UIState::new();
+        assert_eq!(state.panel, Panel::Sequencer);
+        state.toggle_panel();
+        assert_eq!(state.panel, Panel::Mixer);
+        state.toggle_panel();
+        assert_eq!(state.panel, Panel::Sequencer);
+    }
+
+    #[test]
+    fn test_prompt_input() {
+        let mut prompt = Prompt::new(PromptKind::GotoPattern);
+        for c in "4x72".chars() {
+            prompt.push(c);
+        }
+        // Only digits are taken, and at most two of them
+        assert_eq!(prompt.input, "47");
+        prompt.backspace();
+        assert_eq!(prompt.input, "4");
+        prompt.backspace();
+        prompt.backspace();
+        assert_eq!(prompt.input, "");
+    }
+
+    #[test]
+    fn test_euclidean_prompt_input() {
+        let mut prompt = Prompt::new(PromptKind::Euclidean);
+        for c in ",5,,3x1".chars() {
+            prompt.push(c);
+        }
+        // A comma only separates, so it can't lead or repeat
+        assert_eq!(prompt.input, "5,31");
+        assert!(!prompt.is_complete());
+    }
+
+    #[test]
+    fn test_step_paging() {
+        let mut state = UIState::new();
+        state.move_cursor_step(3, 32);
+        
+        state.move_step_page(1, 32);
+        assert_eq!((state.step_page, state.cursor_step), (1, 19));
+        state.move_step_page(1, 32);
+        assert_eq!((state.step_page, state.cursor_step), (0, 3));
+        
+        // Moving the cursor past the page edge turns the page
+        state.move_cursor_step(-4, 32);
+        assert_eq!((state.step_page, state.cursor_step), (1, 31));
+        
+        // A short last page clamps the cursor
+        state.move_step_page(1, 24);
+        state.move_step_page(1, 24);
+        assert_eq!(state.cursor_step, 23);
+    }
+
+    #[test]
+    fn test_visible_steps_follow_playhead() {
+        let mut state = UIState::new();
+        state.move_step_page(1, 32);
+        assert_eq!(state.visible_steps(20, false, 32, 16), 16..32);
+        assert_eq!(state.visible_steps(5, true, 32, 16), 0..16);
+        // The playhead scrolls the view a step at a time and stays on the last column
+        assert_eq!(state.visible_steps(20, true, 32, 16), 5..21);
+        assert_eq!(state.visible_steps(20, true, 32, 8), 13..21);
+        
+        state.follow_playhead = false;
+        assert_eq!(state.visible_steps(5, true, 32, 16), 16..32);
+        // Stale scrolls from a longer pattern are clamped
+        assert_eq!(state.visible_steps(0, false, 16, 16), 0..16);
+    }
+
+    #[test]
+    fn test_cursor_scrolls_narrow_grid() {
+        let mut state = UIState::new();
+        state.move_cursor_step(10, 32);
+        // Ten columns only show the cursor once the view scrolls along
+        assert_eq!(state.visible_steps(0, false, 32, 10), 1..11);
+        state.move_cursor_step(-8, 32);
+        assert_eq!(state.visible_steps(0, false, 32, 10), 0..10);
+        // Wrapping to the end brings the last steps into view
+        state.move_cursor_step(-3, 32);
+        assert_eq!(state.visible_steps(0, false, 32, 10), 22..32);
+    }
+
+    #[test]
+    fn test_notifications() {
+        let mut state = UIState::new();
+        assert_eq!(state.active_notification(), None);
+        
+        state.notify("No input device");
+        assert_eq!(state.active_notification(), Some("No input device"));
+        
+        // Expired notifications are no longer shown
+        state.notification = Some(("Old".to_string(), Instant::now() - NOTIFICATION_DURATION));
+        assert_eq!(state.active_notification(), None);
+    }
+
+    #[test]
+    fn test_dirty_flag() {
+        let mut state = UIState::new();
+        // The first frame always draws
+        assert!(state.take_dirty());
+        assert!(!state.take_dirty());
+        
+        state.notify("Saved");
+        assert!(state.take_dirty());
+        
+        // Expiring a notification needs one more draw to clear it, then nothing
+        state.notification = Some(("Old".to_string(), Instant::now() - NOTIFICATION_DURATION));
+        state.update_notification();
+        assert!(state.take_dirty());
+        state.update_notification();
+        assert!(!state.take_dirty());
+        
+        state.start_flash(vec![(0, 1)]);
+        assert!(state.take_dirty());
+        state.flash_timer = Instant::now() - std::time::Duration::from_millis(200);
+        state.update_flash();
+        assert!(state.take_dirty());
+        state.update_flash();
+        assert!(!state.take_dirty());
+    }
+
     #[test]
     fn test_app_state_navigation() {
         let mut state = AppState::new();
@@ -201,28 +828,64 @@ mod tests {
     fn test_app_state_tempo() {
         let mut state = AppState::new();
         
-        assert_eq!(state.tempo, 120);
-        state.adjust_tempo(10);
-        assert_eq!(state.tempo, 130);
-        state.adjust_tempo(-20);
-        assert_eq!(state.tempo, 110);
+        assert_eq!(state.tempo, 120.0);
+        state.adjust_tempo(10.0);
+        assert_eq!(state.tempo, 130.0);
+        state.adjust_tempo(-20.0);
+        assert_eq!(state.tempo, 110.0);
         
         // Test bounds
-        state.adjust_tempo(-100);
-        assert_eq!(state.tempo, 60);
-        state.adjust_tempo(300);
-        assert_eq!(state.tempo, 300);
+        state.adjust_tempo(-100.0);
+        assert_eq!(state.tempo, 60.0);
+        state.adjust_tempo(300.0);
+        assert_eq!(state.tempo, 300.0);
+        
+        // Half-time tempos keep their fraction in the step length
+        state.set_tempo(87.5);
+        state.adjust_tempo(0.1);
+        assert_eq!(state.tempo, 87.6);
+        state.adjust_tempo(-0.1);
+        assert_eq!(state.step_duration(), Duration::from_secs_f64(15.0 / 87.5));
+    }
+
+    #[test]
+    fn test_step_duration_follows_the_resolution() {
+        let mut state = AppState::new();
+        state.set_tempo(120.0);
+        assert_eq!(state.step_duration(), Duration::from_millis(125));
+        state.steps_per_beat = 2;
+        assert_eq!(state.step_duration(), Duration::from_millis(250));
+        state.steps_per_beat = 3;
+        assert_eq!(state.step_duration().as_micros(), 166_666);
+    }
+
+    #[test]
+    fn test_swing_presets() {
+        let mut state = AppState::new();
+        let expected = [(50, 0.0), (54, 0.08), (58, 0.16), (62, 0.24), (66, 0.32)];
+        
+        for (index, (percent, fraction)) in expected.into_iter().enumerate() {
+            assert_eq!(state.swing_preset as usize, index);
+            assert_eq!(state.swing_percent(), percent);
+            assert!((state.swing_delay_fraction() - fraction).abs() < 1e-6);
+            state.cycle_swing();
+        }
+        
+        // Cycling past the last preset goes back to straight time
+        assert_eq!(state.swing_preset, 0);
+        assert_eq!(state.swing_delay(), Duration::ZERO);
     }
 
     #[test]
     fn test_app_state_toggles() {
         let mut state = AppState::new();
         
-        assert!(!state.is_playing);
+        assert!(!state.is_playing());
         state.toggle_playback();
-        assert!(state.is_playing);
+        assert!(state.is_playing());
         state.toggle_playback();
-        assert!(!state.is_playing);
+        assert!(!state.is_playing());
+        assert_eq!(state.transport, Transport::Paused);
         
         assert!(!state.is_recording);
         state.toggle_recording();
@@ -230,4 +893,77 @@ mod tests {
         state.toggle_recording();
         assert!(!state.is_recording);
     }
+
+    #[test]
+    fn test_stop_disarms_recording() {
+        let mut state = AppState::new();
+        state.toggle_recording();
+        state.toggle_playback();
+        
+        state.toggle_playback();
+        assert_eq!(state.transport, Transport::Paused);
+        assert!(state.is_recording);
+        
+        state.stop();
+        assert_eq!(state.transport, Transport::Stopped);
+        assert!(!state.is_recording);
+    }
+
+    #[test]
+    fn test_transport_position_at_four_steps_per_beat() {
+        assert_eq!(transport_position(0, 0, 4), (1, 1, 1));
+        assert_eq!(transport_position(0, 3, 4), (1, 1, 4));
+        assert_eq!(transport_position(0, 4, 4), (1, 2, 1));
+        assert_eq!(transport_position(2, 15, 4), (3, 4, 4));
+    }
+
+    #[test]
+    fn test_transport_position_at_other_resolutions() {
+        // 3/4 in 8ths: six steps, two to a beat
+        assert_eq!(transport_position(0, 1, 2), (1, 1, 2));
+        assert_eq!(transport_position(0, 5, 2), (1, 3, 2));
+        // 6/8 counted in 8th triplets
+        assert_eq!(transport_position(4, 8, 3), (5, 3, 3));
+        assert_eq!(transport_position(4, 9, 3), (5, 4, 1));
+        // 32nds
+        assert_eq!(transport_position(0, 31, 8), (1, 4, 8));
+        assert_eq!(transport_position(99, 0, 8), (100, 1, 1));
+    }
+
+    #[test]
+    fn test_elapsed_time_pauses_and_resets() {
+        let mut state = AppState::new();
+        assert_eq!(state.elapsed(), Duration::ZERO);
+        
+        state.toggle_playback();
+        std::thread::sleep(Duration::from_millis(5));
+        state.toggle_playback();
+        let paused = state.elapsed();
+        assert!(paused >= Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(state.elapsed(), paused);
+        
+        state.bars_elapsed = 3;
+        state.stop();
+        assert_eq!((state.bars_elapsed, state.elapsed()), (0, Duration::ZERO));
+    }
+
+
+    #[test]
+    fn test_manual_clock_drives_the_step_timer() {
+        let clock = ManualClock::new();
+        let mut state = AppState::new();
+        state.set_clock(Arc::new(clock.clone()));
+        state.toggle_playback();
+        assert!(!state.should_tick());
+        
+        clock.advance(state.step_duration() - Duration::from_millis(1));
+        assert!(!state.should_tick());
+        clock.advance(Duration::from_millis(1));
+        assert!(state.should_tick());
+        
+        state.update_tick_time();
+        assert!(!state.should_tick());
+        assert_eq!(state.elapsed(), state.step_duration());
+    }
 }
\ No newline at end of file
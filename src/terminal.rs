@@ -0,0 +1,149 @@
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+/// Puts the terminal back when dropped, so raw mode and the alternate screen don't outlive
+/// the UI whether it quits, returns an error or panics.
+pub struct TerminalGuard {
+    restore: Option<Box<dyn FnOnce()>>,
+}
+
+impl TerminalGuard {
+    /// Switches to raw mode and the alternate screen, with mouse capture if asked for.
+    pub fn enter(mouse: bool) -> io::Result<Self> {
+        enable_raw_mode()?;
+        // Guarded from here, so a failure below still leaves raw mode
+        let guard = Self::with_restore(move || restore_terminal(mouse));
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        Ok(guard)
+    }
+
+    /// Runs `restore` when dropped instead of the real terminal steps.
+    pub fn with_restore(restore: impl FnOnce() + 'static) -> Self {
+        Self { restore: Some(Box::new(restore)) }
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Some(restore) = self.restore.take() {
+            restore();
+        }
+    }
+}
+
+/// Leaves raw mode and the alternate screen. Each step is tried even if an earlier one
+/// fails, since this also runs while panicking.
+pub fn restore_terminal(mouse: bool) {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    if mouse {
+        let _ = execute!(stdout, DisableMouseCapture);
+    }
+    let _ = execute!(stdout, LeaveAlternateScreen, Show);
+}
+
+type PanicHook = dyn Fn(&std::panic::PanicHookInfo<'_>) + Sync + Send + 'static;
+
+/// Puts back the panic hook [`restore_on_panic`] replaced when dropped.
+pub struct PanicHookGuard {
+    previous: Arc<PanicHook>,
+}
+
+impl Drop for PanicHookGuard {
+    fn drop(&mut self) {
+        let previous = Arc::clone(&self.previous);
+        std::panic::set_hook(Box::new(move |info| previous(info)));
+    }
+}
+
+/// Restores the terminal before a panic's message is printed, which would otherwise land on
+/// the alternate screen and vanish with it. Only a panic on the calling thread does; the UI
+/// keeps the screen when an audio, OSC or MIDI thread dies.
+pub fn restore_on_panic(mouse: bool) -> PanicHookGuard {
+    restore_on_panic_with(move || restore_terminal(mouse))
+}
+
+fn restore_on_panic_with(restore: impl Fn() + Sync + Send + 'static) -> PanicHookGuard {
+    let previous: Arc<PanicHook> = Arc::from(std::panic::take_hook());
+    let ui_thread = thread::current().id();
+    let chained = Arc::clone(&previous);
+    std::panic::set_hook(Box::new(move |info| {
+        if thread::current().id() == ui_thread {
+            restore();
+        }
+        chained(info);
+    }));
+    PanicHookGuard { previous }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_guard(restores: &Arc<AtomicUsize>) -> TerminalGuard {
+        let restores = Arc::clone(restores);
+        TerminalGuard::with_restore(move || {
+            restores.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[test]
+    fn test_guard_restores_once_on_every_exit() {
+        let restores = Arc::new(AtomicUsize::new(0));
+        
+        // Dropped normally
+        let guard = counting_guard(&restores);
+        assert_eq!(restores.load(Ordering::SeqCst), 0);
+        drop(guard);
+        assert_eq!(restores.load(Ordering::SeqCst), 1);
+        
+        // Returning early with an error
+        let failing = || -> Result<(), String> {
+            let _guard = counting_guard(&restores);
+            Err("run failed".to_string())?;
+            Ok(())
+        };
+        assert!(failing().is_err());
+        assert_eq!(restores.load(Ordering::SeqCst), 2);
+        
+        // Unwinding from a panic
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = counting_guard(&restores);
+            panic!("mid-run");
+        }));
+        assert!(result.is_err());
+        assert_eq!(restores.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_panic_hook_only_restores_for_the_ui_thread() {
+        let restores = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&restores);
+        let guard = restore_on_panic_with(move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        });
+        
+        // A worker dying leaves the screen to the UI
+        assert!(thread::spawn(|| panic!("worker")).join().is_err());
+        assert_eq!(restores.load(Ordering::SeqCst), 0);
+        assert!(std::panic::catch_unwind(|| panic!("ui")).is_err());
+        assert_eq!(restores.load(Ordering::SeqCst), 1);
+        
+        // Once the UI is done, panics go back to the hook from before
+        drop(guard);
+        assert!(std::panic::catch_unwind(|| panic!("after")).is_err());
+        assert_eq!(restores.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,313 @@
+use ratatui::style::Color;
+use crate::config::{GroupConfig, ThemeConfig};
+use crate::error::{AppError, Result};
+
+// Standard xterm values for the 16 ANSI colors, used to approximate RGB themes
+const ANSI_PALETTE: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub group_colors: Vec<Color>, // Reused in order when there are more groups
+    pub selected_pad: Color,
+    pub flashing_pad: Color,
+    pub step_highlight: Color,
+    pub accent: Color,     // Active tab, playing transport, loaded pads
+    pub muted: Color,      // Empty pads and steps, help text
+    pub background: Color, // Text on highlighted cells and empty pad fill
+    pub text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            group_colors: vec![
+                Color::Rgb(100, 150, 150), // DRUMS - muted teal
+                Color::Rgb(100, 100, 150), // BASS - muted blue
+                Color::Rgb(150, 100, 150), // LEAD - muted purple
+                Color::Rgb(150, 150, 100), // VOCAL - muted gold
+            ],
+            selected_pad: Color::Cyan,
+            flashing_pad: Color::Magenta,
+            step_highlight: Color::White,
+            accent: Color::Cyan,
+            muted: Color::DarkGray,
+            background: Color::Black,
+            text: Color::White,
+        }
+    }
+}
+
+impl Theme {
+    pub fn high_contrast() -> Self {
+        Self {
+            group_colors: vec![
+                Color::LightCyan,
+                Color::LightBlue,
+                Color::LightMagenta,
+                Color::LightYellow,
+            ],
+            selected_pad: Color::LightGreen,
+            flashing_pad: Color::LightRed,
+            step_highlight: Color::Yellow,
+            accent: Color::LightCyan,
+            muted: Color::DarkGray,
+            background: Color::Black,
+            text: Color::White,
+        }
+    }
+
+    // Grays only, for terminals with few or unreliable colors
+    pub fn mono() -> Self {
+        Self {
+            group_colors: vec![Color::White; 4],
+            selected_pad: Color::White,
+            flashing_pad: Color::DarkGray,
+            step_highlight: Color::Gray,
+            accent: Color::White,
+            muted: Color::DarkGray,
+            background: Color::Black,
+            text: Color::Gray,
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(Self::default()),
+            "high-contrast" | "high_contrast" => Some(Self::high_contrast()),
+            "mono" => Some(Self::mono()),
+            _ => None,
+        }
+    }
+
+    // Builds the preset with any overrides applied; bad names or colors are errors.
+    // A color in a [[groups]] entry wins over the theme's group_colors
+    pub fn from_config(config: &ThemeConfig, groups: &[GroupConfig]) -> Result<Self> {
+        let mut theme = Self::preset(&config.preset).ok_or_else(|| {
+            AppError::Config(format!(
+                "Unknown theme preset '{}' (expected default, high-contrast or mono)",
+                config.preset
+            ))
+        })?;
+
+        if let Some(colors) = &config.group_colors {
+            if colors.len() > theme.group_colors.len() {
+                return Err(AppError::Config(format!(
+                    "Theme has {} group colors, expected at most {}",
+                    colors.len(),
+                    theme.group_colors.len()
+                )));
+            }
+            for (slot, value) in theme.group_colors.iter_mut().zip(colors) {
+                *slot = require_color("group_colors", value)?;
+            }
+        }
+        for (group, entry) in groups.iter().enumerate() {
+            let Some(value) = &entry.color else {
+                continue;
+            };
+            while theme.group_colors.len() <= group {
+                let next = theme.group_color(theme.group_colors.len());
+                theme.group_colors.push(next);
+            }
+            theme.group_colors[group] = require_color(&format!("groups[{}].color", group), value)?;
+        }
+
+        let overrides = [
+            (&config.selected_pad, &mut theme.selected_pad, "selected_pad"),
+            (&config.flashing_pad, &mut theme.flashing_pad, "flash"),
+            (&config.step_highlight, &mut theme.step_highlight, "step_highlight"),
+            (&config.accent, &mut theme.accent, "accent"),
+            (&config.muted, &mut theme.muted, "muted"),
+            (&config.background, &mut theme.background, "background"),
+            (&config.text, &mut theme.text, "text"),
+        ];
+        for (value, slot, key) in overrides {
+            if let Some(value) = value {
+                *slot = require_color(key, value)?;
+            }
+        }
+
+        let truecolor = config.truecolor.unwrap_or_else(supports_truecolor);
+        Ok(if truecolor { theme } else { theme.to_ansi() })
+    }
+
+    pub fn group_color(&self, group: usize) -> Color {
+        // Layouts with more groups than colors reuse them in order
+        self.group_colors[group % self.group_colors.len()]
+    }
+
+    // Swaps every RGB color for the closest of the 16 ANSI colors
+    pub fn to_ansi(&self) -> Self {
+        Self {
+            group_colors: self.group_colors.iter().copied().map(nearest_ansi).collect(),
+            selected_pad: nearest_ansi(self.selected_pad),
+            flashing_pad: nearest_ansi(self.flashing_pad),
+            step_highlight: nearest_ansi(self.step_highlight),
+            accent: nearest_ansi(self.accent),
+            muted: nearest_ansi(self.muted),
+            background: nearest_ansi(self.background),
+            text: nearest_ansi(self.text),
+        }
+    }
+}
+
+fn require_color(key: &str, value: &str) -> Result<Color> {
+    parse_color(value)
+        .ok_or_else(|| AppError::Config(format!("Invalid theme color '{}' for {}", value, key)))
+}
+
+// Accepts hex ("#6496a0") and ANSI color names ("cyan", "light-red", "dark gray")
+pub fn parse_color(value: &str) -> Option<Color> {
+    value.trim().parse::<Color>().ok()
+}
+
+// Terminals advertise 24-bit color through COLORTERM
+pub fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM")
+        .map(|value| matches!(value.to_lowercase().as_str(), "truecolor" | "24bit"))
+        .unwrap_or(false)
+}
+
+pub fn nearest_ansi(color: Color) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+
+    let distance = |&(_, (pr, pg, pb)): &(Color, (u8, u8, u8))| {
+        let dr = r as i32 - pr as i32;
+        let dg = g as i32 - pg as i32;
+        let db = b as i32 - pb as i32;
+        dr * dr + dg * dg + db * db
+    };
+    ANSI_PALETTE
+        .iter()
+        .min_by_key(|entry| distance(entry))
+        .map(|&(ansi, _)| ansi)
+        .unwrap_or(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color_hex() {
+        assert_eq!(parse_color("#6496a0"), Some(Color::Rgb(100, 150, 160)));
+        assert_eq!(parse_color("#FFFFFF"), Some(Color::Rgb(255, 255, 255)));
+        assert_eq!(parse_color(" #000000 "), Some(Color::Rgb(0, 0, 0)));
+    }
+
+    #[test]
+    fn test_parse_color_named() {
+        assert_eq!(parse_color("cyan"), Some(Color::Cyan));
+        assert_eq!(parse_color("Magenta"), Some(Color::Magenta));
+        assert_eq!(parse_color("dark-gray"), Some(Color::DarkGray));
+        assert_eq!(parse_color("light red"), Some(Color::LightRed));
+    }
+
+    #[test]
+    fn test_parse_color_invalid() {
+        assert_eq!(parse_color(""), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#gggggg"), None);
+        assert_eq!(parse_color("not-a-color"), None);
+    }
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(Theme::preset("default"), Some(Theme::default()));
+        assert_eq!(Theme::preset("high-contrast"), Some(Theme::high_contrast()));
+        assert_eq!(Theme::preset("Mono"), Some(Theme::mono()));
+        assert_eq!(Theme::preset("nope"), None);
+        assert_ne!(Theme::default(), Theme::high_contrast());
+    }
+
+    #[test]
+    fn test_theme_from_config_overrides() {
+        let config = ThemeConfig {
+            preset: "high-contrast".to_string(),
+            group_colors: Some(vec!["#010203".to_string()]),
+            selected_pad: Some("green".to_string()),
+            accent: Some("#ff8000".to_string()),
+            truecolor: Some(true),
+            ..ThemeConfig::default()
+        };
+
+        let theme = Theme::from_config(&config, &[]).unwrap();
+        assert_eq!(theme.group_colors[0], Color::Rgb(1, 2, 3));
+        assert_eq!(theme.group_colors[1], Theme::high_contrast().group_colors[1]);
+        assert_eq!(theme.selected_pad, Color::Green);
+        assert_eq!(theme.accent, Color::Rgb(255, 128, 0));
+        assert_eq!(theme.flashing_pad, Theme::high_contrast().flashing_pad);
+    }
+
+    #[test]
+    fn test_theme_from_config_rejects_bad_values() {
+        let bad_color = ThemeConfig {
+            group_colors: Some(vec!["#010203".to_string(), "bogus".to_string()]),
+            ..ThemeConfig::default()
+        };
+        assert!(Theme::from_config(&bad_color, &[]).is_err());
+
+        let bad_hex = ThemeConfig { text: Some("#12345".to_string()), ..ThemeConfig::default() };
+        assert!(Theme::from_config(&bad_hex, &[]).is_err());
+
+        let too_many = ThemeConfig { group_colors: Some(vec!["red".to_string(); 5]), ..ThemeConfig::default() };
+        assert!(Theme::from_config(&too_many, &[]).is_err());
+
+        let bad_preset = ThemeConfig { preset: "neon".to_string(), ..ThemeConfig::default() };
+        assert!(Theme::from_config(&bad_preset, &[]).is_err());
+    }
+
+    #[test]
+    fn test_group_entry_colors() {
+        let config = ThemeConfig { truecolor: Some(true), ..ThemeConfig::default() };
+        let groups = [
+            GroupConfig { color: Some("#102030".to_string()), ..GroupConfig::default() },
+            GroupConfig::default(),
+            GroupConfig::default(),
+            GroupConfig::default(),
+            GroupConfig::default(),
+            GroupConfig { color: Some("red".to_string()), ..GroupConfig::default() },
+        ];
+        let theme = Theme::from_config(&config, &groups).unwrap();
+        assert_eq!(theme.group_color(0), Color::Rgb(16, 32, 48));
+        assert_eq!(theme.group_color(1), Theme::default().group_color(1));
+        // Groups between the preset's colors and the entry keep cycling through the preset
+        assert_eq!(theme.group_color(4), Color::Rgb(16, 32, 48));
+        assert_eq!(theme.group_color(5), Color::Red);
+
+        let bad = [GroupConfig { color: Some("bogus".to_string()), ..GroupConfig::default() }];
+        assert!(Theme::from_config(&config, &bad).is_err());
+    }
+
+    #[test]
+    fn test_nearest_ansi() {
+        assert_eq!(nearest_ansi(Color::Rgb(250, 5, 5)), Color::LightRed);
+        assert_eq!(nearest_ansi(Color::Rgb(10, 10, 10)), Color::Black);
+        assert_eq!(nearest_ansi(Color::Rgb(120, 130, 125)), Color::DarkGray);
+        // Named colors are already ANSI
+        assert_eq!(nearest_ansi(Color::Magenta), Color::Magenta);
+
+        let config = ThemeConfig { truecolor: Some(false), ..ThemeConfig::default() };
+        let theme = Theme::from_config(&config, &[]).unwrap();
+        assert!(!theme.group_colors.iter().any(|color| matches!(color, Color::Rgb(..))));
+    }
+}
@@ -1,15 +1,127 @@
-use crate::app::App;
+use crate::app::{App, TEMPO_DRIFT_BPM};
+use crate::bus::master_gain_label;
+use crate::pad_settings::PlaybackMode;
+use crate::sequencer::{page_range, ClockDivision, Pattern, Signature, MAX_PATTERNS, MAX_VELOCITY, PADS_PER_GROUP, STEPS_PER_PAGE};
+use crate::state::{GridMode, Panel, Prompt, Transport, ViewMode};
+use crate::theme::Theme;
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Flex, Layout, Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Cell, Paragraph, Row, Table, Tabs,
+        Block, BorderType, Borders, Cell, Clear, Paragraph, Row, Table, Tabs,
     },
     Frame,
 };
 
-pub fn draw(f: &mut Frame, app: &App) {
+// Slots per row in the pattern map; arrows up and down jump a whole row
+pub const PATTERN_MAP_COLUMNS: usize = 10;
+
+// Below this the UI can't render anything useful
+const MIN_WIDTH: u16 = 60;
+const MIN_HEIGHT: u16 = 18;
+
+// Pad label column + 16 three-wide step columns + borders
+const SEQUENCER_WIDTH: u16 = 3 + 16 * 3 + 2;
+
+const METER_SEGMENTS: usize = 10;
+// Pattern info box + header row + 16 pad rows + borders
+const SEQUENCER_HEIGHT: u16 = 3 + 1 + 16 + 1 + 2;
+const MIXER_WIDTH: u16 = 26;
+const STACKED_PADS_HEIGHT: u16 = 12;
+const STACKED_MIXER_HEIGHT: u16 = 5;
+
+// Header + footer
+const CHROME_HEIGHT: u16 = 6;
+
+// Width needed to show pads, sequencer and mixer side by side
+const FULL_MIN_WIDTH: u16 = SEQUENCER_WIDTH + MIXER_WIDTH + 33;
+// Below this pads and a side panel can't share the screen comfortably
+const COMPACT_MIN_WIDTH: u16 = 80;
+const COMPACT_MIN_HEIGHT: u16 = 24;
+const STACKED_MIN_HEIGHT: u16 = CHROME_HEIGHT + STACKED_PADS_HEIGHT + SEQUENCER_HEIGHT + STACKED_MIXER_HEIGHT;
+
+// Screen areas the mouse can act on, recorded while drawing each frame
+#[derive(Debug, Clone, Default)]
+pub struct HitAreas {
+    pads: Vec<(Rect, usize)>,
+    steps: Vec<(Rect, usize, usize)>, // (cell, pad, step)
+    accents: Vec<(Rect, usize)>,      // (cell, step) in the accent row
+    tempo: Option<Rect>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTarget {
+    Pad(usize),
+    Step(usize, usize), // (pad, step)
+    Accent(usize),      // Step
+    Tempo,
+}
+
+impl HitAreas {
+    pub fn target_at(&self, column: u16, row: u16) -> Option<HitTarget> {
+        let position = Position::new(column, row);
+        if let Some(&(_, pad)) = self.pads.iter().find(|(area, _)| area.contains(position)) {
+            return Some(HitTarget::Pad(pad));
+        }
+        if let Some(&(_, pad, step)) = self.steps.iter().find(|(area, _, _)| area.contains(position)) {
+            return Some(HitTarget::Step(pad, step));
+        }
+        if let Some(&(_, step)) = self.accents.iter().find(|(area, _)| area.contains(position)) {
+            return Some(HitTarget::Accent(step));
+        }
+        self.tempo
+            .filter(|area| area.contains(position))
+            .map(|_| HitTarget::Tempo)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    Full,     // Pads, sequencer and mixer side by side
+    Compact,  // Pads beside a sequencer/mixer panel switched with a key
+    Stacked,  // Pads, sequencer and mixer in a single column
+    Minimal,  // Just the switchable panel and a status line
+    TooSmall, // Only a resize hint
+}
+
+pub fn layout_mode(area: Rect) -> LayoutMode {
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        LayoutMode::TooSmall
+    } else if area.width >= FULL_MIN_WIDTH && area.height >= COMPACT_MIN_HEIGHT {
+        LayoutMode::Full
+    } else if area.width < COMPACT_MIN_WIDTH && area.height >= STACKED_MIN_HEIGHT {
+        LayoutMode::Stacked
+    } else if area.width >= COMPACT_MIN_WIDTH && area.height >= COMPACT_MIN_HEIGHT {
+        LayoutMode::Compact
+    } else {
+        LayoutMode::Minimal
+    }
+}
+
+pub fn draw(f: &mut Frame, app: &App, theme: &Theme) -> HitAreas {
+    let mut hits = HitAreas::default();
+    let mode = layout_mode(f.area());
+    match mode {
+        LayoutMode::TooSmall => {
+            draw_too_small(f, f.area());
+            return hits;
+        }
+        LayoutMode::Minimal => {
+            let [panel, status] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(f.area());
+            if app.ui_state.view == ViewMode::Overview {
+                draw_overview(f, panel, app, theme);
+            } else {
+                draw_panel(f, panel, app, theme, &mut hits);
+            }
+            draw_status_line(f, status, app, theme);
+            draw_pad_detail(f, f.area(), app, theme);
+            draw_pattern_map(f, f.area(), app, theme);
+            return hits;
+        }
+        _ => {}
+    }
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -19,38 +131,234 @@ pub fn draw(f: &mut Frame, app: &App) {
         ])
         .split(f.area());
 
-    draw_header(f, chunks[0], app);
-    draw_main_content(f, chunks[1], app);
-    draw_footer(f, chunks[2], app);
-}
-
-fn draw_header(f: &mut Frame, area: Rect, app: &App) {
-    let group_names = ["DRUMS", "BASS", "LEAD", "VOCAL"];
-    let titles: Vec<Line> = group_names
-        .iter()
-        .enumerate()
-        .map(|(i, name)| {
-            let group_color = match i {
-                0 => Color::Rgb(100, 150, 150), // DRUMS - muted teal
-                1 => Color::Rgb(100, 100, 150), // BASS - muted blue  
-                2 => Color::Rgb(150, 100, 150), // LEAD - muted purple
-                3 => Color::Rgb(150, 150, 100), // VOCAL - muted gold
-                _ => Color::DarkGray,
+    draw_header(f, chunks[0], app, theme);
+    draw_main_content(f, chunks[1], app, theme, mode, &mut hits);
+    draw_footer(f, chunks[2], app, theme, &mut hits);
+    draw_pad_detail(f, f.area(), app, theme);
+    draw_pattern_map(f, f.area(), app, theme);
+    hits
+}
+
+// Modal with what's loaded on the selected pad and how it's set to play
+fn draw_pad_detail(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let (true, Some(pad)) = (app.ui_state.pad_detail, app.ui_state.selected_pad) else {
+        return;
+    };
+    let group = app.get_current_group();
+    let label = |name: &str| Span::styled(format!("{:<8}", name), Style::default().fg(theme.muted));
+
+    let mut lines = vec![Line::from(vec![
+        label("Sample"),
+        Span::raw(app.sample_bank.get_sample_name(group, pad).unwrap_or("-").to_string()),
+    ])];
+    match app.sample_bank.get_sample_info(group, pad) {
+        Some(info) => {
+            let format = app.sample_bank.get_sample_format(group, pad).map_or("?", |format| format.label());
+            let channels = match info.channels {
+                1 => "mono".to_string(),
+                2 => "stereo".to_string(),
+                channels => format!("{} ch", channels),
+            };
+            let length = info.duration.map_or("unknown".to_string(), |duration| format!("{:.2}s", duration.as_secs_f32()));
+            lines.push(Line::from(vec![
+                label("File"),
+                Span::raw(app.sample_bank.get_sample_path(group, pad).unwrap_or("(in memory)").to_string()),
+            ]));
+            lines.push(Line::from(vec![
+                label("Format"),
+                Span::raw(format!("{} · {:.1} kHz · {}", format, info.sample_rate as f32 / 1000.0, channels)),
+            ]));
+            lines.push(Line::from(vec![label("Length"), Span::raw(length)]));
+            let memory = if app.sample_bank.is_streamed(group, pad) {
+                format!("streamed from disk · {} file", format_bytes(info.bytes))
+            } else {
+                format_bytes(info.bytes)
+            };
+            lines.push(Line::from(vec![label("Memory"), Span::raw(memory)]));
+            let level = match app.sample_bank.get_loudness(group, pad) {
+                Some(loudness) => format!(
+                    "{} dB · peak {} · RMS {} dBFS",
+                    format_db(loudness.gain),
+                    format_db(loudness.peak),
+                    format_db(loudness.rms)
+                ),
+                None => "off".to_string(),
+            };
+            lines.push(Line::from(vec![label("Norm"), Span::raw(level)]));
+            // Velocity ranges switch the pad over from its layer mode
+            let velocity = |index| {
+                app.sample_bank
+                    .get_velocity_range(group, pad, index)
+                    .map_or(String::new(), |(min, max)| format!(" · vel {}–{}", min, max))
+            };
+            if app.sample_bank.layer_count(group, pad) > 1 {
+                let switched = (0..app.sample_bank.layer_count(group, pad)).any(|index| app.sample_bank.get_velocity_range(group, pad, index).is_some());
+                let mode = if switched { "velocity" } else { app.sample_bank.get_layer_mode(group, pad).label() };
+                lines.push(Line::from(vec![label("Layers"), Span::raw(format!("{}{}", mode, velocity(0)))]));
+            }
+            for (index, layer) in app.sample_bank.get_layers(group, pad).iter().enumerate() {
+                lines.push(Line::from(vec![
+                    label(&format!("Layer {}", index + 1)),
+                    Span::raw(format!("{} · {} dB{}", layer.name, format_db(layer.gain), velocity(index + 1))),
+                ]));
+            }
+        }
+        None => lines.push(Line::from(Span::styled("No sample loaded", Style::default().fg(theme.muted)))),
+    }
+
+    let settings = app.mixer.get_pad_settings(group, pad);
+    let (start, end) = app.sample_bank.get_trim(group, pad);
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        label("Gain"),
+        Span::raw(format!("{:.2}", settings.gain)),
+        Span::raw(format!("   Pan {:+.2}   Pitch {:+.1} st", settings.pan, settings.pitch)),
+    ]));
+    lines.push(Line::from(vec![
+        label("Trim"),
+        Span::raw(format!("{:.0}–{:.0}%", start * 100.0, end * 100.0)),
+    ]));
+    lines.push(Line::from(vec![
+        label("Mode"),
+        Span::raw(format!(
+            "{}{}{}",
+            match settings.mode {
+                PlaybackMode::OneShot => "one-shot",
+                PlaybackMode::Choke => "choke",
+            },
+            if settings.reverse { " · reversed" } else { "" },
+            if app.mixer.is_pad_muted(group, pad) { " · muted" } else { "" },
+        )),
+    ]));
+
+    // Every group's samples stay in memory, which adds up with long stereo files
+    let (count, bytes) = app.sample_bank.memory_usage();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("Bank: {} samples · {}", count, format_bytes(bytes)),
+        Style::default().fg(theme.muted),
+    )));
+
+    let group_color = theme.group_color(group);
+    let width = area.width.min(60);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let [detail] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(area);
+    let [detail] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(detail);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(group_color))
+        .title(format!(" {} · pad {} ", app.sample_bank.get_group_name(group), pad + 1));
+    f.render_widget(Clear, detail);
+    f.render_widget(Paragraph::new(lines).block(block), detail);
+}
+
+// Silence reads as -inf rather than a huge negative number
+fn format_db(gain: f32) -> String {
+    if gain > 0.0 {
+        format!("{:+.1}", 20.0 * gain.log10())
+    } else {
+        "-inf".to_string()
+    }
+}
+
+// Sizes in the units a sample library is usually measured in
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+// Modal grid of every pattern slot in the current group, drawn over everything else
+fn draw_pattern_map(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let Some(cursor) = app.ui_state.pattern_map else {
+        return;
+    };
+
+    let rows = MAX_PATTERNS.div_ceil(PATTERN_MAP_COLUMNS);
+    let width = (PATTERN_MAP_COLUMNS * 3 + 1) as u16 + 2;
+    let height = rows as u16 + 2;
+    let [map] = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center).areas(area);
+    let [map] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(map);
+
+    let group_color = theme.group_color(app.get_current_group());
+    let used = app.used_patterns();
+    let active = app.get_current_pattern();
+
+    let lines: Vec<Line> = (0..rows)
+        .map(|row| {
+            let mut spans = vec![Span::raw(" ")];
+            for slot in (row * PATTERN_MAP_COLUMNS..(row + 1) * PATTERN_MAP_COLUMNS).take_while(|&s| s < MAX_PATTERNS) {
+                let mut style = if used.binary_search(&slot).is_ok() {
+                    Style::default().fg(theme.background).bg(group_color)
+                } else {
+                    Style::default().fg(theme.muted)
+                };
+                if slot == active {
+                    style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+                }
+                if slot == cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                spans.push(Span::styled(format!("{:02}", slot + 1), style));
+                spans.push(Span::raw(" "));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(group_color))
+        .title(format!(" Patterns · {:02} ", cursor + 1))
+        .title_bottom(" ⏎ go · Esc close ");
+    f.render_widget(Clear, map);
+    f.render_widget(Paragraph::new(lines).block(block), map);
+}
+
+fn draw_too_small(f: &mut Frame, area: Rect) {
+    let text = format!(
+        "Terminal too small\n{}x{} (need at least {}x{})",
+        area.width, area.height, MIN_WIDTH, MIN_HEIGHT
+    );
+    let message = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .alignment(Alignment::Center);
+    f.render_widget(message, area);
+}
+
+fn draw_header(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let titles: Vec<Line> = (0..app.group_count())
+        .map(|i| {
+            // Groups scanned from kit folders show the kit that's loaded
+            let name = match app.sample_bank.get_kit_name(i) {
+                Some(kit) => format!("{}/{}", app.sample_bank.get_group_name(i), kit),
+                None => app.sample_bank.get_group_name(i),
             };
+            let group_color = theme.group_color(i);
             
-            if i == app.get_current_group() {
-                Line::from(Span::styled(
+            let tab = if i == app.get_current_group() {
+                Span::styled(
                     format!(" {} ", name),
                     Style::default()
-                        .fg(Color::Black)
+                        .fg(theme.background)
                         .bg(group_color)
                         .add_modifier(Modifier::BOLD),
-                ))
+                )
             } else {
-                Line::from(Span::styled(
+                Span::styled(
                     format!(" {} ", name),
                     Style::default().fg(group_color),
-                ))
+                )
+            };
+            // Armed groups get a record dot; with none armed, hits record into the current group
+            if app.is_record_armed(i) {
+                Line::from(vec![tab, Span::styled("●", Style::default().fg(Color::Red))])
+            } else {
+                Line::from(tab)
             }
         })
         .collect();
@@ -58,49 +366,295 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
     let tabs = Tabs::new(titles)
         .block(Block::default().borders(Borders::ALL).title("K.O.II Terminal"))
         .select(app.get_current_group())
-        .style(Style::default().fg(Color::White))
-        .highlight_style(Style::default().fg(Color::Cyan));
+        .style(Style::default().fg(theme.text))
+        .highlight_style(Style::default().fg(theme.accent));
 
     f.render_widget(tabs, area);
 }
 
-fn draw_main_content(f: &mut Frame, area: Rect, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(50), // Pad grid
-            Constraint::Percentage(25), // Pattern view
-            Constraint::Percentage(25), // Mixer
-        ])
-        .split(area);
+fn draw_main_content(f: &mut Frame, area: Rect, app: &App, theme: &Theme, mode: LayoutMode, hits: &mut HitAreas) {
+    if app.ui_state.view == ViewMode::Overview {
+        draw_overview(f, area, app, theme);
+        return;
+    }
+
+    match mode {
+        LayoutMode::Full => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Min(0),                    // Pad grid
+                    Constraint::Length(SEQUENCER_WIDTH),   // Pattern view
+                    Constraint::Length(MIXER_WIDTH),       // Mixer
+                ])
+                .split(area);
+
+            draw_pad_grid(f, chunks[0], app, theme, hits);
+            draw_pattern_view(f, chunks[1], app, theme, hits);
+            draw_mixer(f, chunks[2], app, theme);
+        }
+        LayoutMode::Compact => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Min(0),                    // Pad grid
+                    Constraint::Length(SEQUENCER_WIDTH),   // Sequencer or mixer
+                ])
+                .split(area);
 
-    draw_pad_grid(f, chunks[0], app);
-    draw_pattern_view(f, chunks[1], app);
-    draw_mixer(f, chunks[2], app);
+            draw_pad_grid(f, chunks[0], app, theme, hits);
+            
+            let [tabs, panel] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(chunks[1]);
+            draw_panel_tabs(f, tabs, app, theme);
+            draw_panel(f, panel, app, theme, hits);
+        }
+        LayoutMode::Stacked => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(STACKED_PADS_HEIGHT),  // Pad grid
+                    Constraint::Length(SEQUENCER_HEIGHT),     // Pattern view
+                    Constraint::Min(STACKED_MIXER_HEIGHT),    // Mixer
+                ])
+                .split(area);
+
+            draw_pad_grid(f, chunks[0], app, theme, hits);
+            draw_pattern_view(f, chunks[1], app, theme, hits);
+            draw_mixer(f, chunks[2], app, theme);
+        }
+        LayoutMode::Minimal | LayoutMode::TooSmall => {}
+    }
+}
+
+fn draw_panel(f: &mut Frame, area: Rect, app: &App, theme: &Theme, hits: &mut HitAreas) {
+    match app.ui_state.panel {
+        Panel::Sequencer => draw_pattern_view(f, area, app, theme, hits),
+        Panel::Mixer => draw_mixer(f, area, app, theme),
+    }
+}
+
+// Every group's active pattern at once, one lane per group
+fn draw_overview(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let groups = app.group_count();
+    let lanes = Layout::vertical(vec![Constraint::Ratio(1, groups as u32); groups]).split(area);
+    for (group, &lane) in lanes.iter().enumerate() {
+        draw_overview_lane(f, lane, app, theme, group);
+    }
+}
+
+fn draw_overview_lane(f: &mut Frame, area: Rect, app: &App, theme: &Theme, group: usize) {
+    let color = theme.group_color(group);
+    let pattern = app.get_group_pattern_data(group);
+    let length = pattern.map_or(STEPS_PER_PAGE, |pattern| pattern.length);
+
+    // Each lane wraps at its own length and clock, so lanes can disagree with the shared step
+    let playhead = app.get_group_pattern_step(group);
+    let steps = page_range(playhead / STEPS_PER_PAGE, length);
+
+    let (border_type, border_color) = if group == app.get_current_group() {
+        (BorderType::Thick, color)
+    } else {
+        (BorderType::Plain, theme.muted)
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(border_type)
+        .border_style(Style::default().fg(border_color))
+        .title(Span::styled(
+            format!(
+                " {} {} · Pattern {:02}{} · Step {:02}/{} ",
+                group + 1,
+                app.sample_bank.get_group_name(group),
+                app.get_group_pattern(group) + 1,
+                division_suffix(app.get_clock_division(group)),
+                playhead + 1,
+                length
+            ),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let step_style = |step: usize, lit: bool| {
+        if step == playhead && app.is_playing() {
+            Style::default().fg(theme.background).bg(theme.step_highlight)
+        } else if lit {
+            Style::default().fg(color)
+        } else {
+            Style::default().fg(theme.muted)
+        }
+    };
+
+    // The density row always shows; pads with hits get their own strip while there's room
+    let mut density = vec![Span::styled("Σ  ", Style::default().fg(theme.text))];
+    for step in steps.clone() {
+        let count = pattern.map_or(0, |pattern| pattern.get_hits_at_step(step).len());
+        density.push(Span::styled(format!("{} ", density_glyph(count)), step_style(step, count > 0)));
+    }
+    let mut lines = vec![Line::from(density)];
+
+    let pads_with_hits = (0..app.pad_count()).filter(|&pad| pattern.is_some_and(|pattern| pattern.steps[pad].contains(&true)));
+    for pad in pads_with_hits.take(inner.height.saturating_sub(1) as usize) {
+        let mut strip = vec![Span::styled(format!("{:2} ", pad), Style::default().fg(theme.text))];
+        for step in steps.clone() {
+            let hit = pattern.is_some_and(|pattern| pattern.steps[pad][step]);
+            strip.push(Span::styled(if hit { "● " } else { "· " }, step_style(step, hit)));
+        }
+        lines.push(Line::from(strip));
+    }
+
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+// How many pads hit on a step, as a heat glyph
+pub fn density_glyph(hits: usize) -> char {
+    match hits {
+        0 => '·',
+        1 => '░',
+        2 => '▒',
+        3 => '▓',
+        _ => '█',
+    }
 }
 
-fn draw_pad_grid(f: &mut Frame, area: Rect, app: &App) {
+fn draw_panel_tabs(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let panels = [Panel::Sequencer, Panel::Mixer];
+    let selected = panels.iter().position(|&panel| panel == app.ui_state.panel).unwrap_or(0);
+    let tabs = Tabs::new(panels.iter().map(|panel| panel.label()).collect::<Vec<_>>())
+        .select(selected)
+        .style(Style::default().fg(theme.muted))
+        .highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, area);
+}
+
+// Groups at normal speed don't show their division
+fn division_suffix(division: ClockDivision) -> String {
+    match division {
+        ClockDivision::Normal => String::new(),
+        division => format!(" {}", division.label()),
+    }
+}
+
+// Chained groups list the patterns they cycle through
+fn chain_suffix(chain: &[usize]) -> String {
+    if chain.is_empty() {
+        return String::new();
+    }
+    let patterns: Vec<String> = chain.iter().map(|pattern| format!("{:02}", pattern + 1)).collect();
+    format!("  Chain {}", patterns.join("→"))
+}
+
+// Marks a tempo that came from the playing pattern rather than the global setting
+fn pattern_tempo_marker(app: &App) -> &'static str {
+    if app.is_tempo_from_pattern() {
+        " [P]"
+    } else {
+        ""
+    }
+}
+
+// The tempo the sequencer really managed, when it has fallen noticeably behind or ahead
+fn drift_suffix(app: &App) -> String {
+    match app.measured_bpm() {
+        Some(bpm) if (bpm - app.get_tempo()).abs() >= TEMPO_DRIFT_BPM => format!(" (~{:.1})", bpm),
+        _ => String::new(),
+    }
+}
+
+// Plain 4/4 sixteenths go unmentioned
+fn signature_suffix(signature: Signature) -> String {
+    if signature == Signature::default() {
+        String::new()
+    } else {
+        format!(" · {}", signature.label())
+    }
+}
+
+// The last step of each beat carries a divider, so the grid reads in beats
+fn beat_divider(step: usize, signature: Signature) -> &'static str {
+    if (step + 1).is_multiple_of(signature.steps_per_beat.max(1)) {
+        "│"
+    } else {
+        ""
+    }
+}
+
+// A morphing group shows where it's headed and how far it's got
+fn morph_suffix(morph: Option<(usize, f32)>) -> String {
+    match morph {
+        Some((to, progress)) => format!("  Morph→{:02} {:.0}%", to + 1, progress * 100.0),
+        None => String::new(),
+    }
+}
+
+// The prompt's label and what's been typed so far, with a cursor
+fn prompt_line(prompt: &Prompt, theme: &Theme) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{}: ", prompt.kind.label()), Style::default().fg(theme.accent)),
+        Span::styled(format!("{}▏", prompt.input), Style::default().fg(theme.text).add_modifier(Modifier::BOLD)),
+        Span::styled("  ⏎ ok · Esc cancel", Style::default().fg(theme.muted)),
+    ])
+}
+
+// One-line stand-in for the header and footer on tiny terminals
+fn draw_status_line(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    if let Some(prompt) = &app.ui_state.prompt {
+        f.render_widget(Paragraph::new(prompt_line(prompt, theme)), area);
+        return;
+    }
+
+    let text = match app.ui_state.active_notification() {
+        Some(message) => message.to_string(),
+        None => format!(
+            "{} P{:02} │ {} {:.1} BPM │ Step {:02}/{}{} │ W:{}",
+            app.sample_bank.get_group_name(app.get_current_group()),
+            app.get_current_pattern() + 1,
+            match app.get_transport() {
+                Transport::Playing => "⏵",
+                Transport::Paused => "⏸",
+                Transport::Stopped => "⏹",
+            },
+            app.get_tempo(),
+            app.get_current_pattern_step() + 1,
+            app.get_current_pattern_length(),
+            if app.is_recording() { " ● REC" } else { "" },
+            app.ui_state.panel.next().label(),
+        ),
+    };
+    let style = if app.ui_state.active_notification().is_some() {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(theme.background).bg(theme.group_color(app.get_current_group()))
+    };
+    f.render_widget(Paragraph::new(text).style(style), area);
+}
+
+fn draw_pad_grid(f: &mut Frame, area: Rect, app: &App, theme: &Theme, hits: &mut HitAreas) {
+    let (row_count, col_count) = pad_grid_shape(app.pad_count());
+    let bank = app.get_visible_pads();
     let rows = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(25); 4])
+        .constraints(vec![Constraint::Ratio(1, row_count as u32); row_count])
         .split(area);
 
     for (row_idx, row_area) in rows.iter().enumerate() {
         let cols = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(25); 4])
+            .constraints(vec![Constraint::Ratio(1, col_count as u32); col_count])
             .split(*row_area);
 
         for (col_idx, col_area) in cols.iter().enumerate() {
-            let pad_idx = row_idx * 4 + col_idx;
+            let pad_idx = pad_at(row_idx, col_idx);
+            hits.pads.push((*col_area, pad_idx));
             let is_selected = app.get_selected_pad() == Some(pad_idx);
             let is_flashing = app.is_pad_flashing(app.get_current_group(), pad_idx);
+            let is_muted = app.is_pad_muted(app.get_current_group(), pad_idx);
             
             let sample_name = app.sample_bank
                 .get_sample_name(app.get_current_group(), pad_idx)
                 .unwrap_or("Empty");
 
-            let key_hint = match pad_idx {
+            let key_hint = match pad_idx % PADS_PER_GROUP {
                 0 => "7".to_string(),
                 1 => "8".to_string(),
                 2 => "9".to_string(),
@@ -122,33 +676,66 @@ fn draw_pad_grid(f: &mut Frame, area: Rect, app: &App) {
 
             let block_style = if is_flashing {
                 Style::default()
-                    .fg(Color::White)
-                    .bg(Color::Magenta)
+                    .fg(theme.text)
+                    .bg(theme.flashing_pad)
                     .add_modifier(Modifier::BOLD)
             } else if is_selected {
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Cyan)
+                    .fg(theme.background)
+                    .bg(theme.selected_pad)
             } else if app.sample_bank.has_sample(app.get_current_group(), pad_idx) {
                 Style::default()
-                    .fg(Color::Cyan)
-                    .bg(Color::DarkGray)
+                    .fg(theme.accent)
+                    .bg(theme.muted)
             } else {
                 Style::default()
-                    .fg(Color::DarkGray)
-                    .bg(Color::Black)
+                    .fg(theme.muted)
+                    .bg(theme.background)
             };
 
+            // Muted pads are dimmed whatever else is going on with them
+            let block_style = if is_muted { block_style.add_modifier(Modifier::DIM) } else { block_style };
             let pad_block = Block::default()
                 .borders(Borders::ALL)
                 .style(block_style);
 
+            // Trimmed pads show the part of the sample that plays, the rest its length
+            let trim_line = match app.sample_bank.get_trim(app.get_current_group(), pad_idx) {
+                (start, end) if start > 0.0 || end < 1.0 => {
+                    format!("{:.0}–{:.0}%", start * 100.0, end * 100.0)
+                }
+                _ => app.sample_bank
+                    .get_sample_info(app.get_current_group(), pad_idx)
+                    .and_then(|info| info.duration)
+                    .map_or(String::new(), |duration| format!("{:.2}s", duration.as_secs_f32())),
+            };
+
+            // Loaded pads are tagged with the format found in the file, and +N for layers on top
+            let format_tag = app.sample_bank
+                .get_sample_format(app.get_current_group(), pad_idx)
+                .map_or(String::new(), |format| format!(" {}", format.label()));
+            let format_tag = match app.sample_bank.get_layers(app.get_current_group(), pad_idx).len() {
+                0 => format_tag,
+                layers => format!("{} +{}", format_tag, layers),
+            };
+
+            // Keys play the active bank; the other bank's hints are dimmed
+            let key_style = if bank.contains(&pad_idx) {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().add_modifier(Modifier::DIM)
+            };
+
             let text = Text::from(vec![
-                Line::from(Span::styled(
-                    format!("[{}]", key_hint),
-                    Style::default().add_modifier(Modifier::BOLD),
-                )),
-                Line::from(""),
+                Line::from(vec![
+                    Span::styled(format!("[{}]", key_hint), key_style),
+                    Span::styled(format_tag, Style::default().add_modifier(Modifier::DIM)),
+                    Span::styled(if is_muted { " X" } else { "" }, Style::default().add_modifier(Modifier::BOLD)),
+                ]),
+                match app.transpose_source() {
+                    Some(_) => transpose_hint(pad_idx),
+                    None => Line::from(trim_line),
+                },
                 Line::from(Span::styled(
                     sample_name,
                     Style::default(),
@@ -164,7 +751,29 @@ fn draw_pad_grid(f: &mut Frame, area: Rect, app: &App) {
     }
 }
 
-fn draw_pattern_view(f: &mut Frame, area: Rect, app: &App) {
+// In transpose mode a pad shows how far up it plays the source, with the black keys of
+// a keyboard dimmed so the octave can be found at a glance
+fn transpose_hint(pad: usize) -> Line<'static> {
+    let style = if matches!(pad % 12, 1 | 3 | 6 | 8 | 10) {
+        Style::default().add_modifier(Modifier::DIM)
+    } else {
+        Style::default().add_modifier(Modifier::BOLD)
+    };
+    Line::from(Span::styled(format!("♪ +{}", pad), style))
+}
+
+// Four rows of pads, as many columns as that takes: 4x4 for 16 pads, 8x4 for 32
+pub fn pad_grid_shape(pads: usize) -> (usize, usize) {
+    (4, pads.div_ceil(4).max(1))
+}
+
+// Each bank is a 4x4 block, so with 32 pads the second bank sits to the right of the
+// first and the keys land on the same spot in either
+pub fn pad_at(row: usize, col: usize) -> usize {
+    (col / 4) * PADS_PER_GROUP + row * 4 + col % 4
+}
+
+fn draw_pattern_view(f: &mut Frame, area: Rect, app: &App, theme: &Theme, hits: &mut HitAreas) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -174,94 +783,227 @@ fn draw_pattern_view(f: &mut Frame, area: Rect, app: &App) {
         .split(area);
 
     // Pattern info  
-    let group_names = ["DRUMS", "BASS", "LEAD", "VOCAL"];
-    let pattern_info = Paragraph::new(format!(
-        "{} Pattern: {:02}\nStep: {:02}/16",
-        group_names[app.get_current_group()],
-        app.get_current_pattern() + 1,
-        app.get_current_step() + 1
-    ))
-    .block(Block::default().borders(Borders::ALL).title("Pattern"));
+    let pattern = app.get_current_pattern_data();
+    let length = app.get_current_pattern_length();
+    let signature = pattern.map(Pattern::get_signature).unwrap_or_default();
+    let pattern_text = match &app.ui_state.prompt {
+        Some(prompt) => Text::from(prompt_line(prompt, theme)),
+        None => Text::from(format!(
+            "{} Pattern: {:02}{}{}{}{}\nStep: {:02}/{}",
+            app.sample_bank.get_group_name(app.get_current_group()),
+            app.get_current_pattern() + 1,
+            division_suffix(app.get_clock_division(app.get_current_group())),
+            chain_suffix(app.get_chain(app.get_current_group())),
+            morph_suffix(app.get_morph(app.get_current_group())),
+            signature_suffix(signature),
+            app.get_current_pattern_step() + 1,
+            length,
+        )),
+    };
+    let pattern_info = Paragraph::new(pattern_text)
+        .block(Block::default().borders(Borders::ALL).title("Pattern"));
     f.render_widget(pattern_info, chunks[0]);
 
-    // Step sequencer grid
-    let pattern_grid = app.get_pattern_grid();
-    let current_step = app.get_current_step();
+    // Step sequencer grid, scrolled so the playhead (or the cursor) stays in sight
+    let current_step = app.get_current_pattern_step();
+    let steps = app.get_visible_steps(step_window(chunks[1].width));
+
+    let grid_mode = app.ui_state.grid_mode;
+    let cursor_step = app.ui_state.cursor_step;
+    let selected_pad = app.ui_state.selected_pad;
 
     // Create header with step numbers
     let mut header_cells = vec![Cell::from("Pad")];
-    for i in 0..16 {
+    for i in steps.clone() {
         let step_style = if i == current_step && app.is_playing() {
-            Style::default().fg(Color::Black).bg(Color::White)
+            Style::default().fg(theme.background).bg(theme.step_highlight)
+        } else if i == cursor_step {
+            Style::default().fg(theme.selected_pad).add_modifier(Modifier::UNDERLINED)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(theme.text)
         };
-        header_cells.push(Cell::from(format!("{:2}", i + 1)).style(step_style));
+        header_cells.push(Cell::from(Line::from(vec![
+            Span::styled(format!("{:2}", i + 1), step_style),
+            Span::styled(beat_divider(i, signature), Style::default().fg(theme.muted)),
+        ])));
     }
-    let header = Row::new(header_cells).style(Style::default().fg(Color::White));
+    let header = Row::new(header_cells).style(Style::default().fg(theme.text));
 
     // Create rows for each pad
     let mut rows = vec![header];
-    for (pad_idx, pad_steps) in pattern_grid.iter().enumerate().take(16) {
+    let pads = app.get_visible_pads();
+    for pad_idx in pads.clone() {
         let mut cells = vec![Cell::from(format!("{:2}", pad_idx))];
-        for (step_idx, &has_hit) in pad_steps.iter().enumerate() {
-            let cell_content = if has_hit { "●" } else { "·" };
-            let group_color = match app.get_current_group() {
-                0 => Color::Rgb(100, 150, 150), // DRUMS - muted teal
-                1 => Color::Rgb(100, 100, 150), // BASS - muted blue  
-                2 => Color::Rgb(150, 100, 150), // LEAD - muted purple
-                3 => Color::Rgb(150, 150, 100), // VOCAL - muted gold
-                _ => Color::DarkGray,
-            };
+        for step_idx in steps.clone() {
+            let has_hit = pattern.is_some_and(|pattern| pattern.steps[pad_idx][step_idx]);
+            let step_muted = has_hit && app.is_step_muted(pad_idx, step_idx);
+            let ratchet = app.get_step_ratchet(pad_idx, step_idx);
+            let cell_content = match grid_mode {
+                _ if !has_hit => '·',
+                GridMode::Steps if step_muted => '⊘',
+                GridMode::Steps if ratchet > 1 => ratchet_glyph(ratchet),
+                GridMode::Steps => nudge_glyph(app.get_step_micro_offset(pad_idx, step_idx)),
+                GridMode::Velocity => velocity_glyph(app.get_step_velocity(pad_idx, step_idx)),
+                GridMode::Probability => probability_glyph(app.get_step_probability(pad_idx, step_idx)),
+            }
+            .to_string();
+            let group_color = theme.group_color(app.get_current_group());
             
-            let cell_style = if step_idx == current_step && app.is_playing() {
-                Style::default().fg(Color::Black).bg(Color::White)
+            let cell_style = if selected_pad == Some(pad_idx) && step_idx == cursor_step {
+                Style::default().fg(theme.background).bg(theme.selected_pad)
+            } else if step_idx == current_step && app.is_playing() {
+                Style::default().fg(theme.background).bg(theme.step_highlight)
+            } else if step_muted {
+                Style::default().fg(group_color).add_modifier(Modifier::DIM | Modifier::CROSSED_OUT)
             } else if has_hit {
                 Style::default().fg(group_color)
             } else {
-                Style::default().fg(Color::DarkGray)
+                Style::default().fg(theme.muted)
             };
-            cells.push(Cell::from(cell_content).style(cell_style));
+            cells.push(Cell::from(Line::from(vec![
+                Span::styled(cell_content, cell_style),
+                Span::styled(format!(" {}", beat_divider(step_idx, signature)), Style::default().fg(theme.muted)),
+            ])));
         }
         rows.push(Row::new(cells));
     }
 
-    let widths = [Constraint::Length(3)]
-        .iter()
-        .chain([Constraint::Length(3); 16].iter())
-        .cloned()
-        .collect::<Vec<_>>();
+    // Accents apply to whole steps, so they get one row under the pads
+    let mut accent_cells = vec![Cell::from("Ac").style(Style::default().fg(theme.accent))];
+    for step_idx in steps.clone() {
+        let accented = app.is_step_accented(step_idx);
+        let style = if step_idx == current_step && app.is_playing() {
+            Style::default().fg(theme.background).bg(theme.step_highlight)
+        } else if accented {
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.muted)
+        };
+        accent_cells.push(Cell::from(Line::from(vec![
+            Span::styled(if accented { "▲" } else { "·" }, style),
+            Span::styled(format!(" {}", beat_divider(step_idx, signature)), Style::default().fg(theme.muted)),
+        ])));
+    }
+    rows.push(Row::new(accent_cells));
+
+    let widths = step_column_widths(steps.len());
+
+    // Cells sit where the table lays out its columns: the header row, then one row per pad
+    let grid_area = Block::default().borders(Borders::ALL).inner(chunks[1]);
+    let columns = Layout::horizontal(widths.clone()).flex(Flex::Start).split(grid_area);
+    for (row, pad_idx) in pads.clone().enumerate() {
+        let y = grid_area.y + 1 + row as u16;
+        if y >= grid_area.bottom() {
+            break;
+        }
+        for (column, step_idx) in columns.iter().skip(1).zip(steps.clone()) {
+            hits.steps.push((Rect::new(column.x, y, column.width, 1), pad_idx, step_idx));
+        }
+    }
+    let accent_y = grid_area.y + 1 + pads.len() as u16;
+    if accent_y < grid_area.bottom() {
+        for (column, step_idx) in columns.iter().skip(1).zip(steps.clone()) {
+            hits.accents.push((Rect::new(column.x, accent_y, column.width, 1), step_idx));
+        }
+    }
 
     let table = Table::new(rows, widths)
-        .block(Block::default().borders(Borders::ALL).title("Sequencer"))
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Sequencer [{}] Step {:02} · {}steps {}–{}{}",
+            grid_mode.label(),
+            cursor_step + 1,
+            if steps.start > 0 { "< " } else { "" },
+            steps.start + 1,
+            steps.end,
+            if steps.end < length { " >" } else { "" }
+        )))
         .column_spacing(0);
 
     f.render_widget(table, chunks[1]);
 }
 
-fn draw_mixer(f: &mut Frame, area: Rect, app: &App) {
+// Pad label column plus one column per step that exists on the page
+pub fn step_column_widths(steps: usize) -> Vec<Constraint> {
+    std::iter::repeat_n(Constraint::Length(3), steps + 1).collect()
+}
+
+// Step columns that fit inside the grid's borders next to the pad labels, up to a page
+pub fn step_window(width: u16) -> usize {
+    (width.saturating_sub(2) as usize / 3).saturating_sub(1).clamp(1, STEPS_PER_PAGE)
+}
+
+// Nudged hits lean towards the side they were pushed
+pub fn nudge_glyph(offset_ms: i16) -> char {
+    match offset_ms {
+        0 => '●',
+        ..0 => '◀',
+        _ => '▶',
+    }
+}
+
+// The number of plays, so a roll reads at a glance
+pub fn ratchet_glyph(ratchet: u8) -> char {
+    char::from_digit(ratchet as u32, 10).unwrap_or('●')
+}
+
+// 1..=127 spreads over the digits 1-9, so every velocity edit changes the glyph
+pub fn velocity_glyph(velocity: u8) -> char {
+    let level = (velocity.clamp(1, MAX_VELOCITY) as u32 * 9).div_ceil(MAX_VELOCITY as u32);
+    char::from_digit(level, 10).unwrap_or('9')
+}
+
+// Tens digit of the percentage; steps that always fire keep the normal dot
+pub fn probability_glyph(probability: u8) -> char {
+    if probability >= 100 {
+        '●'
+    } else {
+        char::from_digit(probability as u32 / 10, 10).unwrap_or('0')
+    }
+}
+
+fn draw_mixer(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    // Lay channels out in a row when the mixer only gets a strip at the bottom
+    let direction = if area.height < 13 { Direction::Horizontal } else { Direction::Vertical };
     let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(5),   // Master volume
-            Constraint::Min(8),      // Group volumes
-        ])
+        .direction(direction)
+        .constraints(match direction {
+            Direction::Vertical => [
+                Constraint::Length(5),   // Master volume
+                Constraint::Min(8),      // Group volumes
+            ],
+            Direction::Horizontal => [
+                Constraint::Percentage(20), // Master volume
+                Constraint::Percentage(80), // Group volumes
+            ],
+        })
         .split(area);
 
     // Master volume section
     let master_vol = (app.get_master_volume() * 100.0) as u8;
     let master_bar = create_volume_bar(master_vol, app.is_master_muted());
-    let master_text = format!(
-        "MASTER: {}%\n{}\n{}", 
-        master_vol,
-        master_bar,
-        if app.is_master_muted() { "[MUTED]" } else { "" }
-    );
+    let master_db = app.get_master_gain_db();
+    let master_gain = if master_db == 0.0 { String::new() } else { format!(" {}", master_gain_label(master_db)) };
+    let master_text = Text::from(vec![
+        Line::from(format!("MASTER: {}%{}", master_vol, master_gain)),
+        meter_line(master_bar, app.get_master_level()),
+        Line::from(if app.is_master_muted() {
+            "[MUTED]".to_string()
+        } else if app.is_clipping() {
+            "[CLIP]".to_string()
+        } else if app.is_limiting() {
+            "[LIMIT]".to_string()
+        } else {
+            width_label(app.mixer.get_master_width())
+        }),
+        Line::from(format!("Room {}", app.mixer.get_reverb_size().name())),
+    ]);
     
-    let master_style = if app.is_master_muted() {
+    let master_style = if app.is_master_muted() || app.is_clipping() {
         Style::default().fg(Color::Red)
+    } else if app.is_limiting() {
+        Style::default().fg(Color::Yellow)
     } else {
-        Style::default().fg(Color::White)
+        Style::default().fg(theme.text)
     };
     
     let master_block = Paragraph::new(master_text)
@@ -270,36 +1012,48 @@ fn draw_mixer(f: &mut Frame, area: Rect, app: &App) {
         .alignment(Alignment::Center);
     f.render_widget(master_block, chunks[0]);
 
-    // Group volumes
-    let group_names = ["DRUMS", "BASS", "LEAD", "VOCAL"];
+    // Group volumes; only the default four groups have keys
     let group_keys = ["1/! F1", "2/@ F2", "3/# F3", "4/$ F4"];
-    let group_colors = [
-        Color::Rgb(100, 150, 150), // DRUMS - muted teal
-        Color::Rgb(100, 100, 150), // BASS - muted blue  
-        Color::Rgb(150, 100, 150), // LEAD - muted purple
-        Color::Rgb(150, 150, 100), // VOCAL - muted gold
-    ];
+    let groups = app.group_count();
     
     let group_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(25); 4])
+        .direction(direction)
+        .constraints(vec![Constraint::Ratio(1, groups as u32); groups])
         .split(chunks[1]);
 
-    for (i, (name, keys)) in group_names.iter().zip(group_keys.iter()).enumerate() {
+    for i in 0..groups {
+        let name = app.sample_bank.get_group_name(i);
+        let keys = group_keys.get(i).copied().unwrap_or("");
         let vol = (app.get_group_volume(i) * 100.0) as u8;
         let bar = create_volume_bar(vol, app.is_group_muted(i));
-        let text = format!("{}: {}%\n{}", name, vol, bar);
+        let text = Text::from(vec![
+            Line::from(format!(
+                "{}: {}%  D{} R{}{}{}",
+                name,
+                vol,
+                (app.get_delay_send(i) * 100.0).round() as u8,
+                (app.get_reverb_send(i) * 100.0).round() as u8,
+                if app.mixer.get_group_gate(i).is_some() { " G" } else { "" },
+                if app.is_group_automated(i) { " A" } else { "" },
+            )),
+            meter_line(bar, app.get_group_level(i)),
+        ]);
         
+        // Groups another group's solo is keeping quiet are greyed out
+        let silenced = app.mixer.is_solo_active() && !app.is_group_soloed(i);
         let style = if app.is_group_muted(i) {
             Style::default().fg(Color::Red)
+        } else if silenced {
+            Style::default().fg(Color::DarkGray)
         } else if i == app.get_current_group() {
-            Style::default().fg(group_colors[i]).add_modifier(Modifier::BOLD)
+            Style::default().fg(theme.group_color(i)).add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(group_colors[i])
+            Style::default().fg(theme.group_color(i))
         };
         
         let mute_indicator = if app.is_group_muted(i) { " [MUTED]" } else { "" };
-        let block_title = format!("{}{}", keys, mute_indicator);
+        let solo_indicator = if app.is_group_soloed(i) { " [SOLO]" } else { "" };
+        let block_title = format!("{}{}{}", keys, mute_indicator, solo_indicator);
         
         let group_block = Paragraph::new(text)
             .block(Block::default().borders(Borders::ALL).title(block_title))
@@ -309,6 +1063,55 @@ fn draw_mixer(f: &mut Frame, area: Rect, app: &App) {
     }
 }
 
+// Whether the screen keeps changing without any new events: flashes, moving meters, the playhead
+pub fn is_animating(app: &App) -> bool {
+    let meters_lit = meter_segments(app.get_master_level()) > 0
+        || (0..app.group_count()).any(|group| meter_segments(app.get_group_level(group)) > 0);
+
+    app.is_playing()
+        || app.is_sampling()
+        || app.get_output_recording_time().is_some()
+        || app.is_limiting()
+        || !app.ui_state.flashing_pads.is_empty()
+        || meters_lit
+}
+
+// Lit segments for a linear peak: 1-9 cover -48..-1 dBFS, the 10th only lights above -1
+pub fn meter_segments(level: f32) -> usize {
+    if level <= 0.0 {
+        return 0;
+    }
+    let db = 20.0 * level.log10();
+    if db > -1.0 {
+        METER_SEGMENTS
+    } else {
+        (((db + 48.0) / 47.0 * 9.0).ceil().max(0.0) as usize).min(METER_SEGMENTS - 1)
+    }
+}
+
+// Volume bar followed by the live level, with the top segment in red
+fn meter_line(bar: String, level: f32) -> Line<'static> {
+    let lit = meter_segments(level);
+    let segment = |index: usize| if index < lit { "▮" } else { "▯" };
+    let body: String = (0..METER_SEGMENTS - 1).map(segment).collect();
+
+    Line::from(vec![
+        Span::raw(bar),
+        Span::raw(" "),
+        Span::raw(body),
+        Span::styled(segment(METER_SEGMENTS - 1), Style::default().fg(Color::Red)),
+    ])
+}
+
+// Only a width other than normal is worth a label
+fn width_label(width: f32) -> String {
+    match (width * 100.0).round() as u16 {
+        0 => "MONO".to_string(),
+        100 => String::new(),
+        percent => format!("Width {}%", percent),
+    }
+}
+
 fn create_volume_bar(volume: u8, is_muted: bool) -> String {
     if is_muted {
         "■■■■■■■■■■".to_string()
@@ -319,7 +1122,7 @@ fn create_volume_bar(volume: u8, is_muted: bool) -> String {
     }
 }
 
-fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
+fn draw_footer(f: &mut Frame, area: Rect, app: &App, theme: &Theme, hits: &mut HitAreas) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
@@ -330,30 +1133,487 @@ fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
         .split(area);
 
     // Transport controls
-    let transport_text = format!(
-        "{}  {}",
-        if app.is_playing() { "⏸ PLAYING" } else { "⏵ STOPPED" },
-        if app.is_recording() { "● REC" } else { "○" }
-    );
+    let audition_text = match app.get_audition() {
+        Some((_, pattern)) => format!("  ▷ PREVIEW {:02}", pattern + 1),
+        None => String::new(),
+    };
+    let transport_text = match app.get_sampling_level() {
+        Some(level) => format!(
+            "◉ SAMPLING {}",
+            create_volume_bar((level.clamp(0.0, 1.0) * 100.0) as u8, false)
+        ),
+        None => format!(
+            "{}  {}{}{}{}",
+            match app.get_transport() {
+                Transport::Playing => "⏵ PLAYING",
+                Transport::Paused => "⏸ PAUSED",
+                Transport::Stopped => "⏹ STOPPED",
+            },
+            if app.is_recording() { "● REC" } else { "○" },
+            if app.is_note_repeat() { "  ↻ REPEAT" } else { "" },
+            audition_text,
+            // Output capture goes to disk, so it's told apart from recording into the pattern
+            app.get_output_recording_time()
+                .map_or(String::new(), |time| format!("  ⏺ DISK {}:{:02}", time.as_secs() / 60, time.as_secs() % 60))
+        ),
+    };
+    let (bar, beat, step) = app.get_transport_position();
+    let elapsed = app.get_elapsed().as_secs();
+    // A chained group's song shows which of its entries is playing
+    let group = app.get_current_group();
+    let entry = app
+        .get_chain_position(group)
+        .map_or(String::new(), |index| format!(" · Chain {}/{}", index + 1, app.get_chain(group).len()));
+    let position = format!(" {:03}.{}.{} · {}:{:02}{} ", bar, beat, step, elapsed / 60, elapsed % 60, entry);
     let transport = Paragraph::new(transport_text)
-        .block(Block::default().borders(Borders::ALL).title("Transport"))
-        .style(if app.is_playing() {
-            Style::default().fg(Color::Cyan)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Transport")
+                .title(Line::from(position).right_aligned()),
+        )
+        .style(if app.is_sampling() || app.get_output_recording_time().is_some() {
+            Style::default().fg(Color::Red)
+        } else if app.is_playing() {
+            Style::default().fg(theme.accent)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(theme.text)
         });
     f.render_widget(transport, chunks[0]);
 
     // Tempo
-    let tempo = Paragraph::new(format!("{} BPM", app.get_tempo()))
+    let tempo_text = match app.get_tempo_target() {
+        _ if app.is_clock_slaved() => format!("{:.1} BPM MIDI · Swing {}%", app.get_tempo(), app.get_swing_percent()),
+        Some(target) => format!("{:.1} BPM →{:.1} · Swing {}%", app.get_tempo(), target, app.get_swing_percent()),
+        None => format!("{:.1}{}{} BPM · Swing {}%", app.get_tempo(), pattern_tempo_marker(app), drift_suffix(app), app.get_swing_percent()),
+    };
+    let tempo = Paragraph::new(tempo_text)
         .block(Block::default().borders(Borders::ALL).title("Tempo"))
         .alignment(Alignment::Center);
     f.render_widget(tempo, chunks[1]);
+    hits.tempo = Some(chunks[1]);
 
-    // Help
-    let help_text = "SPACE:Play/Stop | R:Record | C:Clear | TAB:Groups | ←→:Patterns | ↑↓:Tempo | =/−:Master Vol | M:Master Mute | 1-4/!@#$:Group Vol | F1-F4:Group Mute | ESC:Quit";
-    let help = Paragraph::new(help_text)
-        .block(Block::default().borders(Borders::ALL).title("Controls"))
-        .style(Style::default().fg(Color::DarkGray));
+    // Help, replaced by the latest notification while one is active
+    let help = match app.ui_state.active_notification() {
+        Some(message) => Paragraph::new(message.to_string())
+            .block(Block::default().borders(Borders::ALL).title("Message"))
+            .style(Style::default().fg(Color::Yellow)),
+        None => {
+            let help_text = "SPACE:Play/Pause | S/⇧SPACE:Stop | R:Record | C/^D:Clear/Reset | A/⇧A:Preview | ⇧S:Sample | ⇧B:Resample | E:Grid Mode | [ ]:Step | { }:Vel | ( ):Prob | N:Step Mute | H/⇧H:Nudge | Q:Swing | D:Clock ×½/×1/×2 | F:Accent | < >:Trim Start | Z/⇧Z:Trim End | PgUp/PgDn:Step Page | ^.:Panic | X/⇧X:Export/Import | ^P:Save Pads | TAB:Groups | ←→:Patterns | G:Go to Pattern | ⇧P:Pattern Map | W:Panel | V:Overview | ↑↓/^↑↓:Tempo ±5/±0.1 | T/⇧T:Tempo Point/Clear | U/^U:Pattern Tempo/Clear | ⇧E:Signature | ^L:Note Repeat | =/−:Master Vol | ^G:Master Gain | M:Master Mute | +/_:Width | 1-4/!@#$:Group Vol | 5/%:Delay | 6/^:Reverb | F1-F4:Group Mute | ^W:Clear Automation | F5-F8:Group Solo | ESC:Quit";
+            Paragraph::new(help_text)
+                .block(Block::default().borders(Borders::ALL).title("Controls"))
+                .style(Style::default().fg(theme.muted))
+        }
+    };
     f.render_widget(help, chunks[2]);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+    use crate::pad_settings::PadSettings;
+    use crate::sample::{LayerMode, Normalize};
+
+    #[test]
+    fn test_step_column_widths() {
+        // The pad label column comes first
+        assert_eq!(step_column_widths(8).len(), 9);
+        assert_eq!(step_column_widths(12).len(), 13);
+        assert_eq!(step_column_widths(16).len(), 17);
+    }
+
+    #[test]
+    fn test_step_window() {
+        // Two border columns, then three cells per column including the pad labels
+        assert_eq!(step_window(53), 16);
+        assert_eq!(step_window(120), 16);
+        assert_eq!(step_window(50), 15);
+        assert_eq!(step_window(32), 9);
+        assert_eq!(step_window(4), 1);
+    }
+
+    #[test]
+    fn test_pad_grid_layout() {
+        assert_eq!(pad_grid_shape(16), (4, 4));
+        assert_eq!(pad_grid_shape(32), (4, 8));
+        
+        // The second bank is its own 4x4 block to the right, so keys sit in the same spots
+        assert_eq!(pad_at(0, 0), 0);
+        assert_eq!(pad_at(1, 3), 7);
+        assert_eq!(pad_at(0, 4), 16);
+        assert_eq!(pad_at(3, 7), 31);
+    }
+
+    #[test]
+    fn test_meter_segments() {
+        assert_eq!(meter_segments(0.0), 0);
+        assert_eq!(meter_segments(0.001), 0); // -60 dBFS is below the scale
+        assert_eq!(meter_segments(0.5), 9);
+        assert_eq!(meter_segments(0.05), 5);
+        // Only peaks above -1 dBFS reach the red segment
+        assert_eq!(meter_segments(0.88), 9);
+        assert_eq!(meter_segments(0.9), 10);
+        assert_eq!(meter_segments(1.5), 10);
+    }
+
+    #[test]
+    fn test_layout_mode_selection() {
+        assert_eq!(layout_mode(Rect::new(0, 0, 200, 50)), LayoutMode::Full);
+        assert_eq!(layout_mode(Rect::new(0, 0, FULL_MIN_WIDTH, 30)), LayoutMode::Full);
+        
+        // The classic 80x24 terminal shares one panel between the sequencer and mixer
+        assert_eq!(layout_mode(Rect::new(0, 0, 80, 24)), LayoutMode::Compact);
+        assert_eq!(layout_mode(Rect::new(0, 0, 100, 60)), LayoutMode::Compact);
+        
+        // Narrow but tall terminals stack everything vertically
+        assert_eq!(layout_mode(Rect::new(0, 0, 70, 60)), LayoutMode::Stacked);
+        
+        // Anything else that fits gets a single panel
+        assert_eq!(layout_mode(Rect::new(0, 0, 70, 30)), LayoutMode::Minimal);
+        assert_eq!(layout_mode(Rect::new(0, 0, 120, 19)), LayoutMode::Minimal);
+        assert_eq!(layout_mode(Rect::new(0, 0, MIN_WIDTH, MIN_HEIGHT)), LayoutMode::Minimal);
+        
+        assert_eq!(layout_mode(Rect::new(0, 0, 59, 40)), LayoutMode::TooSmall);
+        assert_eq!(layout_mode(Rect::new(0, 0, 120, 17)), LayoutMode::TooSmall);
+    }
+
+    #[test]
+    fn test_idle_app_is_not_animating() {
+        let mut app = App::new().unwrap();
+        assert!(!is_animating(&app));
+        
+        app.ui_state.start_flash(vec![(0, 0)]);
+        assert!(is_animating(&app));
+        app.ui_state.flashing_pads.clear();
+        
+        app.toggle_playback();
+        assert!(is_animating(&app));
+    }
+
+    fn render(app: &App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| {
+            draw(f, app, &Theme::default());
+        }).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_snapshots_at_common_sizes() {
+        let mut app = App::new().unwrap();
+
+        let full = render(&app, 200, 50);
+        for widget in ["K.O.II Terminal", "Sequencer [STEPS]", "Master", "Transport", "Tempo", "[7]"] {
+            assert!(full.contains(widget), "full layout is missing {}", widget);
+        }
+
+        let compact = render(&app, 80, 24);
+        assert!(compact.contains("Sequencer [STEPS]"));
+        assert!(compact.contains("[7]"));
+        assert!(!compact.contains("Master (=/- M)"));
+        app.toggle_panel();
+        let compact = render(&app, 80, 24);
+        assert!(compact.contains("Master (=/- M)"));
+        assert!(!compact.contains("Sequencer [STEPS]"));
+        app.toggle_panel();
+
+        let stacked = render(&app, 70, 60);
+        assert!(stacked.contains("Sequencer [STEPS]") && stacked.contains("Master"));
+
+        let minimal = render(&app, 70, 20);
+        assert!(minimal.contains("Sequencer [STEPS]"));
+        assert!(minimal.contains("DRUMS P01"));
+        assert!(minimal.contains("120.0 BPM"));
+        assert!(!minimal.contains("Transport"));
+
+        let tiny = render(&app, 40, 10);
+        assert!(tiny.contains("Terminal too small"));
+        assert!(tiny.contains("need at least 60x18"));
+
+        // Degenerate sizes must not panic either
+        for (width, height) in [(1, 1), (60, 18), (79, 23), (80, 23), (111, 24), (300, 100)] {
+            render(&app, width, height);
+        }
+    }
+
+    #[test]
+    fn test_overview_shows_every_group() {
+        let mut app = App::new().unwrap();
+        app.toggle_step(0, 0);
+        app.toggle_overview();
+
+        let overview = render(&app, 200, 50);
+        for lane in ["1 DRUMS", "2 BASS", "3 LEAD", "4 VOCAL"] {
+            assert!(overview.contains(lane), "overview is missing {}", lane);
+        }
+        assert!(!overview.contains("Sequencer [STEPS]"));
+        // Only the current group's lane gets the heavy border
+        assert_eq!(overview.matches('┏').count(), 1);
+
+        // Small terminals swap the panel for the overview too
+        assert!(render(&app, 70, 20).contains("1 DRUMS"));
+
+        app.select_group(2);
+        assert_eq!(app.ui_state.view, ViewMode::Normal);
+        assert_eq!(app.get_current_group(), 2);
+    }
+
+    #[test]
+    fn test_prompt_is_drawn_inline() {
+        let mut app = App::new().unwrap();
+        app.open_goto_pattern();
+        app.prompt_input('4');
+        assert!(render(&app, 200, 50).contains("Go to pattern: 4▏"));
+        assert!(render(&app, 70, 20).contains("Go to pattern: 4▏"));
+    }
+
+    #[test]
+    fn test_pattern_map_lists_every_slot() {
+        let mut app = App::new().unwrap();
+        app.toggle_pattern_map();
+        for size in [(200, 50), (60, 18)] {
+            let map = render(&app, size.0, size.1);
+            assert!(map.contains("Patterns · 01"));
+            assert!(map.contains("91 92 93 94 95 96 97 98 99"));
+        }
+    }
+
+    #[test]
+    fn test_clock_division_is_shown_next_to_the_pattern() {
+        let mut app = App::new().unwrap();
+        assert!(!render(&app, 200, 50).contains("×"));
+        app.cycle_clock_division();
+        app.cycle_clock_division();
+        assert!(render(&app, 200, 50).contains("Pattern: 01 ×½"));
+
+        app.toggle_overview();
+        assert!(render(&app, 200, 50).contains("Pattern 01 ×½"));
+    }
+
+    #[test]
+    fn test_chain_is_shown_next_to_the_pattern() {
+        let mut app = App::new().unwrap();
+        app.append_to_chain();
+        app.go_to_pattern(2).unwrap();
+        app.append_to_chain();
+        assert!(render(&app, 200, 50).contains("Pattern: 03  Chain 01→03"));
+    }
+
+    #[test]
+    fn test_six_configured_groups_get_tabs_and_faders() {
+        let names = ["KICK", "SNARE", "HATS", "BASS", "KEYS", "FX"];
+        let groups = names
+            .iter()
+            .map(|name| crate::config::GroupConfig { name: Some(name.to_string()), ..Default::default() })
+            .collect();
+        let mut app = App::new().unwrap();
+        app.apply_config(&crate::config::Config { groups, ..Default::default() });
+        assert_eq!(app.group_count(), 6);
+        assert_eq!(app.mixer.group_count(), 6);
+        
+        // One tab per group, in order, along the top
+        let screen = render(&app, 200, 50);
+        let tabs = screen.lines().find(|line| line.contains(" KICK ")).unwrap();
+        let positions: Vec<usize> = names.iter().map(|name| tabs.find(&format!(" {} ", name)).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        for name in names {
+            assert!(screen.contains(&format!("{}: 80%", name)), "missing fader {}", name);
+        }
+        assert!(!screen.contains("VOCAL"));
+    }
+
+    #[test]
+    fn test_grid_marks_beats_and_signature() {
+        let mut app = App::new().unwrap();
+        assert_eq!(beat_divider(3, Signature::default()), "│");
+        assert_eq!(beat_divider(4, Signature::default()), "");
+        let screen = render(&app, 200, 50);
+        assert!(screen.contains(" 4│"));
+        assert!(!screen.contains("3/4"));
+        
+        app.set_signature(Signature::new(3, 2).unwrap(), false);
+        let screen = render(&app, 200, 50);
+        assert!(screen.contains("3/4 · 8ths"));
+        assert!(screen.contains("Pad 1  2│ 3  4│ 5  6│ "));
+        assert!(screen.contains(" 0 ·  · │·  · │·  · │ "));
+    }
+
+    #[test]
+    fn test_master_shows_its_gain() {
+        let mut app = App::new().unwrap();
+        assert!(render(&app, 200, 50).contains("MASTER: 70% "));
+        app.mixer.set_master_gain_db(6.0);
+        assert!(render(&app, 200, 50).contains("MASTER: 70% +6.0 dB"));
+    }
+
+    #[test]
+    fn test_soloed_group_is_marked() {
+        let mut app = App::new().unwrap();
+        assert!(!render(&app, 200, 50).contains("[SOLO]"));
+        app.toggle_group_solo(2);
+        assert!(render(&app, 200, 50).contains("3/# F3 [SOLO]"));
+    }
+
+    #[test]
+    fn test_armed_groups_show_a_record_dot() {
+        let mut app = App::new().unwrap();
+        assert!(!render(&app, 200, 50).contains("●"));
+        app.toggle_record_arm(2);
+        assert!(render(&app, 200, 50).contains(" LEAD ●"));
+    }
+
+    #[test]
+    fn test_master_width_is_shown_when_not_normal() {
+        let mut app = App::new().unwrap();
+        assert!(!render(&app, 200, 50).contains("Width"));
+        app.adjust_master_width(0.5);
+        assert!(render(&app, 200, 50).contains("Width 150%"));
+        app.adjust_master_width(-2.0);
+        assert!(render(&app, 200, 50).contains("MONO"));
+    }
+
+    #[test]
+    fn test_accent_row_marks_accented_steps() {
+        let mut app = App::new().unwrap();
+        assert!(render(&app, 200, 50).contains("Ac"));
+        assert!(!render(&app, 200, 50).contains("▲"));
+        app.toggle_accent(2);
+        assert!(render(&app, 200, 50).contains("▲"));
+    }
+
+    #[test]
+    fn test_transport_shows_bar_beat_and_elapsed_time() {
+        let mut app = App::new().unwrap();
+        assert!(render(&app, 200, 50).contains(" 001.1.1 · 0:00 "));
+        
+        app.state.bars_elapsed = 2;
+        for _ in 0..6 {
+            app.sequencer.tick(120.0);
+        }
+        assert!(render(&app, 200, 50).contains(" 003.2.3 · 0:00 "));
+        
+        // In a chain, the entry playing and how many there are
+        app.append_to_chain();
+        app.next_pattern();
+        app.append_to_chain();
+        app.append_to_chain();
+        assert!(render(&app, 200, 50).contains(" 003.2.3 · 0:00 · Chain 1/3 "));
+    }
+
+    #[test]
+    fn test_density_glyph() {
+        assert_eq!(density_glyph(0), '·');
+        assert_eq!(density_glyph(2), '▒');
+        assert_eq!(density_glyph(16), '█');
+    }
+
+    #[test]
+    fn test_velocity_glyph_extremes() {
+        assert_eq!(velocity_glyph(1), '1');
+        assert_eq!(velocity_glyph(0), '1');
+        assert_eq!(velocity_glyph(MAX_VELOCITY), '9');
+        assert_eq!(velocity_glyph(255), '9');
+        
+        // Each velocity edit lands on its own digit
+        let glyphs: Vec<char> = (0..9).map(|i| velocity_glyph(MAX_VELOCITY.saturating_sub(i * 16))).collect();
+        assert_eq!(glyphs, vec!['9', '8', '7', '6', '5', '4', '3', '2', '1']);
+    }
+
+    #[test]
+    fn test_probability_glyph_extremes() {
+        assert_eq!(probability_glyph(100), '●');
+        assert_eq!(probability_glyph(99), '9');
+        assert_eq!(probability_glyph(10), '1');
+        assert_eq!(probability_glyph(0), '0');
+    }
+
+    #[test]
+    fn test_hit_areas_line_up_with_widgets() {
+        let app = App::new().unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(200, 50)).unwrap();
+        let mut hits = HitAreas::default();
+        terminal.draw(|f| hits = draw(f, &app, &Theme::default())).unwrap();
+        let buffer = terminal.backend().buffer();
+
+        // Step cells sit on the table's glyphs, below the step numbers
+        let (first, pad, step) = hits.steps[0];
+        assert_eq!((pad, step), (0, 0));
+        assert_eq!(buffer[(first.x, first.y)].symbol(), "·");
+        assert_eq!(buffer[(first.x + 1, first.y - 1)].symbol(), "1");
+        assert_eq!(hits.target_at(first.x + 2, first.y), Some(HitTarget::Step(0, 0)));
+        let &(last, pad, step) = hits.steps.last().unwrap();
+        assert_eq!((pad, step), (15, 15));
+        assert_eq!(buffer[(last.x, last.y)].symbol(), "·");
+
+        let (pad_area, _) = hits.pads[5];
+        assert_eq!(hits.target_at(pad_area.x + 1, pad_area.y + 1), Some(HitTarget::Pad(5)));
+
+        // The accent row sits under the last pad
+        let (accent, step) = hits.accents[3];
+        assert_eq!(step, 3);
+        assert_eq!(accent.y, last.y + 1);
+        assert_eq!(buffer[(accent.x, accent.y)].symbol(), "·");
+
+        let tempo = hits.tempo.unwrap();
+        assert_eq!(hits.target_at(tempo.x + 2, tempo.y + 1), Some(HitTarget::Tempo));
+        assert_eq!(hits.target_at(0, 0), None); // Header tabs
+    }
+
+    #[test]
+    fn test_pad_detail_modal() {
+        let mut app = App::detached();
+        app.fill_empty_pads_with_synth();
+        app.mixer.set_pad_settings(0, 2, PadSettings { pitch: -3.0, mode: PlaybackMode::Choke, ..PadSettings::default() });
+        
+        // Opening it needs a pad to describe
+        app.toggle_pad_detail();
+        assert!(!app.ui_state.pad_detail);
+        app.ui_state.select_pad(2);
+        app.toggle_pad_detail();
+        
+        let screen = render(&app, 120, 40);
+        for detail in ["DRUMS · pad 3", "synth-hihat", "(in memory)", "WAV · 44.1 kHz · mono", "Pitch -3.0 st", "choke", "Bank: 16 samples"] {
+            assert!(screen.contains(detail), "pad detail is missing {}", detail);
+        }
+        assert!(screen.contains("Norm    off"));
+        // It's drawn over the main view at the smallest layout too
+        assert!(render(&app, 64, 20).contains("pad 3"));
+        
+        app.sample_bank.set_normalize(Normalize::Peak, -1.0);
+        assert!(render(&app, 120, 40).contains(" dB · peak -"));
+        
+        let clap = app.sample_bank.generate_simple_kick();
+        app.sample_bank.add_layer(0, 2, "clap", clap, 0.5).unwrap();
+        assert!(render(&app, 120, 40).contains("Layer 1 clap · -6.0 dB"));
+        app.sample_bank.set_layer_mode(0, 2, LayerMode::Random);
+        assert!(render(&app, 120, 40).contains("Layers  random"));
+        app.sample_bank.set_velocity_range(0, 2, 1, Some((64, 127)));
+        let screen = render(&app, 120, 40);
+        assert!(screen.contains("Layers  velocity"));
+        assert!(screen.contains("Layer 1 clap · -6.0 dB · vel 64–127"));
+        
+        app.toggle_pad_detail();
+        assert!(!render(&app, 120, 40).contains("Bank:"));
+        assert!(render(&app, 200, 40).contains("[9] WAV +1"));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(44_144), "43.1 KB");
+        assert_eq!(format_bytes(12 * 1_048_576 + 300_000), "12.3 MB");
+    }
+
+    #[test]
+    fn test_too_small_has_no_hit_areas() {
+        let app = App::new().unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        let mut hits = HitAreas::default();
+        terminal.draw(|f| hits = draw(f, &app, &Theme::default())).unwrap();
+        assert_eq!(hits.target_at(5, 5), None);
+    }
+}
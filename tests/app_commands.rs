@@ -0,0 +1,159 @@
+// Drives a detached App through Commands, the way keys and OSC messages do
+use ko2_terminal::app::App;
+use ko2_terminal::command::Command;
+use ko2_terminal::config::{Config, LayoutConfig};
+use ko2_terminal::project::Project;
+use ko2_terminal::sequencer::ClockDivision;
+
+fn run(app: &mut App, commands: &[Command]) {
+    for command in commands {
+        command.execute(app).unwrap();
+    }
+}
+
+fn is_silent(block: &[f32]) -> bool {
+    block.iter().all(|&s| s == 0.0)
+}
+
+#[test]
+fn test_synth_kit_pads_are_heard_on_the_bus() {
+    let mut app = App::detached();
+    assert!(!app.sample_bank.has_sample(0, 0));
+
+    run(&mut app, &[Command::FillEmptyPadsWithSynth]);
+    assert!(app.sample_bank.has_sample(0, 0));
+    assert!(is_silent(&app.mixer.render_frames(256)));
+
+    run(&mut app, &[Command::TriggerPad(0)]);
+    assert_eq!(app.ui_state.selected_pad, Some(0));
+    assert!(!is_silent(&app.mixer.render_frames(1024)));
+}
+
+#[test]
+fn test_output_recording_keeps_live_pads() {
+    let mut app = App::detached();
+    let path = std::env::temp_dir().join("ko2_integration_jam.wav");
+    let path = path.to_str().unwrap();
+    run(&mut app, &[Command::FillEmptyPadsWithSynth]);
+    app.start_output_recording(path).unwrap();
+    assert!(app.get_output_recording_time().is_some());
+    
+    run(&mut app, &[Command::TriggerPad(0)]);
+    app.mixer.render_frames(4410);
+    
+    let (written, length) = app.stop_output_recording().unwrap();
+    assert_eq!(written, path);
+    assert_eq!(length.as_millis(), 100);
+    assert!(app.get_output_recording_time().is_none());
+    
+    let reader = hound::WavReader::open(path).unwrap();
+    assert_eq!((reader.spec().channels, reader.spec().sample_rate), (2, 44100));
+    let samples: Vec<i16> = reader.into_samples().map(|s| s.unwrap()).collect();
+    let _ = std::fs::remove_file(path);
+    assert_eq!(samples.len(), 8820);
+    assert!(samples.iter().any(|&s| s != 0));
+    
+    assert!(app.stop_output_recording().is_err());
+}
+
+#[test]
+fn test_muted_group_stays_silent() {
+    let mut app = App::detached();
+    run(&mut app, &[Command::FillEmptyPadsWithSynth, Command::ToggleGroupMute(0)]);
+    // Let the mute ramp finish before listening
+    app.mixer.render_frames(4096);
+
+    run(&mut app, &[Command::TriggerPad(1)]);
+    assert!(is_silent(&app.mixer.render_frames(1024)));
+}
+
+#[test]
+fn test_muted_pad_is_not_played_live() {
+    let mut app = App::detached();
+    run(&mut app, &[Command::FillEmptyPadsWithSynth]);
+    app.ui_state.select_pad(1);
+    
+    run(&mut app, &[Command::ToggleSelectedPadMute, Command::TriggerPad(1)]);
+    assert!(is_silent(&app.mixer.render_frames(1024)));
+    
+    run(&mut app, &[Command::ToggleSelectedPadMute, Command::TriggerPad(1)]);
+    assert!(!is_silent(&app.mixer.render_frames(1024)));
+}
+
+#[test]
+fn test_pattern_edits_and_undo() {
+    let mut app = App::detached();
+    run(&mut app, &[Command::ToggleStep(2, 0), Command::ToggleStep(2, 9)]);
+    let pattern = app.get_current_pattern_data().unwrap();
+    assert!(pattern.get_hit(2, 0) && pattern.get_hit(2, 9));
+    assert!(!pattern.get_hit(2, 1));
+
+    // Filling the selected row puts a hit on every step, and undo brings the old row back
+    run(&mut app, &[Command::TriggerPad(2), Command::FillSelectedPad]);
+    let pattern = app.get_current_pattern_data().unwrap();
+    assert!((0..pattern.length()).all(|step| pattern.get_hit(2, step)));
+
+    run(&mut app, &[Command::Undo]);
+    let pattern = app.get_current_pattern_data().unwrap();
+    assert!(pattern.get_hit(2, 9));
+    assert!(!pattern.get_hit(2, 4));
+}
+
+#[test]
+fn test_project_round_trip_through_the_app() {
+    let mut app = App::detached();
+    run(&mut app, &[
+        Command::SetTempo(133.0),
+        Command::NextGroup,
+        Command::ToggleStep(5, 3),
+        Command::AdjustReverbSend(0.25),
+        Command::CycleGroupClockDiv,
+    ]);
+
+    let path = std::env::temp_dir().join("ko2_integration_round_trip.koii");
+    let path = path.to_str().unwrap();
+    app.project().save(path).unwrap();
+
+    let mut restored = App::detached();
+    restored.apply_project(Project::load(path).unwrap());
+    let _ = std::fs::remove_file(path);
+
+    assert_eq!(restored.get_tempo(), 133.0);
+    assert!((restored.get_reverb_send(1) - 0.25).abs() < 1e-6);
+    assert_eq!(restored.sequencer.get_division(1), ClockDivision::Double);
+    let pattern = restored.sequencer.get_pattern(1, 0).unwrap();
+    assert!(pattern.get_hit(5, 3));
+}
+
+#[test]
+fn test_six_groups_play_mix_and_save() {
+    let mut app = App::detached();
+    let layout = LayoutConfig { groups: 6, ..LayoutConfig::default() };
+    app.apply_config(&Config { layout, ..Config::default() });
+    assert_eq!(app.group_count(), 6);
+    assert!(Command::SelectGroup(6).execute(&mut app).is_err());
+    
+    run(&mut app, &[
+        Command::SelectGroup(5),
+        Command::ToggleStep(3, 2),
+        Command::NextPattern,
+        Command::AdjustGroupVolume(4, -0.3),
+        Command::ToggleGroupMute(5),
+    ]);
+    assert!((app.get_group_volume(4) - 0.5).abs() < 1e-6);
+    assert!(app.is_group_muted(5));
+    
+    let path = std::env::temp_dir().join("ko2_integration_six_groups.koii");
+    let path = path.to_str().unwrap();
+    app.project().save(path).unwrap();
+    
+    let mut restored = App::detached();
+    restored.set_group_count(6);
+    restored.apply_project(Project::load(path).unwrap());
+    let _ = std::fs::remove_file(path);
+    
+    assert_eq!(restored.get_group_pattern(5), 1);
+    assert!((restored.get_group_volume(4) - 0.5).abs() < 1e-6);
+    assert!(restored.is_group_muted(5));
+    assert!(restored.sequencer.get_pattern(5, 0).unwrap().get_hit(3, 2));
+}
@@ -0,0 +1,110 @@
+mod support;
+
+use crossterm::event::KeyCode;
+use ko2_terminal::command::Command;
+use ko2_terminal::config::{Config, GroupConfig, LayoutConfig};
+use support::TestHarness;
+
+#[test]
+fn test_playhead_highlights_its_step_number() {
+    let mut harness = TestHarness::new();
+    assert_eq!(harness.press_key(KeyCode::Char(' ')), Some(Command::TogglePlayback));
+    
+    // Nothing moves until the clock does
+    harness.app.tick();
+    assert_eq!(harness.app.get_current_pattern_step(), 0);
+    
+    harness.advance_steps(4);
+    assert_eq!(harness.app.get_current_pattern_step(), 4);
+    
+    harness.draw();
+    let (pad_x, header_y) = harness.find("Pad").unwrap();
+    let step_x = (pad_x..pad_x + 20).find(|&x| harness.cell(x, header_y).symbol() == "5").unwrap();
+    assert_eq!(harness.cell(step_x, header_y).bg, harness.theme.step_highlight);
+    assert_ne!(harness.cell(step_x - 3, header_y).bg, harness.theme.step_highlight);
+}
+
+#[test]
+fn test_pause_holds_the_playhead() {
+    let mut harness = TestHarness::new();
+    harness.press_key(KeyCode::Char(' '));
+    harness.advance_steps(3);
+    
+    harness.press_key(KeyCode::Char(' '));
+    harness.advance_steps(10);
+    assert_eq!(harness.app.get_current_pattern_step(), 3);
+    assert!(!harness.app.is_playing());
+}
+
+#[test]
+fn test_euclidean_prompt_from_the_keyboard() {
+    let mut harness = TestHarness::new();
+    harness.press_key(KeyCode::Char('7')); // Pad 0
+    assert_eq!(harness.app.ui_state.selected_pad, Some(0));
+    
+    harness.press_ctrl('e');
+    harness.type_keys("3,1");
+    harness.draw();
+    assert!(harness.screen().contains("Euclid pulses[,rotation]: 3,1"));
+    
+    // The prompt swallows the keys, so no pad was played or step toggled on the way
+    harness.press_key(KeyCode::Enter);
+    assert!(harness.app.ui_state.prompt.is_none());
+    let pattern = harness.app.get_current_pattern_data().unwrap();
+    let hits: Vec<usize> = (0..pattern.length()).filter(|&step| pattern.get_hit(0, step)).collect();
+    assert_eq!(hits.len(), 3);
+    assert_eq!(hits[0], 1); // Rotated one step to the right
+}
+
+#[test]
+fn test_loaded_pads_show_their_format() {
+    let mut harness = TestHarness::new();
+    harness.draw();
+    assert!(!harness.screen().contains("[7] WAV"));
+    
+    harness.press_ctrl('k');
+    harness.draw();
+    assert!(harness.screen().contains("[7] WAV"));
+}
+
+#[test]
+fn test_six_group_layout() {
+    let mut harness = TestHarness::new();
+    let layout = LayoutConfig { groups: 6, ..LayoutConfig::default() };
+    harness.app.apply_config(&Config { layout, ..Config::default() });
+    harness.draw();
+    assert!(harness.screen().contains(" GROUP 6 "));
+    
+    // Tab walks every group and wraps after the sixth
+    for _ in 0..5 {
+        harness.press_key(KeyCode::Tab);
+    }
+    assert_eq!(harness.app.get_current_group(), 5);
+    harness.press_key(KeyCode::Tab);
+    assert_eq!(harness.app.get_current_group(), 0);
+    
+    // The mixer has a column per group
+    harness.press_key(KeyCode::Char('w'));
+    harness.draw();
+    assert!(harness.screen().contains("GROUP 5: 80%"));
+    
+    // Overview number keys stop at the last group; past it they're pad keys again
+    harness.press_key(KeyCode::Char('v'));
+    assert_eq!(harness.press_key(KeyCode::Char('6')), Some(Command::SelectGroup(5)));
+    assert_eq!(harness.press_key(KeyCode::Char('7')), Some(Command::TriggerPad(0)));
+}
+
+#[test]
+fn test_renamed_group_shows_everywhere() {
+    let mut harness = TestHarness::new();
+    let perc = GroupConfig { name: Some("PERC".to_string()), ..GroupConfig::default() };
+    harness.app.apply_config(&Config { groups: vec![perc], ..Config::default() });
+    harness.draw();
+    assert!(harness.screen().contains(" PERC "));
+    assert!(harness.screen().contains(" BASS "));
+    assert!(!harness.screen().contains("DRUMS"));
+    
+    harness.press_key(KeyCode::Char('w'));
+    harness.draw();
+    assert!(harness.screen().contains("PERC: 80%"));
+}
@@ -0,0 +1,117 @@
+// Scenario tests drive the app the way the terminal does: keys through the input mapper,
+// the engine ticking on a clock the test moves by hand, and frames drawn into a TestBackend
+#![allow(dead_code)] // Each test binary uses a different part of the harness
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Cell;
+use ratatui::Terminal;
+
+use ko2_terminal::app::App;
+use ko2_terminal::command::Command;
+use ko2_terminal::input::{key_command, InputMapper, KeyBinding};
+use ko2_terminal::state::ManualClock;
+use ko2_terminal::theme::Theme;
+use ko2_terminal::ui::{self, HitAreas};
+
+pub struct TestHarness {
+    pub app: App,
+    pub input: InputMapper,
+    pub theme: Theme,
+    pub clock: ManualClock,
+    pub terminal: Terminal<TestBackend>,
+    pub hit_areas: HitAreas,
+}
+
+impl TestHarness {
+    // A detached app with the default bindings on a terminal big enough for the full layout
+    pub fn new() -> Self {
+        Self::with_size(200, 50)
+    }
+
+    pub fn with_size(width: u16, height: u16) -> Self {
+        let mut app = App::detached();
+        let clock = ManualClock::new();
+        app.state.set_clock(Arc::new(clock.clone()));
+        Self {
+            app,
+            input: InputMapper::new(),
+            theme: Theme::default(),
+            clock,
+            terminal: Terminal::new(TestBackend::new(width, height)).unwrap(),
+            hit_areas: HitAreas::default(),
+        }
+    }
+
+    // Routes a key the way the main loop does; returns the command it ran, if any
+    pub fn press(&mut self, binding: KeyBinding) -> Option<Command> {
+        let command = key_command(&self.app, &self.input, &binding)?;
+        if !matches!(command, Command::Quit | Command::ReloadConfig) {
+            command.execute(&mut self.app).unwrap();
+        }
+        Some(command)
+    }
+
+    pub fn press_key(&mut self, code: KeyCode) -> Option<Command> {
+        self.press(KeyBinding::new(code))
+    }
+
+    pub fn press_ctrl(&mut self, key: char) -> Option<Command> {
+        self.press(KeyBinding::with_modifiers(KeyCode::Char(key), KeyModifiers::CONTROL))
+    }
+
+    // Each character as its own key press, e.g. typing into a prompt
+    pub fn type_keys(&mut self, keys: &str) {
+        for key in keys.chars() {
+            self.press_key(KeyCode::Char(key));
+        }
+    }
+
+    // Moves the clock on and runs one pass of the engine
+    pub fn advance(&mut self, by: Duration) {
+        self.clock.advance(by);
+        self.app.tick();
+    }
+
+    // Exactly `steps` sequencer steps at the current tempo
+    pub fn advance_steps(&mut self, steps: usize) {
+        for _ in 0..steps {
+            let step = self.app.state.step_duration();
+            self.advance(step);
+        }
+    }
+
+    pub fn draw(&mut self) {
+        let (app, theme) = (&self.app, &self.theme);
+        let mut hit_areas = HitAreas::default();
+        self.terminal.draw(|f| hit_areas = ui::draw(f, app, theme)).unwrap();
+        self.hit_areas = hit_areas;
+    }
+
+    pub fn cell(&self, x: u16, y: u16) -> &Cell {
+        &self.terminal.backend().buffer()[(x, y)]
+    }
+
+    pub fn row(&self, y: u16) -> String {
+        let buffer = self.terminal.backend().buffer();
+        (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect()
+    }
+
+    pub fn screen(&self) -> String {
+        let height = self.terminal.backend().buffer().area.height;
+        (0..height).map(|y| self.row(y)).collect::<Vec<_>>().join("\n")
+    }
+
+    // (x, y) of the first cell of the first match of `text`, scanning rows from the top
+    pub fn find(&self, text: &str) -> Option<(u16, u16)> {
+        let height = self.terminal.backend().buffer().area.height;
+        (0..height).find_map(|y| {
+            let row = self.row(y);
+            // Columns count cells, not bytes; box-drawing characters take several bytes
+            row.find(text).map(|index| (row[..index].chars().count() as u16, y))
+        })
+    }
+}